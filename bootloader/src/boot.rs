@@ -947,14 +947,20 @@ unsafe fn stage_d1_register_network_activation(_ctx: &BootContext, platform: &Pl
     );
 }
 
-/// D2. Root FS bring-up: pre-EBS image → persistent block device → RAM HelixFS.
+/// D2. Root FS bring-up: pre-EBS image → persistent block device → RAM HelixFS,
+/// then the background cleaner.
 unsafe fn stage_d2_storage_init(ctx: &BootContext, platform: &PlatformInit) {
     storage::init_persistent_storage(platform.dma(), ctx.tsc_freq, ctx.pre_ebs_helix);
+    if let Err(e) = morpheus_kernel::storage::cleaner::spawn() {
+        log_warn("STORAGE", 852, e);
+    }
 }
 
-/// D3. Create standard root directories. Idempotent.
+/// D3. Create standard root directories (idempotent), then mount `/proc`,
+/// `/sys` and `/dev` over theirs.
 fn stage_d3_initfs_bootstrap() {
     storage::create_init_directories();
+    storage::mount_system_trees();
 }
 
 /// D4. Register framebuffer with the syscall layer (SYS_FB_INFO / SYS_FB_MAP).
@@ -1023,6 +1029,7 @@ unsafe fn stage_e2_enter_userspace(_ctx: &BootContext) -> ! {
         None,
         false,
         false,
+        None,
     ) {
         Ok(pid) => pid,
         Err(_) => boot_panic("BOOT", "failed to spawn /bin/init"),
@@ -18,7 +18,7 @@ use morpheus_block::unified_block_io::UnifiedBlockIo;
 use morpheus_block::usb_msd::UsbMsdInitError;
 use morpheus_block::virtio_blk::VirtioBlkInitError;
 use morpheus_block::{BlockDriver, DeviceKind, MemBlockDevice, RawBlockDevice};
-use morpheus_foundation::storage::{FS_AUTO, FS_HELIX, MNT_STAGED};
+use morpheus_foundation::storage::{FS_AUTO, FS_DEVFS, FS_HELIX, FS_PROC, FS_SYSFS, MNT_STAGED};
 use morpheus_hal_x86_64::dma::DmaRegion;
 use morpheus_hal_x86_64::paging::is_paging_initialized;
 use morpheus_hal_x86_64::paging::kmap_mmio;
@@ -768,6 +768,9 @@ unsafe fn mount_root_volume(volume_id: u64, extra_flags: u32, aux: u64) -> bool
         fs_type: FS_AUTO,
         flags: extra_flags,
         aux,
+        snapshot: None,
+        key: None,
+        manifest: None,
         pid: 0,
         privileged: true,
     };
@@ -785,6 +788,9 @@ unsafe fn mount_fresh_ram_root() -> bool {
         fs_type: FS_HELIX,
         flags: MNT_STAGED,
         aux: RAM_ROOT_BYTES,
+        snapshot: None,
+        key: None,
+        manifest: None,
         pid: 0,
         privileged: true,
     };
@@ -813,7 +819,9 @@ pub fn create_init_directories() {
         return;
     }
 
-    let dirs = ["/bin", "/etc", "/tmp", "/home", "/var", "/dev"];
+    let dirs = [
+        "/bin", "/etc", "/tmp", "/home", "/var", "/dev", "/proc", "/sys",
+    ];
     let ts = read_tsc();
     for dir in &dirs {
         if morpheus_kernel::storage::mkdir_root(dir, ts).is_err() {
@@ -823,6 +831,31 @@ pub fn create_init_directories() {
     }
 }
 
+/// Mount the synthetic `/proc`, `/sys` and `/dev` trees. They need no root
+/// volume: without one they are simply the only things mounted.
+pub fn mount_system_trees() {
+    for (path, fs_type) in [("/proc", FS_PROC), ("/sys", FS_SYSFS), ("/dev", FS_DEVFS)] {
+        let mut mp = [0u8; 256];
+        mp[..path.len()].copy_from_slice(path.as_bytes());
+        let req = morpheus_kernel::storage::MountReq {
+            source_volume_id: morpheus_foundation::storage::VOLUME_NONE,
+            mount_point: mp,
+            mount_point_len: path.len() as u16,
+            fs_type,
+            flags: 0,
+            aux: 0,
+            snapshot: None,
+            key: None,
+            manifest: None,
+            pid: 0,
+            privileged: true,
+        };
+        if morpheus_kernel::storage::mount(&req).is_err() {
+            log_warn("INITFS", 842, "failed to mount a synthetic tree");
+        }
+    }
+}
+
 // fn-ptr vtable over UnifiedBlockIo. Shared DMA buffer is safe: runtime ops serialized
 // by STORAGE_LOCK; boot probing is single-threaded.
 
@@ -9,12 +9,17 @@ use std::path::Path;
 use gpt_disk_io::BlockIo;
 use gpt_disk_types::{BlockSize, Lba};
 
+use morpheus_helix::crypt::{self, Crypt, CryptIo, CryptIoError, KeyParams};
 use morpheus_helix::error::HelixError;
 use morpheus_helix::log::recovery::recover_superblock;
 use morpheus_helix::HelixFs;
 
 const SECTOR_SIZE: u32 = 512;
 
+/// Passphrase of an encrypted image. Set, it also makes `format` (and the
+/// format-on-first-use in `mount`) create an encrypted volume.
+const PASSPHRASE_ENV: &str = "MORPHEUS_PASSPHRASE";
+
 /// "MXROOT" volume UUID stamped into a freshly formatted image.
 const MXROOT_UUID: [u8; 16] = [
     0x4D, 0x58, 0x52, 0x4F, 0x4F, 0x54, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
@@ -82,6 +87,79 @@ impl BlockIo for FileBlockDevice {
     }
 }
 
+/// An image as the engine sees it: decrypted through its key when encrypted.
+struct Disk {
+    raw: FileBlockDevice,
+    crypt: Option<Crypt>,
+}
+
+impl Disk {
+    /// Open `path` without unlocking it.
+    fn open_raw(path: &str) -> Result<Self, String> {
+        let raw =
+            FileBlockDevice::open(path).map_err(|e| format!("cannot open '{}': {}", path, e))?;
+        Ok(Self { raw, crypt: None })
+    }
+
+    /// Open `path`, unlocking an encrypted volume with `$MORPHEUS_PASSPHRASE`.
+    fn open(path: &str) -> Result<Self, String> {
+        let mut disk = Self::open_raw(path)?;
+        if Crypt::is_encrypted(&mut disk.raw, 0, SECTOR_SIZE) {
+            let pass = passphrase()
+                .ok_or_else(|| format!("'{}' is encrypted; set {}", path, PASSPHRASE_ENV))?;
+            let crypt = Crypt::unlock(&mut disk.raw, 0, SECTOR_SIZE, pass.as_bytes())
+                .map_err(|e| format!("unlock: {:?}", e))?;
+            println!("[helix] unlocked encrypted volume");
+            disk.crypt = Some(crypt);
+        }
+        Ok(disk)
+    }
+
+    fn total_sectors(&self) -> u64 {
+        self.raw.total_sectors
+    }
+
+    fn io(&mut self) -> CryptIo<'_, FileBlockDevice> {
+        CryptIo::new(&mut self.raw, self.crypt.as_mut())
+    }
+}
+
+impl BlockIo for Disk {
+    type Error = CryptIoError<FileIoError>;
+
+    fn block_size(&self) -> BlockSize {
+        self.raw.block_size()
+    }
+
+    fn num_blocks(&mut self) -> Result<u64, Self::Error> {
+        self.io().num_blocks()
+    }
+
+    fn read_blocks(&mut self, start_lba: Lba, dst: &mut [u8]) -> Result<(), Self::Error> {
+        self.io().read_blocks(start_lba, dst)
+    }
+
+    fn write_blocks(&mut self, start_lba: Lba, src: &[u8]) -> Result<(), Self::Error> {
+        self.io().write_blocks(start_lba, src)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.io().flush()
+    }
+}
+
+fn passphrase() -> Option<String> {
+    env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty())
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], String> {
+    let mut out = [0u8; N];
+    File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut out))
+        .map_err(|e| format!("/dev/urandom: {}", e))?;
+    Ok(out)
+}
+
 fn usage() {
     eprintln!("morpheus-cli — MorpheusX HelixFS host utility");
     eprintln!();
@@ -91,6 +169,18 @@ fn usage() {
     eprintln!("  morpheus-cli ls     <disk-image> [path]");
     eprintln!("  morpheus-cli rm     <disk-image> <path>   (recursive)");
     eprintln!("  morpheus-cli mkbin  <disk-image>");
+    eprintln!("  morpheus-cli snapshot <disk-image> list | create|delete|rollback <name>");
+    eprintln!("  morpheus-cli restore <disk-image> <path> list | <lsn>");
+    eprintln!("  morpheus-cli send   <disk-image> <stream-out> <to-snapshot> [--from <snapshot>]");
+    eprintln!("  morpheus-cli receive <disk-image> <stream-in>");
+    eprintln!("  morpheus-cli fsck   <disk-image> [--repair]");
+    eprintln!("  morpheus-cli compress <disk-image> <path> lz4|none|inherit");
+    eprintln!();
+    eprintln!("ENCRYPTION:");
+    eprintln!(
+        "  With {} set, format creates an encrypted volume and every command\n  unlocks one.",
+        PASSPHRASE_ENV
+    );
     eprintln!();
     eprintln!("EXAMPLES:");
     eprintln!(
@@ -99,6 +189,14 @@ fn usage() {
     eprintln!("  morpheus-cli inject testing/helix-data.img my-app --dest /bin/app");
     eprintln!("  morpheus-cli pack /dev/sdb2 testing/helix.img --max-mb 384");
     eprintln!("  morpheus-cli ls testing/helix-data.img /bin");
+    eprintln!("  morpheus-cli snapshot testing/helix-data.img rollback pre-update");
+    eprintln!("  morpheus-cli restore testing/helix-data.img /etc/motd 1042");
+    eprintln!("  morpheus-cli send build.img bin-v2.hxs v2 --from v1");
+    eprintln!("  morpheus-cli compress testing/helix-data.img /bin lz4");
+    eprintln!(
+        "  {}=hunter2 morpheus-cli format testing/secret.img",
+        PASSPHRASE_ENV
+    );
 }
 
 fn cmd_pack(disk: &str, output: &str, max_mb: u64) -> Result<(), String> {
@@ -106,8 +204,7 @@ fn cmd_pack(disk: &str, output: &str, max_mb: u64) -> Result<(), String> {
         return Err("--max-mb must be > 0".to_string());
     }
 
-    let mut dev =
-        FileBlockDevice::open(disk).map_err(|e| format!("cannot open '{}': {}", disk, e))?;
+    let mut dev = Disk::open(disk)?;
 
     let sb = recover_superblock(&mut dev, 0, SECTOR_SIZE)
         .map_err(|e| format!("recover_superblock: {:?}", e))?;
@@ -167,10 +264,13 @@ fn cmd_pack(disk: &str, output: &str, max_mb: u64) -> Result<(), String> {
     while copied < bytes {
         let remaining = (bytes - copied) as usize;
         let n = remaining.min(CHUNK);
-        dev.file
+        // Copied as stored: an encrypted image stays encrypted.
+        dev.raw
+            .file
             .seek(SeekFrom::Start(copied))
             .map_err(|_| "seek failed".to_string())?;
-        dev.file
+        dev.raw
+            .file
             .read_exact(&mut buf[..n])
             .map_err(|_| "read failed".to_string())?;
         out.write_all(&buf[..n])
@@ -190,13 +290,13 @@ fn cmd_pack(disk: &str, output: &str, max_mb: u64) -> Result<(), String> {
 /// Open `disk` and mount its HelixFS engine, formatting if there's no valid /
 /// compatible superblock. `HelixFs::mount` does superblock recovery, log replay,
 /// and bitmap rebuild internally.
-fn mount(disk: &str) -> Result<(FileBlockDevice, HelixFs), String> {
-    let mut dev =
-        FileBlockDevice::open(disk).map_err(|e| format!("cannot open '{}': {}", disk, e))?;
+fn mount(disk: &str) -> Result<(Disk, HelixFs), String> {
+    let mut dev = Disk::open(disk)?;
 
     println!(
         "[helix] disk: {} sectors × {} bytes",
-        dev.total_sectors, SECTOR_SIZE
+        dev.total_sectors(),
+        SECTOR_SIZE
     );
 
     let fs = match HelixFs::mount(&mut dev, 0, SECTOR_SIZE) {
@@ -218,11 +318,44 @@ fn mount(disk: &str) -> Result<(FileBlockDevice, HelixFs), String> {
     Ok((dev, fs))
 }
 
-/// Format `dev` as a clean HelixFS over its whole extent.
-fn format_disk(dev: &mut FileBlockDevice) -> Result<(), String> {
-    let total_sectors = dev.total_sectors;
-    morpheus_helix::format::format_helix(dev, 0, total_sectors, SECTOR_SIZE, "root", MXROOT_UUID)
-        .map_err(|e| format!("format_helix: {:?}", e))?;
+/// Format `dev` as a clean HelixFS over its whole extent, encrypted under
+/// `$MORPHEUS_PASSPHRASE` when that is set.
+fn format_disk(dev: &mut Disk) -> Result<(), String> {
+    let total_sectors = dev.total_sectors();
+    dev.crypt = None;
+    match passphrase() {
+        Some(pass) => {
+            let params = KeyParams {
+                passphrase: pass.as_bytes(),
+                salt: random_bytes()?,
+                volume_key: random_bytes()?,
+                kdf_rounds: crypt::DEFAULT_KDF_ROUNDS,
+            };
+            let c = crypt::format(
+                &mut dev.raw,
+                0,
+                total_sectors,
+                SECTOR_SIZE,
+                "root",
+                MXROOT_UUID,
+                &params,
+            )
+            .map_err(|e| format!("format encrypted: {:?}", e))?;
+            dev.crypt = Some(c);
+            println!("[helix] formatted encrypted volume");
+        },
+        None => {
+            morpheus_helix::format::format_helix(
+                dev,
+                0,
+                total_sectors,
+                SECTOR_SIZE,
+                "root",
+                MXROOT_UUID,
+            )
+            .map_err(|e| format!("format_helix: {:?}", e))?;
+        },
+    }
     dev.flush()
         .map_err(|_| "flush after format failed".to_string())
 }
@@ -286,16 +419,24 @@ fn dest_name(dest: &str) -> &str {
 }
 
 fn cmd_ls(disk: &str, path: &str) -> Result<(), String> {
-    let (_dev, fs) = mount(disk)?;
+    let (mut dev, fs) = mount(disk)?;
     let entries = fs
-        .readdir(path)
+        .readdir(&mut dev, path)
         .map_err(|e| format!("readdir {}: {:?}", path, e))?;
 
     println!("{}/  ({} entries)", path, entries.len());
+    let base = path.trim_end_matches('/');
     for e in &entries {
         let name = std::str::from_utf8(&e.name[..e.name_len as usize]).unwrap_or("?");
         let kind = if e.is_dir() { "DIR " } else { "FILE" };
-        println!("  {} {:>10} B   {}", kind, e.size, name);
+        // Compressed files also show what they occupy on disk.
+        let stored = fs
+            .stat(&mut dev, &format!("{}/{}", base, name))
+            .ok()
+            .filter(|st| st.physical_size != st.size && !st.is_dir())
+            .map(|st| format!(" ({} on disk)", st.physical_size))
+            .unwrap_or_default();
+        println!("  {} {:>10} B   {}{}", kind, e.size, name, stored);
     }
     Ok(())
 }
@@ -305,8 +446,8 @@ fn cmd_ls(disk: &str, path: &str) -> Result<(), String> {
 /// are emptied depth-first first. Used to clear a stale repo before re-injecting a
 /// fresh one — inject overwrites by path but never deletes, so without this a
 /// re-injected tree inherits the previous tree's orphaned files.
-fn rm_recursive(dev: &mut FileBlockDevice, fs: &mut HelixFs, path: &str) -> Result<(), String> {
-    let is_dir = match fs.stat(path) {
+fn rm_recursive(dev: &mut Disk, fs: &mut HelixFs, path: &str) -> Result<(), String> {
+    let is_dir = match fs.stat(dev, path) {
         Ok(st) => st.is_dir(),
         Err(HelixError::NotFound) => return Ok(()), // already gone — idempotent
         Err(e) => return Err(format!("stat {}: {:?}", path, e)),
@@ -314,7 +455,7 @@ fn rm_recursive(dev: &mut FileBlockDevice, fs: &mut HelixFs, path: &str) -> Resu
     if is_dir {
         let base = path.trim_end_matches('/');
         let children = fs
-            .readdir(path)
+            .readdir(dev, path)
             .map_err(|e| format!("readdir {}: {:?}", path, e))?;
         for e in &children {
             let name = std::str::from_utf8(&e.name[..e.name_len as usize])
@@ -338,8 +479,7 @@ fn cmd_rm(disk: &str, path: &str) -> Result<(), String> {
 }
 
 fn cmd_format(disk: &str) -> Result<(), String> {
-    let mut dev =
-        FileBlockDevice::open(disk).map_err(|e| format!("cannot open '{}': {}", disk, e))?;
+    let mut dev = Disk::open_raw(disk)?;
     println!("[format] wiping and reformatting {}", disk);
     format_disk(&mut dev)?;
     println!("[format] done — clean HelixFS ready");
@@ -358,6 +498,157 @@ fn cmd_mkbin(disk: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Host wall-clock time for snapshot records (other commands stamp 0).
+fn now_ns() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+fn cmd_snapshot(disk: &str, action: &str, name: Option<&str>) -> Result<(), String> {
+    let (mut dev, mut fs) = mount(disk)?;
+    let need_name = || name.ok_or_else(|| format!("snapshot {} needs a <name>", action));
+    match action {
+        "list" => {
+            let list = fs.list_snapshots();
+            println!("{} snapshot(s)", list.len());
+            for s in &list {
+                println!(
+                    "  lsn {:>10}   {:>20} ns   {}",
+                    s.lsn, s.timestamp_ns, s.name
+                );
+            }
+            return Ok(());
+        },
+        "create" => {
+            let name = need_name()?;
+            let lsn = fs
+                .snapshot(&mut dev, name, now_ns())
+                .map_err(|e| format!("snapshot {}: {:?}", name, e))?;
+            println!("[snapshot] created {} at lsn {}", name, lsn);
+        },
+        "delete" => {
+            let name = need_name()?;
+            fs.delete_snapshot(&mut dev, name)
+                .map_err(|e| format!("delete {}: {:?}", name, e))?;
+            println!("[snapshot] deleted {}", name);
+        },
+        "rollback" => {
+            let name = need_name()?;
+            fs.rollback_to(&mut dev, name, now_ns())
+                .map_err(|e| format!("rollback {}: {:?}", name, e))?;
+            println!("[snapshot] rolled back to {}", name);
+        },
+        _ => return Err(format!("unknown snapshot action '{}'", action)),
+    }
+    fs.sync(&mut dev).map_err(|e| format!("sync: {:?}", e))
+}
+
+fn cmd_send(disk: &str, output: &str, to: &str, from: Option<&str>) -> Result<(), String> {
+    let (mut dev, fs) = mount(disk)?;
+    let resolve = |name: &str| {
+        fs.find_snapshot(name)
+            .map_err(|e| format!("snapshot {}: {:?}", name, e))
+    };
+    let to_lsn = resolve(to)?;
+    let from_lsn = from.map(resolve).transpose()?.unwrap_or(0);
+    let stream = fs
+        .send(&mut dev, from_lsn, to_lsn)
+        .map_err(|e| format!("send: {:?}", e))?;
+    std::fs::write(output, &stream).map_err(|e| format!("cannot write '{}': {}", output, e))?;
+    println!(
+        "[send] {} -> {} : {} bytes to {}",
+        from.unwrap_or("(empty)"),
+        to,
+        stream.len(),
+        output
+    );
+    Ok(())
+}
+
+fn cmd_receive(disk: &str, input: &str) -> Result<(), String> {
+    let stream = std::fs::read(input).map_err(|e| format!("cannot read '{}': {}", input, e))?;
+    let (mut dev, mut fs) = mount(disk)?;
+    let header = fs
+        .receive(&mut dev, &stream, now_ns())
+        .map_err(|e| format!("receive: {:?}", e))?;
+    fs.sync(&mut dev).map_err(|e| format!("sync: {:?}", e))?;
+    println!(
+        "[receive] applied source lsn {} -> {} from {}",
+        header.from_lsn, header.to_lsn, input
+    );
+    Ok(())
+}
+
+/// Set, clear (`none`) or drop (`inherit`) the compression policy of a file or
+/// directory. Applies to later writes; inject after setting it on `/bin`.
+fn cmd_compress(disk: &str, path: &str, policy: &str) -> Result<(), String> {
+    let (mut dev, mut fs) = mount(disk)?;
+    let name = morpheus_helix::compress::POLICY_XATTR;
+    let result = match policy {
+        "lz4" | "none" => fs.setxattr(&mut dev, path, name, policy.as_bytes(), now_ns()),
+        "inherit" => match fs.removexattr(&mut dev, path, name, now_ns()) {
+            Err(HelixError::NoAttribute) => Ok(()),
+            other => other,
+        },
+        _ => return Err(format!("unknown compression policy '{}'", policy)),
+    };
+    result.map_err(|e| format!("compress {}: {:?}", path, e))?;
+    fs.sync(&mut dev).map_err(|e| format!("sync: {:?}", e))?;
+    println!("[compress] {} -> {}", path, policy);
+    Ok(())
+}
+
+/// List the versions of `path`, or bring it (everything below it, for a
+/// directory) back to how it was at `lsn`.
+fn cmd_restore(disk: &str, path: &str, at: &str) -> Result<(), String> {
+    let (mut dev, mut fs) = mount(disk)?;
+    if at == "list" {
+        let versions = fs
+            .versions(&mut dev, path)
+            .map_err(|e| format!("versions {}: {:?}", path, e))?;
+        println!("{} version(s) of {}", versions.len(), path);
+        for (lsn, ts, op) in &versions {
+            println!("  lsn {:>10}   {:>20} ns   {:?}", lsn, ts, op);
+        }
+        return Ok(());
+    }
+    let lsn: u64 = at.parse().map_err(|_| format!("'{}' is not an lsn", at))?;
+    fs.restore(&mut dev, path, lsn, now_ns())
+        .map_err(|e| format!("restore {}: {:?}", path, e))?;
+    fs.sync(&mut dev).map_err(|e| format!("sync: {:?}", e))?;
+    println!("[restore] {} -> lsn {}", path, lsn);
+    Ok(())
+}
+
+/// Check without mounting: a volume too damaged to mount is exactly what
+/// fsck is for, and `mount` would format it.
+fn cmd_fsck(disk: &str, repair: bool) -> Result<(), String> {
+    let mut dev = Disk::open(disk)?;
+    let report = morpheus_helix::fsck::check(&mut dev, 0, SECTOR_SIZE, repair)
+        .map_err(|e| format!("fsck: {:?}", e))?;
+    for finding in &report.findings {
+        let status = if finding.repaired {
+            "repaired"
+        } else {
+            "found"
+        };
+        println!("[fsck] {}: {}", status, finding.problem);
+    }
+    println!(
+        "[fsck] {} entries, {} log records, {} blocks in use",
+        report.entries, report.log_records, report.blocks_claimed
+    );
+    match report.unrepaired().count() {
+        0 => {
+            println!("[fsck] {} clean", disk);
+            Ok(())
+        },
+        n => Err(format!("{} problem(s) left", n)),
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
@@ -433,6 +724,56 @@ fn main() {
             }
             cmd_rm(&args[2], &args[3])
         },
+        "snapshot" => {
+            if args.len() < 4 {
+                eprintln!(
+                    "Usage: morpheus-cli snapshot <disk-image> list | create|delete|rollback <name>"
+                );
+                std::process::exit(1);
+            }
+            cmd_snapshot(&args[2], &args[3], args.get(4).map(|s| s.as_str()))
+        },
+        "restore" => {
+            if args.len() < 5 {
+                eprintln!("Usage: morpheus-cli restore <disk-image> <path> list | <lsn>");
+                std::process::exit(1);
+            }
+            cmd_restore(&args[2], &args[3], &args[4])
+        },
+        "send" => {
+            if args.len() < 5 {
+                eprintln!(
+                    "Usage: morpheus-cli send <disk-image> <stream-out> <to-snapshot> [--from <snapshot>]"
+                );
+                std::process::exit(1);
+            }
+            let from = args
+                .windows(2)
+                .find(|w| w[0] == "--from")
+                .map(|w| w[1].as_str());
+            cmd_send(&args[2], &args[3], &args[4], from)
+        },
+        "receive" => {
+            if args.len() < 4 {
+                eprintln!("Usage: morpheus-cli receive <disk-image> <stream-in>");
+                std::process::exit(1);
+            }
+            cmd_receive(&args[2], &args[3])
+        },
+        "fsck" => {
+            if args.len() < 3 {
+                eprintln!("Usage: morpheus-cli fsck <disk-image> [--repair]");
+                std::process::exit(1);
+            }
+            cmd_fsck(&args[2], args[3..].iter().any(|a| a == "--repair"))
+        },
+        "compress" => {
+            if args.len() < 5 {
+                eprintln!("Usage: morpheus-cli compress <disk-image> <path> lz4|none|inherit");
+                std::process::exit(1);
+            }
+            cmd_compress(&args[2], &args[3], &args[4])
+        },
         _ => {
             usage();
             std::process::exit(1);
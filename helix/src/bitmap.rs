@@ -13,6 +13,14 @@ pub struct BlockBitmap {
     free_count: u64,
     /// Starting index for next allocation scan.
     search_hint: u64,
+    /// Blocks at or past this are not handed out (see `set_alloc_limit`).
+    alloc_limit: u64,
+    /// Frees held back while a transaction is open, so a block the committed
+    /// state still references is never reallocated before the commit lands.
+    deferred: Option<Vec<u64>>,
+    /// `(start, count)` runs handed out while frees are deferred, so an
+    /// aborted transaction can take them back.
+    tx_allocated: Vec<(u64, u64)>,
 }
 
 impl BlockBitmap {
@@ -23,6 +31,9 @@ impl BlockBitmap {
             total_blocks,
             free_count: total_blocks,
             search_hint: 0,
+            alloc_limit: total_blocks,
+            deferred: None,
+            tx_allocated: Vec::new(),
         }
     }
 
@@ -50,6 +61,9 @@ impl BlockBitmap {
             total_blocks,
             free_count: total_blocks - alloc_count,
             search_hint: 0,
+            alloc_limit: total_blocks,
+            deferred: None,
+            tx_allocated: Vec::new(),
         }
     }
 
@@ -69,6 +83,15 @@ impl BlockBitmap {
         self.total_blocks - self.free_count
     }
 
+    /// Blocks allocated here but free in `other`, a map of the same size.
+    pub fn allocated_outside(&self, other: &BlockBitmap) -> u64 {
+        self.bits
+            .iter()
+            .zip(&other.bits)
+            .map(|(a, b)| (a & !b).count_ones() as u64)
+            .sum()
+    }
+
     pub fn is_allocated(&self, block: u64) -> bool {
         if block >= self.total_blocks {
             return false;
@@ -85,14 +108,16 @@ impl BlockBitmap {
         }
 
         let start = self.search_hint;
-        for offset in 0..self.total_blocks {
-            let idx = (start + offset) % self.total_blocks;
+        let limit = self.alloc_limit;
+        for offset in 0..limit {
+            let idx = (start + offset) % limit;
             let byte_idx = (idx / 8) as usize;
             let bit_idx = (idx % 8) as u32;
             if self.bits[byte_idx] & (1 << bit_idx) == 0 {
                 self.bits[byte_idx] |= 1 << bit_idx;
                 self.free_count -= 1;
-                self.search_hint = (idx + 1) % self.total_blocks;
+                self.search_hint = (idx + 1) % limit;
+                self.note_tx_alloc(idx, 1);
                 return Ok(idx);
             }
         }
@@ -112,7 +137,7 @@ impl BlockBitmap {
         let mut run_start: u64 = 0;
         let mut run_len: u64 = 0;
 
-        for idx in 0..self.total_blocks {
+        for idx in 0..self.alloc_limit {
             if !self.is_allocated(idx) {
                 if run_len == 0 {
                     run_start = idx;
@@ -125,7 +150,8 @@ impl BlockBitmap {
                         self.bits[byte_idx] |= 1 << bit_idx;
                     }
                     self.free_count -= count;
-                    self.search_hint = (run_start + count) % self.total_blocks;
+                    self.search_hint = (run_start + count) % self.alloc_limit;
+                    self.note_tx_alloc(run_start, count);
                     return Ok(run_start);
                 }
             } else {
@@ -156,6 +182,7 @@ impl BlockBitmap {
     }
 
     /// Free one block; double-free returns `BitmapCorrupt`.
+    /// While frees are deferred the block stays allocated until `apply_deferred`.
     pub fn free_block(&mut self, block: u64) -> Result<(), HelixError> {
         if block >= self.total_blocks {
             return Err(HelixError::BitmapCorrupt);
@@ -165,6 +192,10 @@ impl BlockBitmap {
         if self.bits[byte_idx] & (1 << bit_idx) == 0 {
             return Err(HelixError::BitmapCorrupt);
         }
+        if let Some(deferred) = self.deferred.as_mut() {
+            deferred.push(block);
+            return Ok(());
+        }
         self.bits[byte_idx] &= !(1 << bit_idx);
         self.free_count += 1;
         if block < self.search_hint {
@@ -180,6 +211,80 @@ impl BlockBitmap {
         Ok(())
     }
 
+    /// Start holding frees back (transaction begin).
+    pub fn begin_deferred(&mut self) {
+        self.deferred = Some(Vec::new());
+        self.tx_allocated.clear();
+    }
+
+    /// Release every held-back free (transaction commit).
+    pub fn apply_deferred(&mut self) -> Result<(), HelixError> {
+        self.tx_allocated.clear();
+        let Some(blocks) = self.deferred.take() else {
+            return Ok(());
+        };
+        for block in blocks {
+            self.free_block(block)?;
+        }
+        Ok(())
+    }
+
+    /// Forget held-back frees; the blocks stay allocated (failed commit).
+    pub fn discard_deferred(&mut self) {
+        self.deferred = None;
+        self.tx_allocated.clear();
+    }
+
+    /// Return to the map as of `begin_deferred` (transaction abort): forget
+    /// the held-back frees and free what was allocated since.
+    pub fn rollback_deferred(&mut self) {
+        self.deferred = None;
+        for (start, count) in core::mem::take(&mut self.tx_allocated) {
+            let _ = self.free_range(start, count);
+        }
+    }
+
+    fn note_tx_alloc(&mut self, start: u64, count: u64) {
+        if self.deferred.is_some() {
+            self.tx_allocated.push((start, count));
+        }
+    }
+
+    /// Stop handing out blocks at or past `limit` (clamped to the volume) while
+    /// a shrink empties them; frees there still land. `total_blocks()` lifts it.
+    pub fn set_alloc_limit(&mut self, limit: u64) {
+        self.alloc_limit = limit.clamp(1, self.total_blocks);
+    }
+
+    /// First allocated block at or past `start`.
+    pub fn first_allocated_from(&self, start: u64) -> Option<u64> {
+        (start..self.total_blocks).find(|&b| self.is_allocated(b))
+    }
+
+    /// Cover `total_blocks` blocks: growing adds free blocks, shrinking drops
+    /// the tail, which must be free (`NoSpace` otherwise). Lifts the
+    /// allocation limit.
+    pub fn resize(&mut self, total_blocks: u64) -> Result<(), HelixError> {
+        if total_blocks == 0 {
+            return Err(HelixError::FormatTooSmall);
+        }
+        if total_blocks < self.total_blocks && self.first_allocated_from(total_blocks).is_some() {
+            return Err(HelixError::NoSpace);
+        }
+        // Clear the bits past the old end in its last byte before they count.
+        let tail_bits = (self.total_blocks % 8) as u32;
+        if tail_bits != 0 {
+            let last = self.bits.len() - 1;
+            self.bits[last] &= (1u8 << tail_bits) - 1;
+        }
+        self.bits.resize(total_blocks.div_ceil(8) as usize, 0);
+        self.free_count = total_blocks - self.allocated_count();
+        self.total_blocks = total_blocks;
+        self.alloc_limit = total_blocks;
+        self.search_hint %= total_blocks;
+        Ok(())
+    }
+
     /// Bitmap blocks needed on disk to cover `total_data_blocks`.
     pub fn disk_blocks_needed(total_data_blocks: u64) -> u64 {
         let bits_per_block = BLOCK_SIZE as u64 * 8;
@@ -1,18 +1,41 @@
-//! Flat on-disk index checkpoint: every live `IndexEntry`, CRC-tagged and packed
-//! 8 per block in a contiguous region. Lets mount restore the namespace without
-//! replaying the whole log, so superseded log segments can be recycled (the log
-//! ring is finite — without this it bricks at `LogFull`).
+//! On-disk namespace index: a B+tree bulk-built into one contiguous region at
+//! every checkpoint (and for every snapshot). Lets mount restore the namespace
+//! without replaying the whole log, so superseded log segments can be recycled
+//! (the log ring is finite — without this it bricks at `LogFull`).
+//!
+//! Leaves come first, `LEAF_ENTRIES_PER_BLOCK` entries each in tree order
+//! (`btree::tree_key`, then path), followed by each internal level, so the
+//! root is the region's last block and the leaf count follows from the entry
+//! count alone. A directory's children share the high half of their key and
+//! sit in adjacent leaves. Nodes use the `BTreeNodeHeader` layout and carry a
+//! CRC over the block; entries keep their own CRC so fsck can salvage a leaf
+//! whose node CRC fails. A region is never modified after it is written; it is
+//! opened lazily through `IndexRegion`, which reads nodes on demand.
 
 use crate::crc::crc32c;
 use crate::error::HelixError;
-use crate::index::btree::NamespaceIndex;
+use crate::index::btree::{self, NamespaceIndex};
 use crate::types::*;
 use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
 use gpt_disk_io::BlockIo;
 use gpt_disk_types::Lba;
 
-pub const ENTRIES_PER_INDEX_BLOCK: usize = BLOCK_SIZE as usize / 512;
 const ENTRY_SIZE: usize = 512;
+/// `BTreeNodeHeader`: type at 0, key_count at 4, self_block at 8, CRC at 16.
+const HDR: usize = 32;
+const CRC_OFF: usize = 16;
+const KEYS_OFF: usize = HDR;
+const CHILDREN_OFF: usize = HDR + INTERNAL_ORDER * 8;
+/// Children per internal node.
+const FANOUT: usize = INTERNAL_ORDER + 1;
+/// Deeper than any tree a volume can hold; a longer descent is a cycle.
+const MAX_DEPTH: usize = 16;
+
+/// Nodes an open region keeps in memory (256 KiB): the upper levels of any
+/// tree plus a working set of leaves.
+pub const NODE_CACHE_NODES: usize = 64;
 
 fn region_lba(partition_lba_start: u64, data_start_block: u64, dbs: u32, rel_block: u64) -> Lba {
     let scale = BLOCK_SIZE as u64 / dbs as u64;
@@ -26,47 +49,311 @@ fn entry_crc(e: &IndexEntry) -> u32 {
     crc32c(bytes)
 }
 
+fn read_u64(buf: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes(buf[off..off + 8].try_into().unwrap())
+}
+
+fn leaf_entry(buf: &[u8], slot: usize) -> IndexEntry {
+    let off = HDR + slot * ENTRY_SIZE;
+    unsafe { core::ptr::read_unaligned(buf[off..].as_ptr() as *const IndexEntry) }
+}
+
+fn leaf_count(entry_count: u64) -> u64 {
+    entry_count.div_ceil(LEAF_ENTRIES_PER_BLOCK as u64).max(1)
+}
+
 /// Number of blocks a region of `entry_count` entries occupies (>= 1).
 pub fn region_blocks(entry_count: usize) -> u64 {
-    entry_count.div_ceil(ENTRIES_PER_INDEX_BLOCK).max(1) as u64
+    let mut level = leaf_count(entry_count as u64);
+    let mut total = level;
+    while level > 1 {
+        level = level.div_ceil(FANOUT as u64);
+        total += level;
+    }
+    total
+}
+
+/// Fill in the header of node `block` and its CRC.
+fn seal_node(buf: &mut [u8], node_type: u8, count: usize, block: BlockAddr) {
+    buf[0] = node_type;
+    buf[4..8].copy_from_slice(&(count as u32).to_le_bytes());
+    buf[8..16].copy_from_slice(&block.to_le_bytes());
+    buf[CRC_OFF..CRC_OFF + 4].copy_from_slice(&[0u8; 4]);
+    let crc = crc32c(buf);
+    buf[CRC_OFF..CRC_OFF + 4].copy_from_slice(&crc.to_le_bytes());
 }
 
+/// Verify a node read from `block`; returns its type and count. The CRC field
+/// is left zeroed.
+fn check_node(buf: &mut [u8], block: BlockAddr) -> Result<(u8, usize), HelixError> {
+    let stored = u32::from_le_bytes(buf[CRC_OFF..CRC_OFF + 4].try_into().unwrap());
+    buf[CRC_OFF..CRC_OFF + 4].copy_from_slice(&[0u8; 4]);
+    let node_type = buf[0];
+    let count = u32::from_le_bytes(buf[4..8].try_into().unwrap()) as usize;
+    let max = match node_type {
+        NODE_LEAF => LEAF_ENTRIES_PER_BLOCK,
+        NODE_INTERNAL => INTERNAL_ORDER,
+        _ => return Err(HelixError::IndexCrcMismatch),
+    };
+    if crc32c(buf) != stored || read_u64(buf, 8) != block || count > max {
+        return Err(HelixError::IndexCrcMismatch);
+    }
+    Ok((node_type, count))
+}
+
+/// Bulk-build the live entries of `index` into a tree at `region_start`, which
+/// has room for `capacity` blocks (`region_blocks` of an upper bound on the
+/// entry count). Streams the namespace: only the first key of each node is
+/// held. Returns `(blocks, entries)` written; the caller frees the rest.
 pub fn write_index_region<B: BlockIo>(
     block_io: &mut B,
     partition_lba_start: u64,
     data_start_block: u64,
     device_block_size: u32,
     region_start: u64,
-    entries: &[IndexEntry],
-) -> Result<(), HelixError> {
-    let blocks = region_blocks(entries.len());
-    for b in 0..blocks {
-        let mut buf = vec![0u8; BLOCK_SIZE as usize];
-        for slot in 0..ENTRIES_PER_INDEX_BLOCK {
-            let idx = b as usize * ENTRIES_PER_INDEX_BLOCK + slot;
-            if idx >= entries.len() {
-                break;
-            }
-            let mut e = entries[idx];
-            e.crc32c = entry_crc(&e);
-            let bytes =
-                unsafe { core::slice::from_raw_parts(&e as *const _ as *const u8, ENTRY_SIZE) };
-            buf[slot * ENTRY_SIZE..(slot + 1) * ENTRY_SIZE].copy_from_slice(bytes);
+    capacity: u64,
+    index: &NamespaceIndex,
+) -> Result<(u64, u64), HelixError> {
+    let write_node = |block_io: &mut B, block: BlockAddr, buf: &[u8]| {
+        if block >= region_start + capacity {
+            return Err(HelixError::NoSpace);
         }
         let lba = region_lba(
             partition_lba_start,
             data_start_block,
             device_block_size,
-            region_start + b,
+            block,
+        );
+        block_io
+            .write_blocks(lba, buf)
+            .map_err(|_| HelixError::IoWriteFailed)
+    };
+
+    let mut buf = vec![0u8; BLOCK_SIZE as usize];
+    let mut filled = 0usize;
+    let mut entries: u64 = 0;
+    // First key of every node on the level being built.
+    let mut firsts: Vec<u64> = Vec::new();
+    let mut cursor = index.cursor();
+    while let Some(mut e) = index.next_entry(block_io, &mut cursor)? {
+        if filled == 0 {
+            firsts.push(btree::tree_key(btree::path_str(&e.path)));
+        }
+        e.crc32c = entry_crc(&e);
+        let bytes = unsafe { core::slice::from_raw_parts(&e as *const _ as *const u8, ENTRY_SIZE) };
+        let off = HDR + filled * ENTRY_SIZE;
+        buf[off..off + ENTRY_SIZE].copy_from_slice(bytes);
+        filled += 1;
+        entries += 1;
+        if filled == LEAF_ENTRIES_PER_BLOCK {
+            let block = region_start + firsts.len() as u64 - 1;
+            seal_node(&mut buf, NODE_LEAF, filled, block);
+            write_node(block_io, block, &buf)?;
+            buf.fill(0);
+            filled = 0;
+        }
+    }
+    // A partial last leaf, or the one empty leaf of an empty tree.
+    if filled > 0 || firsts.is_empty() {
+        if firsts.is_empty() {
+            firsts.push(0);
+        }
+        let block = region_start + firsts.len() as u64 - 1;
+        seal_node(&mut buf, NODE_LEAF, filled, block);
+        write_node(block_io, block, &buf)?;
+    }
+
+    let mut level_start = region_start;
+    let mut next = region_start + firsts.len() as u64;
+    while firsts.len() > 1 {
+        let mut up = Vec::with_capacity(firsts.len().div_ceil(FANOUT));
+        let up_start = next;
+        for (i, keys) in firsts.chunks(FANOUT).enumerate() {
+            buf.fill(0);
+            for (j, key) in keys.iter().skip(1).enumerate() {
+                buf[KEYS_OFF + j * 8..KEYS_OFF + j * 8 + 8].copy_from_slice(&key.to_le_bytes());
+            }
+            for j in 0..keys.len() {
+                let child = level_start + (i * FANOUT + j) as u64;
+                let off = CHILDREN_OFF + j * 8;
+                buf[off..off + 8].copy_from_slice(&child.to_le_bytes());
+            }
+            seal_node(&mut buf, NODE_INTERNAL, keys.len() - 1, next);
+            write_node(block_io, next, &buf)?;
+            up.push(keys[0]);
+            next += 1;
+        }
+        level_start = up_start;
+        firsts = up;
+    }
+    Ok((next - region_start, entries))
+}
+
+/// Open the region at `region_start` for lookups. Only the root is read here;
+/// every other node is read on first use.
+#[allow(clippy::too_many_arguments)]
+pub fn open_index_region<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    region_start: u64,
+    block_count: u64,
+    entry_count: u64,
+) -> Result<IndexRegion, HelixError> {
+    if block_count != region_blocks(entry_count as usize) {
+        return Err(HelixError::IndexCrcMismatch);
+    }
+    let region = IndexRegion {
+        partition_lba_start,
+        data_start_block,
+        device_block_size,
+        start: region_start,
+        blocks: block_count,
+        entries: entry_count,
+        cache: RefCell::new(NodeCache::default()),
+    };
+    region.with_node(block_io, region.root(), |_| ())?;
+    Ok(region)
+}
+
+/// Recently used nodes of one region, verified; evicts the least recently used.
+#[derive(Default)]
+struct NodeCache {
+    /// `(block, last use, contents)`.
+    nodes: Vec<(BlockAddr, u64, Vec<u8>)>,
+    clock: u64,
+}
+
+/// A B+tree region opened for lookups. Memory is bounded by the node cache,
+/// not by the namespace; reads go through the caller's device, like every
+/// other engine operation.
+pub struct IndexRegion {
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    start: BlockAddr,
+    blocks: u64,
+    entries: u64,
+    cache: RefCell<NodeCache>,
+}
+
+impl IndexRegion {
+    /// Entries in the leaves.
+    pub fn entry_count(&self) -> u64 {
+        self.entries
+    }
+
+    /// Nodes currently cached.
+    pub fn cached_nodes(&self) -> usize {
+        self.cache.borrow().nodes.len()
+    }
+
+    fn root(&self) -> BlockAddr {
+        self.start + self.blocks - 1
+    }
+
+    /// Run `f` over the verified node at `block`, reading it on a miss.
+    fn with_node<B: BlockIo, T>(
+        &self,
+        block_io: &mut B,
+        block: BlockAddr,
+        f: impl FnOnce(&[u8]) -> T,
+    ) -> Result<T, HelixError> {
+        if block < self.start || block > self.root() {
+            return Err(HelixError::IndexCrcMismatch);
+        }
+        let mut cache = self.cache.borrow_mut();
+        cache.clock += 1;
+        let now = cache.clock;
+        if let Some(node) = cache.nodes.iter_mut().find(|n| n.0 == block) {
+            node.1 = now;
+            return Ok(f(&node.2));
+        }
+
+        let lba = region_lba(
+            self.partition_lba_start,
+            self.data_start_block,
+            self.device_block_size,
+            block,
         );
+        let mut buf = vec![0u8; BLOCK_SIZE as usize];
         block_io
-            .write_blocks(lba, &buf)
-            .map_err(|_| HelixError::IoWriteFailed)?;
+            .read_blocks(lba, &mut buf)
+            .map_err(|_| HelixError::IoReadFailed)?;
+        check_node(&mut buf, block)?;
+        let out = f(&buf);
+        if cache.nodes.len() < NODE_CACHE_NODES {
+            cache.nodes.push((block, now, buf));
+        } else if let Some(lru) = cache.nodes.iter_mut().min_by_key(|n| n.1) {
+            *lru = (block, now, buf);
+        }
+        Ok(out)
+    }
+
+    /// The leaf holding the first entry whose key is >= `key` (or the last
+    /// leaf, when every key is smaller).
+    pub(crate) fn seek<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        key: u64,
+    ) -> Result<BlockAddr, HelixError> {
+        let mut block = self.root();
+        for _ in 0..MAX_DEPTH {
+            let child = self.with_node(block_io, block, |buf| {
+                if buf[0] == NODE_LEAF {
+                    return None;
+                }
+                let keys = u32::from_le_bytes(buf[4..8].try_into().unwrap()) as usize;
+                // Separators are each child's first key; an equal run may
+                // start in the child before, so only strictly smaller ones
+                // are passed.
+                let i = (0..keys)
+                    .take_while(|&i| read_u64(buf, KEYS_OFF + i * 8) < key)
+                    .count();
+                Some(read_u64(buf, CHILDREN_OFF + i * 8))
+            })?;
+            match child {
+                Some(child) => block = child,
+                None if block < self.start + leaf_count(self.entries) => return Ok(block),
+                None => return Err(HelixError::IndexCrcMismatch),
+            }
+        }
+        Err(HelixError::IndexCrcMismatch)
+    }
+
+    /// The entries of leaf `block`, in tree order.
+    pub(crate) fn leaf<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        block: BlockAddr,
+    ) -> Result<Vec<IndexEntry>, HelixError> {
+        self.with_node(block_io, block, |buf| {
+            if buf[0] != NODE_LEAF {
+                return None;
+            }
+            let count = u32::from_le_bytes(buf[4..8].try_into().unwrap()) as usize;
+            Some((0..count).map(|slot| leaf_entry(buf, slot)).collect())
+        })?
+        .ok_or(HelixError::IndexCrcMismatch)
+    }
+
+    /// The leaf after `block`; `BLOCK_NULL` past the last.
+    pub(crate) fn next_leaf(&self, block: BlockAddr) -> BlockAddr {
+        if block + 1 < self.start + leaf_count(self.entries) {
+            block + 1
+        } else {
+            BLOCK_NULL
+        }
     }
-    Ok(())
 }
 
-pub fn load_index_region<B: BlockIo>(
+/// Read every leaf of a region into `index`, skipping entries that fail their
+/// CRC instead of stopping, and verify the internal nodes. Returns each bad
+/// `(block, slot)`; `slot` is `None` for a node that fails its own checks
+/// while its entries pass. For fsck.
+#[allow(clippy::too_many_arguments)]
+pub fn salvage_index_region<B: BlockIo>(
     block_io: &mut B,
     partition_lba_start: u64,
     data_start_block: u64,
@@ -75,35 +362,46 @@ pub fn load_index_region<B: BlockIo>(
     block_count: u64,
     entry_count: u64,
     index: &mut NamespaceIndex,
-) -> Result<(), HelixError> {
-    let mut loaded: u64 = 0;
+) -> Result<Vec<(BlockAddr, Option<usize>)>, HelixError> {
+    let mut bad = Vec::new();
+    let leaves = leaf_count(entry_count).min(block_count);
+    let mut remaining = entry_count;
     for b in 0..block_count {
-        if loaded >= entry_count {
-            break;
-        }
+        let block = region_start + b;
         let lba = region_lba(
             partition_lba_start,
             data_start_block,
             device_block_size,
-            region_start + b,
+            block,
         );
         let mut buf = vec![0u8; BLOCK_SIZE as usize];
         block_io
             .read_blocks(lba, &mut buf)
             .map_err(|_| HelixError::IoReadFailed)?;
-        for slot in 0..ENTRIES_PER_INDEX_BLOCK {
-            if loaded >= entry_count {
-                break;
+        let node = check_node(&mut buf, block);
+        if b >= leaves {
+            if !matches!(node, Ok((NODE_INTERNAL, _))) {
+                bad.push((block, None));
             }
-            let off = slot * ENTRY_SIZE;
-            let e: IndexEntry =
-                unsafe { core::ptr::read_unaligned(buf[off..].as_ptr() as *const IndexEntry) };
-            if entry_crc(&e) != e.crc32c {
-                return Err(HelixError::IndexCrcMismatch);
+            continue;
+        }
+        // Entries are checked one by one: a leaf is packed full except the
+        // last, so its count is known without trusting the header.
+        let count = remaining.min(LEAF_ENTRIES_PER_BLOCK as u64) as usize;
+        remaining -= count as u64;
+        let mut entries_ok = true;
+        for slot in 0..count {
+            let e = leaf_entry(&buf, slot);
+            if entry_crc(&e) == e.crc32c {
+                index.upsert(e);
+            } else {
+                bad.push((block, Some(slot)));
+                entries_ok = false;
             }
-            index.upsert(e);
-            loaded += 1;
+        }
+        if entries_ok && node != Ok((NODE_LEAF, count)) {
+            bad.push((block, None));
         }
     }
-    Ok(())
+    Ok(bad)
 }
@@ -0,0 +1,238 @@
+//! Background cleaning: log compaction and online defragmentation.
+//!
+//! Neither is needed for correctness; together they keep a long-running
+//! volume from degrading. `HelixFs::clean_step` does a bounded slice of work
+//! and returns, so a caller can drive it from an idle loop with an I/O budget.
+//!
+//! The log ring holds every record since the last checkpoint, but only the
+//! newest change to each path is still live, and the index overlay already
+//! holds exactly those. Compacting the live data out of the ring is therefore
+//! a checkpoint: the cleaner takes one early once the ring is filling with
+//! mostly dead records, instead of a write hitting `LogFull` later.
+//!
+//! Defragmentation walks the index in tree order, resuming where the last
+//! step stopped, and moves each fragmented file (`IS_EXTENT_NODE`) into one
+//! contiguous run: copy the blocks, log a `Relocate` with payload
+//! `[path_len: u16][path][extent_root: u64]`, then free the old runs and node
+//! block. Content, version, mtime and owner are untouched. Files a snapshot
+//! may still see, shared extents, compressed frames and sparse files stay put.
+//!
+//! `HelixFs::resize` uses the same record to empty the tail of a shrinking
+//! volume, where any extent-backed file may have to move.
+
+use crate::bitmap::BlockBitmap;
+use crate::crc::fnv1a_64;
+use crate::error::HelixError;
+use crate::index::btree::{self, NamespaceIndex};
+use crate::log::LogEngine;
+use crate::types::*;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+
+/// Ring fill (percent) past which a mostly dead ring is checkpointed.
+pub const LOG_CLEAN_PCT: u32 = 50;
+
+/// Ring fill (percent) past which the ring is checkpointed however live it is.
+pub const LOG_FORCE_PCT: u32 = 80;
+
+/// Cumulative cleaner progress since mount.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CleanStats {
+    /// Checkpoints taken to recycle the log ring.
+    pub checkpoints: u64,
+    /// Index entries the defragmenter has looked at.
+    pub files_scanned: u64,
+    /// Files moved into a contiguous run.
+    pub files_defragmented: u64,
+    /// Data blocks those moves copied.
+    pub blocks_moved: u64,
+    /// Complete walks over the index.
+    pub passes: u64,
+    /// Blocks of I/O spent, checkpoints included.
+    pub io_blocks: u64,
+}
+
+/// Outcome of one `clean_step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanStep {
+    /// Blocks of I/O this step spent.
+    pub io_blocks: u64,
+    /// A whole pass found nothing to do; the caller may back off.
+    pub idle: bool,
+}
+
+/// Cleaner state carried between steps.
+#[derive(Default)]
+pub(crate) struct CleanState {
+    pub stats: CleanStats,
+    /// Path of the last entry the current pass looked at.
+    pub resume: Option<String>,
+    /// Files moved since the current pass began.
+    pub moved_this_pass: u64,
+}
+
+/// Whether the ring is worth recycling now. Records since the checkpoint
+/// against paths changed since it estimate how much of the ring is dead.
+pub fn log_wants_checkpoint(log: &LogEngine, index: &NamespaceIndex, checkpoint_lsn: Lsn) -> bool {
+    let fill = log.fill_pct();
+    if fill >= LOG_FORCE_PCT {
+        return true;
+    }
+    let records = log
+        .next_lsn()
+        .saturating_sub(1)
+        .saturating_sub(checkpoint_lsn);
+    fill >= LOG_CLEAN_PCT && (index.overlay_len() as u64) * 2 <= records
+}
+
+/// Blocks a checkpoint would cost now: reading the current index region of
+/// `region_blocks` through and writing one sized for its `base_entries` plus
+/// the overlay's. An overlay entry that replaces a base one is counted twice,
+/// so this errs high.
+pub fn checkpoint_blocks(region_blocks: u64, base_entries: u64, index: &NamespaceIndex) -> u64 {
+    region_blocks + crate::checkpoint::region_blocks(base_entries as usize + index.overlay_len())
+}
+
+/// Whether `e` is a fragmented file the defragmenter may move. Pinning and
+/// sharing are the caller's to check.
+pub fn is_fragmented(e: &IndexEntry) -> bool {
+    e.flags & entry_flags::IS_EXTENT_NODE != 0
+        && e.flags
+            & (entry_flags::IS_DIR
+                | entry_flags::IS_DELETED
+                | entry_flags::IS_INLINE
+                | entry_flags::IS_COMPRESSED)
+            == 0
+        && e.extent_root != BLOCK_NULL
+}
+
+/// Move the extent-backed file `entry`, whose runs are `runs`, into one
+/// contiguous run and log the move. A sparse file has its holes written out
+/// as zeros if `fill_holes`, else is left alone. Returns the new
+/// `extent_root`, or `None` if the file stays put or no free run is long
+/// enough. The old runs and node block are left for the caller to free.
+#[allow(clippy::too_many_arguments)]
+pub fn relocate<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    entry: &IndexEntry,
+    runs: &[(u64, u64, u32)],
+    fill_holes: bool,
+    timestamp_ns: u64,
+) -> Result<Option<BlockAddr>, HelixError> {
+    // A compressed file moves as its stored frame.
+    let Some((_, stored, _)) = crate::dedup::extent_of(entry) else {
+        return Ok(None);
+    };
+    let blocks = stored.div_ceil(BLOCK_SIZE as u64);
+    let sparse = runs.iter().map(|r| r.2 as u64).sum::<u64>() != blocks;
+    if blocks == 0 || sparse && !fill_holes {
+        return Ok(None);
+    }
+    let start = match bitmap.alloc_contiguous(blocks) {
+        Ok(start) => start,
+        Err(HelixError::NoSpace) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let scale = BLOCK_SIZE as u64 / device_block_size as u64;
+    let lba = |block: u64| Lba(partition_lba_start + (data_start_block + block) * scale);
+    if sparse {
+        let zero = vec![0u8; BLOCK_SIZE as usize];
+        for logical in 0..blocks {
+            if crate::extent::map_logical(runs, logical).is_some() {
+                continue;
+            }
+            if block_io.write_blocks(lba(start + logical), &zero).is_err() {
+                let _ = bitmap.free_range(start, blocks);
+                return Err(HelixError::IoWriteFailed);
+            }
+        }
+    }
+    let mut buf = vec![0u8; BLOCK_SIZE as usize];
+    for &(logical, physical, count) in runs {
+        for j in 0..count as u64 {
+            let copied = block_io
+                .read_blocks(lba(physical + j), &mut buf)
+                .map_err(|_| HelixError::IoReadFailed)
+                .and_then(|_| {
+                    block_io
+                        .write_blocks(lba(start + logical + j), &buf)
+                        .map_err(|_| HelixError::IoWriteFailed)
+                });
+            if let Err(e) = copied {
+                let _ = bitmap.free_range(start, blocks);
+                return Err(e);
+            }
+        }
+    }
+
+    if let Err(e) = log_relocate(block_io, log, index, entry, start, timestamp_ns) {
+        let _ = bitmap.free_range(start, blocks);
+        return Err(e);
+    }
+    Ok(Some(start))
+}
+
+/// Log that `entry`'s content now lives in the contiguous run at
+/// `extent_root`, already written, and point the index there. `relocate`
+/// without the copy, for a second name sharing an extent that already moved.
+pub fn log_relocate<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    entry: &IndexEntry,
+    extent_root: BlockAddr,
+    timestamp_ns: u64,
+) -> Result<(), HelixError> {
+    let path_b = btree::path_str(&entry.path).as_bytes();
+    let mut payload = Vec::with_capacity(2 + path_b.len() + 8);
+    payload.extend_from_slice(&(path_b.len() as u16).to_le_bytes());
+    payload.extend_from_slice(path_b);
+    payload.extend_from_slice(&extent_root.to_le_bytes());
+    log.append(
+        block_io,
+        LogOp::Relocate,
+        fnv1a_64(path_b),
+        &payload,
+        timestamp_ns,
+    )?;
+    let mut moved = *entry;
+    install(&mut moved, extent_root);
+    index.upsert(moved);
+    Ok(())
+}
+
+fn install(entry: &mut IndexEntry, extent_root: BlockAddr) {
+    entry.extent_root = extent_root;
+    entry.flags &= !entry_flags::IS_EXTENT_NODE;
+}
+
+/// Replay a `Relocate` record; `rest` follows the payload's path.
+pub(crate) fn apply_relocate<B: BlockIo>(
+    block_io: &mut B,
+    index: &mut NamespaceIndex,
+    path: &str,
+    rest: &[u8],
+) -> Result<(), HelixError> {
+    if rest.len() < 8 {
+        return Ok(());
+    }
+    let extent_root = u64::from_le_bytes(rest[..8].try_into().unwrap());
+    if let Some(mut entry) = index
+        .lookup(block_io, path)?
+        .filter(|e| crate::dedup::extent_of(e).is_some())
+    {
+        install(&mut entry, extent_root);
+        index.upsert(entry);
+    }
+    Ok(())
+}
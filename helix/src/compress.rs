@@ -0,0 +1,317 @@
+//! Transparent compression: an LZ4 block codec and the chunked frame a
+//! `COMPRESSED` extent stores.
+//!
+//! Frame layout (contiguous blocks starting at `extent_root`):
+//! `[crc32c: u32][chunk_count: u32][end: u32 × chunk_count][chunk 0][chunk 1]…`
+//! Chunk `k` holds logical bytes `[k·CHUNK_SIZE, (k+1)·CHUNK_SIZE)` and spans
+//! frame bytes `[end[k-1], end[k])` (`end[-1]` = header length). A chunk whose
+//! stored length equals its logical length is kept raw. The CRC covers the
+//! header after itself, so a range read can trust the offsets before it has
+//! touched any chunk. Chunking keeps `read_at` cost proportional to the range.
+
+use crate::crc::crc32c;
+use crate::error::HelixError;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Logical bytes per independently decodable chunk.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Largest compressed file a ranged write turns back into plain blocks when
+/// its new frame does not pay off; the whole file is staged on the heap.
+pub const MAX_UNPACK_SIZE: u64 = 1 << 20;
+
+/// Policy attribute; see `morpheus_foundation::storage::XATTR_COMPRESSION`.
+pub const POLICY_XATTR: &str = morpheus_foundation::storage::XATTR_COMPRESSION;
+pub const POLICY_LZ4: &[u8] = b"lz4";
+pub const POLICY_NONE: &[u8] = b"none";
+
+const MIN_MATCH: usize = 4;
+/// The last 5 bytes are always literals, and no match starts in the last 12.
+const LAST_LITERALS: usize = 5;
+const MF_LIMIT: usize = 12;
+const MAX_OFFSET: usize = 0xFFFF;
+const HASH_LOG: u32 = 12;
+
+fn hash(v: u32) -> usize {
+    (v.wrapping_mul(2_654_435_761) >> (32 - HASH_LOG)) as usize
+}
+
+fn read_u32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn push_len(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 255 {
+        out.push(255);
+        n -= 255;
+    }
+    out.push(n as u8);
+}
+
+fn push_sequence(out: &mut Vec<u8>, literals: &[u8], m: Option<(usize, usize)>) {
+    let lit = literals.len();
+    let ml = m.map_or(0, |(_, len)| len - MIN_MATCH);
+    let token = ((lit.min(15) as u8) << 4) | ml.min(15) as u8;
+    out.push(token);
+    if lit >= 15 {
+        push_len(out, lit - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = m {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if ml >= 15 {
+            push_len(out, ml - 15);
+        }
+    }
+}
+
+/// LZ4 block-format compression of `src`, appended to `out`.
+pub fn compress_block(src: &[u8], out: &mut Vec<u8>) {
+    let n = src.len();
+    let mut anchor = 0;
+    if n > MF_LIMIT {
+        let mut table = vec![0u32; 1 << HASH_LOG];
+        let limit = n - MF_LIMIT;
+        let match_end = n - LAST_LITERALS;
+        let mut i = 0;
+        while i < limit {
+            let seq = read_u32(src, i);
+            let h = hash(seq);
+            let cand = table[h] as usize;
+            table[h] = i as u32;
+            if cand >= i || i - cand > MAX_OFFSET || read_u32(src, cand) != seq {
+                i += 1;
+                continue;
+            }
+            let (mut start, mut back) = (i, cand);
+            while start > anchor && back > 0 && src[start - 1] == src[back - 1] {
+                start -= 1;
+                back -= 1;
+            }
+            let mut end = i + MIN_MATCH;
+            while end < match_end && src[end] == src[cand + end - i] {
+                end += 1;
+            }
+            push_sequence(out, &src[anchor..start], Some((start - back, end - start)));
+            anchor = end;
+            i = end;
+        }
+    }
+    push_sequence(out, &src[anchor..], None);
+}
+
+/// Decode an LZ4 block that must expand to exactly `out_len` bytes.
+pub fn decompress_block(src: &[u8], out_len: usize) -> Result<Vec<u8>, HelixError> {
+    let mut out = Vec::with_capacity(out_len);
+    let mut i = 0;
+    let take_len = |i: &mut usize, mut n: usize| -> Result<usize, HelixError> {
+        loop {
+            let b = *src.get(*i).ok_or(HelixError::ExtentCorrupt)?;
+            *i += 1;
+            n += b as usize;
+            if b != 255 {
+                return Ok(n);
+            }
+        }
+    };
+    loop {
+        let token = *src.get(i).ok_or(HelixError::ExtentCorrupt)?;
+        i += 1;
+        let mut lit = (token >> 4) as usize;
+        if lit == 15 {
+            lit = take_len(&mut i, lit)?;
+        }
+        let literals = src.get(i..i + lit).ok_or(HelixError::ExtentCorrupt)?;
+        if out.len() + lit > out_len {
+            return Err(HelixError::ExtentCorrupt);
+        }
+        out.extend_from_slice(literals);
+        i += lit;
+        if i == src.len() {
+            break;
+        }
+        let off = src.get(i..i + 2).ok_or(HelixError::ExtentCorrupt)?;
+        let offset = u16::from_le_bytes([off[0], off[1]]) as usize;
+        i += 2;
+        let mut len = (token & 0x0F) as usize;
+        if len == 15 {
+            len = take_len(&mut i, len)?;
+        }
+        len += MIN_MATCH;
+        if offset == 0 || offset > out.len() || out.len() + len > out_len {
+            return Err(HelixError::ExtentCorrupt);
+        }
+        // Byte-wise: an overlapping match repeats its own output.
+        let from = out.len() - offset;
+        for k in 0..len {
+            out.push(out[from + k]);
+        }
+    }
+    if out.len() != out_len {
+        return Err(HelixError::ExtentCorrupt);
+    }
+    Ok(out)
+}
+
+pub fn chunk_count(size: u64) -> usize {
+    (size as usize).div_ceil(CHUNK_SIZE)
+}
+
+pub fn header_len(chunks: usize) -> usize {
+    8 + 4 * chunks
+}
+
+/// Encode `data` as a frame. Callers keep `data.len()` within `u32`.
+pub fn pack(data: &[u8]) -> Vec<u8> {
+    let chunks = chunk_count(data.len() as u64);
+    let mut frame = vec![0u8; header_len(chunks)];
+    let mut scratch = Vec::new();
+    for (k, raw) in data.chunks(CHUNK_SIZE).enumerate() {
+        push_raw(&mut frame, k, raw, &mut scratch);
+    }
+    seal(&mut frame, chunks);
+    frame
+}
+
+/// Re-encode the frame of a `size`-byte file as `new_size` bytes with `data`
+/// written at `offset` (`new_size` covers it; a gap reads as zeros). Only
+/// the chunks that change are decoded and compressed again; the rest are
+/// carried over as stored.
+pub fn splice(
+    frame: &[u8],
+    size: u64,
+    offset: u64,
+    data: &[u8],
+    new_size: u64,
+) -> Result<Vec<u8>, HelixError> {
+    let old = FrameHeader::parse(frame, size, frame.len() as u64)?;
+    let end = offset + data.len() as u64;
+    let chunks = chunk_count(new_size);
+    let mut out = vec![0u8; header_len(chunks)];
+    let mut scratch = Vec::new();
+    for k in 0..chunks {
+        let base = (k * CHUNK_SIZE) as u64;
+        let len = (new_size - base).min(CHUNK_SIZE as u64) as usize;
+        let touched = !data.is_empty() && offset < base + len as u64 && end > base;
+        if k < old.chunks() && old.raw_len(k) == len && !touched {
+            let (s, e) = old.span(k);
+            push_stored(&mut out, k, &frame[s..e]);
+            continue;
+        }
+        let mut raw = vec![0u8; len];
+        if k < old.chunks() {
+            let (s, e) = old.span(k);
+            let prev = old.unpack(k, &frame[s..e])?;
+            let keep = prev.len().min(len);
+            raw[..keep].copy_from_slice(&prev[..keep]);
+        }
+        if touched {
+            let lo = offset.max(base);
+            let hi = end.min(base + len as u64);
+            raw[(lo - base) as usize..(hi - base) as usize]
+                .copy_from_slice(&data[(lo - offset) as usize..(hi - offset) as usize]);
+        }
+        push_raw(&mut out, k, &raw, &mut scratch);
+    }
+    seal(&mut out, chunks);
+    Ok(out)
+}
+
+/// Append chunk `k` from its logical bytes, compressed unless that does not
+/// shrink it.
+fn push_raw(frame: &mut Vec<u8>, k: usize, raw: &[u8], scratch: &mut Vec<u8>) {
+    scratch.clear();
+    compress_block(raw, scratch);
+    if scratch.len() < raw.len() {
+        push_stored(frame, k, scratch);
+    } else {
+        push_stored(frame, k, raw);
+    }
+}
+
+/// Append chunk `k` as stored and record where it ends.
+fn push_stored(frame: &mut Vec<u8>, k: usize, stored: &[u8]) {
+    frame.extend_from_slice(stored);
+    let end = (frame.len() as u32).to_le_bytes();
+    frame[8 + 4 * k..12 + 4 * k].copy_from_slice(&end);
+}
+
+/// Fill in the chunk count and header CRC once every chunk is in.
+fn seal(frame: &mut [u8], chunks: usize) {
+    frame[4..8].copy_from_slice(&(chunks as u32).to_le_bytes());
+    let crc = crc32c(&frame[4..header_len(chunks)]);
+    frame[..4].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Validated chunk offsets of a frame holding `size` logical bytes in
+/// `stored_len` bytes. `header` needs at least `header_len` bytes.
+pub struct FrameHeader {
+    ends: Vec<u32>,
+    size: u64,
+}
+
+impl FrameHeader {
+    pub fn parse(header: &[u8], size: u64, stored_len: u64) -> Result<Self, HelixError> {
+        let chunks = chunk_count(size);
+        let hdr = header_len(chunks);
+        if header.len() < hdr || (read_u32(header, 4) as usize) != chunks {
+            return Err(HelixError::ExtentCorrupt);
+        }
+        if crc32c(&header[4..hdr]) != read_u32(header, 0) {
+            return Err(HelixError::ExtentCorrupt);
+        }
+        let ends: Vec<u32> = (0..chunks).map(|k| read_u32(header, 8 + 4 * k)).collect();
+        let mut prev = hdr as u64;
+        for &end in &ends {
+            if (end as u64) < prev {
+                return Err(HelixError::ExtentCorrupt);
+            }
+            prev = end as u64;
+        }
+        if prev != stored_len {
+            return Err(HelixError::ExtentCorrupt);
+        }
+        Ok(Self { ends, size })
+    }
+
+    pub fn chunks(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Frame byte range of chunk `k`.
+    pub fn span(&self, k: usize) -> (usize, usize) {
+        let start = if k == 0 {
+            header_len(self.ends.len())
+        } else {
+            self.ends[k - 1] as usize
+        };
+        (start, self.ends[k] as usize)
+    }
+
+    /// Logical length of chunk `k`.
+    pub fn raw_len(&self, k: usize) -> usize {
+        (self.size as usize - k * CHUNK_SIZE).min(CHUNK_SIZE)
+    }
+
+    /// Decode chunk `k` from its stored bytes.
+    pub fn unpack(&self, k: usize, stored: &[u8]) -> Result<Vec<u8>, HelixError> {
+        let raw = self.raw_len(k);
+        if stored.len() == raw {
+            Ok(stored.to_vec())
+        } else {
+            decompress_block(stored, raw)
+        }
+    }
+}
+
+/// Decode a whole frame.
+pub fn unpack(frame: &[u8], size: u64) -> Result<Vec<u8>, HelixError> {
+    let hdr = FrameHeader::parse(frame, size, frame.len() as u64)?;
+    let mut out = Vec::with_capacity(size as usize);
+    for k in 0..hdr.chunks() {
+        let (s, e) = hdr.span(k);
+        out.extend_from_slice(&hdr.unpack(k, &frame[s..e])?);
+    }
+    Ok(out)
+}
@@ -0,0 +1,222 @@
+//! ChaCha20-Poly1305 AEAD (RFC 8439). Constant-time in the key and data: no
+//! table lookups or secret-dependent branches.
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 12;
+pub const TAG_LEN: usize = 16;
+
+fn quarter(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+fn le32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn chacha_block(key: &[u8; KEY_LEN], counter: u32, nonce: &[u8; NONCE_LEN]) -> [u8; 64] {
+    let mut init = [0u32; 16];
+    init[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    for i in 0..8 {
+        init[4 + i] = le32(&key[4 * i..]);
+    }
+    init[12] = counter;
+    for i in 0..3 {
+        init[13 + i] = le32(&nonce[4 * i..]);
+    }
+    let mut s = init;
+    for _ in 0..10 {
+        quarter(&mut s, 0, 4, 8, 12);
+        quarter(&mut s, 1, 5, 9, 13);
+        quarter(&mut s, 2, 6, 10, 14);
+        quarter(&mut s, 3, 7, 11, 15);
+        quarter(&mut s, 0, 5, 10, 15);
+        quarter(&mut s, 1, 6, 11, 12);
+        quarter(&mut s, 2, 7, 8, 13);
+        quarter(&mut s, 3, 4, 9, 14);
+    }
+    let mut out = [0u8; 64];
+    for i in 0..16 {
+        out[4 * i..4 * i + 4].copy_from_slice(&s[i].wrapping_add(init[i]).to_le_bytes());
+    }
+    out
+}
+
+/// XOR `data` with the ChaCha20 keystream starting at block `counter`.
+pub fn chacha20(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], counter: u32, data: &mut [u8]) {
+    for (i, chunk) in data.chunks_mut(64).enumerate() {
+        let ks = chacha_block(key, counter.wrapping_add(i as u32), nonce);
+        for (d, k) in chunk.iter_mut().zip(ks) {
+            *d ^= k;
+        }
+    }
+}
+
+/// Poly1305 in 26-bit limbs.
+struct Poly1305 {
+    r: [u32; 5],
+    h: [u32; 5],
+    pad: [u32; 4],
+}
+
+impl Poly1305 {
+    fn new(key: &[u8; 32]) -> Self {
+        Self {
+            r: [
+                le32(&key[0..]) & 0x03ff_ffff,
+                (le32(&key[3..]) >> 2) & 0x03ff_ff03,
+                (le32(&key[6..]) >> 4) & 0x03ff_c0ff,
+                (le32(&key[9..]) >> 6) & 0x03f0_3fff,
+                (le32(&key[12..]) >> 8) & 0x000f_ffff,
+            ],
+            h: [0; 5],
+            pad: [
+                le32(&key[16..]),
+                le32(&key[20..]),
+                le32(&key[24..]),
+                le32(&key[28..]),
+            ],
+        }
+    }
+
+    /// Absorb `data` zero-padded to a 16-byte multiple, as the AEAD does.
+    fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut block = [0u8; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.block(&block);
+        }
+    }
+
+    fn block(&mut self, m: &[u8; 16]) {
+        let [r0, r1, r2, r3, r4] = self.r.map(u64::from);
+        let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+        let h = &mut self.h;
+        let h0 = (h[0] + (le32(&m[0..]) & 0x03ff_ffff)) as u64;
+        let h1 = (h[1] + ((le32(&m[3..]) >> 2) & 0x03ff_ffff)) as u64;
+        let h2 = (h[2] + ((le32(&m[6..]) >> 4) & 0x03ff_ffff)) as u64;
+        let h3 = (h[3] + ((le32(&m[9..]) >> 6) & 0x03ff_ffff)) as u64;
+        let h4 = (h[4] + ((le32(&m[12..]) >> 8) | (1 << 24))) as u64;
+
+        let d0 = h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3;
+        let mut d3 = h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4;
+        let mut d4 = h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0;
+
+        d1 += d0 >> 26;
+        d2 += d1 >> 26;
+        d3 += d2 >> 26;
+        d4 += d3 >> 26;
+        let mut n0 = (d0 & 0x03ff_ffff) as u32 + (d4 >> 26) as u32 * 5;
+        let n1 = (d1 & 0x03ff_ffff) as u32 + (n0 >> 26);
+        n0 &= 0x03ff_ffff;
+        *h = [
+            n0,
+            n1,
+            (d2 & 0x03ff_ffff) as u32,
+            (d3 & 0x03ff_ffff) as u32,
+            (d4 & 0x03ff_ffff) as u32,
+        ];
+    }
+
+    fn finish(self) -> [u8; TAG_LEN] {
+        let mut h = self.h;
+        // Full carry, then subtract p = 2^130 - 5 if h >= p.
+        let mut c;
+        for i in 1..5 {
+            c = h[i - 1] >> 26;
+            h[i - 1] &= 0x03ff_ffff;
+            h[i] += c;
+        }
+        c = h[4] >> 26;
+        h[4] &= 0x03ff_ffff;
+        h[0] += c * 5;
+        c = h[0] >> 26;
+        h[0] &= 0x03ff_ffff;
+        h[1] += c;
+
+        let mut g = [0u32; 5];
+        c = 5;
+        for i in 0..4 {
+            g[i] = h[i] + c;
+            c = g[i] >> 26;
+            g[i] &= 0x03ff_ffff;
+        }
+        g[4] = h[4].wrapping_add(c).wrapping_sub(1 << 26);
+        let use_g = (g[4] >> 31).wrapping_sub(1);
+        for i in 0..5 {
+            h[i] = (h[i] & !use_g) | (g[i] & use_g);
+        }
+
+        let words = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8),
+        ];
+        let mut out = [0u8; TAG_LEN];
+        let mut carry = 0u64;
+        for i in 0..4 {
+            carry += words[i] as u64 + self.pad[i] as u64;
+            out[4 * i..4 * i + 4].copy_from_slice(&(carry as u32).to_le_bytes());
+            carry >>= 32;
+        }
+        out
+    }
+}
+
+fn tag_of(
+    key: &[u8; KEY_LEN],
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> [u8; TAG_LEN] {
+    let otk = chacha_block(key, 0, nonce);
+    let mut poly = Poly1305::new(otk[..32].try_into().unwrap());
+    poly.update_padded(aad);
+    poly.update_padded(ciphertext);
+    let mut lens = [0u8; 16];
+    lens[..8].copy_from_slice(&(aad.len() as u64).to_le_bytes());
+    lens[8..].copy_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+    poly.block(&lens);
+    poly.finish()
+}
+
+/// Encrypt `data` in place; returns the tag.
+pub fn seal(
+    key: &[u8; KEY_LEN],
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    data: &mut [u8],
+) -> [u8; TAG_LEN] {
+    chacha20(key, nonce, 1, data);
+    tag_of(key, nonce, aad, data)
+}
+
+/// Authenticate then decrypt `data` in place; `false` (data untouched) if
+/// `tag` does not match.
+pub fn open(
+    key: &[u8; KEY_LEN],
+    nonce: &[u8; NONCE_LEN],
+    aad: &[u8],
+    data: &mut [u8],
+    tag: &[u8; TAG_LEN],
+) -> bool {
+    let expect = tag_of(key, nonce, aad, data);
+    let diff = expect
+        .iter()
+        .zip(tag)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return false;
+    }
+    chacha20(key, nonce, 1, data);
+    true
+}
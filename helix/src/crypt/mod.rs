@@ -0,0 +1,567 @@
+//! Encryption at rest.
+//!
+//! An encrypted volume seals every block except the two superblocks and the
+//! tag region with ChaCha20-Poly1305 under a random per-volume key. That
+//! covers the log (and so inline file data), the bitmap, index checkpoints
+//! and extents alike; the engine never sees ciphertext because it runs on a
+//! `CryptIo` that wraps the raw device.
+//!
+//! Block `b` is sealed with nonce `[b: u64][counter: u32]` (little-endian).
+//! Its counters and tags live in the tag region, entry `b` of
+//! `TAGS_PER_BLOCK` per block: `[ctr_a: u32][ctr_b: u32][tag_a][tag_b]`. A
+//! write takes `max(ctr_a, ctr_b) + 1`, overwrites the older slot and writes
+//! that tag block before the data, so a crash between the two still leaves
+//! the old ciphertext matching the other slot. Both counters zero means the
+//! block was never written and reads as zeros.
+//!
+//! The volume key is sealed in the superblock's `CryptHeader` under a key
+//! derived from the passphrase (PBKDF2-HMAC-SHA256), with the volume UUID as
+//! associated data. The raw superblock carries `crypt_flags::ACTIVE`, so a
+//! plain mount fails with `Encrypted`; `CryptIo` clears it on the way up and
+//! sets it again on the way down.
+//!
+//! Not covered: rolling a block back together with its tag entry to an
+//! earlier version, and the superblock itself, which is only CRC-protected.
+//! `format` does not scrub old plaintext from the data area; it is
+//! unreachable but stays on disk until overwritten.
+
+pub mod aead;
+pub mod kdf;
+
+use crate::error::HelixError;
+use crate::format;
+use crate::log::recovery::newest_superblock;
+use crate::types::*;
+use aead::{KEY_LEN, NONCE_LEN, TAG_LEN};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::{BlockSize, Lba};
+
+const TAG_ENTRY: usize = 8 + 2 * TAG_LEN;
+/// Tag entries per tag-region block.
+pub const TAGS_PER_BLOCK: u64 = BLOCK_SIZE as u64 / TAG_ENTRY as u64;
+/// Passphrase stretching for new volumes.
+pub const DEFAULT_KDF_ROUNDS: u32 = 100_000;
+/// Tag blocks kept in memory; each covers `TAGS_PER_BLOCK` data blocks. A
+/// sealed write run spans at most this many, so its updated tag blocks all
+/// stay cached until they are written out.
+const TAG_CACHE_BLOCKS: usize = 16;
+
+/// Tag-region size for a volume of `total_blocks` blocks.
+pub fn tag_blocks_for(total_blocks: u64) -> u64 {
+    total_blocks.div_ceil(TAGS_PER_BLOCK)
+}
+
+/// Key material for a new volume. The caller supplies the randomness.
+pub struct KeyParams<'a> {
+    pub passphrase: &'a [u8],
+    pub salt: [u8; 16],
+    pub volume_key: [u8; KEY_LEN],
+    pub kdf_rounds: u32,
+}
+
+/// An unlocked volume: the key plus the layout `CryptIo` needs.
+pub struct Crypt {
+    key: [u8; KEY_LEN],
+    lba_start: u64,
+    sector: usize,
+    scale: u64,
+    total_blocks: u64,
+    tag_start: BlockAddr,
+    tag_blocks: u64,
+    /// Write-through cache of tag blocks, by index into the tag region.
+    tags: BTreeMap<u64, Vec<u8>>,
+    /// Tag blocks the write in progress has updated but not written out;
+    /// never evicted.
+    pinned: Range<u64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// Tag region: stored as is.
+    Raw,
+    Superblock,
+    Sealed,
+}
+
+/// Device error, or a block that failed authentication.
+#[derive(Debug)]
+pub enum CryptIoError<E> {
+    Io(E),
+    /// Neither tag slot of `block` authenticates its contents.
+    Auth {
+        block: u64,
+    },
+    /// `block` has used up its nonce counter.
+    CounterExhausted {
+        block: u64,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for CryptIoError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Auth { block } => write!(f, "block {block} failed authentication"),
+            Self::CounterExhausted { block } => write!(f, "block {block} nonce counter exhausted"),
+        }
+    }
+}
+
+fn nonce(block: u64, counter: u32) -> [u8; NONCE_LEN] {
+    let mut n = [0u8; NONCE_LEN];
+    n[..8].copy_from_slice(&block.to_le_bytes());
+    n[8..].copy_from_slice(&counter.to_le_bytes());
+    n
+}
+
+fn derive_kek(passphrase: &[u8], hdr: &CryptHeader) -> [u8; KEY_LEN] {
+    let mut kek = [0u8; KEY_LEN];
+    kdf::pbkdf2_sha256(passphrase, &hdr.salt, hdr.kdf_rounds, &mut kek);
+    kek
+}
+
+/// Format an encrypted HelixFS; the returned `Crypt` opens it. Arguments as
+/// for `format::format_helix`.
+pub fn format<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    total_sectors: u64,
+    device_block_size: u32,
+    label: &str,
+    uuid: [u8; 16],
+    params: &KeyParams<'_>,
+) -> Result<Crypt, HelixError> {
+    let mut sb = format::plan_layout(total_sectors, device_block_size, label, uuid, true)?;
+    sb.crypt.kdf_rounds = params.kdf_rounds.max(1);
+    sb.crypt.salt = params.salt;
+    sb.crypt.wrapped_key = params.volume_key;
+    let kek = derive_kek(params.passphrase, &sb.crypt);
+    sb.crypt.wrapped_tag = aead::seal(&kek, &[0u8; NONCE_LEN], &uuid, &mut sb.crypt.wrapped_key);
+
+    let mut crypt = Crypt::new(
+        params.volume_key,
+        partition_lba_start,
+        device_block_size,
+        &sb,
+    );
+    // Stale bytes in the tag region would read as counters.
+    let zero = vec![0u8; BLOCK_SIZE as usize];
+    for b in 0..sb.crypt.tag_blocks {
+        block_io
+            .write_blocks(Lba(crypt.lba_of(sb.crypt.tag_start + b)), &zero)
+            .map_err(|_| HelixError::IoWriteFailed)?;
+    }
+    format::write_layout(
+        &mut crypt.io(block_io),
+        partition_lba_start,
+        device_block_size,
+        &mut sb,
+    )?;
+    Ok(crypt)
+}
+
+impl Crypt {
+    fn new(
+        key: [u8; KEY_LEN],
+        lba_start: u64,
+        device_block_size: u32,
+        sb: &HelixSuperblock,
+    ) -> Self {
+        Self {
+            key,
+            lba_start,
+            sector: device_block_size as usize,
+            scale: (BLOCK_SIZE / device_block_size) as u64,
+            total_blocks: sb.total_blocks,
+            tag_start: sb.crypt.tag_start,
+            tag_blocks: sb.crypt.tag_blocks,
+            tags: BTreeMap::new(),
+            pinned: 0..0,
+        }
+    }
+
+    /// Unwrap the volume key with `passphrase`. `NotSupported` if the volume
+    /// is not encrypted, `BadPassphrase` if the key does not unwrap.
+    pub fn unlock<B: BlockIo>(
+        block_io: &mut B,
+        partition_lba_start: u64,
+        device_block_size: u32,
+        passphrase: &[u8],
+    ) -> Result<Self, HelixError> {
+        let sb = newest_superblock(block_io, partition_lba_start, device_block_size)?;
+        if sb.crypt.flags & crypt_flags::ACTIVE == 0 {
+            return Err(HelixError::NotSupported);
+        }
+        let kek = derive_kek(passphrase, &sb.crypt);
+        let mut key = sb.crypt.wrapped_key;
+        if !aead::open(
+            &kek,
+            &[0u8; NONCE_LEN],
+            &sb.uuid,
+            &mut key,
+            &sb.crypt.wrapped_tag,
+        ) {
+            return Err(HelixError::BadPassphrase);
+        }
+        Ok(Self::new(key, partition_lba_start, device_block_size, &sb))
+    }
+
+    /// Whether the volume at `partition_lba_start` needs `unlock`.
+    pub fn is_encrypted<B: BlockIo>(
+        block_io: &mut B,
+        partition_lba_start: u64,
+        device_block_size: u32,
+    ) -> bool {
+        newest_superblock(block_io, partition_lba_start, device_block_size)
+            .is_ok_and(|sb| sb.crypt.flags & crypt_flags::ACTIVE != 0)
+    }
+
+    /// The decrypted view of `block_io`.
+    pub fn io<'a, B: BlockIo>(&'a mut self, block_io: &'a mut B) -> CryptIo<'a, B> {
+        CryptIo {
+            dev: block_io,
+            crypt: Some(self),
+        }
+    }
+
+    fn lba_of(&self, block: u64) -> u64 {
+        self.lba_start + block * self.scale
+    }
+
+    /// FS block and sector within it for `lba`; `None` outside the volume.
+    fn locate(&self, lba: u64) -> Option<(u64, u64)> {
+        let rel = lba.checked_sub(self.lba_start)?;
+        let block = rel / self.scale;
+        (block < self.total_blocks).then_some((block, rel % self.scale))
+    }
+
+    fn kind(&self, block: u64) -> Kind {
+        if block == SUPERBLOCK_A_BLOCK || block == SUPERBLOCK_B_BLOCK {
+            Kind::Superblock
+        } else if block >= self.tag_start && block < self.tag_start + self.tag_blocks {
+            Kind::Raw
+        } else {
+            Kind::Sealed
+        }
+    }
+
+    fn tag_entry<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        block: u64,
+    ) -> Result<[u8; TAG_ENTRY], CryptIoError<B::Error>> {
+        let tb = block / TAGS_PER_BLOCK;
+        let at = (block % TAGS_PER_BLOCK) as usize * TAG_ENTRY;
+        if !self.tags.contains_key(&tb) {
+            if self.tags.len() >= TAG_CACHE_BLOCKS {
+                let victim = self.tags.keys().find(|k| !self.pinned.contains(k));
+                if let Some(&victim) = victim {
+                    self.tags.remove(&victim);
+                }
+            }
+            let mut buf = vec![0u8; BLOCK_SIZE as usize];
+            dev.read_blocks(Lba(self.lba_of(self.tag_start + tb)), &mut buf)
+                .map_err(CryptIoError::Io)?;
+            self.tags.insert(tb, buf);
+        }
+        Ok(self.tags[&tb][at..at + TAG_ENTRY].try_into().unwrap())
+    }
+
+    fn set_tag_entry(&mut self, block: u64, entry: &[u8; TAG_ENTRY]) {
+        let at = (block % TAGS_PER_BLOCK) as usize * TAG_ENTRY;
+        // `tag_entry` just loaded it.
+        let buf = self.tags.get_mut(&(block / TAGS_PER_BLOCK)).unwrap();
+        buf[at..at + TAG_ENTRY].copy_from_slice(entry);
+    }
+
+    fn write_tag_blocks<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        first: u64,
+        last: u64,
+    ) -> Result<(), CryptIoError<B::Error>> {
+        for tb in first / TAGS_PER_BLOCK..=last / TAGS_PER_BLOCK {
+            let buf = &self.tags[&tb];
+            dev.write_blocks(Lba(self.lba_of(self.tag_start + tb)), buf)
+                .map_err(CryptIoError::Io)?;
+        }
+        Ok(())
+    }
+
+    /// Turn the stored form of `block` into what the engine sees.
+    fn open_block<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        block: u64,
+        kind: Kind,
+        buf: &mut [u8],
+    ) -> Result<(), CryptIoError<B::Error>> {
+        match kind {
+            Kind::Raw => Ok(()),
+            Kind::Superblock => {
+                set_active(buf, false);
+                Ok(())
+            },
+            Kind::Sealed => {
+                let e = self.tag_entry(dev, block)?;
+                let ctr_a = u32::from_le_bytes(e[0..4].try_into().unwrap());
+                let ctr_b = u32::from_le_bytes(e[4..8].try_into().unwrap());
+                if ctr_a == 0 && ctr_b == 0 {
+                    buf.fill(0);
+                    return Ok(());
+                }
+                let a = (ctr_a, &e[8..8 + TAG_LEN]);
+                let b = (ctr_b, &e[8 + TAG_LEN..]);
+                let slots = if ctr_a >= ctr_b { [a, b] } else { [b, a] };
+                for (ctr, tag) in slots {
+                    let tag: &[u8; TAG_LEN] = tag.try_into().unwrap();
+                    if ctr != 0 && aead::open(&self.key, &nonce(block, ctr), &[], buf, tag) {
+                        return Ok(());
+                    }
+                }
+                Err(CryptIoError::Auth { block })
+            },
+        }
+    }
+
+    /// Turn `buf` into the stored form of `block`, updating its cached tag
+    /// entry (the caller writes the tag block out).
+    fn seal_block<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        block: u64,
+        kind: Kind,
+        buf: &mut [u8],
+    ) -> Result<(), CryptIoError<B::Error>> {
+        match kind {
+            Kind::Raw => Ok(()),
+            Kind::Superblock => {
+                set_active(buf, true);
+                Ok(())
+            },
+            Kind::Sealed => {
+                let mut e = self.tag_entry(dev, block)?;
+                let ctr_a = u32::from_le_bytes(e[0..4].try_into().unwrap());
+                let ctr_b = u32::from_le_bytes(e[4..8].try_into().unwrap());
+                let ctr = ctr_a
+                    .max(ctr_b)
+                    .checked_add(1)
+                    .ok_or(CryptIoError::CounterExhausted { block })?;
+                let tag = aead::seal(&self.key, &nonce(block, ctr), &[], buf);
+                // Replace the older slot; the newer one still matches the
+                // ciphertext on disk until this write lands.
+                if ctr_a <= ctr_b {
+                    e[0..4].copy_from_slice(&ctr.to_le_bytes());
+                    e[8..8 + TAG_LEN].copy_from_slice(&tag);
+                } else {
+                    e[4..8].copy_from_slice(&ctr.to_le_bytes());
+                    e[8 + TAG_LEN..].copy_from_slice(&tag);
+                }
+                self.set_tag_entry(block, &e);
+                Ok(())
+            },
+        }
+    }
+
+    /// Whole blocks from `block` of the same kind that fit in `len` bytes.
+    fn run(&self, block: u64, len: usize) -> u64 {
+        let kind = self.kind(block);
+        let max = (len / BLOCK_SIZE as usize) as u64;
+        let mut n = 1;
+        while n < max && block + n < self.total_blocks && self.kind(block + n) == kind {
+            n += 1;
+        }
+        n
+    }
+
+    fn read<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        start: u64,
+        dst: &mut [u8],
+    ) -> Result<(), CryptIoError<B::Error>> {
+        let bs = BLOCK_SIZE as usize;
+        let mut i = 0;
+        while i < dst.len() {
+            let lba = start + (i / self.sector) as u64;
+            let Some((block, sub)) = self.locate(lba) else {
+                dev.read_blocks(Lba(lba), &mut dst[i..i + self.sector])
+                    .map_err(CryptIoError::Io)?;
+                i += self.sector;
+                continue;
+            };
+            let kind = self.kind(block);
+            let off = sub as usize * self.sector;
+            if off == 0 && dst.len() - i >= bs {
+                let n = self.run(block, dst.len() - i);
+                let buf = &mut dst[i..i + n as usize * bs];
+                dev.read_blocks(Lba(lba), buf).map_err(CryptIoError::Io)?;
+                for (k, b) in buf.chunks_exact_mut(bs).enumerate() {
+                    self.open_block(dev, block + k as u64, kind, b)?;
+                }
+                i += buf.len();
+            } else {
+                let len = (bs - off).min(dst.len() - i);
+                let mut tmp = vec![0u8; bs];
+                dev.read_blocks(Lba(lba - sub), &mut tmp)
+                    .map_err(CryptIoError::Io)?;
+                self.open_block(dev, block, kind, &mut tmp)?;
+                dst[i..i + len].copy_from_slice(&tmp[off..off + len]);
+                i += len;
+            }
+        }
+        Ok(())
+    }
+
+    /// Seal the `buf.len() / BLOCK_SIZE` blocks from `block` and write out
+    /// the tag blocks covering them.
+    fn seal_run<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        block: u64,
+        kind: Kind,
+        buf: &mut [u8],
+    ) -> Result<(), CryptIoError<B::Error>> {
+        let bs = BLOCK_SIZE as usize;
+        for (k, b) in buf.chunks_exact_mut(bs).enumerate() {
+            self.seal_block(dev, block + k as u64, kind, b)?;
+        }
+        if kind == Kind::Sealed {
+            let last = block + (buf.len() / bs) as u64 - 1;
+            self.write_tag_blocks(dev, block, last)?;
+        }
+        Ok(())
+    }
+
+    fn write<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        start: u64,
+        src: &[u8],
+    ) -> Result<(), CryptIoError<B::Error>> {
+        let bs = BLOCK_SIZE as usize;
+        let mut i = 0;
+        while i < src.len() {
+            let lba = start + (i / self.sector) as u64;
+            let located = self.locate(lba);
+            let raw = located.map_or(true, |(block, _)| self.kind(block) == Kind::Raw);
+            if raw {
+                dev.write_blocks(Lba(lba), &src[i..i + self.sector])
+                    .map_err(CryptIoError::Io)?;
+                i += self.sector;
+                continue;
+            }
+            let (block, sub) = located.unwrap();
+            let kind = self.kind(block);
+            let off = sub as usize * self.sector;
+            let (n, consumed, mut buf) = if off == 0 && src.len() - i >= bs {
+                // Stop where the run would need a tag block more than the
+                // cache holds.
+                let span = (block / TAGS_PER_BLOCK + TAG_CACHE_BLOCKS as u64) * TAGS_PER_BLOCK;
+                let n = self.run(block, src.len() - i).min(span - block);
+                let len = n as usize * bs;
+                (n, len, src[i..i + len].to_vec())
+            } else {
+                // Read-modify-write of a partial block.
+                let len = (bs - off).min(src.len() - i);
+                let mut tmp = vec![0u8; bs];
+                dev.read_blocks(Lba(lba - sub), &mut tmp)
+                    .map_err(CryptIoError::Io)?;
+                self.open_block(dev, block, kind, &mut tmp)?;
+                tmp[off..off + len].copy_from_slice(&src[i..i + len]);
+                (1, len, tmp)
+            };
+            self.pinned = block / TAGS_PER_BLOCK..(block + n - 1) / TAGS_PER_BLOCK + 1;
+            let sealed = self.seal_run(dev, block, kind, &mut buf);
+            self.pinned = 0..0;
+            sealed?;
+            dev.write_blocks(Lba(self.lba_of(block)), &buf)
+                .map_err(CryptIoError::Io)?;
+            i += consumed;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Crypt {
+    fn drop(&mut self) {
+        for b in self.key.iter_mut() {
+            // SAFETY: `b` is a valid, exclusive reference.
+            unsafe { core::ptr::write_volatile(b, 0) };
+        }
+    }
+}
+
+/// Set or clear `crypt_flags::ACTIVE` in a superblock image, keeping its CRC
+/// valid. Leaves anything that is not a valid superblock alone.
+fn set_active(buf: &mut [u8], on: bool) {
+    let mut sb: HelixSuperblock =
+        unsafe { core::ptr::read_unaligned(buf.as_ptr() as *const HelixSuperblock) };
+    if !sb.is_valid() {
+        return;
+    }
+    if on {
+        sb.crypt.flags |= crypt_flags::ACTIVE;
+    } else {
+        sb.crypt.flags &= !crypt_flags::ACTIVE;
+    }
+    sb.update_crc();
+    let bytes =
+        unsafe { core::slice::from_raw_parts(&sb as *const _ as *const u8, BLOCK_SIZE as usize) };
+    buf.copy_from_slice(bytes);
+}
+
+/// A block device seen through an optional `Crypt`: plaintext in, sealed
+/// blocks out. With `crypt: None` it passes everything straight through, so
+/// callers can hold one type for plain and encrypted volumes.
+pub struct CryptIo<'a, B> {
+    dev: &'a mut B,
+    crypt: Option<&'a mut Crypt>,
+}
+
+impl<'a, B: BlockIo> CryptIo<'a, B> {
+    pub fn new(dev: &'a mut B, crypt: Option<&'a mut Crypt>) -> Self {
+        Self { dev, crypt }
+    }
+}
+
+impl<B: BlockIo> BlockIo for CryptIo<'_, B> {
+    type Error = CryptIoError<B::Error>;
+
+    fn block_size(&self) -> BlockSize {
+        self.dev.block_size()
+    }
+
+    fn num_blocks(&mut self) -> Result<u64, Self::Error> {
+        self.dev.num_blocks().map_err(CryptIoError::Io)
+    }
+
+    fn read_blocks(&mut self, start_lba: Lba, dst: &mut [u8]) -> Result<(), Self::Error> {
+        match self.crypt.as_deref_mut() {
+            Some(c) => c.read(self.dev, start_lba.0, dst),
+            None => self
+                .dev
+                .read_blocks(start_lba, dst)
+                .map_err(CryptIoError::Io),
+        }
+    }
+
+    fn write_blocks(&mut self, start_lba: Lba, src: &[u8]) -> Result<(), Self::Error> {
+        match self.crypt.as_deref_mut() {
+            Some(c) => c.write(self.dev, start_lba.0, src),
+            None => self
+                .dev
+                .write_blocks(start_lba, src)
+                .map_err(CryptIoError::Io),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.dev.flush().map_err(CryptIoError::Io)
+    }
+}
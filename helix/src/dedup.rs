@@ -0,0 +1,358 @@
+//! Content-addressed deduplication.
+//!
+//! Sharing is per whole extent tree, and only for whole-file writes: a file
+//! whose bytes equal an existing extent file is logged as a `DedupRef`
+//! pointing at that file's `extent_root` instead of a fresh copy. Files that
+//! merely have blocks in common, or a range write that happens to repeat
+//! existing data, are stored apart. Candidates are found by `content_crc64`
+//! and always confirmed with a full byte compare — a CRC match alone never
+//! shares blocks.
+//!
+//! Refcounts are the number of live index entries, retained versions and
+//! snapshots naming a root; a snapshot counts once however many of its names
+//! share it. They are not persisted: the index, the version history and the
+//! snapshots are authoritative, so mount (and transaction abort) recount
+//! them. An entry a snapshot pins is dropped without a release, so its
+//! reference stays counted until the next recount finds the snapshot's.
+//! Every reclaim of an entry's blocks goes through `release`, which frees
+//! only when the last reference drops.
+//!
+//! Sharing can also be per block: a range write to a file under retention
+//! leaves the blocks it does not touch to both the new version and the one
+//! retained. Those blocks are counted by range, recounted the same way, and
+//! freed with their last holder.
+
+use crate::bitmap::BlockBitmap;
+use crate::error::HelixError;
+use crate::index::btree::NamespaceIndex;
+use crate::types::*;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+
+/// An extent file's storage identity: `(extent_root, size, is_node)`.
+pub type ExtentRef = (BlockAddr, u64, bool);
+
+#[derive(Default)]
+pub struct DedupTable {
+    /// content_crc64 -> extent files known to hold that content.
+    by_crc: BTreeMap<u64, Vec<ExtentRef>>,
+    /// extent_root -> its `by_crc` key, so a freed root is dropped directly.
+    crc_of: BTreeMap<BlockAddr, u64>,
+    /// extent_root -> live entries and retained versions referencing it;
+    /// absent = 1 (unshared).
+    refs: BTreeMap<BlockAddr, u32>,
+    /// Data block range start -> (end, extent files holding it beyond the
+    /// first); absent = one holder.
+    blocks: BTreeMap<BlockAddr, (BlockAddr, u32)>,
+}
+
+impl DedupTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recount from the live index and the roots `held` by retained versions
+    /// (mount, transaction abort). Only live entries become candidates.
+    pub fn rebuild<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        index: &NamespaceIndex,
+        held: &[BlockAddr],
+    ) -> Result<(), HelixError> {
+        self.by_crc.clear();
+        self.crc_of.clear();
+        self.refs.clear();
+        let mut cursor = index.cursor();
+        while let Some(e) = index.next_entry(block_io, &mut cursor)? {
+            let Some(ext) = extent_of(&e) else {
+                continue;
+            };
+            *self.refs.entry(ext.0).or_insert(0) += 1;
+            if e.content_crc64 != 0 && e.flags & entry_flags::IS_COMPRESSED == 0 {
+                self.register(e.content_crc64, ext);
+            }
+        }
+        for &root in held {
+            *self.refs.entry(root).or_insert(0) += 1;
+        }
+        self.refs.retain(|_, n| *n > 1);
+        Ok(())
+    }
+
+    /// Recount the shared blocks from the `(physical, count)` runs of every
+    /// distinct extent file that may share some.
+    pub fn rebuild_blocks(&mut self, runs: &[(BlockAddr, u64)]) {
+        self.blocks.clear();
+        for &(start, count) in runs {
+            self.share_blocks(start, count);
+        }
+        // Each block now counts all its holders; only those beyond one stay.
+        self.blocks.retain(|_, (_, n)| {
+            *n -= 1;
+            *n > 0
+        });
+    }
+
+    /// One more extent file holds `[start, start + count)`.
+    pub fn share_blocks(&mut self, start: BlockAddr, count: u64) {
+        let end = start + count;
+        let mut at = start;
+        for (s, e) in self.split_blocks(start, end) {
+            if at < s {
+                self.blocks.insert(at, (s, 1));
+            }
+            if let Some((_, n)) = self.blocks.get_mut(&s) {
+                *n += 1;
+            }
+            at = e;
+        }
+        if at < end {
+            self.blocks.insert(at, (end, 1));
+        }
+    }
+
+    /// One extent file lets go of `[start, start + count)`; the blocks no
+    /// other one holds are freed.
+    pub fn free_blocks(&mut self, bitmap: &mut BlockBitmap, start: BlockAddr, count: u64) {
+        let end = start + count;
+        let mut at = start;
+        for (s, e) in self.split_blocks(start, end) {
+            if at < s {
+                let _ = bitmap.free_range(at, s - at);
+            }
+            match self.blocks.get_mut(&s) {
+                Some((_, n)) if *n > 1 => *n -= 1,
+                _ => {
+                    self.blocks.remove(&s);
+                },
+            }
+            at = e;
+        }
+        if at < end {
+            let _ = bitmap.free_range(at, end - at);
+        }
+    }
+
+    /// Cut the shared ranges at `start` and `end`; returns those now inside.
+    fn split_blocks(&mut self, start: BlockAddr, end: BlockAddr) -> Vec<(BlockAddr, BlockAddr)> {
+        for at in [start, end] {
+            if let Some((&s, &(e, n))) = self.blocks.range(..at).next_back() {
+                if e > at {
+                    self.blocks.insert(s, (at, n));
+                    self.blocks.insert(at, (e, n));
+                }
+            }
+        }
+        self.blocks
+            .range(start..end)
+            .map(|(&s, &(e, _))| (s, e))
+            .collect()
+    }
+
+    /// Make a freshly written extent file a dedup candidate.
+    pub fn register(&mut self, content_crc64: u64, ext: ExtentRef) {
+        self.forget(ext.0);
+        self.by_crc.entry(content_crc64).or_default().push(ext);
+        self.crc_of.insert(ext.0, content_crc64);
+    }
+
+    /// Stop offering `extent_root` as a candidate: its content (or size) is
+    /// about to change, or it is gone.
+    pub fn forget(&mut self, extent_root: BlockAddr) {
+        let Some(crc) = self.crc_of.remove(&extent_root) else {
+            return;
+        };
+        if let Some(slot) = self.by_crc.get_mut(&crc) {
+            slot.retain(|c| c.0 != extent_root);
+            if slot.is_empty() {
+                self.by_crc.remove(&crc);
+            }
+        }
+    }
+
+    /// References to `extent_root` (1 when unshared).
+    pub fn ref_count(&self, extent_root: BlockAddr) -> u32 {
+        self.refs.get(&extent_root).copied().unwrap_or(1)
+    }
+
+    pub fn is_shared(&self, extent_root: BlockAddr) -> bool {
+        self.ref_count(extent_root) > 1
+    }
+
+    /// One more entry references `extent_root`; returns the new count.
+    pub fn add_ref(&mut self, extent_root: BlockAddr) -> u32 {
+        let n = self.ref_count(extent_root) + 1;
+        self.refs.insert(extent_root, n);
+        n
+    }
+
+    /// An existing extent file whose bytes equal `data`, if any.
+    pub fn find<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        partition_lba_start: u64,
+        data_start_block: u64,
+        device_block_size: u32,
+        content_crc64: u64,
+        data: &[u8],
+    ) -> Option<ExtentRef> {
+        self.by_crc
+            .get(&content_crc64)?
+            .iter()
+            .copied()
+            .find(|&(root, size, is_node)| {
+                size == data.len() as u64
+                    && content_matches(
+                        block_io,
+                        partition_lba_start,
+                        data_start_block,
+                        device_block_size,
+                        root,
+                        size,
+                        is_node,
+                        data,
+                    )
+            })
+    }
+
+    /// Drop one reference to an extent file; its blocks are freed with the last.
+    #[allow(clippy::too_many_arguments)]
+    pub fn release<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        bitmap: &mut BlockBitmap,
+        partition_lba_start: u64,
+        data_start_block: u64,
+        device_block_size: u32,
+        extent_root: BlockAddr,
+        size: u64,
+        is_node: bool,
+    ) {
+        if extent_root == BLOCK_NULL {
+            return;
+        }
+        match self.ref_count(extent_root) {
+            0 | 1 => {
+                self.refs.remove(&extent_root);
+                self.forget(extent_root);
+                if self.blocks.is_empty() {
+                    crate::extent::free_file_blocks(
+                        block_io,
+                        bitmap,
+                        partition_lba_start,
+                        data_start_block,
+                        device_block_size,
+                        extent_root,
+                        size,
+                        is_node,
+                    );
+                    return;
+                }
+                if let Ok(runs) = crate::extent::file_runs(
+                    block_io,
+                    partition_lba_start,
+                    data_start_block,
+                    device_block_size,
+                    extent_root,
+                    size,
+                    is_node,
+                ) {
+                    for (_, physical, count) in runs {
+                        self.free_blocks(bitmap, physical, count as u64);
+                    }
+                }
+                if is_node {
+                    let _ = bitmap.free_block(extent_root);
+                }
+            },
+            2 => {
+                self.refs.remove(&extent_root);
+            },
+            n => {
+                self.refs.insert(extent_root, n - 1);
+            },
+        }
+    }
+}
+
+/// Storage identity of a live extent-backed entry. A compressed entry reports
+/// its frame length: block accounting sees a contiguous run of that size.
+pub fn extent_of(e: &IndexEntry) -> Option<ExtentRef> {
+    let owns_blocks =
+        e.flags & (entry_flags::IS_INLINE | entry_flags::IS_DIR | entry_flags::IS_DELETED) == 0
+            && e.extent_root != BLOCK_NULL;
+    let size = if e.flags & entry_flags::IS_COMPRESSED != 0 {
+        e.stored_len
+    } else {
+        e.size
+    };
+    owns_blocks.then_some((
+        e.extent_root,
+        size,
+        e.flags & entry_flags::IS_EXTENT_NODE != 0,
+    ))
+}
+
+/// Block-by-block compare of an extent file against `data` (same length).
+#[allow(clippy::too_many_arguments)]
+fn content_matches<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    extent_root: BlockAddr,
+    size: u64,
+    is_node: bool,
+    data: &[u8],
+) -> bool {
+    let runs = match crate::extent::file_runs(
+        block_io,
+        partition_lba_start,
+        data_start_block,
+        device_block_size,
+        extent_root,
+        size,
+        is_node,
+    ) {
+        Ok(r) => r,
+        Err(_) => return false,
+    };
+    let scale = BLOCK_SIZE as u64 / device_block_size as u64;
+    let mut buf = vec![0u8; BLOCK_SIZE as usize];
+    for (i, chunk) in data.chunks(BLOCK_SIZE as usize).enumerate() {
+        let Some(physical) = crate::extent::map_logical(&runs, i as u64) else {
+            // A hole: the candidate reads zeros here.
+            if chunk.iter().any(|&b| b != 0) {
+                return false;
+            }
+            continue;
+        };
+        let lba = Lba(partition_lba_start + (data_start_block + physical) * scale);
+        if block_io.read_blocks(lba, &mut buf).is_err() || buf[..chunk.len()] != *chunk {
+            return false;
+        }
+    }
+    true
+}
+
+/// `DedupRef` payload: the extent Write payload plus the post-share refcount,
+/// `[path_len: u16][path][kind: u8][file_size: u64][extent_root: u64][refs: u32]`.
+/// The refcount is informational; replay recounts from the index.
+pub fn build_ref_payload(path: &str, ext: ExtentRef, refs: u32) -> Vec<u8> {
+    let path_b = path.as_bytes();
+    let mut p = Vec::with_capacity(2 + path_b.len() + 21);
+    p.extend_from_slice(&(path_b.len() as u16).to_le_bytes());
+    p.extend_from_slice(path_b);
+    p.push(if ext.2 {
+        extent_kind::NODE
+    } else {
+        extent_kind::CONTIGUOUS
+    });
+    p.extend_from_slice(&ext.1.to_le_bytes());
+    p.extend_from_slice(&ext.0.to_le_bytes());
+    p.extend_from_slice(&refs.to_le_bytes());
+    p
+}
@@ -4,13 +4,18 @@
 //! per-fd cursor state.
 
 use crate::bitmap::BlockBitmap;
+use crate::dedup::DedupTable;
 use crate::error::HelixError;
-use crate::index::btree::NamespaceIndex;
-use crate::log::recovery::{recover_superblock, replay_log, write_superblock};
+use crate::index::btree::{self, NamespaceIndex};
+use crate::log::feed::Change;
+use crate::log::recovery::{recover_superblock, replay_log_until, write_superblock};
 use crate::log::LogEngine;
 use crate::ops;
+use crate::ops::quota::{Limit, Usage};
 use crate::types::*;
 use crate::{crc, format};
+use alloc::collections::BTreeSet;
+use alloc::string::String;
 use alloc::vec::Vec;
 use gpt_disk_io::BlockIo;
 
@@ -26,8 +31,53 @@ pub struct HelixFs {
     /// LSNs of live snapshots, ascending; an overwritten version is retained
     /// while a snapshot in `[old_lsn, new_lsn)` still references it.
     pub snapshot_lsns: Vec<Lsn>,
+    /// Content index and shared-extent refcounts; recounted from the index.
+    pub dedup: DedupTable,
+    /// The on-disk snapshot table, oldest first; `snapshot_lsns` mirrors it.
+    pub(crate) snapshots: Vec<SnapshotEntry>,
+    /// Open transaction, if any; see `begin_tx`.
+    tx: Option<TxState>,
+    /// Snapshot LSN this instance is frozen at (`mount_snapshot`); every
+    /// mutation is refused with `ReadOnly`.
+    frozen: Option<Lsn>,
+    /// Background cleaner progress; see `clean_step`.
+    clean: crate::clean::CleanState,
+    /// Versions kept under a retention policy, oldest first; see
+    /// `ops::retain`.
+    pub(crate) history: Vec<VersionEntry>,
+    /// `history` has versions the history region does not hold yet.
+    pub(crate) history_dirty: bool,
 }
 
+/// Undo state for the open transaction. The index keeps its own journal and
+/// the bitmap defers frees and notes what it hands out.
+struct TxState {
+    begin_lsn: Lsn,
+    /// Versions retained inside the transaction are the ones past this.
+    history_len: usize,
+}
+
+/// Entries that are never versioned: their past is only whether they existed.
+const NOT_VERSIONED: u32 = entry_flags::IS_SYMLINK | entry_flags::IS_HARDLINK;
+
+/// Where `restore` finds the version of a file current at some LSN.
+enum Past {
+    /// The live entry is still that version.
+    Live,
+    /// A retained version; its blocks are shared, not copied.
+    Kept(VersionEntry),
+    /// A snapshot's entry; its blocks stay the snapshot's, so it is copied.
+    Snapshot(IndexEntry),
+    /// The file did not exist then.
+    Absent,
+    /// It existed, but nothing holds that version any more.
+    Gone,
+}
+
+/// The files a restore rewrites, with their past versions, and the
+/// directories it removes.
+type Plan = (Vec<(String, Past)>, Vec<String>);
+
 impl HelixFs {
     /// Assemble from an already-recovered superblock. Does not touch the device;
     /// the index is empty until `replay`. Prefer `mount` for the normal path.
@@ -47,6 +97,13 @@ impl HelixFs {
             partition_lba_start,
             device_block_size,
             snapshot_lsns: Vec::new(),
+            dedup: DedupTable::new(),
+            snapshots: Vec::new(),
+            tx: None,
+            frozen: None,
+            clean: Default::default(),
+            history: Vec::new(),
+            history_dirty: false,
         }
     }
 
@@ -68,6 +125,83 @@ impl HelixFs {
         block_io: &mut B,
         lba_start: u64,
         block_size: u32,
+    ) -> Result<Self, HelixError> {
+        let mut fs = Self::open_volume(block_io, lba_start, block_size)?;
+        fs.index = fs.load_namespace(block_io, Lsn::MAX)?;
+        fs.load_quotas(block_io)?;
+        fs.load_history(block_io)?;
+        fs.rebuild_bitmap_from_index(block_io)?;
+        fs.pin_snapshot_blocks(block_io)?;
+        fs.pin_history_blocks(block_io)?;
+        fs.recount_shares(block_io)?;
+        Ok(fs)
+    }
+
+    /// Mount the namespace exactly as it stood at the snapshot taken at `lsn`:
+    /// its own index region once a checkpoint has written one, else the
+    /// checkpointed index plus the log replayed up to the marker. The result is
+    /// read-only; it sees the blocks the live volume keeps pinned for that
+    /// snapshot. `NotFound` if no snapshot was taken at `lsn`.
+    pub fn mount_snapshot<B: BlockIo>(
+        block_io: &mut B,
+        lba_start: u64,
+        block_size: u32,
+        lsn: Lsn,
+    ) -> Result<Self, HelixError> {
+        let mut fs = Self::open_volume(block_io, lba_start, block_size)?;
+        let entry = *fs
+            .snapshots
+            .iter()
+            .find(|e| e.lsn == lsn)
+            .ok_or(HelixError::NotFound)?;
+        fs.index = fs.snapshot_namespace(block_io, &entry)?;
+        fs.rebuild_bitmap_from_index(block_io)?;
+        fs.dedup.rebuild(block_io, &fs.index, &[])?;
+        fs.frozen = Some(lsn);
+        Ok(fs)
+    }
+
+    /// Snapshot LSN of a `mount_snapshot` instance; `None` for a live mount.
+    pub fn frozen_at(&self) -> Option<Lsn> {
+        self.frozen
+    }
+
+    /// Every snapshot, oldest first.
+    pub fn list_snapshots(&self) -> Vec<ops::snapshot::SnapshotInfo> {
+        self.snapshots.iter().map(Into::into).collect()
+    }
+
+    /// LSN of the newest snapshot called `name`.
+    pub fn find_snapshot(&self, name: &str) -> Result<Lsn, HelixError> {
+        ops::snapshot::find(&self.snapshots, name)
+            .map(|i| self.snapshots[i].lsn)
+            .ok_or(HelixError::NotFound)
+    }
+
+    /// `find_snapshot` on an unmounted volume: reads the superblock and
+    /// snapshot table only, for resolving a name before `mount_snapshot`.
+    pub fn lookup_snapshot<B: BlockIo>(
+        block_io: &mut B,
+        lba_start: u64,
+        block_size: u32,
+        name: &str,
+    ) -> Result<Lsn, HelixError> {
+        Self::open_volume(block_io, lba_start, block_size)?.find_snapshot(name)
+    }
+
+    pub(crate) fn check_writable(&self) -> Result<(), HelixError> {
+        match self.frozen {
+            Some(_) => Err(HelixError::ReadOnly),
+            None => Ok(()),
+        }
+    }
+
+    /// Superblock recovery, log head reload and the snapshot table; index
+    /// still empty.
+    fn open_volume<B: BlockIo>(
+        block_io: &mut B,
+        lba_start: u64,
+        block_size: u32,
     ) -> Result<Self, HelixError> {
         let sb = recover_superblock(block_io, lba_start, block_size)?;
         if sb.version != HELIX_VERSION {
@@ -76,83 +210,433 @@ impl HelixFs {
         let mut fs = Self::from_superblock(sb, lba_start, block_size);
         // Reload head so flush() doesn't clobber existing records.
         fs.log.reload_head_segment(block_io)?;
-        // A checkpoint captures the namespace as of checkpoint_lsn; load it, then
-        // replay only the records logged after it.
-        if fs.sb.index_root_block != BLOCK_NULL {
-            crate::checkpoint::load_index_region(
+        let table = ops::snapshot::load_table(block_io, lba_start, block_size, &fs.sb)?;
+        fs.set_snapshots(table);
+        Ok(fs)
+    }
+
+    pub(crate) fn set_snapshots(&mut self, table: Vec<SnapshotEntry>) {
+        self.snapshot_lsns = table.iter().map(|e| e.lsn).collect();
+        self.snapshots = table;
+    }
+
+    /// The namespace as of `until_lsn`: the checkpoint plus the ring up to it.
+    fn load_namespace<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        until_lsn: Lsn,
+    ) -> Result<NamespaceIndex, HelixError> {
+        // A checkpoint captures the namespace as of checkpoint_lsn; open its
+        // tree, then replay only the records logged after it.
+        let mut index = if self.sb.index_root_block != BLOCK_NULL {
+            NamespaceIndex::with_base(self.open_index_region(
                 block_io,
-                fs.partition_lba_start,
-                fs.sb.data_start_block,
-                fs.device_block_size,
-                fs.sb.index_root_block,
-                fs.sb.index_depth as u64,
-                fs.sb.index_entry_count as u64,
-                &mut fs.index,
-            )?;
+                self.sb.index_root_block,
+                self.sb.index_region_blocks as u64,
+                self.sb.index_entry_count as u64,
+            )?)
+        } else {
+            NamespaceIndex::new()
+        };
+        // Markers in the ring are ignored: the snapshot table is authoritative.
+        let mut markers = Vec::new();
+        replay_log_until(
+            block_io,
+            &self.log,
+            &mut index,
+            &mut markers,
+            self.sb.checkpoint_lsn,
+            until_lsn,
+        )?;
+        Ok(index)
+    }
+
+    /// The namespace a snapshot sees.
+    pub(crate) fn snapshot_namespace<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        entry: &SnapshotEntry,
+    ) -> Result<NamespaceIndex, HelixError> {
+        if entry.index_root == BLOCK_NULL {
+            return self.load_namespace(block_io, entry.lsn);
         }
-        let mut snapshots = Vec::new();
-        replay_log(
+        let region = self.open_index_region(
             block_io,
-            &fs.log,
-            &mut fs.index,
-            &mut snapshots,
-            fs.sb.checkpoint_lsn,
+            entry.index_root,
+            entry.index_blocks as u64,
+            entry.index_entry_count as u64,
         )?;
-        fs.snapshot_lsns = snapshots;
-        fs.rebuild_bitmap_from_index(block_io);
-        Ok(fs)
+        Ok(NamespaceIndex::with_base(region))
     }
 
-    /// After replay the bitmap is zero; mark every extent-backed live file's
-    /// blocks (and its extent-node block) used or new allocations would overlap
-    /// existing data.
-    fn rebuild_bitmap_from_index<B: BlockIo>(&mut self, block_io: &mut B) {
-        // Snapshot the targets first so the index borrow doesn't overlap the
-        // bitmap/device access below.
-        let targets: Vec<(BlockAddr, u64, bool)> = self
-            .index
-            .all_entries()
+    fn open_index_region<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        start: BlockAddr,
+        blocks: u64,
+        entries: u64,
+    ) -> Result<crate::checkpoint::IndexRegion, HelixError> {
+        crate::checkpoint::open_index_region(
+            block_io,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            start,
+            blocks,
+            entries,
+        )
+    }
+
+    /// Keep every snapshot's blocks allocated across a remount: versions a
+    /// snapshot still sees are not in the live index, so the rebuild alone
+    /// would hand them out again. The table and index regions are marked too.
+    pub(crate) fn pin_snapshot_blocks<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+    ) -> Result<(), HelixError> {
+        for entry in self.snapshots.clone() {
+            let index = self.snapshot_namespace(block_io, &entry)?;
+            self.mark_index_blocks(block_io, &index)?;
+            if entry.index_root != BLOCK_NULL {
+                self.bitmap
+                    .mark_range_used(entry.index_root, entry.index_blocks as u64);
+            }
+        }
+        if self.sb.snapshot_table_block != BLOCK_NULL {
+            self.bitmap.mark_block_used(self.sb.snapshot_table_block);
+        }
+        Ok(())
+    }
+
+    /// The retained versions: the history region, then the `Retain` records
+    /// the ring holds past it.
+    pub(crate) fn load_history<B: BlockIo>(&mut self, block_io: &mut B) -> Result<(), HelixError> {
+        let mut history = ops::retain::load_region(
+            block_io,
+            self.partition_lba_start,
+            self.device_block_size,
+            &self.sb,
+        )?;
+        let logged = ops::retain::replay(block_io, &self.log, self.sb.history_lsn)?;
+        self.history_dirty = !logged.is_empty();
+        history.extend(logged);
+        self.history = history;
+        Ok(())
+    }
+
+    /// Adopt the quota region under the namespace just loaded, then the
+    /// quota attributes the ring changed past it.
+    pub(crate) fn load_quotas<B: BlockIo>(&mut self, block_io: &mut B) -> Result<(), HelixError> {
+        let quotas = ops::quota::load_region(
+            block_io,
+            self.partition_lba_start,
+            self.device_block_size,
+            &self.sb,
+        )?;
+        self.index.install_quotas(quotas);
+        self.reconcile_quotas(block_io)
+    }
+
+    /// Rebuild the quotas from the directories' attributes and count them
+    /// over the whole namespace, trusting nothing the region holds.
+    pub(crate) fn recount_quotas<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+    ) -> Result<(), HelixError> {
+        let mut limits = Vec::new();
+        let mut cursor = self.index.cursor();
+        while let Some(e) = self.index.next_entry(block_io, &mut cursor)? {
+            if e.flags & entry_flags::IS_DIR == 0 {
+                continue;
+            }
+            // Unreadable attributes are fsck's finding, not a quota.
+            if let Some(limit) = self.quota_limit(block_io, &e).unwrap_or(None) {
+                limits.push((String::from(btree::path_str(&e.path)), limit));
+            }
+        }
+        self.index.recount_quotas(block_io, limits)
+    }
+
+    /// Drop the quotas whose directory is gone and take up what the
+    /// attributes of the directories changed since the checkpoint say.
+    fn reconcile_quotas<B: BlockIo>(&mut self, block_io: &mut B) -> Result<(), HelixError> {
+        self.index.settle(block_io)?;
+        let dirs: Vec<String> = self.index.quotas().iter().map(|q| q.dir.clone()).collect();
+        for dir in dirs {
+            if self.index.lookup(block_io, &dir)?.is_none() {
+                self.index.drop_quota(&dir);
+            }
+        }
+        for e in self.index.changed_dirs() {
+            let dir = btree::path_str(&e.path);
+            match self.quota_limit(block_io, &e)? {
+                Some(limit) => self.index.set_quota(block_io, dir, limit)?,
+                None => self.index.drop_quota(dir),
+            }
+        }
+        Ok(())
+    }
+
+    /// The quota directory entry `e`'s attributes set, if any.
+    fn quota_limit<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        e: &IndexEntry,
+    ) -> Result<Option<Limit>, HelixError> {
+        if e.xattr_len == 0 {
+            return Ok(None);
+        }
+        let attrs = ops::xattr::load_block(
+            block_io,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            e.xattr_block,
+            e.xattr_len,
+        )?;
+        Ok(attrs
+            .get(ops::quota::POLICY_XATTR)
+            .and_then(|v| Limit::parse(v))
+            .filter(|l| !l.is_unlimited()))
+    }
+
+    /// Refuse a write that leaves `path` `new_size` bytes long past a quota.
+    fn check_growth<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        new_size: u64,
+    ) -> Result<(), HelixError> {
+        if self.index.quotas().is_empty() {
+            return Ok(());
+        }
+        let grow = match self.index.lookup(block_io, path)? {
+            Some(e) => Usage {
+                bytes: new_size.saturating_sub(Usage::of(&e).bytes),
+                entries: 0,
+            },
+            None => Usage {
+                bytes: new_size,
+                entries: 1,
+            },
+        };
+        self.index.check_quota(block_io, path, grow, None)
+    }
+
+    /// Refuse to create `path`, with `bytes` of content, past a quota.
+    fn check_create<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        bytes: u64,
+    ) -> Result<(), HelixError> {
+        if self.index.quotas().is_empty() || self.index.lookup_flex(block_io, path)?.is_some() {
+            return Ok(());
+        }
+        self.index
+            .check_quota(block_io, path, Usage { bytes, entries: 1 }, None)
+    }
+
+    /// Refuse a rename that takes a quota past its limit: what moves counts
+    /// against the quotas over `new_path` that were not already over it.
+    fn check_move<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<(), HelixError> {
+        if self.index.quotas().is_empty() {
+            return Ok(());
+        }
+        let Some(src) = self.index.lookup_flex(block_io, old_path)? else {
+            return Ok(());
+        };
+        let from = String::from(btree::path_str(&src.path));
+        let mut moved = Usage::of(&src);
+        let to = if src.flags & entry_flags::IS_DIR != 0 {
+            let to = ops::quota::dir_form(new_path);
+            let entering = self
+                .index
+                .quotas()
+                .iter()
+                .any(|q| q.covers(&to) && !q.covers(&from));
+            if entering {
+                moved.add(self.index.subtree_usage(block_io, &from)?);
+            }
+            to
+        } else {
+            String::from(new_path)
+        };
+        self.index.check_quota(block_io, &to, moved, Some(&from))
+    }
+
+    /// Keep the history region and every retained version allocated.
+    pub(crate) fn pin_history_blocks<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+    ) -> Result<(), HelixError> {
+        if self.sb.history_count != 0 && self.sb.history_block != BLOCK_NULL {
+            self.bitmap
+                .mark_range_used(self.sb.history_block, self.sb.history_blocks as u64);
+        }
+        for v in self.history.clone() {
+            self.mark_entry_blocks(block_io, &ops::retain::as_entry(&v));
+        }
+        Ok(())
+    }
+
+    /// Extent roots the retained versions hold, for the dedup refcounts.
+    pub(crate) fn history_roots(&self) -> Vec<BlockAddr> {
+        self.history
             .iter()
-            .filter(|e| {
-                e.flags & (entry_flags::IS_DELETED | entry_flags::IS_DIR | entry_flags::IS_INLINE)
-                    == 0
-                    && e.extent_root != BLOCK_NULL
-            })
-            .map(|e| {
-                (
-                    e.extent_root,
-                    e.size,
-                    e.flags & entry_flags::IS_EXTENT_NODE != 0,
-                )
-            })
-            .collect();
+            .filter_map(|v| crate::dedup::extent_of(&ops::retain::as_entry(v)))
+            .map(|ext| ext.0)
+            .collect()
+    }
 
-        for (extent_root, size, is_node) in targets {
-            if is_node {
-                self.bitmap.mark_block_used(extent_root);
-                if let Ok(extents) = crate::extent::read_extent_node(
-                    block_io,
-                    self.partition_lba_start,
-                    self.sb.data_start_block,
-                    self.device_block_size,
-                    extent_root,
-                ) {
-                    for (_, physical, count) in extents {
-                        self.bitmap.mark_range_used(physical, count as u64);
+    /// Extent roots the snapshots see, each once per snapshot. A name a
+    /// snapshot alone still shows is a holder the live index has lost, and
+    /// without it a later sharer's release would free the snapshot's data.
+    fn snapshot_roots<B: BlockIo>(&self, block_io: &mut B) -> Result<Vec<BlockAddr>, HelixError> {
+        let mut roots = Vec::new();
+        for entry in &self.snapshots {
+            let index = self.snapshot_namespace(block_io, entry)?;
+            let mut seen = BTreeSet::new();
+            let mut cursor = index.cursor();
+            while let Some(e) = index.next_entry(block_io, &mut cursor)? {
+                if let Some((root, ..)) = crate::dedup::extent_of(&e) {
+                    if seen.insert(root) {
+                        roots.push(root);
                     }
                 }
-            } else {
-                let blocks_needed = size.div_ceil(BLOCK_SIZE as u64);
-                if blocks_needed > 0 {
-                    self.bitmap.mark_range_used(extent_root, blocks_needed);
+            }
+        }
+        Ok(roots)
+    }
+
+    /// Recount what the live index, the retained versions and the snapshots
+    /// share: whole extent files, and the blocks range writes left to a
+    /// version and its successor. Only the files with history can share
+    /// blocks.
+    pub(crate) fn recount_shares<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+    ) -> Result<(), HelixError> {
+        let mut held = self.history_roots();
+        held.extend(self.snapshot_roots(block_io)?);
+        self.dedup.rebuild(block_io, &self.index, &held)?;
+        let mut holders: Vec<IndexEntry> = self.history.iter().map(ops::retain::as_entry).collect();
+        if !holders.is_empty() {
+            let files: BTreeSet<Lsn> = self.history.iter().map(|v| v.first_lsn).collect();
+            let mut cursor = self.index.cursor();
+            while let Some(e) = self.index.next_entry(block_io, &mut cursor)? {
+                if files.contains(&e.first_lsn) {
+                    holders.push(e);
                 }
             }
         }
+        let mut seen = BTreeSet::new();
+        let mut runs = Vec::new();
+        for (root, size, is_node) in holders.iter().filter_map(crate::dedup::extent_of) {
+            if !seen.insert(root) {
+                continue;
+            }
+            let file = crate::extent::file_runs(
+                block_io,
+                self.partition_lba_start,
+                self.sb.data_start_block,
+                self.device_block_size,
+                root,
+                size,
+                is_node,
+            )?;
+            runs.extend(file.into_iter().map(|(_, p, c)| (p, c as u64)));
+        }
+        self.dedup.rebuild_blocks(&runs);
+        Ok(())
+    }
+
+    /// Recompute the allocation map from what is still referenced: the live
+    /// index, the checkpoint, the retained versions and every remaining
+    /// snapshot — what a remount would find. Frees whatever only a deleted
+    /// snapshot was holding.
+    pub(crate) fn reclaim_unpinned<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+    ) -> Result<(), HelixError> {
+        self.bitmap = BlockBitmap::new(self.bitmap.total_blocks());
+        self.rebuild_bitmap_from_index(block_io)?;
+        self.pin_snapshot_blocks(block_io)?;
+        self.pin_history_blocks(block_io)?;
+        self.recount_shares(block_io)
+    }
+
+    /// After replay the bitmap is zero; mark every extent-backed live file's
+    /// blocks (and its extent-node block) used or new allocations would overlap
+    /// existing data.
+    pub(crate) fn rebuild_bitmap_from_index<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+    ) -> Result<(), HelixError> {
+        let index = core::mem::take(&mut self.index);
+        let marked = self.mark_index_blocks(block_io, &index);
+        self.index = index;
+        marked?;
 
         // The on-disk index checkpoint region is also live storage.
         if self.sb.index_root_block != BLOCK_NULL {
             self.bitmap
-                .mark_range_used(self.sb.index_root_block, self.sb.index_depth as u64);
+                .mark_range_used(self.sb.index_root_block, self.sb.index_region_blocks as u64);
+        }
+        // So is the quota region written with it.
+        if self.sb.quota_count != 0 && self.sb.quota_block != BLOCK_NULL {
+            self.bitmap
+                .mark_range_used(self.sb.quota_block, self.sb.quota_blocks as u64);
+        }
+        Ok(())
+    }
+
+    /// Mark every block the entries of `index` own: extent runs, extent-node
+    /// blocks and attribute blocks.
+    fn mark_index_blocks<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        index: &NamespaceIndex,
+    ) -> Result<(), HelixError> {
+        let mut cursor = index.cursor();
+        while let Some(e) = index.next_entry(block_io, &mut cursor)? {
+            self.mark_entry_blocks(block_io, &e);
+        }
+        Ok(())
+    }
+
+    /// Mark the blocks one entry owns.
+    fn mark_entry_blocks<B: BlockIo>(&mut self, block_io: &mut B, e: &IndexEntry) {
+        // Attribute blocks belong to files and directories alike.
+        if e.xattr_len != 0 {
+            self.bitmap.mark_block_used(e.xattr_block);
+        }
+        let Some((extent_root, size, is_node)) = crate::dedup::extent_of(e) else {
+            return;
+        };
+        if is_node {
+            self.bitmap.mark_block_used(extent_root);
+            if let Ok(extents) = crate::extent::read_extent_node(
+                block_io,
+                self.partition_lba_start,
+                self.sb.data_start_block,
+                self.device_block_size,
+                extent_root,
+            ) {
+                for (_, physical, count) in extents {
+                    self.bitmap.mark_range_used(physical, count as u64);
+                }
+            }
+        } else {
+            let blocks_needed = size.div_ceil(BLOCK_SIZE as u64);
+            if blocks_needed > 0 {
+                self.bitmap.mark_range_used(extent_root, blocks_needed);
+            }
         }
     }
 
@@ -165,7 +649,8 @@ impl HelixFs {
         flags: u32,
         timestamp_ns: u64,
     ) -> Result<u64, HelixError> {
-        let exists = self.index.lookup(path).is_some();
+        let path = &self.content_path(block_io, path)?;
+        let exists = self.index.lookup(block_io, path)?.is_some();
 
         if !exists {
             if flags & open_flags::O_CREATE != 0 {
@@ -177,7 +662,10 @@ impl HelixFs {
             self.write_empty(block_io, path, timestamp_ns)?;
         }
 
-        let idx_entry = self.index.lookup(path).ok_or(HelixError::NotFound)?;
+        let idx_entry = self
+            .index
+            .lookup(block_io, path)?
+            .ok_or(HelixError::NotFound)?;
         Ok(idx_entry.key)
     }
 
@@ -187,13 +675,38 @@ impl HelixFs {
         path: &str,
         timestamp_ns: u64,
     ) -> Result<(), HelixError> {
-        // Route through write() so O_TRUNC over an existing file reclaims it.
-        self.write(block_io, path, &[], timestamp_ns)
+        // Route through write_versioned() so O_TRUNC over an existing file
+        // reclaims it.
+        self.check_writable()?;
+        self.check_growth(block_io, path, 0)?;
+        self.write_versioned(block_io, path, &[], timestamp_ns, false)
+    }
+
+    /// Where `path`'s content lives: a hard-linked name resolves to its link
+    /// record. Records themselves are not reachable by path.
+    fn content_path<B: BlockIo>(&self, block_io: &mut B, path: &str) -> Result<String, HelixError> {
+        if ops::link::is_record(path) {
+            return Err(HelixError::PathInvalid);
+        }
+        ops::link::content_path(block_io, &self.index, path)
     }
 
     /// Full file contents. The caller applies its own fd offset/slicing.
     pub fn read<B: BlockIo>(&self, block_io: &mut B, path: &str) -> Result<Vec<u8>, HelixError> {
-        let idx_entry = self.index.lookup(path).ok_or(HelixError::NotFound)?;
+        let path = &self.content_path(block_io, path)?;
+        self.read_content(block_io, path)
+    }
+
+    /// `read` of a path already resolved by `content_path`.
+    fn read_content<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Vec<u8>, HelixError> {
+        let idx_entry = self
+            .index
+            .lookup(block_io, path)?
+            .ok_or(HelixError::NotFound)?;
         if idx_entry.flags & entry_flags::IS_INLINE != 0 {
             let size = idx_entry.size as usize;
             let mut v = alloc::vec![0u8; size];
@@ -211,34 +724,358 @@ impl HelixFs {
         }
     }
 
-    /// Overwrite `path` with `data` (log-structured: a new version is appended).
-    pub fn write<B: BlockIo>(
+    /// Copy up to `buf.len()` bytes of `path` from `offset`, reading only the
+    /// blocks the range covers. Returns the byte count (0 at or past EOF).
+    pub fn read_at<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, HelixError> {
+        let path = &self.content_path(block_io, path)?;
+        ops::read::read_file_range(
+            block_io,
+            &self.index,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+            offset,
+            buf,
+        )
+    }
+
+    /// Write `data` at `offset`, creating `path` if absent; a gap past EOF reads
+    /// as zeros. Only the touched blocks are rewritten and one extent delta
+    /// record is logged, so the cost scales with `data`, not the file.
+    pub fn write_at<B: BlockIo>(
         &mut self,
         block_io: &mut B,
         path: &str,
+        offset: u64,
         data: &[u8],
         timestamp_ns: u64,
-    ) -> Result<(), HelixError> {
-        // Capture the prior version's blocks before write_file replaces the entry.
-        let old = self.index.lookup(path).and_then(|e| {
-            let extent_backed = e.flags
-                & (entry_flags::IS_INLINE | entry_flags::IS_DIR | entry_flags::IS_DELETED)
-                == 0
-                && e.extent_root != BLOCK_NULL;
-            extent_backed.then_some((
-                e.extent_root,
-                e.size,
-                e.lsn,
-                e.flags & entry_flags::IS_EXTENT_NODE != 0,
-            ))
-        });
-
-        let new_lsn = self.write_file_checkpointing(block_io, path, data, timestamp_ns)?;
+    ) -> Result<usize, HelixError> {
+        let path = &self.content_path(block_io, path)?;
+        let cur_size = match self.index.lookup(block_io, path)? {
+            Some(e) if e.flags & entry_flags::IS_DIR != 0 => return Err(HelixError::IsADirectory),
+            Some(_) if data.is_empty() => return Ok(0),
+            Some(e) => e.size,
+            None => 0,
+        };
+        let end = offset
+            .checked_add(data.len() as u64)
+            .ok_or(HelixError::InvalidOffset)?;
+        self.write_range(
+            block_io,
+            path,
+            offset,
+            data,
+            cur_size.max(end),
+            timestamp_ns,
+        )?;
+        Ok(data.len())
+    }
 
-        // Reclaim the superseded version unless a snapshot still references it.
-        if let Some((extent_root, size, old_lsn, is_node)) = old {
+    /// Splice `data` at `offset` and set the size to `new_size`. Results that fit
+    /// inline are staged and rewritten whole; anything larger goes through the
+    /// copy-on-write range path and reclaims only the blocks it superseded.
+    fn write_range<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        offset: u64,
+        data: &[u8],
+        new_size: u64,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        self.check_growth(block_io, path, new_size)?;
+        if new_size <= INLINE_DATA_SIZE as u64 {
+            let mut buf = alloc::vec![0u8; new_size as usize];
+            match ops::read::read_file_range(
+                block_io,
+                &self.index,
+                self.partition_lba_start,
+                self.sb.data_start_block,
+                self.device_block_size,
+                path,
+                0,
+                &mut buf,
+            ) {
+                Ok(_) | Err(HelixError::NotFound) => {},
+                Err(e) => return Err(e),
+            }
+            let start = offset as usize;
+            buf[start..start + data.len()].copy_from_slice(data);
+            return self.write_versioned(block_io, path, &buf, timestamp_ns, false);
+        }
+
+        // A frame cannot take a block splice: it is re-encoded instead.
+        if let Some(e) = self
+            .index
+            .lookup(block_io, path)?
+            .filter(|e| e.flags & entry_flags::IS_COMPRESSED != 0)
+        {
+            return self.splice_compressed(
+                block_io,
+                path,
+                &e,
+                offset,
+                data,
+                new_size,
+                timestamp_ns,
+            );
+        }
+
+        // A pinned prior version must not share blocks with its successor, or a
+        // later reclaim of the successor would free the snapshot's data.
+        // Likewise a deduplicated one: its blocks belong to every sharer.
+        // A version kept as history does share the blocks the write leaves
+        // alone, each counted once per holder.
+        let next_lsn = self.log.next_lsn();
+        let old = self
+            .index
+            .lookup(block_io, path)?
+            .map(|e| (e.lsn, crate::dedup::extent_of(&e)));
+        let pinned = old.is_some_and(|(lsn, _)| self.snapshot_pins(lsn, next_lsn));
+        let shared = old
+            .and_then(|(_, ext)| ext)
+            .filter(|ext| self.dedup.is_shared(ext.0));
+        let prior = self.retained(block_io, path)?;
+        let relocate = pinned || shared.is_some();
+        if let Some((_, Some(ext))) = old {
+            // The range write changes the content behind this root.
+            self.dedup.forget(ext.0);
+        }
+
+        let (lsn, superseded, kept) = self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::write::write_file_range(
+                dev,
+                &mut s.log,
+                &mut s.index,
+                &mut s.bitmap,
+                s.partition_lba_start,
+                s.device_block_size,
+                s.sb.data_start_block,
+                path,
+                offset,
+                data,
+                new_size,
+                timestamp_ns,
+                relocate,
+                prior.is_some(),
+            )
+        })?;
+        self.note_created(block_io, path, lsn)?;
+
+        if let Some(prior) = prior {
+            for (physical, count) in kept {
+                self.dedup.share_blocks(physical, count);
+            }
+            return self.retain_version(block_io, &prior, lsn, timestamp_ns);
+        }
+        if pinned {
+            return Ok(());
+        }
+        match shared {
+            Some((extent_root, size, is_node)) => self.dedup.release(
+                block_io,
+                &mut self.bitmap,
+                self.partition_lba_start,
+                self.sb.data_start_block,
+                self.device_block_size,
+                extent_root,
+                size,
+                is_node,
+            ),
+            None => {
+                for (physical, count) in superseded {
+                    self.dedup.free_blocks(&mut self.bitmap, physical, count);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// `write_range` for the compressed file `e`: only the chunks the range
+    /// touches are decoded and compressed again, and the new frame replaces
+    /// the old as a whole version. A file of at most `compress::MAX_UNPACK_SIZE`
+    /// bytes is stored plain instead if the frame no longer saves a block or finds
+    /// no contiguous run; a larger one stays a frame, and fails `NoSpace`
+    /// without a run for it rather than unpack on the heap.
+    #[allow(clippy::too_many_arguments)]
+    fn splice_compressed<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        e: &IndexEntry,
+        offset: u64,
+        data: &[u8],
+        new_size: u64,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        // Past `u32::MAX` bytes a frame cannot index the file.
+        if new_size > u32::MAX as u64 {
+            return Err(HelixError::FileTooLarge);
+        }
+        let frame = ops::read::read_extent_data(
+            block_io,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            e.extent_root,
+            e.stored_len,
+        )?;
+        let old = crate::dedup::extent_of(e).map(|ext| (ext, e.lsn));
+        let prior = self.retained(block_io, path)?;
+        let spliced = crate::compress::splice(&frame, e.size, offset, data, new_size)?;
+        drop(frame);
+        let unpackable = new_size <= crate::compress::MAX_UNPACK_SIZE;
+        let saves = (spliced.len() as u64).div_ceil(BLOCK_SIZE as u64)
+            < new_size.div_ceil(BLOCK_SIZE as u64);
+        let written = if saves || !unpackable {
+            self.with_checkpoint_retry(block_io, |s, dev| {
+                ops::write::write_frame(
+                    dev,
+                    &mut s.log,
+                    &mut s.index,
+                    &mut s.bitmap,
+                    s.partition_lba_start,
+                    s.device_block_size,
+                    s.sb.data_start_block,
+                    path,
+                    new_size,
+                    &spliced,
+                    timestamp_ns,
+                )
+            })
+        } else {
+            Err(HelixError::NoSpace)
+        };
+        let lsn = match written {
+            Err(HelixError::NoSpace) if unpackable => {
+                let content = crate::compress::unpack(&spliced, new_size)?;
+                self.write_file_checkpointing(block_io, path, &content, timestamp_ns, false)?
+            },
+            other => other?,
+        };
+        self.note_created(block_io, path, lsn)?;
+        self.supersede(block_io, old, prior, lsn, timestamp_ns)
+    }
+
+    /// Overwrite `path` with `data` (log-structured: a new version is appended).
+    pub fn write<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        data: &[u8],
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        let compress = data.len() > INLINE_DATA_SIZE && self.compression_wanted(block_io, path)?;
+        let path = &self.content_path(block_io, path)?;
+        self.check_growth(block_io, path, data.len() as u64)?;
+        self.write_versioned(block_io, path, data, timestamp_ns, compress)
+    }
+
+    /// `write` with the compression policy already resolved.
+    fn write_versioned<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        data: &[u8],
+        timestamp_ns: u64,
+        compress: bool,
+    ) -> Result<(), HelixError> {
+        // Capture the prior version's blocks before write_file replaces the entry.
+        let old = self
+            .index
+            .lookup(block_io, path)?
+            .and_then(|e| crate::dedup::extent_of(&e).map(|ext| (ext, e.lsn)));
+        let prior = self.retained(block_io, path)?;
+
+        // Identical content already on disk: share it instead of copying.
+        let content_crc = crc::crc64(data);
+        let existing = if data.len() > INLINE_DATA_SIZE {
+            self.dedup.find(
+                block_io,
+                self.partition_lba_start,
+                self.sb.data_start_block,
+                self.device_block_size,
+                content_crc,
+                data,
+            )
+        } else {
+            None
+        };
+
+        let new_lsn = match existing {
+            Some(shared) => self.share_extent(block_io, path, shared, content_crc, timestamp_ns)?,
+            None => {
+                let lsn =
+                    self.write_file_checkpointing(block_io, path, data, timestamp_ns, compress)?;
+                // A frame is not plain bytes, so it is never offered for sharing.
+                if let Some(ext) = self
+                    .index
+                    .lookup(block_io, path)?
+                    .filter(|e| e.flags & entry_flags::IS_COMPRESSED == 0)
+                    .and_then(|e| crate::dedup::extent_of(&e))
+                {
+                    self.dedup.register(content_crc, ext);
+                }
+                lsn
+            },
+        };
+        self.note_created(block_io, path, new_lsn)?;
+        self.supersede(block_io, old, prior, new_lsn, timestamp_ns)
+    }
+
+    /// Point `path` at the existing extent file `shared` with one more
+    /// reference to it; returns the `DedupRef` LSN.
+    fn share_extent<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        shared: crate::dedup::ExtentRef,
+        content_crc: u64,
+        timestamp_ns: u64,
+    ) -> Result<Lsn, HelixError> {
+        let refs = self.dedup.ref_count(shared.0) + 1;
+        let lsn = self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::write::write_dedup_ref(
+                dev,
+                &mut s.log,
+                &mut s.index,
+                path,
+                shared,
+                content_crc,
+                refs,
+                timestamp_ns,
+            )
+        })?;
+        self.dedup.add_ref(shared.0);
+        Ok(lsn)
+    }
+
+    /// Dispose of the version the record at `new_lsn` replaced: keep `prior`
+    /// as history, or reclaim `old`'s blocks unless a snapshot still
+    /// references them.
+    fn supersede<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        old: Option<(crate::dedup::ExtentRef, Lsn)>,
+        prior: Option<IndexEntry>,
+        new_lsn: Lsn,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        if let Some(prior) = prior {
+            return self.retain_version(block_io, &prior, new_lsn, timestamp_ns);
+        }
+        if let Some(((extent_root, size, is_node), old_lsn)) = old {
             if !self.snapshot_pins(old_lsn, new_lsn) {
-                crate::extent::free_file_blocks(
+                self.dedup.release(
                     block_io,
                     &mut self.bitmap,
                     self.partition_lba_start,
@@ -261,12 +1098,123 @@ impl HelixFs {
             .any(|&s| s >= old_lsn && s < new_lsn)
     }
 
+    /// An entry about to be dropped outright (unlink, rename clobber) keeps its
+    /// blocks if any snapshot was taken since the file was created. Renames
+    /// move `lsn`, so `first_lsn` is the conservative bound.
+    fn entry_pinned<B: BlockIo>(&self, block_io: &mut B, path: &str) -> Result<bool, HelixError> {
+        Ok(self
+            .index
+            .lookup_flex(block_io, path)?
+            .is_some_and(|e| self.snapshot_pins(e.first_lsn, Lsn::MAX)))
+    }
+
+    /// Whether whole-file writes to `path` compress: the nearest
+    /// `helix.compression` attribute on the file itself or an ancestor
+    /// directory decides; none means no.
+    fn compression_wanted<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<bool, HelixError> {
+        Ok(self
+            .nearest_policy(block_io, path, crate::compress::POLICY_XATTR)?
+            .is_some_and(|policy| policy == crate::compress::POLICY_LZ4))
+    }
+
+    /// The retention policy governing `path`: the nearest `helix.retention`
+    /// attribute on the file itself or an ancestor directory. `None` if there
+    /// is none or the nearest does not parse; `path` then keeps no history.
+    pub fn retention<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Option<ops::retain::Retention>, HelixError> {
+        Ok(self
+            .nearest_policy(block_io, path, ops::retain::POLICY_XATTR)?
+            .and_then(|policy| ops::retain::Retention::parse(&policy)))
+    }
+
+    /// Value of attribute `name` on `path` or its nearest ancestor that has it.
+    fn nearest_policy<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+        name: &str,
+    ) -> Result<Option<Vec<u8>>, HelixError> {
+        let mut at = path;
+        loop {
+            if let Some(e) = self
+                .index
+                .lookup_flex(block_io, at)?
+                .filter(|e| e.xattr_len != 0)
+            {
+                let attrs = ops::xattr::load_block(
+                    block_io,
+                    self.partition_lba_start,
+                    self.sb.data_start_block,
+                    self.device_block_size,
+                    e.xattr_block,
+                    e.xattr_len,
+                )?;
+                if let Some(policy) = attrs.get(name) {
+                    return Ok(Some(policy.clone()));
+                }
+            }
+            if at == "/" {
+                return Ok(None);
+            }
+            at = btree::parent_path(at);
+        }
+    }
+
+    /// The entry at content path `path` if replacing it keeps it as a
+    /// version: a regular file under a policy that keeps any. Hard-linked
+    /// files are not versioned.
+    fn retained<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Option<IndexEntry>, HelixError> {
+        if ops::link::is_record(path) {
+            return Ok(None);
+        }
+        let Some(entry) = self.index.lookup(block_io, path)?.filter(|e| {
+            e.flags & (entry_flags::IS_DIR | entry_flags::IS_SYMLINK | entry_flags::IS_HARDLINK)
+                == 0
+        }) else {
+            return Ok(None);
+        };
+        Ok(self
+            .retention(block_io, path)?
+            .filter(|r| r.keeps_any())
+            .map(|_| entry))
+    }
+
+    /// Keep `old`, replaced by the record at `superseded_lsn`, as history.
+    /// Its blocks stay allocated; the caller skips the reclaim.
+    fn retain_version<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        old: &IndexEntry,
+        superseded_lsn: Lsn,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        let v = ops::retain::version_of(old, superseded_lsn, timestamp_ns);
+        self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::retain::log_retain(dev, &mut s.log, &v, timestamp_ns)
+        })?;
+        self.history.push(v);
+        self.history_dirty = true;
+        Ok(())
+    }
+
     fn write_file_inner<B: BlockIo>(
         &mut self,
         block_io: &mut B,
         path: &str,
         data: &[u8],
         timestamp_ns: u64,
+        compress: bool,
     ) -> Result<Lsn, HelixError> {
         ops::write::write_file(
             block_io,
@@ -279,6 +1227,7 @@ impl HelixFs {
             path,
             data,
             timestamp_ns,
+            compress,
         )
     }
 
@@ -290,11 +1239,12 @@ impl HelixFs {
         path: &str,
         data: &[u8],
         timestamp_ns: u64,
+        compress: bool,
     ) -> Result<Lsn, HelixError> {
-        match self.write_file_inner(block_io, path, data, timestamp_ns) {
-            Err(HelixError::LogFull) => {
-                self.checkpoint(block_io)?;
-                self.write_file_inner(block_io, path, data, timestamp_ns)
+        match self.write_file_inner(block_io, path, data, timestamp_ns, compress) {
+            Err(HelixError::LogFull) if self.tx.is_none() => {
+                self.checkpoint_dropping(block_io, Vec::new())?;
+                self.write_file_inner(block_io, path, data, timestamp_ns, compress)
             },
             other => other,
         }
@@ -302,7 +1252,9 @@ impl HelixFs {
 
     /// Run a mutation; on a full log, checkpoint to recycle the ring and retry
     /// once. Mutations roll back cleanly on `LogFull` (append is their first
-    /// fallible step), so the retry is safe.
+    /// fallible step), so the retry is safe. Inside a transaction the ring
+    /// cannot be recycled, so `LogFull` is returned as-is. The checkpoint
+    /// leaves expired versions be: the mutation may be about to share one.
     fn with_checkpoint_retry<B, F, T>(
         &mut self,
         block_io: &mut B,
@@ -313,8 +1265,8 @@ impl HelixFs {
         F: FnMut(&mut Self, &mut B) -> Result<T, HelixError>,
     {
         match op(self, block_io) {
-            Err(HelixError::LogFull) => {
-                self.checkpoint(block_io)?;
+            Err(HelixError::LogFull) if self.tx.is_none() => {
+                self.checkpoint_dropping(block_io, Vec::new())?;
                 op(self, block_io)
             },
             other => other,
@@ -324,38 +1276,61 @@ impl HelixFs {
     /// Persist the live namespace to the on-disk index region and recycle the
     /// log ring. Crash-safe: the new region is durable before the superblock
     /// that points at it, and the old region is freed only afterward.
+    /// Snapshots whose markers the ring still holds get their own region
+    /// first, so they outlive it. Retention policies are applied here: the
+    /// history region is rewritten without the versions they no longer keep,
+    /// and those are freed with the old region. Refused while a transaction
+    /// is open: the ring still holds its records.
     pub fn checkpoint<B: BlockIo>(&mut self, block_io: &mut B) -> Result<(), HelixError> {
-        let live: Vec<IndexEntry> = self
-            .index
-            .all_entries()
-            .iter()
-            .filter(|e| e.flags & entry_flags::IS_DELETED == 0)
-            .copied()
-            .collect();
-        let blocks_needed = crate::checkpoint::region_blocks(live.len());
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let expired = self.expired_versions(block_io)?;
+        self.checkpoint_dropping(block_io, expired)
+    }
 
-        let region_start = self.bitmap.alloc_contiguous(blocks_needed)?;
-        if let Err(e) = crate::checkpoint::write_index_region(
+    /// `checkpoint`, dropping the retained versions at positions `drop`.
+    fn checkpoint_dropping<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        drop: Vec<usize>,
+    ) -> Result<(), HelixError> {
+        let old_table = match self.materialize_snapshots(block_io)? {
+            Some(table) => self.store_snapshot_table(block_io, &table)?,
+            None => None,
+        };
+        // The ring is about to lose every `Retain` record.
+        let (old_history, dropped) = if self.history_dirty || !drop.is_empty() {
+            let (keep, dropped) = self.split_history(drop);
+            (self.store_history(block_io, keep)?, dropped)
+        } else {
+            (None, Vec::new())
+        };
+        // The figures as of the tree about to be written.
+        self.index.settle(block_io)?;
+        let old_quotas = ops::quota::store_region(
             block_io,
+            &mut self.bitmap,
             self.partition_lba_start,
-            self.sb.data_start_block,
             self.device_block_size,
-            region_start,
-            &live,
-        ) {
-            let _ = self.bitmap.free_range(region_start, blocks_needed);
-            return Err(e);
-        }
+            &mut self.sb,
+            self.index.quotas(),
+        )?;
+        let index = core::mem::take(&mut self.index);
+        let written = self.write_index_tree(block_io, &index);
+        self.index = index;
+        let (region_start, blocks, entries) = written?;
 
         let old_root = self.sb.index_root_block;
-        let old_blocks = self.sb.index_depth as u64;
+        let old_blocks = self.sb.index_region_blocks as u64;
 
         let checkpoint_lsn = self.log.next_lsn().saturating_sub(1);
         self.log.reset_ring();
 
         self.sb.index_root_block = region_start;
-        self.sb.index_depth = blocks_needed as u32;
-        self.sb.index_entry_count = live.len() as u32;
+        self.sb.index_region_blocks = blocks as u32;
+        self.sb.index_entry_count = entries as u32;
         self.sb.checkpoint_lsn = checkpoint_lsn;
         self.sb.committed_lsn = self.log.flush(block_io)?;
         self.sb.log_head_segment = self.log.head_segment();
@@ -378,63 +1353,646 @@ impl HelixFs {
         )?;
         block_io.flush().map_err(|_| HelixError::IoFlushFailed)?;
 
+        // A one-segment ring has nowhere to advance to, so recycling it means
+        // starting its only segment over. Only now that no superblock needs
+        // the records in it; whatever is left past the new head is at or
+        // below `checkpoint_lsn`, which replay skips.
+        if self.log.segment_count() == 1 {
+            self.log.clear_head_segment();
+            self.sb.log_head_offset = self.log.head_offset();
+            for slot in 0..2 {
+                write_superblock(
+                    block_io,
+                    self.partition_lba_start,
+                    self.device_block_size,
+                    &mut self.sb,
+                    slot,
+                )?;
+            }
+            block_io.flush().map_err(|_| HelixError::IoFlushFailed)?;
+        }
+
+        // Everything the overlay held is in the new tree now.
+        let region = self.open_index_region(block_io, region_start, blocks, entries)?;
+        self.index.rebase(region);
         if old_root != BLOCK_NULL {
             let _ = self.bitmap.free_range(old_root, old_blocks);
         }
+        if let Some(block) = old_table {
+            let _ = self.bitmap.free_block(block);
+        }
+        if let Some((start, blocks)) = old_quotas {
+            let _ = self.bitmap.free_range(start, blocks);
+        }
+        self.free_versions(block_io, old_history, &dropped);
         Ok(())
     }
 
-    pub fn stat(&self, path: &str) -> Result<FileStat, HelixError> {
-        ops::read::stat_file(&self.index, path)
+    /// Positions in the history of the versions their policies no longer
+    /// keep. Versions no policy governs any more are kept until pruned.
+    fn expired_versions<B: BlockIo>(&self, block_io: &mut B) -> Result<Vec<usize>, HelixError> {
+        let now_ns = self.log.newest_ns();
+        let mut paths: Vec<&str> = self.history.iter().map(ops::retain::path_of).collect();
+        paths.sort_unstable();
+        paths.dedup();
+        let mut expired = Vec::new();
+        for path in paths {
+            if let Some(policy) = self.retention(block_io, path)? {
+                expired.extend(ops::retain::expired(&self.history, path, policy, now_ns));
+            }
+        }
+        Ok(expired)
     }
 
-    pub fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, HelixError> {
-        ops::dir::readdir(&self.index, path)
+    /// Split the history into what stays and the versions at `drop`.
+    fn split_history(&self, mut drop: Vec<usize>) -> (Vec<VersionEntry>, Vec<VersionEntry>) {
+        drop.sort_unstable();
+        let (mut keep, mut dropped) = (Vec::new(), Vec::new());
+        for (i, v) in self.history.iter().enumerate() {
+            if drop.binary_search(&i).is_ok() {
+                dropped.push(*v);
+            } else {
+                keep.push(*v);
+            }
+        }
+        (keep, dropped)
     }
 
-    pub fn mkdir<B: BlockIo>(
+    /// Write `history` as the new history region, covering every `Retain`
+    /// record logged so far, and adopt it; the superblock is updated in
+    /// memory only. Returns the superseded region to free once the
+    /// superblock is durable.
+    fn store_history<B: BlockIo>(
         &mut self,
         block_io: &mut B,
-        path: &str,
-        timestamp_ns: u64,
-    ) -> Result<(), HelixError> {
-        self.with_checkpoint_retry(block_io, |s, dev| {
-            ops::dir::mkdir(dev, &mut s.log, &mut s.index, path, timestamp_ns).map(|_| ())
-        })
+        history: Vec<VersionEntry>,
+    ) -> Result<Option<(BlockAddr, u64)>, HelixError> {
+        let old = ops::retain::store_region(
+            block_io,
+            &mut self.bitmap,
+            self.partition_lba_start,
+            self.device_block_size,
+            &mut self.sb,
+            &history,
+        )?;
+        self.sb.history_lsn = self.log.next_lsn().saturating_sub(1);
+        self.history = history;
+        self.history_dirty = false;
+        Ok(old)
     }
 
-    /// File or empty directory.
-    pub fn unlink<B: BlockIo>(
+    /// Free a superseded history region and the versions dropped with it,
+    /// except what a snapshot or another sharer still holds.
+    fn free_versions<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        old_region: Option<(BlockAddr, u64)>,
+        dropped: &[VersionEntry],
+    ) {
+        if let Some((start, blocks)) = old_region {
+            let _ = self.bitmap.free_range(start, blocks);
+        }
+        for v in dropped {
+            if self.snapshot_pins(v.first_lsn, v.superseded_lsn) {
+                continue;
+            }
+            if let Some((extent_root, size, is_node)) =
+                crate::dedup::extent_of(&ops::retain::as_entry(v))
+            {
+                self.dedup.release(
+                    block_io,
+                    &mut self.bitmap,
+                    self.partition_lba_start,
+                    self.sb.data_start_block,
+                    self.device_block_size,
+                    extent_root,
+                    size,
+                    is_node,
+                );
+            }
+        }
+    }
+
+    /// Drop at once the retained versions of `path` — of every file under it,
+    /// for a directory, deleted files included — that `policy` would not keep
+    /// at `now_ns`. Returns how many were dropped. The shorter history is
+    /// durable before their blocks are freed.
+    pub fn prune_versions<B: BlockIo>(
         &mut self,
         block_io: &mut B,
         path: &str,
-        timestamp_ns: u64,
-    ) -> Result<(), HelixError> {
-        self.with_checkpoint_retry(block_io, |s, dev| {
-            ops::dir::unlink(
-                dev,
-                &mut s.log,
-                &mut s.index,
-                &mut s.bitmap,
-                s.partition_lba_start,
-                s.sb.data_start_block,
-                s.device_block_size,
-                path,
-                timestamp_ns,
-            )
-            .map(|_| ())
-        })
+        policy: ops::retain::Retention,
+        now_ns: u64,
+    ) -> Result<u64, HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let path = &self.content_path(block_io, path)?;
+        let dir = path.trim_end_matches('/');
+        let mut paths: Vec<&str> = self
+            .history
+            .iter()
+            .map(ops::retain::path_of)
+            .filter(|p| *p == path || p.strip_prefix(dir).is_some_and(|r| r.starts_with('/')))
+            .collect();
+        paths.sort_unstable();
+        paths.dedup();
+        let drop: Vec<usize> = paths
+            .into_iter()
+            .flat_map(|p| ops::retain::expired(&self.history, p, policy, now_ns))
+            .collect();
+        if drop.is_empty() {
+            return Ok(0);
+        }
+        let (keep, dropped) = self.split_history(drop);
+        let old_region = self.store_history(block_io, keep)?;
+        self.sync(block_io)?;
+        self.free_versions(block_io, old_region, &dropped);
+        Ok(dropped.len() as u64)
     }
 
-    pub fn rename<B: BlockIo>(
+    /// One increment of background cleaning: recycle the log ring if it is
+    /// worth it and `budget` covers the checkpoint, then carry the
+    /// defragmentation pass on until about `budget` blocks of I/O are spent,
+    /// the index leaves the scan reads included. Past the checkpoint, at least
+    /// one unit of work is done per call, however large. Moves are made durable before the step returns, so the
+    /// blocks they free cannot be reused ahead of their `Relocate` records.
+    pub fn clean_step<B: BlockIo>(
         &mut self,
         block_io: &mut B,
-        old_path: &str,
+        budget: u64,
+        timestamp_ns: u64,
+    ) -> Result<crate::clean::CleanStep, HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let mut spent = 0;
+        let mut checkpointed = false;
+        if crate::clean::log_wants_checkpoint(&self.log, &self.index, self.sb.checkpoint_lsn) {
+            // Too big for one step: a full ring checkpoints on its own.
+            let cost = crate::clean::checkpoint_blocks(
+                self.sb.index_region_blocks as u64,
+                self.sb.index_entry_count as u64,
+                &self.index,
+            );
+            if cost <= budget {
+                self.checkpoint(block_io)?;
+                spent += cost;
+                self.clean.stats.checkpoints += 1;
+                checkpointed = true;
+            }
+        }
+
+        let mut cursor = match &self.clean.resume {
+            Some(path) => self.index.cursor_after(path),
+            None => self.index.cursor(),
+        };
+        let mut moved = 0;
+        let mut scanned = 0u64;
+        let mut pass_done = false;
+        while spent == 0 || spent < budget {
+            let next = self.index.next_entry(block_io, &mut cursor)?;
+            spent += cursor.take_leaf_reads();
+            let Some(e) = next else {
+                pass_done = true;
+                break;
+            };
+            // A leaf's worth of entries costs a block even when the overlay
+            // holds them, so a walk over memory alone is bounded too.
+            scanned += 1;
+            if scanned % LEAF_ENTRIES_PER_BLOCK as u64 == 0 {
+                spent += 1;
+            }
+            let path = String::from(btree::path_str(&e.path));
+            self.clean.stats.files_scanned += 1;
+            self.clean.resume = Some(path.clone());
+            // Whatever a snapshot may see stays where it is; renames move
+            // `lsn`, so `first_lsn` is the bound, as in `entry_pinned`.
+            if !crate::clean::is_fragmented(&e)
+                || self.dedup.is_shared(e.extent_root)
+                || self.snapshot_pins(e.first_lsn, Lsn::MAX)
+            {
+                continue;
+            }
+            let runs = crate::extent::read_extent_node(
+                block_io,
+                self.partition_lba_start,
+                self.sb.data_start_block,
+                self.device_block_size,
+                e.extent_root,
+            )?;
+            spent += 1;
+            let Some(root) = self.with_checkpoint_retry(block_io, |s, dev| {
+                crate::clean::relocate(
+                    dev,
+                    &mut s.log,
+                    &mut s.index,
+                    &mut s.bitmap,
+                    s.partition_lba_start,
+                    s.sb.data_start_block,
+                    s.device_block_size,
+                    &e,
+                    &runs,
+                    false,
+                    timestamp_ns,
+                )
+            })?
+            else {
+                continue;
+            };
+            // Blocks a retained version shares stay with it.
+            self.dedup.release(
+                block_io,
+                &mut self.bitmap,
+                self.partition_lba_start,
+                self.sb.data_start_block,
+                self.device_block_size,
+                e.extent_root,
+                e.size,
+                true,
+            );
+            if e.content_crc64 != 0 {
+                self.dedup.register(e.content_crc64, (root, e.size, false));
+            }
+
+            let blocks = e.size.div_ceil(BLOCK_SIZE as u64);
+            spent += 2 * blocks;
+            moved += 1;
+            self.clean.stats.files_defragmented += 1;
+            self.clean.stats.blocks_moved += blocks;
+            // A retry may have checkpointed and rebased the tree under the cursor.
+            cursor = self.index.cursor_after(&path);
+        }
+
+        if moved > 0 {
+            self.sync(block_io)?;
+        }
+        self.clean.moved_this_pass += moved;
+        let idle = pass_done && self.clean.moved_this_pass == 0 && !checkpointed;
+        if pass_done {
+            self.clean.resume = None;
+            self.clean.moved_this_pass = 0;
+            self.clean.stats.passes += 1;
+        }
+        self.clean.stats.io_blocks += spent;
+        Ok(crate::clean::CleanStep {
+            io_blocks: spent,
+            idle,
+        })
+    }
+
+    /// Cleaner progress since mount.
+    pub fn clean_stats(&self) -> crate::clean::CleanStats {
+        self.clean.stats
+    }
+
+    /// Free and pinned space, log and index fill, snapshots and retained
+    /// versions. Telling pinned blocks apart walks the namespace, marking
+    /// what it holds into a scratch map, so this reads every extent node.
+    pub fn usage<B: BlockIo>(&mut self, block_io: &mut B) -> Result<VolumeUsage, HelixError> {
+        let scratch = BlockBitmap::new(self.bitmap.total_blocks());
+        let allocated = core::mem::replace(&mut self.bitmap, scratch);
+        let marked = self.rebuild_bitmap_from_index(block_io);
+        let referenced = core::mem::replace(&mut self.bitmap, allocated);
+        marked?;
+        Ok(VolumeUsage {
+            total_blocks: self.bitmap.total_blocks(),
+            free_blocks: self.bitmap.free_count(),
+            pinned_blocks: self.bitmap.allocated_outside(&referenced),
+            log_used_pct: self.log.log_utilization_pct(),
+            snapshots: self.snapshots.len() as u32,
+            live_entries: self.index.live_count(block_io)?,
+            stored_entries: self.index.stored_len(),
+            versions: self.history.len() as u64,
+        })
+    }
+
+    /// The quota on directory `path` and what its subtree holds now.
+    pub fn quota<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Option<(Limit, Usage)>, HelixError> {
+        self.index.settle(block_io)?;
+        let dir = ops::quota::dir_form(path);
+        Ok(self
+            .index
+            .quotas()
+            .iter()
+            .find(|q| q.dir == dir)
+            .map(|q| (q.limit, q.used)))
+    }
+
+    /// Grow or shrink the volume to `new_total_blocks` blocks; when growing,
+    /// the device must already cover the new size. A shrink first moves every
+    /// file out of the cut tail, each into one contiguous run, and
+    /// checkpoints so the index region lies below it too: `NoSpace` if the
+    /// files do not fit, `InUse` if a snapshot or a retained version still
+    /// holds blocks there.
+    /// Files moved before a failure stay moved. The new geometry lands in one
+    /// superblock update, and either slot describes a consistent volume: the
+    /// tail is empty before a shrink is recorded and nothing uses a grown one
+    /// until after. `NotSupported` on an encrypted volume, whose tag region
+    /// is sized at format.
+    pub fn resize<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        new_total_blocks: u64,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        if self.sb.crypt.tag_blocks != 0 {
+            return Err(HelixError::NotSupported);
+        }
+        if new_total_blocks <= self.sb.data_start_block {
+            return Err(HelixError::FormatTooSmall);
+        }
+        let data_blocks = new_total_blocks - self.sb.data_start_block;
+        match new_total_blocks.cmp(&self.sb.total_blocks) {
+            core::cmp::Ordering::Equal => return Ok(()),
+            core::cmp::Ordering::Greater => {
+                // Refuse a size the device does not have before recording it;
+                // one past the last addressable sector it cannot have either.
+                let scale = (BLOCK_SIZE / self.device_block_size) as u64;
+                let last = (new_total_blocks - 1)
+                    .checked_mul(scale)
+                    .and_then(|off| self.partition_lba_start.checked_add(off))
+                    .ok_or(HelixError::NoSpace)?;
+                let mut buf = alloc::vec![0u8; BLOCK_SIZE as usize];
+                block_io
+                    .read_blocks(gpt_disk_types::Lba(last), &mut buf)
+                    .map_err(|_| HelixError::IoReadFailed)?;
+            },
+            core::cmp::Ordering::Less => self.evacuate_tail(block_io, data_blocks, timestamp_ns)?,
+        }
+        self.bitmap.resize(data_blocks)?;
+        self.sb.total_blocks = new_total_blocks;
+        self.sb.data_block_count = data_blocks;
+        self.sync(block_io)
+    }
+
+    /// Empty data blocks `keep..` ahead of a shrink; see `resize`.
+    fn evacuate_tail<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        keep: u64,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        if self.bitmap.allocated_count() > keep {
+            return Err(HelixError::NoSpace);
+        }
+        self.bitmap.set_alloc_limit(keep);
+        let moved = self.move_out_of_tail(block_io, keep, timestamp_ns);
+        // What the moves left behind is reclaimed only once they are durable.
+        let settled = self
+            .sync(block_io)
+            .and_then(|()| self.reclaim_unpinned(block_io));
+        // The rebuild dropped the limit; the index region must honour it too.
+        self.bitmap.set_alloc_limit(keep);
+        let region_end = self
+            .sb
+            .index_root_block
+            .saturating_add(self.sb.index_region_blocks as u64);
+        // A checkpoint rewrites the quota region too.
+        let quota_end = self
+            .sb
+            .quota_block
+            .saturating_add(self.sb.quota_blocks as u64);
+        let rewrite = (self.sb.index_root_block != BLOCK_NULL && region_end > keep)
+            || (self.sb.quota_count != 0 && quota_end > keep);
+        let done = match (moved, settled) {
+            (Ok(()), Ok(())) if rewrite => self.checkpoint(block_io),
+            (Ok(()), Ok(())) => Ok(()),
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        };
+        self.bitmap.set_alloc_limit(self.bitmap.total_blocks());
+        done?;
+        match self.bitmap.first_allocated_from(keep) {
+            Some(_) => Err(HelixError::InUse),
+            None => Ok(()),
+        }
+    }
+
+    /// Relocate every unpinned file and attribute block with anything at or
+    /// past data block `keep`. Names sharing an extent follow the first one
+    /// to move instead of copying it again.
+    fn move_out_of_tail<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        keep: u64,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        let mut moved = alloc::collections::BTreeMap::new();
+        let mut cursor = self.index.cursor();
+        while let Some(e) = self.index.next_entry(block_io, &mut cursor)? {
+            // Whatever a snapshot may see stays; `evacuate_tail` reports it.
+            if self.snapshot_pins(e.first_lsn, Lsn::MAX) {
+                continue;
+            }
+            let path = String::from(btree::path_str(&e.path));
+            let mut changed = false;
+            if let Some((root, size, is_node)) = crate::dedup::extent_of(&e) {
+                let runs = crate::extent::file_runs(
+                    block_io,
+                    self.partition_lba_start,
+                    self.sb.data_start_block,
+                    self.device_block_size,
+                    root,
+                    size,
+                    is_node,
+                )?;
+                if (is_node && root >= keep) || runs.iter().any(|r| r.1 + r.2 as u64 > keep) {
+                    let to = match moved.get(&root) {
+                        Some(&to) => {
+                            self.with_checkpoint_retry(block_io, |s, dev| {
+                                crate::clean::log_relocate(
+                                    dev,
+                                    &mut s.log,
+                                    &mut s.index,
+                                    &e,
+                                    to,
+                                    timestamp_ns,
+                                )
+                            })?;
+                            to
+                        },
+                        None => self
+                            .with_checkpoint_retry(block_io, |s, dev| {
+                                crate::clean::relocate(
+                                    dev,
+                                    &mut s.log,
+                                    &mut s.index,
+                                    &mut s.bitmap,
+                                    s.partition_lba_start,
+                                    s.sb.data_start_block,
+                                    s.device_block_size,
+                                    &e,
+                                    &runs,
+                                    true,
+                                    timestamp_ns,
+                                )
+                            })?
+                            .ok_or(HelixError::NoSpace)?,
+                    };
+                    moved.insert(root, to);
+                    changed = true;
+                }
+            }
+            if e.xattr_len != 0 && e.xattr_block >= keep {
+                let attrs = ops::xattr::load_block(
+                    block_io,
+                    self.partition_lba_start,
+                    self.sb.data_start_block,
+                    self.device_block_size,
+                    e.xattr_block,
+                    e.xattr_len,
+                )?;
+                self.store_xattrs(block_io, &path, &attrs, timestamp_ns)?;
+                changed = true;
+            }
+            if changed {
+                // A checkpoint retry may have rebased the tree under the cursor.
+                cursor = self.index.cursor_after(&path);
+            }
+        }
+        // The history region moves whole; the versions it lists stay put.
+        let history_end = self
+            .sb
+            .history_block
+            .saturating_add(self.sb.history_blocks as u64);
+        if self.sb.history_count != 0 && history_end > keep {
+            let history = self.history.clone();
+            self.store_history(block_io, history)?;
+        }
+        Ok(())
+    }
+
+    pub fn stat<B: BlockIo>(&self, block_io: &mut B, path: &str) -> Result<FileStat, HelixError> {
+        if ops::link::is_record(path) {
+            return Err(HelixError::PathInvalid);
+        }
+        ops::read::stat_file(block_io, &self.index, path)
+    }
+
+    pub fn readdir<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Vec<DirEntry>, HelixError> {
+        ops::dir::readdir(block_io, &self.index, path)
+    }
+
+    pub fn mkdir<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        self.check_create(block_io, path, 0)?;
+        self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::dir::mkdir(dev, &mut s.log, &mut s.index, path, timestamp_ns).map(|_| ())
+        })
+    }
+
+    /// File or empty directory.
+    pub fn unlink<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        let content = self.content_path(block_io, path)?;
+        let pinned = self.entry_pinned(block_io, &content)?;
+        let prior = self.retained(block_io, &content)?;
+        let reclaim = !pinned && prior.is_none();
+        let lsn = self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::dir::unlink(
+                dev,
+                &mut s.log,
+                &mut s.index,
+                &mut s.bitmap,
+                &mut s.dedup,
+                s.partition_lba_start,
+                s.sb.data_start_block,
+                s.device_block_size,
+                path,
+                reclaim,
+                timestamp_ns,
+            )
+        })?;
+        self.index.drop_quota(&ops::quota::dir_form(path));
+        // The last version keeps its content; the attributes go.
+        if let Some(prior) = prior {
+            if prior.xattr_len != 0 && !pinned {
+                let _ = self.bitmap.free_block(prior.xattr_block);
+            }
+            self.retain_version(block_io, &prior, lsn, timestamp_ns)?;
+        }
+        Ok(())
+    }
+
+    pub fn rename<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        old_path: &str,
         new_path: &str,
         timestamp_ns: u64,
     ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        if ops::link::is_record(old_path) {
+            return Err(HelixError::PathInvalid);
+        }
+        let dest = self.content_path(block_io, new_path)?;
+        let reclaim_dest = !self.entry_pinned(block_io, &dest)?;
+        self.check_move(block_io, old_path, new_path)?;
+        let held = self.index.quotas().to_vec();
         self.with_checkpoint_retry(block_io, |s, dev| {
             ops::write::rename(
+                dev,
+                &mut s.log,
+                &mut s.index,
+                &mut s.bitmap,
+                &mut s.dedup,
+                s.partition_lba_start,
+                s.sb.data_start_block,
+                s.device_block_size,
+                old_path,
+                new_path,
+                reclaim_dest,
+                timestamp_ns,
+            )
+            .map(|_| ())
+        })?;
+        if !held.is_empty() {
+            self.index.move_quotas(
+                &ops::quota::dir_form(old_path),
+                &ops::quota::dir_form(new_path),
+                &held,
+            );
+        }
+        Ok(())
+    }
+
+    /// Create `path` as a symbolic link to `target`. The target is stored
+    /// verbatim and need not exist; following it is the caller's job.
+    pub fn symlink<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        target: &str,
+        path: &str,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        self.check_create(block_io, path, target.len() as u64)?;
+        self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::link::symlink(
                 dev,
                 &mut s.log,
                 &mut s.index,
@@ -442,6 +2000,48 @@ impl HelixFs {
                 s.partition_lba_start,
                 s.sb.data_start_block,
                 s.device_block_size,
+                target,
+                path,
+                timestamp_ns,
+            )
+            .map(|_| ())
+        })
+    }
+
+    /// Target of the symbolic link `path`.
+    pub fn readlink<B: BlockIo>(&self, block_io: &mut B, path: &str) -> Result<String, HelixError> {
+        if ops::link::is_record(path) {
+            return Err(HelixError::PathInvalid);
+        }
+        ops::link::readlink(
+            block_io,
+            &self.index,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+        )
+    }
+
+    /// Hard-link `new_path` to the file at `old_path`. Both names then share
+    /// content, attributes and versions until one is unlinked.
+    pub fn link<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        old_path: &str,
+        new_path: &str,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        if ops::link::is_record(old_path) {
+            return Err(HelixError::PathInvalid);
+        }
+        self.check_create(block_io, new_path, 0)?;
+        self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::link::link(
+                dev,
+                &mut s.log,
+                &mut s.index,
                 old_path,
                 new_path,
                 timestamp_ns,
@@ -450,76 +2050,606 @@ impl HelixFs {
         })
     }
 
-    /// Resize `path` via log-structured RMW. Grows capped at `MAX_TRUNCATE_GROW`
-    /// to prevent OOM on the small kernel heap.
-    pub fn truncate<B: BlockIo>(
-        &mut self,
+    /// Resize `path`. Shrinking frees the blocks past the new EOF; growing
+    /// leaves a hole that reads as zeros. Neither stages the file in memory.
+    pub fn truncate<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        new_size: u64,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        let path = &self.content_path(block_io, path)?;
+        let cur_size = {
+            let idx_entry = self
+                .index
+                .lookup(block_io, path)?
+                .ok_or(HelixError::NotFound)?;
+            if idx_entry.flags & entry_flags::IS_DIR != 0 {
+                return Err(HelixError::IsADirectory);
+            }
+            idx_entry.size
+        };
+
+        if new_size == cur_size {
+            return Ok(());
+        }
+        self.write_range(block_io, path, 0, &[], new_size, timestamp_ns)
+    }
+
+    /// Extended attributes of `path`, by name.
+    pub fn listxattr<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Vec<String>, HelixError> {
+        let path = &self.content_path(block_io, path)?;
+        let attrs = ops::xattr::load(
+            block_io,
+            &self.index,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+        )?;
+        Ok(attrs.into_keys().collect())
+    }
+
+    pub fn getxattr<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+        name: &str,
+    ) -> Result<Vec<u8>, HelixError> {
+        ops::xattr::validate_name(name)?;
+        let path = &self.content_path(block_io, path)?;
+        let mut attrs = ops::xattr::load(
+            block_io,
+            &self.index,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+        )?;
+        attrs.remove(name).ok_or(HelixError::NoAttribute)
+    }
+
+    /// Create or replace one attribute. Content and mtime are untouched.
+    pub fn setxattr<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        name: &str,
+        value: &[u8],
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        ops::xattr::validate_name(name)?;
+        if name == crate::compress::POLICY_XATTR
+            && value != crate::compress::POLICY_LZ4
+            && value != crate::compress::POLICY_NONE
+        {
+            return Err(HelixError::NotSupported);
+        }
+        let path = &self.content_path(block_io, path)?;
+        let quota = if name == ops::quota::POLICY_XATTR {
+            let dir = self
+                .index
+                .lookup_flex(block_io, path)?
+                .filter(|e| e.flags & entry_flags::IS_DIR != 0)
+                .ok_or(HelixError::NotADirectory)?;
+            let limit = Limit::parse(value).ok_or(HelixError::NotSupported)?;
+            Some((String::from(btree::path_str(&dir.path)), limit))
+        } else {
+            None
+        };
+        let mut attrs = ops::xattr::load(
+            block_io,
+            &self.index,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+        )?;
+        attrs.insert(String::from(name), value.to_vec());
+        self.store_xattrs(block_io, path, &attrs, timestamp_ns)?;
+        match quota {
+            Some((dir, limit)) => self.index.set_quota(block_io, &dir, limit),
+            None => Ok(()),
+        }
+    }
+
+    pub fn removexattr<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        name: &str,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        ops::xattr::validate_name(name)?;
+        let path = &self.content_path(block_io, path)?;
+        let mut attrs = ops::xattr::load(
+            block_io,
+            &self.index,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+        )?;
+        if attrs.remove(name).is_none() {
+            return Err(HelixError::NoAttribute);
+        }
+        self.store_xattrs(block_io, path, &attrs, timestamp_ns)?;
+        if name == ops::quota::POLICY_XATTR {
+            self.index.drop_quota(&ops::quota::dir_form(path));
+        }
+        Ok(())
+    }
+
+    /// Extended attributes of `path` as they were at `lsn` (e.g. a snapshot).
+    pub fn xattrs_at_lsn<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+        lsn: Lsn,
+    ) -> Result<ops::xattr::Xattrs, HelixError> {
+        ops::xattr::xattrs_at_lsn(
+            block_io,
+            &self.log,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+            lsn,
+        )
+    }
+
+    /// Owner and permission bits of `path`.
+    pub fn owner<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<ops::owner::Owner, HelixError> {
+        if ops::link::is_record(path) {
+            return Err(HelixError::PathInvalid);
+        }
+        let entry = self
+            .index
+            .lookup_flex(block_io, path)?
+            .ok_or(HelixError::NotFound)?;
+        let entry = ops::link::resolve(block_io, &self.index, entry)?;
+        Ok(ops::owner::of(&entry))
+    }
+
+    /// Replace the permission bits of `path`; the owner is kept.
+    pub fn chmod<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        mode: u32,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        let owner = self.owner(block_io, path)?;
+        self.set_owner(
+            block_io,
+            path,
+            ops::owner::Owner { mode, ..owner },
+            timestamp_ns,
+        )
+    }
+
+    /// Give `path` to `uid`/`gid`; the permission bits are kept.
+    pub fn chown<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        uid: u32,
+        gid: u32,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        let owner = self.owner(block_io, path)?;
+        self.set_owner(
+            block_io,
+            path,
+            ops::owner::Owner { uid, gid, ..owner },
+            timestamp_ns,
+        )
+    }
+
+    fn set_owner<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        owner: ops::owner::Owner,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        let path = &self.content_path(block_io, path)?;
+        self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::owner::set(dev, &mut s.log, &mut s.index, path, owner, timestamp_ns).map(|_| ())
+        })
+    }
+
+    /// Log `attrs` as the new attribute set of `path` and reclaim the old
+    /// block unless a snapshot still references it.
+    fn store_xattrs<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        attrs: &ops::xattr::Xattrs,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        let (new_lsn, old) = self.with_checkpoint_retry(block_io, |s, dev| {
+            ops::xattr::store(
+                dev,
+                &mut s.log,
+                &mut s.index,
+                &mut s.bitmap,
+                s.partition_lba_start,
+                s.sb.data_start_block,
+                s.device_block_size,
+                path,
+                attrs,
+                timestamp_ns,
+            )
+        })?;
+        if let Some((block, old_lsn)) = old {
+            if !self.snapshot_pins(old_lsn, new_lsn) {
+                let _ = self.bitmap.free_block(block);
+            }
+        }
+        Ok(())
+    }
+
+    /// All versions of `path`, oldest-to-newest: the retained ones, listed as
+    /// `Write`s, and whatever the log ring still holds. Under a retention
+    /// policy the content listed is exactly the retained versions and the
+    /// current one; older records in the ring name versions already dropped.
+    pub fn versions<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Vec<(Lsn, u64, LogOp)>, HelixError> {
+        let path = &self.content_path(block_io, path)?;
+        let mut versions = match ops::read::list_versions(block_io, &self.log, path) {
+            Ok(v) => v,
+            Err(HelixError::NotFound) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let kept: Vec<&VersionEntry> = self
+            .history
+            .iter()
+            .filter(|v| ops::retain::path_of(v) == path)
+            .collect();
+        if self.retention(block_io, path)?.is_some() {
+            let live = self
+                .index
+                .lookup(block_io, path)?
+                .filter(|e| e.flags & entry_flags::IS_DIR == 0);
+            versions.retain(|&(lsn, _, op)| {
+                !matches!(op, LogOp::Write | LogOp::DedupRef)
+                    || live.is_some_and(|e| e.lsn == lsn)
+                    || kept.iter().any(|v| v.lsn == lsn)
+            });
+            if let Some(e) = live.filter(|e| !versions.iter().any(|r| r.0 == e.lsn)) {
+                versions.push((e.lsn, e.modified_ns, LogOp::Write));
+            }
+        }
+        for v in kept {
+            if !versions.iter().any(|r| r.0 == v.lsn) {
+                versions.push((v.lsn, v.modified_ns, LogOp::Write));
+            }
+        }
+        if versions.is_empty() {
+            return Err(HelixError::NotFound);
+        }
+        versions.sort_by_key(|r| r.0);
+        Ok(versions)
+    }
+
+    /// Content of `path` as of the version logged at `lsn` (see `versions`):
+    /// a retained version, the live one, or one the log ring still holds.
+    pub fn read_version<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+        lsn: Lsn,
+    ) -> Result<Vec<u8>, HelixError> {
+        let path = &self.content_path(block_io, path)?;
+        if let Some(v) = self
+            .history
+            .iter()
+            .rev()
+            .find(|v| v.lsn == lsn && ops::retain::path_of(v) == path)
+        {
+            return ops::read::read_entry(
+                block_io,
+                self.partition_lba_start,
+                self.sb.data_start_block,
+                self.device_block_size,
+                &ops::retain::as_entry(v),
+            );
+        }
+        if self
+            .index
+            .lookup(block_io, path)?
+            .is_some_and(|e| e.lsn == lsn)
+        {
+            return self.read_content(block_io, path);
+        }
+        ops::read::read_file_at_lsn(
+            block_io,
+            &self.log,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+            lsn,
+        )
+    }
+
+    /// Bring `path` back to how it was at `lsn`, as one transaction.
+    ///
+    /// A file gets the version that was current at `lsn` — any LSN `versions`
+    /// lists names one — even if it has been deleted since. A retained version
+    /// is pointed at, not copied: the file shares its blocks with the history
+    /// until either lets go. A version that only a snapshot still holds is
+    /// copied, as `rollback_to` does. `NotFound` if neither holds it.
+    ///
+    /// A directory, live or deleted, has every file below it restored that
+    /// way, and what was created under it since `lsn` removed; files whose
+    /// version is gone are left as they are. Attributes are not restored. The
+    /// replaced versions are retained as usual, so a restore can be undone.
+    pub fn restore<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        lsn: Lsn,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let path = &self.content_path(block_io, path)?;
+        let snap = self.namespace_after(block_io, lsn)?;
+        let live = self.index.lookup_flex(block_io, path)?;
+        let past = match live {
+            Some(e) if e.flags & entry_flags::IS_DIR != 0 => None,
+            _ if path == "/" => None,
+            _ => match self.past_version(block_io, path, lsn, live, snap.as_ref())? {
+                Past::Absent | Past::Gone if live.is_some() => return Err(HelixError::NotFound),
+                // Nothing of that name: a deleted directory, if anything.
+                Past::Absent | Past::Gone => None,
+                past => Some(past),
+            },
+        };
+        let (files, dirs) = match past {
+            Some(Past::Live) => return Ok(()),
+            Some(past) => (alloc::vec![(String::from(path), past)], Vec::new()),
+            None => self.subtree_at(block_io, path, lsn, snap.as_ref())?,
+        };
+        if files.is_empty() && dirs.is_empty() {
+            return Err(HelixError::NotFound);
+        }
+
+        self.begin_tx(block_io, timestamp_ns)?;
+        match self
+            .apply_past(block_io, &files, &dirs, snap.as_ref(), timestamp_ns)
+            .and_then(|()| self.reconcile_quotas(block_io))
+        {
+            Ok(()) => self.commit(block_io, timestamp_ns).map(|_| ()),
+            Err(e) => {
+                self.abort(block_io, timestamp_ns)?;
+                Err(e)
+            },
+        }
+    }
+
+    /// The namespace of the oldest snapshot taken at or after `lsn`: every
+    /// version current at `lsn` that it holds is still current in it.
+    fn namespace_after<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        lsn: Lsn,
+    ) -> Result<Option<NamespaceIndex>, HelixError> {
+        match self.snapshots.iter().find(|e| e.lsn >= lsn) {
+            Some(entry) => self.snapshot_namespace(block_io, entry).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Where the version of file `path` current at `lsn` is to be had.
+    /// `live` is its live entry.
+    fn past_version<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+        lsn: Lsn,
+        live: Option<IndexEntry>,
+        snap: Option<&NamespaceIndex>,
+    ) -> Result<Past, HelixError> {
+        if live.is_some_and(|e| e.lsn <= lsn) {
+            return Ok(Past::Live);
+        }
+        let mine = || {
+            self.history
+                .iter()
+                .rev()
+                .filter(move |v| ops::retain::path_of(v) == path)
+        };
+        if let Some(v) = mine().find(|v| v.lsn <= lsn && lsn < v.superseded_lsn) {
+            return Ok(Past::Kept(*v));
+        }
+        if let Some(snap) = snap {
+            if let Some(e) = snap
+                .lookup(block_io, path)?
+                .filter(|e| e.lsn <= lsn && e.flags & NOT_VERSIONED == 0)
+            {
+                return Ok(Past::Snapshot(e));
+            }
+        }
+        let existed = live.is_some_and(|e| e.first_lsn <= lsn)
+            || mine().any(|v| v.first_lsn <= lsn && lsn < v.superseded_lsn);
+        Ok(if existed { Past::Gone } else { Past::Absent })
+    }
+
+    /// What restoring directory `path` to `lsn` does: the files below it
+    /// with where their past versions are, and the directories created
+    /// since, deepest first.
+    fn subtree_at<B: BlockIo>(
+        &self,
         block_io: &mut B,
         path: &str,
-        new_size: u64,
-        timestamp_ns: u64,
-    ) -> Result<(), HelixError> {
-        // Snapshot metadata, then drop the index borrow before the write borrow.
-        let (is_inline, cur_size, inline_copy) = {
-            let idx_entry = self.index.lookup(path).ok_or(HelixError::NotFound)?;
-            if idx_entry.flags & entry_flags::IS_DIR != 0 {
-                return Err(HelixError::IsADirectory);
+        lsn: Lsn,
+        snap: Option<&NamespaceIndex>,
+    ) -> Result<Plan, HelixError> {
+        let dir = path.trim_end_matches('/');
+        let below = |p: &str| {
+            !ops::link::is_record(p)
+                && p.strip_prefix(dir)
+                    .is_some_and(|r| r.len() > 1 && r.starts_with('/'))
+        };
+        let mut paths: Vec<String> = Vec::new();
+        let mut dirs: Vec<String> = Vec::new();
+        let mut cursor = self.index.cursor();
+        while let Some(e) = self.index.next_entry(block_io, &mut cursor)? {
+            let p = btree::path_str(&e.path);
+            if !below(p) {
+                continue;
             }
-            if idx_entry.flags & entry_flags::IS_DELETED != 0 {
-                return Err(HelixError::NotFound);
+            if e.flags & entry_flags::IS_DIR == 0 {
+                paths.push(String::from(p));
+            } else if e.first_lsn > lsn {
+                dirs.push(String::from(p.trim_end_matches('/')));
             }
-            let is_inline = idx_entry.flags & entry_flags::IS_INLINE != 0;
-            let inline_copy = if is_inline {
-                let size = (idx_entry.size as usize).min(INLINE_DATA_SIZE);
-                idx_entry.inline_data[..size].to_vec()
-            } else {
-                Vec::new()
+        }
+        paths.extend(
+            self.history
+                .iter()
+                .map(ops::retain::path_of)
+                .filter(|p| below(p))
+                .map(String::from),
+        );
+        if let Some(snap) = snap {
+            let mut cursor = snap.cursor();
+            while let Some(e) = snap.next_entry(block_io, &mut cursor)? {
+                let p = btree::path_str(&e.path);
+                if e.flags & (entry_flags::IS_DIR | NOT_VERSIONED) == 0 && below(p) {
+                    paths.push(String::from(p));
+                }
+            }
+        }
+        paths.sort_unstable();
+        paths.dedup();
+
+        let mut files = Vec::new();
+        for p in paths {
+            let live = self.index.lookup(block_io, &p)?;
+            // Links are not versioned: they only go, if they came later.
+            let past = match live {
+                Some(e) if e.flags & NOT_VERSIONED != 0 => {
+                    if e.first_lsn > lsn {
+                        Past::Absent
+                    } else {
+                        continue;
+                    }
+                },
+                _ => self.past_version(block_io, &p, lsn, live, snap)?,
             };
-            (is_inline, idx_entry.size, inline_copy)
-        };
+            match past {
+                Past::Live | Past::Gone => {},
+                Past::Absent if live.is_none() => {},
+                past => files.push((p, past)),
+            }
+        }
+        dirs.sort_unstable_by_key(|d| core::cmp::Reverse(d.len()));
+        Ok((files, dirs))
+    }
 
-        if new_size == cur_size {
-            return Ok(());
+    /// Carry out a restore inside the open transaction.
+    fn apply_past<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        files: &[(String, Past)],
+        dirs: &[String],
+        snap: Option<&NamespaceIndex>,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        for (path, past) in files {
+            match past {
+                Past::Kept(v) => self.restore_kept(block_io, path, v, timestamp_ns)?,
+                Past::Snapshot(e) => {
+                    let snap = snap.ok_or(HelixError::NotFound)?;
+                    let data = self.read_entry(block_io, snap, e)?;
+                    self.write_versioned(block_io, path, &data, timestamp_ns, false)?;
+                },
+                Past::Absent => self.unlink(block_io, path, timestamp_ns)?,
+                Past::Live | Past::Gone => {},
+            }
         }
-        if new_size > cur_size && new_size > MAX_TRUNCATE_GROW {
-            return Err(HelixError::FileTooLarge);
+        // A directory something was moved into since stays.
+        for dir in dirs {
+            match self.unlink(block_io, dir, timestamp_ns) {
+                Ok(()) | Err(HelixError::DirectoryNotEmpty) => {},
+                Err(e) => return Err(e),
+            }
         }
+        Ok(())
+    }
 
-        let current = if is_inline {
-            inline_copy
-        } else {
-            ops::read::read_file(
+    /// Make retained version `v` the content of `path` again: shared when it
+    /// is a plain extent file, rewritten when inline or compressed.
+    fn restore_kept<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        v: &VersionEntry,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        let kept = ops::retain::as_entry(v);
+        let compressed = kept.flags & entry_flags::IS_COMPRESSED != 0;
+        let Some(ext) = crate::dedup::extent_of(&kept).filter(|_| !compressed) else {
+            let data = ops::read::read_entry(
                 block_io,
-                &self.index,
                 self.partition_lba_start,
                 self.sb.data_start_block,
                 self.device_block_size,
-                path,
-            )?
+                &kept,
+            )?;
+            return self.write_versioned(block_io, path, &data, timestamp_ns, compressed);
         };
-
-        let mut new_data = current;
-        new_data.resize(new_size as usize, 0u8);
-
-        self.write(block_io, path, &new_data, timestamp_ns)
-    }
-
-    /// All logged versions of `path`, oldest-to-newest.
-    pub fn versions<B: BlockIo>(
-        &self,
-        block_io: &mut B,
-        path: &str,
-    ) -> Result<Vec<(Lsn, u64, LogOp)>, HelixError> {
-        ops::read::list_versions(block_io, &self.log, path)
+        let old = self
+            .index
+            .lookup(block_io, path)?
+            .and_then(|e| crate::dedup::extent_of(&e).map(|ext| (ext, e.lsn)));
+        let prior = self.retained(block_io, path)?;
+        let lsn = self.share_extent(block_io, path, ext, v.content_crc64, timestamp_ns)?;
+        self.note_created(block_io, path, lsn)?;
+        if v.content_crc64 != 0 {
+            self.dedup.register(v.content_crc64, ext);
+        }
+        self.supersede(block_io, old, prior, lsn, timestamp_ns)
     }
 
-    /// Append a snapshot marker; returned LSN is the point-in-time handle for `O_AT_LSN`.
+    /// Append a snapshot marker and add it to the snapshot table; returned LSN
+    /// is the point-in-time handle for `O_AT_LSN` and `mount_snapshot`. Names
+    /// need not be unique (lookups take the newest). `SnapshotTableFull` once
+    /// `MAX_SNAPSHOTS` exist.
     pub fn snapshot<B: BlockIo>(
         &mut self,
         block_io: &mut B,
         name: &str,
         timestamp_ns: u64,
     ) -> Result<Lsn, HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let mut entry = ops::snapshot::new_entry(name, 0, timestamp_ns)?;
+        if self.snapshots.len() >= ops::snapshot::MAX_SNAPSHOTS {
+            return Err(HelixError::SnapshotTableFull);
+        }
         // Payload: [name_len: u16][name]; hash so same-named snapshots correlate.
         let name_b = name.as_bytes();
         let name_hash = crc::fnv1a_64(name_b);
@@ -530,15 +2660,571 @@ impl HelixFs {
         let lsn = self
             .log
             .append(block_io, LogOp::Snapshot, name_hash, &payload, timestamp_ns)?;
-        self.snapshot_lsns.push(lsn);
+        entry.lsn = lsn;
+        let mut table = self.snapshots.clone();
+        table.push(entry);
+        let old_table = self.store_snapshot_table(block_io, &table)?;
         // Persist the log AND the superblock; flushing the log alone leaves the
         // replay boundary behind the marker, so a crash would drop it.
         self.sync(block_io)?;
+        if let Some(block) = old_table {
+            let _ = self.bitmap.free_block(block);
+        }
+        Ok(lsn)
+    }
+
+    /// Drop the newest snapshot called `name` and free every block only it
+    /// was keeping. Refused while a transaction is open.
+    pub fn delete_snapshot<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        name: &str,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let pos = ops::snapshot::find(&self.snapshots, name).ok_or(HelixError::NotFound)?;
+        let mut table = self.snapshots.clone();
+        table.remove(pos);
+        self.store_snapshot_table(block_io, &table)?;
+        self.sync(block_io)?;
+        // The old table block and the snapshot's index region go with the rest.
+        self.reclaim_unpinned(block_io)
+    }
+
+    /// Make the live namespace equal to the newest snapshot called `name`:
+    /// paths created since are removed, and files, directories and attributes
+    /// the snapshot holds are restored. Runs as one transaction, so a crash or
+    /// error leaves the namespace as it was. Restored files are rewritten, not
+    /// shared with the snapshot, which stays valid and can be rolled back to
+    /// again.
+    pub fn rollback_to<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        name: &str,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let pos = ops::snapshot::find(&self.snapshots, name).ok_or(HelixError::NotFound)?;
+        let entry = self.snapshots[pos];
+        let target = self.snapshot_namespace(block_io, &entry)?;
+
+        self.begin_tx(block_io, timestamp_ns)?;
+        match self
+            .apply_namespace(block_io, &target, timestamp_ns)
+            .and_then(|()| self.reconcile_quotas(block_io))
+        {
+            Ok(()) => self.commit(block_io, timestamp_ns).map(|_| ()),
+            Err(e) => {
+                self.abort(block_io, timestamp_ns)?;
+                Err(e)
+            },
+        }
+    }
+
+    /// Rewrite the live namespace into `target` (another index over this
+    /// volume's blocks). Entries whose version and attributes match are left
+    /// alone.
+    fn apply_namespace<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        target: &NamespaceIndex,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        use ops::stream::Change;
+
+        for change in ops::stream::delta(block_io, &self.index, target)? {
+            match change {
+                Change::Remove(path) => self.unlink(block_io, &path, timestamp_ns)?,
+                Change::Mkdir(t) => {
+                    let path = btree::path_str(&t.path).trim_end_matches('/');
+                    self.mkdir(block_io, path, timestamp_ns)?;
+                },
+                Change::Write(t) => {
+                    let path = btree::path_str(&t.path);
+                    let data = self.read_entry(block_io, target, &t)?;
+                    self.detach(block_io, path, timestamp_ns)?;
+                    self.write(block_io, path, &data, timestamp_ns)?;
+                },
+                Change::SetXattrs(t) => {
+                    let attrs = self.load_entry_xattrs(block_io, &t)?;
+                    let path = self.content_path(block_io, btree::path_str(&t.path))?;
+                    self.store_xattrs(block_io, &path, &attrs, timestamp_ns)?;
+                },
+                Change::Symlink(t) => {
+                    let link = self.read_entry(block_io, target, &t)?;
+                    let link = core::str::from_utf8(&link).map_err(|_| HelixError::PathInvalid)?;
+                    self.replace_symlink(block_io, link, btree::path_str(&t.path), timestamp_ns)?;
+                },
+                Change::SetOwner(t) => {
+                    let owner = ops::owner::of(&t);
+                    self.set_owner(block_io, btree::path_str(&t.path), owner, timestamp_ns)?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop `path` if it is a hard-linked name, so that rewriting it gives it
+    /// content of its own rather than changing every name's.
+    fn detach<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        if self
+            .index
+            .lookup(block_io, path)?
+            .is_some_and(|e| e.flags & entry_flags::IS_HARDLINK != 0)
+        {
+            self.unlink(block_io, path, timestamp_ns)?;
+        }
+        Ok(())
+    }
+
+    /// `symlink`, replacing whatever file or link `path` holds.
+    fn replace_symlink<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        target: &str,
+        path: &str,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        if self.index.lookup(block_io, path)?.is_some() {
+            self.unlink(block_io, path, timestamp_ns)?;
+        }
+        self.symlink(block_io, target, path, timestamp_ns)
+    }
+
+    /// Content of file entry `t` of `index` (an index over this volume).
+    fn read_entry<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        index: &NamespaceIndex,
+        t: &IndexEntry,
+    ) -> Result<Vec<u8>, HelixError> {
+        let path = ops::link::content_path(block_io, index, btree::path_str(&t.path))?;
+        ops::read::read_file(
+            block_io,
+            index,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            &path,
+        )
+    }
+
+    fn load_entry_xattrs<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        t: &IndexEntry,
+    ) -> Result<ops::xattr::Xattrs, HelixError> {
+        ops::xattr::load_block(
+            block_io,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            t.xattr_block,
+            t.xattr_len,
+        )
+    }
+
+    /// The namespace of the snapshot taken at `lsn`; 0 is the empty namespace.
+    fn namespace_at_snapshot<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        lsn: Lsn,
+    ) -> Result<NamespaceIndex, HelixError> {
+        if lsn == 0 {
+            return Ok(NamespaceIndex::new());
+        }
+        let entry = *self
+            .snapshots
+            .iter()
+            .find(|e| e.lsn == lsn)
+            .ok_or(HelixError::NotFound)?;
+        self.snapshot_namespace(block_io, &entry)
+    }
+
+    /// Serialise every namespace change between the snapshots taken at
+    /// `from_lsn` and `to_lsn` into a send stream (see `ops::stream`).
+    /// `from_lsn` 0 sends the whole of `to_lsn`. Changed files travel whole,
+    /// with their mtime and attributes. `NotFound` unless both are snapshots
+    /// of this volume.
+    pub fn send<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        from_lsn: Lsn,
+        to_lsn: Lsn,
+    ) -> Result<Vec<u8>, HelixError> {
+        use ops::stream::{Change, StreamOp};
+
+        let from = self.namespace_at_snapshot(block_io, from_lsn)?;
+        let to = self.namespace_at_snapshot(block_io, to_lsn)?;
+        let mut out = ops::stream::StreamWriter::new(&ops::stream::StreamHeader {
+            from_lsn,
+            to_lsn,
+            source_uuid: self.sb.uuid,
+        });
+        for change in ops::stream::delta(block_io, &from, &to)? {
+            match change {
+                Change::Remove(path) => out.push(&StreamOp::Remove { path: &path }),
+                Change::Mkdir(t) => out.push(&StreamOp::Mkdir {
+                    path: btree::path_str(&t.path).trim_end_matches('/'),
+                    created_ns: t.created_ns,
+                }),
+                Change::Write(t) => {
+                    let data = self.read_entry(block_io, &to, &t)?;
+                    out.push(&StreamOp::Write {
+                        path: btree::path_str(&t.path),
+                        modified_ns: t.modified_ns,
+                        data: &data,
+                    });
+                },
+                Change::SetXattrs(t) => {
+                    let attrs = self.load_entry_xattrs(block_io, &t)?;
+                    let blob = if attrs.is_empty() {
+                        Vec::new()
+                    } else {
+                        ops::xattr::encode(&attrs)?
+                    };
+                    out.push(&StreamOp::SetXattrs {
+                        path: btree::path_str(&t.path),
+                        blob: &blob,
+                    });
+                },
+                Change::Symlink(t) => {
+                    let target = self.read_entry(block_io, &to, &t)?;
+                    out.push(&StreamOp::Symlink {
+                        path: btree::path_str(&t.path),
+                        target: core::str::from_utf8(&target)
+                            .map_err(|_| HelixError::PathInvalid)?,
+                    });
+                },
+                Change::SetOwner(t) => out.push(&StreamOp::SetOwner {
+                    path: btree::path_str(&t.path),
+                    owner: ops::owner::of(&t),
+                }),
+            }
+        }
+        Ok(out.finish())
+    }
+
+    /// Replay a send stream onto this volume as one transaction: all of it
+    /// lands or none does. The stream is verified end to end before anything
+    /// is touched. The volume must hold the stream's `from` state — a removal
+    /// of a path that is not there fails the whole receive with `NotFound`;
+    /// beyond that the base is not checked. Returns the stream's header.
+    pub fn receive<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        stream: &[u8],
+        timestamp_ns: u64,
+    ) -> Result<ops::stream::StreamHeader, HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let (header, records) = ops::stream::parse(stream)?;
+
+        self.begin_tx(block_io, timestamp_ns)?;
+        match self
+            .apply_stream(block_io, &records, timestamp_ns)
+            .and_then(|()| self.reconcile_quotas(block_io))
+        {
+            Ok(()) => self.commit(block_io, timestamp_ns).map(|_| header),
+            Err(e) => {
+                self.abort(block_io, timestamp_ns)?;
+                Err(e)
+            },
+        }
+    }
+
+    fn apply_stream<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        records: &[ops::stream::StreamOp<'_>],
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        use ops::stream::StreamOp;
+
+        for record in records {
+            match *record {
+                StreamOp::Remove { path } => self.unlink(block_io, path, timestamp_ns)?,
+                StreamOp::Mkdir { path, created_ns } => self.mkdir(block_io, path, created_ns)?,
+                StreamOp::Write {
+                    path,
+                    modified_ns,
+                    data,
+                } => {
+                    self.detach(block_io, path, timestamp_ns)?;
+                    self.write(block_io, path, data, modified_ns)?;
+                },
+                StreamOp::SetXattrs { path, blob } => {
+                    let attrs = if blob.is_empty() {
+                        ops::xattr::Xattrs::new()
+                    } else {
+                        ops::xattr::decode(blob).map_err(|_| HelixError::StreamInvalid)?
+                    };
+                    let path = self.content_path(block_io, path)?;
+                    self.store_xattrs(block_io, &path, &attrs, timestamp_ns)?;
+                },
+                StreamOp::Symlink { path, target } => {
+                    self.replace_symlink(block_io, target, path, timestamp_ns)?
+                },
+                StreamOp::SetOwner { path, owner } => {
+                    self.set_owner(block_io, path, owner, timestamp_ns)?
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `table` as the new snapshot table and adopt it; the superblock is
+    /// updated in memory only. Returns the superseded table block to free once
+    /// the superblock is durable.
+    pub(crate) fn store_snapshot_table<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        table: &[SnapshotEntry],
+    ) -> Result<Option<BlockAddr>, HelixError> {
+        let old = ops::snapshot::store_table(
+            block_io,
+            &mut self.bitmap,
+            self.partition_lba_start,
+            self.device_block_size,
+            &mut self.sb,
+            table,
+        )?;
+        self.set_snapshots(table.to_vec());
+        Ok(old)
+    }
+
+    /// Write each snapshot still held only by the log ring to its own index
+    /// region. Runs before a checkpoint recycles the ring; the regions become
+    /// durable with the checkpoint's superblock. Returns the new table, or
+    /// `None` when every snapshot already has a region.
+    fn materialize_snapshots<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+    ) -> Result<Option<Vec<SnapshotEntry>>, HelixError> {
+        if self.snapshots.iter().all(|e| e.index_root != BLOCK_NULL) {
+            return Ok(None);
+        }
+        let mut table = self.snapshots.clone();
+        let mut written: Vec<(BlockAddr, u64)> = Vec::new();
+        let mut result = Ok(());
+        for entry in table.iter_mut().filter(|e| e.index_root == BLOCK_NULL) {
+            result = self.write_snapshot_region(block_io, entry);
+            match result {
+                Ok(()) => written.push((entry.index_root, entry.index_blocks as u64)),
+                Err(_) => break,
+            }
+        }
+        if let Err(e) = result {
+            for (start, count) in written {
+                let _ = self.bitmap.free_range(start, count);
+            }
+            return Err(e);
+        }
+        Ok(Some(table))
+    }
+
+    fn write_snapshot_region<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        entry: &mut SnapshotEntry,
+    ) -> Result<(), HelixError> {
+        let index = self.load_namespace(block_io, entry.lsn)?;
+        let (start, blocks, entries) = self.write_index_tree(block_io, &index)?;
+        entry.index_root = start;
+        entry.index_blocks = blocks as u32;
+        entry.index_entry_count = entries as u32;
+        Ok(())
+    }
+
+    /// Write `index` as a fresh B+tree region. Space is reserved for the
+    /// entry bound and the unused tail handed back. Returns `(start, blocks,
+    /// entries)`.
+    fn write_index_tree<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        index: &NamespaceIndex,
+    ) -> Result<(BlockAddr, u64, u64), HelixError> {
+        let capacity = crate::checkpoint::region_blocks(index.entry_bound());
+        let start = self.bitmap.alloc_contiguous(capacity)?;
+        match crate::checkpoint::write_index_region(
+            block_io,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            start,
+            capacity,
+            index,
+        ) {
+            Ok((blocks, entries)) => {
+                if blocks < capacity {
+                    let _ = self.bitmap.free_range(start + blocks, capacity - blocks);
+                }
+                Ok((start, blocks, entries))
+            },
+            Err(e) => {
+                let _ = self.bitmap.free_range(start, capacity);
+                Err(e)
+            },
+        }
+    }
+
+    /// Open a transaction: every mutation until `commit` is replayed together,
+    /// or not at all. One transaction per volume; a second `begin_tx` is
+    /// `TxConflict`. The log cannot checkpoint while it is open, so a
+    /// transaction larger than the free ring fails with `LogFull` — abort it.
+    pub fn begin_tx<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        timestamp_ns: u64,
+    ) -> Result<Lsn, HelixError> {
+        self.check_writable()?;
+        if self.tx.is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let lsn = self.with_checkpoint_retry(block_io, |fs, dev| {
+            fs.log.append(dev, LogOp::TxBegin, 0, &[], timestamp_ns)
+        })?;
+        self.tx = Some(TxState {
+            begin_lsn: lsn,
+            history_len: self.history.len(),
+        });
+        self.log.set_active_tx(lsn);
+        self.index.begin_journal();
+        self.bitmap.begin_deferred();
+        Ok(lsn)
+    }
+
+    /// Log `TxCommit` and sync. Blocks superseded inside the transaction are
+    /// released only once the commit is durable.
+    pub fn commit<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        timestamp_ns: u64,
+    ) -> Result<Lsn, HelixError> {
+        let begin_lsn = self
+            .tx
+            .as_ref()
+            .map(|t| t.begin_lsn)
+            .ok_or(HelixError::NoActiveTransaction)?;
+        let lsn = self.log.append_full(
+            block_io,
+            LogOp::TxCommit,
+            0,
+            0,
+            0,
+            begin_lsn,
+            &[],
+            timestamp_ns,
+        )?;
+        self.tx = None;
+        self.log.set_active_tx(0);
+        self.index.commit_journal();
+
+        // If the commit never reaches disk, replay will drop the transaction
+        // and the superseded blocks are live again: leak them until remount
+        // rather than hand them out.
+        match self.sync(block_io) {
+            Ok(()) => self.bitmap.apply_deferred()?,
+            Err(e) => {
+                self.bitmap.discard_deferred();
+                return Err(e);
+            },
+        }
         Ok(lsn)
     }
 
-    /// Flush log, write both superblock slots, flush device.
+    /// Discard the open transaction: the index and allocation map return to
+    /// their state at `begin_tx`. Blocks written inside it were copy-on-write,
+    /// so the committed data underneath is untouched.
+    pub fn abort<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        timestamp_ns: u64,
+    ) -> Result<(), HelixError> {
+        let tx = self.tx.take().ok_or(HelixError::NoActiveTransaction)?;
+        // Best effort: replay drops an unterminated transaction anyway.
+        let _ = self.log.append_full(
+            block_io,
+            LogOp::TxAbort,
+            0,
+            0,
+            0,
+            tx.begin_lsn,
+            &[],
+            timestamp_ns,
+        );
+        self.log.set_active_tx(0);
+        if let Some(feed) = self.log.feed_mut() {
+            feed.discard(tx.begin_lsn);
+        }
+        self.index.rollback_journal();
+        self.bitmap.rollback_deferred();
+        self.history.truncate(tx.history_len);
+        self.recount_shares(block_io)
+    }
+
+    /// `TxBegin` LSN of the open transaction.
+    pub fn active_tx(&self) -> Option<Lsn> {
+        self.tx.as_ref().map(|t| t.begin_lsn)
+    }
+
+    /// Start or stop collecting namespace changes for `take_changes`.
+    pub fn watch(&mut self, on: bool) {
+        self.log.set_feed(on);
+    }
+
+    /// Changes since the last call, oldest first, and whether the feed's
+    /// bound dropped some; see `log::feed`. Empty unless `watch` is on.
+    pub fn take_changes(&mut self) -> (Vec<Change>, bool) {
+        let open = self.active_tx().unwrap_or(0);
+        match self.log.feed_mut() {
+            Some(feed) => feed.drain(open),
+            None => (Vec::new(), false),
+        }
+    }
+
+    /// Tell the change feed whether content record `lsn` brought `path`
+    /// into being; the log alone cannot say.
+    fn note_created<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        lsn: Lsn,
+    ) -> Result<(), HelixError> {
+        if self.log.feed_mut().is_none() {
+            return Ok(());
+        }
+        if self
+            .index
+            .lookup(block_io, path)?
+            .is_some_and(|e| e.first_lsn == lsn)
+        {
+            if let Some(feed) = self.log.feed_mut() {
+                feed.created(lsn);
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush log, write both superblock slots, flush device. A no-op on a
+    /// snapshot mount, which has nothing to flush and must not touch the
+    /// superblock the live mount owns.
     pub fn sync<B: BlockIo>(&mut self, block_io: &mut B) -> Result<(), HelixError> {
+        if self.frozen.is_some() {
+            return Ok(());
+        }
         let committed_lsn = self.log.flush(block_io)?;
 
         self.sb.committed_lsn = committed_lsn;
@@ -565,6 +3251,3 @@ impl HelixFs {
         Ok(())
     }
 }
-
-/// Largest size a `truncate` may *grow* a file to (heap-staged RMW bound).
-pub const MAX_TRUNCATE_GROW: u64 = 1 << 20; // 1 MiB
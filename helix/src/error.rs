@@ -48,4 +48,23 @@ pub enum HelixError {
     MountNotFound,
     PermissionDenied,
     InvalidOffset,
+    /// Extended attribute not set on this entry.
+    NoAttribute,
+    /// An entry's extended attributes would exceed one block.
+    AttributeTooLarge,
+    /// The snapshot table is full; delete a snapshot first.
+    SnapshotTableFull,
+    /// A send stream failed its magic, version, CRC or record checks.
+    StreamInvalid,
+    /// The volume is encrypted; open it through `crypt::Crypt::unlock`.
+    Encrypted,
+    /// The passphrase does not unwrap the volume key.
+    BadPassphrase,
+    /// `readlink` on an entry that is not a symbolic link.
+    NotASymlink,
+    /// A shrink would cut off blocks that cannot move: a snapshot still
+    /// sees them.
+    InUse,
+    /// A directory quota (`ops::quota`) would be exceeded.
+    QuotaExceeded,
 }
@@ -170,3 +170,41 @@ pub fn read_extent_file<B: BlockIo>(
     }
     Ok(result)
 }
+
+/// A file's `(logical, physical, count)` runs regardless of residency: the
+/// node's list, or one run for a contiguous file. Logical blocks absent from
+/// every run are holes and read as zeros.
+pub fn file_runs<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    extent_root: u64,
+    size: u64,
+    is_node: bool,
+) -> Result<Vec<(u64, u64, u32)>, HelixError> {
+    if extent_root == BLOCK_NULL {
+        return Ok(Vec::new());
+    }
+    if is_node {
+        return read_extent_node(
+            block_io,
+            partition_lba_start,
+            data_start_block,
+            device_block_size,
+            extent_root,
+        );
+    }
+    let blocks = size.div_ceil(BLOCK_SIZE as u64);
+    if blocks == 0 {
+        return Ok(Vec::new());
+    }
+    Ok(vec![(0, extent_root, blocks as u32)])
+}
+
+/// Physical block backing `logical`, or `None` for a hole.
+pub fn map_logical(runs: &[(u64, u64, u32)], logical: u64) -> Option<u64> {
+    runs.iter()
+        .find(|(l, _, c)| logical >= *l && logical < *l + *c as u64)
+        .map(|(l, p, _)| p + (logical - l))
+}
@@ -1,7 +1,7 @@
 //! Partition layout:
 //!   0..1  dual superblocks
 //!   2..   log region (`LOG_SEGMENT_BLOCKS` × `log_segment_count`)
-//!   then  bitmap, then data.
+//!   then  bitmap, then (encrypted volumes only) the tag region, then data.
 
 use crate::error::HelixError;
 use crate::log::recovery::write_superblock;
@@ -18,6 +18,20 @@ pub fn format_helix<B: BlockIo>(
     device_block_size: u32,
     label: &str,
     uuid: [u8; 16],
+) -> Result<HelixSuperblock, HelixError> {
+    let mut sb = plan_layout(total_sectors, device_block_size, label, uuid, false)?;
+    write_layout(block_io, partition_lba_start, device_block_size, &mut sb)?;
+    Ok(sb)
+}
+
+/// The superblock of a fresh volume. `encrypted` reserves the tag region
+/// between the bitmap and the data area; the caller fills in the key.
+pub(crate) fn plan_layout(
+    total_sectors: u64,
+    device_block_size: u32,
+    label: &str,
+    uuid: [u8; 16],
+    encrypted: bool,
 ) -> Result<HelixSuperblock, HelixError> {
     let sector_scale = BLOCK_SIZE / device_block_size;
     let total_fs_blocks = total_sectors / sector_scale as u64;
@@ -40,7 +54,13 @@ pub fn format_helix<B: BlockIo>(
     let bitmap_blocks = data_blocks_approx.div_ceil(32768);
 
     let bitmap_start = log_end + 1;
-    let data_start = bitmap_start + bitmap_blocks;
+    let tag_start = bitmap_start + bitmap_blocks;
+    let tag_blocks = if encrypted {
+        crate::crypt::tag_blocks_for(total_fs_blocks)
+    } else {
+        0
+    };
+    let data_start = tag_start + tag_blocks;
     let data_block_count = total_fs_blocks.saturating_sub(data_start);
 
     if data_block_count == 0 {
@@ -60,7 +80,7 @@ pub fn format_helix<B: BlockIo>(
     sb.data_start_block = data_start;
     sb.data_block_count = data_block_count;
     sb.index_root_block = BLOCK_NULL;
-    sb.index_depth = 0;
+    sb.index_region_blocks = 0;
     sb.committed_lsn = 0;
     sb.checkpoint_lsn = 0;
     sb.log_head_segment = 0;
@@ -74,13 +94,29 @@ pub fn format_helix<B: BlockIo>(
 
     sb.snapshot_count = 0;
     sb.snapshot_table_block = BLOCK_NULL;
-    sb.blocks_used = superblock_blocks + log_blocks + bitmap_blocks;
+    sb.history_block = BLOCK_NULL;
+    sb.quota_block = BLOCK_NULL;
+    sb.blocks_used = superblock_blocks + log_blocks + bitmap_blocks + tag_blocks;
     sb.file_count = 0;
     sb.dir_count = 1; // root
     sb.created_ns = 0; // caller sets real timestamp
     sb.last_mount_ns = 0;
     sb.mount_count = 0;
 
+    if encrypted {
+        sb.crypt.tag_start = tag_start;
+        sb.crypt.tag_blocks = tag_blocks;
+    }
+    Ok(sb)
+}
+
+/// Write the first log segment header, a zeroed bitmap and both superblocks.
+pub(crate) fn write_layout<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    sb: &mut HelixSuperblock,
+) -> Result<(), HelixError> {
     let seg_header = LogSegmentHeader {
         magic: LOG_SEGMENT_MAGIC,
         _pad_magic: 0,
@@ -93,7 +129,6 @@ pub fn format_helix<B: BlockIo>(
         _reserved: [0u8; 20],
     };
 
-    let seg_block_lba = partition_lba_start + log_start * sector_scale as u64;
     let scale = (BLOCK_SIZE / device_block_size) as u64;
     let mut seg_buf = vec![0u8; BLOCK_SIZE as usize];
     let hdr_bytes = unsafe {
@@ -104,32 +139,29 @@ pub fn format_helix<B: BlockIo>(
     };
     seg_buf[..hdr_bytes.len()].copy_from_slice(hdr_bytes);
 
-    for j in 0..scale {
-        let lba = Lba(seg_block_lba + j);
-        let start = (j as usize) * device_block_size as usize;
-        let end = start + device_block_size as usize;
+    // Whole blocks: an encrypting device seals each block as one unit.
+    block_io
+        .write_blocks(
+            Lba(partition_lba_start + sb.log_start_block * scale),
+            &seg_buf,
+        )
+        .map_err(|_| HelixError::IoWriteFailed)?;
+
+    let zero_block = vec![0u8; BLOCK_SIZE as usize];
+    for blk in 0..sb.bitmap_blocks {
+        let abs_lba = partition_lba_start + (sb.bitmap_start + blk) * scale;
         block_io
-            .write_blocks(lba, &seg_buf[start..end])
+            .write_blocks(Lba(abs_lba), &zero_block)
             .map_err(|_| HelixError::IoWriteFailed)?;
     }
 
-    let zero_block = vec![0u8; device_block_size as usize];
-    for blk in 0..bitmap_blocks {
-        let abs_lba = partition_lba_start + (bitmap_start + blk) * scale;
-        for j in 0..scale {
-            block_io
-                .write_blocks(Lba(abs_lba + j), &zero_block)
-                .map_err(|_| HelixError::IoWriteFailed)?;
-        }
-    }
-
     sb.update_crc();
-    write_superblock(block_io, partition_lba_start, device_block_size, &mut sb, 0)?;
-    write_superblock(block_io, partition_lba_start, device_block_size, &mut sb, 1)?;
+    write_superblock(block_io, partition_lba_start, device_block_size, sb, 0)?;
+    write_superblock(block_io, partition_lba_start, device_block_size, sb, 1)?;
 
     block_io.flush().map_err(|_| HelixError::IoFlushFailed)?;
 
-    Ok(sb)
+    Ok(())
 }
 
 /// Quick HelixFS check via superblock A only.
@@ -0,0 +1,938 @@
+//! Consistency checker.
+//!
+//! `check` examines an unmounted volume, `HelixFs::fsck` a mounted one. Both
+//! verify both superblock copies, every record CRC in the log ring, every
+//! node and `IndexEntry.crc32c` of the checkpoint tree, the snapshot table and each
+//! snapshot's index, the version history, and every extent node and attribute
+//! block. Then every block the live namespace, the retained versions, the
+//! snapshots and the metadata claim is claimed
+//! once: a block claimed by two different owners is double-allocated, unless
+//! both are versions of one file, which range writes leave sharing it. The
+//! allocation map is cross-checked against the claims — allocated but
+//! unclaimed blocks are orphans, claimed but free ones would be handed out
+//! again — and every live entry must have its parent directory. Every
+//! hard-linked name must have its link record, and every record the count of
+//! names that refer to it.
+//!
+//! With `repair`, what cannot be trusted is dropped and a fresh checkpoint
+//! written: unreadable index entries, records behind a bad log record and
+//! snapshots with an unreadable index are lost, and so is the history behind a
+//! corrupt history region; live files and retained versions with corrupt or
+//! cross-linked extents are removed; corrupt attributes are cleared; missing
+//! parent directories are recreated; names without a record are removed and
+//! link counts reset to the names found, a record no name refers to going
+//! too; the allocation map is recomputed.
+//! Problems only a snapshot sees are reported and left alone — snapshots are
+//! immutable. Repairs log with timestamp 0, as the host tools do.
+
+use crate::bitmap::BlockBitmap;
+use crate::engine::HelixFs;
+use crate::error::HelixError;
+use crate::index::btree::{self, NamespaceIndex};
+use crate::log::recovery::{read_superblock, recover_superblock, replay_log_until};
+use crate::ops;
+use crate::types::*;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use gpt_disk_io::BlockIo;
+
+/// What claims a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    /// A live entry.
+    Live(String),
+    /// An entry only the snapshot at `lsn` still sees.
+    Snapshot {
+        lsn: Lsn,
+        path: String,
+    },
+    /// The retained version of `path` logged at `lsn`.
+    Version {
+        lsn: Lsn,
+        path: String,
+    },
+    /// The checkpoint index region.
+    Checkpoint,
+    SnapshotTable,
+    /// The index region of the snapshot at this LSN.
+    SnapshotIndex(Lsn),
+    /// The version history region.
+    History,
+    /// The quota region.
+    Quotas,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// Superblock copy `slot` (0 = A, 1 = B) fails its magic or CRC.
+    Superblock { slot: u64 },
+    /// A record in the log ring fails its CRC; the rest of its segment is lost.
+    LogRecord { segment: u64, offset: u32 },
+    /// An entry in the checkpoint index region fails its CRC.
+    IndexEntry { block: BlockAddr, slot: usize },
+    /// A node of the checkpoint tree fails its CRC or structure checks.
+    IndexNode { block: BlockAddr },
+    /// The snapshot table fails its CRC; every snapshot is lost.
+    SnapshotTable,
+    /// The namespace of the snapshot at this LSN cannot be read.
+    SnapshotIndex { lsn: Lsn },
+    /// The version history region fails its CRC; the versions it held are lost.
+    History,
+    /// The quota region fails its CRC; the quotas are counted afresh.
+    Quotas,
+    /// An extent node fails its CRC, or a run leaves the data region.
+    Extent { owner: Owner },
+    /// An attribute block fails its CRC or lies outside the data region.
+    Attributes { owner: Owner },
+    /// A live entry whose parent directory does not exist.
+    Dangling { path: String },
+    /// A hard-linked name whose link record does not exist.
+    BrokenLink { path: String },
+    /// A link record whose `link_count` disagrees with the names found.
+    LinkCount {
+        path: String,
+        names: u32,
+        recorded: u32,
+    },
+    /// `block` was already claimed when `owner` claimed it.
+    DoubleAllocated { block: BlockAddr, owner: Owner },
+    /// Allocated but claimed by nothing: leaked.
+    Orphaned { start: BlockAddr, count: u64 },
+    /// Claimed but free: the next allocation would overwrite it.
+    Unallocated { start: BlockAddr, count: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub problem: Problem,
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FsckReport {
+    pub findings: Vec<Finding>,
+    /// Live entries examined.
+    pub entries: u64,
+    /// Valid records in the log ring.
+    pub log_records: u64,
+    /// Blocks claimed by live data, snapshots and metadata.
+    pub blocks_claimed: u64,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// Problems still present.
+    pub fn unrepaired(&self) -> impl Iterator<Item = &Problem> {
+        self.findings
+            .iter()
+            .filter(|f| !f.repaired)
+            .map(|f| &f.problem)
+    }
+
+    fn push(&mut self, problem: Problem) {
+        self.findings.push(Finding {
+            problem,
+            repaired: false,
+        });
+    }
+}
+
+impl fmt::Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Owner::Live(path) => write!(f, "{}", path),
+            Owner::Snapshot { lsn, path } => write!(f, "{} (snapshot @{})", path, lsn),
+            Owner::Version { lsn, path } => write!(f, "{} (version @{})", path, lsn),
+            Owner::Checkpoint => write!(f, "checkpoint index"),
+            Owner::SnapshotTable => write!(f, "snapshot table"),
+            Owner::SnapshotIndex(lsn) => write!(f, "index of snapshot @{}", lsn),
+            Owner::History => write!(f, "version history"),
+            Owner::Quotas => write!(f, "quota region"),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Superblock { slot } => write!(f, "superblock copy {} invalid", slot),
+            Problem::LogRecord { segment, offset } => {
+                write!(
+                    f,
+                    "log record CRC mismatch (segment {}, offset {})",
+                    segment, offset
+                )
+            },
+            Problem::IndexEntry { block, slot } => {
+                write!(
+                    f,
+                    "checkpoint entry CRC mismatch (block {}, slot {})",
+                    block, slot
+                )
+            },
+            Problem::IndexNode { block } => write!(f, "checkpoint index node {} corrupt", block),
+            Problem::SnapshotTable => write!(f, "snapshot table CRC mismatch"),
+            Problem::SnapshotIndex { lsn } => write!(f, "snapshot @{} unreadable", lsn),
+            Problem::History => write!(f, "version history CRC mismatch"),
+            Problem::Quotas => write!(f, "quota region CRC mismatch"),
+            Problem::Extent { owner } => write!(f, "{}: corrupt extents", owner),
+            Problem::Attributes { owner } => write!(f, "{}: corrupt attributes", owner),
+            Problem::Dangling { path } => write!(f, "{}: parent directory missing", path),
+            Problem::BrokenLink { path } => write!(f, "{}: link record missing", path),
+            Problem::LinkCount {
+                path,
+                names,
+                recorded,
+            } => write!(f, "{}: {} names, link count {}", path, names, recorded),
+            Problem::DoubleAllocated { block, owner } => {
+                write!(f, "{}: block {} already in use", owner, block)
+            },
+            Problem::Orphaned { start, count } => {
+                write!(
+                    f,
+                    "blocks {}..{} allocated but unused",
+                    start,
+                    start + count
+                )
+            },
+            Problem::Unallocated { start, count } => {
+                write!(f, "blocks {}..{} in use but free", start, start + count)
+            },
+        }
+    }
+}
+
+/// Check the unmounted volume at `lba_start`. With `repair`, fix what can be
+/// fixed and leave it mountable. `Err` only when there is no volume to check
+/// (no valid superblock, another version) or the device fails.
+pub fn check<B: BlockIo>(
+    block_io: &mut B,
+    lba_start: u64,
+    block_size: u32,
+    repair: bool,
+) -> Result<FsckReport, HelixError> {
+    let mut report = FsckReport::default();
+    let sb = recover_superblock(block_io, lba_start, block_size)?;
+    if sb.version != HELIX_VERSION {
+        return Err(HelixError::IncompatibleVersion);
+    }
+    let mut fs = HelixFs::from_superblock(sb, lba_start, block_size);
+    fs.log.reload_head_segment(block_io)?;
+    check_superblocks(block_io, lba_start, block_size, &mut report)?;
+    check_log(&fs, block_io, &mut report)?;
+
+    let mut table_dirty = false;
+    let table = match ops::snapshot::load_table(block_io, lba_start, block_size, &fs.sb) {
+        Ok(table) => table,
+        Err(HelixError::IndexCrcMismatch) => {
+            report.push(Problem::SnapshotTable);
+            table_dirty = true;
+            Vec::new()
+        },
+        Err(e) => return Err(e),
+    };
+    fs.set_snapshots(table);
+    match fs.load_history(block_io) {
+        Ok(()) => {},
+        Err(HelixError::IndexCrcMismatch) => {
+            report.push(Problem::History);
+            fs.history_dirty = true;
+        },
+        Err(e) => return Err(e),
+    }
+
+    check_quota_region(&fs, block_io, &mut report)?;
+
+    fs.index = salvage_namespace(&fs, block_io, &mut report)?;
+    // Whatever salvage dropped drops out of the figures too.
+    fs.recount_quotas(block_io)?;
+    table_dirty |= drop_unreadable_snapshots(&mut fs, block_io, &mut report);
+    fs.rebuild_bitmap_from_index(block_io)?;
+    fs.pin_snapshot_blocks(block_io)?;
+    fs.pin_history_blocks(block_io)?;
+    fs.recount_shares(block_io)?;
+
+    check_namespaces(&fs, block_io, &mut report)?;
+    if repair && !report.is_clean() {
+        fs.repair(block_io, &mut report, table_dirty)?;
+    }
+    Ok(report)
+}
+
+impl HelixFs {
+    /// `fsck::check` on this mounted volume, cross-checking the in-memory
+    /// allocation map too. Repair needs a writable instance; refused while a
+    /// transaction is open.
+    pub fn fsck<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        repair: bool,
+    ) -> Result<FsckReport, HelixError> {
+        if repair {
+            self.check_writable()?;
+        }
+        if self.active_tx().is_some() {
+            return Err(HelixError::TxConflict);
+        }
+        let mut report = FsckReport::default();
+        check_superblocks(
+            block_io,
+            self.partition_lba_start,
+            self.device_block_size,
+            &mut report,
+        )?;
+        check_log(self, block_io, &mut report)?;
+        let findings = report.findings.len();
+        check_checkpoint(self, block_io, &mut NamespaceIndex::new(), &mut report)?;
+        if report.findings.len() > findings {
+            // The live index pages the tree in and would trip over the
+            // damage: go on with what salvage recovers, as `check` does.
+            self.index = salvage_namespace(self, block_io, &mut FsckReport::default())?;
+            self.recount_quotas(block_io)?;
+        }
+        // What was read at mount is in memory; a checkpoint rewrites it.
+        check_quota_region(self, block_io, &mut report)?;
+        let table_dirty = drop_unreadable_snapshots(self, block_io, &mut report);
+        match ops::retain::load_region(
+            block_io,
+            self.partition_lba_start,
+            self.device_block_size,
+            &self.sb,
+        ) {
+            Ok(_) => {},
+            Err(HelixError::IndexCrcMismatch) => {
+                // What was read at mount is still in memory; rewrite it.
+                report.push(Problem::History);
+                self.history_dirty = true;
+            },
+            Err(e) => return Err(e),
+        }
+
+        check_namespaces(self, block_io, &mut report)?;
+        if repair && !report.is_clean() {
+            self.repair(block_io, &mut report, table_dirty)?;
+        }
+        Ok(report)
+    }
+
+    /// Fix every finding not confined to a snapshot, then recompute the
+    /// allocation map and checkpoint, which rewrites both superblocks, the
+    /// index region and the ring.
+    fn repair<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        report: &mut FsckReport,
+        table_dirty: bool,
+    ) -> Result<(), HelixError> {
+        let mut fixed = vec![false; report.findings.len()];
+        for (i, finding) in report.findings.iter().enumerate() {
+            fixed[i] = match &finding.problem {
+                Problem::Extent {
+                    owner: Owner::Live(path),
+                }
+                | Problem::DoubleAllocated {
+                    owner: Owner::Live(path),
+                    ..
+                } => {
+                    self.drop_entry(block_io, path)?;
+                    true
+                },
+                Problem::Attributes {
+                    owner: Owner::Live(path),
+                } => {
+                    ops::xattr::store(
+                        block_io,
+                        &mut self.log,
+                        &mut self.index,
+                        &mut self.bitmap,
+                        self.partition_lba_start,
+                        self.sb.data_start_block,
+                        self.device_block_size,
+                        path,
+                        &ops::xattr::Xattrs::new(),
+                        0,
+                    )?;
+                    true
+                },
+                Problem::Dangling { path } => {
+                    self.adopt(block_io, path)?;
+                    true
+                },
+                Problem::BrokenLink { path } | Problem::LinkCount { path, names: 0, .. } => {
+                    self.drop_entry(block_io, path)?;
+                    true
+                },
+                Problem::Extent {
+                    owner: Owner::Version { lsn, path },
+                }
+                | Problem::DoubleAllocated {
+                    owner: Owner::Version { lsn, path },
+                    ..
+                } => {
+                    // Like `drop_entry`: nothing is freed, the checkpoint
+                    // below rewrites the history.
+                    self.history
+                        .retain(|v| v.lsn != *lsn || ops::retain::path_of(v) != path);
+                    self.history_dirty = true;
+                    true
+                },
+                Problem::LinkCount { path, names, .. } => {
+                    // In the checkpoint below.
+                    if let Some(mut record) = self.index.lookup(block_io, path)? {
+                        record.link_count = *names;
+                        self.index.upsert(record);
+                    }
+                    true
+                },
+                Problem::Extent { .. }
+                | Problem::Attributes { .. }
+                | Problem::DoubleAllocated { .. } => false,
+                _ => true,
+            };
+        }
+
+        // The map first: the table and checkpoint below allocate from it.
+        self.reclaim_unpinned(block_io)?;
+        if table_dirty {
+            let table = self.snapshots.clone();
+            self.store_snapshot_table(block_io, &table)?;
+        }
+        self.checkpoint(block_io)?;
+        // Everything the ring held is in the checkpoint now; drop a corrupt
+        // record left in the head segment before anything lands behind it.
+        if report
+            .findings
+            .iter()
+            .any(|f| matches!(f.problem, Problem::LogRecord { .. }))
+        {
+            self.log.clear_head_segment();
+            self.sync(block_io)?;
+        }
+        // The old table block and whatever dropped snapshots held.
+        self.reclaim_unpinned(block_io)?;
+
+        for (finding, fixed) in report.findings.iter_mut().zip(fixed) {
+            finding.repaired = fixed;
+        }
+        Ok(())
+    }
+
+    /// Remove a live entry without freeing anything: its blocks cannot be
+    /// trusted. The map rebuild releases what nothing else claims.
+    fn drop_entry<B: BlockIo>(&mut self, block_io: &mut B, path: &str) -> Result<(), HelixError> {
+        match ops::dir::unlink(
+            block_io,
+            &mut self.log,
+            &mut self.index,
+            &mut self.bitmap,
+            &mut self.dedup,
+            self.partition_lba_start,
+            self.sb.data_start_block,
+            self.device_block_size,
+            path,
+            false,
+            0,
+        ) {
+            // Already dropped for another finding.
+            Ok(_) | Err(HelixError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Recreate the missing parent of `path`; a file holding the parent's
+    /// name cannot be turned into a directory, so then `path` goes instead.
+    fn adopt<B: BlockIo>(&mut self, block_io: &mut B, path: &str) -> Result<(), HelixError> {
+        let parent = btree::parent_path(path);
+        if live_dir(block_io, &self.index, parent)? {
+            return Ok(());
+        }
+        match ops::dir::mkdir(block_io, &mut self.log, &mut self.index, parent, 0) {
+            Ok(_) => Ok(()),
+            Err(HelixError::AlreadyExists) => self.drop_entry(block_io, path),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn live_dir<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    path: &str,
+) -> Result<bool, HelixError> {
+    Ok(index
+        .lookup(block_io, path)?
+        .is_some_and(|e| e.flags & entry_flags::IS_DIR != 0))
+}
+
+fn check_superblocks<B: BlockIo>(
+    block_io: &mut B,
+    lba_start: u64,
+    block_size: u32,
+    report: &mut FsckReport,
+) -> Result<(), HelixError> {
+    for slot in 0..2 {
+        if !read_superblock(block_io, lba_start, block_size, slot)?.is_valid() {
+            report.push(Problem::Superblock { slot });
+        }
+    }
+    Ok(())
+}
+
+fn check_log<B: BlockIo>(
+    fs: &HelixFs,
+    block_io: &mut B,
+    report: &mut FsckReport,
+) -> Result<(), HelixError> {
+    let (records, corrupt) = fs.log.verify_ring(block_io)?;
+    report.log_records = records;
+    for (segment, offset) in corrupt {
+        report.push(Problem::LogRecord { segment, offset });
+    }
+    Ok(())
+}
+
+/// The quota region only holds figures a recount rebuilds; report it if
+/// unreadable so the repair checkpoint rewrites it.
+fn check_quota_region<B: BlockIo>(
+    fs: &HelixFs,
+    block_io: &mut B,
+    report: &mut FsckReport,
+) -> Result<(), HelixError> {
+    match ops::quota::load_region(
+        block_io,
+        fs.partition_lba_start,
+        fs.device_block_size,
+        &fs.sb,
+    ) {
+        Ok(_) => Ok(()),
+        Err(HelixError::IndexCrcMismatch) => {
+            report.push(Problem::Quotas);
+            Ok(())
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Load the checkpoint region into `index`, skipping (and reporting) entries
+/// that fail their CRC and nodes that fail their checks.
+fn check_checkpoint<B: BlockIo>(
+    fs: &HelixFs,
+    block_io: &mut B,
+    index: &mut NamespaceIndex,
+    report: &mut FsckReport,
+) -> Result<(), HelixError> {
+    if fs.sb.index_root_block == BLOCK_NULL {
+        return Ok(());
+    }
+    let bad = crate::checkpoint::salvage_index_region(
+        block_io,
+        fs.partition_lba_start,
+        fs.sb.data_start_block,
+        fs.device_block_size,
+        fs.sb.index_root_block,
+        fs.sb.index_region_blocks as u64,
+        fs.sb.index_entry_count as u64,
+        index,
+    )?;
+    for (block, slot) in bad {
+        report.push(match slot {
+            Some(slot) => Problem::IndexEntry { block, slot },
+            None => Problem::IndexNode { block },
+        });
+    }
+    Ok(())
+}
+
+/// Mount the namespace without bailing out on the first bad entry: what the
+/// checkpoint tree still holds, then the log.
+fn salvage_namespace<B: BlockIo>(
+    fs: &HelixFs,
+    block_io: &mut B,
+    report: &mut FsckReport,
+) -> Result<NamespaceIndex, HelixError> {
+    let mut index = NamespaceIndex::new();
+    check_checkpoint(fs, block_io, &mut index, report)?;
+    let mut markers = Vec::new();
+    replay_log_until(
+        block_io,
+        &fs.log,
+        &mut index,
+        &mut markers,
+        fs.sb.checkpoint_lsn,
+        Lsn::MAX,
+    )?;
+    Ok(index)
+}
+
+/// Drop from the table every snapshot whose namespace cannot be read; true if
+/// any was.
+fn drop_unreadable_snapshots<B: BlockIo>(
+    fs: &mut HelixFs,
+    block_io: &mut B,
+    report: &mut FsckReport,
+) -> bool {
+    let mut table = fs.snapshots.clone();
+    table.retain(|entry| {
+        let readable = fs
+            .snapshot_namespace(block_io, entry)
+            .and_then(|index| index.entries(block_io))
+            .is_ok();
+        if !readable {
+            report.push(Problem::SnapshotIndex { lsn: entry.lsn });
+        }
+        readable
+    });
+    let dropped = table.len() != fs.snapshots.len();
+    if dropped {
+        fs.set_snapshots(table);
+    }
+    dropped
+}
+
+/// Every block claimed so far, and who claimed each extent and attribute block.
+struct Claims {
+    claimed: BlockBitmap,
+    /// `(extent_root, is_node)` -> blocks claimed for it; a contiguous extent
+    /// seen again at a larger size claims the difference.
+    extents: BTreeMap<(BlockAddr, bool), u64>,
+    xattrs: BTreeSet<BlockAddr>,
+    /// A file's `first_lsn` -> the runs its versions have claimed so far.
+    files: BTreeMap<Lsn, Vec<(BlockAddr, u64)>>,
+}
+
+impl Claims {
+    /// Claim `[start, start + count)`; returns the first block already
+    /// claimed, if any. Blocks past the data region are skipped.
+    fn claim(&mut self, start: BlockAddr, count: u64) -> Option<BlockAddr> {
+        let mut conflict = None;
+        for block in start..start.saturating_add(count) {
+            if block >= self.claimed.total_blocks() {
+                break;
+            }
+            if self.claimed.is_allocated(block) {
+                conflict.get_or_insert(block);
+            }
+            self.claimed.mark_block_used(block);
+        }
+        conflict
+    }
+
+    /// `claim` for a version of the file first logged at `file`: unless it
+    /// is `live`, blocks another version of it claimed are shared, not
+    /// double-allocated. Two live entries never share this way.
+    fn claim_file(
+        &mut self,
+        start: BlockAddr,
+        count: u64,
+        file: Lsn,
+        live: bool,
+    ) -> Option<BlockAddr> {
+        let kin = self.files.entry(file).or_default();
+        let mut conflict = None;
+        for block in start..start.saturating_add(count) {
+            if block >= self.claimed.total_blocks() {
+                break;
+            }
+            if self.claimed.is_allocated(block)
+                && (live || !kin.iter().any(|&(s, c)| (s..s + c).contains(&block)))
+            {
+                conflict.get_or_insert(block);
+            }
+            self.claimed.mark_block_used(block);
+        }
+        kin.push((start, count));
+        conflict
+    }
+
+    fn in_range(&self, start: BlockAddr, count: u64) -> bool {
+        start
+            .checked_add(count)
+            .is_some_and(|end| end <= self.claimed.total_blocks())
+    }
+}
+
+fn check_namespaces<B: BlockIo>(
+    fs: &HelixFs,
+    block_io: &mut B,
+    report: &mut FsckReport,
+) -> Result<(), HelixError> {
+    let mut claims = Claims {
+        claimed: BlockBitmap::new(fs.bitmap.total_blocks()),
+        extents: BTreeMap::new(),
+        xattrs: BTreeSet::new(),
+        files: BTreeMap::new(),
+    };
+    let mut claim_region = |claims: &mut Claims, start, count, owner: Owner| {
+        if let Some(block) = claims.claim(start, count) {
+            report.push(Problem::DoubleAllocated { block, owner });
+        }
+    };
+
+    // Metadata first, then the live namespace, then what only snapshots see.
+    if fs.sb.index_root_block != BLOCK_NULL {
+        claim_region(
+            &mut claims,
+            fs.sb.index_root_block,
+            fs.sb.index_region_blocks as u64,
+            Owner::Checkpoint,
+        );
+    }
+    if fs.sb.snapshot_table_block != BLOCK_NULL {
+        claim_region(
+            &mut claims,
+            fs.sb.snapshot_table_block,
+            1,
+            Owner::SnapshotTable,
+        );
+    }
+    for entry in fs.snapshots.iter().filter(|e| e.index_root != BLOCK_NULL) {
+        claim_region(
+            &mut claims,
+            entry.index_root,
+            entry.index_blocks as u64,
+            Owner::SnapshotIndex(entry.lsn),
+        );
+    }
+    if fs.sb.history_count != 0 && fs.sb.history_block != BLOCK_NULL {
+        claim_region(
+            &mut claims,
+            fs.sb.history_block,
+            fs.sb.history_blocks as u64,
+            Owner::History,
+        );
+    }
+    if fs.sb.quota_count != 0 && fs.sb.quota_block != BLOCK_NULL {
+        claim_region(
+            &mut claims,
+            fs.sb.quota_block,
+            fs.sb.quota_blocks as u64,
+            Owner::Quotas,
+        );
+    }
+
+    let live = sorted_live(block_io, &fs.index)?;
+    report.entries = live.len() as u64;
+    let mut names: BTreeMap<u64, u32> = BTreeMap::new();
+    for e in &live {
+        let path = btree::path_str(&e.path);
+        if e.flags & entry_flags::IS_HARDLINK != 0 {
+            if ops::link::resolve(block_io, &fs.index, *e).is_ok() {
+                *names.entry(e.link_id).or_insert(0) += 1;
+            } else {
+                report.push(Problem::BrokenLink {
+                    path: String::from(path),
+                });
+            }
+        }
+        claim_entry(
+            fs,
+            block_io,
+            &mut claims,
+            e,
+            Owner::Live(String::from(path)),
+            report,
+        );
+        let parent = btree::parent_path(path);
+        if parent != "/" && !live_dir(block_io, &fs.index, parent)? {
+            report.push(Problem::Dangling {
+                path: String::from(path),
+            });
+        }
+    }
+    for e in &live {
+        let path = btree::path_str(&e.path);
+        if !ops::link::is_record(path) || e.flags & entry_flags::IS_DIR != 0 {
+            continue;
+        }
+        let id = u64::from_str_radix(&path[ops::link::LINK_DIR.len()..], 16).unwrap_or(0);
+        let found = names.get(&id).copied().unwrap_or(0);
+        if found != e.link_count {
+            report.push(Problem::LinkCount {
+                path: String::from(path),
+                names: found,
+                recorded: e.link_count,
+            });
+        }
+    }
+    for v in &fs.history {
+        let owner = Owner::Version {
+            lsn: v.lsn,
+            path: String::from(ops::retain::path_of(v)),
+        };
+        claim_entry(
+            fs,
+            block_io,
+            &mut claims,
+            &ops::retain::as_entry(v),
+            owner,
+            report,
+        );
+    }
+    for snap in &fs.snapshots {
+        let Ok(index) = fs.snapshot_namespace(block_io, snap) else {
+            continue;
+        };
+        for e in sorted_live(block_io, &index)? {
+            let owner = Owner::Snapshot {
+                lsn: snap.lsn,
+                path: String::from(btree::path_str(&e.path)),
+            };
+            claim_entry(fs, block_io, &mut claims, &e, owner, report);
+        }
+    }
+    report.blocks_claimed = claims.claimed.allocated_count();
+
+    // Coalesce map/claim disagreements into runs.
+    let mut run: Option<(bool, BlockAddr, u64)> = None;
+    let flush = |run: Option<(bool, BlockAddr, u64)>, report: &mut FsckReport| {
+        if let Some((orphan, start, count)) = run {
+            report.push(if orphan {
+                Problem::Orphaned { start, count }
+            } else {
+                Problem::Unallocated { start, count }
+            });
+        }
+    };
+    for block in 0..fs.bitmap.total_blocks() {
+        let allocated = fs.bitmap.is_allocated(block);
+        let state = (allocated != claims.claimed.is_allocated(block)).then_some(allocated);
+        match (state, run) {
+            (Some(orphan), Some((o, start, count))) if o == orphan && start + count == block => {
+                run = Some((o, start, count + 1));
+            },
+            (state, _) => {
+                flush(run.take(), report);
+                run = state.map(|orphan| (orphan, block, 1));
+            },
+        }
+    }
+    flush(run, report);
+    Ok(())
+}
+
+fn sorted_live<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+) -> Result<Vec<IndexEntry>, HelixError> {
+    let mut live = index.entries(block_io)?;
+    live.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+    Ok(live)
+}
+
+fn frame_decodes<B: BlockIo>(fs: &HelixFs, block_io: &mut B, e: &IndexEntry) -> bool {
+    crate::ops::read::read_compressed(
+        block_io,
+        fs.partition_lba_start,
+        fs.sb.data_start_block,
+        fs.device_block_size,
+        e.extent_root,
+        e.size,
+        e.stored_len,
+    )
+    .is_ok_and(|data| e.content_crc64 == 0 || crate::crc::crc64(&data) == e.content_crc64)
+}
+
+/// Claim an entry's extent and attribute block, once per distinct extent or
+/// block: entries sharing storage (dedup, a snapshot's unchanged files) share
+/// one claim.
+fn claim_entry<B: BlockIo>(
+    fs: &HelixFs,
+    block_io: &mut B,
+    claims: &mut Claims,
+    e: &IndexEntry,
+    owner: Owner,
+    report: &mut FsckReport,
+) {
+    if let Some((root, size, is_node)) = crate::dedup::extent_of(e) {
+        let done = claims.extents.get(&(root, is_node)).copied();
+        let runs = match (is_node, done) {
+            (true, Some(_)) => Ok(Vec::new()),
+            (true, None) => {
+                // The node block is claimed even when unreadable, as mount marks it.
+                let node = crate::extent::read_extent_node(
+                    block_io,
+                    fs.partition_lba_start,
+                    fs.sb.data_start_block,
+                    fs.device_block_size,
+                    root,
+                );
+                let mut runs = vec![(root, 1)];
+                match node {
+                    Ok(extents)
+                        if extents
+                            .iter()
+                            .all(|&(_, p, c)| c > 0 && claims.in_range(p, c as u64)) =>
+                    {
+                        runs.extend(extents.iter().map(|&(_, p, c)| (p, c as u64)));
+                        Ok(runs)
+                    },
+                    _ => Err(runs),
+                }
+            },
+            (false, done) => {
+                let need = size.div_ceil(BLOCK_SIZE as u64);
+                let done = done.unwrap_or(0);
+                if !claims.in_range(root, need) {
+                    Err(Vec::new())
+                } else if need > done {
+                    Ok(vec![(root + done, need - done)])
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+        };
+        let (runs, corrupt) = match runs {
+            Ok(runs) => (runs, false),
+            Err(runs) => (runs, true),
+        };
+        // A compressed frame is checked once, through a full decode.
+        let corrupt = corrupt
+            || (e.flags & entry_flags::IS_COMPRESSED != 0
+                && done.is_none()
+                && !frame_decodes(fs, block_io, e));
+        let claimed: u64 = runs.iter().map(|&(_, c)| c).sum();
+        *claims.extents.entry((root, is_node)).or_insert(0) += claimed;
+        let live = matches!(owner, Owner::Live(_));
+        if corrupt {
+            report.push(Problem::Extent {
+                owner: owner.clone(),
+            });
+        }
+        if let Some(block) = runs
+            .iter()
+            .find_map(|&(s, c)| claims.claim_file(s, c, e.first_lsn, live))
+        {
+            report.push(Problem::DoubleAllocated {
+                block,
+                owner: owner.clone(),
+            });
+        }
+    }
+
+    if e.xattr_len != 0 && claims.xattrs.insert(e.xattr_block) {
+        let readable = claims.in_range(e.xattr_block, 1)
+            && ops::xattr::load_block(
+                block_io,
+                fs.partition_lba_start,
+                fs.sb.data_start_block,
+                fs.device_block_size,
+                e.xattr_block,
+                e.xattr_len,
+            )
+            .is_ok();
+        if !readable {
+            report.push(Problem::Attributes {
+                owner: owner.clone(),
+            });
+        }
+        if let Some(block) = claims.claim(e.xattr_block, 1) {
+            report.push(Problem::DoubleAllocated { block, owner });
+        }
+    }
+}
@@ -1,16 +1,69 @@
-//! In-memory namespace index. Sorted `Vec<IndexEntry>`; on-disk serialization via checkpoint.
+//! Namespace index: the B+tree written by the last checkpoint, read lazily
+//! through `checkpoint::IndexRegion`, under an in-memory overlay of every
+//! entry changed since. Lookups and scans merge the two in tree order; a
+//! checkpoint folds the overlay into a fresh tree and starts a new one.
 
+use crate::checkpoint::IndexRegion;
 use crate::crc::fnv1a_64;
 use crate::error::HelixError;
+use crate::ops::quota::{Limit, Quota, Usage};
 use crate::types::*;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::ops::Bound;
+use gpt_disk_io::BlockIo;
 
-const EXPECTED_MAX_ENTRIES: usize = 1_000_000;
+/// High half of a `tree_key`: the parent directory's.
+const PARENT_MASK: u64 = 0xFFFF_FFFF_0000_0000;
+
+/// Tree order: `tree_key`, then the zero-padded path.
+type SortKey = (u64, [u8; 256]);
+
+/// Prior overlay state per touched key; `None` = not in the overlay.
+type UndoJournal = BTreeMap<SortKey, Option<IndexEntry>>;
 
 pub struct NamespaceIndex {
-    /// Sorted by (key, path bytes).
-    entries: Vec<IndexEntry>,
+    /// The last checkpoint's tree; `None` on a volume that has none yet.
+    base: Option<IndexRegion>,
+    /// Entries changed since `base` was written. A removed base entry stays
+    /// as an `IS_DELETED` tombstone until the next checkpoint, so the overlay
+    /// holds at most one entry per record in the log ring.
+    overlay: BTreeMap<SortKey, IndexEntry>,
+    /// Undo journal while a transaction is open.
+    journal: Option<UndoJournal>,
+    /// Directory quotas and their figures (see `ops::quota`).
+    quotas: Vec<Quota>,
+    /// Overlay keys charged without taking off the base entry they may
+    /// shadow; `settle` does that.
+    unsettled: BTreeSet<SortKey>,
+    /// `quotas` and `unsettled` as of `begin_journal`.
+    quota_journal: Option<(Vec<Quota>, BTreeSet<SortKey>)>,
+}
+
+/// An in-order walk over the merged namespace, advanced by
+/// `NamespaceIndex::next_entry`. Holds no borrow, so the device and the index
+/// stay usable between steps.
+pub struct Cursor {
+    /// Entries with a smaller `tree_key` are skipped.
+    from: u64,
+    /// Sort key of the last entry passed; the overlay resumes after it.
+    after: Option<SortKey>,
+    /// The base leaf being walked and the next unconsumed slot in it.
+    leaf: Vec<IndexEntry>,
+    slot: usize,
+    next_leaf: BlockAddr,
+    started: bool,
+    /// Base leaves loaded since the last `take_leaf_reads`.
+    leaf_reads: u64,
+}
+
+impl Cursor {
+    /// Base leaves the walk has loaded since the last call, for callers
+    /// that budget their I/O.
+    pub fn take_leaf_reads(&mut self) -> u64 {
+        core::mem::take(&mut self.leaf_reads)
+    }
 }
 
 impl Default for NamespaceIndex {
@@ -19,211 +72,517 @@ impl Default for NamespaceIndex {
     }
 }
 
+/// Tree order key of `path`: FNV-1a of the parent directory in the high half
+/// and of the name in the low half, so the children of one directory form a
+/// single key range. Collisions are told apart by the full path.
+pub fn tree_key(path: &str) -> u64 {
+    let parent = parent_path(path);
+    let name = path.get(parent.len()..).unwrap_or("");
+    (fnv1a_64(parent.as_bytes()) & PARENT_MASK) | (fnv1a_64(name.as_bytes()) >> 32)
+}
+
+fn sort_key(path: &str) -> SortKey {
+    let mut buf = [0u8; 256];
+    set_path(&mut buf, path.as_bytes());
+    (tree_key(path), buf)
+}
+
+fn entry_key(e: &IndexEntry) -> SortKey {
+    sort_key(path_str(&e.path))
+}
+
+fn is_live(e: &IndexEntry) -> bool {
+    e.flags & entry_flags::IS_DELETED == 0
+}
+
 impl NamespaceIndex {
     pub fn new() -> Self {
         Self {
-            entries: Vec::with_capacity(1024),
+            base: None,
+            overlay: BTreeMap::new(),
+            journal: None,
+            quotas: Vec::new(),
+            unsettled: BTreeSet::new(),
+            quota_journal: None,
         }
     }
 
-    pub fn live_count(&self) -> usize {
-        self.entries
-            .iter()
-            .filter(|e| e.flags & entry_flags::IS_DELETED == 0)
-            .count()
+    /// The namespace held by `base`, nothing changed yet.
+    pub fn with_base(base: IndexRegion) -> Self {
+        Self {
+            base: Some(base),
+            ..Self::new()
+        }
     }
 
-    pub fn total_count(&self) -> usize {
-        self.entries.len()
+    pub fn base(&self) -> Option<&IndexRegion> {
+        self.base.as_ref()
     }
 
-    fn find_pos(&self, key: u64, path: &[u8]) -> Result<usize, usize> {
-        self.entries.binary_search_by(|e| {
-            e.key.cmp(&key).then_with(|| {
-                let e_path = path_bytes(&e.path);
-                e_path.cmp(path)
-            })
-        })
-    }
-
-    pub fn lookup(&self, path: &str) -> Option<&IndexEntry> {
-        let key = fnv1a_64(path.as_bytes());
-        let path_b = path.as_bytes();
-        match self.find_pos(key, path_b) {
-            Ok(idx) => {
-                let entry = &self.entries[idx];
-                if entry.flags & entry_flags::IS_DELETED != 0 {
-                    None
-                } else {
-                    Some(entry)
+    /// Make `base` (a tree holding this whole namespace) the new base and
+    /// empty the overlay. After a checkpoint; never inside a transaction.
+    /// The quota figures must be settled first.
+    pub fn rebase(&mut self, base: IndexRegion) {
+        self.base = Some(base);
+        self.overlay.clear();
+        self.unsettled.clear();
+    }
+
+    /// Entries held in memory.
+    pub fn overlay_len(&self) -> usize {
+        self.overlay.len()
+    }
+
+    /// Entries in the namespace. Looks each overlay entry up in the base, so
+    /// it costs a seek per entry changed since the checkpoint.
+    pub fn live_count<B: BlockIo>(&self, block_io: &mut B) -> Result<u64, HelixError> {
+        let mut n = self.base.as_ref().map_or(0, |b| b.entry_count());
+        for (key, e) in &self.overlay {
+            match (is_live(e), self.base_get(block_io, key)?.is_some()) {
+                (true, false) => n += 1,
+                (false, true) => n = n.saturating_sub(1),
+                _ => {},
+            }
+        }
+        Ok(n)
+    }
+
+    /// Entries stored: the base's plus the overlay's, tombstones included.
+    pub fn stored_len(&self) -> u64 {
+        self.base.as_ref().map_or(0, |b| b.entry_count()) + self.overlay.len() as u64
+    }
+
+    /// Upper bound on live entries, for sizing a region.
+    pub fn entry_bound(&self) -> usize {
+        let base = self.base.as_ref().map_or(0, |b| b.entry_count() as usize);
+        base + self.overlay.values().filter(|e| is_live(e)).count()
+    }
+
+    /// The entry stored under `key`, tombstones included.
+    fn get<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        key: &SortKey,
+    ) -> Result<Option<IndexEntry>, HelixError> {
+        if let Some(e) = self.overlay.get(key) {
+            return Ok(Some(*e));
+        }
+        self.base_get(block_io, key)
+    }
+
+    fn base_get<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        key: &SortKey,
+    ) -> Result<Option<IndexEntry>, HelixError> {
+        let Some(base) = &self.base else {
+            return Ok(None);
+        };
+        let mut block = base.seek(block_io, key.0)?;
+        while block != BLOCK_NULL {
+            for e in base.leaf(block_io, block)? {
+                match entry_key(&e).cmp(key) {
+                    core::cmp::Ordering::Less => {},
+                    core::cmp::Ordering::Equal => return Ok(Some(e)),
+                    core::cmp::Ordering::Greater => return Ok(None),
                 }
-            },
-            Err(_) => None,
+            }
+            block = base.next_leaf(block);
         }
+        Ok(None)
+    }
+
+    pub fn lookup<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Option<IndexEntry>, HelixError> {
+        Ok(self.get(block_io, &sort_key(path))?.filter(is_live))
     }
 
     /// Try path as-is, then toggle trailing `/`. Dirs are stored with trailing `/`.
-    pub fn lookup_flex(&self, path: &str) -> Option<&IndexEntry> {
-        if let Some(entry) = self.lookup(path) {
-            return Some(entry);
+    pub fn lookup_flex<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<Option<IndexEntry>, HelixError> {
+        if let Some(entry) = self.lookup(block_io, path)? {
+            return Ok(Some(entry));
         }
         if !path.ends_with('/') {
             let mut with_slash = String::from(path);
             with_slash.push('/');
-            self.lookup(&with_slash)
+            self.lookup(block_io, &with_slash)
         } else if path.len() > 1 {
-            self.lookup(&path[..path.len() - 1])
+            self.lookup(block_io, &path[..path.len() - 1])
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn lookup_mut(&mut self, path: &str) -> Option<&mut IndexEntry> {
-        let key = fnv1a_64(path.as_bytes());
-        let path_b = path.as_bytes();
-        match self.find_pos(key, path_b) {
-            Ok(idx) => {
-                let entry = &mut self.entries[idx];
-                if entry.flags & entry_flags::IS_DELETED != 0 {
-                    None
-                } else {
-                    Some(entry)
-                }
-            },
-            Err(_) => None,
+    /// Insert or replace; lands in the overlay.
+    pub fn upsert(&mut self, entry: IndexEntry) {
+        let key = entry_key(&entry);
+        self.journal_touch(&key);
+        if !self.quotas.is_empty() {
+            match self.overlay.get(&key) {
+                Some(old) => charge(&mut self.quotas, old, false),
+                None => {
+                    self.unsettled.insert(key);
+                },
+            }
+            charge(&mut self.quotas, &entry, true);
         }
+        self.overlay.insert(key, entry);
     }
 
-    pub fn lookup_flex_mut(&mut self, path: &str) -> Option<&mut IndexEntry> {
-        if self.lookup(path).is_some() {
-            return self.lookup_mut(path);
+    /// Remove `path` (either slash form). A base entry is shadowed by a
+    /// tombstone; one that only the overlay holds is dropped outright.
+    pub fn mark_deleted<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+    ) -> Result<(), HelixError> {
+        let entry = self
+            .lookup_flex(block_io, path)?
+            .ok_or(HelixError::NotFound)?;
+        let key = entry_key(&entry);
+        self.journal_touch(&key);
+        // `entry` is what the key counts for now; a base entry it replaced
+        // is still `settle`'s to take off.
+        charge(&mut self.quotas, &entry, false);
+        if self.base_get(block_io, &key)?.is_some() {
+            let mut tombstone = entry;
+            tombstone.flags |= entry_flags::IS_DELETED;
+            self.overlay.insert(key, tombstone);
+        } else {
+            self.overlay.remove(&key);
         }
-        if !path.ends_with('/') {
-            let mut with_slash = String::from(path);
-            with_slash.push('/');
-            if self.lookup(&with_slash).is_some() {
-                return self.lookup_mut(&with_slash);
+        Ok(())
+    }
+
+    /// Direct children of `dir`. Use `"/"` for root; otherwise must end with
+    /// `/`. Reads only the leaves holding that directory.
+    pub fn readdir<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        dir: &str,
+    ) -> Result<Vec<IndexEntry>, HelixError> {
+        let range = fnv1a_64(dir.as_bytes()) & PARENT_MASK;
+        let mut cursor = self.cursor_from(range);
+        let mut results = Vec::new();
+        while let Some(entry) = self.next_entry(block_io, &mut cursor)? {
+            let path = path_str(&entry.path);
+            if tree_key(path) & PARENT_MASK != range {
+                break;
             }
-        } else if path.len() > 1 {
-            let without = &path[..path.len() - 1];
-            if self.lookup(without).is_some() {
-                return self.lookup_mut(without);
+            if path != dir && parent_path(path) == dir {
+                results.push(entry);
             }
         }
-        None
+        Ok(results)
     }
 
-    /// Look up by key (hash) alone. On collision returns the first live match.
-    pub fn lookup_by_key(&self, key: u64) -> Option<&IndexEntry> {
-        let start = self.entries.partition_point(|e| e.key < key);
-        for entry in &self.entries[start..] {
-            if entry.key != key {
-                break;
+    /// A walk over every live entry, in tree order.
+    pub fn cursor(&self) -> Cursor {
+        self.cursor_from(0)
+    }
+
+    /// A walk over the live entries that sort after `path`, for resuming one
+    /// across calls that may change the index in between.
+    pub fn cursor_after(&self, path: &str) -> Cursor {
+        let key = sort_key(path);
+        let mut cursor = self.cursor_from(key.0);
+        cursor.after = Some(key);
+        cursor
+    }
+
+    fn cursor_from(&self, from: u64) -> Cursor {
+        Cursor {
+            from,
+            after: None,
+            leaf: Vec::new(),
+            slot: 0,
+            next_leaf: BLOCK_NULL,
+            started: false,
+            leaf_reads: 0,
+        }
+    }
+
+    /// The next live entry of `cursor`'s walk; `None` at the end.
+    pub fn next_entry<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        cursor: &mut Cursor,
+    ) -> Result<Option<IndexEntry>, HelixError> {
+        use core::cmp::Ordering;
+
+        loop {
+            let base = self.peek_base(block_io, cursor)?;
+            let over = match &cursor.after {
+                Some(after) => self
+                    .overlay
+                    .range((Bound::Excluded(after), Bound::Unbounded))
+                    .next(),
+                None => self.overlay.range((cursor.from, [0u8; 256])..).next(),
+            };
+            // On a tie the overlay's version shadows the base's.
+            let (key, entry, from_base) = match (base, over) {
+                (None, None) => return Ok(None),
+                (Some((bk, be)), None) => (bk, be, true),
+                (None, Some((ok, oe))) => (*ok, *oe, false),
+                (Some((bk, be)), Some((ok, oe))) => match bk.cmp(ok) {
+                    Ordering::Less => (bk, be, true),
+                    Ordering::Equal => (bk, *oe, true),
+                    Ordering::Greater => (*ok, *oe, false),
+                },
+            };
+            if from_base {
+                cursor.slot += 1;
             }
-            if entry.flags & entry_flags::IS_DELETED == 0 {
-                return Some(entry);
+            cursor.after = Some(key);
+            if is_live(&entry) {
+                return Ok(Some(entry));
             }
         }
-        None
     }
 
-    /// Upsert; auto-compact tombstones once they exceed live entries (and index >= 512).
-    pub fn upsert(&mut self, entry: IndexEntry) {
-        let path_b = path_bytes(&entry.path);
-        match self.find_pos(entry.key, path_b) {
-            Ok(idx) => {
-                self.entries[idx] = entry;
-            },
-            Err(idx) => {
-                self.entries.insert(idx, entry);
-            },
+    /// The next base entry of the walk, without consuming it.
+    fn peek_base<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        cursor: &mut Cursor,
+    ) -> Result<Option<(SortKey, IndexEntry)>, HelixError> {
+        let Some(base) = &self.base else {
+            return Ok(None);
+        };
+        if !cursor.started {
+            cursor.started = true;
+            cursor.next_leaf = base.seek(block_io, cursor.from)?;
+        }
+        loop {
+            while let Some(e) = cursor.leaf.get(cursor.slot) {
+                let key = entry_key(e);
+                if key.0 >= cursor.from && cursor.after.map_or(true, |a| key > a) {
+                    return Ok(Some((key, *e)));
+                }
+                cursor.slot += 1;
+            }
+            if cursor.next_leaf == BLOCK_NULL {
+                return Ok(None);
+            }
+            cursor.leaf = base.leaf(block_io, cursor.next_leaf)?;
+            cursor.leaf_reads += 1;
+            cursor.slot = 0;
+            cursor.next_leaf = base.next_leaf(cursor.next_leaf);
         }
+    }
 
-        let total = self.entries.len();
-        let live = self.live_count();
-        if total >= 512 && total > live.saturating_mul(2) {
-            self.compact();
+    /// Every live entry, in tree order. Materializes the namespace: for
+    /// callers that need it whole (fsck, tests); walk a `cursor` otherwise.
+    pub fn entries<B: BlockIo>(&self, block_io: &mut B) -> Result<Vec<IndexEntry>, HelixError> {
+        let mut cursor = self.cursor();
+        let mut all = Vec::new();
+        while let Some(e) = self.next_entry(block_io, &mut cursor)? {
+            all.push(e);
         }
+        Ok(all)
+    }
+
+    /// Start recording undo state (transaction begin).
+    pub fn begin_journal(&mut self) {
+        self.journal = Some(BTreeMap::new());
+        self.quota_journal = Some((self.quotas.clone(), self.unsettled.clone()));
     }
 
-    pub fn mark_deleted(&mut self, path: &str) -> Result<(), HelixError> {
-        let idx = {
-            let key = fnv1a_64(path.as_bytes());
-            match self.find_pos(key, path.as_bytes()) {
-                Ok(i) => i,
-                Err(_) => {
-                    let alt = if !path.ends_with('/') {
-                        let mut s = String::from(path);
-                        s.push('/');
-                        s
-                    } else if path.len() > 1 {
-                        String::from(&path[..path.len() - 1])
-                    } else {
-                        return Err(HelixError::NotFound);
-                    };
-                    let alt_key = fnv1a_64(alt.as_bytes());
-                    self.find_pos(alt_key, alt.as_bytes())
-                        .map_err(|_| HelixError::NotFound)?
+    /// Keep every change since `begin_journal` (transaction commit).
+    pub fn commit_journal(&mut self) {
+        self.journal = None;
+        self.quota_journal = None;
+    }
+
+    /// Undo every change since `begin_journal` (transaction abort). The base
+    /// cannot have moved: checkpoints are refused inside a transaction.
+    pub fn rollback_journal(&mut self) {
+        let Some(journal) = self.journal.take() else {
+            return;
+        };
+        if let Some((quotas, unsettled)) = self.quota_journal.take() {
+            self.quotas = quotas;
+            self.unsettled = unsettled;
+        }
+        for (key, prior) in journal {
+            match prior {
+                Some(e) => {
+                    self.overlay.insert(key, e);
+                },
+                None => {
+                    self.overlay.remove(&key);
                 },
             }
-        };
-        self.entries[idx].flags |= entry_flags::IS_DELETED;
-        Ok(())
+        }
     }
 
-    /// Direct children of `parent_path`. Use `"/"` for root; otherwise must end with `/`.
-    pub fn readdir(&self, parent_path: &str) -> Vec<&IndexEntry> {
-        let prefix = if parent_path == "/" {
-            "/".as_bytes()
-        } else {
-            parent_path.as_bytes()
-        };
+    /// Every quota, with its figures; only settled ones are exact.
+    pub fn quotas(&self) -> &[Quota] {
+        &self.quotas
+    }
 
-        let mut results = Vec::new();
+    /// Adopt `quotas`, whose figures count the base, by charging what the
+    /// overlay changed. Settle before trusting the figures.
+    pub fn install_quotas(&mut self, quotas: Vec<Quota>) {
+        self.quotas = quotas;
+        self.unsettled.clear();
+        if self.quotas.is_empty() {
+            return;
+        }
+        for (key, e) in &self.overlay {
+            charge(&mut self.quotas, e, true);
+            self.unsettled.insert(*key);
+        }
+    }
 
-        for entry in &self.entries {
-            if entry.flags & entry_flags::IS_DELETED != 0 {
-                continue;
+    /// Take off the base entries that charged overlay entries replaced.
+    pub fn settle<B: BlockIo>(&mut self, block_io: &mut B) -> Result<(), HelixError> {
+        while let Some(key) = self.unsettled.first().copied() {
+            if let Some(old) = self.base_get(block_io, &key)? {
+                charge(&mut self.quotas, &old, false);
             }
-            let e_path = path_bytes(&entry.path);
-            if !e_path.starts_with(prefix) {
-                continue;
-            }
-            if e_path == prefix {
-                continue;
-            }
-            let remainder = &e_path[prefix.len()..];
+            self.unsettled.remove(&key);
+        }
+        Ok(())
+    }
 
-            // Direct child: no '/' in remainder, or trailing '/' only.
-            let slash_count = remainder.iter().filter(|&&b| b == b'/').count();
-            let is_direct_child =
-                slash_count == 0 || (slash_count == 1 && remainder.last() == Some(&b'/'));
+    /// Set the quota on directory `dir` (trailing `/`) to `limit`, counting
+    /// its subtree if it had none; an unlimited one drops it.
+    pub fn set_quota<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        dir: &str,
+        limit: Limit,
+    ) -> Result<(), HelixError> {
+        if limit.is_unlimited() {
+            self.drop_quota(dir);
+            return Ok(());
+        }
+        if let Some(q) = self.quotas.iter_mut().find(|q| q.dir == dir) {
+            q.limit = limit;
+            return Ok(());
+        }
+        // Later charges must not take off what the count below never saw.
+        self.settle(block_io)?;
+        let used = self.subtree_usage(block_io, dir)?;
+        self.quotas.push(Quota {
+            dir: String::from(dir),
+            limit,
+            used,
+        });
+        Ok(())
+    }
 
-            if is_direct_child {
-                results.push(entry);
+    /// What everything below directory `dir` (trailing `/`) counts for.
+    pub fn subtree_usage<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+        dir: &str,
+    ) -> Result<Usage, HelixError> {
+        let mut used = Usage::default();
+        let mut pending = alloc::vec![String::from(dir)];
+        while let Some(d) = pending.pop() {
+            for e in self.readdir(block_io, &d)? {
+                used.add(Usage::of(&e));
+                if e.flags & entry_flags::IS_DIR != 0 {
+                    pending.push(String::from(path_str(&e.path)));
+                }
             }
         }
+        Ok(used)
+    }
 
-        results
+    pub fn drop_quota(&mut self, dir: &str) {
+        self.quotas.retain(|q| q.dir != dir);
+    }
+
+    /// Count every quota in `limits` afresh over the whole namespace.
+    pub fn recount_quotas<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        limits: Vec<(String, Limit)>,
+    ) -> Result<(), HelixError> {
+        let mut quotas: Vec<Quota> = limits
+            .into_iter()
+            .map(|(dir, limit)| Quota {
+                dir,
+                limit,
+                used: Usage::default(),
+            })
+            .collect();
+        let mut cursor = self.cursor();
+        while let Some(e) = self.next_entry(block_io, &mut cursor)? {
+            charge(&mut quotas, &e, true);
+        }
+        self.quotas = quotas;
+        self.unsettled.clear();
+        Ok(())
     }
 
-    pub fn all_entries(&self) -> &[IndexEntry] {
-        &self.entries
+    /// Re-home the quotas at or below directory `from` under `to` after the
+    /// subtree moved. The move drained their figures entry by entry, so
+    /// they take back what they held before it (`held`, from `quotas`).
+    pub fn move_quotas(&mut self, from: &str, to: &str, held: &[Quota]) {
+        for q in self.quotas.iter_mut() {
+            let Some(before) = held.iter().find(|h| h.dir == q.dir) else {
+                continue;
+            };
+            if let Some(rest) = q.dir.strip_prefix(from) {
+                let mut dir = String::from(to);
+                dir.push_str(rest);
+                q.dir = dir;
+                q.used = before.used;
+            }
+        }
     }
 
-    pub fn all_entries_mut(&mut self) -> &mut [IndexEntry] {
-        &mut self.entries
+    /// Whether adding `grow` below `path` keeps every quota over it within
+    /// its limit; quotas that also cover `from` (where the entry moves from)
+    /// are left out. `QuotaExceeded` if not.
+    pub fn check_quota<B: BlockIo>(
+        &mut self,
+        block_io: &mut B,
+        path: &str,
+        grow: Usage,
+        from: Option<&str>,
+    ) -> Result<(), HelixError> {
+        if self.quotas.is_empty() || grow == Usage::default() {
+            return Ok(());
+        }
+        self.settle(block_io)?;
+        let over = self.quotas.iter().any(|q| {
+            q.covers(path)
+                && !from.is_some_and(|f| q.covers(f))
+                && !q.limit.admits(q.used.plus(grow))
+        });
+        if over {
+            return Err(HelixError::QuotaExceeded);
+        }
+        Ok(())
     }
 
-    /// Drop tombstones from the in-memory index.
-    pub fn compact(&mut self) {
-        self.entries
-            .retain(|e| e.flags & entry_flags::IS_DELETED == 0);
+    /// Live directories the overlay holds: those the ring touched since the
+    /// checkpoint.
+    pub fn changed_dirs(&self) -> Vec<IndexEntry> {
+        self.overlay
+            .values()
+            .filter(|e| is_live(e) && e.flags & entry_flags::IS_DIR != 0)
+            .copied()
+            .collect()
     }
 
-    /// Used during recovery before replay.
-    pub fn clear(&mut self) {
-        self.entries.clear();
+    fn journal_touch(&mut self, key: &SortKey) {
+        let prior = self.overlay.get(key).copied();
+        if let Some(journal) = self.journal.as_mut() {
+            journal.entry(*key).or_insert(prior);
+        }
     }
 
     pub fn make_file_entry(
@@ -275,6 +634,22 @@ impl NamespaceIndex {
     }
 }
 
+/// Add (or take off) what `e` counts for to every quota over it.
+fn charge(quotas: &mut [Quota], e: &IndexEntry, add: bool) {
+    if quotas.is_empty() {
+        return;
+    }
+    let usage = Usage::of(e);
+    let path = path_str(&e.path);
+    for q in quotas.iter_mut().filter(|q| q.covers(path)) {
+        if add {
+            q.used.add(usage);
+        } else {
+            q.used.sub(usage);
+        }
+    }
+}
+
 pub fn path_bytes(buf: &[u8; 256]) -> &[u8] {
     let len = buf.iter().position(|&b| b == 0).unwrap_or(256);
     &buf[..len]
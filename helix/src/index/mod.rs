@@ -1,5 +1,5 @@
-//! Namespace B-tree: FNV-1a path_hash -> IndexEntry. In-memory, checkpointed
-//! to disk; rebuilt on mount by log replay. Hash collisions resolved by full
-//! path compare in leaves.
+//! Namespace B+tree: (parent, name) -> IndexEntry. Paged in from the last
+//! checkpoint on demand; changes since then live in memory and are replayed
+//! from the log on mount. Hash collisions resolved by full path compare.
 
 pub mod btree;
@@ -8,7 +8,11 @@
 //! - Log is append-only; records validated by CRC32C; first bad CRC ends scan.
 //! - On-disk B-tree only flushed via Checkpoint records; otherwise in RAM.
 //! - Three-writes rule: data → flush → pointer → flush.
-//! - Every Write/Append carries CRC64 of payload for dedup.
+//! - Every Write/Append carries CRC64 of payload; whole-file duplicates share
+//!   one extent tree via refcounted `DedupRef` records (see `dedup`).
+//! - Files under a `helix.compression` policy store LZ4 frames (see `compress`).
+//! - Encrypted volumes seal every block but the superblocks and tags (see `crypt`).
+//! - Log recycling and defragmentation also run incrementally (see `clean`).
 
 #![no_std]
 #![allow(dead_code)]
@@ -17,15 +21,20 @@ extern crate alloc;
 
 pub mod bitmap;
 pub mod checkpoint;
+pub mod clean;
+pub mod compress;
 pub mod crc;
+pub mod crypt;
+pub mod dedup;
 pub mod engine;
 pub mod error;
 pub mod extent;
 pub mod format;
+pub mod fsck;
 pub mod index;
 pub mod log;
 pub mod ops;
 pub mod types;
 
-pub use engine::{HelixFs, MAX_TRUNCATE_GROW};
+pub use engine::HelixFs;
 pub use error::HelixError;
@@ -0,0 +1,135 @@
+//! Change feed: the namespace changes behind each appended record, for
+//! notifying watchers without polling.
+//!
+//! Off unless enabled (`HelixFs::watch`); host tools never pay for it.
+//! Records decode to a [`Change`] as they are appended. Changes made inside
+//! a transaction are held back until it commits and dropped if it aborts.
+//! Link records are internal and never surface, so neither does a content
+//! write through a hard-linked name. The feed is bounded: past
+//! [`FEED_CAPACITY`] undrained changes it drops the newest and says so.
+
+use super::recovery::decode_path_payload;
+use crate::ops::link::is_record;
+use crate::types::{LogOp, Lsn};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Undrained changes kept before the feed reports loss.
+pub const FEED_CAPACITY: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A file, directory or link came into being.
+    Create,
+    /// A file's content changed.
+    Modify,
+    Delete,
+    /// `path` moved to `to`.
+    Rename,
+    /// Extended attributes, owner or permission bits changed.
+    Attrib,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// LSN of the record; shared by both halves of a rename downstream.
+    pub lsn: Lsn,
+    pub kind: ChangeKind,
+    /// Absolute within the volume, without a trailing `/`.
+    pub path: String,
+    /// Destination of a `Rename`; empty otherwise.
+    pub to: String,
+}
+
+#[derive(Default)]
+pub struct ChangeFeed {
+    /// `(tx_begin_lsn, change)`, oldest first; 0 = outside a transaction.
+    pending: Vec<(Lsn, Change)>,
+    /// Changes were dropped since the last drain.
+    lost: bool,
+}
+
+impl ChangeFeed {
+    /// Note the record just appended.
+    pub fn record(&mut self, op: LogOp, lsn: Lsn, tx_begin_lsn: Lsn, payload: &[u8]) {
+        let Some(change) = decode(op, lsn, payload) else {
+            return;
+        };
+        if self.pending.len() >= FEED_CAPACITY {
+            self.lost = true;
+            return;
+        }
+        self.pending.push((tx_begin_lsn, change));
+    }
+
+    /// Record `lsn` wrote a file that did not exist before it.
+    pub fn created(&mut self, lsn: Lsn) {
+        if let Some((_, c)) = self.pending.iter_mut().rev().find(|(_, c)| c.lsn == lsn) {
+            if c.kind == ChangeKind::Modify {
+                c.kind = ChangeKind::Create;
+            }
+        }
+    }
+
+    /// Forget the changes of the aborted transaction `tx_begin_lsn`.
+    pub fn discard(&mut self, tx_begin_lsn: Lsn) {
+        self.pending.retain(|(tx, _)| *tx != tx_begin_lsn);
+    }
+
+    /// Take every change not inside the open transaction `open_tx` (0 =
+    /// none), oldest first, and whether any were dropped before them.
+    pub fn drain(&mut self, open_tx: Lsn) -> (Vec<Change>, bool) {
+        let mut out = Vec::new();
+        let mut held = Vec::new();
+        for (tx, change) in self.pending.drain(..) {
+            if open_tx != 0 && tx == open_tx {
+                held.push((tx, change));
+            } else {
+                out.push(change);
+            }
+        }
+        self.pending = held;
+        (out, core::mem::take(&mut self.lost))
+    }
+}
+
+/// Content writes decode as `Modify`; the engine marks the ones that
+/// created their file (`created`), which needs the index.
+fn decode(op: LogOp, lsn: Lsn, payload: &[u8]) -> Option<Change> {
+    let kind = match op {
+        LogOp::Write | LogOp::DedupRef | LogOp::Append | LogOp::Truncate => ChangeKind::Modify,
+        LogOp::Delete => ChangeKind::Delete,
+        LogOp::MkDir | LogOp::Symlink | LogOp::Link => ChangeKind::Create,
+        LogOp::Rename => ChangeKind::Rename,
+        LogOp::SetMeta | LogOp::SetOwner => ChangeKind::Attrib,
+        _ => return None,
+    };
+    let (path, rest) = decode_path_payload(payload)?;
+    // Rename and Link: [old_len: u16][old][new_len: u16][new].
+    let second = match op {
+        LogOp::Rename | LogOp::Link => Some(decode_path_payload(rest)?.0),
+        _ => None,
+    };
+    let (path, to) = match (op, second) {
+        (LogOp::Link, Some(new)) => (new, ""),
+        (_, Some(new)) => (path, new),
+        _ => (path, ""),
+    };
+    if is_record(path) || is_record(to) {
+        return None;
+    }
+    Some(Change {
+        lsn,
+        kind,
+        path: String::from(trim(path)),
+        to: String::from(trim(to)),
+    })
+}
+
+fn trim(path: &str) -> &str {
+    if path.len() > 1 {
+        path.trim_end_matches('/')
+    } else {
+        path
+    }
+}
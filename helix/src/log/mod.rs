@@ -5,14 +5,17 @@
 //! - Superblock `committed_lsn` only advances after a flush.
 //! - Recovery scans forward from `checkpoint_lsn`, validates each CRC, stops at first failure.
 
+pub mod feed;
 pub mod recovery;
 pub mod segment;
+pub mod tx;
 
 use crate::crc::{crc32c, crc32c_two, crc64};
 use crate::error::HelixError;
 use crate::types::*;
 use alloc::vec;
 use alloc::vec::Vec;
+use feed::ChangeFeed;
 use gpt_disk_io::BlockIo;
 use gpt_disk_types::Lba;
 
@@ -29,6 +32,12 @@ pub struct LogEngine {
     /// Oldest live segment.
     tail_segment: u64,
     next_lsn: Lsn,
+    /// `TxBegin` LSN stamped on every record while a transaction is open; 0 = none.
+    active_tx: Lsn,
+    /// Latest timestamp appended since mount; 0 = none yet.
+    newest_ns: u64,
+    /// Namespace changes for watchers; `None` until enabled.
+    feed: Option<ChangeFeed>,
     write_buf: Vec<u8>,
     record_count: u32,
     partition_lba_start: u64,
@@ -45,6 +54,9 @@ impl LogEngine {
             head_offset: sb.log_head_offset,
             tail_segment: sb.log_tail_segment,
             next_lsn: sb.committed_lsn + 1,
+            active_tx: 0,
+            newest_ns: 0,
+            feed: None,
             write_buf: vec![0u8; LOG_SEGMENT_BYTES as usize],
             record_count: 0,
             partition_lba_start,
@@ -56,6 +68,34 @@ impl LogEngine {
         self.next_lsn
     }
 
+    /// Tag subsequent records with `tx_begin_lsn` (0 closes the transaction).
+    pub fn set_active_tx(&mut self, tx_begin_lsn: Lsn) {
+        self.active_tx = tx_begin_lsn;
+    }
+
+    pub fn active_tx(&self) -> Lsn {
+        self.active_tx
+    }
+
+    /// Latest timestamp appended since mount; the engine's notion of "now"
+    /// where a caller supplies none (0 = unknown).
+    pub fn newest_ns(&self) -> u64 {
+        self.newest_ns
+    }
+
+    /// Start (or stop and discard) the change feed.
+    pub fn set_feed(&mut self, on: bool) {
+        match (on, self.feed.is_some()) {
+            (true, false) => self.feed = Some(ChangeFeed::default()),
+            (false, _) => self.feed = None,
+            _ => {},
+        }
+    }
+
+    pub fn feed_mut(&mut self) -> Option<&mut ChangeFeed> {
+        self.feed.as_mut()
+    }
+
     pub fn head_segment(&self) -> u64 {
         self.head_segment
     }
@@ -78,6 +118,17 @@ impl LogEngine {
         self.tail_segment = self.head_segment;
     }
 
+    /// Drop every record in the head segment, keeping its header. For fsck
+    /// repair, right before a checkpoint captures the namespace: replay would
+    /// stop at a corrupt record there and lose whatever is appended after it.
+    pub fn clear_head_segment(&mut self) {
+        let seg_hdr_size = core::mem::size_of::<LogSegmentHeader>();
+        let end = self.head_offset as usize;
+        self.write_buf[seg_hdr_size..end].fill(0);
+        self.head_offset = seg_hdr_size as u32;
+        self.record_count = 0;
+    }
+
     /// Live segments (tail..=head) / total, percent. 100 = log full; warn near 80 for GC.
     pub fn log_utilization_pct(&self) -> u32 {
         let n = self.segment_count.max(1);
@@ -89,6 +140,16 @@ impl LogEngine {
         ((used * 100) / n) as u32
     }
 
+    /// Bytes in use from the tail to the head's write offset / ring size,
+    /// percent. Unlike `log_utilization_pct` it sees a partly filled segment,
+    /// so it means something on a one-segment ring.
+    pub fn fill_pct(&self) -> u32 {
+        let n = self.segment_count.max(1);
+        let behind = (self.head_segment + n - self.tail_segment) % n;
+        let used = behind * LOG_SEGMENT_BYTES + self.head_offset as u64;
+        ((used * 100) / (n * LOG_SEGMENT_BYTES)) as u32
+    }
+
     fn segment_remaining(&self) -> u32 {
         LOG_SEGMENT_BYTES as u32 - self.head_offset
     }
@@ -121,6 +182,7 @@ impl LogEngine {
     }
 
     /// Append with header `flags` + secondary_hash + tx_begin_lsn (extent, rename, tx ops).
+    /// A zero `tx_begin_lsn` inherits the open transaction, if any.
     #[allow(clippy::too_many_arguments)]
     pub fn append_full<B: BlockIo>(
         &mut self,
@@ -134,6 +196,11 @@ impl LogEngine {
         timestamp_ns: u64,
     ) -> Result<Lsn, HelixError> {
         let lsn = self.next_lsn;
+        let tx_begin_lsn = if tx_begin_lsn == 0 {
+            self.active_tx
+        } else {
+            tx_begin_lsn
+        };
 
         let payload_crc = if payload.is_empty() {
             0
@@ -217,6 +284,10 @@ impl LogEngine {
         self.head_offset += total_size;
         self.record_count += 1;
         self.next_lsn += 1;
+        self.newest_ns = self.newest_ns.max(timestamp_ns);
+        if let Some(feed) = self.feed.as_mut() {
+            feed.record(op, lsn, tx_begin_lsn, payload);
+        }
 
         Ok(lsn)
     }
@@ -378,18 +449,56 @@ impl LogEngine {
         Ok(())
     }
 
-    /// Scan tail..=head, calling `visitor` per valid record. Stops on first
+    /// Scan tail..=head, calling `visitor` per valid record with the device,
+    /// which it may use: the segment is already read. Stops on first
     /// CRC failure or at the head write position. Returns highest valid LSN.
     /// Reads whole segments; head segment reuses the loaded `write_buf`.
     pub fn scan_forward<B: BlockIo, F>(
+        &self,
+        block_io: &mut B,
+        start_segment: u64,
+        start_offset: u32,
+        visitor: F,
+    ) -> Result<Lsn, HelixError>
+    where
+        F: FnMut(&mut B, &LogRecordHeader, &[u8]) -> Result<(), HelixError>,
+    {
+        self.scan_ring(block_io, start_segment, start_offset, visitor, |_, _| {})
+    }
+
+    /// Walk the whole ring tail..=head for fsck: `(valid records, (segment,
+    /// offset) of each record that fails its CRC)`. A failure ends its
+    /// segment, as in replay; the walk goes on with the next one.
+    pub fn verify_ring<B: BlockIo>(
+        &self,
+        block_io: &mut B,
+    ) -> Result<(u64, Vec<(u64, u32)>), HelixError> {
+        let mut records = 0u64;
+        let mut corrupt = Vec::new();
+        self.scan_ring(
+            block_io,
+            self.tail_segment,
+            core::mem::size_of::<LogSegmentHeader>() as u32,
+            |_, _, _| {
+                records += 1;
+                Ok(())
+            },
+            |seg, offset| corrupt.push((seg, offset)),
+        )?;
+        Ok((records, corrupt))
+    }
+
+    fn scan_ring<B: BlockIo, F, C>(
         &self,
         block_io: &mut B,
         start_segment: u64,
         start_offset: u32,
         mut visitor: F,
+        mut on_corrupt: C,
     ) -> Result<Lsn, HelixError>
     where
-        F: FnMut(&LogRecordHeader, &[u8]) -> Result<(), HelixError>,
+        F: FnMut(&mut B, &LogRecordHeader, &[u8]) -> Result<(), HelixError>,
+        C: FnMut(u64, u32),
     {
         let hdr_size = core::mem::size_of::<LogRecordHeader>();
         let seg_hdr_size = core::mem::size_of::<LogSegmentHeader>() as u32;
@@ -458,6 +567,7 @@ impl LogEngine {
                 let payload_end = payload_start + payload_len;
 
                 if payload_end > buf.len() || offset + total > LOG_SEGMENT_BYTES as u32 {
+                    on_corrupt(seg, offset);
                     break;
                 }
 
@@ -470,11 +580,12 @@ impl LogEngine {
                 };
                 let computed = crc32c_two(hdr_bytes, payload);
                 if computed != header.record_crc32c {
+                    on_corrupt(seg, offset);
                     break;
                 }
 
                 highest_lsn = header.lsn;
-                visitor(&header, payload)?;
+                visitor(block_io, &header, payload)?;
                 offset += total;
             }
 
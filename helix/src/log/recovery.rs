@@ -8,6 +8,7 @@
 use crate::crc::fnv1a_64;
 use crate::error::HelixError;
 use crate::index::btree::NamespaceIndex;
+use crate::log::tx::TxGate;
 use crate::log::LogEngine;
 use crate::types::*;
 use alloc::vec;
@@ -15,30 +16,52 @@ use alloc::vec::Vec;
 use gpt_disk_io::BlockIo;
 use gpt_disk_types::Lba;
 
-/// Read both superblocks; return the one with the higher valid `committed_lsn`.
-pub fn recover_superblock<B: BlockIo>(
+/// Read one superblock copy (`slot`: 0 = A, 1 = B) without validating it.
+pub fn read_superblock<B: BlockIo>(
     block_io: &mut B,
     partition_lba_start: u64,
     device_block_size: u32,
+    slot: u64,
 ) -> Result<HelixSuperblock, HelixError> {
     let blocks_per_sector = BLOCK_SIZE as u64 / device_block_size as u64;
-
+    let block = if slot == 0 {
+        SUPERBLOCK_A_BLOCK
+    } else {
+        SUPERBLOCK_B_BLOCK
+    };
     let mut buf = vec![0u8; BLOCK_SIZE as usize];
-
-    let lba_a = Lba(partition_lba_start + SUPERBLOCK_A_BLOCK * blocks_per_sector);
     block_io
-        .read_blocks(lba_a, &mut buf)
+        .read_blocks(
+            Lba(partition_lba_start + block * blocks_per_sector),
+            &mut buf,
+        )
         .map_err(|_| HelixError::IoReadFailed)?;
-    let sb_a: HelixSuperblock =
-        unsafe { core::ptr::read_unaligned(buf.as_ptr() as *const HelixSuperblock) };
-    let a_valid = sb_a.is_valid();
+    Ok(unsafe { core::ptr::read_unaligned(buf.as_ptr() as *const HelixSuperblock) })
+}
 
-    let lba_b = Lba(partition_lba_start + SUPERBLOCK_B_BLOCK * blocks_per_sector);
-    block_io
-        .read_blocks(lba_b, &mut buf)
-        .map_err(|_| HelixError::IoReadFailed)?;
-    let sb_b: HelixSuperblock =
-        unsafe { core::ptr::read_unaligned(buf.as_ptr() as *const HelixSuperblock) };
+/// Read both superblocks; return the one with the higher valid `committed_lsn`.
+/// `Encrypted` if it is the raw superblock of an encrypted volume.
+pub fn recover_superblock<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    device_block_size: u32,
+) -> Result<HelixSuperblock, HelixError> {
+    let sb = newest_superblock(block_io, partition_lba_start, device_block_size)?;
+    if sb.crypt.flags & crypt_flags::ACTIVE != 0 {
+        return Err(HelixError::Encrypted);
+    }
+    Ok(sb)
+}
+
+/// `recover_superblock` without the encryption check.
+pub(crate) fn newest_superblock<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    device_block_size: u32,
+) -> Result<HelixSuperblock, HelixError> {
+    let sb_a = read_superblock(block_io, partition_lba_start, device_block_size, 0)?;
+    let a_valid = sb_a.is_valid();
+    let sb_b = read_superblock(block_io, partition_lba_start, device_block_size, 1)?;
     let b_valid = sb_b.is_valid();
 
     match (a_valid, b_valid) {
@@ -84,7 +107,7 @@ pub fn write_superblock<B: BlockIo>(
 }
 
 /// v2 payload prefix: `[path_len: u16 LE][path][rest...]`.
-fn decode_path_payload(payload: &[u8]) -> Option<(&str, &[u8])> {
+pub(crate) fn decode_path_payload(payload: &[u8]) -> Option<(&str, &[u8])> {
     if payload.len() < 2 {
         return None;
     }
@@ -110,170 +133,260 @@ pub fn replay_log<B: BlockIo>(
     index: &mut NamespaceIndex,
     snapshots: &mut Vec<Lsn>,
     checkpoint_lsn: Lsn,
+) -> Result<Lsn, HelixError> {
+    replay_log_until(block_io, log, index, snapshots, checkpoint_lsn, Lsn::MAX)
+}
+
+/// `replay_log`, ignoring records after `until_lsn`: the namespace as of that
+/// LSN (snapshot mounts). A transaction whose commit lies past `until_lsn` is
+/// dropped like a torn one.
+pub fn replay_log_until<B: BlockIo>(
+    block_io: &mut B,
+    log: &LogEngine,
+    index: &mut NamespaceIndex,
+    snapshots: &mut Vec<Lsn>,
+    checkpoint_lsn: Lsn,
+    until_lsn: Lsn,
 ) -> Result<Lsn, HelixError> {
     let start_offset = core::mem::size_of::<LogSegmentHeader>() as u32;
+    // Transaction records only land once their TxCommit is seen; whatever is
+    // still buffered when the scan stops belongs to a torn transaction.
+    let mut gate = TxGate::new();
 
     let highest_lsn = log.scan_forward(
         block_io,
         log.tail_segment(),
         start_offset,
-        |hdr, payload| {
+        |dev, hdr, payload| {
             // Records at or below the checkpoint are already in the loaded index.
-            if hdr.lsn <= checkpoint_lsn {
+            if hdr.lsn <= checkpoint_lsn || hdr.lsn > until_lsn {
                 return Ok(());
             }
-            let op = match LogOp::from_u8(hdr.op) {
-                Some(o) => o,
-                None => return Ok(()),
-            };
+            gate.feed(hdr, payload, |h, p| {
+                apply_record(dev, index, snapshots, h, p)
+            })
+        },
+    )?;
 
-            match op {
-                LogOp::MkDir => {
-                    if let Some((path, _rest)) = decode_path_payload(payload) {
-                        let entry = NamespaceIndex::make_dir_entry(path, hdr.lsn, hdr.timestamp_ns);
-                        index.upsert(entry);
-                    }
-                },
+    Ok(highest_lsn)
+}
 
-                LogOp::Write => {
-                    if let Some((path, data)) = decode_path_payload(payload) {
-                        // Extent payload: [0xFF][file_size: u64][logical: u64][physical: u64][count: u32][pad: u32].
-                        // Inline payload: raw data. The IS_EXTENT header flag — not
-                        // any payload byte — decides which, so inline data may begin
-                        // with 0xFF without being mistaken for an extent.
-                        let is_extent = hdr.flags & rec_flags::IS_EXTENT != 0;
-                        let new_entry = if is_extent && data.len() >= 17 {
-                            let kind = data[0];
-                            let file_size =
-                                u64::from_le_bytes(data[1..9].try_into().unwrap_or([0u8; 8]));
-                            let extent_root =
-                                u64::from_le_bytes(data[9..17].try_into().unwrap_or([0u8; 8]));
-                            let mut e = NamespaceIndex::make_file_entry(
-                                path,
-                                hdr.lsn,
-                                file_size,
-                                hdr.timestamp_ns,
-                                None,
-                                extent_root,
-                                0,
-                            );
-                            if kind == extent_kind::NODE {
-                                e.flags |= entry_flags::IS_EXTENT_NODE;
-                            }
-                            Some(e)
-                        } else if !is_extent && data.len() <= INLINE_DATA_SIZE {
-                            let crc = if data.is_empty() {
-                                0
-                            } else {
-                                crate::crc::crc64(data)
-                            };
-                            Some(NamespaceIndex::make_file_entry(
-                                path,
-                                hdr.lsn,
-                                data.len() as u64,
-                                hdr.timestamp_ns,
-                                Some(data),
-                                BLOCK_NULL,
-                                crc,
-                            ))
-                        } else {
-                            None
-                        };
+/// Apply one committed record to the index; `Err` only when the index
+/// itself cannot be read.
+fn apply_record<B: BlockIo>(
+    block_io: &mut B,
+    index: &mut NamespaceIndex,
+    snapshots: &mut Vec<Lsn>,
+    hdr: &LogRecordHeader,
+    payload: &[u8],
+) -> Result<(), HelixError> {
+    let op = match LogOp::from_u8(hdr.op) {
+        Some(o) => o,
+        None => return Ok(()),
+    };
 
-                        if let Some(mut entry) = new_entry {
-                            // A rewrite must keep the file's birth metadata and
-                            // grow its version count — same as the live write
-                            // path — so stat() is identical before and after a
-                            // remount. A fresh entry after an intervening Delete
-                            // (lookup returns None) is correctly treated as new.
-                            if let Some(existing) = index.lookup(path) {
-                                entry.created_ns = existing.created_ns;
-                                entry.first_lsn = existing.first_lsn;
-                                entry.version_count = existing.version_count + 1;
-                            }
-                            index.upsert(entry);
-                        }
+    match op {
+        LogOp::MkDir => {
+            if let Some((path, _rest)) = decode_path_payload(payload) {
+                let entry = NamespaceIndex::make_dir_entry(path, hdr.lsn, hdr.timestamp_ns);
+                index.upsert(entry);
+            }
+        },
+
+        // A DedupRef is an extent Write naming blocks another entry already owns.
+        LogOp::Write | LogOp::DedupRef => {
+            if let Some((path, data)) = decode_path_payload(payload) {
+                // Extent payload: [0xFF][file_size: u64][logical: u64][physical: u64][count: u32][pad: u32].
+                // Inline payload: raw data. The IS_EXTENT header flag — not
+                // any payload byte — decides which, so inline data may begin
+                // with 0xFF without being mistaken for an extent.
+                let is_extent = hdr.flags & rec_flags::IS_EXTENT != 0;
+                let new_entry = if is_extent && data.len() >= 17 {
+                    let kind = data[0];
+                    let file_size = u64::from_le_bytes(data[1..9].try_into().unwrap_or([0u8; 8]));
+                    let extent_root =
+                        u64::from_le_bytes(data[9..17].try_into().unwrap_or([0u8; 8]));
+                    let mut e = NamespaceIndex::make_file_entry(
+                        path,
+                        hdr.lsn,
+                        file_size,
+                        hdr.timestamp_ns,
+                        None,
+                        extent_root,
+                        hdr.secondary_hash,
+                    );
+                    if kind == extent_kind::NODE {
+                        e.flags |= entry_flags::IS_EXTENT_NODE;
                     }
-                },
+                    if kind == extent_kind::COMPRESSED && data.len() >= 25 {
+                        e.flags |= entry_flags::IS_COMPRESSED;
+                        e.stored_len =
+                            u64::from_le_bytes(data[17..25].try_into().unwrap_or([0u8; 8]));
+                    }
+                    if op == LogOp::DedupRef {
+                        e.flags |= entry_flags::IS_DEDUP;
+                    }
+                    Some(e)
+                } else if !is_extent && data.len() <= INLINE_DATA_SIZE {
+                    let crc = if data.is_empty() {
+                        0
+                    } else {
+                        crate::crc::crc64(data)
+                    };
+                    Some(NamespaceIndex::make_file_entry(
+                        path,
+                        hdr.lsn,
+                        data.len() as u64,
+                        hdr.timestamp_ns,
+                        Some(data),
+                        BLOCK_NULL,
+                        crc,
+                    ))
+                } else {
+                    None
+                };
 
-                LogOp::Delete => {
-                    if let Some((path, _rest)) = decode_path_payload(payload) {
-                        let _ = index.mark_deleted(path);
+                if let Some(mut entry) = new_entry {
+                    // A rewrite must keep the file's birth metadata and
+                    // grow its version count — same as the live write
+                    // path — so stat() is identical before and after a
+                    // remount. A fresh entry after an intervening Delete
+                    // (lookup returns None) is correctly treated as new.
+                    if let Some(existing) = index.lookup(block_io, path)? {
+                        entry.inherit(&existing);
                     }
-                },
+                    index.upsert(entry);
+                }
+            }
+        },
 
-                LogOp::Rename => {
-                    // v2: [old_path_len: u16][old_path][new_path_len: u16][new_path].
-                    if let Some((old_path, rest)) = decode_path_payload(payload) {
-                        if rest.len() >= 2 {
-                            let new_len = u16::from_le_bytes([rest[0], rest[1]]) as usize;
-                            if rest.len() >= 2 + new_len {
-                                if let Ok(new_path) = core::str::from_utf8(&rest[2..2 + new_len]) {
-                                    if let Some(old_entry) = index.lookup(old_path) {
-                                        let mut new_entry = *old_entry;
-                                        new_entry.key = fnv1a_64(new_path.as_bytes());
-                                        let nb = new_path.as_bytes();
-                                        new_entry.path = [0u8; 256];
-                                        let l = nb.len().min(MAX_PATH_LEN);
-                                        new_entry.path[..l].copy_from_slice(&nb[..l]);
-                                        new_entry.lsn = hdr.lsn;
-                                        new_entry.modified_ns = hdr.timestamp_ns;
-                                        let _ = index.mark_deleted(old_path);
-                                        index.upsert(new_entry);
-                                    }
+        LogOp::Delete => {
+            if let Some((path, _rest)) = decode_path_payload(payload) {
+                if let Some(entry) = index.lookup_flex(block_io, path)? {
+                    index.mark_deleted(block_io, path)?;
+                    crate::ops::link::drop_name(block_io, index, &entry)?;
+                }
+            }
+        },
+
+        LogOp::Rename => {
+            // v2: [old_path_len: u16][old_path][new_path_len: u16][new_path].
+            if let Some((old_path, rest)) = decode_path_payload(payload) {
+                if rest.len() >= 2 {
+                    let new_len = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+                    if rest.len() >= 2 + new_len {
+                        if let Ok(new_path) = core::str::from_utf8(&rest[2..2 + new_len]) {
+                            if let Some(old_entry) = index.lookup(block_io, old_path)? {
+                                let mut new_entry = old_entry;
+                                new_entry.key = fnv1a_64(new_path.as_bytes());
+                                let nb = new_path.as_bytes();
+                                new_entry.path = [0u8; 256];
+                                let l = nb.len().min(MAX_PATH_LEN);
+                                new_entry.path[..l].copy_from_slice(&nb[..l]);
+                                new_entry.lsn = hdr.lsn;
+                                new_entry.modified_ns = hdr.timestamp_ns;
+                                let clobbered = index.lookup(block_io, new_path)?;
+                                index.mark_deleted(block_io, old_path)?;
+                                index.upsert(new_entry);
+                                if let Some(dest) = clobbered {
+                                    crate::ops::link::drop_name(block_io, index, &dest)?;
                                 }
                             }
                         }
                     }
-                },
+                }
+            }
+        },
 
-                LogOp::Snapshot => snapshots.push(hdr.lsn),
+        LogOp::Snapshot => snapshots.push(hdr.lsn),
 
-                // Skipped during replay.
-                LogOp::SetMeta
-                | LogOp::DedupRef
-                | LogOp::TxBegin
-                | LogOp::TxCommit
-                | LogOp::TxAbort
-                | LogOp::Checkpoint
-                | LogOp::Truncate => {},
+        // [path_len: u16][path][extent_root: u64][target].
+        LogOp::Symlink => {
+            if let Some((path, rest)) = decode_path_payload(payload) {
+                crate::ops::link::apply_symlink(index, path, rest, hdr.lsn, hdr.timestamp_ns);
+            }
+        },
 
-                LogOp::Append => {
-                    // v2 payload: [path_len: u16][path][appended_data].
-                    if let Some((path, appended)) = decode_path_payload(payload) {
-                        if let Some(existing) = index.lookup_mut(path) {
-                            let old_size = existing.size as usize;
-                            let new_size = old_size + appended.len();
+        // Rename's payload: [old_path_len: u16][old_path][new_path_len: u16][new_path].
+        LogOp::Link => {
+            if let Some((old_path, rest)) = decode_path_payload(payload) {
+                if let Some((new_path, _)) = decode_path_payload(rest) {
+                    crate::ops::link::apply_link(
+                        block_io,
+                        index,
+                        old_path,
+                        new_path,
+                        hdr.lsn,
+                        hdr.timestamp_ns,
+                    )?;
+                }
+            }
+        },
 
-                            if existing.flags & entry_flags::IS_INLINE != 0 {
-                                if new_size <= INLINE_DATA_SIZE {
-                                    existing.inline_data[old_size..new_size]
-                                        .copy_from_slice(appended);
-                                    existing.size = new_size as u64;
-                                } else {
-                                    // Promoted to extent by write path; that
-                                    // appears as a separate Write record.
-                                    existing.flags &= !entry_flags::IS_INLINE;
-                                    existing.size = new_size as u64;
-                                }
-                            } else {
-                                // Data blocks already written by original append.
-                                existing.size = new_size as u64;
-                            }
-                            existing.lsn = hdr.lsn;
-                            existing.modified_ns = hdr.timestamp_ns;
-                            existing.version_count += 1;
-                        }
-                        // Orphaned append (entry missing): skip.
+        // [path_len: u16][path][xattr_block: u64][xattr_len: u32].
+        LogOp::SetMeta => {
+            if let Some((path, rest)) = decode_path_payload(payload) {
+                if let Some((block, len)) = crate::ops::xattr::decode_meta(rest) {
+                    if let Some(mut entry) = index.lookup(block_io, path)? {
+                        entry.xattr_block = block;
+                        entry.xattr_len = len;
+                        entry.xattr_lsn = hdr.lsn;
+                        index.upsert(entry);
                     }
-                },
+                }
             }
+        },
 
-            Ok(())
+        LogOp::SetOwner => {
+            if let Some((path, rest)) = decode_path_payload(payload) {
+                crate::ops::owner::apply_set(block_io, index, path, rest)?;
+            }
         },
-    )?;
 
-    index.compact();
+        LogOp::Relocate => {
+            if let Some((path, rest)) = decode_path_payload(payload) {
+                crate::clean::apply_relocate(block_io, index, path, rest)?;
+            }
+        },
 
-    Ok(highest_lsn)
+        // Skipped during replay; `Retain` records rebuild the version history
+        // instead (see `ops::retain`).
+        LogOp::TxBegin
+        | LogOp::TxCommit
+        | LogOp::TxAbort
+        | LogOp::Checkpoint
+        | LogOp::Truncate
+        | LogOp::Retain => {},
+
+        LogOp::Append => {
+            // v2 payload: [path_len: u16][path][appended_data].
+            if let Some((path, appended)) = decode_path_payload(payload) {
+                if let Some(mut existing) = index.lookup(block_io, path)? {
+                    let old_size = existing.size as usize;
+                    let new_size = old_size + appended.len();
+
+                    if existing.flags & entry_flags::IS_INLINE != 0 {
+                        if new_size <= INLINE_DATA_SIZE {
+                            existing.inline_data[old_size..new_size].copy_from_slice(appended);
+                            existing.size = new_size as u64;
+                        } else {
+                            // Promoted to extent by write path; that
+                            // appears as a separate Write record.
+                            existing.flags &= !entry_flags::IS_INLINE;
+                            existing.size = new_size as u64;
+                        }
+                    } else {
+                        // Data blocks already written by original append.
+                        existing.size = new_size as u64;
+                    }
+                    existing.lsn = hdr.lsn;
+                    existing.modified_ns = hdr.timestamp_ns;
+                    existing.version_count += 1;
+                    index.upsert(existing);
+                }
+                // Orphaned append (entry missing): skip.
+            }
+        },
+    }
+    Ok(())
 }
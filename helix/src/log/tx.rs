@@ -0,0 +1,65 @@
+//! Transaction gating for log scans.
+//!
+//! Records logged inside a transaction carry the LSN of their `TxBegin` in
+//! `tx_begin_lsn`. A scan must not expose them until the matching `TxCommit`
+//! (same `tx_begin_lsn`) is seen; a `TxAbort`, or the end of the log with no
+//! commit (power cut mid-transaction), discards them. Untagged records pass
+//! straight through.
+
+use crate::error::HelixError;
+use crate::types::*;
+use alloc::vec::Vec;
+
+/// Buffers tagged records until their transaction resolves.
+#[derive(Default)]
+pub struct TxGate {
+    pending: Vec<(LogRecordHeader, Vec<u8>)>,
+}
+
+impl TxGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one scanned record; `apply` runs for every record it makes visible,
+    /// in log order.
+    pub fn feed<F>(
+        &mut self,
+        hdr: &LogRecordHeader,
+        payload: &[u8],
+        mut apply: F,
+    ) -> Result<(), HelixError>
+    where
+        F: FnMut(&LogRecordHeader, &[u8]) -> Result<(), HelixError>,
+    {
+        match LogOp::from_u8(hdr.op) {
+            Some(LogOp::TxBegin) => Ok(()),
+            Some(LogOp::TxCommit) => {
+                let tx = hdr.tx_begin_lsn;
+                let (committed, rest): (Vec<_>, Vec<_>) = core::mem::take(&mut self.pending)
+                    .into_iter()
+                    .partition(|(h, _)| h.tx_begin_lsn == tx);
+                self.pending = rest;
+                for (h, p) in &committed {
+                    apply(h, p)?;
+                }
+                Ok(())
+            },
+            Some(LogOp::TxAbort) => {
+                let tx = hdr.tx_begin_lsn;
+                self.pending.retain(|(h, _)| h.tx_begin_lsn != tx);
+                Ok(())
+            },
+            _ if hdr.tx_begin_lsn != 0 => {
+                self.pending.push((*hdr, payload.to_vec()));
+                Ok(())
+            },
+            _ => apply(hdr, payload),
+        }
+    }
+
+    /// Records still waiting on a commit (an open or torn transaction).
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+}
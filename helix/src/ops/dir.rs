@@ -31,11 +31,14 @@ pub fn mkdir<B: BlockIo>(
         return Err(HelixError::PathTooLong);
     }
 
-    if index.lookup(&normalized).is_some() {
+    if index.lookup(block_io, &normalized)?.is_some() {
         return Err(HelixError::AlreadyExists);
     }
     // A directory must not collide with an existing file of the same name.
-    if index.lookup(normalized.trim_end_matches('/')).is_some() {
+    if index
+        .lookup(block_io, normalized.trim_end_matches('/'))?
+        .is_some()
+    {
         return Err(HelixError::AlreadyExists);
     }
 
@@ -54,7 +57,11 @@ pub fn mkdir<B: BlockIo>(
 }
 
 /// Returns direct children only; names, not full paths.
-pub fn readdir(index: &NamespaceIndex, dir_path: &str) -> Result<Vec<DirEntry>, HelixError> {
+pub fn readdir<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    dir_path: &str,
+) -> Result<Vec<DirEntry>, HelixError> {
     let normalized = if dir_path == "/" {
         String::from("/")
     } else if dir_path.ends_with('/') {
@@ -66,7 +73,9 @@ pub fn readdir(index: &NamespaceIndex, dir_path: &str) -> Result<Vec<DirEntry>,
     };
 
     if normalized != "/" {
-        let dir_entry = index.lookup(&normalized).ok_or(HelixError::NotFound)?;
+        let dir_entry = index
+            .lookup(block_io, &normalized)?
+            .ok_or(HelixError::NotFound)?;
         if dir_entry.flags & entry_flags::IS_DIR == 0 {
             return Err(HelixError::NotADirectory);
         }
@@ -75,7 +84,7 @@ pub fn readdir(index: &NamespaceIndex, dir_path: &str) -> Result<Vec<DirEntry>,
         }
     }
 
-    let children = index.readdir(&normalized);
+    let children = index.readdir(block_io, &normalized)?;
 
     let mut entries = Vec::with_capacity(children.len());
     for child in children {
@@ -92,18 +101,22 @@ pub fn readdir(index: &NamespaceIndex, dir_path: &str) -> Result<Vec<DirEntry>,
             btree::filename(name_str)
         };
 
-        if filename.is_empty() {
+        // `..` is the link record directory (see `link`).
+        if filename.is_empty() || filename == ".." {
             continue;
         }
 
-        let is_dir = child.flags & entry_flags::IS_DIR != 0;
+        let child = super::link::resolve(block_io, index, child)?;
+        let d_type = if child.flags & entry_flags::IS_DIR != 0 {
+            morpheus_foundation::flags::dirent_type::DT_DIR
+        } else if child.flags & entry_flags::IS_SYMLINK != 0 {
+            morpheus_foundation::flags::dirent_type::DT_LNK
+        } else {
+            morpheus_foundation::flags::dirent_type::DT_REG
+        };
         let mut dir_entry = DirEntry {
             size: child.size,
-            d_type: if is_dir {
-                morpheus_foundation::flags::dirent_type::DT_DIR
-            } else {
-                morpheus_foundation::flags::dirent_type::DT_REG
-            },
+            d_type,
             modified_ns: child.modified_ns,
             version_count: child.version_count,
             ..DirEntry::zeroed()
@@ -121,33 +134,29 @@ pub fn readdir(index: &NamespaceIndex, dir_path: &str) -> Result<Vec<DirEntry>,
 }
 
 /// Directories must be empty. Inline data and directories own no blocks; extent
-/// files free every run (and the extent-node block for fragmented ones).
+/// files release every run (and the extent-node block for fragmented ones),
+/// which frees them once no deduplicated sibling still shares them. Any entry's
+/// extended-attribute block is freed with it. `reclaim == false` (a snapshot
+/// still sees the entry) keeps every block allocated.
 #[allow(clippy::too_many_arguments)]
 pub fn unlink<B: BlockIo>(
     block_io: &mut B,
     log: &mut LogEngine,
     index: &mut NamespaceIndex,
     bitmap: &mut crate::bitmap::BlockBitmap,
+    dedup: &mut crate::dedup::DedupTable,
     partition_lba_start: u64,
     data_start_block: u64,
     device_block_size: u32,
     path: &str,
+    reclaim: bool,
     timestamp_ns: u64,
 ) -> Result<Lsn, HelixError> {
     // Capture before any &mut borrow of index. Path may lack trailing '/'.
-    let (extent_root, size, is_inline, is_dir, is_node) = {
-        let entry = index.lookup_flex(path).ok_or(HelixError::NotFound)?;
-        if entry.flags & entry_flags::IS_DELETED != 0 {
-            return Err(HelixError::NotFound);
-        }
-        (
-            entry.extent_root,
-            entry.size,
-            entry.flags & entry_flags::IS_INLINE != 0,
-            entry.flags & entry_flags::IS_DIR != 0,
-            entry.flags & entry_flags::IS_EXTENT_NODE != 0,
-        )
-    };
+    let entry = index
+        .lookup_flex(block_io, path)?
+        .ok_or(HelixError::NotFound)?;
+    let is_dir = entry.flags & entry_flags::IS_DIR != 0;
 
     if is_dir {
         let normalized = if path.ends_with('/') {
@@ -158,13 +167,13 @@ pub fn unlink<B: BlockIo>(
             s
         };
 
-        let children = index.readdir(&normalized);
+        let children = index.readdir(block_io, &normalized)?;
         if !children.is_empty() {
             return Err(HelixError::DirectoryNotEmpty);
         }
     }
 
-    let actual_path = if index.lookup(path).is_some() {
+    let actual_path = if index.lookup(block_io, path)?.is_some() {
         String::from(path)
     } else if !path.ends_with('/') {
         let mut s = String::from(path);
@@ -179,11 +188,30 @@ pub fn unlink<B: BlockIo>(
     payload.extend_from_slice(&(del_bytes.len() as u16).to_le_bytes());
     payload.extend_from_slice(del_bytes);
     let lsn = log.append(block_io, LogOp::Delete, hash, &payload, timestamp_ns)?;
-    index.mark_deleted(&actual_path)?;
+    index.mark_deleted(block_io, &actual_path)?;
+    // A hard-linked name owns nothing; its last one releases the record.
+    let entry = if entry.flags & entry_flags::IS_HARDLINK != 0 {
+        match super::link::drop_name(block_io, index, &entry)? {
+            Some(record) => record,
+            None => return Ok(lsn),
+        }
+    } else {
+        entry
+    };
+    if !reclaim {
+        return Ok(lsn);
+    }
+    let (extent_root, size, is_inline, is_node, xattr_block) = (
+        entry.extent_root,
+        crate::dedup::extent_of(&entry).map_or(entry.size, |ext| ext.1),
+        entry.flags & entry_flags::IS_INLINE != 0,
+        entry.flags & entry_flags::IS_EXTENT_NODE != 0,
+        (entry.xattr_len != 0).then_some(entry.xattr_block),
+    );
 
     // Inline + dirs own no blocks.
     if !is_inline && !is_dir {
-        crate::extent::free_file_blocks(
+        dedup.release(
             block_io,
             bitmap,
             partition_lba_start,
@@ -194,6 +222,9 @@ pub fn unlink<B: BlockIo>(
             is_node,
         );
     }
+    if let Some(block) = xattr_block {
+        let _ = bitmap.free_block(block);
+    }
 
     Ok(lsn)
 }
@@ -217,7 +248,7 @@ fn ensure_parent_dirs<B: BlockIo>(
         current.push_str(part);
         current.push('/');
 
-        if index.lookup(&current).is_none() {
+        if index.lookup(block_io, &current)?.is_none() {
             let hash = fnv1a_64(current.as_bytes());
             let dir_bytes = current.as_bytes();
             let mut dir_payload = Vec::with_capacity(2 + dir_bytes.len());
@@ -0,0 +1,338 @@
+//! Symbolic and hard links.
+//!
+//! A symbolic link is an `IS_SYMLINK` entry whose content is its target,
+//! stored like a small file's: inline up to `INLINE_DATA_SIZE`, else in one
+//! block. Nothing here follows one; resolving paths is the VFS's job.
+//!
+//! A hard-linked file keeps its content in a link record under `LINK_DIR`,
+//! and each of its names is an `IS_HARDLINK` entry carrying the record's id.
+//! `..` is never a valid path component, so no user path reaches a record.
+//! The first `Link` of a file moves it into a record whose id is that
+//! record's LSN, leaving two names. Later ones add a name. Unlinking a name
+//! drops the record's `link_count`, and the last name takes the record with
+//! it. Content operations on a name go to the record (`content_path`).
+
+use crate::bitmap::BlockBitmap;
+use crate::crc::{crc64, fnv1a_64};
+use crate::error::HelixError;
+use crate::index::btree::{self, NamespaceIndex};
+use crate::log::LogEngine;
+use crate::types::*;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+
+/// Directory holding every link record.
+pub const LINK_DIR: &str = "/../";
+
+/// Path of the link record `id`.
+pub fn record_path(id: u64) -> String {
+    format!("{}{:016x}", LINK_DIR, id)
+}
+
+/// Whether `path` names a link record (or their directory).
+pub fn is_record(path: &str) -> bool {
+    path.starts_with(LINK_DIR) || path == "/.."
+}
+
+/// `btree::validate_path`, letting link records through. For operations that
+/// rewrite the content of a path already resolved by `content_path`.
+pub(crate) fn validate_content_path(path: &str) -> Result<(), HelixError> {
+    if is_record(path) && path.len() > LINK_DIR.len() {
+        return Ok(());
+    }
+    btree::validate_path(path)
+}
+
+/// The entry holding `entry`'s content: its link record for a hard-linked
+/// name, else `entry` itself.
+pub fn resolve<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    entry: IndexEntry,
+) -> Result<IndexEntry, HelixError> {
+    if entry.flags & entry_flags::IS_HARDLINK == 0 {
+        return Ok(entry);
+    }
+    index
+        .lookup(block_io, &record_path(entry.link_id))?
+        .ok_or(HelixError::NotFound)
+}
+
+/// Where `path`'s content lives: its link record for a hard-linked name,
+/// else `path` itself (whether it exists or not).
+pub fn content_path<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    path: &str,
+) -> Result<String, HelixError> {
+    match index.lookup(block_io, path)? {
+        Some(e) if e.flags & entry_flags::IS_HARDLINK != 0 => Ok(record_path(e.link_id)),
+        _ => Ok(String::from(path)),
+    }
+}
+
+fn set_path(entry: &mut IndexEntry, path: &str) {
+    let bytes = path.as_bytes();
+    let len = bytes.len().min(MAX_PATH_LEN);
+    entry.key = fnv1a_64(bytes);
+    entry.path = [0u8; 256];
+    entry.path[..len].copy_from_slice(&bytes[..len]);
+}
+
+fn symlink_entry(
+    path: &str,
+    target: &[u8],
+    extent_root: BlockAddr,
+    lsn: Lsn,
+    timestamp_ns: u64,
+) -> IndexEntry {
+    let mut entry = NamespaceIndex::make_file_entry(
+        path,
+        lsn,
+        target.len() as u64,
+        timestamp_ns,
+        Some(target),
+        extent_root,
+        crc64(target),
+    );
+    entry.flags |= entry_flags::IS_SYMLINK;
+    entry
+}
+
+fn name_entry(path: &str, id: u64, lsn: Lsn, timestamp_ns: u64) -> IndexEntry {
+    let mut entry =
+        NamespaceIndex::make_file_entry(path, lsn, 0, timestamp_ns, None, BLOCK_NULL, 0);
+    entry.flags |= entry_flags::IS_HARDLINK;
+    entry.link_id = id;
+    entry
+}
+
+/// Create `path` as a symbolic link to `target`, which is stored verbatim and
+/// need not exist. Logs a `Symlink` with payload
+/// `[path_len: u16][path][extent_root: u64][target]`; `extent_root` is
+/// `BLOCK_NULL` for an inline target.
+#[allow(clippy::too_many_arguments)]
+pub fn symlink<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    target: &str,
+    path: &str,
+    timestamp_ns: u64,
+) -> Result<Lsn, HelixError> {
+    btree::validate_path(path)?;
+    if path.ends_with('/') {
+        return Err(HelixError::PathInvalid);
+    }
+    if target.is_empty() {
+        return Err(HelixError::PathInvalid);
+    }
+    if target.len() > MAX_PATH_LEN {
+        return Err(HelixError::PathTooLong);
+    }
+    if index.lookup_flex(block_io, path)?.is_some() {
+        return Err(HelixError::AlreadyExists);
+    }
+    super::write::ensure_parent_dirs(block_io, log, index, path, timestamp_ns)?;
+
+    let target = target.as_bytes();
+    let extent_root = if target.len() > INLINE_DATA_SIZE {
+        let block = bitmap.alloc_block()?;
+        let mut buf = vec![0u8; BLOCK_SIZE as usize];
+        buf[..target.len()].copy_from_slice(target);
+        let scale = BLOCK_SIZE as u64 / device_block_size as u64;
+        let lba = Lba(partition_lba_start + (data_start_block + block) * scale);
+        if block_io.write_blocks(lba, &buf).is_err() {
+            let _ = bitmap.free_block(block);
+            return Err(HelixError::IoWriteFailed);
+        }
+        block
+    } else {
+        BLOCK_NULL
+    };
+
+    let path_b = path.as_bytes();
+    let mut payload = Vec::with_capacity(2 + path_b.len() + 8 + target.len());
+    payload.extend_from_slice(&(path_b.len() as u16).to_le_bytes());
+    payload.extend_from_slice(path_b);
+    payload.extend_from_slice(&extent_root.to_le_bytes());
+    payload.extend_from_slice(target);
+    let lsn = match log.append(
+        block_io,
+        LogOp::Symlink,
+        fnv1a_64(path_b),
+        &payload,
+        timestamp_ns,
+    ) {
+        Ok(lsn) => lsn,
+        Err(e) => {
+            if extent_root != BLOCK_NULL {
+                let _ = bitmap.free_block(extent_root);
+            }
+            return Err(e);
+        },
+    };
+
+    index.upsert(symlink_entry(path, target, extent_root, lsn, timestamp_ns));
+    Ok(lsn)
+}
+
+/// Replay a `Symlink` record; `rest` follows the payload's path.
+pub(crate) fn apply_symlink(
+    index: &mut NamespaceIndex,
+    path: &str,
+    rest: &[u8],
+    lsn: Lsn,
+    timestamp_ns: u64,
+) {
+    if rest.len() < 8 {
+        return;
+    }
+    let extent_root = u64::from_le_bytes(rest[..8].try_into().unwrap_or([0u8; 8]));
+    let target = &rest[8..];
+    if target.is_empty() || target.len() > MAX_PATH_LEN {
+        return;
+    }
+    index.upsert(symlink_entry(path, target, extent_root, lsn, timestamp_ns));
+}
+
+/// Target of the symbolic link `path`. A hard-linked name of one resolves.
+pub fn readlink<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    path: &str,
+) -> Result<String, HelixError> {
+    let entry = index.lookup(block_io, path)?.ok_or(HelixError::NotFound)?;
+    let entry = resolve(block_io, index, entry)?;
+    if entry.flags & entry_flags::IS_SYMLINK == 0 {
+        return Err(HelixError::NotASymlink);
+    }
+    let target = super::read::read_file(
+        block_io,
+        index,
+        partition_lba_start,
+        data_start_block,
+        device_block_size,
+        btree::path_str(&entry.path),
+    )?;
+    String::from_utf8(target).map_err(|_| HelixError::PathInvalid)
+}
+
+/// Give the file at `old` the further name `new`. Logs a `Link` with the
+/// `Rename` payload `[old_len: u16][old][new_len: u16][new]`.
+pub fn link<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    old: &str,
+    new: &str,
+    timestamp_ns: u64,
+) -> Result<Lsn, HelixError> {
+    btree::validate_path(new)?;
+    if new.ends_with('/') {
+        return Err(HelixError::PathInvalid);
+    }
+    let src = index
+        .lookup_flex(block_io, old)?
+        .ok_or(HelixError::NotFound)?;
+    if src.flags & entry_flags::IS_DIR != 0 {
+        return Err(HelixError::IsADirectory);
+    }
+    if index.lookup_flex(block_io, new)?.is_some() {
+        return Err(HelixError::AlreadyExists);
+    }
+
+    super::write::ensure_parent_dirs(block_io, log, index, new, timestamp_ns)?;
+    if src.flags & entry_flags::IS_HARDLINK == 0 {
+        // The record will be created in `LINK_DIR`.
+        super::write::ensure_parent_dirs(block_io, log, index, &record_path(0), timestamp_ns)?;
+    }
+
+    let (old_b, new_b) = (old.as_bytes(), new.as_bytes());
+    let mut payload = Vec::with_capacity(4 + old_b.len() + new_b.len());
+    payload.extend_from_slice(&(old_b.len() as u16).to_le_bytes());
+    payload.extend_from_slice(old_b);
+    payload.extend_from_slice(&(new_b.len() as u16).to_le_bytes());
+    payload.extend_from_slice(new_b);
+    let lsn = log.append_full(
+        block_io,
+        LogOp::Link,
+        0,
+        fnv1a_64(new_b),
+        fnv1a_64(old_b),
+        0,
+        &payload,
+        timestamp_ns,
+    )?;
+    apply_link(block_io, index, old, new, lsn, timestamp_ns)?;
+    Ok(lsn)
+}
+
+/// The index half of `link`, shared with replay. A file that is not linked
+/// yet moves into the record `lsn`, its name becoming the first of two.
+pub(crate) fn apply_link<B: BlockIo>(
+    block_io: &mut B,
+    index: &mut NamespaceIndex,
+    old: &str,
+    new: &str,
+    lsn: Lsn,
+    timestamp_ns: u64,
+) -> Result<(), HelixError> {
+    let Some(src) = index.lookup(block_io, old)? else {
+        return Ok(());
+    };
+    let id = if src.flags & entry_flags::IS_HARDLINK != 0 {
+        let Some(mut record) = index.lookup(block_io, &record_path(src.link_id))? else {
+            return Ok(());
+        };
+        record.link_count += 1;
+        index.upsert(record);
+        src.link_id
+    } else {
+        let mut record = src;
+        set_path(&mut record, &record_path(lsn));
+        record.link_count = 2;
+        index.upsert(record);
+        let mut first = name_entry(old, lsn, lsn, timestamp_ns);
+        first.created_ns = src.created_ns;
+        index.upsert(first);
+        lsn
+    };
+    index.upsert(name_entry(new, id, lsn, timestamp_ns));
+    Ok(())
+}
+
+/// Account for the removal of `name` from the namespace. For a hard-linked
+/// name the record loses a link; the last one removes the record, which is
+/// returned so the caller can reclaim what it owns. Shared with replay.
+pub(crate) fn drop_name<B: BlockIo>(
+    block_io: &mut B,
+    index: &mut NamespaceIndex,
+    name: &IndexEntry,
+) -> Result<Option<IndexEntry>, HelixError> {
+    if name.flags & entry_flags::IS_HARDLINK == 0 {
+        return Ok(None);
+    }
+    let path = record_path(name.link_id);
+    let Some(mut record) = index.lookup(block_io, &path)? else {
+        return Ok(None);
+    };
+    if record.link_count > 1 {
+        record.link_count -= 1;
+        index.upsert(record);
+        return Ok(None);
+    }
+    index.mark_deleted(block_io, &path)?;
+    Ok(Some(record))
+}
@@ -1,5 +1,12 @@
 //! High-level fs ops over (log, index, bitmap).
 
 pub mod dir;
+pub mod link;
+pub mod owner;
+pub mod quota;
 pub mod read;
+pub mod retain;
+pub mod snapshot;
+pub mod stream;
 pub mod write;
+pub mod xattr;
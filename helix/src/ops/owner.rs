@@ -0,0 +1,126 @@
+//! Ownership and permission bits.
+//!
+//! Every entry has an owner `uid`/`gid` and permission bits, stored in the
+//! entry itself and changed by a `SetOwner` record with payload
+//! `[path_len: u16][path][uid: u32][gid: u32][mode: u32]`. Entries written
+//! before they existed (no `HAS_OWNER`) belong to root with `default_mode`.
+//! Nothing here enforces them; that is the VFS's job. A hard-linked file's
+//! owner lives in its link record, like its content.
+
+use crate::crc::fnv1a_64;
+use crate::error::HelixError;
+use crate::index::btree::{self, NamespaceIndex};
+use crate::log::LogEngine;
+use crate::types::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+
+/// The permission bits `chmod` may set.
+pub const PERM_MASK: u32 = 0o7777;
+
+/// Owner and permission bits of one entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Owner {
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+}
+
+/// Permission bits of an entry that has never been given any.
+pub fn default_mode(e: &IndexEntry) -> u32 {
+    if e.flags & (entry_flags::IS_DIR | entry_flags::IS_SYMLINK) != 0 {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+/// The owner a newly created entry of `e`'s kind gets.
+pub fn initial(e: &IndexEntry) -> Owner {
+    Owner {
+        uid: 0,
+        gid: 0,
+        mode: default_mode(e),
+    }
+}
+
+/// `e`'s owner, defaults filled in.
+pub fn of(e: &IndexEntry) -> Owner {
+    if e.flags & entry_flags::HAS_OWNER == 0 {
+        return initial(e);
+    }
+    Owner {
+        uid: e.uid,
+        gid: e.gid,
+        mode: e.mode & PERM_MASK,
+    }
+}
+
+fn install(entry: &mut IndexEntry, owner: Owner) {
+    entry.flags |= entry_flags::HAS_OWNER;
+    entry.uid = owner.uid;
+    entry.gid = owner.gid;
+    entry.mode = owner.mode & PERM_MASK;
+}
+
+/// Give `path` a new owner and permission bits. Content, version and mtime
+/// are untouched.
+pub fn set<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    path: &str,
+    owner: Owner,
+    timestamp_ns: u64,
+) -> Result<Lsn, HelixError> {
+    // Log under the stored path so directories replay either way.
+    let mut entry = index
+        .lookup_flex(block_io, path)?
+        .ok_or(HelixError::NotFound)?;
+    let canonical = String::from(btree::path_str(&entry.path));
+
+    let path_b = canonical.as_bytes();
+    let mut payload = Vec::with_capacity(2 + path_b.len() + 12);
+    payload.extend_from_slice(&(path_b.len() as u16).to_le_bytes());
+    payload.extend_from_slice(path_b);
+    payload.extend_from_slice(&owner.uid.to_le_bytes());
+    payload.extend_from_slice(&owner.gid.to_le_bytes());
+    payload.extend_from_slice(&(owner.mode & PERM_MASK).to_le_bytes());
+    let lsn = log.append(
+        block_io,
+        LogOp::SetOwner,
+        fnv1a_64(path_b),
+        &payload,
+        timestamp_ns,
+    )?;
+
+    install(&mut entry, owner);
+    index.upsert(entry);
+    Ok(lsn)
+}
+
+/// Replay a `SetOwner` record; `rest` follows the payload's path.
+pub(crate) fn apply_set<B: BlockIo>(
+    block_io: &mut B,
+    index: &mut NamespaceIndex,
+    path: &str,
+    rest: &[u8],
+) -> Result<(), HelixError> {
+    if rest.len() < 12 {
+        return Ok(());
+    }
+    let word = |i: usize| u32::from_le_bytes(rest[i..i + 4].try_into().unwrap());
+    if let Some(mut entry) = index.lookup(block_io, path)? {
+        install(
+            &mut entry,
+            Owner {
+                uid: word(0),
+                gid: word(4),
+                mode: word(8),
+            },
+        );
+        index.upsert(entry);
+    }
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Directory quotas.
+//!
+//! A `helix.quota` attribute on a directory limits its subtree: `bytes:N` of
+//! file content, `entries:N` names, or both joined by `,`; 0 or a missing
+//! limit is unlimited. Every name below the directory counts once and every
+//! file its size, nested quotas included. Hard-linked content lives in a
+//! link record and counts nowhere; its names still count.
+//!
+//! The index keeps each quota's figures current as entries change
+//! (`NamespaceIndex::upsert` and `mark_deleted`), so a check is a compare.
+//! An entry replacing a checkpointed one is charged at once, but the one it
+//! replaced is only taken off by `NamespaceIndex::settle`, which needs the
+//! device; the engine settles before it checks a limit or checkpoints. A
+//! transaction abort puts the figures back with the rest of the overlay.
+//!
+//! The quotas and their figures as of the last checkpoint live in a region
+//! the superblock names (`quota_block`, `quota_blocks`, `quota_count`,
+//! `quota_crc`), copy-on-write like the history. Mount charges the entries
+//! the ring changed since, then picks up directories whose attribute the
+//! ring set, changed or dropped.
+
+use crate::bitmap::BlockBitmap;
+use crate::crc::crc32c;
+use crate::error::HelixError;
+use crate::index::btree;
+use crate::ops::link::is_record;
+use crate::types::*;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+
+/// Policy attribute; see `morpheus_foundation::storage::XATTR_QUOTA`.
+pub const POLICY_XATTR: &str = morpheus_foundation::storage::XATTR_QUOTA;
+
+const ENTRY_SIZE: usize = core::mem::size_of::<QuotaEntry>();
+
+/// What a quota allows; 0 is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limit {
+    pub bytes: u64,
+    pub entries: u64,
+}
+
+impl Limit {
+    /// Parse the attribute syntax: `bytes:N`, `entries:N`, or both joined
+    /// by `,`.
+    pub fn parse(value: &[u8]) -> Option<Self> {
+        let mut limit = Self::default();
+        for part in core::str::from_utf8(value).ok()?.split(',') {
+            let (kind, n) = part.trim().split_once(':')?;
+            let n: u64 = n.parse().ok()?;
+            match kind {
+                "bytes" => limit.bytes = n,
+                "entries" => limit.entries = n,
+                _ => return None,
+            }
+        }
+        Some(limit)
+    }
+
+    pub fn is_unlimited(self) -> bool {
+        self.bytes == 0 && self.entries == 0
+    }
+
+    /// Whether a subtree holding `used` is within the limit.
+    pub fn admits(self, used: Usage) -> bool {
+        (self.bytes == 0 || used.bytes <= self.bytes)
+            && (self.entries == 0 || used.entries <= self.entries)
+    }
+}
+
+/// What a subtree holds, or what an entry adds to every quota above it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub bytes: u64,
+    pub entries: u64,
+}
+
+impl Usage {
+    /// What `e` counts for: nothing for a tombstone or a link record, one
+    /// name, and the content of a file that holds its own.
+    pub fn of(e: &IndexEntry) -> Self {
+        if e.flags & entry_flags::IS_DELETED != 0 || is_record(btree::path_str(&e.path)) {
+            return Self::default();
+        }
+        let own = entry_flags::IS_DIR | entry_flags::IS_HARDLINK;
+        Self {
+            bytes: if e.flags & own == 0 { e.size } else { 0 },
+            entries: 1,
+        }
+    }
+
+    pub fn add(&mut self, other: Self) {
+        self.bytes = self.bytes.saturating_add(other.bytes);
+        self.entries = self.entries.saturating_add(other.entries);
+    }
+
+    pub fn sub(&mut self, other: Self) {
+        self.bytes = self.bytes.saturating_sub(other.bytes);
+        self.entries = self.entries.saturating_sub(other.entries);
+    }
+
+    pub fn plus(mut self, other: Self) -> Self {
+        self.add(other);
+        self
+    }
+}
+
+/// One directory's quota and what its subtree holds now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quota {
+    /// The directory, with its trailing `/`.
+    pub dir: String,
+    pub limit: Limit,
+    pub used: Usage,
+}
+
+impl Quota {
+    /// Whether `path` lies below the directory.
+    pub fn covers(&self, path: &str) -> bool {
+        path.len() > self.dir.len() && path.starts_with(self.dir.as_str()) && !is_record(path)
+    }
+
+    fn to_entry(&self) -> QuotaEntry {
+        let mut e = QuotaEntry {
+            path: [0u8; 256],
+            max_bytes: self.limit.bytes,
+            max_entries: self.limit.entries,
+            bytes: self.used.bytes,
+            entries: self.used.entries,
+            _reserved: [0u8; 224],
+        };
+        let p = self.dir.as_bytes();
+        let n = p.len().min(MAX_PATH_LEN);
+        e.path[..n].copy_from_slice(&p[..n]);
+        e
+    }
+
+    fn from_entry(e: &QuotaEntry) -> Self {
+        Self {
+            dir: String::from(btree::path_str(&e.path)),
+            limit: Limit {
+                bytes: e.max_bytes,
+                entries: e.max_entries,
+            },
+            used: Usage {
+                bytes: e.bytes,
+                entries: e.entries,
+            },
+        }
+    }
+}
+
+/// `path` in the form directories are stored under: trailing `/`.
+pub fn dir_form(path: &str) -> String {
+    let mut dir = String::from(path);
+    if !dir.ends_with('/') {
+        dir.push('/');
+    }
+    dir
+}
+
+fn region_lba(partition_lba_start: u64, data_start_block: u64, dbs: u32, rel_block: u64) -> Lba {
+    let scale = BLOCK_SIZE as u64 / dbs as u64;
+    Lba(partition_lba_start + (data_start_block + rel_block) * scale)
+}
+
+fn as_bytes(entries: &[QuotaEntry]) -> &[u8] {
+    // SAFETY: QuotaEntry is repr(C) plain data without padding.
+    unsafe {
+        core::slice::from_raw_parts(entries.as_ptr() as *const u8, entries.len() * ENTRY_SIZE)
+    }
+}
+
+fn from_bytes(bytes: &[u8]) -> QuotaEntry {
+    // SAFETY: every bit pattern is a valid QuotaEntry.
+    unsafe { core::ptr::read_unaligned(bytes.as_ptr() as *const QuotaEntry) }
+}
+
+/// Read the region the superblock names.
+pub fn load_region<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    sb: &HelixSuperblock,
+) -> Result<Vec<Quota>, HelixError> {
+    let count = sb.quota_count as usize;
+    if count == 0 || sb.quota_block == BLOCK_NULL {
+        return Ok(Vec::new());
+    }
+    let bytes_len = count
+        .checked_mul(ENTRY_SIZE)
+        .ok_or(HelixError::IndexCrcMismatch)?;
+    let blocks = bytes_len.div_ceil(BLOCK_SIZE as usize);
+    if blocks as u64 > sb.quota_blocks as u64 {
+        return Err(HelixError::IndexCrcMismatch);
+    }
+    let mut buf = vec![0u8; blocks * BLOCK_SIZE as usize];
+    block_io
+        .read_blocks(
+            region_lba(
+                partition_lba_start,
+                sb.data_start_block,
+                device_block_size,
+                sb.quota_block,
+            ),
+            &mut buf,
+        )
+        .map_err(|_| HelixError::IoReadFailed)?;
+    let bytes = &buf[..bytes_len];
+    if crc32c(bytes) != sb.quota_crc {
+        return Err(HelixError::IndexCrcMismatch);
+    }
+    Ok(bytes
+        .chunks_exact(ENTRY_SIZE)
+        .map(|b| Quota::from_entry(&from_bytes(b)))
+        .collect())
+}
+
+/// Write `quotas` to a fresh region and point `sb` at it. The caller
+/// persists the superblock, then frees the returned superseded region, if
+/// any, as `(start, blocks)`.
+pub fn store_region<B: BlockIo>(
+    block_io: &mut B,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    sb: &mut HelixSuperblock,
+    quotas: &[Quota],
+) -> Result<Option<(BlockAddr, u64)>, HelixError> {
+    let old = (sb.quota_count != 0 && sb.quota_block != BLOCK_NULL)
+        .then_some((sb.quota_block, sb.quota_blocks as u64));
+    let entries: Vec<QuotaEntry> = quotas.iter().map(Quota::to_entry).collect();
+    let bytes = as_bytes(&entries);
+    let blocks = bytes.len().div_ceil(BLOCK_SIZE as usize) as u64;
+    let start = if entries.is_empty() {
+        BLOCK_NULL
+    } else {
+        let start = bitmap.alloc_contiguous(blocks)?;
+        let mut buf = vec![0u8; blocks as usize * BLOCK_SIZE as usize];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let lba = region_lba(
+            partition_lba_start,
+            sb.data_start_block,
+            device_block_size,
+            start,
+        );
+        if block_io.write_blocks(lba, &buf).is_err() {
+            let _ = bitmap.free_range(start, blocks);
+            return Err(HelixError::IoWriteFailed);
+        }
+        start
+    };
+    sb.quota_block = start;
+    sb.quota_blocks = blocks as u32;
+    sb.quota_count = entries.len() as u64;
+    sb.quota_crc = crc32c(bytes);
+    Ok(old)
+}
@@ -3,6 +3,7 @@
 use crate::crc::fnv1a_64;
 use crate::error::HelixError;
 use crate::index::btree::NamespaceIndex;
+use crate::log::tx::TxGate;
 use crate::log::LogEngine;
 use crate::types::*;
 use alloc::vec;
@@ -19,16 +20,28 @@ pub fn read_file<B: BlockIo>(
     device_block_size: u32,
     path: &str,
 ) -> Result<Vec<u8>, HelixError> {
-    let entry = index.lookup(path).ok_or(HelixError::NotFound)?;
+    let entry = index.lookup(block_io, path)?.ok_or(HelixError::NotFound)?;
 
     if entry.flags & entry_flags::IS_DIR != 0 {
         return Err(HelixError::IsADirectory);
     }
+    read_entry(
+        block_io,
+        partition_lba_start,
+        data_region_start_block,
+        device_block_size,
+        &entry,
+    )
+}
 
-    if entry.flags & entry_flags::IS_DELETED != 0 {
-        return Err(HelixError::NotFound);
-    }
-
+/// Whole content of a file entry, whatever its storage.
+pub(crate) fn read_entry<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    data_region_start_block: u64,
+    device_block_size: u32,
+    entry: &IndexEntry,
+) -> Result<Vec<u8>, HelixError> {
     if entry.flags & entry_flags::IS_INLINE != 0 {
         let size = entry.size as usize;
         let mut data = vec![0u8; size];
@@ -36,6 +49,23 @@ pub fn read_file<B: BlockIo>(
         return Ok(data);
     }
 
+    if entry.flags & entry_flags::IS_COMPRESSED != 0 {
+        let data = read_compressed(
+            block_io,
+            partition_lba_start,
+            data_region_start_block,
+            device_block_size,
+            entry.extent_root,
+            entry.size,
+            entry.stored_len,
+        )?;
+        // Chunks decode without a content check; the whole file has one.
+        if entry.content_crc64 != 0 && crate::crc::crc64(&data) != entry.content_crc64 {
+            return Err(HelixError::ExtentCorrupt);
+        }
+        return Ok(data);
+    }
+
     if entry.flags & entry_flags::IS_EXTENT_NODE != 0 {
         return crate::extent::read_extent_file(
             block_io,
@@ -57,6 +87,81 @@ pub fn read_file<B: BlockIo>(
     )
 }
 
+/// Copy up to `buf.len()` bytes starting at `offset`, reading only the blocks
+/// the range covers. Holes read as zeros. Returns bytes copied (0 at/past EOF).
+#[allow(clippy::too_many_arguments)]
+pub fn read_file_range<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    partition_lba_start: u64,
+    data_region_start_block: u64,
+    device_block_size: u32,
+    path: &str,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<usize, HelixError> {
+    let entry = index.lookup(block_io, path)?.ok_or(HelixError::NotFound)?;
+
+    if entry.flags & entry_flags::IS_DIR != 0 {
+        return Err(HelixError::IsADirectory);
+    }
+
+    if offset >= entry.size {
+        return Ok(0);
+    }
+    let n = (buf.len() as u64).min(entry.size - offset) as usize;
+
+    if entry.flags & entry_flags::IS_INLINE != 0 {
+        let start = offset as usize;
+        buf[..n].copy_from_slice(&entry.inline_data[start..start + n]);
+        return Ok(n);
+    }
+
+    if entry.flags & entry_flags::IS_COMPRESSED != 0 {
+        read_compressed_range(
+            block_io,
+            partition_lba_start,
+            data_region_start_block,
+            device_block_size,
+            &entry,
+            offset,
+            &mut buf[..n],
+        )?;
+        return Ok(n);
+    }
+
+    let runs = crate::extent::file_runs(
+        block_io,
+        partition_lba_start,
+        data_region_start_block,
+        device_block_size,
+        entry.extent_root,
+        entry.size,
+        entry.flags & entry_flags::IS_EXTENT_NODE != 0,
+    )?;
+
+    let scale = BLOCK_SIZE as u64 / device_block_size as u64;
+    let mut block_buf = vec![0u8; BLOCK_SIZE as usize];
+    let mut done = 0usize;
+    while done < n {
+        let pos = offset + done as u64;
+        let within = (pos % BLOCK_SIZE as u64) as usize;
+        let chunk = (BLOCK_SIZE as usize - within).min(n - done);
+        match crate::extent::map_logical(&runs, pos / BLOCK_SIZE as u64) {
+            Some(physical) => {
+                let lba = Lba(partition_lba_start + (data_region_start_block + physical) * scale);
+                block_io
+                    .read_blocks(lba, &mut block_buf)
+                    .map_err(|_| HelixError::IoReadFailed)?;
+                buf[done..done + chunk].copy_from_slice(&block_buf[within..within + chunk]);
+            },
+            None => buf[done..done + chunk].fill(0),
+        }
+        done += chunk;
+    }
+    Ok(n)
+}
+
 /// Replay tail..=`target_lsn`; returns state as of that LSN.
 pub fn read_file_at_lsn<B: BlockIo>(
     block_io: &mut B,
@@ -73,6 +178,8 @@ pub fn read_file_at_lsn<B: BlockIo>(
     let mut last_extent_root: Option<u64> = None;
     let mut last_file_size: Option<u64> = None;
     let mut last_is_node = false;
+    let mut last_stored_len: Option<u64> = None;
+    let mut gate = TxGate::new();
 
     // Circular walk: tail -> head.
     let segment_count = log.segment_count();
@@ -95,53 +202,67 @@ pub fn read_file_at_lsn<B: BlockIo>(
                         break;
                     }
 
-                    if header.path_hash == path_hash {
-                        if let Some(op) = LogOp::from_u8(header.op) {
-                            match op {
-                                LogOp::Write => {
-                                    // Payload after [path_len:u16][path]. Extent records
-                                    // are tagged by the IS_EXTENT header flag (never by a
-                                    // payload byte) and carry [kind][file_size][extent_root].
-                                    let data = if payload.len() >= 2 {
-                                        let plen =
-                                            u16::from_le_bytes([payload[0], payload[1]]) as usize;
-                                        let start = 2 + plen;
-                                        if start <= payload.len() {
-                                            &payload[start..]
+                    // Uncommitted transaction records are not part of any version.
+                    gate.feed(&header, &payload, |header, payload| {
+                        if header.path_hash == path_hash {
+                            if let Some(op) = LogOp::from_u8(header.op) {
+                                match op {
+                                    LogOp::Write | LogOp::DedupRef => {
+                                        // Payload after [path_len:u16][path]. Extent records
+                                        // are tagged by the IS_EXTENT header flag (never by a
+                                        // payload byte) and carry [kind][file_size][extent_root].
+                                        let data = if payload.len() >= 2 {
+                                            let plen = u16::from_le_bytes([payload[0], payload[1]])
+                                                as usize;
+                                            let start = 2 + plen;
+                                            if start <= payload.len() {
+                                                &payload[start..]
+                                            } else {
+                                                payload
+                                            }
                                         } else {
-                                            &payload[..]
+                                            payload
+                                        };
+
+                                        if header.flags & rec_flags::IS_EXTENT != 0
+                                            && data.len() >= 17
+                                        {
+                                            last_write_data = None;
+                                            last_file_size = Some(u64::from_le_bytes(
+                                                data[1..9].try_into().unwrap_or([0u8; 8]),
+                                            ));
+                                            last_extent_root = Some(u64::from_le_bytes(
+                                                data[9..17].try_into().unwrap_or([0u8; 8]),
+                                            ));
+                                            last_is_node = data[0] == extent_kind::NODE;
+                                            last_stored_len = (data[0] == extent_kind::COMPRESSED
+                                                && data.len() >= 25)
+                                                .then(|| {
+                                                    u64::from_le_bytes(
+                                                        data[17..25].try_into().unwrap_or([0u8; 8]),
+                                                    )
+                                                });
+                                        } else if data.len() <= INLINE_DATA_SIZE {
+                                            last_write_data = Some(data.to_vec());
+                                            last_extent_root = None;
+                                            last_file_size = None;
+                                            last_is_node = false;
+                                            last_stored_len = None;
                                         }
-                                    } else {
-                                        &payload[..]
-                                    };
-
-                                    if header.flags & rec_flags::IS_EXTENT != 0 && data.len() >= 17
-                                    {
+                                    },
+                                    LogOp::Delete => {
                                         last_write_data = None;
-                                        last_file_size = Some(u64::from_le_bytes(
-                                            data[1..9].try_into().unwrap_or([0u8; 8]),
-                                        ));
-                                        last_extent_root = Some(u64::from_le_bytes(
-                                            data[9..17].try_into().unwrap_or([0u8; 8]),
-                                        ));
-                                        last_is_node = data[0] == extent_kind::NODE;
-                                    } else if data.len() <= INLINE_DATA_SIZE {
-                                        last_write_data = Some(data.to_vec());
                                         last_extent_root = None;
                                         last_file_size = None;
                                         last_is_node = false;
-                                    }
-                                },
-                                LogOp::Delete => {
-                                    last_write_data = None;
-                                    last_extent_root = None;
-                                    last_file_size = None;
-                                    last_is_node = false;
-                                },
-                                _ => {},
+                                        last_stored_len = None;
+                                    },
+                                    _ => {},
+                                }
                             }
                         }
-                    }
+                        Ok(())
+                    })?;
 
                     // 8-byte aligned.
                     offset += header.total_size() as u32;
@@ -162,6 +283,17 @@ pub fn read_file_at_lsn<B: BlockIo>(
 
     if let Some(extent_root) = last_extent_root {
         let size = last_file_size.unwrap_or(0);
+        if let Some(stored_len) = last_stored_len {
+            return read_compressed(
+                block_io,
+                partition_lba_start,
+                data_region_start_block,
+                device_block_size,
+                extent_root,
+                size,
+                stored_len,
+            );
+        }
         if last_is_node {
             return crate::extent::read_extent_file(
                 block_io,
@@ -193,6 +325,7 @@ pub fn list_versions<B: BlockIo>(
 ) -> Result<Vec<(Lsn, u64, LogOp)>, HelixError> {
     let path_hash = fnv1a_64(path.as_bytes());
     let mut versions = Vec::new();
+    let mut gate = TxGate::new();
 
     let segment_count = log.segment_count();
     let tail = log.tail_segment();
@@ -209,22 +342,29 @@ pub fn list_versions<B: BlockIo>(
             }
 
             match log.read_record(block_io, seg, offset) {
-                Ok((header, _payload)) => {
-                    if header.path_hash == path_hash {
-                        if let Some(op) = LogOp::from_u8(header.op) {
-                            match op {
-                                LogOp::Write
-                                | LogOp::Append
-                                | LogOp::Delete
-                                | LogOp::Rename
-                                | LogOp::Truncate
-                                | LogOp::SetMeta => {
-                                    versions.push((header.lsn, header.timestamp_ns, op));
-                                },
-                                _ => {},
+                Ok((header, payload)) => {
+                    gate.feed(&header, &payload, |header, _payload| {
+                        if header.path_hash == path_hash {
+                            if let Some(op) = LogOp::from_u8(header.op) {
+                                match op {
+                                    LogOp::Write
+                                    | LogOp::DedupRef
+                                    | LogOp::Append
+                                    | LogOp::Delete
+                                    | LogOp::Rename
+                                    | LogOp::Truncate
+                                    | LogOp::SetMeta
+                                    | LogOp::Symlink
+                                    | LogOp::Link
+                                    | LogOp::SetOwner => {
+                                        versions.push((header.lsn, header.timestamp_ns, op));
+                                    },
+                                    _ => {},
+                                }
                             }
                         }
-                    }
+                        Ok(())
+                    })?;
 
                     offset += header.total_size() as u32;
                 },
@@ -245,34 +385,53 @@ pub fn list_versions<B: BlockIo>(
     Ok(versions)
 }
 
-pub fn stat_file(index: &NamespaceIndex, path: &str) -> Result<FileStat, HelixError> {
+pub fn stat_file<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    path: &str,
+) -> Result<FileStat, HelixError> {
     // lookup_flex: dirs stored with trailing '/', callers usually omit it.
-    let entry = index.lookup_flex(path).ok_or(HelixError::NotFound)?;
-
-    if entry.flags & entry_flags::IS_DELETED != 0 {
-        return Err(HelixError::NotFound);
-    }
-
-    let mode = if entry.flags & entry_flags::IS_DIR != 0 {
-        morpheus_foundation::flags::mode::S_IFDIR
+    let entry = index
+        .lookup_flex(block_io, path)?
+        .ok_or(HelixError::NotFound)?;
+    let entry = super::link::resolve(block_io, index, entry)?;
+
+    // Directories leave `nlink` to the caller, which knows their subdirectories.
+    let nlink = if entry.flags & entry_flags::IS_DIR != 0 {
+        0
     } else {
-        morpheus_foundation::flags::mode::S_IFREG
+        entry.link_count.max(1) as u64
     };
+    let (mode, physical_size) = if entry.flags & entry_flags::IS_DIR != 0 {
+        (morpheus_foundation::flags::mode::S_IFDIR, 0)
+    } else if entry.flags & entry_flags::IS_SYMLINK != 0 {
+        (morpheus_foundation::flags::mode::S_IFLNK, entry.size)
+    } else if entry.flags & entry_flags::IS_COMPRESSED != 0 {
+        (morpheus_foundation::flags::mode::S_IFREG, entry.stored_len)
+    } else {
+        (morpheus_foundation::flags::mode::S_IFREG, entry.size)
+    };
+    let owner = super::owner::of(&entry);
     Ok(FileStat {
         key: entry.key,
         size: entry.size,
-        mode,
+        mode: mode | owner.mode,
+        uid: owner.uid,
+        gid: owner.gid,
         created_ns: entry.created_ns,
         modified_ns: entry.modified_ns,
         accessed_ns: entry.modified_ns,
         version_count: entry.version_count,
         lsn: entry.lsn,
         first_lsn: entry.first_lsn,
+        physical_size,
+        nlink,
         ..FileStat::default()
     })
 }
 
-fn read_extent_data<B: BlockIo>(
+/// The first `file_size` bytes of the contiguous run at `extent_root`.
+pub(crate) fn read_extent_data<B: BlockIo>(
     block_io: &mut B,
     partition_lba_start: u64,
     data_region_start_block: u64,
@@ -312,3 +471,89 @@ fn read_extent_data<B: BlockIo>(
 
     Ok(result)
 }
+
+/// Whole-file decode of a compressed extent.
+pub(crate) fn read_compressed<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    data_region_start_block: u64,
+    device_block_size: u32,
+    extent_root: u64,
+    file_size: u64,
+    stored_len: u64,
+) -> Result<Vec<u8>, HelixError> {
+    let frame = read_extent_data(
+        block_io,
+        partition_lba_start,
+        data_region_start_block,
+        device_block_size,
+        extent_root,
+        stored_len,
+    )?;
+    crate::compress::unpack(&frame, file_size)
+}
+
+/// Fill `buf` from logical `offset` of a compressed extent, decoding only the
+/// chunks the range covers.
+fn read_compressed_range<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    data_region_start_block: u64,
+    device_block_size: u32,
+    entry: &IndexEntry,
+    offset: u64,
+    buf: &mut [u8],
+) -> Result<(), HelixError> {
+    use crate::compress::{chunk_count, header_len, FrameHeader, CHUNK_SIZE};
+    let mut frame_bytes = |from: usize, to: usize| {
+        read_run_bytes(
+            block_io,
+            partition_lba_start,
+            data_region_start_block,
+            device_block_size,
+            entry.extent_root,
+            from,
+            to,
+        )
+    };
+    let hdr_len = header_len(chunk_count(entry.size));
+    if hdr_len as u64 > entry.stored_len {
+        return Err(HelixError::ExtentCorrupt);
+    }
+    let hdr = FrameHeader::parse(&frame_bytes(0, hdr_len)?, entry.size, entry.stored_len)?;
+    let start = offset as usize;
+    let end = start + buf.len();
+    for k in start / CHUNK_SIZE..end.div_ceil(CHUNK_SIZE) {
+        let (s, e) = hdr.span(k);
+        let chunk = hdr.unpack(k, &frame_bytes(s, e)?)?;
+        let base = k * CHUNK_SIZE;
+        let lo = start.max(base);
+        let hi = end.min(base + chunk.len());
+        buf[lo - start..hi - start].copy_from_slice(&chunk[lo - base..hi - base]);
+    }
+    Ok(())
+}
+
+/// Bytes `[from, to)` of the contiguous run starting at `extent_root`.
+fn read_run_bytes<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    data_region_start_block: u64,
+    device_block_size: u32,
+    extent_root: u64,
+    from: usize,
+    to: usize,
+) -> Result<Vec<u8>, HelixError> {
+    let bs = BLOCK_SIZE as usize;
+    let first = from / bs;
+    let mut buf = vec![0u8; (to.div_ceil(bs) - first) * bs];
+    let scale = BLOCK_SIZE as u64 / device_block_size as u64;
+    let lba =
+        Lba(partition_lba_start + (data_region_start_block + extent_root + first as u64) * scale);
+    block_io
+        .read_blocks(lba, &mut buf)
+        .map_err(|_| HelixError::IoReadFailed)?;
+    buf.truncate(to - first * bs);
+    buf.drain(..from - first * bs);
+    Ok(buf)
+}
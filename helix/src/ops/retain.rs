@@ -0,0 +1,302 @@
+//! Version retention.
+//!
+//! An overwrite normally reclaims the prior version at once, unless a
+//! snapshot still sees it. Under a `helix.retention` policy — on the file or
+//! the nearest ancestor directory that has one — the prior version is kept
+//! instead: its blocks stay allocated and a `Retain` record with payload
+//! `[path_len: u16][path][VersionEntry from lsn on]` logs it. Unlink keeps
+//! the last version the same way. Hard-linked files are not versioned.
+//!
+//! - `all`: every version, until pruned by hand;
+//! - `last:N`: the newest N prior versions;
+//! - `within:S`: versions replaced in the last S seconds;
+//! - `none`: nothing, and `versions` stops listing what the ring still names.
+//!
+//! Retained versions form the history, oldest first. The superblock names a
+//! contiguous region holding all of it up to `history_lsn` (`history_block`,
+//! `history_blocks`, `history_count`, `history_crc`); mount adds the `Retain`
+//! records the ring holds after that. The region is copy-on-write like the
+//! snapshot table. A checkpoint applies each file's policy and writes a fresh
+//! region before it recycles the ring; `HelixFs::prune_versions` applies a
+//! given policy at once. Both free what they drop once the shorter history
+//! is durable, unless a snapshot or another file still uses it.
+//! `HelixFs::restore` makes a version current again by sharing its blocks,
+//! counted like any other deduplicated extent.
+
+use crate::bitmap::BlockBitmap;
+use crate::crc::{crc32c, fnv1a_64};
+use crate::error::HelixError;
+use crate::index::btree::{self, NamespaceIndex};
+use crate::log::recovery::decode_path_payload;
+use crate::log::tx::TxGate;
+use crate::log::LogEngine;
+use crate::types::*;
+use alloc::vec;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+
+/// Policy attribute; see `morpheus_foundation::storage::XATTR_RETENTION`.
+pub const POLICY_XATTR: &str = morpheus_foundation::storage::XATTR_RETENTION;
+
+const ENTRY_SIZE: usize = core::mem::size_of::<VersionEntry>();
+
+/// Bytes of a `VersionEntry` before `lsn`: `key` and `path`, which a
+/// `Retain` record carries in its path prefix instead.
+const IDENTITY: usize = 8 + 256;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+/// How many prior versions of a file to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retention {
+    All,
+    Last(u32),
+    /// Versions replaced at most this many nanoseconds ago.
+    Within(u64),
+    None,
+}
+
+impl Retention {
+    /// Parse the attribute syntax: `all`, `last:N`, `within:S`, `none`.
+    pub fn parse(value: &[u8]) -> Option<Self> {
+        match value {
+            b"all" => return Some(Self::All),
+            b"none" => return Some(Self::None),
+            _ => {},
+        }
+        let (kind, n) = core::str::from_utf8(value).ok()?.split_once(':')?;
+        let n: u64 = n.parse().ok()?;
+        match kind {
+            "last" => Some(Self::Last(u32::try_from(n).ok()?)),
+            "within" => Some(Self::Within(n.checked_mul(NS_PER_SEC)?)),
+            _ => None,
+        }
+    }
+
+    /// Whether an overwrite keeps the prior version at all.
+    pub fn keeps_any(self) -> bool {
+        match self {
+            Self::All => true,
+            Self::Last(n) => n > 0,
+            Self::Within(ns) => ns > 0,
+            Self::None => false,
+        }
+    }
+}
+
+fn region_lba(partition_lba_start: u64, data_start_block: u64, dbs: u32, rel_block: u64) -> Lba {
+    let scale = BLOCK_SIZE as u64 / dbs as u64;
+    Lba(partition_lba_start + (data_start_block + rel_block) * scale)
+}
+
+fn as_bytes(entries: &[VersionEntry]) -> &[u8] {
+    // SAFETY: VersionEntry is repr(C) plain data without padding.
+    unsafe {
+        core::slice::from_raw_parts(entries.as_ptr() as *const u8, entries.len() * ENTRY_SIZE)
+    }
+}
+
+fn from_bytes(bytes: &[u8]) -> VersionEntry {
+    // SAFETY: every bit pattern is a valid VersionEntry.
+    unsafe { core::ptr::read_unaligned(bytes.as_ptr() as *const VersionEntry) }
+}
+
+pub fn path_of(v: &VersionEntry) -> &str {
+    btree::path_str(&v.path)
+}
+
+/// The version `entry` was until the record at `superseded_lsn` replaced it.
+pub fn version_of(entry: &IndexEntry, superseded_lsn: Lsn, superseded_ns: u64) -> VersionEntry {
+    // SAFETY: all-zeros is valid for every field.
+    let mut v: VersionEntry = unsafe { core::mem::zeroed() };
+    v.key = entry.key;
+    v.path = entry.path;
+    v.lsn = entry.lsn;
+    v.first_lsn = entry.first_lsn;
+    v.superseded_lsn = superseded_lsn;
+    v.modified_ns = entry.modified_ns;
+    v.superseded_ns = superseded_ns;
+    v.size = entry.size;
+    v.extent_root = entry.extent_root;
+    v.stored_len = entry.stored_len;
+    v.content_crc64 = entry.content_crc64;
+    v.flags = entry.flags
+        & (entry_flags::IS_INLINE | entry_flags::IS_EXTENT_NODE | entry_flags::IS_COMPRESSED);
+    v.inline_data = entry.inline_data;
+    v
+}
+
+/// An index entry standing for `v`, for the read and block-accounting paths.
+pub fn as_entry(v: &VersionEntry) -> IndexEntry {
+    let mut e = NamespaceIndex::make_file_entry(
+        path_of(v),
+        v.lsn,
+        v.size,
+        v.modified_ns,
+        None,
+        v.extent_root,
+        v.content_crc64,
+    );
+    e.flags = v.flags;
+    e.first_lsn = v.first_lsn;
+    e.stored_len = v.stored_len;
+    e.inline_data = v.inline_data;
+    e
+}
+
+/// Log that `v` is kept.
+pub fn log_retain<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    v: &VersionEntry,
+    timestamp_ns: u64,
+) -> Result<Lsn, HelixError> {
+    let path_b = btree::path_str(&v.path).as_bytes();
+    let tail = &as_bytes(core::slice::from_ref(v))[IDENTITY..];
+    let mut payload = Vec::with_capacity(2 + path_b.len() + tail.len());
+    payload.extend_from_slice(&(path_b.len() as u16).to_le_bytes());
+    payload.extend_from_slice(path_b);
+    payload.extend_from_slice(tail);
+    log.append(block_io, LogOp::Retain, v.key, &payload, timestamp_ns)
+}
+
+/// The committed `Retain` records after `after_lsn`, in log order.
+pub fn replay<B: BlockIo>(
+    block_io: &mut B,
+    log: &LogEngine,
+    after_lsn: Lsn,
+) -> Result<Vec<VersionEntry>, HelixError> {
+    let mut out = Vec::new();
+    let mut gate = TxGate::new();
+    log.scan_forward(
+        block_io,
+        log.tail_segment(),
+        core::mem::size_of::<LogSegmentHeader>() as u32,
+        |_, hdr, payload| {
+            if hdr.lsn <= after_lsn {
+                return Ok(());
+            }
+            gate.feed(hdr, payload, |h, p| {
+                if h.op != LogOp::Retain as u8 {
+                    return Ok(());
+                }
+                let Some((path, rest)) = decode_path_payload(p) else {
+                    return Ok(());
+                };
+                if rest.len() < ENTRY_SIZE - IDENTITY {
+                    return Ok(());
+                }
+                let mut bytes = vec![0u8; ENTRY_SIZE];
+                bytes[8..8 + path.len()].copy_from_slice(path.as_bytes());
+                bytes[IDENTITY..].copy_from_slice(&rest[..ENTRY_SIZE - IDENTITY]);
+                let mut v = from_bytes(&bytes);
+                v.key = fnv1a_64(path.as_bytes());
+                out.push(v);
+                Ok(())
+            })
+        },
+    )?;
+    Ok(out)
+}
+
+/// Read the region the superblock names.
+pub fn load_region<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    sb: &HelixSuperblock,
+) -> Result<Vec<VersionEntry>, HelixError> {
+    let count = sb.history_count as usize;
+    if count == 0 || sb.history_block == BLOCK_NULL {
+        return Ok(Vec::new());
+    }
+    let bytes_len = count
+        .checked_mul(ENTRY_SIZE)
+        .ok_or(HelixError::IndexCrcMismatch)?;
+    let blocks = bytes_len.div_ceil(BLOCK_SIZE as usize);
+    if blocks as u64 > sb.history_blocks as u64 {
+        return Err(HelixError::IndexCrcMismatch);
+    }
+    let mut buf = vec![0u8; blocks * BLOCK_SIZE as usize];
+    block_io
+        .read_blocks(
+            region_lba(
+                partition_lba_start,
+                sb.data_start_block,
+                device_block_size,
+                sb.history_block,
+            ),
+            &mut buf,
+        )
+        .map_err(|_| HelixError::IoReadFailed)?;
+    let bytes = &buf[..bytes_len];
+    if crc32c(bytes) != sb.history_crc {
+        return Err(HelixError::IndexCrcMismatch);
+    }
+    Ok(bytes.chunks_exact(ENTRY_SIZE).map(from_bytes).collect())
+}
+
+/// Write `entries` to a fresh region and point `sb` at it. The caller
+/// persists the superblock, then frees the returned superseded region, if
+/// any, as `(start, blocks)`.
+pub fn store_region<B: BlockIo>(
+    block_io: &mut B,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    sb: &mut HelixSuperblock,
+    entries: &[VersionEntry],
+) -> Result<Option<(BlockAddr, u64)>, HelixError> {
+    let old = (sb.history_count != 0 && sb.history_block != BLOCK_NULL)
+        .then_some((sb.history_block, sb.history_blocks as u64));
+    let bytes = as_bytes(entries);
+    let blocks = bytes.len().div_ceil(BLOCK_SIZE as usize) as u64;
+    let start = if entries.is_empty() {
+        BLOCK_NULL
+    } else {
+        let start = bitmap.alloc_contiguous(blocks)?;
+        let mut buf = vec![0u8; blocks as usize * BLOCK_SIZE as usize];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let lba = region_lba(
+            partition_lba_start,
+            sb.data_start_block,
+            device_block_size,
+            start,
+        );
+        if block_io.write_blocks(lba, &buf).is_err() {
+            let _ = bitmap.free_range(start, blocks);
+            return Err(HelixError::IoWriteFailed);
+        }
+        start
+    };
+    sb.history_block = start;
+    sb.history_blocks = blocks as u32;
+    sb.history_count = entries.len() as u64;
+    sb.history_crc = crc32c(bytes);
+    Ok(old)
+}
+
+/// Positions in `history` of the versions of `path` that `policy` drops, at
+/// time `now_ns`. An unknown time (0) expires nothing under `within`.
+pub fn expired(history: &[VersionEntry], path: &str, policy: Retention, now_ns: u64) -> Vec<usize> {
+    let mine: Vec<usize> = history
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| path_of(v) == path)
+        .map(|(i, _)| i)
+        .collect();
+    match policy {
+        Retention::All => Vec::new(),
+        Retention::None => mine,
+        Retention::Last(n) => {
+            let cut = mine.len().saturating_sub(n as usize);
+            mine[..cut].to_vec()
+        },
+        Retention::Within(_) if now_ns == 0 => Vec::new(),
+        Retention::Within(ns) => mine
+            .into_iter()
+            .filter(|&i| history[i].superseded_ns.saturating_add(ns) < now_ns)
+            .collect(),
+    }
+}
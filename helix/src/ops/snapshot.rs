@@ -0,0 +1,161 @@
+//! The snapshot table.
+//!
+//! Snapshots live in one data block of `SnapshotEntry`s named by the
+//! superblock (`snapshot_table_block`, `snapshot_count`, `snapshot_table_crc`),
+//! oldest first. The table is copy-on-write: every change writes a fresh block
+//! and the superblock that points at it, so the table the superblock names is
+//! always complete. The table, not the `Snapshot` markers in the log, is
+//! authoritative — a deleted snapshot's marker may still sit in the ring.
+
+use crate::bitmap::BlockBitmap;
+use crate::crc::crc32c;
+use crate::error::HelixError;
+use crate::types::*;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+
+const ENTRY_SIZE: usize = core::mem::size_of::<SnapshotEntry>();
+
+/// Table capacity: one block of entries.
+pub const MAX_SNAPSHOTS: usize = BLOCK_SIZE as usize / ENTRY_SIZE;
+
+/// Longest snapshot name (the on-disk field is NUL-terminated).
+pub const MAX_SNAPSHOT_NAME_LEN: usize = 63;
+
+/// One snapshot as callers see it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub lsn: Lsn,
+    pub timestamp_ns: u64,
+    pub name: String,
+}
+
+impl From<&SnapshotEntry> for SnapshotInfo {
+    fn from(e: &SnapshotEntry) -> Self {
+        Self {
+            lsn: e.lsn,
+            timestamp_ns: e.timestamp_ns,
+            name: String::from(entry_name(e)),
+        }
+    }
+}
+
+fn block_lba(partition_lba_start: u64, data_start_block: u64, dbs: u32, rel_block: u64) -> Lba {
+    let scale = BLOCK_SIZE as u64 / dbs as u64;
+    Lba(partition_lba_start + (data_start_block + rel_block) * scale)
+}
+
+fn as_bytes(entries: &[SnapshotEntry]) -> &[u8] {
+    // SAFETY: SnapshotEntry is repr(C) plain data without padding.
+    unsafe {
+        core::slice::from_raw_parts(entries.as_ptr() as *const u8, entries.len() * ENTRY_SIZE)
+    }
+}
+
+/// A fresh entry, not yet materialized; `PathInvalid` if `name` does not fit.
+pub fn new_entry(name: &str, lsn: Lsn, timestamp_ns: u64) -> Result<SnapshotEntry, HelixError> {
+    let b = name.as_bytes();
+    if b.len() > MAX_SNAPSHOT_NAME_LEN || b.contains(&0) {
+        return Err(HelixError::PathInvalid);
+    }
+    let mut e = SnapshotEntry {
+        name: [0u8; 64],
+        lsn,
+        timestamp_ns,
+        index_root: BLOCK_NULL,
+        index_blocks: 0,
+        index_entry_count: 0,
+        _pad: [0u8; 32],
+    };
+    e.name[..b.len()].copy_from_slice(b);
+    Ok(e)
+}
+
+pub fn entry_name(e: &SnapshotEntry) -> &str {
+    let end = e.name.iter().position(|&b| b == 0).unwrap_or(e.name.len());
+    core::str::from_utf8(&e.name[..end]).unwrap_or("")
+}
+
+/// Position of the newest entry called `name`.
+pub fn find(entries: &[SnapshotEntry], name: &str) -> Option<usize> {
+    entries.iter().rposition(|e| entry_name(e) == name)
+}
+
+/// Read the table the superblock names.
+pub fn load_table<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    sb: &HelixSuperblock,
+) -> Result<Vec<SnapshotEntry>, HelixError> {
+    let count = sb.snapshot_count as usize;
+    if count == 0 || sb.snapshot_table_block == BLOCK_NULL {
+        return Ok(Vec::new());
+    }
+    if count > MAX_SNAPSHOTS {
+        return Err(HelixError::IndexCrcMismatch);
+    }
+    let mut buf = vec![0u8; BLOCK_SIZE as usize];
+    block_io
+        .read_blocks(
+            block_lba(
+                partition_lba_start,
+                sb.data_start_block,
+                device_block_size,
+                sb.snapshot_table_block,
+            ),
+            &mut buf,
+        )
+        .map_err(|_| HelixError::IoReadFailed)?;
+    let bytes = &buf[..count * ENTRY_SIZE];
+    if crc32c(bytes) != sb.snapshot_table_crc {
+        return Err(HelixError::IndexCrcMismatch);
+    }
+    Ok(bytes
+        .chunks_exact(ENTRY_SIZE)
+        // SAFETY: every bit pattern is a valid SnapshotEntry.
+        .map(|c| unsafe { core::ptr::read_unaligned(c.as_ptr() as *const SnapshotEntry) })
+        .collect())
+}
+
+/// Write `entries` to a fresh block and point `sb` at it. The caller persists
+/// the superblock, then frees the returned superseded block, if any.
+pub fn store_table<B: BlockIo>(
+    block_io: &mut B,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    sb: &mut HelixSuperblock,
+    entries: &[SnapshotEntry],
+) -> Result<Option<BlockAddr>, HelixError> {
+    if entries.len() > MAX_SNAPSHOTS {
+        return Err(HelixError::SnapshotTableFull);
+    }
+    let old = (sb.snapshot_table_block != BLOCK_NULL).then_some(sb.snapshot_table_block);
+    let bytes = as_bytes(entries);
+    let block = if entries.is_empty() {
+        BLOCK_NULL
+    } else {
+        let block = bitmap.alloc_block()?;
+        let mut buf = vec![0u8; BLOCK_SIZE as usize];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let lba = block_lba(
+            partition_lba_start,
+            sb.data_start_block,
+            device_block_size,
+            block,
+        );
+        if block_io.write_blocks(lba, &buf).is_err() {
+            let _ = bitmap.free_block(block);
+            return Err(HelixError::IoWriteFailed);
+        }
+        block
+    };
+    sb.snapshot_table_block = block;
+    sb.snapshot_count = entries.len() as u32;
+    sb.snapshot_table_crc = crc32c(bytes);
+    Ok(old)
+}
@@ -0,0 +1,398 @@
+//! Send streams: the namespace changes between two snapshots as a portable,
+//! versioned byte stream another volume can replay.
+//!
+//! Layout (little-endian): a 48-byte header
+//! `[magic: 8][version: u32][_pad: u32][from_lsn: u64][to_lsn: u64][source_uuid: 16]`,
+//! then records `[op: u8][path_len: u16][path][body]`, then an `End` op and a
+//! crc32c of everything before it. Bodies:
+//!
+//! - `Remove`: none.
+//! - `Mkdir`: `[created_ns: u64]`.
+//! - `Write`: `[modified_ns: u64][len: u64][data]` — the whole file.
+//! - `SetXattrs`: `[len: u32][blob]` in `ops::xattr::encode` form; 0 = clear.
+//! - `Symlink`: `[len: u16][target]`.
+//! - `SetOwner`: `[uid: u32][gid: u32][mode: u32]`.
+//!
+//! Records are ordered so they apply front to back: removals deepest first,
+//! then creations and rewrites parents first. Nothing in a stream refers to
+//! the source's blocks or LSNs beyond the header, so it replays onto any
+//! volume holding the `from` state. Hard links are not preserved: each name
+//! travels as a file of its own.
+
+use crate::crc::crc32c;
+use crate::error::HelixError;
+use crate::index::btree::{self, NamespaceIndex};
+use crate::types::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+
+pub const STREAM_MAGIC: [u8; 8] = *b"HXSTREAM";
+pub const STREAM_VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 48;
+
+const OP_END: u8 = 0;
+const OP_REMOVE: u8 = 1;
+const OP_MKDIR: u8 = 2;
+const OP_WRITE: u8 = 3;
+const OP_SET_XATTRS: u8 = 4;
+const OP_SYMLINK: u8 = 5;
+const OP_SET_OWNER: u8 = 6;
+
+/// What a stream moves between: the two snapshot LSNs on the source volume
+/// (`from_lsn` 0 = from empty) and that volume's UUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamHeader {
+    pub from_lsn: Lsn,
+    pub to_lsn: Lsn,
+    pub source_uuid: [u8; 16],
+}
+
+/// One step from the `from` namespace towards `to`. Entries are `to`'s.
+#[derive(Clone)]
+pub enum Change {
+    Remove(String),
+    Mkdir(IndexEntry),
+    Write(IndexEntry),
+    SetXattrs(IndexEntry),
+    Symlink(IndexEntry),
+    SetOwner(IndexEntry),
+}
+
+/// One decoded record; borrows the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamOp<'a> {
+    Remove {
+        path: &'a str,
+    },
+    Mkdir {
+        path: &'a str,
+        created_ns: u64,
+    },
+    Write {
+        path: &'a str,
+        modified_ns: u64,
+        data: &'a [u8],
+    },
+    SetXattrs {
+        path: &'a str,
+        blob: &'a [u8],
+    },
+    Symlink {
+        path: &'a str,
+        target: &'a str,
+    },
+    SetOwner {
+        path: &'a str,
+        owner: super::owner::Owner,
+    },
+}
+
+fn is_dir(e: &IndexEntry) -> bool {
+    e.flags & entry_flags::IS_DIR != 0
+}
+
+/// Directory, symbolic link or file; a change of kind is a remove and create.
+fn kind(e: &IndexEntry) -> u32 {
+    e.flags & (entry_flags::IS_DIR | entry_flags::IS_SYMLINK)
+}
+
+/// `e` as a stream sees it: link records are skipped, and a hard-linked name
+/// stands for its record's content under its own path.
+fn view<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    e: IndexEntry,
+) -> Result<Option<IndexEntry>, HelixError> {
+    if super::link::is_record(btree::path_str(&e.path)) {
+        return Ok(None);
+    }
+    if e.flags & entry_flags::IS_HARDLINK == 0 {
+        return Ok(Some(e));
+    }
+    let mut content = super::link::resolve(block_io, index, e)?;
+    content.key = e.key;
+    content.path = e.path;
+    Ok(Some(content))
+}
+
+fn lookup_view<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    path: &str,
+) -> Result<Option<IndexEntry>, HelixError> {
+    match index.lookup(block_io, path)? {
+        Some(e) => view(block_io, index, e),
+        None => Ok(None),
+    }
+}
+
+fn xattr_ref(e: Option<&IndexEntry>) -> BlockAddr {
+    match e {
+        Some(e) if e.xattr_len != 0 => e.xattr_block,
+        _ => BLOCK_NULL,
+    }
+}
+
+/// Whether `t` needs a `SetOwner` after its other changes. A rewrite keeps
+/// the owner of the version it replaces, but applying one may recreate the
+/// entry (a symbolic link, a hard-linked name), which starts over.
+fn owner_changed(current: Option<&IndexEntry>, t: &IndexEntry, rewritten: bool) -> bool {
+    let want = super::owner::of(t);
+    want != current.map_or_else(|| super::owner::initial(t), super::owner::of)
+        || (rewritten && want != super::owner::initial(t))
+}
+
+/// The changes that turn `from` into `to`, both indexes over the same
+/// volume's blocks. An entry whose version and attribute block match is left
+/// alone; one that changed kind is removed and recreated.
+pub fn delta<B: BlockIo>(
+    block_io: &mut B,
+    from: &NamespaceIndex,
+    to: &NamespaceIndex,
+) -> Result<Vec<Change>, HelixError> {
+    let mut changes = Vec::new();
+
+    // Children sort after their directory, so reverse order empties it first.
+    let mut doomed: Vec<String> = Vec::new();
+    let mut cursor = from.cursor();
+    while let Some(e) = from.next_entry(block_io, &mut cursor)? {
+        let Some(e) = view(block_io, from, e)? else {
+            continue;
+        };
+        let path = btree::path_str(&e.path);
+        if lookup_view(block_io, to, path)?.map_or(true, |t| kind(&t) != kind(&e)) {
+            doomed.push(String::from(path));
+        }
+    }
+    doomed.sort_unstable();
+    changes.extend(doomed.into_iter().rev().map(Change::Remove));
+
+    // Parents sort before their children.
+    let mut wanted: Vec<(IndexEntry, Option<IndexEntry>, bool)> = Vec::new();
+    let mut cursor = to.cursor();
+    while let Some(t) = to.next_entry(block_io, &mut cursor)? {
+        let Some(t) = view(block_io, to, t)? else {
+            continue;
+        };
+        let current =
+            lookup_view(block_io, from, btree::path_str(&t.path))?.filter(|c| kind(c) == kind(&t));
+        let changed = if is_dir(&t) {
+            current.is_none()
+        } else {
+            current.map_or(true, |c| c.lsn != t.lsn)
+        };
+        if changed
+            || xattr_ref(current.as_ref()) != xattr_ref(Some(&t))
+            || owner_changed(current.as_ref(), &t, changed)
+        {
+            wanted.push((t, current, changed));
+        }
+    }
+    wanted.sort_unstable_by(|a, b| a.0.path.cmp(&b.0.path));
+    for (t, current, changed) in wanted {
+        if is_dir(&t) {
+            if changed {
+                changes.push(Change::Mkdir(t));
+            }
+        } else if changed {
+            if t.flags & entry_flags::IS_SYMLINK != 0 {
+                changes.push(Change::Symlink(t));
+            } else {
+                changes.push(Change::Write(t));
+            }
+        }
+        // A rewrite keeps the attributes of the version it replaces.
+        if xattr_ref(current.as_ref()) != xattr_ref(Some(&t)) {
+            changes.push(Change::SetXattrs(t));
+        }
+        if owner_changed(current.as_ref(), &t, changed) {
+            changes.push(Change::SetOwner(t));
+        }
+    }
+    Ok(changes)
+}
+
+/// Builds a stream record by record.
+pub struct StreamWriter {
+    buf: Vec<u8>,
+}
+
+impl StreamWriter {
+    pub fn new(header: &StreamHeader) -> Self {
+        let mut buf = Vec::with_capacity(HEADER_SIZE);
+        buf.extend_from_slice(&STREAM_MAGIC);
+        buf.extend_from_slice(&STREAM_VERSION.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&header.from_lsn.to_le_bytes());
+        buf.extend_from_slice(&header.to_lsn.to_le_bytes());
+        buf.extend_from_slice(&header.source_uuid);
+        Self { buf }
+    }
+
+    pub fn push(&mut self, op: &StreamOp<'_>) {
+        let (code, path) = match op {
+            StreamOp::Remove { path } => (OP_REMOVE, path),
+            StreamOp::Mkdir { path, .. } => (OP_MKDIR, path),
+            StreamOp::Write { path, .. } => (OP_WRITE, path),
+            StreamOp::SetXattrs { path, .. } => (OP_SET_XATTRS, path),
+            StreamOp::Symlink { path, .. } => (OP_SYMLINK, path),
+            StreamOp::SetOwner { path, .. } => (OP_SET_OWNER, path),
+        };
+        self.buf.push(code);
+        self.buf
+            .extend_from_slice(&(path.len() as u16).to_le_bytes());
+        self.buf.extend_from_slice(path.as_bytes());
+        match op {
+            StreamOp::Remove { .. } => {},
+            StreamOp::Mkdir { created_ns, .. } => {
+                self.buf.extend_from_slice(&created_ns.to_le_bytes());
+            },
+            StreamOp::Write {
+                modified_ns, data, ..
+            } => {
+                self.buf.extend_from_slice(&modified_ns.to_le_bytes());
+                self.buf
+                    .extend_from_slice(&(data.len() as u64).to_le_bytes());
+                self.buf.extend_from_slice(data);
+            },
+            StreamOp::SetXattrs { blob, .. } => {
+                self.buf
+                    .extend_from_slice(&(blob.len() as u32).to_le_bytes());
+                self.buf.extend_from_slice(blob);
+            },
+            StreamOp::Symlink { target, .. } => {
+                self.buf
+                    .extend_from_slice(&(target.len() as u16).to_le_bytes());
+                self.buf.extend_from_slice(target.as_bytes());
+            },
+            StreamOp::SetOwner { owner, .. } => {
+                self.buf.extend_from_slice(&owner.uid.to_le_bytes());
+                self.buf.extend_from_slice(&owner.gid.to_le_bytes());
+                self.buf.extend_from_slice(&owner.mode.to_le_bytes());
+            },
+        }
+    }
+
+    /// Terminate and checksum the stream.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buf.push(OP_END);
+        let crc = crc32c(&self.buf);
+        self.buf.extend_from_slice(&crc.to_le_bytes());
+        self.buf
+    }
+}
+
+/// Cursor over a stream's bytes; every read is bounds-checked.
+struct Reader<'a> {
+    buf: &'a [u8],
+    off: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], HelixError> {
+        let end = self.off.checked_add(n).ok_or(HelixError::StreamInvalid)?;
+        let s = self
+            .buf
+            .get(self.off..end)
+            .ok_or(HelixError::StreamInvalid)?;
+        self.off = end;
+        Ok(s)
+    }
+
+    fn u16(&mut self) -> Result<u16, HelixError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, HelixError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, HelixError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn path(&mut self) -> Result<&'a str, HelixError> {
+        let len = self.u16()? as usize;
+        let path = core::str::from_utf8(self.take(len)?).map_err(|_| HelixError::StreamInvalid)?;
+        btree::validate_path(path).map_err(|_| HelixError::StreamInvalid)?;
+        Ok(path)
+    }
+}
+
+/// Verify and decode a whole stream. `StreamInvalid` on a bad magic, an
+/// unknown version, a CRC mismatch or a malformed record; nothing is returned
+/// from a stream that does not check out end to end.
+pub fn parse(stream: &[u8]) -> Result<(StreamHeader, Vec<StreamOp<'_>>), HelixError> {
+    if stream.len() < HEADER_SIZE + 5 {
+        return Err(HelixError::StreamInvalid);
+    }
+    let (body, crc) = stream.split_at(stream.len() - 4);
+    if crc32c(body) != u32::from_le_bytes(crc.try_into().unwrap()) {
+        return Err(HelixError::StreamInvalid);
+    }
+    let mut r = Reader { buf: body, off: 0 };
+    if r.take(8)? != STREAM_MAGIC || r.u32()? != STREAM_VERSION {
+        return Err(HelixError::StreamInvalid);
+    }
+    r.u32()?;
+    let header = StreamHeader {
+        from_lsn: r.u64()?,
+        to_lsn: r.u64()?,
+        source_uuid: r.take(16)?.try_into().unwrap(),
+    };
+
+    let mut ops = Vec::new();
+    loop {
+        let code = r.take(1)?[0];
+        if code == OP_END {
+            break;
+        }
+        let path = r.path()?;
+        ops.push(match code {
+            OP_REMOVE => StreamOp::Remove { path },
+            OP_MKDIR => StreamOp::Mkdir {
+                path,
+                created_ns: r.u64()?,
+            },
+            OP_WRITE => {
+                let modified_ns = r.u64()?;
+                let len = usize::try_from(r.u64()?).map_err(|_| HelixError::StreamInvalid)?;
+                StreamOp::Write {
+                    path,
+                    modified_ns,
+                    data: r.take(len)?,
+                }
+            },
+            OP_SET_XATTRS => {
+                let len = r.u32()? as usize;
+                StreamOp::SetXattrs {
+                    path,
+                    blob: r.take(len)?,
+                }
+            },
+            OP_SYMLINK => {
+                let len = r.u16()? as usize;
+                StreamOp::Symlink {
+                    path,
+                    target: core::str::from_utf8(r.take(len)?)
+                        .map_err(|_| HelixError::StreamInvalid)?,
+                }
+            },
+            OP_SET_OWNER => StreamOp::SetOwner {
+                path,
+                owner: super::owner::Owner {
+                    uid: r.u32()?,
+                    gid: r.u32()?,
+                    mode: r.u32()?,
+                },
+            },
+            _ => return Err(HelixError::StreamInvalid),
+        });
+    }
+    if r.off != body.len() {
+        return Err(HelixError::StreamInvalid);
+    }
+    Ok((header, ops))
+}
@@ -2,6 +2,7 @@
 
 use crate::bitmap::BlockBitmap;
 use crate::crc::{crc64, fnv1a_64};
+use crate::dedup::{DedupTable, ExtentRef};
 use crate::error::HelixError;
 use crate::index::btree::NamespaceIndex;
 use crate::log::LogEngine;
@@ -25,7 +26,8 @@ fn build_extent_payload(path: &str, kind: u8, file_size: u64, extent_root: u64)
 }
 
 /// Inline if `data.len() <= INLINE_DATA_SIZE` (96 B); else allocate extent(s).
-/// Auto-creates parent directories.
+/// With `compress`, an extent file is stored as a `compress` frame when that
+/// saves at least one block. Auto-creates parent directories.
 #[allow(clippy::too_many_arguments)]
 pub fn write_file<B: BlockIo>(
     block_io: &mut B,
@@ -38,13 +40,14 @@ pub fn write_file<B: BlockIo>(
     path: &str,
     data: &[u8],
     timestamp_ns: u64,
+    compress: bool,
 ) -> Result<Lsn, HelixError> {
-    crate::index::btree::validate_path(path)?;
+    super::link::validate_content_path(path)?;
     if path.len() > 1 && path.ends_with('/') {
         return Err(HelixError::PathInvalid);
     }
     // A file write must never shadow or clobber an existing directory.
-    if let Some(existing) = index.lookup_flex(path) {
+    if let Some(existing) = index.lookup_flex(block_io, path)? {
         if existing.flags & entry_flags::IS_DIR != 0 {
             return Err(HelixError::IsADirectory);
         }
@@ -64,7 +67,7 @@ pub fn write_file<B: BlockIo>(
         payload.extend_from_slice(data);
         let lsn = log.append(block_io, LogOp::Write, path_hash, &payload, timestamp_ns)?;
 
-        let is_update = index.lookup(path).is_some();
+        let existing = index.lookup(block_io, path)?;
         let mut entry = NamespaceIndex::make_file_entry(
             path,
             lsn,
@@ -75,12 +78,8 @@ pub fn write_file<B: BlockIo>(
             content_crc,
         );
 
-        if is_update {
-            if let Some(existing) = index.lookup(path) {
-                entry.created_ns = existing.created_ns;
-                entry.first_lsn = existing.first_lsn;
-                entry.version_count = existing.version_count + 1;
-            }
+        if let Some(existing) = existing {
+            entry.inherit(&existing);
         }
 
         index.upsert(entry);
@@ -88,28 +87,104 @@ pub fn write_file<B: BlockIo>(
     }
 
     let blocks_needed = (data.len() as u64).div_ceil(BLOCK_SIZE as u64);
-
-    // Prefer contiguous for sequential-read throughput; fall back to fragmented.
-    let data_start_relative = match bitmap.alloc_contiguous(blocks_needed) {
-        Ok(start) => start,
-        Err(HelixError::NoSpace) => {
-            return write_file_fragmented(
-                block_io,
-                log,
-                index,
-                bitmap,
-                partition_lba_start,
-                device_block_size,
-                data_start_block,
-                path,
-                data,
-                timestamp_ns,
-                path_hash,
-                content_crc,
-            );
-        },
-        Err(e) => return Err(e),
+    let frame = if compress && data.len() <= u32::MAX as usize {
+        Some(crate::compress::pack(data))
+            .filter(|f| (f.len() as u64).div_ceil(BLOCK_SIZE as u64) < blocks_needed)
+    } else {
+        None
     };
+    let stored = frame.as_deref().unwrap_or(data);
+
+    // Prefer contiguous for sequential-read throughput; fall back to fragmented
+    // (always uncompressed: a frame is only ever read as one run).
+    match write_contiguous(
+        block_io,
+        log,
+        index,
+        bitmap,
+        partition_lba_start,
+        device_block_size,
+        data_start_block,
+        path,
+        data.len() as u64,
+        stored,
+        frame.is_some(),
+        content_crc,
+        timestamp_ns,
+    ) {
+        Err(HelixError::NoSpace) => write_file_fragmented(
+            block_io,
+            log,
+            index,
+            bitmap,
+            partition_lba_start,
+            device_block_size,
+            data_start_block,
+            path,
+            data,
+            timestamp_ns,
+            path_hash,
+            content_crc,
+        ),
+        other => other,
+    }
+}
+
+/// Store a ready-made `compress` frame holding `size` bytes as the new
+/// content of the existing file `path`. Its whole-content CRC stays unset, as
+/// for a ranged version. `NoSpace` if no contiguous run fits the frame.
+#[allow(clippy::too_many_arguments)]
+pub fn write_frame<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    data_start_block: u64,
+    path: &str,
+    size: u64,
+    frame: &[u8],
+    timestamp_ns: u64,
+) -> Result<Lsn, HelixError> {
+    write_contiguous(
+        block_io,
+        log,
+        index,
+        bitmap,
+        partition_lba_start,
+        device_block_size,
+        data_start_block,
+        path,
+        size,
+        frame,
+        true,
+        0,
+        timestamp_ns,
+    )
+}
+
+/// Write `stored` — the content itself, or with `compressed` the frame of
+/// `size` bytes — to one contiguous run and log it as `path`'s new version.
+/// `NoSpace` if there is no run that long.
+#[allow(clippy::too_many_arguments)]
+fn write_contiguous<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    data_start_block: u64,
+    path: &str,
+    size: u64,
+    stored: &[u8],
+    compressed: bool,
+    content_crc: u64,
+    timestamp_ns: u64,
+) -> Result<Lsn, HelixError> {
+    let blocks_needed = (stored.len() as u64).div_ceil(BLOCK_SIZE as u64);
+    let data_start_relative = bitmap.alloc_contiguous(blocks_needed)?;
 
     // Roll back the bitmap on any I/O failure to avoid orphaned blocks.
     let scale = BLOCK_SIZE as u64 / device_block_size as u64;
@@ -117,8 +192,8 @@ pub fn write_file<B: BlockIo>(
     let mut io_failed = false;
     for i in 0..blocks_needed {
         let mut block_buf = vec![0u8; BLOCK_SIZE as usize];
-        let chunk = (data.len() - write_offset).min(BLOCK_SIZE as usize);
-        block_buf[..chunk].copy_from_slice(&data[write_offset..write_offset + chunk]);
+        let chunk = (stored.len() - write_offset).min(BLOCK_SIZE as usize);
+        block_buf[..chunk].copy_from_slice(&stored[write_offset..write_offset + chunk]);
         write_offset += chunk;
 
         let abs_block = data_start_block + data_start_relative + i;
@@ -133,18 +208,21 @@ pub fn write_file<B: BlockIo>(
         return Err(HelixError::IoWriteFailed);
     }
 
-    let full_payload = build_extent_payload(
-        path,
-        extent_kind::CONTIGUOUS,
-        data.len() as u64,
-        data_start_relative,
-    );
+    let kind = if compressed {
+        extent_kind::COMPRESSED
+    } else {
+        extent_kind::CONTIGUOUS
+    };
+    let mut full_payload = build_extent_payload(path, kind, size, data_start_relative);
+    if compressed {
+        full_payload.extend_from_slice(&(stored.len() as u64).to_le_bytes());
+    }
     let lsn = match log.append_full(
         block_io,
         LogOp::Write,
         rec_flags::IS_EXTENT,
-        path_hash,
-        0,
+        fnv1a_64(path.as_bytes()),
+        content_crc,
         0,
         &full_payload,
         timestamp_ns,
@@ -156,23 +234,23 @@ pub fn write_file<B: BlockIo>(
         },
     };
 
-    let is_update = index.lookup(path).is_some();
+    let existing = index.lookup(block_io, path)?;
     let mut entry = NamespaceIndex::make_file_entry(
         path,
         lsn,
-        data.len() as u64,
+        size,
         timestamp_ns,
         None,
         data_start_relative,
         content_crc,
     );
+    if compressed {
+        entry.flags |= entry_flags::IS_COMPRESSED;
+        entry.stored_len = stored.len() as u64;
+    }
 
-    if is_update {
-        if let Some(existing) = index.lookup(path) {
-            entry.created_ns = existing.created_ns;
-            entry.first_lsn = existing.first_lsn;
-            entry.version_count = existing.version_count + 1;
-        }
+    if let Some(existing) = existing {
+        entry.inherit(&existing);
     }
 
     index.upsert(entry);
@@ -273,7 +351,7 @@ fn write_file_fragmented<B: BlockIo>(
         LogOp::Write,
         rec_flags::IS_EXTENT,
         path_hash,
-        0,
+        content_crc,
         0,
         &full_payload,
         timestamp_ns,
@@ -286,7 +364,7 @@ fn write_file_fragmented<B: BlockIo>(
         },
     };
 
-    let is_update = index.lookup(path).is_some();
+    let existing = index.lookup(block_io, path)?;
     let mut entry = NamespaceIndex::make_file_entry(
         path,
         lsn,
@@ -298,19 +376,435 @@ fn write_file_fragmented<B: BlockIo>(
     );
     entry.flags |= entry_flags::IS_EXTENT_NODE;
 
-    if is_update {
-        if let Some(existing) = index.lookup(path) {
-            entry.created_ns = existing.created_ns;
-            entry.first_lsn = existing.first_lsn;
-            entry.version_count = existing.version_count + 1;
+    if let Some(existing) = existing {
+        entry.inherit(&existing);
+    }
+
+    index.upsert(entry);
+    Ok(lsn)
+}
+
+/// Point `path` at an existing extent file with identical content instead of
+/// writing a copy. Logs a `DedupRef` (tagged `IS_EXTENT`, so it parses like an
+/// extent Write) carrying `refs`, the post-share reference count, and the
+/// content CRC in `secondary_hash`. The caller has verified the content and
+/// owns the refcount bump.
+#[allow(clippy::too_many_arguments)]
+pub fn write_dedup_ref<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    path: &str,
+    shared: ExtentRef,
+    content_crc: u64,
+    refs: u32,
+    timestamp_ns: u64,
+) -> Result<Lsn, HelixError> {
+    super::link::validate_content_path(path)?;
+    if path.len() > 1 && path.ends_with('/') {
+        return Err(HelixError::PathInvalid);
+    }
+    if let Some(existing) = index.lookup_flex(block_io, path)? {
+        if existing.flags & entry_flags::IS_DIR != 0 {
+            return Err(HelixError::IsADirectory);
         }
     }
 
+    ensure_parent_dirs(block_io, log, index, path, timestamp_ns)?;
+
+    let path_hash = fnv1a_64(path.as_bytes());
+    let payload = crate::dedup::build_ref_payload(path, shared, refs);
+    let lsn = log.append_full(
+        block_io,
+        LogOp::DedupRef,
+        rec_flags::IS_EXTENT,
+        path_hash,
+        content_crc,
+        0,
+        &payload,
+        timestamp_ns,
+    )?;
+
+    let (extent_root, size, is_node) = shared;
+    let mut entry = NamespaceIndex::make_file_entry(
+        path,
+        lsn,
+        size,
+        timestamp_ns,
+        None,
+        extent_root,
+        content_crc,
+    );
+    entry.flags |= entry_flags::IS_DEDUP;
+    if is_node {
+        entry.flags |= entry_flags::IS_EXTENT_NODE;
+    }
+    if let Some(existing) = index.lookup(block_io, path)? {
+        entry.inherit(&existing);
+    }
+
     index.upsert(entry);
     Ok(lsn)
 }
 
-fn ensure_parent_dirs<B: BlockIo>(
+/// `(physical, count)` block runs.
+type Runs = Vec<(u64, u64)>;
+
+/// Rewrite `[offset, offset + data.len())` of `path` and set its size to
+/// `new_size` (> `INLINE_DATA_SIZE`, >= the range end) without staging the file.
+/// Only the touched blocks — plus the block holding a shrunken EOF — are
+/// copied-on-write; untouched runs carry over into a fresh extent node and gaps
+/// stay holes. Logs one `IS_DELTA` extent Write. Returns the LSN, the
+/// superseded `(physical, count)` runs, which the caller reclaims unless a
+/// snapshot pins the prior version, and the carried-over ones. `relocate`
+/// rewrites every block so the new version shares none with the old one.
+/// `retain` keeps the prior version as it is: the carried-over runs become
+/// shared with it, and the new version never takes over its extent root.
+#[allow(clippy::too_many_arguments)]
+pub fn write_file_range<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    device_block_size: u32,
+    data_start_block: u64,
+    path: &str,
+    offset: u64,
+    data: &[u8],
+    new_size: u64,
+    timestamp_ns: u64,
+    relocate: bool,
+    retain: bool,
+) -> Result<(Lsn, Runs, Runs), HelixError> {
+    super::link::validate_content_path(path)?;
+    if path.len() > 1 && path.ends_with('/') {
+        return Err(HelixError::PathInvalid);
+    }
+    if let Some(existing) = index.lookup_flex(block_io, path)? {
+        if existing.flags & entry_flags::IS_DIR != 0 {
+            return Err(HelixError::IsADirectory);
+        }
+    }
+    let end = offset
+        .checked_add(data.len() as u64)
+        .ok_or(HelixError::InvalidOffset)?;
+    if new_size <= INLINE_DATA_SIZE as u64 || end > new_size {
+        return Err(HelixError::InvalidOffset);
+    }
+
+    let old = index.lookup(block_io, path)?;
+    let (old_size, old_runs, old_inline, old_node) = match &old {
+        None => (0, Vec::new(), None, BLOCK_NULL),
+        Some(e) if e.flags & entry_flags::IS_INLINE != 0 => {
+            (e.size, Vec::new(), Some(e.inline_data), BLOCK_NULL)
+        },
+        Some(e) => {
+            let is_node = e.flags & entry_flags::IS_EXTENT_NODE != 0;
+            let runs = crate::extent::file_runs(
+                block_io,
+                partition_lba_start,
+                data_start_block,
+                device_block_size,
+                e.extent_root,
+                e.size,
+                is_node,
+            )?;
+            (
+                e.size,
+                runs,
+                None,
+                if is_node { e.extent_root } else { BLOCK_NULL },
+            )
+        },
+    };
+    if old.is_none() {
+        ensure_parent_dirs(block_io, log, index, path, timestamp_ns)?;
+    }
+
+    let bs = BLOCK_SIZE as u64;
+    let new_blocks = new_size.div_ceil(bs);
+
+    // Logical block ranges to copy-on-write: ascending, disjoint, `[start, end)`.
+    let mut dirty: Vec<(u64, u64)> = Vec::new();
+    if relocate {
+        dirty.push((0, new_blocks));
+    } else {
+        // Inline bytes have no block yet; they become block 0.
+        if old_inline.is_some() && old_size > 0 {
+            push_dirty(&mut dirty, 0, 1);
+        }
+        if !data.is_empty() {
+            push_dirty(&mut dirty, offset / bs, end.div_ceil(bs));
+        }
+        // A shrunken EOF mid-block: rewrite it so the tail past EOF reads zero.
+        if new_size < old_size && new_size % bs != 0 {
+            push_dirty(&mut dirty, new_size / bs, new_size / bs + 1);
+        }
+    }
+
+    // Split the old runs into the pieces the new version keeps and the pieces it
+    // supersedes (rewritten, or cut off past the new EOF).
+    let mut runs: Vec<(u64, u64, u32)> = Vec::new();
+    let mut superseded: Vec<(u64, u64)> = Vec::new();
+    for &(logical, physical, count) in &old_runs {
+        let run_end = logical + count as u64;
+        let mut cursor = logical;
+        while cursor < run_end {
+            let (dropped, next) = classify_block(cursor, &dirty, new_blocks);
+            let stop = next.min(run_end);
+            let piece_phys = physical + (cursor - logical);
+            if dropped {
+                superseded.push((piece_phys, stop - cursor));
+            } else {
+                runs.push((cursor, piece_phys, (stop - cursor) as u32));
+            }
+            cursor = stop;
+        }
+    }
+    if old_node != BLOCK_NULL {
+        superseded.push((old_node, 1));
+    }
+    // A node needs at least one run; an all-hole file materializes its last block.
+    if runs.is_empty() && dirty.is_empty() {
+        dirty.push((new_blocks - 1, new_blocks));
+    }
+
+    let fresh = alloc_dirty_runs(bitmap, &dirty)?;
+    let free_fresh = |bitmap: &mut BlockBitmap| {
+        for (_, physical, count) in &fresh {
+            let _ = bitmap.free_range(*physical, *count as u64);
+        }
+    };
+
+    let kept: Vec<(u64, u64)> = runs.iter().map(|&(_, p, c)| (p, c as u64)).collect();
+    runs.extend_from_slice(&fresh);
+    runs.sort_unstable_by_key(|r| r.0);
+    let mut merged: Vec<(u64, u64, u32)> = Vec::with_capacity(runs.len());
+    for run in runs {
+        if let Some(last) = merged.last_mut() {
+            if last.0 + last.2 as u64 == run.0
+                && last.1 + last.2 as u64 == run.1
+                && (last.2 as u64 + run.2 as u64) <= u32::MAX as u64
+            {
+                last.2 += run.2;
+                continue;
+            }
+        }
+        merged.push(run);
+    }
+
+    if merged.len() > EXTENTS_PER_LEAF {
+        free_fresh(bitmap);
+        if relocate {
+            return Err(HelixError::NoSpace);
+        }
+        // Too fragmented for one leaf: move the whole file to fresh blocks.
+        return write_file_range(
+            block_io,
+            log,
+            index,
+            bitmap,
+            partition_lba_start,
+            device_block_size,
+            data_start_block,
+            path,
+            offset,
+            data,
+            new_size,
+            timestamp_ns,
+            true,
+            retain,
+        );
+    }
+
+    // Copy-on-write each dirty block: preimage (zeroed past the surviving EOF),
+    // then the new bytes on top.
+    let scale = bs / device_block_size as u64;
+    let keep_size = old_size.min(new_size);
+    let mut block_buf = vec![0u8; BLOCK_SIZE as usize];
+    for &(logical, physical, count) in &fresh {
+        for j in 0..count as u64 {
+            let blk_start = (logical + j) * bs;
+            block_buf.fill(0);
+
+            let covered = offset <= blk_start && end >= blk_start + bs;
+            if !covered && blk_start < keep_size {
+                let keep = (keep_size - blk_start).min(bs) as usize;
+                if let Some(inline) = &old_inline {
+                    block_buf[..keep].copy_from_slice(&inline[..keep]);
+                } else if let Some(old_phys) = crate::extent::map_logical(&old_runs, logical + j) {
+                    let lba = Lba(partition_lba_start + (data_start_block + old_phys) * scale);
+                    if block_io.read_blocks(lba, &mut block_buf).is_err() {
+                        free_fresh(bitmap);
+                        return Err(HelixError::IoReadFailed);
+                    }
+                    block_buf[keep..].fill(0);
+                }
+            }
+
+            let d_start = offset.max(blk_start);
+            let d_end = end.min(blk_start + bs);
+            if d_start < d_end {
+                block_buf[(d_start - blk_start) as usize..(d_end - blk_start) as usize]
+                    .copy_from_slice(&data[(d_start - offset) as usize..(d_end - offset) as usize]);
+            }
+
+            let lba = Lba(partition_lba_start + (data_start_block + physical + j) * scale);
+            if block_io.write_blocks(lba, &block_buf).is_err() {
+                free_fresh(bitmap);
+                return Err(HelixError::IoWriteFailed);
+            }
+        }
+    }
+
+    // A contiguous successor starting on a retained version's blocks would
+    // take over its root; a node keeps the two apart.
+    let contiguous = merged.len() == 1
+        && merged[0].0 == 0
+        && merged[0].2 as u64 == new_blocks
+        && (!retain || kept.is_empty());
+    let (kind, extent_root) = if contiguous {
+        (extent_kind::CONTIGUOUS, merged[0].1)
+    } else {
+        let node_block = match bitmap.alloc_block() {
+            Ok(b) => b,
+            Err(e) => {
+                free_fresh(bitmap);
+                return Err(e);
+            },
+        };
+        if let Err(e) = crate::extent::write_extent_node(
+            block_io,
+            partition_lba_start,
+            data_start_block,
+            device_block_size,
+            node_block,
+            &merged,
+        ) {
+            let _ = bitmap.free_block(node_block);
+            free_fresh(bitmap);
+            return Err(e);
+        }
+        (extent_kind::NODE, node_block)
+    };
+    let free_new = |bitmap: &mut BlockBitmap| {
+        if kind == extent_kind::NODE {
+            let _ = bitmap.free_block(extent_root);
+        }
+        free_fresh(bitmap);
+    };
+
+    let mut payload = build_extent_payload(path, kind, new_size, extent_root);
+    payload.extend_from_slice(&offset.to_le_bytes());
+    payload.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    let lsn = match log.append_full(
+        block_io,
+        LogOp::Write,
+        rec_flags::IS_EXTENT | rec_flags::IS_DELTA,
+        fnv1a_64(path.as_bytes()),
+        0,
+        0,
+        &payload,
+        timestamp_ns,
+    ) {
+        Ok(l) => l,
+        Err(e) => {
+            free_new(bitmap);
+            return Err(e);
+        },
+    };
+
+    // Whole-content CRC would need the whole file; ranged versions leave it unset.
+    let mut entry =
+        NamespaceIndex::make_file_entry(path, lsn, new_size, timestamp_ns, None, extent_root, 0);
+    if kind == extent_kind::NODE {
+        entry.flags |= entry_flags::IS_EXTENT_NODE;
+    }
+    if let Some(existing) = old {
+        entry.inherit(&existing);
+    }
+    index.upsert(entry);
+    Ok((lsn, superseded, kept))
+}
+
+/// Append `[start, end)` to an ascending dirty list, merging overlap/adjacency.
+fn push_dirty(dirty: &mut Vec<(u64, u64)>, start: u64, end: u64) {
+    if let Some(last) = dirty.last_mut() {
+        if start <= last.1 {
+            last.1 = last.1.max(end);
+            return;
+        }
+    }
+    dirty.push((start, end));
+}
+
+/// Whether logical block `b` is superseded (dirty or past the new EOF), and the
+/// next block at which that answer can change.
+fn classify_block(b: u64, dirty: &[(u64, u64)], new_blocks: u64) -> (bool, u64) {
+    if b >= new_blocks {
+        return (true, u64::MAX);
+    }
+    for &(start, end) in dirty {
+        if b < start {
+            return (false, start.min(new_blocks));
+        }
+        if b < end {
+            return (true, end);
+        }
+    }
+    (false, new_blocks)
+}
+
+/// Back every dirty block with a fresh physical block: one contiguous run when
+/// the bitmap has it, else block-by-block. Returns `(logical, physical, count)`.
+fn alloc_dirty_runs(
+    bitmap: &mut BlockBitmap,
+    dirty: &[(u64, u64)],
+) -> Result<Vec<(u64, u64, u32)>, HelixError> {
+    let total: u64 = dirty.iter().map(|(s, e)| e - s).sum();
+    let mut runs: Vec<(u64, u64, u32)> = Vec::new();
+    if total == 0 {
+        return Ok(runs);
+    }
+
+    match bitmap.alloc_contiguous(total) {
+        Ok(start) => {
+            let mut physical = start;
+            for &(s, e) in dirty {
+                runs.push((s, physical, (e - s) as u32));
+                physical += e - s;
+            }
+            return Ok(runs);
+        },
+        Err(HelixError::NoSpace) => {},
+        Err(e) => return Err(e),
+    }
+
+    for &(s, e) in dirty {
+        for logical in s..e {
+            let phys = match bitmap.alloc_block() {
+                Ok(p) => p,
+                Err(err) => {
+                    for (_, physical, count) in &runs {
+                        let _ = bitmap.free_range(*physical, *count as u64);
+                    }
+                    return Err(err);
+                },
+            };
+            if let Some(last) = runs.last_mut() {
+                if last.0 + last.2 as u64 == logical && last.1 + last.2 as u64 == phys {
+                    last.2 += 1;
+                    continue;
+                }
+            }
+            runs.push((logical, phys, 1));
+        }
+    }
+    Ok(runs)
+}
+
+pub(crate) fn ensure_parent_dirs<B: BlockIo>(
     block_io: &mut B,
     log: &mut LogEngine,
     index: &mut NamespaceIndex,
@@ -327,7 +821,7 @@ fn ensure_parent_dirs<B: BlockIo>(
         current.push_str(part);
         current.push('/');
 
-        if index.lookup(&current).is_none() {
+        if index.lookup(block_io, &current)?.is_none() {
             let hash = fnv1a_64(current.as_bytes());
             let dir_bytes = current.as_bytes();
             let mut dir_payload = Vec::with_capacity(2 + dir_bytes.len());
@@ -348,16 +842,20 @@ pub fn rename<B: BlockIo>(
     log: &mut LogEngine,
     index: &mut NamespaceIndex,
     bitmap: &mut BlockBitmap,
+    dedup: &mut DedupTable,
     partition_lba_start: u64,
     data_start_block: u64,
     device_block_size: u32,
     old_path: &str,
     new_path: &str,
+    reclaim_dest: bool,
     timestamp_ns: u64,
 ) -> Result<Lsn, HelixError> {
     crate::index::btree::validate_path(new_path)?;
 
-    let src = *index.lookup_flex(old_path).ok_or(HelixError::NotFound)?;
+    let src = index
+        .lookup_flex(block_io, old_path)?
+        .ok_or(HelixError::NotFound)?;
 
     if src.flags & entry_flags::IS_DIR != 0 {
         let old_prefix = dir_prefix(old_path);
@@ -369,22 +867,24 @@ pub fn rename<B: BlockIo>(
         if new_prefix.starts_with(old_prefix.as_str()) {
             return Err(HelixError::PathInvalid);
         }
-        if index.lookup(&new_prefix).is_some() {
+        if index.lookup(block_io, &new_prefix)?.is_some() {
             return Err(HelixError::AlreadyExists);
         }
 
-        let mut moves: Vec<(String, String)> = Vec::new();
-        for entry in index.all_entries() {
-            if entry.flags & entry_flags::IS_DELETED != 0 {
-                continue;
-            }
-            let p = crate::index::btree::path_str(&entry.path);
-            if p.starts_with(old_prefix.as_str()) {
+        // The directory itself, then its subtree one listing at a time.
+        let mut moves: Vec<(String, String)> = vec![(old_prefix.clone(), new_prefix.clone())];
+        let mut pending = vec![old_prefix.clone()];
+        while let Some(dir) = pending.pop() {
+            for entry in index.readdir(block_io, &dir)? {
+                let p = crate::index::btree::path_str(&entry.path);
                 let mut to = new_prefix.clone();
                 to.push_str(&p[old_prefix.len()..]);
                 if to.len() > MAX_PATH_LEN {
                     return Err(HelixError::PathTooLong);
                 }
+                if entry.flags & entry_flags::IS_DIR != 0 {
+                    pending.push(String::from(p));
+                }
                 moves.push((String::from(p), to));
             }
         }
@@ -396,19 +896,31 @@ pub fn rename<B: BlockIo>(
         return Ok(last);
     }
 
-    // File rename: clobbering a file frees its blocks; a directory target is refused.
-    if let Some(dest) = index.lookup_flex(new_path) {
-        if dest.flags & entry_flags::IS_DIR != 0 {
-            return Err(HelixError::IsADirectory);
-        }
-        let (extent_root, size, is_node, is_inline) = (
+    // File rename: clobbering a file releases its blocks and attributes unless
+    // `reclaim_dest` is false (a snapshot still sees it); the moved entry keeps
+    // its own. A hard-linked target only loses a name. A directory target is
+    // refused.
+    let dest = index.lookup_flex(block_io, new_path)?;
+    if dest.is_some_and(|d| d.flags & entry_flags::IS_DIR != 0) {
+        return Err(HelixError::IsADirectory);
+    }
+    let lsn = log_rename_one(block_io, log, index, old_path, new_path, timestamp_ns)?;
+    let dest = match dest {
+        Some(d) if d.flags & entry_flags::IS_HARDLINK != 0 => {
+            super::link::drop_name(block_io, index, &d)?
+        },
+        other => other,
+    };
+    if let Some(dest) = dest {
+        let (extent_root, size, is_node, is_inline, xattr_block) = (
             dest.extent_root,
-            dest.size,
+            crate::dedup::extent_of(&dest).map_or(dest.size, |ext| ext.1),
             dest.flags & entry_flags::IS_EXTENT_NODE != 0,
             dest.flags & entry_flags::IS_INLINE != 0,
+            (dest.xattr_len != 0).then_some(dest.xattr_block),
         );
-        if !is_inline {
-            crate::extent::free_file_blocks(
+        if reclaim_dest && !is_inline {
+            dedup.release(
                 block_io,
                 bitmap,
                 partition_lba_start,
@@ -419,10 +931,11 @@ pub fn rename<B: BlockIo>(
                 is_node,
             );
         }
-        index.mark_deleted(new_path)?;
+        if let Some(block) = xattr_block.filter(|_| reclaim_dest) {
+            let _ = bitmap.free_block(block);
+        }
     }
-
-    log_rename_one(block_io, log, index, old_path, new_path, timestamp_ns)
+    Ok(lsn)
 }
 
 fn dir_prefix(path: &str) -> String {
@@ -441,7 +954,9 @@ fn log_rename_one<B: BlockIo>(
     new_full: &str,
     timestamp_ns: u64,
 ) -> Result<Lsn, HelixError> {
-    let mut new_entry = *index.lookup(old_full).ok_or(HelixError::NotFound)?;
+    let mut new_entry = index
+        .lookup(block_io, old_full)?
+        .ok_or(HelixError::NotFound)?;
     let new_key = fnv1a_64(new_full.as_bytes());
     new_entry.key = new_key;
     new_entry.path = [0u8; 256];
@@ -469,7 +984,7 @@ fn log_rename_one<B: BlockIo>(
     )?;
 
     new_entry.lsn = lsn;
-    index.mark_deleted(old_full)?;
+    index.mark_deleted(block_io, old_full)?;
     index.upsert(new_entry);
     Ok(lsn)
 }
@@ -0,0 +1,319 @@
+//! Extended attributes: per-entry key/value pairs.
+//!
+//! An entry's attributes live together in one data block,
+//! `[crc32c: u32][count: u16]` then `[name_len: u8][value_len: u16][name][value]`
+//! per attribute, sorted by name. Every change writes a fresh block
+//! (copy-on-write) and logs a `SetMeta` pointing the entry at it; the caller
+//! reclaims the superseded block unless a snapshot pins it. Attributes follow
+//! the entry through rewrites and renames; only unlink drops them.
+
+use crate::bitmap::BlockBitmap;
+use crate::crc::{crc32c, fnv1a_64};
+use crate::error::HelixError;
+use crate::index::btree::{self, NamespaceIndex};
+use crate::log::recovery::decode_path_payload;
+use crate::log::tx::TxGate;
+use crate::log::LogEngine;
+use crate::types::*;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+
+/// Attribute name -> value.
+pub type Xattrs = BTreeMap<String, Vec<u8>>;
+
+pub const MAX_XATTR_NAME_LEN: usize = 255;
+
+const HDR: usize = 6;
+
+fn block_lba(partition_lba_start: u64, data_start_block: u64, dbs: u32, rel_block: u64) -> Lba {
+    let scale = BLOCK_SIZE as u64 / dbs as u64;
+    Lba(partition_lba_start + (data_start_block + rel_block) * scale)
+}
+
+/// Names are non-empty UTF-8 without NUL (the syscall layer lists them
+/// NUL-separated).
+pub fn validate_name(name: &str) -> Result<(), HelixError> {
+    if name.is_empty() || name.len() > MAX_XATTR_NAME_LEN || name.contains('\0') {
+        return Err(HelixError::PathInvalid);
+    }
+    Ok(())
+}
+
+/// Serialize `attrs`; `AttributeTooLarge` if they do not fit one block.
+pub fn encode(attrs: &Xattrs) -> Result<Vec<u8>, HelixError> {
+    let mut blob = vec![0u8; HDR];
+    blob[4..6].copy_from_slice(&(attrs.len() as u16).to_le_bytes());
+    for (name, value) in attrs {
+        if blob.len() + 3 + name.len() + value.len() > BLOCK_SIZE as usize {
+            return Err(HelixError::AttributeTooLarge);
+        }
+        blob.push(name.len() as u8);
+        blob.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        blob.extend_from_slice(name.as_bytes());
+        blob.extend_from_slice(value);
+    }
+    let crc = crc32c(&blob[4..]);
+    blob[0..4].copy_from_slice(&crc.to_le_bytes());
+    Ok(blob)
+}
+
+/// Parse and CRC-verify a blob produced by `encode`.
+pub fn decode(blob: &[u8]) -> Result<Xattrs, HelixError> {
+    if blob.len() < HDR {
+        return Err(HelixError::ExtentCorrupt);
+    }
+    let stored = u32::from_le_bytes(blob[0..4].try_into().unwrap());
+    if crc32c(&blob[4..]) != stored {
+        return Err(HelixError::ExtentCorrupt);
+    }
+    let count = u16::from_le_bytes([blob[4], blob[5]]) as usize;
+    let mut attrs = Xattrs::new();
+    let mut off = HDR;
+    for _ in 0..count {
+        if off + 3 > blob.len() {
+            return Err(HelixError::ExtentCorrupt);
+        }
+        let name_len = blob[off] as usize;
+        let value_len = u16::from_le_bytes([blob[off + 1], blob[off + 2]]) as usize;
+        off += 3;
+        if off + name_len + value_len > blob.len() {
+            return Err(HelixError::ExtentCorrupt);
+        }
+        let name = core::str::from_utf8(&blob[off..off + name_len])
+            .map_err(|_| HelixError::ExtentCorrupt)?;
+        attrs.insert(
+            String::from(name),
+            blob[off + name_len..off + name_len + value_len].to_vec(),
+        );
+        off += name_len + value_len;
+    }
+    Ok(attrs)
+}
+
+/// Read the attribute block at `xattr_block` (`xattr_len` bytes; 0 = none).
+pub fn load_block<B: BlockIo>(
+    block_io: &mut B,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    xattr_block: BlockAddr,
+    xattr_len: u32,
+) -> Result<Xattrs, HelixError> {
+    if xattr_len == 0 {
+        return Ok(Xattrs::new());
+    }
+    if xattr_len > BLOCK_SIZE {
+        return Err(HelixError::ExtentCorrupt);
+    }
+    let mut buf = vec![0u8; BLOCK_SIZE as usize];
+    block_io
+        .read_blocks(
+            block_lba(
+                partition_lba_start,
+                data_start_block,
+                device_block_size,
+                xattr_block,
+            ),
+            &mut buf,
+        )
+        .map_err(|_| HelixError::IoReadFailed)?;
+    decode(&buf[..xattr_len as usize])
+}
+
+/// The live attributes of `path` (file or directory).
+pub fn load<B: BlockIo>(
+    block_io: &mut B,
+    index: &NamespaceIndex,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    path: &str,
+) -> Result<Xattrs, HelixError> {
+    let entry = index
+        .lookup_flex(block_io, path)?
+        .ok_or(HelixError::NotFound)?;
+    load_block(
+        block_io,
+        partition_lba_start,
+        data_start_block,
+        device_block_size,
+        entry.xattr_block,
+        entry.xattr_len,
+    )
+}
+
+/// Replace the attributes of `path` with `attrs`: write them to a fresh block
+/// (none when empty) and log a `SetMeta`. Content, size and mtime are
+/// untouched. Returns the LSN and the superseded `(block, xattr_lsn)`, if any.
+#[allow(clippy::too_many_arguments)]
+pub fn store<B: BlockIo>(
+    block_io: &mut B,
+    log: &mut LogEngine,
+    index: &mut NamespaceIndex,
+    bitmap: &mut BlockBitmap,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    path: &str,
+    attrs: &Xattrs,
+    timestamp_ns: u64,
+) -> Result<(Lsn, Option<(BlockAddr, Lsn)>), HelixError> {
+    // Log under the stored path so directories replay whether or not the
+    // caller spelled the trailing '/'.
+    let mut entry = index
+        .lookup_flex(block_io, path)?
+        .ok_or(HelixError::NotFound)?;
+    let old = (entry.xattr_len != 0).then_some((entry.xattr_block, entry.xattr_lsn));
+    let canonical = String::from(btree::path_str(&entry.path));
+
+    let (block, len) = if attrs.is_empty() {
+        (BLOCK_NULL, 0u32)
+    } else {
+        let blob = encode(attrs)?;
+        let block = bitmap.alloc_block()?;
+        let mut buf = vec![0u8; BLOCK_SIZE as usize];
+        buf[..blob.len()].copy_from_slice(&blob);
+        let lba = block_lba(
+            partition_lba_start,
+            data_start_block,
+            device_block_size,
+            block,
+        );
+        if block_io.write_blocks(lba, &buf).is_err() {
+            let _ = bitmap.free_block(block);
+            return Err(HelixError::IoWriteFailed);
+        }
+        (block, blob.len() as u32)
+    };
+
+    let path_b = canonical.as_bytes();
+    let mut payload = Vec::with_capacity(2 + path_b.len() + 12);
+    payload.extend_from_slice(&(path_b.len() as u16).to_le_bytes());
+    payload.extend_from_slice(path_b);
+    payload.extend_from_slice(&block.to_le_bytes());
+    payload.extend_from_slice(&len.to_le_bytes());
+    let lsn = match log.append(
+        block_io,
+        LogOp::SetMeta,
+        fnv1a_64(path_b),
+        &payload,
+        timestamp_ns,
+    ) {
+        Ok(lsn) => lsn,
+        Err(e) => {
+            if len != 0 {
+                let _ = bitmap.free_block(block);
+            }
+            return Err(e);
+        },
+    };
+
+    entry.xattr_block = block;
+    entry.xattr_len = len;
+    entry.xattr_lsn = lsn;
+    index.upsert(entry);
+    Ok((lsn, old))
+}
+
+/// Decode a `SetMeta` payload after `[path_len][path]`: `(xattr_block, xattr_len)`.
+pub fn decode_meta(rest: &[u8]) -> Option<(BlockAddr, u32)> {
+    if rest.len() < 12 {
+        return None;
+    }
+    Some((
+        u64::from_le_bytes(rest[0..8].try_into().ok()?),
+        u32::from_le_bytes(rest[8..12].try_into().ok()?),
+    ))
+}
+
+/// Attributes of `path` as of `target_lsn`. Walks tail..=`target_lsn` tracking
+/// each path's attribute block through SetMeta, Rename and Delete, so a file
+/// renamed after its attributes were set still resolves.
+#[allow(clippy::too_many_arguments)]
+pub fn xattrs_at_lsn<B: BlockIo>(
+    block_io: &mut B,
+    log: &LogEngine,
+    partition_lba_start: u64,
+    data_start_block: u64,
+    device_block_size: u32,
+    path: &str,
+    target_lsn: Lsn,
+) -> Result<Xattrs, HelixError> {
+    // path_hash -> (xattr_block, xattr_len).
+    let mut by_path: BTreeMap<u64, (BlockAddr, u32)> = BTreeMap::new();
+    let mut gate = TxGate::new();
+
+    let segment_count = log.segment_count();
+    let tail = log.tail_segment();
+    let head = log.head_segment();
+    let head_offset = log.head_offset();
+
+    let mut seg = tail;
+    'walk: loop {
+        let mut offset = core::mem::size_of::<LogSegmentHeader>() as u32;
+        loop {
+            if seg == head && offset >= head_offset {
+                break;
+            }
+            let Ok((header, payload)) = log.read_record(block_io, seg, offset) else {
+                break;
+            };
+            if header.lsn > target_lsn {
+                break 'walk;
+            }
+            gate.feed(&header, &payload, |header, payload| {
+                let Some((_, rest)) = decode_path_payload(payload) else {
+                    return Ok(());
+                };
+                match LogOp::from_u8(header.op) {
+                    Some(LogOp::SetMeta) => {
+                        if let Some(meta) = decode_meta(rest) {
+                            by_path.insert(header.path_hash, meta);
+                        }
+                    },
+                    Some(LogOp::Delete) => {
+                        by_path.remove(&header.path_hash);
+                    },
+                    Some(LogOp::Rename) => {
+                        if let Some((new_path, _)) = decode_path_payload(rest) {
+                            let moved = by_path.remove(&header.path_hash);
+                            let new_hash = fnv1a_64(new_path.as_bytes());
+                            match moved {
+                                Some(meta) => by_path.insert(new_hash, meta),
+                                None => by_path.remove(&new_hash),
+                            };
+                        }
+                    },
+                    _ => {},
+                }
+                Ok(())
+            })?;
+            offset += header.total_size() as u32;
+        }
+        if seg == head {
+            break;
+        }
+        seg = (seg + 1) % segment_count;
+    }
+
+    // Directories are logged with their trailing '/'.
+    let mut dir_form = String::from(path);
+    dir_form.push('/');
+    let (block, len) = by_path
+        .get(&fnv1a_64(path.as_bytes()))
+        .or_else(|| by_path.get(&fnv1a_64(dir_form.as_bytes())))
+        .copied()
+        .unwrap_or((BLOCK_NULL, 0));
+    load_block(
+        block_io,
+        partition_lba_start,
+        data_start_block,
+        device_block_size,
+        block,
+        len,
+    )
+}
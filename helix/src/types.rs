@@ -6,8 +6,12 @@ pub const HELIX_MAGIC: [u8; 8] = *b"HELIXFS1";
 
 /// Layout version. v1: path_hash only. v2: full path in payload. v3: extent
 /// Write records are flagged out-of-band (`rec_flags::IS_EXTENT`) instead of by
-/// an in-band marker byte that could collide with inline user data.
-pub const HELIX_VERSION: u32 = 3;
+/// an in-band marker byte that could collide with inline user data. v4: index
+/// regions are B+trees keyed by (parent, name) instead of flat entry arrays.
+/// The superblock's `index_region_blocks`, formerly `index_depth`, has always
+/// held the region's length in blocks, never a tree depth; the rename moves
+/// no bytes.
+pub const HELIX_VERSION: u32 = 4;
 
 pub const BLOCK_SIZE: u32 = 4096;
 pub const BLOCK_SHIFT: u32 = 12;
@@ -58,10 +62,10 @@ pub struct HelixSuperblock {
     pub data_start_block: u64,
     pub data_block_count: u64,
 
-    /// B-tree root at last checkpoint.
+    /// First block of the checkpoint B+tree region (see `checkpoint`).
     pub index_root_block: BlockAddr,
-    /// 0 = root is a leaf.
-    pub index_depth: u32,
+    /// Blocks in that region; its root is the last one.
+    pub index_region_blocks: u32,
     pub _pad0: u32,
 
     pub committed_lsn: Lsn,
@@ -76,9 +80,11 @@ pub struct HelixSuperblock {
     /// Null-terminated UTF-8, max 63 chars.
     pub label: [u8; 64],
 
+    /// One block of `SnapshotEntry`s (`BLOCK_NULL` when there are none).
     pub snapshot_table_block: BlockAddr,
     pub snapshot_count: u32,
-    pub _pad2: u32,
+    /// CRC32C of the first `snapshot_count` table entries.
+    pub snapshot_table_crc: u32,
 
     pub blocks_used: u64,
     pub file_count: u64,
@@ -90,15 +96,62 @@ pub struct HelixSuperblock {
     /// CRC32C of the whole 4 KiB block with this field zeroed.
     pub crc32c: u32,
 
-    /// Live entries serialized to the on-disk index checkpoint region
-    /// (`index_root_block` for `index_depth` blocks).
+    /// Live entries in the leaves of the checkpoint region
+    /// (`index_root_block` for `index_region_blocks` blocks).
     pub index_entry_count: u32,
 
-    pub _reserved: [u8; 3808],
+    /// Encryption at rest; all zero on a plain volume (see `crypt`).
+    pub crypt: CryptHeader,
+
+    /// Contiguous region of `VersionEntry`s, `history_blocks` long
+    /// (`BLOCK_NULL` when there are none; see `ops::retain`).
+    pub history_block: BlockAddr,
+    pub history_blocks: u32,
+    /// CRC32C of the first `history_count` entries.
+    pub history_crc: u32,
+    pub history_count: u64,
+    /// The region holds every version retained up to this LSN; replay adds
+    /// the `Retain` records after it.
+    pub history_lsn: Lsn,
+
+    /// Contiguous region of `QuotaEntry`s, `quota_blocks` long, as of the
+    /// last checkpoint (`BLOCK_NULL` when there are none; see `ops::quota`).
+    pub quota_block: BlockAddr,
+    pub quota_blocks: u32,
+    /// CRC32C of the first `quota_count` entries.
+    pub quota_crc: u32,
+    pub quota_count: u64,
+
+    pub _reserved: [u8; 3624],
 }
 
 const _ASSERT_SB_SIZE: () = assert!(core::mem::size_of::<HelixSuperblock>() == 4096);
 
+pub mod crypt_flags {
+    /// Every block other than the superblocks and the tag region is sealed.
+    /// Only ever set on disk: the unlocked view of the superblock clears it.
+    pub const ACTIVE: u32 = 1 << 0;
+}
+
+/// Key material of an encrypted volume, kept in the superblock. The volume
+/// key is sealed under a key derived from the passphrase with
+/// PBKDF2-HMAC-SHA256 (`kdf_rounds`, `salt`).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CryptHeader {
+    pub flags: u32,
+    pub kdf_rounds: u32,
+    pub salt: [u8; 16],
+    pub wrapped_key: [u8; 32],
+    pub wrapped_tag: [u8; 16],
+    /// Per-block nonce counters and tags, `tag_blocks` blocks from `tag_start`.
+    pub tag_start: BlockAddr,
+    pub tag_blocks: u64,
+    pub _reserved: [u8; 40],
+}
+
+const _ASSERT_CRYPT_SIZE: () = assert!(core::mem::size_of::<CryptHeader>() == 128);
+
 impl HelixSuperblock {
     pub const fn zeroed() -> Self {
         // SAFETY: all-zeros is valid for every field.
@@ -146,16 +199,69 @@ impl fmt::Debug for HelixSuperblock {
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct SnapshotEntry {
+    /// Null-terminated UTF-8, max 63 bytes.
     pub name: [u8; 64],
     pub lsn: Lsn,
     pub timestamp_ns: u64,
-    /// B-tree root at this LSN for fast snapshot mount.
+    /// Index region holding the namespace at `lsn` (checkpoint B+tree, see
+    /// `index_blocks`/`index_entry_count`). `BLOCK_NULL` while the marker is
+    /// still in the log ring; the next checkpoint writes it before recycling.
     pub index_root: BlockAddr,
-    pub _pad: [u8; 40],
+    pub index_blocks: u32,
+    pub index_entry_count: u32,
+    pub _pad: [u8; 32],
 }
 
 const _ASSERT_SNAP_SIZE: () = assert!(core::mem::size_of::<SnapshotEntry>() == 128);
 
+/// A prior version of a file kept under a retention policy; stored in the
+/// history region (see `ops::retain`).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct VersionEntry {
+    /// FNV-1a of `path`.
+    pub key: u64,
+    /// The path the version was written under, NUL-padded.
+    pub path: [u8; 256],
+    /// LSN of the record carrying the version's data.
+    pub lsn: Lsn,
+    /// `first_lsn` of the entry it came from; decides snapshot pins.
+    pub first_lsn: Lsn,
+    /// LSN of the write or delete that replaced it.
+    pub superseded_lsn: Lsn,
+    pub modified_ns: u64,
+    pub superseded_ns: u64,
+    pub size: u64,
+    pub extent_root: BlockAddr,
+    pub stored_len: u64,
+    pub content_crc64: u64,
+    /// The entry's storage flags (`IS_INLINE`, `IS_EXTENT_NODE`,
+    /// `IS_COMPRESSED`).
+    pub flags: u32,
+    pub _pad: u32,
+    pub inline_data: [u8; INLINE_DATA_SIZE],
+    pub _reserved: [u8; 72],
+}
+
+const _ASSERT_VERSION_SIZE: () = assert!(core::mem::size_of::<VersionEntry>() == 512);
+
+/// A directory quota and what its subtree held at the last checkpoint;
+/// stored in the quota region (see `ops::quota`).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct QuotaEntry {
+    /// The directory, with its trailing `/`, NUL-padded.
+    pub path: [u8; 256],
+    /// Limits; 0 is unlimited.
+    pub max_bytes: u64,
+    pub max_entries: u64,
+    pub bytes: u64,
+    pub entries: u64,
+    pub _reserved: [u8; 224],
+}
+
+const _ASSERT_QUOTA_SIZE: () = assert!(core::mem::size_of::<QuotaEntry>() == 512);
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct LogSegmentHeader {
@@ -185,7 +291,7 @@ pub enum LogOp {
     MkDir = 0x04,
     Rename = 0x05,
     SetMeta = 0x06,
-    /// Payload_crc64 matches an existing block.
+    /// Path shares an existing extent file with identical content (see `dedup`).
     DedupRef = 0x07,
     TxBegin = 0x08,
     /// Atomically applies records since TxBegin.
@@ -195,6 +301,17 @@ pub enum LogOp {
     /// B-tree root flushed to disk.
     Checkpoint = 0x0C,
     Truncate = 0x0D,
+    /// A symbolic link; the payload carries its target.
+    Symlink = 0x0E,
+    /// Another name for an existing file (see `ops::link`).
+    Link = 0x0F,
+    /// New owner and permission bits (see `ops::owner`).
+    SetOwner = 0x10,
+    /// A file moved into one contiguous run (see `clean`).
+    Relocate = 0x11,
+    /// An overwritten or deleted version kept under a retention policy (see
+    /// `ops::retain`).
+    Retain = 0x12,
 }
 
 impl LogOp {
@@ -213,6 +330,11 @@ impl LogOp {
             0x0B => Some(Self::Snapshot),
             0x0C => Some(Self::Checkpoint),
             0x0D => Some(Self::Truncate),
+            0x0E => Some(Self::Symlink),
+            0x0F => Some(Self::Link),
+            0x10 => Some(Self::SetOwner),
+            0x11 => Some(Self::Relocate),
+            0x12 => Some(Self::Retain),
             _ => None,
         }
     }
@@ -234,9 +356,11 @@ pub struct LogRecordHeader {
     pub path_hash: u64,
     /// Payload CRC64; used for dedup.
     pub payload_crc64: u64,
-    /// New path hash on Rename, else 0.
+    /// New path hash on Rename; content CRC64 on whole-file extent Write and
+    /// DedupRef (0 = unknown, e.g. a ranged write); else 0.
     pub secondary_hash: u64,
-    /// Matching TxBegin LSN on TxCommit, else 0.
+    /// Owning TxBegin LSN on records logged inside a transaction and on its
+    /// TxCommit/TxAbort, else 0.
     pub tx_begin_lsn: Lsn,
     /// CRC32C of header + payload with this field zeroed.
     pub record_crc32c: u32,
@@ -258,6 +382,10 @@ pub mod rec_flags {
     /// Write payload is an extent descriptor, not inline data. Authoritative —
     /// classification must never inspect payload bytes.
     pub const IS_EXTENT: u8 = 1 << 0;
+    /// Extent Write from a ranged write: only `[offset, offset+len)` changed, and
+    /// the descriptor is followed by `[offset: u64][len: u64]`. The descriptor
+    /// alone still fully describes the new version.
+    pub const IS_DELTA: u8 = 1 << 1;
 }
 
 pub mod entry_flags {
@@ -266,9 +394,22 @@ pub mod entry_flags {
     pub const IS_INLINE: u32 = 1 << 2;
     /// Synthetic VFS node (e.g. /sys).
     pub const IS_SYS: u32 = 1 << 3;
+    /// Created by a `DedupRef`: `extent_root` is shared with another entry and
+    /// reclaimed through the dedup refcount.
     pub const IS_DEDUP: u32 = 1 << 4;
     /// `extent_root` addresses an extent-node block, not a contiguous run.
     pub const IS_EXTENT_NODE: u32 = 1 << 5;
+    /// `extent_root` is the first of `ceil(stored_len/BLOCK)` contiguous blocks
+    /// holding a `compress` frame.
+    pub const IS_COMPRESSED: u32 = 1 << 6;
+    /// A symbolic link; the content is its target path.
+    pub const IS_SYMLINK: u32 = 1 << 7;
+    /// One name of a hard-linked file. Owns nothing: the content lives in the
+    /// link record `ops::link::record_path(link_id)`.
+    pub const IS_HARDLINK: u32 = 1 << 8;
+    /// `uid`, `gid` and `mode` are set. Entries without it belong to root,
+    /// with `ops::owner::default_mode`.
+    pub const HAS_OWNER: u32 = 1 << 9;
 }
 
 /// Discriminates an extent Write payload's residency. Stored as the first byte
@@ -278,9 +419,12 @@ pub mod extent_kind {
     pub const CONTIGUOUS: u8 = 1;
     /// `extent_root` is an extent-node block listing every run.
     pub const NODE: u8 = 2;
+    /// Contiguous `compress` frame; the descriptor is followed by its
+    /// `stored_len: u64`.
+    pub const COMPRESSED: u8 = 3;
 }
 
-/// 512-byte B-tree leaf entry; 8 entries per 4 KiB block.
+/// 512-byte B+tree leaf entry; 7 per 4 KiB leaf after the node header.
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct IndexEntry {
@@ -304,22 +448,61 @@ pub struct IndexEntry {
     /// CRC64 of current content; for dedup.
     pub content_crc64: u64,
     pub crc32c: u32,
-    pub _reserved: [u8; 60],
+    /// Encoded size of the xattr block; 0 = no attributes.
+    pub xattr_len: u32,
+    /// Block holding this entry's extended attributes (see `ops::xattr`);
+    /// meaningful only when `xattr_len` is nonzero.
+    pub xattr_block: BlockAddr,
+    /// LSN of the SetMeta that installed `xattr_block`; decides snapshot pins.
+    pub xattr_lsn: Lsn,
+    /// Frame length of an `IS_COMPRESSED` extent; 0 otherwise.
+    pub stored_len: u64,
+    /// Link record an `IS_HARDLINK` name refers to; 0 otherwise.
+    pub link_id: u64,
+    /// Names referring to this entry, on a link record; 0 otherwise.
+    pub link_count: u32,
+    /// Owner and permission bits (`0o7777`); valid with `HAS_OWNER`.
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+    pub _reserved: [u8; 8],
 }
 
 const _ASSERT_ENTRY_SIZE: () = assert!(core::mem::size_of::<IndexEntry>() == 512);
 
-/// B-tree internal node header. Block layout:
-/// `[header(32)][keys: u64 × ORDER][children: u64 × (ORDER+1)]`.
+impl IndexEntry {
+    /// Carry identity over from the version this entry replaces: birth time,
+    /// first LSN, a grown version count, the extended attributes, the link
+    /// count and the owner.
+    pub fn inherit(&mut self, prior: &IndexEntry) {
+        self.created_ns = prior.created_ns;
+        self.first_lsn = prior.first_lsn;
+        self.version_count = prior.version_count + 1;
+        self.xattr_len = prior.xattr_len;
+        self.xattr_block = prior.xattr_block;
+        self.xattr_lsn = prior.xattr_lsn;
+        self.link_count = prior.link_count;
+        self.flags |= prior.flags & entry_flags::HAS_OWNER;
+        self.uid = prior.uid;
+        self.gid = prior.gid;
+        self.mode = prior.mode;
+    }
+}
+
+/// B+tree node header. Internal block layout:
+/// `[header(32)][keys: u64 × ORDER][children: u64 × (ORDER+1)]`; leaf:
+/// `[header(32)][IndexEntry × LEAF_ENTRIES_PER_BLOCK]`.
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct BTreeNodeHeader {
     /// 0x01 internal, 0x02 leaf.
     pub node_type: u8,
     pub _pad: [u8; 3],
+    /// Keys of an internal node (children = keys + 1); entries of a leaf.
     pub key_count: u32,
     /// Self-block; validates the block was read from where we expected.
     pub self_block: BlockAddr,
+    /// CRC32C of the whole block with this field zeroed.
     pub crc32c: u32,
     pub _reserved: [u8; 12],
 }
@@ -426,6 +609,26 @@ impl FileDescriptor {
     }
 }
 
+/// Space and health of a mounted volume; see `HelixFs::usage`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VolumeUsage {
+    /// Data blocks, `BLOCK_SIZE` each.
+    pub total_blocks: u64,
+    pub free_blocks: u64,
+    /// Allocated blocks the namespace does not reference: what only
+    /// snapshots and retained versions hold.
+    pub pinned_blocks: u64,
+    /// `LogEngine::log_utilization_pct`; appends fail with `LogFull` at 100.
+    pub log_used_pct: u32,
+    pub snapshots: u32,
+    /// Entries in the namespace.
+    pub live_entries: u64,
+    /// Entries the index stores, tombstones awaiting a checkpoint included.
+    pub stored_entries: u64,
+    /// Versions retained under a retention policy.
+    pub versions: u64,
+}
+
 // Canonical kernel↔userland FFI types, syscall numbers, and seek whence all live
 // in morpheus-foundation — the single source of truth. Re-exported (not
 // re-declared) so the values can never desync.
@@ -0,0 +1,125 @@
+//! The paged namespace index: a checkpointed tree is read back a node at a
+//! time, and changes made since shadow it until the next checkpoint.
+
+mod common;
+
+use common::{fresh, MemBio};
+use morpheus_helix::checkpoint::{region_blocks, NODE_CACHE_NODES};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+const DIRS: usize = 8;
+const FILES_PER_DIR: usize = 250;
+
+fn names(fs: &HelixFs, dev: &mut MemBio, dir: &str) -> Vec<String> {
+    let mut out: Vec<String> = fs
+        .readdir(dev, dir)
+        .unwrap()
+        .iter()
+        .map(|e| String::from(std::str::from_utf8(&e.name[..e.name_len as usize]).unwrap()))
+        .collect();
+    out.sort();
+    out
+}
+
+/// `DIRS` directories of `FILES_PER_DIR` small files, checkpointed.
+fn populated() -> MemBio {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    for d in 0..DIRS {
+        for f in 0..FILES_PER_DIR {
+            let path = format!("/d{d}/f{f}");
+            fs.write(&mut dev, &path, path.as_bytes(), 1).unwrap();
+        }
+    }
+    fs.checkpoint(&mut dev).unwrap();
+    dev
+}
+
+#[test]
+fn a_large_namespace_pages_in_through_a_bounded_cache() {
+    let mut dev = populated();
+    let fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    let entries = DIRS * (FILES_PER_DIR + 1);
+    let base = fs.index.base().unwrap();
+    assert_eq!(base.entry_count(), entries as u64);
+    assert_eq!(fs.sb.index_region_blocks as u64, region_blocks(entries));
+    assert_eq!(fs.index.overlay_len(), 0, "a clean mount holds nothing");
+
+    for (d, f) in [(0, 0), (3, 127), (7, FILES_PER_DIR - 1), (5, 64)] {
+        let path = format!("/d{d}/f{f}");
+        assert_eq!(fs.read(&mut dev, &path).unwrap(), path.as_bytes());
+    }
+    assert_eq!(fs.read(&mut dev, "/d2/nope"), Err(HelixError::NotFound));
+
+    let mut want: Vec<String> = (0..FILES_PER_DIR).map(|f| format!("f{f}")).collect();
+    want.sort();
+    for d in 0..DIRS {
+        assert_eq!(names(&fs, &mut dev, &format!("/d{d}")), want);
+    }
+    assert!(fs.index.base().unwrap().cached_nodes() <= NODE_CACHE_NODES);
+}
+
+#[test]
+fn changes_shadow_the_tree_until_the_next_checkpoint() {
+    let mut dev = populated();
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    fs.unlink(&mut dev, "/d1/f10", 2).unwrap();
+    fs.rename(&mut dev, "/d1/f11", "/d6/moved", 3).unwrap();
+    fs.write(&mut dev, "/d1/f12", b"rewritten", 4).unwrap();
+    fs.rename(&mut dev, "/d4", "/e4", 5).unwrap();
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    let check = |fs: &HelixFs, dev: &mut MemBio| {
+        assert_eq!(fs.read(dev, "/d1/f10"), Err(HelixError::NotFound));
+        assert_eq!(fs.read(dev, "/d1/f11"), Err(HelixError::NotFound));
+        assert_eq!(fs.read(dev, "/d6/moved").unwrap(), b"/d1/f11");
+        assert_eq!(fs.read(dev, "/d1/f12").unwrap(), b"rewritten");
+        assert_eq!(fs.read(dev, "/e4/f9").unwrap(), b"/d4/f9");
+        assert!(fs.stat(dev, "/d4").is_err());
+        assert_eq!(names(fs, dev, "/d1").len(), FILES_PER_DIR - 2);
+        assert_eq!(names(fs, dev, "/d6").len(), FILES_PER_DIR + 1);
+        assert_eq!(names(fs, dev, "/e4").len(), FILES_PER_DIR);
+        assert!(!names(fs, dev, "/").contains(&String::from("d4")));
+    };
+
+    // Replayed over the old tree: removed entries are tombstones.
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    check(&fs, &mut dev);
+    assert!(fs.index.overlay_len() > 0);
+
+    fs.checkpoint(&mut dev).unwrap();
+    assert_eq!(fs.index.overlay_len(), 0);
+    check(&fs, &mut dev);
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.index.overlay_len(), 0);
+    assert_eq!(
+        fs.index.base().unwrap().entry_count(),
+        (DIRS * (FILES_PER_DIR + 1) - 1) as u64
+    );
+    check(&fs, &mut dev);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn an_abort_restores_entries_held_by_the_tree() {
+    let mut dev = populated();
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    fs.begin_tx(&mut dev, 2).unwrap();
+    fs.unlink(&mut dev, "/d0/f0", 3).unwrap();
+    fs.write(&mut dev, "/d0/f1", b"changed", 4).unwrap();
+    fs.write(&mut dev, "/d0/new", b"new", 5).unwrap();
+    assert_eq!(fs.read(&mut dev, "/d0/f0"), Err(HelixError::NotFound));
+    fs.abort(&mut dev, 6).unwrap();
+
+    assert_eq!(fs.index.overlay_len(), 0);
+    assert_eq!(fs.read(&mut dev, "/d0/f0").unwrap(), b"/d0/f0");
+    assert_eq!(fs.read(&mut dev, "/d0/f1").unwrap(), b"/d0/f1");
+    assert_eq!(fs.read(&mut dev, "/d0/new"), Err(HelixError::NotFound));
+    assert_eq!(names(&fs, &mut dev, "/d0").len(), FILES_PER_DIR);
+}
@@ -0,0 +1,194 @@
+//! Background cleaning: `clean_step` moves fragmented files into one
+//! contiguous run without changing what they read back, leaves what a
+//! snapshot still sees alone, stays within its I/O budget (index reads and
+//! checkpoints included), and recycles a log ring that is filling with dead
+//! records.
+
+mod common;
+
+use common::MemBio;
+use morpheus_helix::clean::{LOG_CLEAN_PCT, LOG_FORCE_PCT};
+use morpheus_helix::types::entry_flags;
+use morpheus_helix::HelixFs;
+
+/// 2200 sectors -> exactly 16 data blocks (see `fragmentation.rs`).
+const SMALL_SECTORS: usize = 2200;
+/// 16384 sectors -> 8 MiB volume with a one-segment log ring.
+const DISK_SECTORS: usize = 16384;
+const BLOCK: usize = 4096;
+
+/// Fill the 16 data blocks with one-block files, free every odd one, then
+/// write `/frag` across two non-adjacent holes (plus a node block).
+fn fragmented() -> (MemBio, HelixFs, Vec<u8>) {
+    let mut dev = MemBio::new(SMALL_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+    for i in 0..16u8 {
+        fs.write(&mut dev, &format!("/f{i}"), &vec![0x10 + i; BLOCK], 1)
+            .unwrap();
+    }
+    for i in (1..16u8).step_by(2) {
+        fs.unlink(&mut dev, &format!("/f{i}"), 2).unwrap();
+    }
+    let frag: Vec<u8> = (0..2 * BLOCK).map(|i| (i / 7) as u8).collect();
+    fs.write(&mut dev, "/frag", &frag, 3).unwrap();
+    (dev, fs, frag)
+}
+
+fn is_node(dev: &mut MemBio, fs: &HelixFs, path: &str) -> bool {
+    let e = fs.index.lookup(dev, path).unwrap().unwrap();
+    e.flags & entry_flags::IS_EXTENT_NODE != 0
+}
+
+#[test]
+fn defrag_moves_a_file_into_one_run() {
+    let (mut dev, mut fs, frag) = fragmented();
+    assert!(is_node(&mut dev, &fs, "/frag"));
+
+    // No two free blocks are adjacent yet: nothing can move.
+    let step = fs.clean_step(&mut dev, 1024, 4).unwrap();
+    assert!(step.idle);
+    assert!(is_node(&mut dev, &fs, "/frag"));
+
+    // Freeing two neighbours opens a three-block run.
+    fs.unlink(&mut dev, "/f8", 5).unwrap();
+    fs.unlink(&mut dev, "/f10", 5).unwrap();
+    let free = fs.bitmap.free_count();
+    let before = fs.stat(&mut dev, "/frag").unwrap();
+    let step = fs.clean_step(&mut dev, 1024, 6).unwrap();
+    assert!(!step.idle);
+    assert!(!is_node(&mut dev, &fs, "/frag"));
+    // Two data blocks moved; the node block is gone.
+    assert_eq!(fs.bitmap.free_count(), free + 1);
+    assert_eq!(fs.read(&mut dev, "/frag").unwrap(), frag);
+    let after = fs.stat(&mut dev, "/frag").unwrap();
+    assert_eq!(
+        (after.lsn, after.modified_ns, after.version_count),
+        (before.lsn, before.modified_ns, before.version_count)
+    );
+    let stats = fs.clean_stats();
+    assert_eq!((stats.files_defragmented, stats.blocks_moved), (1, 2));
+
+    // The move was synced: it survives a remount without one.
+    drop(fs);
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert!(!is_node(&mut dev, &fs, "/frag"));
+    assert_eq!(fs.read(&mut dev, "/frag").unwrap(), frag);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    assert!(fs.clean_step(&mut dev, 1024, 7).unwrap().idle);
+}
+
+#[test]
+fn defrag_leaves_snapshotted_files_alone() {
+    let (mut dev, mut fs, frag) = fragmented();
+    fs.unlink(&mut dev, "/f8", 4).unwrap();
+    fs.unlink(&mut dev, "/f10", 4).unwrap();
+    fs.snapshot(&mut dev, "s", 5).unwrap();
+    assert!(fs.clean_step(&mut dev, 1024, 6).unwrap().idle);
+    assert!(is_node(&mut dev, &fs, "/frag"));
+
+    fs.delete_snapshot(&mut dev, "s").unwrap();
+    fs.clean_step(&mut dev, 1024, 8).unwrap();
+    assert!(!is_node(&mut dev, &fs, "/frag"));
+    assert_eq!(fs.read(&mut dev, "/frag").unwrap(), frag);
+}
+
+#[test]
+fn defrag_is_incremental_within_its_budget() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+    // Interleave appends so each file's blocks alternate with the others'.
+    let names = ["/a", "/b", "/c"];
+    for round in 0..3u8 {
+        for (i, name) in names.iter().enumerate() {
+            let data = vec![round * 16 + i as u8; BLOCK];
+            let off = round as u64 * BLOCK as u64;
+            fs.write_at(&mut dev, name, off, &data, 1).unwrap();
+        }
+    }
+    for name in names {
+        assert!(is_node(&mut dev, &fs, name));
+    }
+
+    // One file costs its node read plus a read and a write per block.
+    for moved in 1..=3 {
+        let step = fs.clean_step(&mut dev, 1, 2).unwrap();
+        assert_eq!(step.io_blocks, 1 + 2 * 3);
+        assert_eq!(fs.clean_stats().files_defragmented, moved);
+    }
+    for (i, name) in names.iter().enumerate() {
+        assert!(!is_node(&mut dev, &fs, name));
+        let data = fs.read(&mut dev, name).unwrap();
+        for round in 0..3usize {
+            assert!(data[round * BLOCK..(round + 1) * BLOCK]
+                .iter()
+                .all(|&b| b == round as u8 * 16 + i as u8));
+        }
+    }
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn a_live_ring_is_left_until_nearly_full() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    // Every record names a distinct file, so none of them is dead.
+    let mut n = 0u64;
+    while fs.log.fill_pct() < LOG_CLEAN_PCT {
+        fs.write(&mut dev, &format!("/d/{n}"), b"live", n).unwrap();
+        n += 1;
+    }
+    fs.clean_step(&mut dev, 64, n).unwrap();
+    assert_eq!(fs.clean_stats().checkpoints, 0);
+
+    while fs.log.fill_pct() < LOG_FORCE_PCT {
+        fs.write(&mut dev, &format!("/d/{n}"), b"live", n).unwrap();
+        n += 1;
+    }
+    // Rewriting an index this size is more than a small step may spend, and
+    // walking it is charged as it goes.
+    let step = fs.clean_step(&mut dev, 64, n).unwrap();
+    assert_eq!(fs.clean_stats().checkpoints, 0);
+    assert_eq!(step.io_blocks, 64);
+    assert!(!step.idle);
+
+    fs.clean_step(&mut dev, 4096, n).unwrap();
+    assert_eq!(fs.clean_stats().checkpoints, 1);
+    assert!(fs.log.fill_pct() < LOG_CLEAN_PCT);
+
+    // The rewritten index is read back a leaf at a time, each one charged.
+    let scanned = fs.clean_stats().files_scanned;
+    let step = fs.clean_step(&mut dev, 8, n).unwrap();
+    assert!(step.io_blocks <= 9);
+    assert!(fs.clean_stats().files_scanned - scanned < 8 * 7);
+}
+
+#[test]
+fn a_mostly_dead_ring_is_checkpointed_early() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+    fs.write(&mut dev, "/cold", b"kept", 0).unwrap();
+
+    // Each rewrite of one file is dead the moment the next one lands.
+    let mut i = 0u64;
+    while fs.log.fill_pct() < LOG_CLEAN_PCT {
+        fs.write(&mut dev, "/hot", &i.to_le_bytes(), i).unwrap();
+        i += 1;
+    }
+    let step = fs.clean_step(&mut dev, 64, i).unwrap();
+    assert!(!step.idle);
+    assert_eq!(fs.clean_stats().checkpoints, 1);
+    assert!(fs.log.fill_pct() < LOG_CLEAN_PCT);
+    fs.write(&mut dev, "/hot", b"after", i).unwrap();
+    fs.sync(&mut dev).unwrap();
+
+    drop(fs);
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.read(&mut dev, "/hot").unwrap(), b"after");
+    assert_eq!(fs.read(&mut dev, "/cold").unwrap(), b"kept");
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
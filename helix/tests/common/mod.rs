@@ -10,6 +10,7 @@
 use core::fmt;
 use gpt_disk_io::BlockIo;
 use gpt_disk_types::{BlockSize, Lba};
+use morpheus_helix::HelixFs;
 
 pub const SECTOR: usize = 512;
 
@@ -86,6 +87,31 @@ impl BlockIo for MemBio {
     }
 }
 
+/// Format the whole of `dev` with 512-byte sectors and mount it.
+pub fn fresh(dev: &mut MemBio) -> HelixFs {
+    let sectors = dev.sectors();
+    HelixFs::format_and_mount(dev, 0, sectors, 512, "t", [0u8; 16]).unwrap()
+}
+
+/// Remount `dev` and check fsck finds it clean.
+pub fn remount_clean(dev: &mut MemBio) -> HelixFs {
+    let mut fs = HelixFs::mount(dev, 0, 512).unwrap();
+    assert!(fs.fsck(dev, false).unwrap().is_clean());
+    fs
+}
+
+/// `len` bytes that differ per `seed` and do not repeat within 256 bytes.
+pub fn pattern(len: usize, seed: u8) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+        .collect()
+}
+
+/// Two blocks of `byte`: extent-backed, so versions own blocks.
+pub fn content(byte: u8) -> Vec<u8> {
+    vec![byte; 2 * 4096]
+}
+
 /// Write-back-cache disk for crash-consistency tests. Writes land in a volatile
 /// cache; only `flush()` commits the cache to durable media. `crash()` models a
 /// power cut: every write since the last `flush()` evaporates. This makes
@@ -0,0 +1,334 @@
+//! Transparent compression: a policy on a file or directory stores later
+//! writes as LZ4 frames in fewer blocks; reads, ranged reads, remounts,
+//! snapshots and fsck see the original bytes; ranged writes re-encode only
+//! the chunks they touch; unlink and incompressible data fall back to plain
+//! storage correctly.
+
+mod common;
+
+use common::{fresh, MemBio};
+use morpheus_helix::compress::{self, CHUNK_SIZE, POLICY_XATTR};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::types::entry_flags;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+/// Log-like lines: compresses well, but not into one repeated run.
+fn text(len: usize, seed: u8) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = seed as usize;
+    while out.len() < len {
+        let line = format!(
+            "[{}] helix: wrote extent {} lsn {}\n",
+            i % 50,
+            i % 977,
+            i % 13
+        );
+        out.extend_from_slice(line.as_bytes());
+        i = i.wrapping_mul(31).wrapping_add(7);
+    }
+    out.truncate(len);
+    out
+}
+
+/// xorshift noise: does not compress.
+fn noise(len: usize, seed: u64) -> Vec<u8> {
+    let mut x = seed | 1;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as u8
+        })
+        .collect()
+}
+
+fn is_compressed(fs: &HelixFs, dev: &mut MemBio, path: &str) -> bool {
+    fs.index
+        .lookup(dev, path)
+        .unwrap()
+        .is_some_and(|e| e.flags & entry_flags::IS_COMPRESSED != 0)
+}
+
+#[test]
+fn codec_round_trips() {
+    for data in [
+        Vec::new(),
+        b"abc".to_vec(),
+        vec![7u8; 100_000],
+        text(3 * CHUNK_SIZE + 123, 1),
+        noise(CHUNK_SIZE + 5, 9),
+    ] {
+        let frame = compress::pack(&data);
+        assert_eq!(compress::unpack(&frame, data.len() as u64).unwrap(), data);
+    }
+    let frame = compress::pack(&text(200_000, 2));
+    assert!(frame.len() < 100_000);
+    let mut bad = frame.clone();
+    let last = bad.len() - 1;
+    bad[last] ^= 0xFF;
+    bad.truncate(last);
+    assert_eq!(
+        compress::unpack(&bad, 200_000),
+        Err(HelixError::ExtentCorrupt)
+    );
+}
+
+#[test]
+fn a_splice_re_encodes_only_the_chunks_it_touches() {
+    let data = text(3 * CHUNK_SIZE + 500, 4);
+    let frame = compress::pack(&data);
+    let at = CHUNK_SIZE as u64 + 10;
+    let spliced =
+        compress::splice(&frame, data.len() as u64, at, b"PATCHED", data.len() as u64).unwrap();
+    let mut want = data.clone();
+    want[at as usize..at as usize + 7].copy_from_slice(b"PATCHED");
+    assert_eq!(compress::unpack(&spliced, want.len() as u64).unwrap(), want);
+
+    // Chunks 0, 2 and 3 carry over byte for byte.
+    let size = data.len() as u64;
+    let old = compress::FrameHeader::parse(&frame, size, frame.len() as u64).unwrap();
+    let new = compress::FrameHeader::parse(&spliced, size, spliced.len() as u64).unwrap();
+    for k in [0, 2, 3] {
+        let (a, b) = old.span(k);
+        let (c, d) = new.span(k);
+        assert_eq!(frame[a..b], spliced[c..d]);
+    }
+
+    // Growing past EOF zero-fills the gap; shrinking drops the tail.
+    let grown = compress::splice(&frame, size, size + 100, b"end", size + 103).unwrap();
+    let mut want = data.clone();
+    want.resize(data.len() + 100, 0);
+    want.extend_from_slice(b"end");
+    assert_eq!(compress::unpack(&grown, size + 103).unwrap(), want);
+    let shrunk = compress::splice(&frame, size, 1000, b"", 1000).unwrap();
+    assert_eq!(compress::unpack(&shrunk, 1000).unwrap(), data[..1000]);
+}
+
+#[test]
+fn a_directory_policy_compresses_writes_below_it() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/logs", 1).unwrap();
+    fs.setxattr(&mut dev, "/logs", POLICY_XATTR, b"lz4", 2)
+        .unwrap();
+    let data = text(300_000, 3);
+
+    let before = fs.bitmap.allocated_count();
+    fs.write(&mut dev, "/logs/a/day1", &data, 3).unwrap();
+    let used = fs.bitmap.allocated_count() - before;
+    assert!(is_compressed(&fs, &mut dev, "/logs/a/day1"));
+    assert!(used < data.len().div_ceil(4096) as u64 / 2, "{used} blocks");
+
+    let st = fs.stat(&mut dev, "/logs/a/day1").unwrap();
+    assert_eq!(st.size, data.len() as u64);
+    assert!(st.physical_size < st.size / 2);
+    assert_eq!(fs.read(&mut dev, "/logs/a/day1").unwrap(), data);
+
+    fs.write(&mut dev, "/plain", &data, 4).unwrap();
+    assert!(!is_compressed(&fs, &mut dev, "/plain"));
+    assert_eq!(
+        fs.stat(&mut dev, "/plain").unwrap().physical_size,
+        data.len() as u64
+    );
+}
+
+#[test]
+fn ranged_reads_decode_only_what_they_need() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    let data = text(5 * CHUNK_SIZE + 777, 4);
+    fs.write(&mut dev, "/f", b"", 1).unwrap();
+    fs.setxattr(&mut dev, "/f", POLICY_XATTR, b"lz4", 2)
+        .unwrap();
+    fs.write(&mut dev, "/f", &data, 3).unwrap();
+    assert!(is_compressed(&fs, &mut dev, "/f"));
+
+    for (offset, len) in [
+        (0, 10),
+        (CHUNK_SIZE - 3, 10),
+        (2 * CHUNK_SIZE + 5, 2 * CHUNK_SIZE),
+        (data.len() - 20, 100),
+    ] {
+        let mut buf = vec![0u8; len];
+        let n = fs.read_at(&mut dev, "/f", offset as u64, &mut buf).unwrap();
+        let want = &data[offset..(offset + len).min(data.len())];
+        assert_eq!(&buf[..n], want, "offset {offset}");
+    }
+    let mut buf = [0u8; 4];
+    assert_eq!(
+        fs.read_at(&mut dev, "/f", data.len() as u64, &mut buf)
+            .unwrap(),
+        0
+    );
+}
+
+#[test]
+fn compressed_files_survive_remount_and_snapshots() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/z", 1).unwrap();
+    fs.setxattr(&mut dev, "/z", POLICY_XATTR, b"lz4", 2)
+        .unwrap();
+    let v1 = text(150_000, 5);
+    let v2 = text(150_000, 6);
+    fs.write(&mut dev, "/z/f", &v1, 3).unwrap();
+    let snap = fs.snapshot(&mut dev, "s", 4).unwrap();
+    fs.write(&mut dev, "/z/f", &v2, 5).unwrap();
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    // Replay from the log.
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert!(is_compressed(&fs, &mut dev, "/z/f"));
+    assert_eq!(fs.read(&mut dev, "/z/f").unwrap(), v2);
+    let at_snap = morpheus_helix::ops::read::read_file_at_lsn(
+        &mut dev,
+        &fs.log,
+        fs.partition_lba_start,
+        fs.sb.data_start_block,
+        fs.device_block_size,
+        "/z/f",
+        snap,
+    )
+    .unwrap();
+    assert_eq!(at_snap, v1);
+
+    // And from a checkpoint.
+    fs.checkpoint(&mut dev).unwrap();
+    drop(fs);
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.read(&mut dev, "/z/f").unwrap(), v2);
+    assert!(fs.stat(&mut dev, "/z/f").unwrap().physical_size < v2.len() as u64);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    drop(fs);
+    let old = HelixFs::mount_snapshot(&mut dev, 0, 512, snap).unwrap();
+    assert_eq!(old.read(&mut dev, "/z/f").unwrap(), v1);
+}
+
+#[test]
+fn a_file_opts_out_and_noise_stays_plain() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/d", 1).unwrap();
+    fs.setxattr(&mut dev, "/d", POLICY_XATTR, b"lz4", 2)
+        .unwrap();
+    fs.write(&mut dev, "/d/keep", b"", 3).unwrap();
+    fs.setxattr(&mut dev, "/d/keep", POLICY_XATTR, b"none", 4)
+        .unwrap();
+    fs.write(&mut dev, "/d/keep", &text(50_000, 7), 5).unwrap();
+    assert!(!is_compressed(&fs, &mut dev, "/d/keep"));
+
+    let random = noise(50_000, 11);
+    fs.write(&mut dev, "/d/random", &random, 6).unwrap();
+    assert!(
+        !is_compressed(&fs, &mut dev, "/d/random"),
+        "a frame would not save a block"
+    );
+    assert_eq!(fs.read(&mut dev, "/d/random").unwrap(), random);
+
+    assert_eq!(
+        fs.setxattr(&mut dev, "/d", POLICY_XATTR, b"zstd", 7),
+        Err(HelixError::NotSupported)
+    );
+}
+
+#[test]
+fn ranged_writes_and_unlink_leave_no_stray_blocks() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    let base = fs.bitmap.allocated_count();
+    fs.mkdir(&mut dev, "/c", 1).unwrap();
+    fs.setxattr(&mut dev, "/c", POLICY_XATTR, b"lz4", 2)
+        .unwrap();
+    let attrs = fs.bitmap.allocated_count();
+    let mut data = text(120_000, 8);
+    fs.write(&mut dev, "/c/f", &data, 3).unwrap();
+    assert!(is_compressed(&fs, &mut dev, "/c/f"));
+
+    fs.write_at(&mut dev, "/c/f", 70_000, b"PATCHED", 4)
+        .unwrap();
+    data[70_000..70_007].copy_from_slice(b"PATCHED");
+    assert!(
+        is_compressed(&fs, &mut dev, "/c/f"),
+        "the splice keeps the file compressed"
+    );
+    assert_eq!(fs.read(&mut dev, "/c/f").unwrap(), data);
+    let stored = fs
+        .index
+        .lookup(&mut dev, "/c/f")
+        .unwrap()
+        .unwrap()
+        .stored_len;
+    assert_eq!(
+        fs.bitmap.allocated_count() - attrs,
+        stored.div_ceil(4096),
+        "only the new frame"
+    );
+
+    fs.write(&mut dev, "/c/f", &data, 5).unwrap();
+    assert!(is_compressed(&fs, &mut dev, "/c/f"));
+    fs.truncate(&mut dev, "/c/f", 10_000, 6).unwrap();
+    assert!(is_compressed(&fs, &mut dev, "/c/f"));
+    assert_eq!(fs.read(&mut dev, "/c/f").unwrap(), data[..10_000]);
+
+    fs.write(&mut dev, "/c/g", &data, 7).unwrap();
+    fs.unlink(&mut dev, "/c/g", 8).unwrap();
+    fs.unlink(&mut dev, "/c/f", 9).unwrap();
+    assert_eq!(fs.bitmap.allocated_count(), attrs);
+    assert!(attrs > base);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn a_corrupt_frame_fails_reads_and_fsck() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/f", b"", 1).unwrap();
+    fs.setxattr(&mut dev, "/f", POLICY_XATTR, b"lz4", 2)
+        .unwrap();
+    fs.write(&mut dev, "/f", &text(100_000, 9), 3).unwrap();
+    let e = fs.index.lookup(&mut dev, "/f").unwrap().unwrap();
+    let at = (fs.sb.data_start_block + e.extent_root) as usize * 4096 + e.stored_len as usize / 2;
+    let byte = dev.peek(at, 1)[0];
+    dev.poke(at, &[byte ^ 0x5A]);
+
+    assert_eq!(fs.read(&mut dev, "/f"), Err(HelixError::ExtentCorrupt));
+    let report = fs.fsck(&mut dev, false).unwrap();
+    assert!(report
+        .findings
+        .iter()
+        .any(|f| matches!(f.problem, morpheus_helix::fsck::Problem::Extent { .. })));
+}
+
+#[test]
+fn a_far_ranged_write_never_unpacks_the_whole_file() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/c", 1).unwrap();
+    fs.setxattr(&mut dev, "/c", POLICY_XATTR, b"lz4", 2)
+        .unwrap();
+    let data = text(120_000, 9);
+    fs.write(&mut dev, "/c/f", &data, 3).unwrap();
+    assert!(is_compressed(&fs, &mut dev, "/c/f"));
+
+    // Far past `MAX_UNPACK_SIZE` the gap is zero chunks in the frame.
+    let far = 64 * compress::MAX_UNPACK_SIZE;
+    fs.write_at(&mut dev, "/c/f", far, b"tail", 4).unwrap();
+    assert!(is_compressed(&fs, &mut dev, "/c/f"));
+    assert_eq!(fs.stat(&mut dev, "/c/f").unwrap().size, far + 4);
+    let mut buf = [0xAAu8; 8];
+    fs.read_at(&mut dev, "/c/f", far - 4, &mut buf).unwrap();
+    assert_eq!(&buf, b"\0\0\0\0tail");
+
+    // A frame cannot index more than `u32::MAX` bytes: refused, unchanged.
+    assert_eq!(
+        fs.write_at(&mut dev, "/c/f", 1 << 32, b"x", 5),
+        Err(HelixError::FileTooLarge)
+    );
+    assert_eq!(fs.stat(&mut dev, "/c/f").unwrap().size, far + 4);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
@@ -0,0 +1,181 @@
+//! Whole-file dedup: a write whose bytes already exist on disk becomes a
+//! `DedupRef` to the existing extent, and reclaim frees shared blocks only when
+//! the last referencing path or snapshot lets go. A CRC match alone must never
+//! share.
+
+mod common;
+
+use common::{pattern, MemBio};
+use morpheus_helix::types::entry_flags;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+const BLOCK: usize = 4096;
+
+fn is_dedup(fs: &HelixFs, dev: &mut MemBio, path: &str) -> bool {
+    fs.index.lookup(dev, path).unwrap().unwrap().flags & entry_flags::IS_DEDUP != 0
+}
+
+#[test]
+fn identical_files_share_blocks_until_the_last_unlink() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let baseline = fs.bitmap.allocated_count();
+    let bin = pattern(40 * BLOCK + 17, 1);
+    fs.write(&mut dev, "/bin/a", &bin, 1).unwrap();
+    let one_copy = fs.bitmap.allocated_count();
+
+    fs.write(&mut dev, "/usr/bin/b", &bin, 2).unwrap();
+    fs.write(&mut dev, "/opt/c", &bin, 3).unwrap();
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        one_copy,
+        "identical content must not allocate a second copy"
+    );
+    assert!(is_dedup(&fs, &mut dev, "/usr/bin/b"));
+    let root = fs
+        .index
+        .lookup(&mut dev, "/bin/a")
+        .unwrap()
+        .unwrap()
+        .extent_root;
+    assert_eq!(fs.dedup.ref_count(root), 3);
+    assert_eq!(fs.read(&mut dev, "/opt/c").unwrap(), bin);
+
+    fs.unlink(&mut dev, "/bin/a", 4).unwrap();
+    fs.unlink(&mut dev, "/opt/c", 5).unwrap();
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        one_copy,
+        "shared blocks freed while a path still references them"
+    );
+    assert_eq!(fs.read(&mut dev, "/usr/bin/b").unwrap(), bin);
+
+    fs.unlink(&mut dev, "/usr/bin/b", 6).unwrap();
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        baseline,
+        "the last reference must free the blocks"
+    );
+}
+
+#[test]
+fn modifying_a_shared_copy_leaves_its_siblings_alone() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let bin = pattern(12 * BLOCK, 2);
+    fs.write(&mut dev, "/a", &bin, 1).unwrap();
+    fs.write(&mut dev, "/b", &bin, 2).unwrap();
+
+    fs.write_at(&mut dev, "/b", 5 * BLOCK as u64, b"patched", 3)
+        .unwrap();
+    fs.truncate(&mut dev, "/a", 3 * BLOCK as u64, 4).unwrap();
+    fs.write(&mut dev, "/c", &bin, 5).unwrap();
+    fs.rename(&mut dev, "/c", "/a", 6).unwrap();
+
+    let mut expect_b = bin.clone();
+    expect_b[5 * BLOCK..5 * BLOCK + 7].copy_from_slice(b"patched");
+    assert_eq!(fs.read(&mut dev, "/b").unwrap(), expect_b);
+    assert_eq!(fs.read(&mut dev, "/a").unwrap(), bin);
+}
+
+#[test]
+fn crc_match_without_byte_match_is_not_shared() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let decoy = pattern(6 * BLOCK, 3);
+    let real = pattern(6 * BLOCK, 4);
+    fs.write(&mut dev, "/decoy", &decoy, 1).unwrap();
+
+    // Forge a collision: advertise the decoy's blocks under the real content's CRC.
+    let e = fs.index.lookup(&mut dev, "/decoy").unwrap().unwrap();
+    let ext = (
+        e.extent_root,
+        e.size,
+        e.flags & entry_flags::IS_EXTENT_NODE != 0,
+    );
+    fs.dedup.register(morpheus_helix::crc::crc64(&real), ext);
+
+    fs.write(&mut dev, "/real", &real, 2).unwrap();
+    assert!(
+        !is_dedup(&fs, &mut dev, "/real"),
+        "shared on a CRC match alone"
+    );
+    assert_eq!(fs.read(&mut dev, "/real").unwrap(), real);
+    assert_eq!(fs.read(&mut dev, "/decoy").unwrap(), decoy);
+}
+
+#[test]
+fn refcounts_survive_remount() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let bin = pattern(20 * BLOCK, 5);
+    fs.write(&mut dev, "/a", &bin, 1).unwrap();
+    fs.write(&mut dev, "/b", &bin, 2).unwrap();
+    fs.sync(&mut dev).unwrap();
+    let allocated = fs.bitmap.allocated_count();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.bitmap.allocated_count(), allocated);
+    assert!(is_dedup(&fs, &mut dev, "/b"), "DedupRef lost on replay");
+
+    // The replayed content CRC keeps the file a dedup candidate.
+    fs.write(&mut dev, "/c", &bin, 3).unwrap();
+    assert!(is_dedup(&fs, &mut dev, "/c"));
+    assert_eq!(fs.bitmap.allocated_count(), allocated);
+
+    fs.unlink(&mut dev, "/a", 4).unwrap();
+    fs.unlink(&mut dev, "/b", 5).unwrap();
+    // Fresh allocations must not land on the blocks /c still shares.
+    fs.write(&mut dev, "/filler", &pattern(30 * BLOCK, 6), 6)
+        .unwrap();
+    assert_eq!(fs.read(&mut dev, "/c").unwrap(), bin);
+}
+
+#[test]
+fn a_snapshotted_original_outlives_a_later_sharer_across_remount() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let bin = pattern(20 * BLOCK, 7);
+    fs.write(&mut dev, "/a", &bin, 1).unwrap();
+    let snap = fs.snapshot(&mut dev, "s", 2).unwrap();
+    // A sharer born after the snapshot, then the original gone: only the
+    // snapshot still sees `/a`'s name for these blocks.
+    fs.write(&mut dev, "/b", &bin, 3).unwrap();
+    assert!(is_dedup(&fs, &mut dev, "/b"));
+    fs.unlink(&mut dev, "/a", 4).unwrap();
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    // The recount must see the snapshot's reference, or dropping the last
+    // live one frees what the snapshot reads.
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    let allocated = fs.bitmap.allocated_count();
+    fs.unlink(&mut dev, "/b", 5).unwrap();
+    assert_eq!(fs.bitmap.allocated_count(), allocated);
+    // Fill the volume: whatever was freed gets written over.
+    let mut n = 0;
+    while fs
+        .write(&mut dev, &format!("/filler{n}"), &pattern(64 * BLOCK, n), 6)
+        .is_ok()
+    {
+        n += 1;
+    }
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    let view = HelixFs::mount_snapshot(&mut dev, 0, 512, snap).unwrap();
+    assert_eq!(view.read(&mut dev, "/a").unwrap(), bin);
+}
@@ -0,0 +1,257 @@
+//! Encryption at rest: the AEAD and KDF against their RFC vectors, and an
+//! encrypted volume that round-trips through remounts, keeps plaintext off
+//! the disk, refuses wrong passphrases and plain mounts, and turns tampering
+//! into read failures.
+
+mod common;
+
+use common::MemBio;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+use morpheus_helix::crypt::{self, aead, kdf, Crypt, KeyParams};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+const PASS: &[u8] = b"correct horse battery staple";
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn format(dev: &mut MemBio) -> Crypt {
+    let sectors = dev.sectors();
+    let params = KeyParams {
+        passphrase: PASS,
+        salt: [7u8; 16],
+        volume_key: [0x42u8; 32],
+        kdf_rounds: 16,
+    };
+    crypt::format(dev, 0, sectors, 512, "secret", [9u8; 16], &params).unwrap()
+}
+
+fn contains(hay: &[u8], needle: &[u8]) -> bool {
+    hay.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn primitives_match_rfc_vectors() {
+    assert_eq!(
+        kdf::sha256(b"abc").to_vec(),
+        hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+    );
+    let mut dk = [0u8; 32];
+    kdf::pbkdf2_sha256(b"password", b"salt", 4096, &mut dk);
+    assert_eq!(
+        dk.to_vec(),
+        hex("c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a")
+    );
+
+    // RFC 8439 §2.8.2.
+    let key: [u8; 32] = core::array::from_fn(|i| 0x80 + i as u8);
+    let nonce: [u8; 12] = hex("070000004041424344454647").try_into().unwrap();
+    let aad = hex("50515253c0c1c2c3c4c5c6c7");
+    let plain = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+    let mut buf = plain.to_vec();
+    let tag = aead::seal(&key, &nonce, &aad, &mut buf);
+    assert_eq!(
+        buf,
+        hex("d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc3ff4def08e4b7a9de576d26586cec64b6116")
+    );
+    assert_eq!(tag.to_vec(), hex("1ae10b594f09e26a7e902ecbd0600691"));
+
+    let mut bad = tag;
+    bad[0] ^= 1;
+    let sealed = buf.clone();
+    assert!(!aead::open(&key, &nonce, &aad, &mut buf, &bad));
+    assert_eq!(buf, sealed, "a failed open leaves the ciphertext alone");
+    assert!(aead::open(&key, &nonce, &aad, &mut buf, &tag));
+    assert_eq!(buf, plain);
+}
+
+#[test]
+fn an_encrypted_volume_round_trips_and_hides_its_contents() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut c = format(&mut dev);
+    let big: Vec<u8> = b"TOP-SECRET-EXTENT ".repeat(2000);
+    {
+        let mut io = c.io(&mut dev);
+        let mut fs = HelixFs::mount(&mut io, 0, 512).unwrap();
+        fs.mkdir(&mut io, "/d", 1).unwrap();
+        fs.write(&mut io, "/d/inline", b"TOP-SECRET-INLINE", 2)
+            .unwrap();
+        fs.write(&mut io, "/d/big", &big, 3).unwrap();
+        fs.checkpoint(&mut io).unwrap();
+        fs.write(&mut io, "/d/late", b"TOP-SECRET-LOGGED", 4)
+            .unwrap();
+        fs.sync(&mut io).unwrap();
+    }
+    drop(c);
+
+    let disk = dev.peek(0, DISK_SECTORS * 512);
+    for secret in [
+        &b"TOP-SECRET-INLINE"[..],
+        b"TOP-SECRET-EXTENT",
+        b"TOP-SECRET-LOGGED",
+        b"/d/inline",
+    ] {
+        assert!(!contains(&disk, secret), "{:?} on disk", secret);
+    }
+
+    let mut c = Crypt::unlock(&mut dev, 0, 512, PASS).unwrap();
+    let mut io = c.io(&mut dev);
+    let mut fs = HelixFs::mount(&mut io, 0, 512).unwrap();
+    assert_eq!(fs.read(&mut io, "/d/inline").unwrap(), b"TOP-SECRET-INLINE");
+    assert_eq!(fs.read(&mut io, "/d/big").unwrap(), big);
+    assert_eq!(fs.read(&mut io, "/d/late").unwrap(), b"TOP-SECRET-LOGGED");
+    assert!(fs.fsck(&mut io, false).unwrap().is_clean());
+}
+
+#[test]
+fn wrong_passphrases_and_plain_mounts_are_refused() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    drop(format(&mut dev));
+
+    assert!(Crypt::is_encrypted(&mut dev, 0, 512));
+    assert!(morpheus_helix::format::is_helix(&mut dev, 0, 512));
+    assert_eq!(
+        HelixFs::mount(&mut dev, 0, 512).err(),
+        Some(HelixError::Encrypted)
+    );
+    assert_eq!(
+        Crypt::unlock(&mut dev, 0, 512, b"hunter2").err(),
+        Some(HelixError::BadPassphrase)
+    );
+
+    let mut plain = MemBio::new(DISK_SECTORS);
+    let sectors = plain.sectors();
+    HelixFs::format_and_mount(&mut plain, 0, sectors, 512, "p", [0u8; 16]).unwrap();
+    assert!(!Crypt::is_encrypted(&mut plain, 0, 512));
+    assert_eq!(
+        Crypt::unlock(&mut plain, 0, 512, PASS).err(),
+        Some(HelixError::NotSupported)
+    );
+}
+
+#[test]
+fn tampered_blocks_fail_to_read() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut c = format(&mut dev);
+    let data = vec![0xA5u8; 64 * 1024];
+    let other_data = vec![0x5Au8; 64 * 1024];
+    let (at, other) = {
+        let mut io = c.io(&mut dev);
+        let mut fs = HelixFs::mount(&mut io, 0, 512).unwrap();
+        fs.write(&mut io, "/f", &data, 1).unwrap();
+        fs.write(&mut io, "/g", &other_data, 2).unwrap();
+        fs.sync(&mut io).unwrap();
+        let f = fs.index.lookup(&mut io, "/f").unwrap().unwrap().extent_root;
+        let g = fs.index.lookup(&mut io, "/g").unwrap().unwrap().extent_root;
+        let base = fs.sb.data_start_block;
+        ((base + f) as usize * 4096, (base + g) as usize * 4096)
+    };
+
+    // One flipped bit fails authentication rather than returning garbage.
+    let byte = dev.peek(at + 100, 1)[0];
+    dev.poke(at + 100, &[byte ^ 1]);
+    let fs = HelixFs::mount(&mut c.io(&mut dev), 0, 512).unwrap();
+    assert_eq!(
+        fs.read(&mut c.io(&mut dev), "/f"),
+        Err(HelixError::IoReadFailed)
+    );
+
+    // So does moving a valid ciphertext block to another address.
+    dev.poke(at + 100, &[byte]);
+    let moved = dev.peek(other, 4096);
+    dev.poke(at, &moved);
+    let mut io = c.io(&mut dev);
+    assert_eq!(fs.read(&mut io, "/f"), Err(HelixError::IoReadFailed));
+    assert_eq!(fs.read(&mut io, "/g").unwrap(), other_data);
+}
+
+#[test]
+fn a_torn_rewrite_still_reads_the_previous_contents() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut c = format(&mut dev);
+    let old = [1u8; 4096];
+    let base = {
+        let mut io = c.io(&mut dev);
+        let fs = HelixFs::mount(&mut io, 0, 512).unwrap();
+        fs.sb.data_start_block as usize * 4096
+    };
+    let lba = Lba(base as u64 / 512 + 80);
+    c.io(&mut dev).write_blocks(lba, &old).unwrap();
+    let on_disk = dev.peek(lba.0 as usize * 512, 4096);
+
+    // The new tag lands, then the crash eats the data write.
+    c.io(&mut dev).write_blocks(lba, &[2u8; 4096]).unwrap();
+    dev.poke(lba.0 as usize * 512, &on_disk);
+    drop(c);
+
+    let mut c = Crypt::unlock(&mut dev, 0, 512, PASS).unwrap();
+    let mut buf = [0u8; 4096];
+    c.io(&mut dev).read_blocks(lba, &mut buf).unwrap();
+    assert_eq!(buf, old);
+    // Unaligned reads go through the same path.
+    let mut part = [0u8; 512];
+    c.io(&mut dev)
+        .read_blocks(Lba(lba.0 + 3), &mut part)
+        .unwrap();
+    assert_eq!(part, [1u8; 512]);
+}
+
+/// 32768 sectors -> 4096 blocks, room for runs spanning many tag blocks.
+const BIG_DISK_SECTORS: usize = 32768;
+/// Tag entries per tag block, as `crypt::TAGS_PER_BLOCK`.
+const TPB: u64 = crypt::TAGS_PER_BLOCK;
+
+fn pattern(block: u64) -> Vec<u8> {
+    (0..4096).map(|i| (block as usize * 7 + i) as u8).collect()
+}
+
+#[test]
+fn a_write_spanning_more_tag_blocks_than_the_cache_holds_round_trips() {
+    let mut dev = MemBio::new(BIG_DISK_SECTORS);
+    let mut c = format(&mut dev);
+    // 2000 blocks need 20 tag blocks; the cache holds 16.
+    let first = 1024u64;
+    let data: Vec<u8> = (first..first + 2000).flat_map(pattern).collect();
+    c.io(&mut dev).write_blocks(Lba(first * 8), &data).unwrap();
+    drop(c);
+
+    let mut c = Crypt::unlock(&mut dev, 0, 512, PASS).unwrap();
+    let mut back = vec![0u8; data.len()];
+    c.io(&mut dev)
+        .read_blocks(Lba(first * 8), &mut back)
+        .unwrap();
+    assert!(back == data);
+}
+
+#[test]
+fn a_write_across_a_cold_tag_block_keeps_the_cached_one() {
+    let mut dev = MemBio::new(BIG_DISK_SECTORS);
+    let mut c = format(&mut dev);
+    // Fill the cache with tag blocks {10, 12..=26}, 10 the oldest.
+    let mut buf = [0u8; 4096];
+    for tb in core::iter::once(10).chain(12..=26) {
+        c.io(&mut dev)
+            .read_blocks(Lba(tb * TPB * 8), &mut buf)
+            .unwrap();
+    }
+    // Two blocks straddling tag blocks 10 and 11: loading 11 must not evict 10.
+    let first = 11 * TPB - 1;
+    let data: Vec<u8> = (first..first + 2).flat_map(pattern).collect();
+    c.io(&mut dev).write_blocks(Lba(first * 8), &data).unwrap();
+    drop(c);
+
+    let mut c = Crypt::unlock(&mut dev, 0, 512, PASS).unwrap();
+    let mut back = vec![0u8; data.len()];
+    c.io(&mut dev)
+        .read_blocks(Lba(first * 8), &mut back)
+        .unwrap();
+    assert_eq!(back, data);
+}
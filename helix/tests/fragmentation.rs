@@ -103,7 +103,7 @@ fn fragmented_file_survives_remount_with_blocks_reserved() {
 
 /// /frag's physical blocks: its extent-node block plus every run it lists.
 fn frag_blocks(dev: &mut MemBio, fs: &HelixFs) -> Vec<u64> {
-    let node_block = fs.index.lookup("/frag").unwrap().extent_root;
+    let node_block = fs.index.lookup(dev, "/frag").unwrap().unwrap().extent_root;
     let mut blocks = vec![node_block];
     let extents = morpheus_helix::extent::read_extent_node(
         dev,
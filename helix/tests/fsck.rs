@@ -0,0 +1,244 @@
+//! fsck: a healthy volume checks clean online and offline; a corrupt
+//! checkpoint entry, a bad log record, allocation-map drift, a dangling entry
+//! and a cross-linked extent are found, and repaired into a volume that
+//! mounts and checks clean.
+
+mod common;
+
+use common::{fresh, pattern, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::fsck::{self, Owner, Problem};
+use morpheus_helix::index::btree::NamespaceIndex;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+fn problems(report: &fsck::FsckReport) -> Vec<Problem> {
+    report.findings.iter().map(|f| f.problem.clone()).collect()
+}
+
+#[test]
+fn a_churned_volume_checks_clean() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/a/big", &pattern(70000, 1), 1).unwrap();
+    fs.write(&mut dev, "/a/copy", &pattern(70000, 1), 2)
+        .unwrap();
+    fs.write(&mut dev, "/b/small", b"tiny", 3).unwrap();
+    fs.setxattr(&mut dev, "/b/small", "user.k", b"v", 4)
+        .unwrap();
+    fs.snapshot(&mut dev, "s1", 5).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+    fs.write(&mut dev, "/a/big", &pattern(70000, 2), 6).unwrap();
+    fs.unlink(&mut dev, "/a/copy", 7).unwrap();
+    fs.mkdir(&mut dev, "/empty", 8).unwrap();
+    fs.snapshot(&mut dev, "s2", 9).unwrap();
+    fs.write(&mut dev, "/b/small", b"changed", 10).unwrap();
+
+    let report = fs.fsck(&mut dev, false).unwrap();
+    assert!(report.is_clean(), "{:?}", problems(&report));
+    assert_eq!(report.entries, 5);
+    assert!(report.log_records > 0);
+    assert_eq!(report.blocks_claimed, fs.bitmap.allocated_count());
+
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+    let report = fsck::check(&mut dev, 0, 512, false).unwrap();
+    assert!(report.is_clean(), "{:?}", problems(&report));
+}
+
+#[test]
+fn a_corrupt_checkpoint_entry_is_salvaged() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    for i in 0..12u8 {
+        fs.write(&mut dev, &format!("/d/f{i}"), &pattern(9000, i), i as u64)
+            .unwrap();
+    }
+    fs.checkpoint(&mut dev).unwrap();
+    let root = fs.sb.index_root_block;
+    let region = (fs.sb.data_start_block + root) as usize * 4096;
+    drop(fs);
+
+    // A flipped byte in the path of the first leaf's first entry.
+    let byte = dev.peek(region + 32 + 9, 1)[0];
+    dev.poke(region + 32 + 9, &[byte ^ 0x20]);
+    assert_eq!(
+        HelixFs::mount(&mut dev, 0, 512).err(),
+        Some(HelixError::IndexCrcMismatch)
+    );
+
+    let report = fsck::check(&mut dev, 0, 512, false).unwrap();
+    assert!(problems(&report).contains(&Problem::IndexEntry {
+        block: root,
+        slot: 0
+    }));
+    assert_eq!(report.unrepaired().count(), report.findings.len());
+    assert!(
+        HelixFs::mount(&mut dev, 0, 512).is_err(),
+        "check alone changes nothing"
+    );
+
+    let report = fsck::check(&mut dev, 0, 512, true).unwrap();
+    assert_eq!(report.unrepaired().count(), 0);
+    let fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    let survivors = (0..12u8)
+        .filter(|&i| {
+            fs.read(&mut dev, &format!("/d/f{i}"))
+                .is_ok_and(|data| data == pattern(9000, i))
+        })
+        .count();
+    assert!(survivors >= 11, "only the corrupt entry is lost");
+    drop(fs);
+    let report = fsck::check(&mut dev, 0, 512, false).unwrap();
+    assert!(report.is_clean(), "{:?}", problems(&report));
+}
+
+#[test]
+fn a_corrupt_log_record_is_reported_and_cut_off() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/first", b"one", 1).unwrap();
+    fs.sync(&mut dev).unwrap();
+    let seg0 = fs.sb.log_start_block as usize * 4096;
+    drop(fs);
+
+    // First payload byte of the first record (64-byte segment and record
+    // headers).
+    let byte = dev.peek(seg0 + 128, 1)[0];
+    dev.poke(seg0 + 128, &[byte ^ 1]);
+    let report = fsck::check(&mut dev, 0, 512, true).unwrap();
+    assert!(problems(&report)
+        .iter()
+        .any(|p| matches!(p, Problem::LogRecord { offset: 64, .. })));
+    assert_eq!(report.unrepaired().count(), 0);
+
+    let report = fsck::check(&mut dev, 0, 512, false).unwrap();
+    assert!(report.is_clean(), "{:?}", problems(&report));
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    fs.write(&mut dev, "/after", b"two", 2).unwrap();
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+    let fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(
+        fs.read(&mut dev, "/after").unwrap(),
+        b"two",
+        "records after the cut replay"
+    );
+}
+
+#[test]
+fn allocation_map_drift_is_repaired_online() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    let data = pattern(40000, 7);
+    fs.write(&mut dev, "/f", &data, 1).unwrap();
+    let allocated = fs.bitmap.allocated_count();
+    let root = fs
+        .index
+        .lookup(&mut dev, "/f")
+        .unwrap()
+        .unwrap()
+        .extent_root;
+    let leak = (0..fs.bitmap.total_blocks())
+        .rev()
+        .find(|&b| !fs.bitmap.is_allocated(b))
+        .unwrap();
+    fs.bitmap.mark_block_used(leak);
+    fs.bitmap.free_block(root + 2).unwrap();
+
+    let report = fs.fsck(&mut dev, false).unwrap();
+    assert_eq!(
+        problems(&report),
+        [
+            Problem::Unallocated {
+                start: root + 2,
+                count: 1
+            },
+            Problem::Orphaned {
+                start: leak,
+                count: 1
+            },
+        ]
+    );
+
+    let report = fs.fsck(&mut dev, true).unwrap();
+    assert_eq!(report.unrepaired().count(), 0);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        allocated + fs.sb.index_region_blocks as u64,
+        "the file plus the checkpoint repair wrote"
+    );
+    assert_eq!(fs.read(&mut dev, "/f").unwrap(), data);
+
+    fs.begin_tx(&mut dev, 2).unwrap();
+    assert_eq!(fs.fsck(&mut dev, false).err(), Some(HelixError::TxConflict));
+}
+
+#[test]
+fn dangling_and_cross_linked_entries_are_repaired() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    let victim = pattern(30000, 3);
+    fs.write(&mut dev, "/victim", &victim, 1).unwrap();
+    let root = fs
+        .index
+        .lookup(&mut dev, "/victim")
+        .unwrap()
+        .unwrap()
+        .extent_root;
+    fs.snapshot(&mut dev, "s", 2).unwrap();
+
+    // Entries no operation would produce: a child without its directory, and
+    // a file claiming the tail of another's extent.
+    let lsn = fs.index.lookup(&mut dev, "/victim").unwrap().unwrap().lsn;
+    fs.index.upsert(NamespaceIndex::make_file_entry(
+        "/ghost/child",
+        lsn,
+        5,
+        3,
+        Some(b"child"),
+        0,
+        0,
+    ));
+    fs.index.upsert(NamespaceIndex::make_file_entry(
+        "/x-thief",
+        lsn,
+        8192,
+        3,
+        None,
+        root + 3,
+        0,
+    ));
+
+    let report = fs.fsck(&mut dev, false).unwrap();
+    assert_eq!(
+        problems(&report),
+        [
+            Problem::Dangling {
+                path: "/ghost/child".into()
+            },
+            Problem::DoubleAllocated {
+                block: root + 3,
+                owner: Owner::Live("/x-thief".into()),
+            },
+        ]
+    );
+
+    let report = fs.fsck(&mut dev, true).unwrap();
+    assert_eq!(report.unrepaired().count(), 0);
+    assert!(fs.stat(&mut dev, "/ghost").is_ok());
+    assert_eq!(fs.read(&mut dev, "/ghost/child").unwrap(), b"child");
+    assert_eq!(fs.read(&mut dev, "/x-thief"), Err(HelixError::NotFound));
+    assert_eq!(fs.read(&mut dev, "/victim").unwrap(), victim);
+
+    drop(fs);
+    let fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.read(&mut dev, "/ghost/child").unwrap(), b"child");
+    assert_eq!(fs.list_snapshots().len(), 1);
+    drop(fs);
+    let report = fsck::check(&mut dev, 0, 512, false).unwrap();
+    assert!(report.is_clean(), "{:?}", problems(&report));
+}
@@ -0,0 +1,180 @@
+//! Links: a symbolic link stores its target, inline or in a block, and
+//! survives replay and checkpoints; hard-linked names share content and
+//! attributes, the last unlink reclaims them, and fsck repairs a bad count.
+//! Rollback restores both; a send stream carries each name as its own file.
+
+mod common;
+
+use common::{fresh, pattern, MemBio};
+use morpheus_foundation::flags::mode::{S_IFLNK, S_IFMT, S_IFREG};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::fsck::Problem;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+fn names(fs: &HelixFs, dev: &mut MemBio, dir: &str) -> Vec<String> {
+    let mut out: Vec<String> = fs
+        .readdir(dev, dir)
+        .unwrap()
+        .iter()
+        .map(|e| String::from(std::str::from_utf8(&e.name[..e.name_len as usize]).unwrap()))
+        .collect();
+    out.sort();
+    out
+}
+
+#[test]
+fn symlinks_store_their_target() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    let long = format!("/{}", "deep/".repeat(40));
+    fs.symlink(&mut dev, "../etc/conf", "/a/short", 1).unwrap();
+    fs.symlink(&mut dev, &long, "/a/long", 2).unwrap();
+
+    assert_eq!(
+        fs.symlink(&mut dev, "x", "/a/short", 3),
+        Err(HelixError::AlreadyExists)
+    );
+    fs.write(&mut dev, "/a/file", b"data", 3).unwrap();
+    assert_eq!(
+        fs.readlink(&mut dev, "/a/file"),
+        Err(HelixError::NotASymlink)
+    );
+    let st = fs.stat(&mut dev, "/a/long").unwrap();
+    assert_eq!(st.mode & S_IFMT, S_IFLNK);
+    assert_eq!(st.size, long.len() as u64);
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.readlink(&mut dev, "/a/short").unwrap(), "../etc/conf");
+    assert_eq!(fs.readlink(&mut dev, "/a/long").unwrap(), long);
+    fs.checkpoint(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.readlink(&mut dev, "/a/long").unwrap(), long);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    let used = fs.bitmap.allocated_count();
+    fs.unlink(&mut dev, "/a/long", 4).unwrap();
+    assert_eq!(fs.bitmap.allocated_count(), used - 1);
+}
+
+#[test]
+fn hard_links_share_content_until_the_last_unlink() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    let before = fs.bitmap.allocated_count();
+    fs.write(&mut dev, "/a", &pattern(20000, 1), 1).unwrap();
+    fs.link(&mut dev, "/a", "/d/b", 2).unwrap();
+    fs.link(&mut dev, "/d/b", "/c", 3).unwrap();
+    assert_eq!(
+        fs.link(&mut dev, "/a", "/c", 4),
+        Err(HelixError::AlreadyExists)
+    );
+    assert_eq!(
+        fs.link(&mut dev, "/d", "/e", 4),
+        Err(HelixError::IsADirectory)
+    );
+
+    fs.write(&mut dev, "/c", &pattern(30000, 2), 4).unwrap();
+    fs.setxattr(&mut dev, "/d/b", "user.k", b"v", 5).unwrap();
+    for name in ["/a", "/d/b", "/c"] {
+        assert_eq!(fs.read(&mut dev, name).unwrap(), pattern(30000, 2));
+        assert_eq!(fs.getxattr(&mut dev, name, "user.k").unwrap(), b"v");
+        let st = fs.stat(&mut dev, name).unwrap();
+        assert_eq!(st.mode & S_IFMT, S_IFREG);
+        assert_eq!((st.nlink, st.size), (3, 30000));
+    }
+    assert_eq!(names(&fs, &mut dev, "/"), ["a", "c", "d"]);
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    fs.unlink(&mut dev, "/a", 6).unwrap();
+    fs.rename(&mut dev, "/d/b", "/b", 7).unwrap();
+    assert_eq!(fs.stat(&mut dev, "/c").unwrap().nlink, 2);
+    assert_eq!(fs.read(&mut dev, "/b").unwrap(), pattern(30000, 2));
+    fs.checkpoint(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.read(&mut dev, "/c").unwrap(), pattern(30000, 2));
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.unlink(&mut dev, "/b", 8).unwrap();
+    assert_eq!(fs.stat(&mut dev, "/c").unwrap().nlink, 1);
+    fs.unlink(&mut dev, "/c", 9).unwrap();
+    assert_eq!(fs.read(&mut dev, "/c"), Err(HelixError::NotFound));
+    fs.checkpoint(&mut dev).unwrap();
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        before + fs.sb.index_region_blocks as u64,
+        "only the checkpoint region is left"
+    );
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn a_wrong_link_count_is_repaired() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/a", b"shared", 1).unwrap();
+    fs.link(&mut dev, "/a", "/b", 2).unwrap();
+    let record = morpheus_helix::ops::link::record_path(
+        fs.index.lookup(&mut dev, "/a").unwrap().unwrap().link_id,
+    );
+    let mut entry = fs.index.lookup(&mut dev, &record).unwrap().unwrap();
+    entry.link_count = 5;
+    fs.index.upsert(entry);
+
+    let report = fs.fsck(&mut dev, false).unwrap();
+    assert_eq!(
+        report
+            .findings
+            .iter()
+            .map(|f| f.problem.clone())
+            .collect::<Vec<_>>(),
+        [Problem::LinkCount {
+            path: record,
+            names: 2,
+            recorded: 5
+        }]
+    );
+    fs.fsck(&mut dev, true).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.stat(&mut dev, "/b").unwrap().nlink, 2);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn links_survive_send_and_rollback() {
+    let mut src = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut src);
+    fs.write(&mut src, "/f", &pattern(9000, 3), 1).unwrap();
+    fs.link(&mut src, "/f", "/g", 2).unwrap();
+    fs.symlink(&mut src, "/f", "/s", 3).unwrap();
+    let snap = fs.snapshot(&mut src, "s1", 4).unwrap();
+    let stream = fs.send(&mut src, 0, snap).unwrap();
+
+    fs.write(&mut src, "/g", b"changed", 5).unwrap();
+    fs.unlink(&mut src, "/s", 6).unwrap();
+    fs.symlink(&mut src, "/elsewhere", "/s", 7).unwrap();
+    fs.rollback_to(&mut src, "s1", 8).unwrap();
+    for name in ["/f", "/g"] {
+        assert_eq!(fs.read(&mut src, name).unwrap(), pattern(9000, 3));
+    }
+    assert_eq!(fs.readlink(&mut src, "/s").unwrap(), "/f");
+    assert!(fs.fsck(&mut src, false).unwrap().is_clean());
+
+    // Each name arrives as a file of its own.
+    let mut dst = MemBio::new(DISK_SECTORS);
+    let mut copy = fresh(&mut dst);
+    copy.receive(&mut dst, &stream, 9).unwrap();
+    assert_eq!(copy.readlink(&mut dst, "/s").unwrap(), "/f");
+    assert_eq!(copy.read(&mut dst, "/g").unwrap(), pattern(9000, 3));
+    assert_eq!(copy.stat(&mut dst, "/g").unwrap().nlink, 1);
+}
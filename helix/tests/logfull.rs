@@ -64,7 +64,27 @@ fn dir_ops_self_heal_on_log_full() {
 
     let fs2 = HelixFs::mount(&mut dev, 0, 512).unwrap();
     assert!(
-        fs2.stat("/keep").is_ok(),
+        fs2.stat(&mut dev, "/keep").is_ok(),
         "dir lost across checkpoint + remount"
     );
 }
+
+#[test]
+fn one_segment_ring_starts_over() {
+    // 16384 sectors -> 8 MiB volume with a single log segment: no segment to
+    // advance into, so the checkpoint must restart the only one.
+    let mut dev = MemBio::new(16384);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+    assert_eq!(fs.log.segment_count(), 1);
+
+    for i in 0..30_000u32 {
+        fs.write(&mut dev, "/a", &i.to_le_bytes(), 100 + i as u64)
+            .unwrap_or_else(|e| panic!("write {i} bricked a one-segment log: {e:?}"));
+    }
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    let fs2 = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs2.read(&mut dev, "/a").unwrap(), 29_999u32.to_le_bytes());
+}
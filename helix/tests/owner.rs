@@ -0,0 +1,98 @@
+//! Ownership: entries start out root's with default permission bits;
+//! `chmod`/`chown` survive replay, checkpoints and rewrites, follow hard
+//! links to their record, and travel through rollback and send streams.
+
+mod common;
+
+use common::{fresh, MemBio};
+use morpheus_foundation::flags::mode::{S_IFDIR, S_IFREG};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::ops::owner::Owner;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+fn owner(uid: u32, gid: u32, mode: u32) -> Owner {
+    Owner { uid, gid, mode }
+}
+
+#[test]
+fn owners_survive_replay_and_rewrites() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/bin/init", b"elf", 1).unwrap();
+    let st = fs.stat(&mut dev, "/bin/init").unwrap();
+    assert_eq!((st.mode, st.uid, st.gid), (S_IFREG | 0o644, 0, 0));
+    assert_eq!(fs.stat(&mut dev, "/bin").unwrap().mode, S_IFDIR | 0o755);
+
+    fs.write(&mut dev, "/home/u/notes", b"mine", 2).unwrap();
+    fs.chown(&mut dev, "/home/u", 1000, 100, 3).unwrap();
+    fs.chown(&mut dev, "/home/u/notes", 1000, 100, 3).unwrap();
+    fs.chmod(&mut dev, "/home/u/notes", 0o100600, 4).unwrap();
+    assert_eq!(
+        fs.chmod(&mut dev, "/nope", 0o600, 5),
+        Err(HelixError::NotFound)
+    );
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(
+        fs.owner(&mut dev, "/home/u/notes").unwrap(),
+        owner(1000, 100, 0o600)
+    );
+    assert_eq!(
+        fs.owner(&mut dev, "/home/u").unwrap(),
+        owner(1000, 100, 0o755)
+    );
+    fs.write(&mut dev, "/home/u/notes", b"rewritten", 6)
+        .unwrap();
+    fs.truncate(&mut dev, "/home/u/notes", 3, 7).unwrap();
+    fs.rename(&mut dev, "/home/u", "/home/v", 8).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    let st = fs.stat(&mut dev, "/home/v/notes").unwrap();
+    assert_eq!((st.mode, st.uid, st.gid), (S_IFREG | 0o600, 1000, 100));
+    assert_eq!(fs.read(&mut dev, "/home/v/notes").unwrap(), b"rew");
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn hard_links_share_their_owner() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/a", b"shared", 1).unwrap();
+    fs.chown(&mut dev, "/a", 7, 7, 2).unwrap();
+    fs.link(&mut dev, "/a", "/b", 3).unwrap();
+    fs.chmod(&mut dev, "/b", 0o640, 4).unwrap();
+    for name in ["/a", "/b"] {
+        assert_eq!(fs.owner(&mut dev, name).unwrap(), owner(7, 7, 0o640));
+    }
+}
+
+#[test]
+fn owners_survive_rollback_and_send() {
+    let mut src = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut src);
+    fs.write(&mut src, "/f", b"data", 1).unwrap();
+    fs.mkdir(&mut src, "/d", 1).unwrap();
+    fs.chown(&mut src, "/f", 5, 6, 2).unwrap();
+    fs.chmod(&mut src, "/d", 0o700, 2).unwrap();
+    let snap = fs.snapshot(&mut src, "s1", 3).unwrap();
+    let stream = fs.send(&mut src, 0, snap).unwrap();
+
+    fs.chown(&mut src, "/f", 0, 0, 4).unwrap();
+    fs.chmod(&mut src, "/d", 0o777, 4).unwrap();
+    fs.rollback_to(&mut src, "s1", 5).unwrap();
+    assert_eq!(fs.owner(&mut src, "/f").unwrap(), owner(5, 6, 0o644));
+    assert_eq!(fs.owner(&mut src, "/d").unwrap(), owner(0, 0, 0o700));
+
+    let mut dst = MemBio::new(DISK_SECTORS);
+    let mut copy = fresh(&mut dst);
+    copy.receive(&mut dst, &stream, 6).unwrap();
+    assert_eq!(copy.owner(&mut dst, "/f").unwrap(), owner(5, 6, 0o644));
+    assert_eq!(copy.owner(&mut dst, "/d").unwrap(), owner(0, 0, 0o700));
+}
@@ -0,0 +1,249 @@
+//! Ranged I/O must scale with the range, not the file: `write_at` rewrites only
+//! the blocks it touches (copy-on-write, one delta record), `read_at` reads only
+//! the blocks it covers, and `truncate` grows into a hole instead of staging the
+//! whole file on the heap.
+
+mod common;
+
+use common::{pattern, MemBio};
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+const BLOCK: usize = 4096;
+
+fn read_all(fs: &HelixFs, dev: &mut MemBio, path: &str) -> Vec<u8> {
+    let size = fs.stat(dev, path).unwrap().size as usize;
+    let mut buf = vec![0u8; size];
+    assert_eq!(fs.read_at(dev, path, 0, &mut buf).unwrap(), size);
+    buf
+}
+
+#[test]
+fn write_at_rewrites_only_touched_blocks() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let mut expect = pattern(64 * BLOCK, 7);
+    fs.write(&mut dev, "/db", &expect, 1).unwrap();
+    let before = fs.bitmap.allocated_count();
+
+    // Straddles the block 1/2 boundary: two blocks rewritten, two freed.
+    let patch = [0xEEu8; 100];
+    let off = 2 * BLOCK - 40;
+    assert_eq!(
+        fs.write_at(&mut dev, "/db", off as u64, &patch, 2).unwrap(),
+        100
+    );
+    expect[off..off + 100].copy_from_slice(&patch);
+
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        before + 1,
+        "a ranged write must cost its blocks plus one extent node, not a full copy"
+    );
+    assert_eq!(
+        read_all(&fs, &mut dev, "/db"),
+        expect,
+        "patched content wrong"
+    );
+    assert_eq!(
+        fs.read(&mut dev, "/db").unwrap(),
+        expect,
+        "whole-file read disagrees"
+    );
+
+    let st = fs.stat(&mut dev, "/db").unwrap();
+    assert_eq!(st.size, expect.len() as u64);
+    assert_eq!(st.version_count, 2, "a ranged write is a new version");
+}
+
+#[test]
+fn read_at_slices_and_stops_at_eof() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let data = pattern(3 * BLOCK + 123, 3);
+    fs.write(&mut dev, "/f", &data, 1).unwrap();
+
+    let mut buf = vec![0u8; BLOCK + 10];
+    let n = fs
+        .read_at(&mut dev, "/f", BLOCK as u64 - 5, &mut buf)
+        .unwrap();
+    assert_eq!(n, buf.len());
+    assert_eq!(&buf[..], &data[BLOCK - 5..2 * BLOCK + 5]);
+
+    let n = fs
+        .read_at(&mut dev, "/f", data.len() as u64 - 20, &mut buf)
+        .unwrap();
+    assert_eq!(n, 20, "a read crossing EOF must be short");
+    assert_eq!(&buf[..20], &data[data.len() - 20..]);
+
+    assert_eq!(
+        fs.read_at(&mut dev, "/f", data.len() as u64, &mut buf)
+            .unwrap(),
+        0,
+        "a read at EOF returns 0"
+    );
+
+    fs.write(&mut dev, "/small", b"inline bytes", 2).unwrap();
+    let mut small = [0u8; 5];
+    assert_eq!(fs.read_at(&mut dev, "/small", 7, &mut small).unwrap(), 5);
+    assert_eq!(&small, b"bytes");
+}
+
+#[test]
+fn write_at_past_eof_leaves_a_zero_gap() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    // Inline file promoted to extents by a far write: the prefix must survive.
+    fs.write(&mut dev, "/log", b"head", 1).unwrap();
+    let off = 10 * BLOCK as u64 + 17;
+    fs.write_at(&mut dev, "/log", off, b"tail", 2).unwrap();
+
+    let got = read_all(&fs, &mut dev, "/log");
+    assert_eq!(got.len() as u64, off + 4);
+    assert_eq!(&got[..4], b"head");
+    assert!(
+        got[4..off as usize].iter().all(|&b| b == 0),
+        "gap past the old EOF must read as zeros"
+    );
+    assert_eq!(&got[off as usize..], b"tail");
+
+    // A fresh path is created by write_at, parents included.
+    fs.write_at(&mut dev, "/var/new", 200, b"x", 3).unwrap();
+    let got = read_all(&fs, &mut dev, "/var/new");
+    assert_eq!(got.len(), 201);
+    assert_eq!(got[200], b'x');
+    assert!(fs.stat(&mut dev, "/var").unwrap().is_dir());
+}
+
+#[test]
+fn truncate_grows_past_old_cap_without_staging() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/big", &pattern(5000, 1), 1).unwrap();
+    let before = fs.bitmap.allocated_count();
+
+    // 64 MiB on an 8 MiB volume: only possible as a hole.
+    let huge = 64u64 << 20;
+    fs.truncate(&mut dev, "/big", huge, 2).unwrap();
+    assert_eq!(fs.stat(&mut dev, "/big").unwrap().size, huge);
+    assert!(
+        fs.bitmap.allocated_count() <= before + 2,
+        "sparse growth must not allocate the hole"
+    );
+
+    let mut buf = [0xFFu8; 64];
+    assert_eq!(
+        fs.read_at(&mut dev, "/big", huge - 64, &mut buf).unwrap(),
+        64
+    );
+    assert!(buf.iter().all(|&b| b == 0), "hole must read as zeros");
+
+    let mut head = vec![0u8; 5000];
+    fs.read_at(&mut dev, "/big", 0, &mut head).unwrap();
+    assert_eq!(head, pattern(5000, 1), "grown file lost its original bytes");
+}
+
+#[test]
+fn shrink_then_grow_reads_zero_tail() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let data = pattern(4 * BLOCK, 9);
+    fs.write(&mut dev, "/f", &data, 1).unwrap();
+    let before = fs.bitmap.allocated_count();
+
+    fs.truncate(&mut dev, "/f", BLOCK as u64 + 300, 2).unwrap();
+    assert!(
+        fs.bitmap.allocated_count() < before,
+        "shrinking must free the blocks past the new EOF"
+    );
+    fs.truncate(&mut dev, "/f", 3 * BLOCK as u64, 3).unwrap();
+
+    let got = read_all(&fs, &mut dev, "/f");
+    assert_eq!(&got[..BLOCK + 300], &data[..BLOCK + 300]);
+    assert!(
+        got[BLOCK + 300..].iter().all(|&b| b == 0),
+        "bytes cut by a shrink must not reappear on regrowth"
+    );
+
+    // Shrinking into inline range stages the few remaining bytes.
+    fs.truncate(&mut dev, "/f", 50, 4).unwrap();
+    assert_eq!(fs.read(&mut dev, "/f").unwrap(), &data[..50]);
+}
+
+#[test]
+fn ranged_writes_survive_remount() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let mut expect = pattern(20 * BLOCK, 5);
+    fs.write(&mut dev, "/db", &expect, 1).unwrap();
+    for (i, off) in [0usize, 3 * BLOCK + 1, 11 * BLOCK, 19 * BLOCK + 4000]
+        .into_iter()
+        .enumerate()
+    {
+        let patch = pattern(700, 100 + i as u8);
+        fs.write_at(&mut dev, "/db", off as u64, &patch, 2 + i as u64)
+            .unwrap();
+        if off + patch.len() > expect.len() {
+            expect.resize(off + patch.len(), 0);
+        }
+        expect[off..off + patch.len()].copy_from_slice(&patch);
+    }
+    fs.sync(&mut dev).unwrap();
+    let allocated = fs.bitmap.allocated_count();
+    drop(fs);
+
+    let fs2 = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(
+        read_all(&fs2, &mut dev, "/db"),
+        expect,
+        "ranged writes lost across remount"
+    );
+    assert_eq!(
+        fs2.bitmap.allocated_count(),
+        allocated,
+        "bitmap rebuild disagrees with the live allocation"
+    );
+}
+
+#[test]
+fn snapshot_pinned_version_is_not_shared() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let original = pattern(8 * BLOCK, 2);
+    fs.write(&mut dev, "/f", &original, 1).unwrap();
+    let snap = fs.snapshot(&mut dev, "s", 2).unwrap();
+
+    fs.write_at(&mut dev, "/f", 10, b"one", 3).unwrap();
+    fs.write_at(&mut dev, "/f", 5 * BLOCK as u64, b"two", 4)
+        .unwrap();
+
+    let at_snap = morpheus_helix::ops::read::read_file_at_lsn(
+        &mut dev,
+        &fs.log,
+        fs.partition_lba_start,
+        fs.sb.data_start_block,
+        fs.device_block_size,
+        "/f",
+        snap,
+    )
+    .unwrap();
+    assert_eq!(
+        at_snap, original,
+        "a version pinned by a snapshot must keep its blocks"
+    );
+}
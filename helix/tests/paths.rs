@@ -39,7 +39,7 @@ fn write_over_directory_rejected() {
         "a write over a directory must fail"
     );
     assert!(
-        fs.stat("/d").unwrap().is_dir(),
+        fs.stat(&mut dev, "/d").unwrap().is_dir(),
         "directory was clobbered/shadowed by a file write"
     );
     assert_eq!(
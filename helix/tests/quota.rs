@@ -0,0 +1,193 @@
+//! Directory quotas: the figures follow writes, unlinks and renames, limits
+//! refuse with `QuotaExceeded`, and both survive a checkpoint, a remount and
+//! an aborted transaction.
+
+mod common;
+
+use common::{fresh, MemBio};
+use morpheus_helix::ops::quota::{Limit, Usage};
+use morpheus_helix::{HelixError, HelixFs};
+
+const DISK_SECTORS: usize = 8192;
+const QUOTA: &str = "helix.quota";
+
+fn used(fs: &mut HelixFs, dev: &mut MemBio, dir: &str) -> Usage {
+    fs.quota(dev, dir).unwrap().expect("no quota").1
+}
+
+fn usage(bytes: u64, entries: u64) -> Usage {
+    Usage { bytes, entries }
+}
+
+#[test]
+fn parses_the_attribute() {
+    assert_eq!(
+        Limit::parse(b"bytes:4096,entries:8"),
+        Some(Limit {
+            bytes: 4096,
+            entries: 8
+        })
+    );
+    assert_eq!(
+        Limit::parse(b"entries:3"),
+        Some(Limit {
+            bytes: 0,
+            entries: 3
+        })
+    );
+    assert_eq!(Limit::parse(b"bytes"), None);
+    assert_eq!(Limit::parse(b"blocks:1"), None);
+}
+
+#[test]
+fn counts_what_was_there_and_what_follows() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/q", 1).unwrap();
+    fs.write(&mut dev, "/q/a", &[1u8; 100], 2).unwrap();
+    fs.write(&mut dev, "/outside", &[1u8; 100], 3).unwrap();
+    fs.setxattr(&mut dev, "/q", QUOTA, b"bytes:10000", 4)
+        .unwrap();
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(100, 1));
+
+    fs.mkdir(&mut dev, "/q/d", 5).unwrap();
+    fs.write(&mut dev, "/q/d/b", &[2u8; 5000], 6).unwrap();
+    fs.write(&mut dev, "/q/a", &[1u8; 40], 7).unwrap();
+    fs.write_at(&mut dev, "/q/d/b", 6000, &[3u8; 10], 8)
+        .unwrap();
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(40 + 6010, 3));
+
+    fs.truncate(&mut dev, "/q/d/b", 10, 9).unwrap();
+    fs.unlink(&mut dev, "/q/a", 10).unwrap();
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(10, 2));
+
+    fs.removexattr(&mut dev, "/q", QUOTA, 11).unwrap();
+    assert!(fs.quota(&mut dev, "/q").unwrap().is_none());
+}
+
+#[test]
+fn limits_refuse_growth() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/q", 1).unwrap();
+    fs.setxattr(&mut dev, "/q", QUOTA, b"bytes:8192,entries:3", 2)
+        .unwrap();
+
+    fs.write(&mut dev, "/q/a", &[1u8; 8000], 3).unwrap();
+    assert_eq!(
+        fs.write_at(&mut dev, "/q/a", 8000, &[1u8; 200], 4),
+        Err(HelixError::QuotaExceeded)
+    );
+    assert_eq!(
+        fs.truncate(&mut dev, "/q/a", 9000, 5),
+        Err(HelixError::QuotaExceeded)
+    );
+    // Shrinking is always allowed, and frees room.
+    fs.write(&mut dev, "/q/a", &[1u8; 100], 6).unwrap();
+
+    fs.mkdir(&mut dev, "/q/d", 7).unwrap();
+    fs.symlink(&mut dev, "/q/a", "/q/l", 8).unwrap();
+    assert_eq!(
+        fs.mkdir(&mut dev, "/q/e", 9),
+        Err(HelixError::QuotaExceeded)
+    );
+    assert_eq!(
+        fs.write(&mut dev, "/q/d/f", b"x", 10),
+        Err(HelixError::QuotaExceeded)
+    );
+    // Nothing was created by the refused calls.
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(100 + 4, 3));
+    assert!(fs.stat(&mut dev, "/q/d/f").is_err());
+
+    // Outside the directory nothing is limited.
+    fs.write(&mut dev, "/free", &[1u8; 20000], 11).unwrap();
+}
+
+#[test]
+fn renames_move_the_charge() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/q", 1).unwrap();
+    fs.mkdir(&mut dev, "/r", 2).unwrap();
+    fs.setxattr(&mut dev, "/q", QUOTA, b"bytes:1000", 3)
+        .unwrap();
+    fs.write(&mut dev, "/r/big", &[1u8; 2000], 4).unwrap();
+    fs.write(&mut dev, "/r/small", &[1u8; 500], 5).unwrap();
+
+    assert_eq!(
+        fs.rename(&mut dev, "/r/big", "/q/big", 6),
+        Err(HelixError::QuotaExceeded)
+    );
+    fs.rename(&mut dev, "/r/small", "/q/small", 7).unwrap();
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(500, 1));
+
+    // Within the directory the charge stays put.
+    fs.rename(&mut dev, "/q/small", "/q/moved", 8).unwrap();
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(500, 1));
+
+    fs.rename(&mut dev, "/q/moved", "/r/back", 9).unwrap();
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(0, 0));
+
+    // A directory that moves takes its quota and figures along.
+    fs.write(&mut dev, "/q/a", &[1u8; 300], 10).unwrap();
+    fs.rename(&mut dev, "/q", "/r/q", 11).unwrap();
+    assert!(fs.quota(&mut dev, "/q").unwrap().is_none());
+    assert_eq!(used(&mut fs, &mut dev, "/r/q"), usage(300, 1));
+}
+
+#[test]
+fn survives_checkpoint_and_remount() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/q", 1).unwrap();
+    fs.setxattr(&mut dev, "/q", QUOTA, b"entries:10", 2)
+        .unwrap();
+    fs.write(&mut dev, "/q/a", &[1u8; 100], 3).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+
+    // Changes the ring holds past the checkpoint, one replacing a
+    // checkpointed entry.
+    fs.write(&mut dev, "/q/a", &[1u8; 700], 4).unwrap();
+    fs.write(&mut dev, "/q/b", &[1u8; 50], 5).unwrap();
+    fs.mkdir(&mut dev, "/p", 6).unwrap();
+    fs.setxattr(&mut dev, "/p", QUOTA, b"bytes:64", 7).unwrap();
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(750, 2));
+    assert_eq!(fs.quota(&mut dev, "/p").unwrap().unwrap().0.bytes, 64);
+    assert_eq!(
+        fs.write(&mut dev, "/p/x", &[1u8; 65], 8),
+        Err(HelixError::QuotaExceeded)
+    );
+
+    fs.checkpoint(&mut dev).unwrap();
+    drop(fs);
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(750, 2));
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn an_abort_restores_the_figures() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/q", 1).unwrap();
+    fs.setxattr(&mut dev, "/q", QUOTA, b"bytes:1000", 2)
+        .unwrap();
+    fs.write(&mut dev, "/q/a", &[1u8; 100], 3).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+
+    fs.begin_tx(&mut dev, 4).unwrap();
+    fs.write(&mut dev, "/q/a", &[1u8; 900], 5).unwrap();
+    fs.write(&mut dev, "/q/b", &[1u8; 50], 6).unwrap();
+    fs.removexattr(&mut dev, "/q", QUOTA, 7).unwrap();
+    fs.abort(&mut dev, 8).unwrap();
+
+    assert_eq!(used(&mut fs, &mut dev, "/q"), usage(100, 1));
+    assert_eq!(
+        fs.write(&mut dev, "/q/c", &[1u8; 901], 9),
+        Err(HelixError::QuotaExceeded)
+    );
+}
@@ -33,8 +33,8 @@ fn overwrite_frees_prior_extent_blocks() {
     assert_eq!(fs.read(&mut dev, "/a").unwrap(), vec![20u8; 2 * BLOCK]);
 }
 
-fn blocks_of(fs: &HelixFs, path: &str, n: u64) -> Vec<u64> {
-    let root = fs.index.lookup(path).unwrap().extent_root;
+fn blocks_of(fs: &HelixFs, dev: &mut MemBio, path: &str, n: u64) -> Vec<u64> {
+    let root = fs.index.lookup(dev, path).unwrap().unwrap().extent_root;
     (0..n).map(|i| root + i).collect()
 }
 
@@ -45,7 +45,7 @@ fn snapshot_pins_overwritten_blocks() {
     let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
 
     fs.write(&mut dev, "/a", &[1u8; 2 * BLOCK], 1).unwrap();
-    let pinned = blocks_of(&fs, "/a", 2);
+    let pinned = blocks_of(&fs, &mut dev, "/a", 2);
     fs.snapshot(&mut dev, "s", 5).unwrap();
     fs.write(&mut dev, "/a", &[2u8; 2 * BLOCK], 10).unwrap();
 
@@ -69,7 +69,7 @@ fn snapshot_pin_survives_remount() {
     drop(fs);
 
     let mut fs2 = HelixFs::mount(&mut dev, 0, 512).unwrap();
-    let pinned = blocks_of(&fs2, "/a", 2);
+    let pinned = blocks_of(&fs2, &mut dev, "/a", 2);
     fs2.write(&mut dev, "/a", &[2u8; 2 * BLOCK], 10).unwrap();
 
     for b in pinned {
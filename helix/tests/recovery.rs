@@ -19,7 +19,7 @@ fn disk_with_twice_written_file() -> MemBio {
     fs.sync(&mut dev).unwrap();
 
     // Sanity: the live engine tracks the history correctly before any remount.
-    let live = fs.stat("/f").unwrap();
+    let live = fs.stat(&mut dev, "/f").unwrap();
     assert_eq!(live.version_count, 2, "live version_count");
     assert_eq!(live.created_ns, 100, "live created_ns");
 
@@ -31,7 +31,7 @@ fn version_count_survives_remount() {
     let mut dev = disk_with_twice_written_file();
 
     let fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
-    let st = fs.stat("/f").unwrap();
+    let st = fs.stat(&mut dev, "/f").unwrap();
 
     assert_eq!(
         st.version_count, 2,
@@ -44,7 +44,7 @@ fn created_ns_survives_remount() {
     let mut dev = disk_with_twice_written_file();
 
     let fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
-    let st = fs.stat("/f").unwrap();
+    let st = fs.stat(&mut dev, "/f").unwrap();
 
     assert_eq!(
         st.created_ns, 100,
@@ -27,7 +27,7 @@ fn rename_to_overlong_path_is_rejected() {
         "overlong rename must fail"
     );
     assert!(
-        fs.stat("/a").is_ok(),
+        fs.stat(&mut dev, "/a").is_ok(),
         "source must survive a rejected rename"
     );
 }
@@ -76,7 +76,10 @@ fn rename_directory_moves_children() {
         b"world",
         "nested child not moved"
     );
-    assert!(fs.stat("/d").is_err(), "old directory must be gone");
+    assert!(
+        fs.stat(&mut dev, "/d").is_err(),
+        "old directory must be gone"
+    );
     assert!(
         fs.read(&mut dev, "/d/f").is_err(),
         "old child path must be gone"
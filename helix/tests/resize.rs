@@ -0,0 +1,161 @@
+//! Online resize: growing hands out the new blocks and survives a remount;
+//! shrinking moves whatever lives in the cut tail first, keeps shared extents
+//! shared, and refuses to cut off what a snapshot still sees.
+
+mod common;
+
+use common::{remount_clean, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::HelixFs;
+
+const BLOCK: usize = 4096;
+/// 2200 sectors -> 275 FS blocks, 16 of them data (see `fragmentation.rs`).
+const SMALL_SECTORS: u64 = 2200;
+/// Room for 16 more data blocks past the small volume.
+const BIG_SECTORS: usize = 2200 + 16 * 8;
+const SMALL_BLOCKS: u64 = SMALL_SECTORS / 8;
+const BIG_BLOCKS: u64 = BIG_SECTORS as u64 / 8;
+
+fn block_of(dev: &mut MemBio, fs: &HelixFs, path: &str) -> u64 {
+    fs.index.lookup(dev, path).unwrap().unwrap().extent_root
+}
+
+#[test]
+fn grow_adds_blocks_that_survive_a_remount() {
+    let mut dev = MemBio::new(BIG_SECTORS);
+    let mut fs =
+        HelixFs::format_and_mount(&mut dev, 0, SMALL_SECTORS, 512, "t", [0u8; 16]).unwrap();
+    let a = vec![0xA1u8; 16 * BLOCK];
+    fs.write(&mut dev, "/a", &a, 1).unwrap();
+    assert_eq!(
+        fs.write(&mut dev, "/b", &vec![0xB2; BLOCK], 2),
+        Err(HelixError::NoSpace)
+    );
+
+    fs.resize(&mut dev, BIG_BLOCKS, 3).unwrap();
+    assert_eq!(fs.bitmap.total_blocks(), 32);
+    let b = vec![0xB2u8; 8 * BLOCK];
+    fs.write(&mut dev, "/b", &b, 4).unwrap();
+    fs.sync(&mut dev).unwrap();
+
+    drop(fs);
+    let fs = remount_clean(&mut dev);
+    assert_eq!(
+        (fs.sb.total_blocks, fs.sb.data_block_count),
+        (BIG_BLOCKS, 32)
+    );
+    assert_eq!(fs.read(&mut dev, "/a").unwrap(), a);
+    assert_eq!(fs.read(&mut dev, "/b").unwrap(), b);
+}
+
+#[test]
+fn grow_past_the_device_is_refused() {
+    let mut dev = MemBio::new(BIG_SECTORS);
+    let mut fs =
+        HelixFs::format_and_mount(&mut dev, 0, SMALL_SECTORS, 512, "t", [0u8; 16]).unwrap();
+    assert_eq!(
+        fs.resize(&mut dev, BIG_BLOCKS + 1, 1),
+        Err(HelixError::IoReadFailed)
+    );
+    // So far past it that its last sector has no address.
+    assert_eq!(fs.resize(&mut dev, u64::MAX, 2), Err(HelixError::NoSpace));
+    assert_eq!(fs.sb.total_blocks, SMALL_BLOCKS);
+    assert_eq!(fs.bitmap.total_blocks(), 16);
+}
+
+/// 32 data blocks; `/f0`..`/f23` take one block each and a checkpoint puts
+/// the index region past them, then the first sixteen are deleted so the
+/// survivors all sit in the upper half.
+fn high_files() -> (MemBio, HelixFs) {
+    let mut dev = MemBio::new(BIG_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+    for i in 0..24u8 {
+        fs.write(&mut dev, &format!("/f{i}"), &vec![i; BLOCK], 1)
+            .unwrap();
+    }
+    fs.checkpoint(&mut dev).unwrap();
+    assert!(fs.sb.index_root_block >= 24);
+    for i in 0..16u8 {
+        fs.unlink(&mut dev, &format!("/f{i}"), 2).unwrap();
+    }
+    (dev, fs)
+}
+
+#[test]
+fn shrink_moves_the_tail_first() {
+    let (mut dev, mut fs) = high_files();
+    assert!(block_of(&mut dev, &fs, "/f23") >= 16);
+    // Same content as `/f20`: deduplicated onto its extent.
+    fs.write(&mut dev, "/twin", &vec![20u8; BLOCK], 3).unwrap();
+    assert_eq!(
+        block_of(&mut dev, &fs, "/twin"),
+        block_of(&mut dev, &fs, "/f20")
+    );
+    fs.setxattr(&mut dev, "/f23", "user.k", b"v", 4).unwrap();
+    let before = fs.stat(&mut dev, "/f23").unwrap();
+
+    fs.resize(&mut dev, SMALL_BLOCKS, 5).unwrap();
+    assert_eq!(fs.bitmap.total_blocks(), 16);
+    assert!(fs.sb.index_root_block + fs.sb.index_region_blocks as u64 <= 16);
+    for i in 16..24u8 {
+        let path = format!("/f{i}");
+        assert!(block_of(&mut dev, &fs, &path) < 16);
+        assert_eq!(fs.read(&mut dev, &path).unwrap(), vec![i; BLOCK]);
+    }
+    let twin = block_of(&mut dev, &fs, "/twin");
+    assert_eq!(twin, block_of(&mut dev, &fs, "/f20"));
+    assert!(fs.dedup.is_shared(twin));
+    assert_eq!(fs.getxattr(&mut dev, "/f23", "user.k").unwrap(), b"v");
+    let after = fs.stat(&mut dev, "/f23").unwrap();
+    assert_eq!(
+        (after.modified_ns, after.version_count),
+        (before.modified_ns, before.version_count)
+    );
+
+    drop(fs);
+    let fs = remount_clean(&mut dev);
+    assert_eq!(
+        (fs.sb.total_blocks, fs.sb.data_block_count),
+        (SMALL_BLOCKS, 16)
+    );
+    assert_eq!(fs.read(&mut dev, "/f23").unwrap(), vec![23u8; BLOCK]);
+    assert_eq!(fs.read(&mut dev, "/twin").unwrap(), vec![20u8; BLOCK]);
+}
+
+#[test]
+fn shrink_refuses_what_a_snapshot_still_sees() {
+    let (mut dev, mut fs) = high_files();
+    fs.snapshot(&mut dev, "s", 3).unwrap();
+    assert_eq!(fs.resize(&mut dev, SMALL_BLOCKS, 4), Err(HelixError::InUse));
+    assert_eq!(fs.sb.total_blocks, BIG_BLOCKS);
+    assert_eq!(fs.read(&mut dev, "/f23").unwrap(), vec![23u8; BLOCK]);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+
+    fs.delete_snapshot(&mut dev, "s").unwrap();
+    fs.resize(&mut dev, SMALL_BLOCKS, 5).unwrap();
+    drop(fs);
+    let fs = remount_clean(&mut dev);
+    assert_eq!(fs.read(&mut dev, "/f23").unwrap(), vec![23u8; BLOCK]);
+}
+
+#[test]
+fn shrink_below_what_is_used_is_refused() {
+    let (mut dev, mut fs) = high_files();
+    let data_start = fs.sb.data_start_block;
+    assert_eq!(
+        fs.resize(&mut dev, data_start + 8, 3),
+        Err(HelixError::NoSpace)
+    );
+    assert_eq!(
+        fs.resize(&mut dev, data_start, 3),
+        Err(HelixError::FormatTooSmall)
+    );
+    assert_eq!(fs.sb.total_blocks, BIG_BLOCKS);
+    for i in 16..24u8 {
+        assert_eq!(
+            fs.read(&mut dev, &format!("/f{i}")).unwrap(),
+            vec![i; BLOCK]
+        );
+    }
+}
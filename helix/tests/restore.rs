@@ -0,0 +1,192 @@
+//! Restore by LSN: a retained version is pointed at again without copying
+//! and outlives pruning of the history; deleted files and whole directories
+//! come back; a snapshot-held version is copied; a version nothing holds is
+//! `NotFound`.
+
+mod common;
+
+use common::{content, fresh, remount_clean, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::ops::retain::{Retention, POLICY_XATTR};
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+fn lsn_of(fs: &HelixFs, dev: &mut MemBio, path: &str) -> u64 {
+    fs.stat(dev, path).unwrap().lsn
+}
+
+#[test]
+fn a_retained_version_comes_back_without_a_copy() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/etc", 1).unwrap();
+    fs.setxattr(&mut dev, "/etc", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    fs.write(&mut dev, "/etc/cfg", &content(1), 3).unwrap();
+    let first = lsn_of(&fs, &mut dev, "/etc/cfg");
+    fs.write(&mut dev, "/etc/cfg", &content(2), 4).unwrap();
+    let second = lsn_of(&fs, &mut dev, "/etc/cfg");
+
+    let free = fs.bitmap.free_count();
+    fs.restore(&mut dev, "/etc/cfg", first, 5).unwrap();
+    assert_eq!(fs.bitmap.free_count(), free);
+    assert_eq!(fs.read(&mut dev, "/etc/cfg").unwrap(), content(1));
+    // The replaced version is kept in turn: the restore can be undone.
+    assert_eq!(
+        fs.read_version(&mut dev, "/etc/cfg", second).unwrap(),
+        content(2)
+    );
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.sync(&mut dev).unwrap();
+
+    // Dropping the history leaves the shared blocks to the file.
+    drop(fs);
+    let mut fs = remount_clean(&mut dev);
+    assert_eq!(
+        fs.prune_versions(&mut dev, "/etc", Retention::None, 6),
+        Ok(2)
+    );
+    assert_eq!(fs.read(&mut dev, "/etc/cfg").unwrap(), content(1));
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.write(&mut dev, "/etc/cfg", &content(3), 7).unwrap();
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn a_deleted_file_comes_back() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/d", 1).unwrap();
+    fs.setxattr(&mut dev, "/d", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    fs.write(&mut dev, "/d/gone", &content(7), 3).unwrap();
+    let lsn = lsn_of(&fs, &mut dev, "/d/gone");
+    fs.unlink(&mut dev, "/d/gone", 4).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+
+    fs.restore(&mut dev, "/d/gone", lsn, 5).unwrap();
+    assert_eq!(fs.read(&mut dev, "/d/gone").unwrap(), content(7));
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.sync(&mut dev).unwrap();
+
+    drop(fs);
+    let fs = remount_clean(&mut dev);
+    assert_eq!(fs.read(&mut dev, "/d/gone").unwrap(), content(7));
+}
+
+#[test]
+fn a_directory_comes_back_whole() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/d", 1).unwrap();
+    fs.setxattr(&mut dev, "/d", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    fs.write(&mut dev, "/d/a", &content(1), 3).unwrap();
+    fs.write(&mut dev, "/d/b", b"small", 3).unwrap();
+    let then = lsn_of(&fs, &mut dev, "/d/b");
+
+    fs.write(&mut dev, "/d/a", &content(2), 4).unwrap();
+    fs.unlink(&mut dev, "/d/b", 4).unwrap();
+    fs.write(&mut dev, "/d/new", &content(3), 4).unwrap();
+    fs.mkdir(&mut dev, "/d/sub", 4).unwrap();
+    fs.write(&mut dev, "/d/sub/x", b"x", 4).unwrap();
+    fs.symlink(&mut dev, "a", "/d/link", 4).unwrap();
+
+    fs.restore(&mut dev, "/d", then, 5).unwrap();
+    assert_eq!(fs.read(&mut dev, "/d/a").unwrap(), content(1));
+    assert_eq!(fs.read(&mut dev, "/d/b").unwrap(), b"small");
+    for later in ["/d/new", "/d/sub/x", "/d/sub", "/d/link"] {
+        assert_eq!(fs.stat(&mut dev, later).err(), Some(HelixError::NotFound));
+    }
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.sync(&mut dev).unwrap();
+
+    drop(fs);
+    let fs = remount_clean(&mut dev);
+    let mut names: Vec<String> = fs
+        .readdir(&mut dev, "/d")
+        .unwrap()
+        .iter()
+        .map(|e| String::from(std::str::from_utf8(&e.name[..e.name_len as usize]).unwrap()))
+        .collect();
+    names.sort();
+    assert_eq!(names, ["a", "b"]);
+}
+
+#[test]
+fn a_deleted_directory_comes_back() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/home", 1).unwrap();
+    fs.setxattr(&mut dev, "/home", POLICY_XATTR, b"all", 1)
+        .unwrap();
+    fs.mkdir(&mut dev, "/home/proj", 2).unwrap();
+    fs.write(&mut dev, "/home/proj/src/main", &content(4), 3)
+        .unwrap();
+    fs.write(&mut dev, "/home/proj/notes", b"todo", 3).unwrap();
+    let then = lsn_of(&fs, &mut dev, "/home/proj/notes");
+    fs.unlink(&mut dev, "/home/proj/src/main", 4).unwrap();
+    fs.unlink(&mut dev, "/home/proj/src", 4).unwrap();
+    fs.unlink(&mut dev, "/home/proj/notes", 4).unwrap();
+    fs.unlink(&mut dev, "/home/proj", 4).unwrap();
+
+    fs.restore(&mut dev, "/home/proj", then, 5).unwrap();
+    assert_eq!(
+        fs.read(&mut dev, "/home/proj/src/main").unwrap(),
+        content(4)
+    );
+    assert_eq!(fs.read(&mut dev, "/home/proj/notes").unwrap(), b"todo");
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn a_snapshot_version_is_copied() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/f", &content(1), 1).unwrap();
+    let first = lsn_of(&fs, &mut dev, "/f");
+    fs.snapshot(&mut dev, "s", 2).unwrap();
+    fs.write(&mut dev, "/f", &content(2), 3).unwrap();
+
+    fs.restore(&mut dev, "/f", first, 4).unwrap();
+    assert_eq!(fs.read(&mut dev, "/f").unwrap(), content(1));
+    fs.delete_snapshot(&mut dev, "s").unwrap();
+    assert_eq!(fs.read(&mut dev, "/f").unwrap(), content(1));
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn a_version_nothing_holds_is_not_found() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/f", &content(1), 1).unwrap();
+    let first = lsn_of(&fs, &mut dev, "/f");
+    fs.write(&mut dev, "/f", &content(2), 2).unwrap();
+
+    assert_eq!(
+        fs.restore(&mut dev, "/f", first, 3),
+        Err(HelixError::NotFound)
+    );
+    assert_eq!(
+        fs.restore(&mut dev, "/f", first - 1, 3),
+        Err(HelixError::NotFound)
+    );
+    assert_eq!(
+        fs.restore(&mut dev, "/never", first, 3),
+        Err(HelixError::NotFound)
+    );
+    // The live version is a no-op.
+    let now = lsn_of(&fs, &mut dev, "/f");
+    fs.restore(&mut dev, "/f", now, 3).unwrap();
+    assert_eq!(lsn_of(&fs, &mut dev, "/f"), now);
+
+    fs.begin_tx(&mut dev, 4).unwrap();
+    assert_eq!(
+        fs.restore(&mut dev, "/f", now, 5),
+        Err(HelixError::TxConflict)
+    );
+    fs.abort(&mut dev, 5).unwrap();
+    assert_eq!(fs.read(&mut dev, "/f").unwrap(), content(2));
+}
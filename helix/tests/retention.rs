@@ -0,0 +1,262 @@
+//! Version retention: a `helix.retention` policy keeps prior versions past
+//! checkpoints and remounts, readable by LSN; checkpoints enforce `last:N`
+//! and `within:S` and free what they drop; `prune_versions` drops at once;
+//! unlink keeps the last version; an aborted transaction keeps nothing;
+//! range writes share the blocks they leave alone with the kept version.
+
+mod common;
+
+use common::{content, fresh, remount_clean, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::ops::retain::{Retention, POLICY_XATTR};
+use morpheus_helix::types::LogOp;
+use morpheus_helix::HelixFs;
+
+const BLOCK: usize = 4096;
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+/// 32768 sectors -> 16 MiB volume.
+const BIG_DISK_SECTORS: usize = 32768;
+const SEC: u64 = 1_000_000_000;
+
+/// LSNs of the content versions `versions` lists, once the ring is on disk.
+fn writes(fs: &mut HelixFs, dev: &mut MemBio, path: &str) -> Vec<u64> {
+    fs.sync(dev).unwrap();
+    fs.versions(dev, path)
+        .unwrap()
+        .into_iter()
+        .filter(|v| v.2 == LogOp::Write)
+        .map(|v| v.0)
+        .collect()
+}
+
+#[test]
+fn policies_parse() {
+    assert_eq!(Retention::parse(b"all"), Some(Retention::All));
+    assert_eq!(Retention::parse(b"none"), Some(Retention::None));
+    assert_eq!(Retention::parse(b"last:3"), Some(Retention::Last(3)));
+    assert_eq!(
+        Retention::parse(b"within:60"),
+        Some(Retention::Within(60 * SEC))
+    );
+    for bad in [&b"last"[..], b"last:-1", b"keep:2", b"within:x", b""] {
+        assert_eq!(Retention::parse(bad), None);
+    }
+}
+
+#[test]
+fn keep_all_survives_checkpoint_and_remount() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/etc", 1).unwrap();
+    fs.setxattr(&mut dev, "/etc", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    for (i, b) in [1u8, 2, 3].into_iter().enumerate() {
+        fs.write(&mut dev, "/etc/cfg", &content(b), 3 + i as u64)
+            .unwrap();
+    }
+    let lsns = writes(&mut fs, &mut dev, "/etc/cfg");
+    assert_eq!(lsns.len(), 3);
+    fs.checkpoint(&mut dev).unwrap();
+    fs.sync(&mut dev).unwrap();
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+
+    drop(fs);
+    let mut fs = remount_clean(&mut dev);
+    assert_eq!(writes(&mut fs, &mut dev, "/etc/cfg"), lsns);
+    for (lsn, b) in lsns.into_iter().zip([1u8, 2, 3]) {
+        assert_eq!(
+            fs.read_version(&mut dev, "/etc/cfg", lsn).unwrap(),
+            content(b)
+        );
+    }
+    assert_eq!(fs.read(&mut dev, "/etc/cfg").unwrap(), content(3));
+}
+
+#[test]
+fn last_n_is_enforced_at_checkpoint() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/app.log", &content(0), 1).unwrap();
+    fs.setxattr(&mut dev, "/app.log", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    for b in 1..=4u8 {
+        fs.write(&mut dev, "/app.log", &content(b), 2 + b as u64)
+            .unwrap();
+    }
+    fs.checkpoint(&mut dev).unwrap();
+    fs.setxattr(&mut dev, "/app.log", POLICY_XATTR, b"last:1", 7)
+        .unwrap();
+    let free = fs.bitmap.free_count();
+    fs.checkpoint(&mut dev).unwrap();
+    // Three of the four 2-block versions go.
+    assert_eq!(fs.bitmap.free_count(), free + 6);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.sync(&mut dev).unwrap();
+
+    drop(fs);
+    let mut fs = remount_clean(&mut dev);
+    let lsns = writes(&mut fs, &mut dev, "/app.log");
+    assert_eq!(lsns.len(), 2);
+    assert_eq!(
+        fs.read_version(&mut dev, "/app.log", lsns[0]).unwrap(),
+        content(3)
+    );
+    assert_eq!(
+        fs.prune_versions(&mut dev, "/app.log", Retention::All, 9),
+        Ok(0)
+    );
+}
+
+#[test]
+fn within_measures_from_the_replacing_write() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/var", SEC).unwrap();
+    fs.setxattr(&mut dev, "/var", POLICY_XATTR, b"within:10", SEC)
+        .unwrap();
+    fs.write(&mut dev, "/var/f", &content(1), SEC).unwrap();
+    fs.write(&mut dev, "/var/f", &content(2), 2 * SEC).unwrap();
+    fs.write(&mut dev, "/var/f", &content(3), 30 * SEC).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+
+    // Version 1 was replaced at 2 s, 28 s ago; version 2 only just.
+    let lsns = writes(&mut fs, &mut dev, "/var/f");
+    assert_eq!(lsns.len(), 2);
+    assert_eq!(
+        fs.read_version(&mut dev, "/var/f", lsns[0]).unwrap(),
+        content(2)
+    );
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn prune_drops_at_once_and_for_good() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/d", 1).unwrap();
+    fs.setxattr(&mut dev, "/d", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    for b in 1..=3u8 {
+        fs.write(&mut dev, "/d/a", &content(b), 2 + b as u64)
+            .unwrap();
+        fs.write(&mut dev, "/d/b", &content(b + 10), 2 + b as u64)
+            .unwrap();
+    }
+    let free = fs.bitmap.free_count();
+    assert_eq!(
+        fs.prune_versions(&mut dev, "/d/a", Retention::Last(1), 9),
+        Ok(1)
+    );
+    assert_eq!(fs.prune_versions(&mut dev, "/d", Retention::None, 9), Ok(3));
+    assert!(fs.bitmap.free_count() >= free + 8);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+
+    // The ring still holds the records; the history does not come back.
+    drop(fs);
+    let mut fs = remount_clean(&mut dev);
+    assert_eq!(fs.prune_versions(&mut dev, "/", Retention::None, 9), Ok(0));
+    assert_eq!(writes(&mut fs, &mut dev, "/d/a").len(), 1);
+    assert_eq!(fs.read(&mut dev, "/d/b").unwrap(), content(13));
+}
+
+#[test]
+fn unlink_keeps_the_last_version() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.mkdir(&mut dev, "/d", 1).unwrap();
+    fs.setxattr(&mut dev, "/d", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    fs.write(&mut dev, "/d/gone", &content(7), 3).unwrap();
+    fs.setxattr(&mut dev, "/d/gone", "user.k", b"v", 4).unwrap();
+    let lsn = fs.stat(&mut dev, "/d/gone").unwrap().lsn;
+    fs.unlink(&mut dev, "/d/gone", 5).unwrap();
+    assert_eq!(fs.read(&mut dev, "/d/gone"), Err(HelixError::NotFound));
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.checkpoint(&mut dev).unwrap();
+    fs.sync(&mut dev).unwrap();
+
+    drop(fs);
+    let mut fs = remount_clean(&mut dev);
+    assert!(writes(&mut fs, &mut dev, "/d/gone").contains(&lsn));
+    assert_eq!(
+        fs.read_version(&mut dev, "/d/gone", lsn).unwrap(),
+        content(7)
+    );
+    assert_eq!(fs.prune_versions(&mut dev, "/d", Retention::None, 6), Ok(1));
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+}
+
+#[test]
+fn aborted_transaction_keeps_nothing() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/f", &content(1), 1).unwrap();
+    fs.setxattr(&mut dev, "/f", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    fs.begin_tx(&mut dev, 3).unwrap();
+    fs.write(&mut dev, "/f", &content(2), 4).unwrap();
+    assert_eq!(
+        fs.prune_versions(&mut dev, "/f", Retention::None, 5),
+        Err(HelixError::TxConflict)
+    );
+    fs.abort(&mut dev, 5).unwrap();
+    assert_eq!(fs.read(&mut dev, "/f").unwrap(), content(1));
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.sync(&mut dev).unwrap();
+
+    drop(fs);
+    let mut fs = remount_clean(&mut dev);
+    assert_eq!(fs.prune_versions(&mut dev, "/f", Retention::None, 6), Ok(0));
+}
+
+#[test]
+fn small_writes_to_a_large_file_share_the_untouched_blocks() {
+    let mut dev = MemBio::new(BIG_DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    let blocks = 256u64;
+    let original: Vec<u8> = (0..blocks as usize * BLOCK)
+        .map(|i| (i / BLOCK) as u8)
+        .collect();
+    fs.write(&mut dev, "/big", &original, 1).unwrap();
+    fs.setxattr(&mut dev, "/big", POLICY_XATTR, b"all", 2)
+        .unwrap();
+    let free = fs.bitmap.free_count();
+
+    // 200 versions, each a 64-byte patch to a different block; copying the
+    // file each time would take 200 times its size.
+    let mut model = original.clone();
+    for i in 0..200u64 {
+        let off = (i * 37 % blocks) * BLOCK as u64 + 100;
+        let patch = [i as u8 ^ 0x5a; 64];
+        fs.write_at(&mut dev, "/big", off, &patch, 3 + i).unwrap();
+        model[off as usize..off as usize + 64].copy_from_slice(&patch);
+    }
+    assert!(free - fs.bitmap.free_count() < 4 * blocks);
+    assert_eq!(fs.read(&mut dev, "/big").unwrap(), model);
+    let lsns = writes(&mut fs, &mut dev, "/big");
+    assert_eq!(lsns.len(), 201);
+    assert_eq!(
+        fs.read_version(&mut dev, "/big", lsns[0]).unwrap(),
+        original
+    );
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    fs.checkpoint(&mut dev).unwrap();
+    fs.sync(&mut dev).unwrap();
+
+    // Dropping the history after a remount frees only what the live file
+    // no longer holds.
+    drop(fs);
+    let mut fs = remount_clean(&mut dev);
+    assert_eq!(
+        fs.prune_versions(&mut dev, "/big", Retention::None, 300),
+        Ok(200)
+    );
+    assert_eq!(fs.read(&mut dev, "/big").unwrap(), model);
+    assert!(fs.fsck(&mut dev, false).unwrap().is_clean());
+    assert!(fs.bitmap.free_count() + blocks + 8 >= free);
+
+    drop(fs);
+    let fs = remount_clean(&mut dev);
+    assert_eq!(fs.read(&mut dev, "/big").unwrap(), model);
+}
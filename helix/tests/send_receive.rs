@@ -0,0 +1,146 @@
+//! Send streams: a full and then an incremental stream replicate a tree onto
+//! another volume; damaged streams and a mismatched base change nothing.
+
+mod common;
+
+use common::{fresh, pattern, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::index::btree;
+use morpheus_helix::types::entry_flags;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+/// Every live path with its content (None for directories), mtime and
+/// attributes, sorted.
+#[allow(clippy::type_complexity)]
+fn tree(fs: &HelixFs, dev: &mut MemBio) -> Vec<(String, Option<Vec<u8>>, u64, Vec<String>)> {
+    let mut out: Vec<_> = fs
+        .index
+        .entries(dev)
+        .unwrap()
+        .into_iter()
+        .map(|e| {
+            let path = String::from(btree::path_str(&e.path));
+            let is_dir = e.flags & entry_flags::IS_DIR != 0;
+            let data = (!is_dir).then(|| fs.read(dev, &path).unwrap());
+            let mtime = if is_dir { 0 } else { e.modified_ns };
+            let attrs = fs
+                .listxattr(dev, &path)
+                .unwrap()
+                .into_iter()
+                .map(|k| {
+                    let v = fs.getxattr(dev, &path, &k).unwrap();
+                    format!("{k}={v:?}")
+                })
+                .collect();
+            (path, data, mtime, attrs)
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+#[test]
+fn full_then_incremental_replicates_the_tree() {
+    let mut src = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut src);
+    fs.write(&mut src, "/bin/init", &pattern(60000, 1), 10)
+        .unwrap();
+    fs.write(&mut src, "/bin/sh", &pattern(9000, 2), 11)
+        .unwrap();
+    fs.write(&mut src, "/bin/tiny", b"#!", 12).unwrap();
+    fs.write(&mut src, "/etc/conf", b"v1", 13).unwrap();
+    fs.setxattr(&mut src, "/etc/conf", "user.k", b"a", 14)
+        .unwrap();
+    let s1 = fs.snapshot(&mut src, "s1", 15).unwrap();
+    fs.checkpoint(&mut src).unwrap();
+
+    let full = fs.send(&mut src, 0, s1).unwrap();
+    let mut dst = MemBio::new(DISK_SECTORS);
+    let mut replica = fresh(&mut dst);
+    let header = replica.receive(&mut dst, &full, 100).unwrap();
+    assert_eq!((header.from_lsn, header.to_lsn), (0, s1));
+    let view = HelixFs::mount_snapshot(&mut src, 0, 512, s1).unwrap();
+    assert_eq!(tree(&replica, &mut dst), tree(&view, &mut src));
+
+    // An update: rewrite, remove, add, a new directory, attribute changes.
+    fs.write(&mut src, "/bin/init", &pattern(60000, 3), 20)
+        .unwrap();
+    fs.unlink(&mut src, "/bin/tiny", 21).unwrap();
+    fs.write(&mut src, "/bin/new", &pattern(5000, 4), 22)
+        .unwrap();
+    fs.mkdir(&mut src, "/opt", 23).unwrap();
+    fs.setxattr(&mut src, "/etc/conf", "user.k", b"b", 24)
+        .unwrap();
+    fs.setxattr(&mut src, "/opt", "user.d", b"dir", 25).unwrap();
+    let s2 = fs.snapshot(&mut src, "s2", 26).unwrap();
+
+    let delta = fs.send(&mut src, s1, s2).unwrap();
+    assert!(
+        delta.len() < full.len(),
+        "unchanged files must not travel again"
+    );
+    replica.receive(&mut dst, &delta, 200).unwrap();
+    let view = HelixFs::mount_snapshot(&mut src, 0, 512, s2).unwrap();
+    let expected = tree(&view, &mut src);
+    assert_eq!(tree(&replica, &mut dst), expected);
+
+    drop(replica);
+    let replica = HelixFs::mount(&mut dst, 0, 512).unwrap();
+    assert_eq!(tree(&replica, &mut dst), expected, "receive is durable");
+}
+
+#[test]
+fn damaged_streams_are_refused_before_anything_changes() {
+    let mut src = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut src);
+    fs.write(&mut src, "/a", &pattern(20000, 1), 1).unwrap();
+    let s = fs.snapshot(&mut src, "s", 2).unwrap();
+    let stream = fs.send(&mut src, 0, s).unwrap();
+
+    let mut dst = MemBio::new(DISK_SECTORS);
+    let mut replica = fresh(&mut dst);
+    let mut flipped = stream.clone();
+    flipped[100] ^= 1;
+    assert_eq!(
+        replica.receive(&mut dst, &flipped, 3),
+        Err(HelixError::StreamInvalid)
+    );
+    assert_eq!(
+        replica.receive(&mut dst, &stream[..stream.len() - 1], 3),
+        Err(HelixError::StreamInvalid)
+    );
+    assert_eq!(
+        replica.receive(&mut dst, b"not a stream", 3),
+        Err(HelixError::StreamInvalid)
+    );
+    assert!(tree(&replica, &mut dst).is_empty());
+
+    assert_eq!(fs.send(&mut src, 0, s + 1), Err(HelixError::NotFound));
+}
+
+#[test]
+fn incremental_on_the_wrong_base_is_rolled_back() {
+    let mut src = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut src);
+    fs.write(&mut src, "/old", b"gone soon", 1).unwrap();
+    let s1 = fs.snapshot(&mut src, "s1", 2).unwrap();
+    fs.write(&mut src, "/added", &pattern(8000, 1), 3).unwrap();
+    fs.unlink(&mut src, "/old", 4).unwrap();
+    let s2 = fs.snapshot(&mut src, "s2", 5).unwrap();
+    let delta = fs.send(&mut src, s1, s2).unwrap();
+
+    // This volume never received s1, so there is no /old to remove.
+    let mut dst = MemBio::new(DISK_SECTORS);
+    let mut replica = fresh(&mut dst);
+    replica.write(&mut dst, "/local", b"mine", 6).unwrap();
+    let before = tree(&replica, &mut dst);
+    assert_eq!(
+        replica.receive(&mut dst, &delta, 7),
+        Err(HelixError::NotFound)
+    );
+    assert_eq!(tree(&replica, &mut dst), before);
+    assert_eq!(replica.active_tx(), None);
+}
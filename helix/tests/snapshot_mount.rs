@@ -0,0 +1,170 @@
+//! Snapshot mounts: the namespace exactly as of a snapshot marker, built from
+//! the checkpointed index plus log replay up to it, read-only, and backed by
+//! blocks the live volume keeps pinned.
+
+mod common;
+
+use common::{pattern, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+#[test]
+fn snapshot_mount_shows_the_namespace_at_the_marker() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let old = pattern(30000, 1);
+    fs.write(&mut dev, "/etc/conf", &old, 1).unwrap();
+    fs.write(&mut dev, "/doomed", b"short-lived", 2).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+    fs.write(&mut dev, "/after-ckpt", b"replayed", 3).unwrap();
+    let snap = fs.snapshot(&mut dev, "pre-update", 4).unwrap();
+
+    fs.write(&mut dev, "/etc/conf", &pattern(30000, 2), 5)
+        .unwrap();
+    fs.unlink(&mut dev, "/doomed", 6).unwrap();
+    fs.write(&mut dev, "/new", b"later", 7).unwrap();
+    fs.sync(&mut dev).unwrap();
+
+    assert_eq!(fs.find_snapshot("pre-update"), Ok(snap));
+    assert_eq!(fs.find_snapshot("missing"), Err(HelixError::NotFound));
+
+    assert_eq!(
+        HelixFs::lookup_snapshot(&mut dev, 0, 512, "pre-update"),
+        Ok(snap)
+    );
+
+    let view = HelixFs::mount_snapshot(&mut dev, 0, 512, snap).unwrap();
+    assert_eq!(view.frozen_at(), Some(snap));
+    assert_eq!(view.read(&mut dev, "/etc/conf").unwrap(), old);
+    assert_eq!(view.read(&mut dev, "/doomed").unwrap(), b"short-lived");
+    assert_eq!(view.read(&mut dev, "/after-ckpt").unwrap(), b"replayed");
+    assert_eq!(view.read(&mut dev, "/new"), Err(HelixError::NotFound));
+
+    // The live mount is unaffected.
+    assert_eq!(fs.read(&mut dev, "/etc/conf").unwrap(), pattern(30000, 2));
+    assert_eq!(fs.read(&mut dev, "/doomed"), Err(HelixError::NotFound));
+}
+
+#[test]
+fn snapshot_blocks_stay_pinned_across_a_remount() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let a = pattern(40000, 3);
+    let b = pattern(20000, 4);
+    fs.write(&mut dev, "/a", &a, 1).unwrap();
+    fs.write(&mut dev, "/b", &b, 2).unwrap();
+    let snap = fs.snapshot(&mut dev, "s", 3).unwrap();
+    fs.write(&mut dev, "/a", &pattern(40000, 5), 4).unwrap();
+    fs.unlink(&mut dev, "/b", 5).unwrap();
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    // Churn on a fresh mount must not reuse the snapshot's blocks.
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    for i in 0..6u8 {
+        fs.write(&mut dev, "/filler", &pattern(60000, 10 + i), 6 + i as u64)
+            .unwrap();
+    }
+    fs.sync(&mut dev).unwrap();
+
+    let view = HelixFs::mount_snapshot(&mut dev, 0, 512, snap).unwrap();
+    assert_eq!(view.read(&mut dev, "/a").unwrap(), a);
+    assert_eq!(view.read(&mut dev, "/b").unwrap(), b);
+}
+
+#[test]
+fn snapshot_mount_is_read_only() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/f", b"x", 1).unwrap();
+    let snap = fs.snapshot(&mut dev, "s", 2).unwrap();
+    drop(fs);
+
+    let mut view = HelixFs::mount_snapshot(&mut dev, 0, 512, snap).unwrap();
+    assert_eq!(
+        view.write(&mut dev, "/f", b"y", 3),
+        Err(HelixError::ReadOnly)
+    );
+    assert_eq!(
+        view.write_at(&mut dev, "/f", 0, b"y", 3),
+        Err(HelixError::ReadOnly)
+    );
+    assert_eq!(view.mkdir(&mut dev, "/d", 3), Err(HelixError::ReadOnly));
+    assert_eq!(view.unlink(&mut dev, "/f", 3), Err(HelixError::ReadOnly));
+    assert_eq!(
+        view.rename(&mut dev, "/f", "/g", 3),
+        Err(HelixError::ReadOnly)
+    );
+    assert_eq!(
+        view.setxattr(&mut dev, "/f", "user.k", b"v", 3),
+        Err(HelixError::ReadOnly)
+    );
+    assert_eq!(
+        view.snapshot(&mut dev, "again", 3),
+        Err(HelixError::ReadOnly)
+    );
+    assert_eq!(view.checkpoint(&mut dev), Err(HelixError::ReadOnly));
+    assert_eq!(view.sync(&mut dev), Ok(()), "sync on a view is a no-op");
+    assert_eq!(view.read(&mut dev, "/f").unwrap(), b"x");
+}
+
+#[test]
+fn unknown_markers_are_not_found() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/f", b"x", 1).unwrap();
+    let snap = fs.snapshot(&mut dev, "s", 2).unwrap();
+    // A non-marker LSN is not a snapshot.
+    let write_lsn = fs.index.lookup(&mut dev, "/f").unwrap().unwrap().lsn;
+    fs.sync(&mut dev).unwrap();
+    assert_eq!(
+        HelixFs::mount_snapshot(&mut dev, 0, 512, write_lsn).err(),
+        Some(HelixError::NotFound)
+    );
+    assert_eq!(
+        HelixFs::mount_snapshot(&mut dev, 0, 512, snap + 1000).err(),
+        Some(HelixError::NotFound)
+    );
+}
+
+#[test]
+fn snapshot_outlives_the_checkpoint_that_recycles_its_marker() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let old = pattern(25000, 6);
+    fs.write(&mut dev, "/f", &old, 1).unwrap();
+    fs.write(&mut dev, "/gone", b"bye", 2).unwrap();
+    let snap = fs.snapshot(&mut dev, "s", 3).unwrap();
+    fs.write(&mut dev, "/f", &pattern(25000, 7), 4).unwrap();
+    fs.unlink(&mut dev, "/gone", 5).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+    fs.write(&mut dev, "/later", b"x", 6).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.snapshot_lsns, [snap]);
+    for i in 0..4u8 {
+        fs.write(&mut dev, "/filler", &pattern(50000, 20 + i), 7 + i as u64)
+            .unwrap();
+    }
+    fs.sync(&mut dev).unwrap();
+
+    let view = HelixFs::mount_snapshot(&mut dev, 0, 512, snap).unwrap();
+    assert_eq!(view.read(&mut dev, "/f").unwrap(), old);
+    assert_eq!(view.read(&mut dev, "/gone").unwrap(), b"bye");
+    assert_eq!(view.read(&mut dev, "/later"), Err(HelixError::NotFound));
+}
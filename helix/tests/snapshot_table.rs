@@ -0,0 +1,186 @@
+//! The snapshot table: listing, deletion (which must hand back exactly the
+//! blocks only that snapshot kept) and rollback of the live namespace.
+
+mod common;
+
+use common::{pattern, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::ops::snapshot::MAX_SNAPSHOTS;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+fn names(fs: &HelixFs) -> Vec<String> {
+    fs.list_snapshots().into_iter().map(|s| s.name).collect()
+}
+
+#[test]
+fn list_survives_remount_and_checkpoint() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/f", b"1", 1).unwrap();
+    let a = fs.snapshot(&mut dev, "a", 2).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+    let b = fs.snapshot(&mut dev, "b", 3).unwrap();
+    let a2 = fs.snapshot(&mut dev, "a", 4).unwrap();
+    drop(fs);
+
+    let fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    let list = fs.list_snapshots();
+    assert_eq!(
+        list.iter()
+            .map(|s| (s.lsn, s.timestamp_ns, s.name.as_str()))
+            .collect::<Vec<_>>(),
+        [(a, 2, "a"), (b, 3, "b"), (a2, 4, "a")]
+    );
+    assert_eq!(fs.find_snapshot("a"), Ok(a2), "the newest name wins");
+}
+
+#[test]
+fn invalid_names_and_a_full_table_are_refused() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    assert_eq!(
+        fs.snapshot(&mut dev, &"n".repeat(64), 1),
+        Err(HelixError::PathInvalid)
+    );
+    for i in 0..MAX_SNAPSHOTS {
+        fs.snapshot(&mut dev, &format!("s{i}"), i as u64).unwrap();
+    }
+    assert_eq!(
+        fs.snapshot(&mut dev, "one-more", 99),
+        Err(HelixError::SnapshotTableFull)
+    );
+    fs.delete_snapshot(&mut dev, "s0").unwrap();
+    fs.snapshot(&mut dev, "one-more", 100).unwrap();
+    assert_eq!(names(&fs).len(), MAX_SNAPSHOTS);
+}
+
+#[test]
+fn delete_frees_only_what_the_snapshot_pinned() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/keep", &pattern(30000, 1), 1).unwrap();
+    let v1 = pattern(40000, 2);
+    fs.write(&mut dev, "/big", &v1, 2).unwrap();
+    fs.snapshot(&mut dev, "old", 3).unwrap();
+    let v2 = pattern(40000, 3);
+    fs.write(&mut dev, "/big", &v2, 4).unwrap();
+    let other = fs.snapshot(&mut dev, "other", 5).unwrap();
+    fs.unlink(&mut dev, "/keep", 6).unwrap();
+    fs.write(&mut dev, "/big", &pattern(40000, 4), 7).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+
+    let pinned = fs.bitmap.allocated_count();
+    fs.delete_snapshot(&mut dev, "old").unwrap();
+    let v1_blocks = 40000u64.div_ceil(4096);
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        pinned - v1_blocks - 1,
+        "the superseded /big and the snapshot's index region must be freed"
+    );
+    assert_eq!(names(&fs), ["other"]);
+    assert_eq!(
+        fs.delete_snapshot(&mut dev, "old"),
+        Err(HelixError::NotFound)
+    );
+
+    // The remaining snapshot still sees its versions after reuse and remount.
+    fs.write(&mut dev, "/filler", &pattern(200000, 5), 8)
+        .unwrap();
+    fs.sync(&mut dev).unwrap();
+    let allocated = fs.bitmap.allocated_count();
+    drop(fs);
+    let fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.bitmap.allocated_count(), allocated);
+    let view = HelixFs::mount_snapshot(&mut dev, 0, 512, other).unwrap();
+    assert_eq!(view.read(&mut dev, "/big").unwrap(), v2);
+    assert_eq!(view.read(&mut dev, "/keep").unwrap(), pattern(30000, 1));
+}
+
+#[test]
+fn rollback_restores_the_snapshot_namespace() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let kernel = pattern(50000, 1);
+    fs.write(&mut dev, "/boot/kernel", &kernel, 1).unwrap();
+    fs.write(&mut dev, "/etc/conf", b"stable", 2).unwrap();
+    fs.setxattr(&mut dev, "/etc/conf", "user.k", b"v1", 3)
+        .unwrap();
+    fs.write(&mut dev, "/etc/removed", b"still wanted", 4)
+        .unwrap();
+    fs.write(&mut dev, "/same", &pattern(9000, 2), 5).unwrap();
+    fs.snapshot(&mut dev, "pre-update", 6).unwrap();
+
+    // A botched update.
+    fs.write(&mut dev, "/boot/kernel", &pattern(50000, 9), 7)
+        .unwrap();
+    fs.write(&mut dev, "/etc/conf", b"broken", 8).unwrap();
+    fs.setxattr(&mut dev, "/etc/conf", "user.k", b"v2", 9)
+        .unwrap();
+    fs.unlink(&mut dev, "/etc/removed", 10).unwrap();
+    fs.write(&mut dev, "/opt/new/tool", b"junk", 11).unwrap();
+    fs.mkdir(&mut dev, "/var", 12).unwrap();
+    let same_lsn = fs.index.lookup(&mut dev, "/same").unwrap().unwrap().lsn;
+
+    fs.rollback_to(&mut dev, "pre-update", 13).unwrap();
+    let check = |fs: &HelixFs, dev: &mut MemBio| {
+        assert_eq!(fs.read(dev, "/boot/kernel").unwrap(), kernel);
+        assert_eq!(fs.read(dev, "/etc/conf").unwrap(), b"stable");
+        assert_eq!(fs.getxattr(dev, "/etc/conf", "user.k").unwrap(), b"v1");
+        assert_eq!(fs.read(dev, "/etc/removed").unwrap(), b"still wanted");
+        assert_eq!(fs.read(dev, "/opt/new/tool"), Err(HelixError::NotFound));
+        assert!(fs.stat(dev, "/opt/new").is_err());
+        assert!(fs.stat(dev, "/var").is_err());
+    };
+    check(&fs, &mut dev);
+    assert_eq!(
+        fs.index.lookup(&mut dev, "/same").unwrap().unwrap().lsn,
+        same_lsn,
+        "unchanged files are left alone"
+    );
+    assert_eq!(names(&fs), ["pre-update"], "rollback keeps the snapshot");
+
+    // Durable, and the snapshot is still good for another round.
+    drop(fs);
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    check(&fs, &mut dev);
+    fs.write(&mut dev, "/etc/conf", b"broken again", 14)
+        .unwrap();
+    fs.rollback_to(&mut dev, "pre-update", 15).unwrap();
+    check(&fs, &mut dev);
+
+    assert_eq!(
+        fs.rollback_to(&mut dev, "missing", 16),
+        Err(HelixError::NotFound)
+    );
+}
+
+#[test]
+fn snapshot_admin_is_refused_inside_a_transaction() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.snapshot(&mut dev, "s", 1).unwrap();
+    fs.begin_tx(&mut dev, 2).unwrap();
+    assert_eq!(
+        fs.delete_snapshot(&mut dev, "s"),
+        Err(HelixError::TxConflict)
+    );
+    assert_eq!(
+        fs.rollback_to(&mut dev, "s", 3),
+        Err(HelixError::TxConflict)
+    );
+    fs.abort(&mut dev, 4).unwrap();
+    fs.delete_snapshot(&mut dev, "s").unwrap();
+}
@@ -0,0 +1,152 @@
+//! Transactions: records between `TxBegin` and `TxCommit` replay together or not
+//! at all. A torn transaction (durable records, no durable commit) must vanish on
+//! remount, and `abort` must leave the live volume exactly as `begin_tx` found it.
+
+mod common;
+
+use common::{pattern, CrashBio, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+#[test]
+fn committed_transaction_survives_remount() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/a", b"old a", 1).unwrap();
+    fs.begin_tx(&mut dev, 2).unwrap();
+    fs.write(&mut dev, "/a", b"new a", 3).unwrap();
+    fs.write(&mut dev, "/b", &pattern(20000, 1), 4).unwrap();
+    fs.mkdir(&mut dev, "/d", 5).unwrap();
+    fs.commit(&mut dev, 6).unwrap();
+    assert_eq!(fs.active_tx(), None);
+    drop(fs);
+
+    let fs2 = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs2.read(&mut dev, "/a").unwrap(), b"new a");
+    assert_eq!(fs2.read(&mut dev, "/b").unwrap(), pattern(20000, 1));
+    assert!(fs2.stat(&mut dev, "/d").unwrap().is_dir());
+}
+
+#[test]
+fn torn_transaction_is_dropped_on_replay() {
+    let mut dev = CrashBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let original = pattern(12000, 2);
+    fs.write(&mut dev, "/keep", &original, 1).unwrap();
+    fs.sync(&mut dev).unwrap();
+
+    fs.begin_tx(&mut dev, 2).unwrap();
+    fs.write(&mut dev, "/keep", &pattern(12000, 3), 3).unwrap();
+    fs.write(&mut dev, "/new", b"half", 4).unwrap();
+    fs.unlink(&mut dev, "/keep", 5).unwrap();
+    // Every record so far is durable, but the commit never is.
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+    dev.crash();
+
+    let fs2 = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(
+        fs2.read(&mut dev, "/keep").unwrap(),
+        original,
+        "a torn transaction leaked into the replayed namespace"
+    );
+    assert!(
+        fs2.stat(&mut dev, "/new").is_err(),
+        "uncommitted create survived"
+    );
+    let versions = fs2.versions(&mut dev, "/keep").unwrap();
+    assert_eq!(
+        versions.len(),
+        1,
+        "uncommitted versions listed: {versions:?}"
+    );
+}
+
+#[test]
+fn abort_restores_namespace_and_allocation() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let original = pattern(30000, 4);
+    fs.write(&mut dev, "/f", &original, 1).unwrap();
+    fs.write(&mut dev, "/gone", b"bye", 2).unwrap();
+    let allocated = fs.bitmap.allocated_count();
+    let map = fs.bitmap.as_bytes().to_vec();
+
+    fs.begin_tx(&mut dev, 3).unwrap();
+    fs.write(&mut dev, "/f", &pattern(50000, 5), 4).unwrap();
+    fs.write_at(&mut dev, "/f", 100, b"patch", 5).unwrap();
+    fs.unlink(&mut dev, "/gone", 6).unwrap();
+    fs.write(&mut dev, "/fresh", &pattern(9000, 6), 7).unwrap();
+    fs.abort(&mut dev, 8).unwrap();
+
+    assert_eq!(fs.read(&mut dev, "/f").unwrap(), original);
+    assert_eq!(fs.stat(&mut dev, "/f").unwrap().version_count, 1);
+    assert_eq!(fs.read(&mut dev, "/gone").unwrap(), b"bye");
+    assert!(fs.stat(&mut dev, "/fresh").is_err());
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        allocated,
+        "abort must release every block the transaction allocated"
+    );
+    assert_eq!(fs.bitmap.as_bytes(), map, "and only those");
+
+    // Still usable, and the aborted records stay dead across a remount.
+    fs.write(&mut dev, "/after", b"ok", 9).unwrap();
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+    let fs2 = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs2.read(&mut dev, "/f").unwrap(), original);
+    assert!(fs2.stat(&mut dev, "/fresh").is_err());
+    assert_eq!(fs2.read(&mut dev, "/after").unwrap(), b"ok");
+}
+
+#[test]
+fn superseded_blocks_are_not_reused_before_commit() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    let original = pattern(40000, 7);
+    fs.write(&mut dev, "/f", &original, 1).unwrap();
+    let free_before = fs.bitmap.free_count();
+
+    fs.begin_tx(&mut dev, 2).unwrap();
+    fs.write(&mut dev, "/f", &pattern(40000, 8), 3).unwrap();
+    assert!(
+        fs.bitmap.free_count() < free_before,
+        "the committed version's blocks were freed inside the transaction"
+    );
+    fs.commit(&mut dev, 4).unwrap();
+    assert_eq!(
+        fs.bitmap.free_count(),
+        free_before,
+        "commit must release the superseded version"
+    );
+}
+
+#[test]
+fn transaction_state_errors() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    assert_eq!(fs.commit(&mut dev, 1), Err(HelixError::NoActiveTransaction));
+    assert_eq!(fs.abort(&mut dev, 1), Err(HelixError::NoActiveTransaction));
+
+    let begin = fs.begin_tx(&mut dev, 2).unwrap();
+    assert_eq!(fs.active_tx(), Some(begin));
+    assert_eq!(fs.begin_tx(&mut dev, 3), Err(HelixError::TxConflict));
+    assert_eq!(fs.checkpoint(&mut dev), Err(HelixError::TxConflict));
+    assert_eq!(fs.snapshot(&mut dev, "s", 4), Err(HelixError::TxConflict));
+    fs.abort(&mut dev, 5).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+}
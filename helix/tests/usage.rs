@@ -0,0 +1,56 @@
+//! Volume usage: free space follows writes, the namespace counts match what
+//! the index holds across a checkpoint, and blocks kept only for a snapshot
+//! show up as pinned until the snapshot goes.
+
+mod common;
+
+use common::{fresh, MemBio};
+
+const DISK_SECTORS: usize = 8192;
+const BLOCK: usize = 4096;
+
+#[test]
+fn counts_follow_the_namespace() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    let before = fs.usage(&mut dev).unwrap();
+    assert_eq!(before.pinned_blocks, 0);
+    assert_eq!(before.snapshots, 0);
+    assert_eq!(before.free_blocks, before.total_blocks);
+
+    fs.write(&mut dev, "/a", &[1u8; 2 * BLOCK], 1).unwrap();
+    fs.mkdir(&mut dev, "/d", 2).unwrap();
+    let u = fs.usage(&mut dev).unwrap();
+    assert_eq!(u.live_entries, before.live_entries + 2);
+    assert_eq!(u.free_blocks, before.free_blocks - 2);
+    assert_eq!(u.pinned_blocks, 0);
+
+    fs.checkpoint(&mut dev).unwrap();
+    fs.unlink(&mut dev, "/a", 3).unwrap();
+    let u = fs.usage(&mut dev).unwrap();
+    assert_eq!(u.live_entries, before.live_entries + 1);
+    // The checkpoint still holds `/a`, shadowed by a tombstone until the next.
+    assert_eq!(u.stored_entries, u.live_entries + 2);
+
+    fs.checkpoint(&mut dev).unwrap();
+    let u = fs.usage(&mut dev).unwrap();
+    assert_eq!(u.stored_entries, u.live_entries);
+}
+
+#[test]
+fn snapshot_blocks_are_pinned() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/a", &[1u8; 2 * BLOCK], 1).unwrap();
+    fs.snapshot(&mut dev, "s", 2).unwrap();
+    fs.write(&mut dev, "/a", &[2u8; 2 * BLOCK], 3).unwrap();
+
+    let u = fs.usage(&mut dev).unwrap();
+    assert_eq!(u.snapshots, 1);
+    assert!(u.pinned_blocks >= 2, "pinned {}", u.pinned_blocks);
+
+    fs.delete_snapshot(&mut dev, "s").unwrap();
+    let u = fs.usage(&mut dev).unwrap();
+    assert_eq!(u.snapshots, 0);
+    assert_eq!(u.pinned_blocks, 0);
+}
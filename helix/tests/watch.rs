@@ -0,0 +1,104 @@
+//! Change feed: off until enabled; every namespace change comes out once,
+//! oldest first, with creations told apart from rewrites and link records
+//! hidden; a transaction's changes wait for its commit and vanish with its
+//! abort; past the bound the feed drops changes and says so.
+
+mod common;
+
+use common::{fresh, MemBio};
+use morpheus_helix::log::feed::{ChangeKind, FEED_CAPACITY};
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+fn take(fs: &mut HelixFs) -> Vec<(ChangeKind, String, String)> {
+    let (changes, lost) = fs.take_changes();
+    assert!(!lost);
+    changes
+        .into_iter()
+        .map(|c| (c.kind, c.path, c.to))
+        .collect()
+}
+
+fn ch(kind: ChangeKind, path: &str, to: &str) -> (ChangeKind, String, String) {
+    (kind, String::from(path), String::from(to))
+}
+
+#[test]
+fn every_change_comes_out_once() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.write(&mut dev, "/before", b"x", 1).unwrap();
+    assert!(take(&mut fs).is_empty());
+
+    fs.watch(true);
+    fs.write(&mut dev, "/f", b"one", 2).unwrap();
+    fs.write(&mut dev, "/f", b"two", 3).unwrap();
+    fs.mkdir(&mut dev, "/d", 4).unwrap();
+    fs.rename(&mut dev, "/f", "/d/g", 5).unwrap();
+    fs.setxattr(&mut dev, "/d/g", "user.k", b"v", 6).unwrap();
+    fs.chmod(&mut dev, "/d/g", 0o600, 7).unwrap();
+    fs.symlink(&mut dev, "g", "/d/s", 8).unwrap();
+    fs.link(&mut dev, "/d/g", "/d/h", 9).unwrap();
+    fs.write(&mut dev, "/d/g", b"three", 10).unwrap();
+    fs.unlink(&mut dev, "/before", 11).unwrap();
+
+    use ChangeKind::*;
+    assert_eq!(
+        take(&mut fs),
+        [
+            ch(Create, "/f", ""),
+            ch(Modify, "/f", ""),
+            ch(Create, "/d", ""),
+            ch(Rename, "/f", "/d/g"),
+            ch(Attrib, "/d/g", ""),
+            ch(Attrib, "/d/g", ""),
+            ch(Create, "/d/s", ""),
+            ch(Create, "/d/h", ""),
+            ch(Delete, "/before", ""),
+        ]
+    );
+    assert!(take(&mut fs).is_empty());
+
+    fs.watch(false);
+    fs.write(&mut dev, "/d/g", b"four", 12).unwrap();
+    fs.watch(true);
+    assert!(take(&mut fs).is_empty());
+}
+
+#[test]
+fn transactions_surface_on_commit_only() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.watch(true);
+
+    fs.begin_tx(&mut dev, 1).unwrap();
+    fs.write(&mut dev, "/a", b"a", 2).unwrap();
+    assert!(take(&mut fs).is_empty());
+    fs.commit(&mut dev, 3).unwrap();
+    assert_eq!(take(&mut fs), [ch(ChangeKind::Create, "/a", "")]);
+
+    fs.begin_tx(&mut dev, 4).unwrap();
+    fs.write(&mut dev, "/b", b"b", 5).unwrap();
+    fs.unlink(&mut dev, "/a", 5).unwrap();
+    fs.abort(&mut dev, 6).unwrap();
+    assert!(take(&mut fs).is_empty());
+}
+
+#[test]
+fn overflow_is_reported_once() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let mut fs = fresh(&mut dev);
+    fs.watch(true);
+    for i in 0..FEED_CAPACITY + 10 {
+        fs.write(&mut dev, "/f", &[i as u8], i as u64).unwrap();
+    }
+    let (changes, lost) = fs.take_changes();
+    assert_eq!(changes.len(), FEED_CAPACITY);
+    assert!(lost);
+    fs.write(&mut dev, "/f", b"x", 0).unwrap();
+    let (changes, lost) = fs.take_changes();
+    assert_eq!(changes.len(), 1);
+    assert!(!lost);
+}
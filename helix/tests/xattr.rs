@@ -0,0 +1,187 @@
+//! Extended attributes: stored through `SetMeta` records, rebuilt by replay,
+//! carried by rewrites and renames, and still readable at a snapshot after the
+//! live set has moved on.
+
+mod common;
+
+use common::{pattern, MemBio};
+use morpheus_helix::error::HelixError;
+use morpheus_helix::HelixFs;
+
+/// 16384 sectors -> 8 MiB volume.
+const DISK_SECTORS: usize = 16384;
+
+#[test]
+fn set_get_list_remove() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/f", b"content", 1).unwrap();
+    let before = fs.stat(&mut dev, "/f").unwrap();
+    fs.setxattr(&mut dev, "/f", "user.mime", b"text/plain", 2)
+        .unwrap();
+    fs.setxattr(&mut dev, "/f", "user.tag", b"a", 3).unwrap();
+    fs.setxattr(&mut dev, "/f", "user.tag", b"b", 4).unwrap();
+
+    assert_eq!(
+        fs.getxattr(&mut dev, "/f", "user.mime").unwrap(),
+        b"text/plain"
+    );
+    assert_eq!(fs.getxattr(&mut dev, "/f", "user.tag").unwrap(), b"b");
+    assert_eq!(
+        fs.listxattr(&mut dev, "/f").unwrap(),
+        ["user.mime", "user.tag"]
+    );
+    let after = fs.stat(&mut dev, "/f").unwrap();
+    assert_eq!(
+        (after.modified_ns, after.size),
+        (before.modified_ns, before.size),
+        "setting an attribute must not touch content metadata"
+    );
+
+    fs.removexattr(&mut dev, "/f", "user.mime", 5).unwrap();
+    assert_eq!(
+        fs.getxattr(&mut dev, "/f", "user.mime"),
+        Err(HelixError::NoAttribute)
+    );
+    assert_eq!(
+        fs.removexattr(&mut dev, "/f", "user.mime", 6),
+        Err(HelixError::NoAttribute)
+    );
+
+    // Directories carry attributes too, with or without the trailing '/'.
+    fs.mkdir(&mut dev, "/d", 7).unwrap();
+    fs.setxattr(&mut dev, "/d", "user.owner", b"me", 8).unwrap();
+    assert_eq!(fs.getxattr(&mut dev, "/d/", "user.owner").unwrap(), b"me");
+}
+
+#[test]
+fn attributes_survive_remount_and_checkpoint() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/a", &pattern(20000, 1), 1).unwrap();
+    fs.setxattr(&mut dev, "/a", "user.k", b"v1", 2).unwrap();
+    fs.checkpoint(&mut dev).unwrap();
+    fs.write(&mut dev, "/b", b"small", 3).unwrap();
+    fs.setxattr(&mut dev, "/b", "user.k", b"v2", 4).unwrap();
+    fs.sync(&mut dev).unwrap();
+    let allocated = fs.bitmap.allocated_count();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(fs.getxattr(&mut dev, "/a", "user.k").unwrap(), b"v1");
+    assert_eq!(fs.getxattr(&mut dev, "/b", "user.k").unwrap(), b"v2");
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        allocated,
+        "attribute blocks must be marked live on remount"
+    );
+
+    // Fresh allocations must not land on an attribute block.
+    fs.write(&mut dev, "/filler", &pattern(40000, 2), 5)
+        .unwrap();
+    assert_eq!(fs.getxattr(&mut dev, "/a", "user.k").unwrap(), b"v1");
+}
+
+#[test]
+fn attributes_follow_rewrites_and_renames() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/src", b"one", 1).unwrap();
+    fs.setxattr(&mut dev, "/src", "user.k", b"kept", 2).unwrap();
+    fs.write(&mut dev, "/src", &pattern(9000, 3), 3).unwrap();
+    fs.write_at(&mut dev, "/src", 10, b"patch", 4).unwrap();
+
+    fs.write(&mut dev, "/dst", b"victim", 5).unwrap();
+    fs.setxattr(&mut dev, "/dst", "user.k", b"victim's", 6)
+        .unwrap();
+    let allocated = fs.bitmap.allocated_count();
+    fs.rename(&mut dev, "/src", "/dst", 7).unwrap();
+    assert_eq!(
+        fs.bitmap.allocated_count(),
+        allocated - 1,
+        "the clobbered file's attribute block must be freed"
+    );
+    assert_eq!(fs.getxattr(&mut dev, "/dst", "user.k").unwrap(), b"kept");
+
+    fs.mkdir(&mut dev, "/dir", 8).unwrap();
+    fs.rename(&mut dev, "/dst", "/dir/moved", 9).unwrap();
+    fs.sync(&mut dev).unwrap();
+    drop(fs);
+
+    let mut fs = HelixFs::mount(&mut dev, 0, 512).unwrap();
+    assert_eq!(
+        fs.getxattr(&mut dev, "/dir/moved", "user.k").unwrap(),
+        b"kept"
+    );
+    let allocated = fs.bitmap.allocated_count();
+    fs.unlink(&mut dev, "/dir/moved", 10).unwrap();
+    assert!(
+        fs.bitmap.allocated_count() < allocated,
+        "unlink must free the attribute block"
+    );
+    fs.write(&mut dev, "/dir/moved", b"new", 11).unwrap();
+    assert!(
+        fs.listxattr(&mut dev, "/dir/moved").unwrap().is_empty(),
+        "a recreated path must not inherit the deleted file's attributes"
+    );
+}
+
+#[test]
+fn snapshot_keeps_superseded_attributes() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/f", b"x", 1).unwrap();
+    fs.setxattr(&mut dev, "/f", "user.k", b"old", 2).unwrap();
+    let snap = fs.snapshot(&mut dev, "s", 3).unwrap();
+    fs.setxattr(&mut dev, "/f", "user.k", b"new", 4).unwrap();
+    fs.rename(&mut dev, "/f", "/g", 5).unwrap();
+    // Churn allocations so an unpinned block would be reused.
+    for i in 0..4u64 {
+        fs.setxattr(&mut dev, "/g", "user.k", &pattern(100, i as u8), 6 + i)
+            .unwrap();
+    }
+
+    let at_snap = fs.xattrs_at_lsn(&mut dev, "/f", snap).unwrap();
+    assert_eq!(at_snap.get("user.k").map(Vec::as_slice), Some(&b"old"[..]));
+    assert!(fs.xattrs_at_lsn(&mut dev, "/g", snap).unwrap().is_empty());
+
+    fs.sync(&mut dev).unwrap();
+    let live = fs.xattrs_at_lsn(&mut dev, "/g", u64::MAX).unwrap();
+    assert_eq!(live.get("user.k"), Some(&pattern(100, 3)));
+}
+
+#[test]
+fn rejects_oversized_and_invalid_attributes() {
+    let mut dev = MemBio::new(DISK_SECTORS);
+    let sectors = dev.sectors();
+    let mut fs = HelixFs::format_and_mount(&mut dev, 0, sectors, 512, "t", [0u8; 16]).unwrap();
+
+    fs.write(&mut dev, "/f", b"x", 1).unwrap();
+    fs.setxattr(&mut dev, "/f", "user.a", &[1u8; 3000], 2)
+        .unwrap();
+    assert_eq!(
+        fs.setxattr(&mut dev, "/f", "user.b", &[2u8; 2000], 3),
+        Err(HelixError::AttributeTooLarge)
+    );
+    assert_eq!(
+        fs.listxattr(&mut dev, "/f").unwrap(),
+        ["user.a"],
+        "a rejected set must leave the old attributes intact"
+    );
+    assert_eq!(
+        fs.setxattr(&mut dev, "/f", "", b"v", 4),
+        Err(HelixError::PathInvalid)
+    );
+    assert_eq!(
+        fs.setxattr(&mut dev, "/missing", "user.a", b"v", 5),
+        Err(HelixError::NotFound)
+    );
+}
@@ -12,17 +12,26 @@ use crate::is_error;
 use crate::raw::*;
 
 // open flags + seek whence are canonical in morpheus-foundation — single source.
-pub use morpheus_foundation::flags::open_flags::{O_APPEND, O_CREATE, O_READ, O_TRUNC, O_WRITE};
+pub use morpheus_foundation::flags::open_flags::{
+    O_APPEND, O_CREATE, O_NOFOLLOW, O_READ, O_TRUNC, O_WRITE,
+};
 pub use morpheus_foundation::syscall_abi::{SEEK_CUR, SEEK_END, SEEK_SET};
 
 // Storage-subsystem ABI (volumes/mounts) — re-exported so `libmorpheus::fs::*`
 // paths stay stable and the kernel↔userland seam is single-sourced.
 pub use morpheus_foundation::storage::{
-    DEV_AHCI, DEV_RAM, DEV_SDHCI, DEV_USBMSD, DEV_VIRTIO, FS_AUTO, FS_FAT32, FS_HELIX, FS_NONE,
-    FS_UNKNOWN, MNT_FORCE, MNT_RDONLY, MNT_STAGED, VOLUME_NONE, VOL_EPHEMERAL, VOL_MOUNTED,
-    VOL_RDONLY, VOL_REMOVABLE,
+    DEV_AHCI, DEV_RAM, DEV_SDHCI, DEV_USBMSD, DEV_VIRTIO, FS_AUTO, FS_DEVFS, FS_EV_ALL,
+    FS_EV_ATTRIB, FS_EV_CREATE, FS_EV_DELETE, FS_EV_MODIFY, FS_EV_MOVED_FROM, FS_EV_MOVED_TO,
+    FS_EV_OVERFLOW, FS_EXT4, FS_FAT32, FS_HELIX, FS_ISO9660, FS_NONE, FS_PROC, FS_SYSFS,
+    FS_UNKNOWN, FS_WATCH_SUBTREE, MNT_FORCE, MNT_FSCK, MNT_KEY, MNT_MANIFEST, MNT_RDONLY,
+    MNT_SNAPSHOT, MNT_STAGED, MOUNT_KEY_MAX, TX_ABORT, TX_BEGIN, TX_COMMIT, VOLUME_NONE,
+    VOL_EPHEMERAL, VOL_MOUNTED, VOL_RDONLY, VOL_REMOVABLE, XATTR_COMPRESSION, XATTR_QUOTA,
+    XATTR_RETENTION,
+};
+pub use morpheus_foundation::types::{
+    CleanStats, FsEvent, MountInfo, MountKey, MountManifest, SnapshotInfo, SnapshotSpec, StatFs,
+    VolumeInfo,
 };
-pub use morpheus_foundation::types::{MountInfo, VolumeInfo};
 
 pub fn open(path: &str, flags: u32) -> Result<usize, u64> {
     let ret = unsafe {
@@ -141,6 +150,123 @@ pub fn stat(path: &str, buf: &mut [u8]) -> Result<(), u64> {
     }
 }
 
+/// `stat` of a symbolic link itself rather than what it points at.
+pub fn lstat(path: &str, buf: &mut [u8]) -> Result<(), u64> {
+    let ret = unsafe {
+        sys_lstat(
+            path.as_ptr() as u64,
+            path.len() as u64,
+            buf.as_mut_ptr() as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Create `path` as a symbolic link to `target`, which need not exist.
+pub fn symlink(target: &str, path: &str) -> Result<(), u64> {
+    let ret = unsafe {
+        sys_symlink(
+            target.as_ptr() as u64,
+            target.len() as u64,
+            path.as_ptr() as u64,
+            path.len() as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Target of the symbolic link `path`; `EINVAL` if it is not one.
+pub fn readlink(path: &str) -> Result<String, u64> {
+    let target = probe_fetch(|buf, len| unsafe {
+        sys_readlink(path.as_ptr() as u64, path.len() as u64, buf, len)
+    })?;
+    Ok(String::from_utf8_lossy(&target).into_owned())
+}
+
+/// Hard-link `new` to the file at `old`; both must be on one mount.
+pub fn link(old: &str, new: &str) -> Result<(), u64> {
+    let ret = unsafe {
+        sys_link(
+            old.as_ptr() as u64,
+            old.len() as u64,
+            new.as_ptr() as u64,
+            new.len() as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Set the permission bits (`mode & 0o7777`) of `path`; owner or root only.
+pub fn chmod(path: &str, mode: u32) -> Result<(), u64> {
+    let ret = unsafe { sys_chmod(path.as_ptr() as u64, path.len() as u64, mode as u64) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Give `path` to `uid`/`gid`; root only.
+pub fn chown(path: &str, uid: u32, gid: u32) -> Result<(), u64> {
+    let ret = unsafe {
+        sys_chown(
+            path.as_ptr() as u64,
+            path.len() as u64,
+            uid as u64,
+            gid as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Run one background-cleaner step of about `budget` blocks of I/O on the
+/// volume holding `path` (root only), then report its progress since mount.
+/// `budget == 0` only reports.
+pub fn clean(path: &str, budget: u64) -> Result<CleanStats, u64> {
+    let mut stats = CleanStats::default();
+    let ret = unsafe {
+        sys_fs_clean(
+            path.as_ptr() as u64,
+            path.len() as u64,
+            budget,
+            &mut stats as *mut CleanStats as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(stats)
+    }
+}
+
+/// Grow or shrink the volume mounted at `path` (root only), GPT entry and
+/// filesystem together, to `lba_count` sectors; 0 takes all the free space
+/// after it. Returns the new size in sectors.
+pub fn resize(path: &str, lba_count: u64) -> Result<u64, u64> {
+    let ret = unsafe { sys_fs_resize(path.as_ptr() as u64, path.len() as u64, lba_count) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
 pub fn sync() -> Result<(), u64> {
     let ret = unsafe { syscall0(SYS_SYNC) };
     if is_error(ret) {
@@ -168,6 +294,7 @@ pub fn truncate(path: &str, new_size: u64) -> Result<(), u64> {
 }
 
 /// Record a named filesystem snapshot; returns its LSN (a point-in-time handle).
+/// Root only.
 pub fn snapshot(name: &str) -> Result<u64, u64> {
     let ret = unsafe { syscall2(SYS_SNAPSHOT, name.as_ptr() as u64, name.len() as u64) };
     if is_error(ret) {
@@ -177,6 +304,209 @@ pub fn snapshot(name: &str) -> Result<u64, u64> {
     }
 }
 
+/// Every snapshot of the root volume, oldest first. Probe count, then fetch.
+pub fn snapshots() -> Result<Vec<SnapshotInfo>, u64> {
+    let count = unsafe { syscall2(SYS_SNAPSHOTS, 0, 0) };
+    if is_error(count) {
+        return Err(count);
+    }
+    let count = count as usize;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut out: Vec<SnapshotInfo> = vec![SnapshotInfo::zeroed(); count];
+    let written = unsafe { syscall2(SYS_SNAPSHOTS, out.as_mut_ptr() as u64, count as u64) };
+    if is_error(written) {
+        return Err(written);
+    }
+    out.truncate(written as usize);
+    Ok(out)
+}
+
+/// Delete the newest snapshot called `name`; blocks only it kept are freed.
+/// Root only.
+pub fn delete_snapshot(name: &str) -> Result<(), u64> {
+    let ret = unsafe { syscall2(SYS_SNAPSHOT_DELETE, name.as_ptr() as u64, name.len() as u64) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Atomically return the root volume to the newest snapshot called `name`.
+/// The snapshot itself is kept.
+pub fn rollback(name: &str) -> Result<(), u64> {
+    let ret = unsafe {
+        syscall2(
+            SYS_SNAPSHOT_ROLLBACK,
+            name.as_ptr() as u64,
+            name.len() as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+fn fs_tx(op: u32, path: &str) -> Result<(), u64> {
+    let ret = unsafe { sys_fs_tx(op as u64, path.as_ptr() as u64, path.len() as u64) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Open a transaction on the filesystem holding `path`. Until `tx_commit`, the
+/// caller's mutations there land atomically across a crash; other processes'
+/// mutations fail `EBUSY`. Exiting without committing aborts.
+pub fn tx_begin(path: &str) -> Result<(), u64> {
+    fs_tx(TX_BEGIN, path)
+}
+
+/// Make the open transaction durable.
+pub fn tx_commit(path: &str) -> Result<(), u64> {
+    fs_tx(TX_COMMIT, path)
+}
+
+/// Discard every change made since `tx_begin`.
+pub fn tx_abort(path: &str) -> Result<(), u64> {
+    fs_tx(TX_ABORT, path)
+}
+
+/// Set extended attribute `name` on `path`, replacing any previous value.
+/// `helix.*` policy attributes need root (`EPERM`).
+pub fn setxattr(path: &str, name: &str, value: &[u8]) -> Result<(), u64> {
+    let ret = unsafe {
+        sys_setxattr(
+            path.as_ptr() as u64,
+            path.len() as u64,
+            name.as_ptr() as u64,
+            name.len() as u64,
+            value.as_ptr() as u64,
+            value.len() as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Value of extended attribute `name` on `path`; `ENODATA` if unset.
+pub fn getxattr(path: &str, name: &str) -> Result<Vec<u8>, u64> {
+    probe_fetch(|buf, len| unsafe {
+        sys_getxattr(
+            path.as_ptr() as u64,
+            path.len() as u64,
+            name.as_ptr() as u64,
+            name.len() as u64,
+            buf,
+            len,
+        )
+    })
+}
+
+/// Names of every extended attribute on `path`.
+pub fn listxattr(path: &str) -> Result<Vec<String>, u64> {
+    let packed = probe_fetch(|buf, len| unsafe {
+        sys_listxattr(path.as_ptr() as u64, path.len() as u64, buf, len)
+    })?;
+    Ok(packed
+        .split(|&b| b == 0)
+        .filter(|n| !n.is_empty())
+        .map(|n| String::from_utf8_lossy(n).into_owned())
+        .collect())
+}
+
+/// Remove extended attribute `name` from `path`; `ENODATA` if unset.
+/// `helix.*` policy attributes need root (`EPERM`).
+pub fn removexattr(path: &str, name: &str) -> Result<(), u64> {
+    let ret = unsafe {
+        sys_removexattr(
+            path.as_ptr() as u64,
+            path.len() as u64,
+            name.as_ptr() as u64,
+            name.len() as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Compress later whole-file writes to `path` (and, for a directory, to
+/// everything below it without its own setting), or opt out with `on = false`.
+/// Existing content is rewritten on its next write, not now. Root only.
+pub fn set_compression(path: &str, on: bool) -> Result<(), u64> {
+    let policy: &[u8] = if on { b"lz4" } else { b"none" };
+    setxattr(path, XATTR_COMPRESSION, policy)
+}
+
+/// Keep prior versions of `path` (and, for a directory, of everything below
+/// it without its own setting) per `policy`: `all`, `last:N`, `within:S`
+/// (seconds) or `none`. Enforced as the volume checkpoints. Root only.
+pub fn set_retention(path: &str, policy: &str) -> Result<(), u64> {
+    setxattr(path, XATTR_RETENTION, policy.as_bytes())
+}
+
+/// Limit what the directory `path` may hold, everything below it counted:
+/// `max_bytes` of file content and `max_entries` names. `0` is unlimited;
+/// both `0` lifts the quota. Writes past a limit fail `EDQUOT`. Root only.
+pub fn set_quota(path: &str, max_bytes: u64, max_entries: u64) -> Result<(), u64> {
+    let policy = alloc::format!("bytes:{},entries:{}", max_bytes, max_entries);
+    setxattr(path, XATTR_QUOTA, policy.as_bytes())
+}
+
+/// Drop now the retained versions of `path` (everything below it, for a
+/// directory) that `policy`, in `set_retention` syntax, would not keep.
+/// Returns how many were dropped.
+pub fn prune_versions(path: &str, policy: &str) -> Result<u64, u64> {
+    let ret = unsafe {
+        sys_fs_prune(
+            path.as_ptr() as u64,
+            path.len() as u64,
+            policy.as_ptr() as u64,
+            policy.len() as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Probe the size, then fetch; retries if the value grew in between (`ERANGE`).
+fn probe_fetch(mut call: impl FnMut(u64, u64) -> u64) -> Result<Vec<u8>, u64> {
+    loop {
+        let len = call(0, 0);
+        if is_error(len) {
+            return Err(len);
+        }
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let mut buf = vec![0u8; len as usize];
+        let got = call(buf.as_mut_ptr() as u64, len);
+        if got == crate::ERANGE {
+            continue;
+        }
+        if is_error(got) {
+            return Err(got);
+        }
+        buf.truncate(got as usize);
+        return Ok(buf);
+    }
+}
+
 /// One logged version of a file. ABI struct: see `morpheus_foundation::types::FileVersion`.
 pub type FileVersion = morpheus_foundation::types::FileVersion;
 
@@ -208,6 +538,103 @@ pub fn versions(path: &str) -> Result<Vec<FileVersion>, u64> {
     Ok(out)
 }
 
+/// Bring `path` back to the version current at `lsn` (see `versions`), even
+/// if it was deleted since; a directory gets everything below it restored,
+/// and so is root only.
+pub fn restore(path: &str, lsn: u64) -> Result<(), u64> {
+    let ret = unsafe { sys_fs_restore(path.as_ptr() as u64, path.len() as u64, lsn) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Space and health of the volume holding `path`.
+pub fn statfs(path: &str) -> Result<StatFs, u64> {
+    let mut st = StatFs::default();
+    let ret = unsafe {
+        sys_statfs(
+            path.as_ptr() as u64,
+            path.len() as u64,
+            &mut st as *mut StatFs as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(st)
+    }
+}
+
+/// `statfs` of the volume an open file lives on.
+pub fn fstatfs(fd: usize) -> Result<StatFs, u64> {
+    let mut st = StatFs::default();
+    let ret = unsafe { sys_fstatfs(fd as u64, &mut st as *mut StatFs as u64) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(st)
+    }
+}
+
+/// A change-notification fd (`O_NONBLOCK`/`O_CLOEXEC` in `flags`); add
+/// watches with `watch_add`, collect events with `read_events`.
+pub fn watch(flags: u32) -> Result<usize, u64> {
+    let ret = unsafe { sys_fs_watch(flags as u64) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+/// Report the `FS_EV_*` changes in `mask` to `path` (and its entries, or
+/// with `FS_WATCH_SUBTREE` everything below) on watch fd `fd`. Returns the
+/// watch descriptor events carry.
+pub fn watch_add(fd: usize, path: &str, mask: u32) -> Result<u32, u64> {
+    let ret = unsafe {
+        sys_fs_watch_add(
+            fd as u64,
+            path.as_ptr() as u64,
+            path.len() as u64,
+            mask as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(ret as u32)
+    }
+}
+
+pub fn watch_rm(fd: usize, wd: u32) -> Result<(), u64> {
+    let ret = unsafe { sys_fs_watch_rm(fd as u64, wd as u64) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
+/// Fill `buf` with queued events from watch fd `fd`; returns how many.
+pub fn read_events(fd: usize, buf: &mut [FsEvent]) -> Result<usize, u64> {
+    let size = core::mem::size_of::<FsEvent>();
+    let ret = unsafe {
+        syscall3(
+            SYS_READ,
+            fd as u64,
+            buf.as_mut_ptr() as u64,
+            core::mem::size_of_val(buf) as u64,
+        )
+    };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(ret as usize / size)
+    }
+}
+
 /// All storage volumes (lsblk-style). Probe count (`max == 0`), then fetch.
 pub fn volumes() -> Result<Vec<VolumeInfo>, u64> {
     let count = unsafe { syscall2(SYS_VOLUMES, 0, 0) };
@@ -249,9 +676,10 @@ pub fn mounts() -> Result<Vec<MountInfo>, u64> {
 }
 
 /// Mount `source_volume_id` (or `VOLUME_NONE` for a fresh RAM volume) at
-/// `mountpoint`. `fs_type` is `FS_AUTO|FS_HELIX|FS_FAT32`; `flags` is `MNT_*`;
-/// `aux` carries the size when staged-from-nothing (else a stage-size cap, 0 =
-/// full source). Returns the `mount_id`.
+/// `mountpoint`. `fs_type` is `FS_AUTO|FS_HELIX|FS_FAT32|FS_ISO9660|FS_EXT4`,
+/// or `FS_PROC|FS_SYSFS|FS_DEVFS` from `VOLUME_NONE`; `flags` is `MNT_*`; `aux`
+/// carries the size when staged-from-nothing (else a stage-size cap, 0 = full
+/// source). Returns the `mount_id`.
 pub fn mount(
     source_volume_id: u64,
     mountpoint: &str,
@@ -277,6 +705,68 @@ pub fn mount(
     }
 }
 
+/// Mount the Helix volume `source_volume_id` read-only at `mountpoint`, as it
+/// stood at the snapshot `spec` selects (`SnapshotSpec::named` or `at_lsn`).
+/// The live mount of the same volume is unaffected. Returns the `mount_id`.
+pub fn mount_snapshot(
+    source_volume_id: u64,
+    mountpoint: &str,
+    spec: &SnapshotSpec,
+) -> Result<u64, u64> {
+    mount(
+        source_volume_id,
+        mountpoint,
+        FS_HELIX,
+        MNT_SNAPSHOT | MNT_RDONLY,
+        spec as *const SnapshotSpec as u64,
+    )
+}
+
+/// `mount` for an encrypted Helix volume: `passphrase` unlocks it, the other
+/// arguments are as for `mount`. A wrong passphrase fails with `EPERM`.
+pub fn mount_encrypted(
+    source_volume_id: u64,
+    mountpoint: &str,
+    passphrase: &[u8],
+    flags: u32,
+    aux: u64,
+) -> Result<u64, u64> {
+    let key = MountKey {
+        passphrase_ptr: passphrase.as_ptr() as u64,
+        passphrase_len: passphrase.len() as u64,
+        aux,
+    };
+    mount(
+        source_volume_id,
+        mountpoint,
+        FS_HELIX,
+        flags | MNT_KEY,
+        &key as *const MountKey as u64,
+    )
+}
+
+/// Mount an ISO stored in chunks across partitions read-only at `mountpoint`.
+/// `manifest` is its serialized manifest (`/.iso/<name>.manifest` on the
+/// ESP); `source_volume_id` is the partition holding the first chunk.
+/// Returns the `mount_id`.
+pub fn mount_chunked_iso(
+    source_volume_id: u64,
+    mountpoint: &str,
+    manifest: &[u8],
+) -> Result<u64, u64> {
+    let spec = MountManifest {
+        manifest_ptr: manifest.as_ptr() as u64,
+        manifest_len: manifest.len() as u64,
+    };
+    mount(
+        source_volume_id,
+        mountpoint,
+        FS_ISO9660,
+        MNT_MANIFEST | MNT_RDONLY,
+        &spec as *const MountManifest as u64,
+    )
+}
+
 /// Unmount the filesystem at `mountpoint`. `flags` is `MNT_*` (`MNT_FORCE` to
 /// revoke open fds).
 pub fn umount(mountpoint: &str, flags: u32) -> Result<(), u64> {
@@ -511,6 +1001,11 @@ impl Metadata {
         self.0.size == 0
     }
 
+    /// Bytes stored on the device; below `len()` for a compressed file.
+    pub fn physical_len(&self) -> u64 {
+        self.0.physical_size
+    }
+
     pub fn is_dir(&self) -> bool {
         (self.0.mode & morpheus_foundation::flags::mode::S_IFMT)
             == morpheus_foundation::flags::mode::S_IFDIR
@@ -34,8 +34,9 @@ pub mod time;
 // source of truth across the syscall seam. Re-exported to keep `libmorpheus::EINVAL`
 // etc. paths stable.
 pub use morpheus_foundation::errno::{
-    errno_value, is_error, EACCES, EADDRINUSE, EADDRNOTAVAIL, EAGAIN, EBADF, EBUSY, ECHILD,
+    errno_value, is_error, E2BIG, EACCES, EADDRINUSE, EADDRNOTAVAIL, EAGAIN, EBADF, EBUSY, ECHILD,
     ECONNABORTED, ECONNREFUSED, ECONNRESET, EEXIST, EFAULT, EHOSTUNREACH, EINPROGRESS, EINTR,
-    EINVAL, EIO, EISDIR, EMFILE, ENETUNREACH, ENODEV, ENOENT, ENOMEM, ENOSPC, ENOSYS, ENOTCONN,
-    ENOTDIR, ENOTEMPTY, ENOTSOCK, EPERM, EPIPE, EROFS, ESRCH, ETIMEDOUT, EWOULDBLOCK,
+    EINVAL, EIO, EISDIR, EMFILE, ENETUNREACH, ENODATA, ENODEV, ENOENT, ENOMEM, ENOSPC, ENOSYS,
+    ENOTCONN, ENOTDIR, ENOTEMPTY, ENOTSOCK, EPERM, EPIPE, ERANGE, EROFS, ESRCH, ETIMEDOUT,
+    EWOULDBLOCK,
 };
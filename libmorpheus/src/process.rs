@@ -26,6 +26,24 @@ pub fn getppid() -> u32 {
     unsafe { syscall0(SYS_GETPPID) as u32 }
 }
 
+/// The caller's `(uid, gid)`; uid 0 is root.
+pub fn getcred() -> (u32, u32) {
+    let ret = unsafe { syscall0(SYS_GETCRED) };
+    (ret as u32, (ret >> 32) as u32)
+}
+
+/// Change the caller's credential; root only, and there is no way back, since
+/// the old uid is not saved. Processes spawned afterwards inherit it. To run an
+/// app unprivileged while staying root, use `spawn_as` or `Command::cred`.
+pub fn setcred(uid: u32, gid: u32) -> Result<(), u64> {
+    let ret = unsafe { syscall2(SYS_SETCRED, uid as u64, gid as u64) };
+    if is_error(ret) {
+        Err(ret)
+    } else {
+        Ok(())
+    }
+}
+
 pub fn yield_cpu() {
     unsafe {
         syscall0(SYS_YIELD);
@@ -144,6 +162,17 @@ pub fn spawn(path: &str) -> Result<u32, u64> {
 
 /// Max 16 args. Child inherits our FDs.
 pub fn spawn_with_args(path: &str, args: &[&str]) -> Result<u32, u64> {
+    spawn_with_cred(path, args, None)
+}
+
+/// Like `spawn_with_args`, but the child starts as `uid`/`gid` while the
+/// caller keeps its own credential. Root only (`EPERM`).
+pub fn spawn_as(path: &str, args: &[&str], uid: u32, gid: u32) -> Result<u32, u64> {
+    spawn_with_cred(path, args, Some((uid, gid)))
+}
+
+fn spawn_with_cred(path: &str, args: &[&str], cred: Option<(u32, u32)>) -> Result<u32, u64> {
+    use morpheus_foundation::flags::SPAWN_SET_CRED;
     // argv descriptor array: [ptr, len] pairs on the stack.
     let mut descs = [[0u64; 2]; 16];
     let count = args.len().min(16);
@@ -151,7 +180,11 @@ pub fn spawn_with_args(path: &str, args: &[&str]) -> Result<u32, u64> {
         descs[i][0] = args[i].as_ptr() as u64;
         descs[i][1] = args[i].len() as u64;
     }
-    let sa = make_spawn_args(path, &descs[..count]);
+    let mut sa = make_spawn_args(path, &descs[..count]);
+    if let Some((uid, gid)) = cred {
+        sa.flags |= SPAWN_SET_CRED;
+        sa.cred = uid as u64 | (gid as u64) << 32;
+    }
     let ret = unsafe {
         syscall1(
             SYS_SPAWN,
@@ -298,6 +331,7 @@ pub fn parse_args<'a>(buf: &'a [u8], out: &mut [&'a str]) -> usize {
 pub struct Command {
     path: String,
     args: Vec<String>,
+    cred: Option<(u32, u32)>,
 }
 
 impl Command {
@@ -305,6 +339,7 @@ impl Command {
         Self {
             path: String::from(path),
             args: Vec::new(),
+            cred: None,
         }
     }
 
@@ -320,12 +355,19 @@ impl Command {
         self
     }
 
+    /// Start the child as `uid`/`gid` instead of the caller's credential.
+    /// Root only; the spawn fails with `EPERM` otherwise.
+    pub fn cred(&mut self, uid: u32, gid: u32) -> &mut Self {
+        self.cred = Some((uid, gid));
+        self
+    }
+
     pub fn spawn_pid(&self) -> error::Result<u32> {
-        if self.args.is_empty() {
+        if self.args.is_empty() && self.cred.is_none() {
             spawn(&self.path).map_err(Error::from_raw)
         } else {
             let refs: Vec<&str> = self.args.iter().map(|s| s.as_str()).collect();
-            spawn_with_args(&self.path, &refs).map_err(Error::from_raw)
+            spawn_with_cred(&self.path, &refs, self.cred).map_err(Error::from_raw)
         }
     }
 
@@ -333,3 +333,137 @@ pub unsafe fn sys_fcntl(fd: u64, cmd: u64, arg: u64) -> u64 {
 pub unsafe fn sys_rmdir(path: u64, path_len: u64) -> u64 {
     syscall2(SYS_RMDIR, path, path_len)
 }
+
+/// `SYS_FS_TX(op, path_ptr, path_len) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fs_tx(op: u64, path: u64, path_len: u64) -> u64 {
+    syscall3(SYS_FS_TX, op, path, path_len)
+}
+
+/// `SYS_SETXATTR(path_ptr, path_len, name_ptr, name_len, val_ptr, val_len) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_setxattr(
+    path: u64,
+    path_len: u64,
+    name: u64,
+    name_len: u64,
+    val: u64,
+    val_len: u64,
+) -> u64 {
+    syscall6(SYS_SETXATTR, path, path_len, name, name_len, val, val_len)
+}
+
+/// `SYS_GETXATTR(path_ptr, path_len, name_ptr, name_len, buf_ptr, buf_len) -> value_len | -errno`.
+#[inline(always)]
+pub unsafe fn sys_getxattr(
+    path: u64,
+    path_len: u64,
+    name: u64,
+    name_len: u64,
+    buf: u64,
+    buf_len: u64,
+) -> u64 {
+    syscall6(SYS_GETXATTR, path, path_len, name, name_len, buf, buf_len)
+}
+
+/// `SYS_LISTXATTR(path_ptr, path_len, buf_ptr, buf_len) -> total_bytes | -errno`.
+#[inline(always)]
+pub unsafe fn sys_listxattr(path: u64, path_len: u64, buf: u64, buf_len: u64) -> u64 {
+    syscall4(SYS_LISTXATTR, path, path_len, buf, buf_len)
+}
+
+/// `SYS_REMOVEXATTR(path_ptr, path_len, name_ptr, name_len) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_removexattr(path: u64, path_len: u64, name: u64, name_len: u64) -> u64 {
+    syscall4(SYS_REMOVEXATTR, path, path_len, name, name_len)
+}
+
+/// `SYS_SYMLINK(target_ptr, target_len, path_ptr, path_len) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_symlink(target: u64, target_len: u64, path: u64, path_len: u64) -> u64 {
+    syscall4(SYS_SYMLINK, target, target_len, path, path_len)
+}
+
+/// `SYS_READLINK(path_ptr, path_len, buf_ptr, buf_len) -> target_len | -errno`.
+#[inline(always)]
+pub unsafe fn sys_readlink(path: u64, path_len: u64, buf: u64, buf_len: u64) -> u64 {
+    syscall4(SYS_READLINK, path, path_len, buf, buf_len)
+}
+
+/// `SYS_LINK(old_ptr, old_len, new_ptr, new_len) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_link(old: u64, old_len: u64, new: u64, new_len: u64) -> u64 {
+    syscall4(SYS_LINK, old, old_len, new, new_len)
+}
+
+/// `SYS_LSTAT(path_ptr, path_len, stat_buf) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_lstat(path: u64, path_len: u64, stat_buf: u64) -> u64 {
+    syscall3(SYS_LSTAT, path, path_len, stat_buf)
+}
+
+/// `SYS_CHMOD(path_ptr, path_len, mode) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_chmod(path: u64, path_len: u64, mode: u64) -> u64 {
+    syscall3(SYS_CHMOD, path, path_len, mode)
+}
+
+/// `SYS_CHOWN(path_ptr, path_len, uid, gid) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_chown(path: u64, path_len: u64, uid: u64, gid: u64) -> u64 {
+    syscall4(SYS_CHOWN, path, path_len, uid, gid)
+}
+
+/// `SYS_FS_CLEAN(path_ptr, path_len, budget, stats_buf) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fs_clean(path: u64, path_len: u64, budget: u64, stats_buf: u64) -> u64 {
+    syscall4(SYS_FS_CLEAN, path, path_len, budget, stats_buf)
+}
+
+/// `SYS_FS_RESIZE(path_ptr, path_len, lba_count) -> lba_count | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fs_resize(path: u64, path_len: u64, lba_count: u64) -> u64 {
+    syscall3(SYS_FS_RESIZE, path, path_len, lba_count)
+}
+
+/// `SYS_FS_PRUNE(path_ptr, path_len, policy_ptr, policy_len) -> dropped | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fs_prune(path: u64, path_len: u64, policy: u64, policy_len: u64) -> u64 {
+    syscall4(SYS_FS_PRUNE, path, path_len, policy, policy_len)
+}
+
+/// `SYS_FS_RESTORE(path_ptr, path_len, lsn) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fs_restore(path: u64, path_len: u64, lsn: u64) -> u64 {
+    syscall3(SYS_FS_RESTORE, path, path_len, lsn)
+}
+
+/// `SYS_FS_WATCH(flags) -> fd | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fs_watch(flags: u64) -> u64 {
+    syscall1(SYS_FS_WATCH, flags)
+}
+
+/// `SYS_FS_WATCH_ADD(fd, path_ptr, path_len, mask) -> wd | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fs_watch_add(fd: u64, path: u64, path_len: u64, mask: u64) -> u64 {
+    syscall4(SYS_FS_WATCH_ADD, fd, path, path_len, mask)
+}
+
+/// `SYS_FS_WATCH_RM(fd, wd) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fs_watch_rm(fd: u64, wd: u64) -> u64 {
+    syscall2(SYS_FS_WATCH_RM, fd, wd)
+}
+
+/// `SYS_STATFS(path_ptr, path_len, buf) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_statfs(path: u64, path_len: u64, buf: u64) -> u64 {
+    syscall3(SYS_STATFS, path, path_len, buf)
+}
+
+/// `SYS_FSTATFS(fd, buf) -> 0 | -errno`.
+#[inline(always)]
+pub unsafe fn sys_fstatfs(fd: u64, buf: u64) -> u64 {
+    syscall2(SYS_FSTATFS, fd, buf)
+}
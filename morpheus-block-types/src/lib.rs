@@ -107,6 +107,24 @@ impl RawBlockDevice {
     pub fn total_bytes(&self) -> u64 {
         self.sectors * self.sector_size as u64
     }
+
+    /// A second handle on the same driver context, for code that reaches a
+    /// registered device while the owner's handle is borrowed elsewhere.
+    ///
+    /// # Safety
+    ///
+    /// The alias must not be used after `self`'s context is freed, nor
+    /// concurrently with I/O through `self` or any other alias.
+    pub unsafe fn alias(&self) -> Self {
+        Self {
+            ctx: self.ctx,
+            sectors: self.sectors,
+            sector_size: self.sector_size,
+            read_fn: self.read_fn,
+            write_fn: self.write_fn,
+            flush_fn: self.flush_fn,
+        }
+    }
 }
 
 impl BlockIo for RawBlockDevice {
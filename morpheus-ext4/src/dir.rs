@@ -0,0 +1,166 @@
+//! Directory parsing: linear entry blocks and htree index nodes.
+//!
+//! An htree directory stays readable as a linear one: its root block holds
+//! `.` and a `..` whose record spans the index, and interior index blocks are
+//! one empty record. So listing always walks entry blocks; only lookups use
+//! the index.
+
+use crate::error::Ext4Error;
+
+fn rd16(b: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes([b[off], b[off + 1]])
+}
+fn rd32(b: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([b[off], b[off + 1], b[off + 2], b[off + 3]])
+}
+
+/// One live entry: inode, name, and `FT_*` type (0 without
+/// `INCOMPAT_FILETYPE`).
+pub struct RawEntry<'a> {
+    pub ino: u32,
+    pub name: &'a [u8],
+    pub file_type: u8,
+}
+
+/// Walks the records of one entry block (or inline region), yielding live
+/// entries. Deleted records (inode 0) and checksum tails are skipped; a
+/// record that does not fit ends the walk with `Corrupt`.
+pub struct Entries<'a> {
+    buf: &'a [u8],
+    at: usize,
+    filetype: bool,
+}
+
+pub fn entries(buf: &[u8], filetype: bool) -> Entries<'_> {
+    Entries {
+        buf,
+        at: 0,
+        filetype,
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<RawEntry<'a>, Ext4Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.at + 8 <= self.buf.len() {
+            let rec = &self.buf[self.at..];
+            let raw_len = rd16(rec, 4);
+            // 64 KiB blocks store a full-block record length as 0 or 0xFFFF.
+            let rec_len = match raw_len {
+                0 | 0xFFFF if self.buf.len() == 65536 => 65536,
+                _ => (raw_len & 0xFFFC) as usize | ((raw_len & 3) as usize) << 16,
+            };
+            let (name_len, file_type) = if self.filetype {
+                (rec[6] as usize, rec[7])
+            } else {
+                (rd16(rec, 6) as usize, 0)
+            };
+            if rec_len < 8 || rec_len > rec.len() || 8 + name_len > rec_len {
+                self.at = self.buf.len();
+                return Some(Err(Ext4Error::Corrupt));
+            }
+            self.at += rec_len;
+            let ino = rd32(rec, 0);
+            if ino != 0 && name_len != 0 {
+                return Some(Ok(RawEntry {
+                    ino,
+                    name: &rec[8..8 + name_len],
+                    file_type,
+                }));
+            }
+        }
+        None
+    }
+}
+
+/// `(inode, file type)` of `name` in one entry block.
+pub fn find(buf: &[u8], filetype: bool, name: &[u8]) -> Result<Option<(u32, u8)>, Ext4Error> {
+    for e in entries(buf, filetype) {
+        let e = e?;
+        if e.name == name {
+            return Ok(Some((e.ino, e.file_type)));
+        }
+    }
+    Ok(None)
+}
+
+/// The `dx_entry` array of an htree index node. Entry 0 carries the
+/// count/limit pair where its hash would be; its hash is implicitly 0.
+pub struct DxEntries<'a> {
+    raw: &'a [u8],
+    pub count: usize,
+}
+
+/// `dx_entry` block numbers are logical blocks of the directory; the top bits
+/// are reserved.
+const DX_BLOCK_MASK: u32 = 0x0FFF_FFFF;
+
+impl<'a> DxEntries<'a> {
+    fn parse(block: &'a [u8], at: usize) -> Option<DxEntries<'a>> {
+        let head = block.get(at..at + 8)?;
+        let limit = rd16(head, 0) as usize;
+        let count = rd16(head, 2) as usize;
+        if count == 0 || count > limit || at + limit * 8 > block.len() {
+            return None;
+        }
+        Some(DxEntries {
+            raw: &block[at..at + count * 8],
+            count,
+        })
+    }
+
+    pub fn hash(&self, i: usize) -> u32 {
+        if i == 0 {
+            0
+        } else {
+            rd32(self.raw, i * 8)
+        }
+    }
+
+    pub fn block(&self, i: usize) -> u32 {
+        rd32(self.raw, i * 8 + 4) & DX_BLOCK_MASK
+    }
+
+    /// Index of the last entry whose hash is at most `hash`.
+    pub fn lookup(&self, hash: u32) -> usize {
+        let (mut lo, mut hi) = (1, self.count);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.hash(mid) <= hash {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo - 1
+    }
+}
+
+/// The htree root: `(hash version, indirect levels, entries)`, or `None` if
+/// block 0 does not hold one this reader understands.
+pub fn dx_root(block: &[u8]) -> Option<(u8, u8, DxEntries<'_>)> {
+    let info = block.get(0x18..0x20)?;
+    let (reserved, hash_version, info_len, levels) = (rd32(info, 0), info[4], info[5], info[6]);
+    if reserved != 0 || info_len != 8 {
+        return None;
+    }
+    Some((
+        hash_version,
+        levels,
+        DxEntries::parse(block, 0x18 + info_len as usize)?,
+    ))
+}
+
+/// An interior htree node: one empty record, then the entries.
+pub fn dx_node(block: &[u8]) -> Option<DxEntries<'_>> {
+    if block.len() < 8 || rd32(block, 0) != 0 {
+        return None;
+    }
+    DxEntries::parse(block, 8)
+}
+
+/// `.` and `..` are listed by no reader call.
+pub fn is_dot(name: &[u8]) -> bool {
+    name == b"." || name == b".."
+}
@@ -0,0 +1,36 @@
+//! ext2/3/4 engine error type.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ext4Error {
+    IoRead,
+
+    /// Superblock magic absent or revision unknown.
+    NotExt,
+    /// Block size, group geometry or inode size out of supported range.
+    BadGeometry,
+    /// Filesystem block size is not a multiple of the device's.
+    InvalidBlockSize,
+    /// An incompat feature this reader cannot honour (compression, external
+    /// journal device, dirdata), or an encrypted inode.
+    Unsupported,
+
+    NotFound,
+    NotADirectory,
+    IsADirectory,
+    NotASymlink,
+    PathTooLong,
+    PathInvalid,
+
+    /// An extent tree, block map, directory block or inode number that does
+    /// not hold together.
+    Corrupt,
+    InvalidOffset,
+}
+
+// `gpt_disk_io::BlockIo::Error` requires Display, so a BlockIo whose Error is
+// Ext4Error (e.g. a RAM-backed device) needs this impl.
+impl core::fmt::Display for Ext4Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self, f)
+    }
+}
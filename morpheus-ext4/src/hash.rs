@@ -0,0 +1,167 @@
+//! htree name hashes (the kernel's `fs/ext4/hash.c`): legacy, half-MD4 and
+//! TEA, each in a signed- and an unsigned-char flavour.
+
+/// `dx_root_info.hash_version` values.
+pub const DX_HASH_LEGACY: u8 = 0;
+pub const DX_HASH_HALF_MD4: u8 = 1;
+pub const DX_HASH_TEA: u8 = 2;
+/// The unsigned flavours; a volume with `FLAGS_UNSIGNED_HASH` stores the
+/// signed number and means this one.
+pub const DX_HASH_LEGACY_UNSIGNED: u8 = 3;
+pub const DX_HASH_HALF_MD4_UNSIGNED: u8 = 4;
+pub const DX_HASH_TEA_UNSIGNED: u8 = 5;
+
+/// The seed used when the superblock's is all zero.
+const DEFAULT_SEED: [u32; 4] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476];
+
+/// Largest major hash; it marks end-of-directory for 32-bit readdir cookies.
+const HTREE_EOF_32BIT: u32 = 0x7FFF_FFFF;
+
+fn char_val(c: u8, signed: bool) -> u32 {
+    if signed {
+        c as i8 as i32 as u32
+    } else {
+        c as u32
+    }
+}
+
+fn dx_hack_hash(name: &[u8], signed: bool) -> u32 {
+    let (mut hash0, mut hash1) = (0x12A3_FE2Du32, 0x37AB_E8F9u32);
+    for &c in name {
+        let mut hash = hash1.wrapping_add(hash0 ^ char_val(c, signed).wrapping_mul(7_152_373));
+        if hash & 0x8000_0000 != 0 {
+            hash = hash.wrapping_sub(0x7FFF_FFFF);
+        }
+        hash1 = hash0;
+        hash0 = hash;
+    }
+    hash0 << 1
+}
+
+/// Pack up to `out.len() * 4` bytes of `msg` into words, padding with the
+/// length of all of `msg`, as `str2hashbuf` does.
+fn str2hashbuf(msg: &[u8], out: &mut [u32], signed: bool) {
+    let len = msg.len() as u32;
+    let mut pad = len | (len << 8);
+    pad |= pad << 16;
+    let mut val = pad;
+    let take = out.len() * 4;
+    let mut words = out.iter_mut();
+    for (i, &c) in msg.iter().take(take).enumerate() {
+        val = char_val(c, signed).wrapping_add(val << 8);
+        if i % 4 == 3 {
+            if let Some(w) = words.next() {
+                *w = val;
+            }
+            val = pad;
+        }
+    }
+    if let Some(w) = words.next() {
+        *w = val;
+    }
+    for w in words {
+        *w = pad;
+    }
+}
+
+fn half_md4_transform(buf: &mut [u32; 4], input: &[u32; 8]) {
+    const K2: u32 = 0x5A82_7999;
+    const K3: u32 = 0x6ED9_EBA1;
+    let f = |x: u32, y: u32, z: u32| z ^ (x & (y ^ z));
+    let g = |x: u32, y: u32, z: u32| (x & y).wrapping_add((x ^ y) & z);
+    let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+    let [mut a, mut b, mut c, mut d] = *buf;
+    macro_rules! round {
+        ($f:expr, $a:ident, $b:ident, $c:ident, $d:ident, $x:expr, $s:expr) => {
+            $a = $a
+                .wrapping_add($f($b, $c, $d))
+                .wrapping_add($x)
+                .rotate_left($s);
+        };
+    }
+    round!(f, a, b, c, d, input[0], 3);
+    round!(f, d, a, b, c, input[1], 7);
+    round!(f, c, d, a, b, input[2], 11);
+    round!(f, b, c, d, a, input[3], 19);
+    round!(f, a, b, c, d, input[4], 3);
+    round!(f, d, a, b, c, input[5], 7);
+    round!(f, c, d, a, b, input[6], 11);
+    round!(f, b, c, d, a, input[7], 19);
+
+    round!(g, a, b, c, d, input[1].wrapping_add(K2), 3);
+    round!(g, d, a, b, c, input[3].wrapping_add(K2), 5);
+    round!(g, c, d, a, b, input[5].wrapping_add(K2), 9);
+    round!(g, b, c, d, a, input[7].wrapping_add(K2), 13);
+    round!(g, a, b, c, d, input[0].wrapping_add(K2), 3);
+    round!(g, d, a, b, c, input[2].wrapping_add(K2), 5);
+    round!(g, c, d, a, b, input[4].wrapping_add(K2), 9);
+    round!(g, b, c, d, a, input[6].wrapping_add(K2), 13);
+
+    round!(h, a, b, c, d, input[3].wrapping_add(K3), 3);
+    round!(h, d, a, b, c, input[7].wrapping_add(K3), 9);
+    round!(h, c, d, a, b, input[2].wrapping_add(K3), 11);
+    round!(h, b, c, d, a, input[6].wrapping_add(K3), 15);
+    round!(h, a, b, c, d, input[1].wrapping_add(K3), 3);
+    round!(h, d, a, b, c, input[5].wrapping_add(K3), 9);
+    round!(h, c, d, a, b, input[0].wrapping_add(K3), 11);
+    round!(h, b, c, d, a, input[4].wrapping_add(K3), 15);
+
+    buf[0] = buf[0].wrapping_add(a);
+    buf[1] = buf[1].wrapping_add(b);
+    buf[2] = buf[2].wrapping_add(c);
+    buf[3] = buf[3].wrapping_add(d);
+}
+
+fn tea_transform(buf: &mut [u32; 4], input: &[u32; 4]) {
+    const DELTA: u32 = 0x9E37_79B9;
+    let (mut sum, mut b0, mut b1) = (0u32, buf[0], buf[1]);
+    let [a, b, c, d] = *input;
+    for _ in 0..16 {
+        sum = sum.wrapping_add(DELTA);
+        b0 = b0.wrapping_add(
+            (b1 << 4).wrapping_add(a) ^ b1.wrapping_add(sum) ^ (b1 >> 5).wrapping_add(b),
+        );
+        b1 = b1.wrapping_add(
+            (b0 << 4).wrapping_add(c) ^ b0.wrapping_add(sum) ^ (b0 >> 5).wrapping_add(d),
+        );
+    }
+    buf[0] = buf[0].wrapping_add(b0);
+    buf[1] = buf[1].wrapping_add(b1);
+}
+
+/// `(major, minor)` hash of `name` under `version`, or `None` for a version
+/// this reader does not know (SipHash, used only by casefolded directories).
+pub fn dx_hash(name: &[u8], version: u8, seed: &[u32; 4]) -> Option<(u32, u32)> {
+    let mut buf = if seed.iter().any(|&w| w != 0) {
+        *seed
+    } else {
+        DEFAULT_SEED
+    };
+    let (hash, minor) = match version {
+        DX_HASH_LEGACY | DX_HASH_LEGACY_UNSIGNED => {
+            (dx_hack_hash(name, version == DX_HASH_LEGACY), 0)
+        },
+        DX_HASH_HALF_MD4 | DX_HASH_HALF_MD4_UNSIGNED => {
+            let mut input = [0u32; 8];
+            for at in (0..name.len()).step_by(32) {
+                str2hashbuf(&name[at..], &mut input, version == DX_HASH_HALF_MD4);
+                half_md4_transform(&mut buf, &input);
+            }
+            (buf[1], buf[2])
+        },
+        DX_HASH_TEA | DX_HASH_TEA_UNSIGNED => {
+            let mut input = [0u32; 4];
+            for at in (0..name.len()).step_by(16) {
+                str2hashbuf(&name[at..], &mut input, version == DX_HASH_TEA);
+                tea_transform(&mut buf, &input);
+            }
+            (buf[0], buf[1])
+        },
+        _ => return None,
+    };
+    let hash = hash & !1;
+    if hash == HTREE_EOF_32BIT << 1 {
+        return Some(((HTREE_EOF_32BIT - 1) << 1, minor));
+    }
+    Some((hash, minor))
+}
@@ -0,0 +1,167 @@
+//! Inode and extent-node parsing. Block lookups that need I/O live on the
+//! engine; this module only decodes bytes already read.
+
+use crate::error::Ext4Error;
+use crate::types::*;
+use alloc::vec::Vec;
+
+fn rd16(b: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes([b[off], b[off + 1]])
+}
+fn rd32(b: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([b[off], b[off + 1], b[off + 2], b[off + 3]])
+}
+
+/// `system.data`: where inline data past `i_block` lives.
+const XATTR_INDEX_SYSTEM: u8 = 7;
+const XATTR_MAGIC: u32 = 0xEA02_0000;
+
+#[derive(Debug, Clone)]
+pub struct Inode {
+    pub mode: u16,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub mtime: u32,
+    pub links: u16,
+    pub flags: u32,
+    /// `i_block`: block map, extent root, or inline data.
+    pub block: [u8; I_BLOCK_LEN],
+    /// The in-inode extended attribute area (past `i_extra_isize`), empty for
+    /// 128-byte inodes.
+    pub ibody: Vec<u8>,
+}
+
+impl Inode {
+    /// Decode one on-disk inode. `largedir` lets a directory's size use its
+    /// high word.
+    pub fn parse(raw: &[u8], largedir: bool) -> Result<Inode, Ext4Error> {
+        if raw.len() < 128 {
+            return Err(Ext4Error::Corrupt);
+        }
+        let mode = rd16(raw, 0x00);
+        let mut size = rd32(raw, 0x04) as u64;
+        if largedir || mode & S_IFMT == S_IFREG {
+            size |= (rd32(raw, 0x6C) as u64) << 32;
+        }
+        let mut block = [0u8; I_BLOCK_LEN];
+        block.copy_from_slice(&raw[0x28..0x28 + I_BLOCK_LEN]);
+        let ibody = if raw.len() > 128 {
+            let extra = rd16(raw, 0x80) as usize;
+            raw.get(128 + extra..)
+                .map(|b| b.to_vec())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        Ok(Inode {
+            mode,
+            uid: rd16(raw, 0x02) as u32 | (rd16(raw, 0x78) as u32) << 16,
+            gid: rd16(raw, 0x18) as u32 | (rd16(raw, 0x7A) as u32) << 16,
+            size,
+            mtime: rd32(raw, 0x10),
+            links: rd16(raw, 0x1A),
+            flags: rd32(raw, 0x20),
+            block,
+            ibody,
+        })
+    }
+
+    pub fn file_type(&self) -> FileType {
+        FileType::from_mode(self.mode)
+    }
+
+    pub fn has_flag(&self, fl: u32) -> bool {
+        self.flags & fl != 0
+    }
+
+    /// A symlink whose target sits in `i_block` itself.
+    pub fn is_fast_symlink(&self) -> bool {
+        self.file_type() == FileType::Symlink
+            && self.size < I_BLOCK_LEN as u64
+            && !self.has_flag(INODE_EXTENTS_FL | INODE_INLINE_DATA_FL)
+    }
+
+    /// The `system.data` attribute value: inline data beyond `i_block`.
+    /// Empty when absent.
+    pub fn inline_tail(&self) -> &[u8] {
+        let b = &self.ibody;
+        if b.len() < 4 || rd32(b, 0) != XATTR_MAGIC {
+            return &[];
+        }
+        let base = 4;
+        let mut at = base;
+        while at + 16 <= b.len() && rd32(b, at) != 0 {
+            let name_len = b[at] as usize;
+            let index = b[at + 1];
+            let offs = rd16(b, at + 2) as usize;
+            let inum = rd32(b, at + 4);
+            let len = rd32(b, at + 8) as usize;
+            let name = b.get(at + 16..at + 16 + name_len).unwrap_or(&[]);
+            if index == XATTR_INDEX_SYSTEM && name == b"data" && inum == 0 {
+                return b.get(base + offs..base + offs + len).unwrap_or(&[]);
+            }
+            at += (16 + name_len + 3) & !3;
+        }
+        &[]
+    }
+}
+
+/// An extent tree node header.
+pub struct ExtentHeader {
+    pub entries: u16,
+    pub depth: u16,
+}
+
+impl ExtentHeader {
+    /// Check the header of `node` and that its entries fit in it.
+    pub fn parse(node: &[u8]) -> Result<ExtentHeader, Ext4Error> {
+        if node.len() < 12 || rd16(node, 0) != EXTENT_MAGIC {
+            return Err(Ext4Error::Corrupt);
+        }
+        let entries = rd16(node, 2);
+        let max = rd16(node, 4);
+        let depth = rd16(node, 6);
+        if entries > max || 12 + max as usize * 12 > node.len() || depth > EXTENT_MAX_DEPTH {
+            return Err(Ext4Error::Corrupt);
+        }
+        Ok(ExtentHeader { entries, depth })
+    }
+}
+
+/// A leaf extent: `len` logical blocks from `lblk` stored from `pblk`.
+pub struct Extent {
+    pub lblk: u32,
+    pub len: u32,
+    pub pblk: u64,
+    /// Allocated but unwritten: reads as zeros.
+    pub unwritten: bool,
+}
+
+/// Entry `i` of a leaf node.
+pub fn extent_at(node: &[u8], i: usize) -> Extent {
+    let e = &node[12 + i * 12..24 + i * 12];
+    let raw_len = rd16(e, 4);
+    let (len, unwritten) = if raw_len > EXTENT_INIT_MAX {
+        (raw_len - EXTENT_INIT_MAX, true)
+    } else {
+        (raw_len, false)
+    };
+    Extent {
+        lblk: rd32(e, 0),
+        len: len as u32,
+        pblk: (rd16(e, 6) as u64) << 32 | rd32(e, 8) as u64,
+        unwritten,
+    }
+}
+
+/// Entry `i` of an index node: `(first logical block, child block)`.
+pub fn index_at(node: &[u8], i: usize) -> (u32, u64) {
+    let e = &node[12 + i * 12..24 + i * 12];
+    (rd32(e, 0), (rd16(e, 8) as u64) << 32 | rd32(e, 4) as u64)
+}
+
+/// The 32-bit block pointer `i` of `i_block` or an indirect block.
+pub fn ptr_at(b: &[u8], i: usize) -> u32 {
+    rd32(b, i * 4)
+}
@@ -0,0 +1,609 @@
+//! Read-only ext2/ext3/ext4 filesystem engine for MorpheusX.
+//!
+//! Pure engine: no knowledge of the kernel VFS, mount table, or `FsBackend`.
+//! Every call borrows a `gpt_disk_io::BlockIo` for its duration, so the
+//! storage adapter needs no device bridge. Nothing is ever written: the
+//! journal is not replayed, so a volume still flagged for recovery is read as
+//! its last checkpoint left it (`needs_recovery`).
+//!
+//! Invariants:
+//! - All block math is partition-relative and offset by `lba_start` before
+//!   touching the device.
+//! - Extent trees are followed at most `EXTENT_MAX_DEPTH` levels and htree
+//!   indexes at most three, so a corrupt pointer cannot loop.
+//! - Symlinks are reported, never followed; the VFS resolves them.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod dir;
+pub mod error;
+pub mod hash;
+pub mod inode;
+pub mod superblock;
+pub mod types;
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use error::Ext4Error;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+use inode::{ExtentHeader, Inode};
+use superblock::Superblock;
+use types::*;
+
+/// Most filesystem blocks one device request reads while copying file data.
+const READ_RUN_BLOCKS: u64 = 32;
+
+/// Where a run of logical blocks lives.
+enum Run {
+    /// `n` blocks with nothing allocated, or allocated but unwritten: zeros.
+    Hole(u64),
+    /// `n` blocks stored contiguously from the physical block.
+    Data(u64, u64),
+}
+
+pub struct Ext4Fs {
+    sb: Superblock,
+    /// Partition start, in absolute device blocks.
+    lba_start: u64,
+    /// Device blocks per filesystem block.
+    scale: u64,
+    /// The group descriptor block read last; inode lookups cluster by group.
+    desc_cache: Option<(u64, Vec<u8>)>,
+}
+
+impl Ext4Fs {
+    /// Mount: read and check the superblock at byte 1024 of the partition.
+    /// The filesystem block size must be a multiple of the device's.
+    pub fn mount<B: BlockIo>(dev: &mut B, lba_start: u64) -> Result<Self, Ext4Error> {
+        let dev_bs = dev.block_size().to_u64();
+        if dev_bs == 0 {
+            return Err(Ext4Error::InvalidBlockSize);
+        }
+        let first = SUPERBLOCK_OFFSET / dev_bs;
+        let end = (SUPERBLOCK_OFFSET + SUPERBLOCK_SIZE as u64).div_ceil(dev_bs);
+        let mut buf = vec![0u8; ((end - first) * dev_bs) as usize];
+        dev.read_blocks(Lba(lba_start + first), &mut buf)
+            .map_err(|_| Ext4Error::IoRead)?;
+        let at = (SUPERBLOCK_OFFSET - first * dev_bs) as usize;
+        let sb = Superblock::parse(&buf[at..at + SUPERBLOCK_SIZE])?;
+        if sb.block_size as u64 % dev_bs != 0 {
+            return Err(Ext4Error::InvalidBlockSize);
+        }
+        Ok(Self {
+            scale: sb.block_size as u64 / dev_bs,
+            sb,
+            lba_start,
+            desc_cache: None,
+        })
+    }
+
+    pub fn superblock(&self) -> &Superblock {
+        &self.sb
+    }
+
+    /// The journal holds transactions not yet checkpointed; what this reader
+    /// sees may be older than what Linux last wrote.
+    pub fn needs_recovery(&self) -> bool {
+        self.sb.has_incompat(INCOMPAT_RECOVER)
+    }
+
+    fn read_blocks<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        block: u64,
+        dst: &mut [u8],
+    ) -> Result<(), Ext4Error> {
+        if block >= self.sb.blocks_count {
+            return Err(Ext4Error::Corrupt);
+        }
+        let lba = block
+            .checked_mul(self.scale)
+            .and_then(|b| b.checked_add(self.lba_start))
+            .ok_or(Ext4Error::InvalidOffset)?;
+        dev.read_blocks(Lba(lba), dst)
+            .map_err(|_| Ext4Error::IoRead)
+    }
+
+    fn read_block<B: BlockIo>(&self, dev: &mut B, block: u64) -> Result<Vec<u8>, Ext4Error> {
+        let mut buf = vec![0u8; self.sb.block_size as usize];
+        self.read_blocks(dev, block, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Inode table block of group `g`.
+    fn inode_table<B: BlockIo>(&mut self, dev: &mut B, g: u32) -> Result<u64, Ext4Error> {
+        let block = self.sb.desc_block(g);
+        if self.desc_cache.as_ref().map_or(true, |(b, _)| *b != block) {
+            self.desc_cache = Some((block, self.read_block(dev, block)?));
+        }
+        let per_block = self.sb.block_size / self.sb.desc_size;
+        let at = ((g % per_block) * self.sb.desc_size) as usize;
+        let desc = &self.desc_cache.as_ref().ok_or(Ext4Error::IoRead)?.1;
+        Ok(superblock::desc_inode_table(
+            &self.sb,
+            &desc[at..at + self.sb.desc_size as usize],
+        ))
+    }
+
+    fn read_inode<B: BlockIo>(&mut self, dev: &mut B, ino: u32) -> Result<Inode, Ext4Error> {
+        if ino == 0 || ino > self.sb.inodes_count {
+            return Err(Ext4Error::Corrupt);
+        }
+        let g = (ino - 1) / self.sb.inodes_per_group;
+        let index = ((ino - 1) % self.sb.inodes_per_group) as u64;
+        let table = self.inode_table(dev, g)?;
+        let byte = index * self.sb.inode_size as u64;
+        let bs = self.sb.block_size as u64;
+        let block = self.read_block(dev, table + byte / bs)?;
+        let at = (byte % bs) as usize;
+        Inode::parse(
+            &block[at..at + self.sb.inode_size as usize],
+            self.sb.has_incompat(INCOMPAT_LARGEDIR),
+        )
+    }
+
+    /// Where logical block `lblk` of `inode` lives, and how many blocks from
+    /// it share that placement.
+    fn map<B: BlockIo>(&self, dev: &mut B, inode: &Inode, lblk: u64) -> Result<Run, Ext4Error> {
+        if inode.has_flag(INODE_EXTENTS_FL) {
+            self.map_extent(dev, inode, lblk)
+        } else {
+            self.map_indirect(dev, inode, lblk)
+        }
+    }
+
+    fn map_extent<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        inode: &Inode,
+        lblk: u64,
+    ) -> Result<Run, Ext4Error> {
+        // Logical block numbers are 32-bit in an extent tree.
+        let Ok(lblk) = u32::try_from(lblk) else {
+            return Ok(Run::Hole(1));
+        };
+        let mut node = inode.block.to_vec();
+        let mut depth = ExtentHeader::parse(&node)?.depth;
+        loop {
+            let head = ExtentHeader::parse(&node)?;
+            if head.depth != depth {
+                return Err(Ext4Error::Corrupt);
+            }
+            let n = head.entries as usize;
+            if depth == 0 {
+                let mut next = u32::MAX;
+                for i in 0..n {
+                    let e = inode::extent_at(&node, i);
+                    if lblk < e.lblk {
+                        next = next.min(e.lblk);
+                    } else if lblk - e.lblk < e.len {
+                        let skip = lblk - e.lblk;
+                        let len = (e.len - skip) as u64;
+                        return Ok(if e.unwritten {
+                            Run::Hole(len)
+                        } else {
+                            Run::Data(e.pblk + skip as u64, len)
+                        });
+                    }
+                }
+                return Ok(Run::Hole((next - lblk).max(1) as u64));
+            }
+            let mut child = None;
+            for i in 0..n {
+                let (first, block) = inode::index_at(&node, i);
+                if first > lblk {
+                    if child.is_none() {
+                        return Ok(Run::Hole((first - lblk) as u64));
+                    }
+                    break;
+                }
+                child = Some(block);
+            }
+            let Some(child) = child else {
+                return Ok(Run::Hole(1));
+            };
+            node = self.read_block(dev, child)?;
+            depth -= 1;
+        }
+    }
+
+    fn map_indirect<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        inode: &Inode,
+        lblk: u64,
+    ) -> Result<Run, Ext4Error> {
+        let per = (self.sb.block_size / 4) as u64;
+        let found = |b: u32| {
+            if b == 0 {
+                Run::Hole(1)
+            } else {
+                Run::Data(b as u64, 1)
+            }
+        };
+        if lblk < N_DIRECT {
+            return Ok(found(inode::ptr_at(&inode.block, lblk as usize)));
+        }
+        // Which tree (single, double, triple) and the index within it.
+        let mut rel = lblk - N_DIRECT;
+        let mut span = per;
+        let mut levels = 1;
+        while rel >= span {
+            rel -= span;
+            span = span.saturating_mul(per);
+            levels += 1;
+            if levels > 3 {
+                return Ok(Run::Hole(1));
+            }
+        }
+        let mut ptr = inode::ptr_at(&inode.block, N_DIRECT as usize + levels - 1);
+        for level in (0..levels).rev() {
+            if ptr == 0 {
+                return Ok(Run::Hole(1));
+            }
+            let block = self.read_block(dev, ptr as u64)?;
+            let stride = per.pow(level as u32);
+            ptr = inode::ptr_at(&block, ((rel / stride) % per) as usize);
+        }
+        Ok(found(ptr))
+    }
+
+    /// Copy inline data at `offset` into `buf`.
+    fn read_inline(inode: &Inode, offset: u64, buf: &mut [u8]) -> usize {
+        let head = &inode.block[..];
+        let tail = inode.inline_tail();
+        let size = inode.size.min((head.len() + tail.len()) as u64);
+        if offset >= size {
+            return 0;
+        }
+        let n = ((size - offset) as usize).min(buf.len());
+        for (i, b) in buf[..n].iter_mut().enumerate() {
+            let at = offset as usize + i;
+            *b = if at < head.len() {
+                head[at]
+            } else {
+                tail[at - head.len()]
+            };
+        }
+        n
+    }
+
+    /// Read up to `buf.len()` bytes of `inode` at `offset`; 0 at EOF.
+    fn read_data<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        inode: &Inode,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Ext4Error> {
+        if inode.has_flag(INODE_ENCRYPT_FL) {
+            return Err(Ext4Error::Unsupported);
+        }
+        if inode.has_flag(INODE_INLINE_DATA_FL) {
+            return Ok(Self::read_inline(inode, offset, buf));
+        }
+        if offset >= inode.size {
+            return Ok(0);
+        }
+        let want = ((inode.size - offset) as usize).min(buf.len());
+        let bs = self.sb.block_size as u64;
+        let mut done = 0usize;
+        let mut scratch = Vec::new();
+        while done < want {
+            let pos = offset + done as u64;
+            let within = (pos % bs) as usize;
+            let (n_blocks, data) = match self.map(dev, inode, pos / bs)? {
+                Run::Hole(n) => (n, None),
+                Run::Data(p, n) => (n.min(READ_RUN_BLOCKS), Some(p)),
+            };
+            let n = (n_blocks * bs - within as u64).min((want - done) as u64) as usize;
+            let dst = &mut buf[done..done + n];
+            match data {
+                None => dst.fill(0),
+                Some(p) => {
+                    let blocks = (within + n).div_ceil(bs as usize);
+                    scratch.resize(blocks * bs as usize, 0);
+                    self.read_blocks(dev, p, &mut scratch)?;
+                    dst.copy_from_slice(&scratch[within..within + n]);
+                },
+            }
+            done += n;
+        }
+        Ok(done)
+    }
+
+    /// Every entry-bearing region of a directory, in order: its blocks, or
+    /// for an inline directory the two inline areas.
+    fn dir_regions<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        inode: &Inode,
+        mut f: impl FnMut(&[u8]) -> Result<bool, Ext4Error>,
+    ) -> Result<(), Ext4Error> {
+        if inode.has_flag(INODE_ENCRYPT_FL) {
+            return Err(Ext4Error::Unsupported);
+        }
+        if inode.has_flag(INODE_INLINE_DATA_FL) {
+            // The first four bytes hold the parent's inode number.
+            if !f(&inode.block[4..])? {
+                return Ok(());
+            }
+            let tail = inode.inline_tail();
+            if !tail.is_empty() {
+                f(tail)?;
+            }
+            return Ok(());
+        }
+        let bs = self.sb.block_size as u64;
+        let blocks = inode.size.div_ceil(bs);
+        let mut lblk = 0;
+        while lblk < blocks {
+            match self.map(dev, inode, lblk)? {
+                Run::Hole(n) => lblk += n,
+                Run::Data(p, n) => {
+                    for i in 0..n.min(blocks - lblk) {
+                        let block = self.read_block(dev, p + i)?;
+                        if !f(&block)? {
+                            return Ok(());
+                        }
+                    }
+                    lblk += n;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn filetype(&self) -> bool {
+        self.sb.has_incompat(INCOMPAT_FILETYPE)
+    }
+
+    /// Find `name` in directory `inode` by walking every entry.
+    fn lookup_linear<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        inode: &Inode,
+        name: &[u8],
+    ) -> Result<Option<u32>, Ext4Error> {
+        let filetype = self.filetype();
+        let mut found = None;
+        self.dir_regions(dev, inode, |region| {
+            found = dir::find(region, filetype, name)?.map(|(ino, _)| ino);
+            Ok(found.is_none())
+        })?;
+        Ok(found)
+    }
+
+    /// Read logical block `lblk` of directory `inode`.
+    fn dir_block<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        inode: &Inode,
+        lblk: u64,
+    ) -> Result<Vec<u8>, Ext4Error> {
+        match self.map(dev, inode, lblk)? {
+            Run::Data(p, _) => self.read_block(dev, p),
+            Run::Hole(_) => Err(Ext4Error::Corrupt),
+        }
+    }
+
+    /// Find `name` through the htree index of `inode`. `None` when the index
+    /// is not one this reader follows, so the caller falls back to a linear
+    /// walk.
+    fn lookup_htree<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        inode: &Inode,
+        name: &[u8],
+    ) -> Result<Option<Option<u32>>, Ext4Error> {
+        let root = self.dir_block(dev, inode, 0)?;
+        let Some((mut version, levels, entries)) = dir::dx_root(&root) else {
+            return Ok(None);
+        };
+        let max_levels = if self.sb.has_incompat(INCOMPAT_LARGEDIR) {
+            3
+        } else {
+            2
+        };
+        if levels >= max_levels {
+            return Ok(None);
+        }
+        if version <= hash::DX_HASH_TEA && self.sb.flags & FLAGS_UNSIGNED_HASH != 0 {
+            version += hash::DX_HASH_LEGACY_UNSIGNED;
+        }
+        let Some((target, _)) = hash::dx_hash(name, version, &self.sb.hash_seed) else {
+            return Ok(None);
+        };
+
+        let mut i = entries.lookup(target);
+        let mut leaf = entries.block(i);
+        // The index node the leaf was chosen from, for collision runs.
+        let mut node = root.clone();
+        let mut node_at_root = true;
+        for _ in 0..levels {
+            node = self.dir_block(dev, inode, leaf as u64)?;
+            node_at_root = false;
+            let Some(entries) = dir::dx_node(&node) else {
+                return Ok(None);
+            };
+            i = entries.lookup(target);
+            leaf = entries.block(i);
+        }
+        let filetype = self.filetype();
+        loop {
+            let block = self.dir_block(dev, inode, leaf as u64)?;
+            if let Some((ino, _)) = dir::find(&block, filetype, name)? {
+                return Ok(Some(Some(ino)));
+            }
+            // Names sharing a hash may spill into the next leaf, which the
+            // index then marks with the low hash bit.
+            let entries = if node_at_root {
+                dir::dx_root(&node).map(|(_, _, e)| e)
+            } else {
+                dir::dx_node(&node)
+            };
+            let Some(entries) = entries else {
+                return Ok(None);
+            };
+            i += 1;
+            if i >= entries.count || entries.hash(i) & !1 != target {
+                return Ok(Some(None));
+            }
+            leaf = entries.block(i);
+        }
+    }
+
+    fn lookup<B: BlockIo>(
+        &self,
+        dev: &mut B,
+        dir_inode: &Inode,
+        name: &[u8],
+    ) -> Result<Option<u32>, Ext4Error> {
+        let indexed = dir_inode.has_flag(INODE_INDEX_FL)
+            && !dir_inode.has_flag(INODE_INLINE_DATA_FL | INODE_CASEFOLD_FL)
+            && self.sb.feature_compat & COMPAT_DIR_INDEX != 0;
+        if indexed {
+            if let Some(found) = self.lookup_htree(dev, dir_inode, name)? {
+                return Ok(found);
+            }
+        }
+        self.lookup_linear(dev, dir_inode, name)
+    }
+
+    /// Resolve a `/`-separated absolute path to its inode number and inode.
+    /// Symlinks along the way are not followed.
+    fn resolve<B: BlockIo>(&mut self, dev: &mut B, path: &str) -> Result<(u32, Inode), Ext4Error> {
+        let mut ino = ROOT_INO;
+        let mut inode = self.read_inode(dev, ino)?;
+        for comp in path.split('/').filter(|s| !s.is_empty() && *s != ".") {
+            if comp == ".." {
+                return Err(Ext4Error::PathInvalid);
+            }
+            if comp.len() > NAME_MAX {
+                return Err(Ext4Error::PathTooLong);
+            }
+            if inode.file_type() != FileType::Directory {
+                return Err(Ext4Error::NotADirectory);
+            }
+            ino = self
+                .lookup(dev, &inode, comp.as_bytes())?
+                .ok_or(Ext4Error::NotFound)?;
+            inode = self.read_inode(dev, ino)?;
+        }
+        Ok((ino, inode))
+    }
+
+    pub fn stat<B: BlockIo>(&mut self, dev: &mut B, path: &str) -> Result<FileStat, Ext4Error> {
+        let (ino, inode) = self.resolve(dev, path)?;
+        Ok(stat_of(ino, &inode))
+    }
+
+    pub fn stat_ino<B: BlockIo>(&mut self, dev: &mut B, ino: u32) -> Result<FileStat, Ext4Error> {
+        let inode = self.read_inode(dev, ino)?;
+        Ok(stat_of(ino, &inode))
+    }
+
+    /// List a directory, `.` and `..` excluded. Names that are not UTF-8 are
+    /// listed lossily.
+    pub fn readdir<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        path: &str,
+    ) -> Result<Vec<DirEntry>, Ext4Error> {
+        let (_, inode) = self.resolve(dev, path)?;
+        if inode.file_type() != FileType::Directory {
+            return Err(Ext4Error::NotADirectory);
+        }
+        let filetype = self.filetype();
+        let mut found = Vec::new();
+        self.dir_regions(dev, &inode, |region| {
+            for e in dir::entries(region, filetype) {
+                let e = e?;
+                if !dir::is_dot(e.name) {
+                    found.push((e.ino, String::from_utf8_lossy(e.name).into_owned()));
+                }
+            }
+            Ok(true)
+        })?;
+        let mut out = Vec::with_capacity(found.len());
+        for (ino, name) in found {
+            let child = self.read_inode(dev, ino)?;
+            out.push(DirEntry {
+                name,
+                ino,
+                file_type: child.file_type(),
+                size: child.size,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Read up to `buf.len()` bytes of inode `ino` at `offset`. Returns bytes
+    /// copied (0 at EOF).
+    pub fn read<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        ino: u32,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Ext4Error> {
+        let inode = self.read_inode(dev, ino)?;
+        match inode.file_type() {
+            FileType::Directory => Err(Ext4Error::IsADirectory),
+            FileType::Regular | FileType::Symlink => self.read_data(dev, &inode, offset, buf),
+            FileType::Other => Err(Ext4Error::Unsupported),
+        }
+    }
+
+    /// Read up to `buf.len()` bytes of `path` at `offset`.
+    pub fn read_at<B: BlockIo>(
+        &mut self,
+        dev: &mut B,
+        path: &str,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Ext4Error> {
+        let (ino, _) = self.resolve(dev, path)?;
+        self.read(dev, ino, offset, buf)
+    }
+
+    /// Target of the symlink at `path`, stored verbatim.
+    pub fn readlink<B: BlockIo>(&mut self, dev: &mut B, path: &str) -> Result<String, Ext4Error> {
+        let (_, inode) = self.resolve(dev, path)?;
+        if inode.file_type() != FileType::Symlink {
+            return Err(Ext4Error::NotASymlink);
+        }
+        if inode.size > PATH_MAX as u64 {
+            return Err(Ext4Error::Corrupt);
+        }
+        let target = if inode.is_fast_symlink() {
+            inode.block[..inode.size as usize].to_vec()
+        } else {
+            let mut buf = vec![0u8; inode.size as usize];
+            let n = self.read_data(dev, &inode, 0, &mut buf)?;
+            buf.truncate(n);
+            buf
+        };
+        String::from_utf8(target).map_err(|_| Ext4Error::PathInvalid)
+    }
+}
+
+fn stat_of(ino: u32, inode: &Inode) -> FileStat {
+    FileStat {
+        ino,
+        file_type: inode.file_type(),
+        mode: inode.mode,
+        size: inode.size,
+        uid: inode.uid,
+        gid: inode.gid,
+        nlink: inode.links,
+        mtime: inode.mtime,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,164 @@
+//! Superblock + group descriptor parsing.
+
+use crate::error::Ext4Error;
+use crate::types::*;
+
+fn rd16(b: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes([b[off], b[off + 1]])
+}
+fn rd32(b: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([b[off], b[off + 1], b[off + 2], b[off + 3]])
+}
+
+/// The superblock fields the reader uses.
+#[derive(Debug, Clone)]
+pub struct Superblock {
+    pub inodes_count: u32,
+    pub blocks_count: u64,
+    pub free_blocks: u64,
+    pub first_data_block: u32,
+    pub block_size: u32,
+    pub blocks_per_group: u32,
+    pub inodes_per_group: u32,
+    /// 1 = cleanly unmounted, 2 = errors detected.
+    pub state: u16,
+    pub inode_size: u32,
+    pub feature_compat: u32,
+    pub feature_incompat: u32,
+    pub feature_ro_compat: u32,
+    pub volume_name: [u8; 16],
+    pub hash_seed: [u32; 4],
+    pub def_hash_version: u8,
+    /// Group descriptor size: 32, or `s_desc_size` under `INCOMPAT_64BIT`.
+    pub desc_size: u32,
+    pub first_meta_bg: u32,
+    pub flags: u32,
+}
+
+impl Superblock {
+    /// Parse the 1024-byte superblock. Rejects volumes using an incompat
+    /// feature outside `INCOMPAT_READABLE`.
+    pub fn parse(buf: &[u8]) -> Result<Superblock, Ext4Error> {
+        if buf.len() < SUPERBLOCK_SIZE || rd16(buf, 0x38) != EXT_MAGIC {
+            return Err(Ext4Error::NotExt);
+        }
+        let rev_level = rd32(buf, 0x4C);
+        if rev_level > 1 {
+            return Err(Ext4Error::NotExt);
+        }
+        let feature_incompat = if rev_level == 0 { 0 } else { rd32(buf, 0x60) };
+        if feature_incompat & !INCOMPAT_READABLE != 0 {
+            return Err(Ext4Error::Unsupported);
+        }
+        let is_64 = feature_incompat & INCOMPAT_64BIT != 0;
+
+        let log_block_size = rd32(buf, 0x18);
+        if log_block_size > 6 {
+            return Err(Ext4Error::BadGeometry);
+        }
+        let block_size = 1024u32 << log_block_size;
+        let inode_size = if rev_level == 0 {
+            128
+        } else {
+            rd16(buf, 0x58) as u32
+        };
+        if inode_size < 128 || !inode_size.is_power_of_two() || inode_size > block_size {
+            return Err(Ext4Error::BadGeometry);
+        }
+        let desc_size = if is_64 { rd16(buf, 0xFE) as u32 } else { 32 };
+        if desc_size < 32 || !desc_size.is_power_of_two() || desc_size > block_size {
+            return Err(Ext4Error::BadGeometry);
+        }
+        let blocks_per_group = rd32(buf, 0x20);
+        let inodes_per_group = rd32(buf, 0x28);
+        if blocks_per_group == 0 || inodes_per_group == 0 {
+            return Err(Ext4Error::BadGeometry);
+        }
+        let hi = |off: usize| {
+            if is_64 {
+                (rd32(buf, off) as u64) << 32
+            } else {
+                0
+            }
+        };
+
+        let mut volume_name = [0u8; 16];
+        volume_name.copy_from_slice(&buf[0x78..0x88]);
+        let mut hash_seed = [0u32; 4];
+        for (i, w) in hash_seed.iter_mut().enumerate() {
+            *w = rd32(buf, 0xEC + i * 4);
+        }
+        let sb = Superblock {
+            inodes_count: rd32(buf, 0x00),
+            blocks_count: rd32(buf, 0x04) as u64 | hi(0x150),
+            free_blocks: rd32(buf, 0x0C) as u64 | hi(0x158),
+            first_data_block: rd32(buf, 0x14),
+            block_size,
+            blocks_per_group,
+            inodes_per_group,
+            state: rd16(buf, 0x3A),
+            inode_size,
+            feature_compat: if rev_level == 0 { 0 } else { rd32(buf, 0x5C) },
+            feature_incompat,
+            feature_ro_compat: if rev_level == 0 { 0 } else { rd32(buf, 0x64) },
+            volume_name,
+            hash_seed,
+            def_hash_version: buf[0xFC],
+            desc_size,
+            first_meta_bg: rd32(buf, 0x104),
+            flags: rd32(buf, 0x160),
+        };
+        if sb.blocks_count <= sb.first_data_block as u64 {
+            return Err(Ext4Error::BadGeometry);
+        }
+        Ok(sb)
+    }
+
+    pub fn has_incompat(&self, bit: u32) -> bool {
+        self.feature_incompat & bit != 0
+    }
+
+    pub fn group_count(&self) -> u32 {
+        let data = self.blocks_count - self.first_data_block as u64;
+        data.div_ceil(self.blocks_per_group as u64) as u32
+    }
+
+    /// Whether group `g` carries a superblock backup (and so a descriptor
+    /// table copy) at its start.
+    pub fn group_has_super(&self, g: u32) -> bool {
+        if g <= 1 || self.feature_ro_compat & RO_COMPAT_SPARSE_SUPER == 0 {
+            return true;
+        }
+        [3u32, 5, 7].iter().any(|&base| {
+            let mut n = base;
+            while n < g {
+                n = n.saturating_mul(base);
+            }
+            n == g
+        })
+    }
+
+    /// Block holding the descriptor of group `g`. Under `META_BG`, groups past
+    /// `first_meta_bg` descriptor blocks keep theirs in the first group of
+    /// their meta group.
+    pub fn desc_block(&self, g: u32) -> u64 {
+        let per_block = self.block_size / self.desc_size;
+        let index = g / per_block;
+        if !self.has_incompat(INCOMPAT_META_BG) || index < self.first_meta_bg {
+            return self.first_data_block as u64 + 1 + index as u64;
+        }
+        let first = index * per_block;
+        let start = self.first_data_block as u64 + first as u64 * self.blocks_per_group as u64;
+        start + self.group_has_super(first) as u64
+    }
+}
+
+/// The inode table block of a group descriptor.
+pub fn desc_inode_table(sb: &Superblock, desc: &[u8]) -> u64 {
+    let lo = rd32(desc, 0x08) as u64;
+    if sb.desc_size >= 64 {
+        lo | (rd32(desc, 0x28) as u64) << 32
+    } else {
+        lo
+    }
+}
@@ -0,0 +1,728 @@
+//! Self-contained host tests against an in-memory ext4 image built byte-for-byte.
+
+use crate::error::Ext4Error;
+use crate::hash::*;
+use crate::types::*;
+use crate::*;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::{BlockSize, Lba};
+
+const BS: usize = 1024;
+const BLOCKS: usize = 512;
+const INODES: usize = 64;
+const INODE_SIZE: usize = 256;
+const INODE_TABLE: usize = 5; // boot, super, GDT, block bitmap, inode bitmap
+const FIRST_FREE: usize = INODE_TABLE + INODES * INODE_SIZE / BS;
+const SEED: [u32; 4] = [0x6745_2301, 0xEFCD_AB89, 0x0011_2233, 0x4455_6677];
+
+/// RAM-backed `BlockIo` for tests only.
+struct MemBio {
+    data: Vec<u8>,
+    block: usize,
+}
+
+impl BlockIo for MemBio {
+    type Error = Ext4Error;
+    fn block_size(&self) -> BlockSize {
+        BlockSize::new(self.block as u32).unwrap()
+    }
+    fn num_blocks(&mut self) -> Result<u64, Self::Error> {
+        Ok((self.data.len() / self.block) as u64)
+    }
+    fn read_blocks(&mut self, start: Lba, dst: &mut [u8]) -> Result<(), Self::Error> {
+        let off = start.0 as usize * self.block;
+        let src = self
+            .data
+            .get(off..off + dst.len())
+            .ok_or(Ext4Error::IoRead)?;
+        dst.copy_from_slice(src);
+        Ok(())
+    }
+    fn write_blocks(&mut self, _start: Lba, _src: &[u8]) -> Result<(), Self::Error> {
+        Err(Ext4Error::Unsupported)
+    }
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn wr16(b: &mut [u8], off: usize, v: u16) {
+    b[off..off + 2].copy_from_slice(&v.to_le_bytes());
+}
+fn wr32(b: &mut [u8], off: usize, v: u32) {
+    b[off..off + 4].copy_from_slice(&v.to_le_bytes());
+}
+
+/// A 1 KiB-block, single-group volume: inodes are placed by number, data
+/// blocks handed out in order.
+struct Builder {
+    img: Vec<u8>,
+    next_block: usize,
+}
+
+impl Builder {
+    fn new(incompat: u32) -> Self {
+        let mut img = alloc::vec![0u8; BLOCKS * BS];
+        let sb = &mut img[1024..2048];
+        wr32(sb, 0x00, INODES as u32);
+        wr32(sb, 0x04, BLOCKS as u32);
+        wr32(sb, 0x0C, 100); // free blocks
+        wr32(sb, 0x14, 1); // first data block
+        wr32(sb, 0x18, 0); // 1 KiB blocks
+        wr32(sb, 0x20, 8192);
+        wr32(sb, 0x28, INODES as u32);
+        wr16(sb, 0x38, EXT_MAGIC);
+        wr16(sb, 0x3A, 1);
+        wr32(sb, 0x4C, 1);
+        wr16(sb, 0x58, INODE_SIZE as u16);
+        wr32(sb, 0x5C, COMPAT_DIR_INDEX);
+        wr32(sb, 0x60, incompat);
+        sb[0x78..0x7F].copy_from_slice(b"linroot");
+        for (i, w) in SEED.iter().enumerate() {
+            wr32(sb, 0xEC + i * 4, *w);
+        }
+        sb[0xFC] = DX_HASH_HALF_MD4;
+        wr32(sb, 0x160, FLAGS_UNSIGNED_HASH);
+        // Group 0's descriptor: only the inode table matters to a reader.
+        wr32(&mut img[2 * BS..], 0x08, INODE_TABLE as u32);
+        Self {
+            img,
+            next_block: FIRST_FREE,
+        }
+    }
+
+    fn alloc(&mut self, n: usize) -> usize {
+        let b = self.next_block;
+        self.next_block += n;
+        assert!(self.next_block <= BLOCKS, "image full");
+        b
+    }
+
+    fn block_mut(&mut self, b: usize) -> &mut [u8] {
+        &mut self.img[b * BS..(b + 1) * BS]
+    }
+
+    /// Store `data` in fresh blocks and return the first.
+    fn put(&mut self, data: &[u8]) -> usize {
+        let b = self.alloc(data.len().div_ceil(BS).max(1));
+        self.img[b * BS..b * BS + data.len()].copy_from_slice(data);
+        b
+    }
+
+    fn inode(&mut self, ino: u32, mode: u16, size: u64, flags: u32, i_block: &[u8]) {
+        let at = INODE_TABLE * BS + (ino as usize - 1) * INODE_SIZE;
+        let raw = &mut self.img[at..at + INODE_SIZE];
+        wr16(raw, 0x00, mode);
+        wr16(raw, 0x02, 1000);
+        wr32(raw, 0x04, size as u32);
+        wr32(raw, 0x10, 1_700_000_000);
+        wr16(raw, 0x1A, 1);
+        wr32(raw, 0x20, flags);
+        raw[0x28..0x28 + i_block.len()].copy_from_slice(i_block);
+        wr32(raw, 0x6C, (size >> 32) as u32);
+        wr16(raw, 0x80, 32); // i_extra_isize
+    }
+
+    /// Append an in-inode `system.data` attribute holding `value`.
+    fn inline_tail(&mut self, ino: u32, value: &[u8]) {
+        let at = INODE_TABLE * BS + (ino as usize - 1) * INODE_SIZE + 128 + 32;
+        let body = &mut self.img[at..at + INODE_SIZE - 160];
+        wr32(body, 0, 0xEA02_0000);
+        let e = &mut body[4..];
+        e[0] = 4; // name_len
+        e[1] = 7; // system
+        wr16(e, 2, 24); // value offset, past this entry and the end marker
+        wr32(e, 8, value.len() as u32);
+        e[16..20].copy_from_slice(b"data");
+        body[4 + 24..4 + 24 + value.len()].copy_from_slice(value);
+    }
+}
+
+/// An extent root in `i_block` with depth 0 and these `(lblk, len, pblk)`.
+fn extent_root(extents: &[(u32, u16, usize)]) -> [u8; 60] {
+    let mut b = [0u8; 60];
+    extent_node(&mut b, 0, 4, extents.len());
+    for (i, &(lblk, len, pblk)) in extents.iter().enumerate() {
+        let e = &mut b[12 + i * 12..];
+        wr32(e, 0, lblk);
+        wr16(e, 4, len);
+        wr32(e, 8, pblk as u32);
+    }
+    b
+}
+
+fn extent_node(b: &mut [u8], depth: u16, max: u16, entries: usize) {
+    wr16(b, 0, EXTENT_MAGIC);
+    wr16(b, 2, entries as u16);
+    wr16(b, 4, max);
+    wr16(b, 6, depth);
+}
+
+/// Directory records filling `len` bytes; the last absorbs the slack.
+fn dir_block(entries: &[(u32, &str, u8)], len: usize) -> Vec<u8> {
+    let mut out = alloc::vec![0u8; len];
+    let mut at = 0;
+    for (i, &(ino, name, ft)) in entries.iter().enumerate() {
+        let need = (8 + name.len() + 3) & !3;
+        let rec = if i + 1 == entries.len() {
+            len - at
+        } else {
+            need
+        };
+        wr32(&mut out[at..], 0, ino);
+        wr16(&mut out[at..], 4, rec as u16);
+        out[at + 6] = name.len() as u8;
+        out[at + 7] = ft;
+        out[at + 8..at + 8 + name.len()].copy_from_slice(name.as_bytes());
+        at += rec;
+    }
+    out
+}
+
+fn pattern(len: usize, salt: u8) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i as u8).wrapping_mul(31).wrapping_add(salt))
+        .collect()
+}
+
+/// Root holds: `hello.txt` (one extent), `big.bin` (three extents with a hole
+/// and an unwritten run), `tree.bin` (depth-1 extent tree), `old.bin` (ext2
+/// block map out to the double-indirect tree), `sub/` with `deep.txt`,
+/// `link` (fast symlink), `longlink` (block symlink).
+fn build() -> Builder {
+    let mut b = Builder::new(INCOMPAT_FILETYPE | INCOMPAT_EXTENTS);
+
+    let hello = b"Hello from the Linux side\n";
+    let hb = b.put(hello);
+    b.inode(
+        12,
+        S_IFREG | 0o644,
+        hello.len() as u64,
+        INODE_EXTENTS_FL,
+        &extent_root(&[(0, 1, hb)]),
+    );
+
+    // big.bin: blocks 0-1 data, 2-3 hole, 4 unwritten, 5-6 data.
+    let big = pattern(7 * BS, 3);
+    let a = b.put(&big[..2 * BS]);
+    let u = b.put(&[0xEE; BS]); // stale bytes under the unwritten extent
+    let c = b.put(&big[5 * BS..]);
+    b.inode(
+        13,
+        S_IFREG | 0o644,
+        big.len() as u64,
+        INODE_EXTENTS_FL,
+        &extent_root(&[(0, 2, a), (4, EXTENT_INIT_MAX + 1, u), (5, 2, c)]),
+    );
+
+    // tree.bin: the root indexes one leaf block holding two extents.
+    let tree = pattern(3 * BS + 100, 9);
+    let t0 = b.put(&tree[..2 * BS]);
+    let t1 = b.put(&tree[2 * BS..]);
+    let leaf = b.alloc(1);
+    {
+        let l = b.block_mut(leaf);
+        extent_node(l, 0, ((BS - 12) / 12) as u16, 2);
+        wr32(&mut l[12..], 0, 0);
+        wr16(&mut l[12..], 4, 2);
+        wr32(&mut l[12..], 8, t0 as u32);
+        wr32(&mut l[24..], 0, 2);
+        wr16(&mut l[24..], 4, 2);
+        wr32(&mut l[24..], 8, t1 as u32);
+    }
+    let mut root = [0u8; 60];
+    extent_node(&mut root, 1, 4, 1);
+    wr32(&mut root[12..], 0, 0);
+    wr32(&mut root[12..], 4, leaf as u32);
+    b.inode(
+        14,
+        S_IFREG | 0o600,
+        tree.len() as u64,
+        INODE_EXTENTS_FL,
+        &root,
+    );
+
+    // old.bin: sparse, with data at block 0, in the single-indirect range
+    // (block 12) and in the double-indirect range (block 12 + 256).
+    let per = BS / 4;
+    let far = 12 + per;
+    let d0 = b.put(&pattern(BS, 1));
+    let d1 = b.put(&pattern(BS, 2));
+    let d2 = b.put(&pattern(BS, 3));
+    let ind = b.alloc(1);
+    wr32(b.block_mut(ind), 0, d1 as u32);
+    let dind = b.alloc(1);
+    let dind_leaf = b.alloc(1);
+    wr32(b.block_mut(dind), 0, dind_leaf as u32);
+    wr32(b.block_mut(dind_leaf), 0, d2 as u32);
+    let mut map = [0u8; 60];
+    wr32(&mut map, 0, d0 as u32);
+    wr32(&mut map, 12 * 4, ind as u32);
+    wr32(&mut map, 13 * 4, dind as u32);
+    b.inode(15, S_IFREG | 0o644, ((far + 1) * BS) as u64, 0, &map);
+
+    let deep = b"deep in a subdirectory";
+    let db = b.put(deep);
+    b.inode(
+        17,
+        S_IFREG | 0o644,
+        deep.len() as u64,
+        INODE_EXTENTS_FL,
+        &extent_root(&[(0, 1, db)]),
+    );
+    let sub = b.put(&dir_block(
+        &[
+            (16, ".", FT_DIR),
+            (2, "..", FT_DIR),
+            (17, "deep.txt", FT_REG_FILE),
+        ],
+        BS,
+    ));
+    b.inode(
+        16,
+        S_IFDIR | 0o755,
+        BS as u64,
+        INODE_EXTENTS_FL,
+        &extent_root(&[(0, 1, sub)]),
+    );
+
+    b.inode(18, S_IFLNK | 0o777, 12, 0, b"sub/deep.txt");
+    let target = alloc::format!("sub/{}", "x".repeat(80));
+    let lb = b.put(target.as_bytes());
+    b.inode(
+        19,
+        S_IFLNK | 0o777,
+        target.len() as u64,
+        INODE_EXTENTS_FL,
+        &extent_root(&[(0, 1, lb)]),
+    );
+
+    let root_dir = dir_block(
+        &[
+            (2, ".", FT_DIR),
+            (2, "..", FT_DIR),
+            (12, "hello.txt", FT_REG_FILE),
+            (13, "big.bin", FT_REG_FILE),
+            (14, "tree.bin", FT_REG_FILE),
+            (15, "old.bin", FT_REG_FILE),
+            (16, "sub", FT_DIR),
+            (18, "link", FT_SYMLINK),
+            (19, "longlink", FT_SYMLINK),
+        ],
+        BS,
+    );
+    let rb = b.put(&root_dir);
+    b.inode(
+        2,
+        S_IFDIR | 0o755,
+        BS as u64,
+        INODE_EXTENTS_FL,
+        &extent_root(&[(0, 1, rb)]),
+    );
+    b
+}
+
+fn mount(b: Builder) -> (Ext4Fs, MemBio) {
+    let mut dev = MemBio {
+        data: b.img,
+        block: 512,
+    };
+    let fs = Ext4Fs::mount(&mut dev, 0).expect("mount");
+    (fs, dev)
+}
+
+fn read_all(fs: &mut Ext4Fs, dev: &mut MemBio, path: &str) -> Vec<u8> {
+    let size = fs.stat(dev, path).unwrap().size as usize;
+    let mut out = alloc::vec![0u8; size];
+    let mut done = 0;
+    // Odd-sized reads cross block and extent boundaries.
+    while done < size {
+        let end = (done + 700).min(size);
+        let n = fs
+            .read_at(dev, path, done as u64, &mut out[done..end])
+            .unwrap();
+        assert!(n > 0);
+        done += n;
+    }
+    out
+}
+
+#[test]
+fn mounts_and_reads_superblock() {
+    let (fs, _) = mount(build());
+    let sb = fs.superblock();
+    assert_eq!(sb.block_size, 1024);
+    assert_eq!(sb.group_count(), 1);
+    assert_eq!(&sb.volume_name[..7], b"linroot");
+    assert!(!fs.needs_recovery());
+}
+
+#[test]
+fn readdir_root_with_types() {
+    let (mut fs, mut dev) = mount(build());
+    let ents = fs.readdir(&mut dev, "/").unwrap();
+    let names: Vec<&str> = ents.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "hello.txt",
+            "big.bin",
+            "tree.bin",
+            "old.bin",
+            "sub",
+            "link",
+            "longlink"
+        ]
+    );
+    let sub = ents.iter().find(|e| e.name == "sub").unwrap();
+    assert_eq!(sub.file_type, FileType::Directory);
+    let link = ents.iter().find(|e| e.name == "link").unwrap();
+    assert_eq!(link.file_type, FileType::Symlink);
+    assert_eq!(ents[0].size, 26);
+}
+
+#[test]
+fn stat_reports_inode_fields() {
+    let (mut fs, mut dev) = mount(build());
+    let st = fs.stat(&mut dev, "/tree.bin").unwrap();
+    assert_eq!(st.ino, 14);
+    assert_eq!(st.file_type, FileType::Regular);
+    assert_eq!(st.mode, S_IFREG | 0o600);
+    assert_eq!(st.uid, 1000);
+    assert_eq!(st.mtime, 1_700_000_000);
+    assert_eq!(fs.stat(&mut dev, "/").unwrap().ino, ROOT_INO);
+}
+
+#[test]
+fn reads_extent_files() {
+    let (mut fs, mut dev) = mount(build());
+    assert_eq!(
+        read_all(&mut fs, &mut dev, "/hello.txt"),
+        b"Hello from the Linux side\n"
+    );
+
+    let big = read_all(&mut fs, &mut dev, "/big.bin");
+    let want = pattern(7 * BS, 3);
+    assert_eq!(big[..2 * BS], want[..2 * BS]);
+    // Hole and unwritten extent read as zeros, not the stale block.
+    assert!(big[2 * BS..5 * BS].iter().all(|&b| b == 0));
+    assert_eq!(big[5 * BS..], want[5 * BS..]);
+
+    assert_eq!(
+        read_all(&mut fs, &mut dev, "/tree.bin"),
+        pattern(3 * BS + 100, 9)
+    );
+}
+
+#[test]
+fn reads_block_mapped_file() {
+    let (mut fs, mut dev) = mount(build());
+    let data = read_all(&mut fs, &mut dev, "/old.bin");
+    let far = 12 + BS / 4;
+    assert_eq!(data.len(), (far + 1) * BS);
+    assert_eq!(data[..BS], pattern(BS, 1)[..]);
+    assert!(data[BS..12 * BS].iter().all(|&b| b == 0));
+    assert_eq!(data[12 * BS..13 * BS], pattern(BS, 2)[..]);
+    assert!(data[13 * BS..far * BS].iter().all(|&b| b == 0));
+    assert_eq!(data[far * BS..], pattern(BS, 3)[..]);
+}
+
+#[test]
+fn nested_path_and_errors() {
+    let (mut fs, mut dev) = mount(build());
+    assert_eq!(
+        read_all(&mut fs, &mut dev, "/sub/deep.txt"),
+        b"deep in a subdirectory"
+    );
+    assert_eq!(
+        fs.stat(&mut dev, "/sub/missing").map(|_| ()),
+        Err(Ext4Error::NotFound)
+    );
+    assert_eq!(
+        fs.stat(&mut dev, "/hello.txt/x").map(|_| ()),
+        Err(Ext4Error::NotADirectory)
+    );
+    let mut buf = [0u8; 4];
+    assert_eq!(
+        fs.read_at(&mut dev, "/sub", 0, &mut buf),
+        Err(Ext4Error::IsADirectory)
+    );
+    assert_eq!(
+        fs.readdir(&mut dev, "/hello.txt").map(|_| ()),
+        Err(Ext4Error::NotADirectory)
+    );
+    // Names are case-sensitive.
+    assert_eq!(
+        fs.stat(&mut dev, "/HELLO.TXT").map(|_| ()),
+        Err(Ext4Error::NotFound)
+    );
+}
+
+#[test]
+fn symlinks_are_reported_not_followed() {
+    let (mut fs, mut dev) = mount(build());
+    assert_eq!(
+        fs.stat(&mut dev, "/link").unwrap().file_type,
+        FileType::Symlink
+    );
+    assert_eq!(fs.readlink(&mut dev, "/link").unwrap(), "sub/deep.txt");
+    let long = fs.readlink(&mut dev, "/longlink").unwrap();
+    assert_eq!(long.len(), 84);
+    assert!(long.starts_with("sub/xxx"));
+    assert_eq!(
+        fs.readlink(&mut dev, "/hello.txt"),
+        Err(Ext4Error::NotASymlink)
+    );
+}
+
+/// Known values from e2fsprogs' `debugfs dx_hash`.
+#[test]
+fn htree_hashes_match_e2fsprogs() {
+    let zero = [0u32; 4];
+    let cases: [(u8, &[u8], u32, u32); 9] = [
+        (DX_HASH_LEGACY, b"a", 0xe74b53e2, 0),
+        (DX_HASH_LEGACY, b"hello.txt", 0x65a05776, 0),
+        (DX_HASH_HALF_MD4, b"a", 0xd5fa7d7a, 0xacb48187),
+        (DX_HASH_HALF_MD4, b"hello.txt", 0xa26e1d86, 0x133b3f98),
+        (
+            DX_HASH_HALF_MD4,
+            b"a_name_that_is_longer_than_thirty_two_bytes.conf",
+            0xed6c086e,
+            0x9de0fac0,
+        ),
+        (DX_HASH_TEA, b"a", 0x6d0ea4c0, 0xc18922df),
+        (DX_HASH_TEA, b"hello.txt", 0x5107c3f2, 0x03840cb7),
+        (
+            DX_HASH_TEA,
+            b"a_name_that_is_longer_than_thirty_two_bytes.conf",
+            0xf47b7c50,
+            0xf013abe6,
+        ),
+        (
+            DX_HASH_LEGACY,
+            b"a_name_that_is_longer_than_thirty_two_bytes.conf",
+            0x0c3a263e,
+            0,
+        ),
+    ];
+    for (version, name, major, minor) in cases {
+        assert_eq!(
+            dx_hash(name, version, &zero),
+            Some((major, minor)),
+            "{version} {:?}",
+            core::str::from_utf8(name)
+        );
+    }
+    // Seeded with UUID 01234567-89ab-cdef-0011-223344556677.
+    let seed = [0x6745_2301, 0xEFCD_AB89, 0x3322_1100, 0x7766_5544];
+    assert_eq!(
+        dx_hash(b"hello.txt", DX_HASH_HALF_MD4, &seed),
+        Some((0x64580dfe, 0x7f0df730))
+    );
+    // High-bit bytes hash differently in the signed flavour.
+    assert_eq!(
+        dx_hash("é.txt".as_bytes(), DX_HASH_HALF_MD4, &zero),
+        Some((0xabae60a8, 0xfc369ad4))
+    );
+    assert_ne!(
+        dx_hash("é.txt".as_bytes(), DX_HASH_HALF_MD4_UNSIGNED, &zero),
+        dx_hash("é.txt".as_bytes(), DX_HASH_HALF_MD4, &zero)
+    );
+    assert_eq!(dx_hash(b"a", 6, &zero), None);
+}
+
+/// A two-leaf htree directory: names are placed by their hash, and a decoy
+/// sitting in the wrong leaf is only visible to a linear walk.
+#[test]
+fn htree_lookup_uses_index() {
+    let mut b = build();
+    let version = DX_HASH_HALF_MD4_UNSIGNED; // the volume sets FLAGS_UNSIGNED_HASH
+    let names: Vec<alloc::string::String> = (0..24)
+        .map(|i| alloc::format!("entry-{i:02}.conf"))
+        .collect();
+    let mut hashed: Vec<(u32, &str, u32)> = names
+        .iter()
+        .enumerate()
+        .map(|(i, n)| {
+            (
+                dx_hash(n.as_bytes(), version, &SEED).unwrap().0,
+                n.as_str(),
+                30 + i as u32,
+            )
+        })
+        .collect();
+    hashed.sort();
+    let split = hashed[hashed.len() / 2].0;
+    let (low, high): (Vec<_>, Vec<_>) = hashed.iter().partition(|(h, _, _)| *h < split);
+
+    for &(_, _, ino) in &hashed {
+        b.inode(ino, S_IFREG | 0o644, 0, 0, &[]);
+    }
+    // The decoy's hash sends it to the high leaf; it is stored in the low one.
+    let decoy = (0..)
+        .map(|i| alloc::format!("decoy-{i}"))
+        .find(|n| dx_hash(n.as_bytes(), version, &SEED).unwrap().0 >= split)
+        .unwrap();
+    b.inode(60, S_IFREG | 0o644, 0, 0, &[]);
+
+    let mut root = dir_block(&[(20, ".", FT_DIR), (2, "..", FT_DIR)], BS);
+    // `..` spans the index: rewrite its record to cover the rest of the block.
+    wr16(&mut root[12..], 4, (BS - 12) as u16);
+    root[0x18..0x20].copy_from_slice(&[0, 0, 0, 0, DX_HASH_HALF_MD4, 8, 0, 0]);
+    wr16(&mut root[0x20..], 0, ((BS - 0x20) / 8) as u16); // limit
+    wr16(&mut root[0x20..], 2, 2); // count
+    wr32(&mut root[0x20..], 4, 1); // hash 0.. -> logical block 1
+    wr32(&mut root[0x28..], 0, split);
+    wr32(&mut root[0x28..], 4, 2); // split.. -> logical block 2
+
+    let mut low_entries: Vec<(u32, &str, u8)> = low
+        .iter()
+        .map(|&&(_, n, ino)| (ino, n, FT_REG_FILE))
+        .collect();
+    let high_entries: Vec<(u32, &str, u8)> = high
+        .iter()
+        .map(|&&(_, n, ino)| (ino, n, FT_REG_FILE))
+        .collect();
+    low_entries.push((60, &decoy, FT_REG_FILE));
+    let blocks = [
+        root,
+        dir_block(&low_entries, BS),
+        dir_block(&high_entries, BS),
+    ];
+    let first = b.alloc(3);
+    for (i, blk) in blocks.iter().enumerate() {
+        b.block_mut(first + i).copy_from_slice(blk);
+    }
+    b.inode(
+        20,
+        S_IFDIR | 0o755,
+        3 * BS as u64,
+        INODE_EXTENTS_FL | INODE_INDEX_FL,
+        &extent_root(&[(0, 3, first)]),
+    );
+    // Hang it off the root by rewriting the `sub` entry's inode. The root's
+    // single extent starts 12 bytes into `i_block`; its `ee_start_lo` is at +8.
+    let rb = b.img[INODE_TABLE * BS + INODE_SIZE + 0x28 + 20..][..4].to_vec();
+    let rb = u32::from_le_bytes([rb[0], rb[1], rb[2], rb[3]]) as usize;
+    let at = b.img[rb * BS..(rb + 1) * BS]
+        .windows(3)
+        .position(|w| w == b"sub")
+        .unwrap()
+        - 8;
+    wr32(b.block_mut(rb), at, 20);
+
+    let (mut fs, mut dev) = mount(b);
+    for (_, name, ino) in &hashed {
+        let st = fs.stat(&mut dev, &alloc::format!("/sub/{name}")).unwrap();
+        assert_eq!(st.ino, *ino, "{name}");
+    }
+    // Listing walks every leaf and sees the decoy; lookup trusts the index.
+    assert_eq!(fs.readdir(&mut dev, "/sub").unwrap().len(), 25);
+    assert_eq!(
+        fs.stat(&mut dev, &alloc::format!("/sub/{decoy}"))
+            .map(|_| ()),
+        Err(Ext4Error::NotFound)
+    );
+}
+
+#[test]
+fn inline_data_files_and_dirs() {
+    let mut b = Builder::new(INCOMPAT_FILETYPE | INCOMPAT_EXTENTS | INCOMPAT_INLINE_DATA);
+    let text = pattern(90, 5);
+    b.inode(
+        12,
+        S_IFREG | 0o644,
+        text.len() as u64,
+        INODE_INLINE_DATA_FL,
+        &text[..60],
+    );
+    b.inline_tail(12, &text[60..]);
+
+    let mut head = alloc::vec![0u8; 60];
+    wr32(&mut head, 0, 2); // parent
+    head[4..].copy_from_slice(&dir_block(&[(12, "small.txt", FT_REG_FILE)], 56));
+    let tail = dir_block(&[(14, "other", FT_REG_FILE)], 16);
+    b.inode(13, S_IFDIR | 0o755, 60 + 16, INODE_INLINE_DATA_FL, &head);
+    b.inline_tail(13, &tail);
+    b.inode(14, S_IFREG | 0o644, 0, 0, &[]);
+
+    let rb = b.put(&dir_block(
+        &[(2, ".", FT_DIR), (2, "..", FT_DIR), (13, "inl", FT_DIR)],
+        BS,
+    ));
+    b.inode(
+        2,
+        S_IFDIR | 0o755,
+        BS as u64,
+        INODE_EXTENTS_FL,
+        &extent_root(&[(0, 1, rb)]),
+    );
+
+    let (mut fs, mut dev) = mount(b);
+    let names: Vec<_> = fs
+        .readdir(&mut dev, "/inl")
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert_eq!(names, ["small.txt", "other"]);
+    assert_eq!(read_all(&mut fs, &mut dev, "/inl/small.txt"), text);
+    assert_eq!(fs.stat(&mut dev, "/inl/other").unwrap().ino, 14);
+}
+
+#[test]
+fn mounts_at_partition_offset() {
+    let b = build();
+    let pad = 2048usize;
+    let mut data = alloc::vec![0xCDu8; pad * 512];
+    data.extend_from_slice(&b.img);
+    let mut dev = MemBio { data, block: 512 };
+    let mut fs = Ext4Fs::mount(&mut dev, pad as u64).expect("mount at offset");
+    assert_eq!(
+        read_all(&mut fs, &mut dev, "/sub/deep.txt"),
+        b"deep in a subdirectory"
+    );
+}
+
+fn mount_err(img: Vec<u8>, block: usize) -> Ext4Error {
+    match Ext4Fs::mount(&mut MemBio { data: img, block }, 0) {
+        Ok(_) => panic!("expected mount to fail"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn rejects_bad_magic() {
+    let mut img = Builder::new(0).img;
+    img[1024 + 0x38] = 0;
+    assert_eq!(mount_err(img, 512), Ext4Error::NotExt);
+}
+
+#[test]
+fn rejects_unsupported_incompat() {
+    let img = Builder::new(INCOMPAT_FILETYPE | INCOMPAT_COMPRESSION).img;
+    assert_eq!(mount_err(img, 512), Ext4Error::Unsupported);
+}
+
+#[test]
+fn rejects_blocks_smaller_than_device() {
+    // 1 KiB filesystem blocks cannot be addressed on a 4 KiB-sector device.
+    let img = Builder::new(0).img;
+    assert_eq!(mount_err(img, 4096), Ext4Error::InvalidBlockSize);
+}
+
+#[test]
+fn flags_journal_needing_recovery() {
+    let (fs, _) = mount(Builder::new(INCOMPAT_FILETYPE | INCOMPAT_RECOVER));
+    assert!(fs.needs_recovery());
+}
+
+#[test]
+fn sparse_super_backup_groups() {
+    let mut img = Builder::new(0).img;
+    wr32(&mut img[1024..], 0x64, RO_COMPAT_SPARSE_SUPER);
+    let sb = crate::superblock::Superblock::parse(&img[1024..2048]).unwrap();
+    let with: Vec<u32> = (0..100).filter(|&g| sb.group_has_super(g)).collect();
+    assert_eq!(with, [0, 1, 3, 5, 7, 9, 25, 27, 49, 81]);
+}
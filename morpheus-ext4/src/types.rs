@@ -0,0 +1,131 @@
+//! ext2/3/4 on-disk constants and engine-facing return types.
+
+use alloc::string::String;
+
+/// Byte offset of the primary superblock from the partition start.
+pub const SUPERBLOCK_OFFSET: u64 = 1024;
+pub const SUPERBLOCK_SIZE: usize = 1024;
+pub const EXT_MAGIC: u16 = 0xEF53;
+
+pub const ROOT_INO: u32 = 2;
+/// Longest name a directory entry holds.
+pub const NAME_MAX: usize = 255;
+/// Longest symlink target the reader returns.
+pub const PATH_MAX: usize = 4096;
+
+/// `s_feature_compat` bits.
+pub const COMPAT_HAS_JOURNAL: u32 = 0x0004;
+pub const COMPAT_DIR_INDEX: u32 = 0x0020;
+
+/// `s_feature_incompat` bits.
+pub const INCOMPAT_COMPRESSION: u32 = 0x0001;
+pub const INCOMPAT_FILETYPE: u32 = 0x0002;
+pub const INCOMPAT_RECOVER: u32 = 0x0004;
+pub const INCOMPAT_JOURNAL_DEV: u32 = 0x0008;
+pub const INCOMPAT_META_BG: u32 = 0x0010;
+pub const INCOMPAT_EXTENTS: u32 = 0x0040;
+pub const INCOMPAT_64BIT: u32 = 0x0080;
+pub const INCOMPAT_MMP: u32 = 0x0100;
+pub const INCOMPAT_FLEX_BG: u32 = 0x0200;
+pub const INCOMPAT_EA_INODE: u32 = 0x0400;
+pub const INCOMPAT_DIRDATA: u32 = 0x1000;
+pub const INCOMPAT_CSUM_SEED: u32 = 0x2000;
+pub const INCOMPAT_LARGEDIR: u32 = 0x4000;
+pub const INCOMPAT_INLINE_DATA: u32 = 0x8000;
+pub const INCOMPAT_ENCRYPT: u32 = 0x10000;
+pub const INCOMPAT_CASEFOLD: u32 = 0x20000;
+
+/// Incompat features a reader may ignore or honours. Encryption and casefold
+/// only matter per inode, where they are checked.
+pub const INCOMPAT_READABLE: u32 = INCOMPAT_FILETYPE
+    | INCOMPAT_RECOVER
+    | INCOMPAT_META_BG
+    | INCOMPAT_EXTENTS
+    | INCOMPAT_64BIT
+    | INCOMPAT_MMP
+    | INCOMPAT_FLEX_BG
+    | INCOMPAT_EA_INODE
+    | INCOMPAT_CSUM_SEED
+    | INCOMPAT_LARGEDIR
+    | INCOMPAT_INLINE_DATA
+    | INCOMPAT_ENCRYPT
+    | INCOMPAT_CASEFOLD;
+
+/// `s_feature_ro_compat` bits.
+pub const RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+
+/// `s_flags`: htree hashes treat name bytes as unsigned chars.
+pub const FLAGS_UNSIGNED_HASH: u32 = 0x0002;
+
+/// `i_flags` bits.
+pub const INODE_ENCRYPT_FL: u32 = 0x0000_0800;
+pub const INODE_INDEX_FL: u32 = 0x0000_1000;
+pub const INODE_EXTENTS_FL: u32 = 0x0008_0000;
+pub const INODE_INLINE_DATA_FL: u32 = 0x1000_0000;
+pub const INODE_CASEFOLD_FL: u32 = 0x4000_0000;
+
+/// `i_mode` type bits.
+pub const S_IFMT: u16 = 0xF000;
+pub const S_IFLNK: u16 = 0xA000;
+pub const S_IFREG: u16 = 0x8000;
+pub const S_IFDIR: u16 = 0x4000;
+
+/// `i_block` holds 15 block slots (60 bytes): 12 direct, then single, double
+/// and triple indirect; or an extent tree root; or inline data.
+pub const N_BLOCKS: usize = 15;
+pub const I_BLOCK_LEN: usize = N_BLOCKS * 4;
+pub const N_DIRECT: u64 = 12;
+
+pub const EXTENT_MAGIC: u16 = 0xF30A;
+/// Extent lengths above this mark an unwritten extent, which reads as zeros.
+pub const EXTENT_INIT_MAX: u16 = 32768;
+/// Deepest extent tree the reader follows (the kernel caps at 5).
+pub const EXTENT_MAX_DEPTH: u16 = 5;
+
+/// Directory entry `file_type` values (with `INCOMPAT_FILETYPE`).
+pub const FT_REG_FILE: u8 = 1;
+pub const FT_DIR: u8 = 2;
+pub const FT_SYMLINK: u8 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Regular,
+    Directory,
+    Symlink,
+    /// Device nodes, FIFOs and sockets: listed and stat-able, never read.
+    Other,
+}
+
+impl FileType {
+    pub fn from_mode(mode: u16) -> FileType {
+        match mode & S_IFMT {
+            S_IFREG => FileType::Regular,
+            S_IFDIR => FileType::Directory,
+            S_IFLNK => FileType::Symlink,
+            _ => FileType::Other,
+        }
+    }
+}
+
+/// One directory entry, `.` and `..` excluded.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub ino: u32,
+    pub file_type: FileType,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    pub ino: u32,
+    pub file_type: FileType,
+    /// Full `i_mode`: type and permission bits.
+    pub mode: u16,
+    pub size: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub nlink: u16,
+    /// Seconds since the Unix epoch.
+    pub mtime: u32,
+}
@@ -22,6 +22,8 @@ pub struct Bpb {
     pub sectors_per_fat: u32,
     pub root_cluster: u32,
     pub total_sectors: u32,
+    /// Partition-relative sector of the FSInfo block; 0 when absent.
+    pub fs_info_sector: u32,
 }
 
 impl Bpb {
@@ -47,6 +49,7 @@ impl Bpb {
         let total_sectors_32 = rd32(buf, 32);
         let sectors_per_fat_32 = rd32(buf, 36);
         let root_cluster = rd32(buf, 44);
+        let fs_info_sector = rd16(buf, 48) as u32;
 
         // FAT32 is identified by zero 16-bit FAT size and a zero root-entry count.
         if sectors_per_fat_16 != 0 || root_entry_count != 0 {
@@ -83,6 +86,12 @@ impl Bpb {
             sectors_per_fat,
             root_cluster,
             total_sectors,
+            // 0 and 0xFFFF both mean "no FSInfo".
+            fs_info_sector: if fs_info_sector < reserved_sectors {
+                fs_info_sector
+            } else {
+                0
+            },
         })
     }
 
@@ -111,3 +120,44 @@ impl Bpb {
         self.data_start_sector() + (cluster - 2) * self.sectors_per_cluster
     }
 }
+
+const FSI_LEAD_SIG: u32 = 0x4161_5252;
+const FSI_STRUC_SIG: u32 = 0x6141_7272;
+const FSI_TRAIL_SIG: u32 = 0xAA55_0000;
+/// FSInfo's "unknown" value for both hints.
+pub const FSI_UNKNOWN: u32 = 0xFFFF_FFFF;
+
+/// The FSInfo hints (fatgen103 §5): last known free-cluster count and where
+/// to start looking for a free cluster. Advisory only; either may be
+/// `FSI_UNKNOWN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsInfo {
+    pub free_count: u32,
+    pub next_free: u32,
+}
+
+impl FsInfo {
+    /// Parse an FSInfo sector; `None` when its signatures are wrong.
+    pub fn parse(buf: &[u8]) -> Option<FsInfo> {
+        if buf.len() < 512
+            || rd32(buf, 0) != FSI_LEAD_SIG
+            || rd32(buf, 484) != FSI_STRUC_SIG
+            || rd32(buf, 508) != FSI_TRAIL_SIG
+        {
+            return None;
+        }
+        Some(FsInfo {
+            free_count: rd32(buf, 488),
+            next_free: rd32(buf, 492),
+        })
+    }
+
+    /// Store the hints into an FSInfo sector, (re)writing its signatures.
+    pub fn write_into(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&FSI_LEAD_SIG.to_le_bytes());
+        buf[484..488].copy_from_slice(&FSI_STRUC_SIG.to_le_bytes());
+        buf[488..492].copy_from_slice(&self.free_count.to_le_bytes());
+        buf[492..496].copy_from_slice(&self.next_free.to_le_bytes());
+        buf[508..512].copy_from_slice(&FSI_TRAIL_SIG.to_le_bytes());
+    }
+}
@@ -64,14 +64,41 @@ fn lfn_units(raw: &[u8], dst: &mut Vec<u16>) {
     }
 }
 
+/// One live entry and where it sits in its directory blob.
+pub struct Slot {
+    pub entry: DirEntry,
+    /// Raw 8.3 name of the short entry.
+    pub short: [u8; 11],
+    pub attr: u8,
+    /// Blob offset of the entry's first slot: its first LFN slot, or the
+    /// short entry when it has none.
+    pub first: usize,
+    /// Blob offset of the short entry.
+    pub short_off: usize,
+}
+
+impl Slot {
+    /// Blob range the entry's slots cover.
+    pub fn span(&self) -> core::ops::Range<usize> {
+        self.first..self.short_off + DIR_ENTRY_SIZE
+    }
+}
+
 /// Walk a directory blob (concatenated cluster data) and yield resolved
 /// entries, skipping `.`/`..`, volume labels, and free/deleted slots.
 pub fn parse_entries(blob: &[u8]) -> Vec<DirEntry> {
+    scan(blob).into_iter().map(|s| s.entry).collect()
+}
+
+/// `parse_entries` with each entry's slot offsets, for mutators.
+pub fn scan(blob: &[u8]) -> Vec<Slot> {
     let mut out = Vec::new();
     let mut lfn_rev: Vec<u16> = Vec::new(); // collected most-significant-slot-first
+    let mut lfn_start = 0;
 
     let mut off = 0;
     while off + DIR_ENTRY_SIZE <= blob.len() {
+        let at = off;
         let raw = &blob[off..off + DIR_ENTRY_SIZE];
         off += DIR_ENTRY_SIZE;
 
@@ -86,6 +113,9 @@ pub fn parse_entries(blob: &[u8]) -> Vec<DirEntry> {
 
         let attr = raw[11];
         if attr & ATTR_LONG_NAME == ATTR_LONG_NAME {
+            if lfn_rev.is_empty() {
+                lfn_start = at;
+            }
             // LFN slots precede their short entry, ordered last-slot-first on disk.
             let mut units = Vec::new();
             lfn_units(raw, &mut units);
@@ -109,10 +139,13 @@ pub fn parse_entries(blob: &[u8]) -> Vec<DirEntry> {
                 | (u16::from_le_bytes([raw[26], raw[27]]) as u32),
         };
 
-        let name = if lfn_rev.is_empty() {
-            short.name.clone()
+        let (name, first) = if lfn_rev.is_empty() {
+            (short.name.clone(), at)
         } else {
-            decode_lfn(&lfn_rev).unwrap_or_else(|| short.name.clone())
+            match decode_lfn(&lfn_rev) {
+                Some(long) => (long, lfn_start),
+                None => (short.name.clone(), lfn_start),
+            }
         };
         lfn_rev.clear();
 
@@ -120,15 +153,23 @@ pub fn parse_entries(blob: &[u8]) -> Vec<DirEntry> {
             continue;
         }
 
-        out.push(DirEntry {
-            name,
-            file_type: if short.is_dir() {
-                FileType::Directory
-            } else {
-                FileType::Regular
+        let mut raw_name = [0u8; 11];
+        raw_name.copy_from_slice(&raw[0..11]);
+        out.push(Slot {
+            entry: DirEntry {
+                name,
+                file_type: if short.is_dir() {
+                    FileType::Directory
+                } else {
+                    FileType::Regular
+                },
+                size: short.size as u64,
+                start_cluster: short.start_cluster,
             },
-            size: short.size as u64,
-            start_cluster: short.start_cluster,
+            short: raw_name,
+            attr,
+            first,
+            short_off: at,
         });
     }
     out
@@ -17,12 +17,19 @@ pub enum Fat32Error {
     IsADirectory,
     PathTooLong,
     PathInvalid,
+    AlreadyExists,
+    DirectoryNotEmpty,
+
+    /// No free cluster left, or a directory at its 65536-entry limit.
+    NoSpace,
+    /// FAT32 sizes are 32-bit; a file cannot reach 4 GiB.
+    FileTooLarge,
 
     /// Cluster chain ran into a free/bad marker or looped past the file size.
     ChainCorrupt,
     InvalidOffset,
 
-    /// Read-only engine; any mutator hits this.
+    /// Mutator on an engine opened read-only.
     ReadOnly,
 }
 
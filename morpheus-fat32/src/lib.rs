@@ -1,9 +1,9 @@
-//! FAT32 read-only filesystem engine for MorpheusX.
+//! FAT32 filesystem engine for MorpheusX.
 //!
 //! Pure engine: generic over `gpt_disk_io::BlockIo`, no knowledge of the
 //! kernel VFS, mount table, or `FsBackend`. The storage subsystem wraps this
-//! behind its adapter. Read-only by contract — every mutator returns
-//! `Fat32Error::ReadOnly`.
+//! behind its adapter. `open` mounts read-only and every mutator returns
+//! `Fat32Error::ReadOnly`; `open_rw` mounts for writing (see `write`).
 //!
 //! Invariants:
 //! - The backend owns the whole device; all sector math is partition-relative
@@ -19,17 +19,22 @@ extern crate alloc;
 pub mod bpb;
 pub mod dir;
 pub mod error;
+pub mod name;
 pub mod types;
+mod write;
 
 use alloc::vec;
 use alloc::vec::Vec;
-use bpb::Bpb;
+use bpb::{Bpb, FsInfo, FSI_UNKNOWN};
 use dir::parse_entries;
 use error::Fat32Error;
 use gpt_disk_io::BlockIo;
 use gpt_disk_types::Lba;
 use types::*;
 
+/// FAT sectors `free_clusters` reads per device request.
+const FAT_SCAN_SECTORS: u32 = 64;
+
 /// Opaque per-fd cookie (start cluster + byte cursor). The kernel stores this
 /// in the backend-private region of `FdState`; it must fit `FD_COOKIE_LEN`.
 #[derive(Debug, Clone, Copy)]
@@ -44,6 +49,20 @@ pub struct Fat32Fs<B: BlockIo> {
     bpb: Bpb,
     /// Partition start, in absolute device sectors.
     lba_start: u64,
+    /// Allocation state of a writable mount; `None` when read-only.
+    rw: Option<RwState>,
+}
+
+/// What a writable mount tracks between calls.
+struct RwState {
+    /// Free data clusters, kept exact from the count taken at mount.
+    free: u32,
+    /// Where the next free-cluster search starts.
+    next_free: u32,
+    /// FAT[1]'s clean bit is cleared on disk: changes since the last `sync`.
+    dirty: bool,
+    /// The volume was not cleanly unmounted before this mount.
+    was_dirty: bool,
 }
 
 impl<B: BlockIo> Fat32Fs<B> {
@@ -62,11 +81,39 @@ impl<B: BlockIo> Fat32Fs<B> {
             dev,
             bpb,
             lba_start,
+            rw: None,
         })
     }
 
+    /// Mount for writing. The free count is taken from the FAT itself, since
+    /// the FSInfo hint may be stale; the volume stays marked clean until the
+    /// first change.
+    pub fn open_rw(dev: B, lba_start: u64) -> Result<Self, Fat32Error> {
+        let mut fs = Self::open(dev, lba_start)?;
+        let was_dirty = fs.next_cluster(1)? & FAT1_CLEAN_SHUTDOWN == 0;
+        let hint = fs.fs_info()?.map_or(FSI_UNKNOWN, |i| i.next_free);
+        let free = fs.count_free()?;
+        fs.rw = Some(RwState {
+            free,
+            next_free: hint,
+            dirty: false,
+            was_dirty,
+        });
+        Ok(fs)
+    }
+
     pub fn capabilities_writable(&self) -> bool {
-        false
+        self.rw.is_some()
+    }
+
+    /// Whether a writable mount found the volume not cleanly unmounted.
+    pub fn was_dirty(&self) -> bool {
+        self.rw.as_ref().is_some_and(|rw| rw.was_dirty)
+    }
+
+    /// The parsed boot sector: geometry for callers reporting volume size.
+    pub fn bpb(&self) -> &Bpb {
+        &self.bpb
     }
 
     fn read_abs(dev: &mut B, lba: u64, dst: &mut [u8]) -> Result<(), Fat32Error> {
@@ -89,9 +136,28 @@ impl<B: BlockIo> Fat32Fs<B> {
         Ok(buf)
     }
 
+    /// The FSInfo hints, if the volume has a valid FSInfo sector.
+    fn fs_info(&mut self) -> Result<Option<FsInfo>, Fat32Error> {
+        if self.bpb.fs_info_sector == 0 {
+            return Ok(None);
+        }
+        let sec = self.read_sectors(self.bpb.fs_info_sector, 1)?;
+        Ok(FsInfo::parse(&sec))
+    }
+
     /// Follow one FAT link. Reads the single sector holding the entry rather
     /// than caching the whole FAT — keeps memory flat for huge volumes.
     fn next_cluster(&mut self, cluster: u32) -> Result<u32, Fat32Error> {
+        self.next_cluster_cached(cluster, &mut None)
+    }
+
+    /// `next_cluster` reusing the FAT sector `cache` holds when the entry is
+    /// in it; a chain walk mostly stays within one sector.
+    fn next_cluster_cached(
+        &mut self,
+        cluster: u32,
+        cache: &mut Option<(u32, Vec<u8>)>,
+    ) -> Result<u32, Fat32Error> {
         let bs = self.bpb.bytes_per_sector;
         let fat_byte = (cluster as u64)
             .checked_mul(4)
@@ -104,7 +170,10 @@ impl<B: BlockIo> Fat32Fs<B> {
             .fat_start_sector()
             .checked_add(sector_in_fat)
             .ok_or(Fat32Error::ChainCorrupt)?;
-        let sec = self.read_sectors(rel, 1)?;
+        if cache.as_ref().map_or(true, |(at, _)| *at != rel) {
+            *cache = Some((rel, self.read_sectors(rel, 1)?));
+        }
+        let sec = &cache.as_ref().ok_or(Fat32Error::IoRead)?.1;
         let raw = u32::from_le_bytes([
             sec[off_in_sector],
             sec[off_in_sector + 1],
@@ -126,6 +195,7 @@ impl<B: BlockIo> Fat32Fs<B> {
             return Err(Fat32Error::ChainCorrupt);
         }
         let max = self.bpb.cluster_count().saturating_add(FIRST_DATA_CLUSTER);
+        let mut cache = None;
         let mut cur = start;
         loop {
             if cur < FIRST_DATA_CLUSTER || cur >= max || cur == FAT32_BAD {
@@ -135,7 +205,7 @@ impl<B: BlockIo> Fat32Fs<B> {
             if out.len() as u32 > self.bpb.cluster_count() {
                 return Err(Fat32Error::ChainCorrupt);
             }
-            let next = self.next_cluster(cur)?;
+            let next = self.next_cluster_cached(cur, &mut cache)?;
             if Self::is_eoc(next) {
                 break;
             }
@@ -225,6 +295,40 @@ impl<B: BlockIo> Fat32Fs<B> {
         Ok(parse_entries(&blob))
     }
 
+    /// Free data clusters. A writable mount keeps the figure as it
+    /// allocates; otherwise it is counted from the FAT.
+    pub fn free_clusters(&mut self) -> Result<u32, Fat32Error> {
+        match &self.rw {
+            Some(rw) => Ok(rw.free),
+            None => self.count_free(),
+        }
+    }
+
+    /// Free data clusters, counted from the first FAT; the FSInfo hint may be
+    /// stale, so it is not trusted. Reads `FAT_SCAN_SECTORS` at a time.
+    fn count_free(&mut self) -> Result<u32, Fat32Error> {
+        let end = self.bpb.cluster_count().saturating_add(FIRST_DATA_CLUSTER);
+        let per_sector = self.bpb.bytes_per_sector / 4;
+        let sectors = end.div_ceil(per_sector).min(self.bpb.sectors_per_fat);
+        let mut free = 0;
+        let mut sector = 0;
+        while sector < sectors {
+            let n = (sectors - sector).min(FAT_SCAN_SECTORS);
+            let buf = self.read_sectors(self.bpb.fat_start_sector() + sector, n)?;
+            let first = sector * per_sector;
+            for (i, e) in buf.chunks_exact(4).enumerate() {
+                let cluster = first + i as u32;
+                if (FIRST_DATA_CLUSTER..end).contains(&cluster)
+                    && u32::from_le_bytes([e[0], e[1], e[2], e[3]]) & FAT_ENTRY_MASK == 0
+                {
+                    free += 1;
+                }
+            }
+            sector += n;
+        }
+        Ok(free)
+    }
+
     /// Open for read: resolve the path, reject directories, return a cookie.
     pub fn open_file(&mut self, path: &str) -> Result<Fat32Cookie, Fat32Error> {
         let st = self.resolve(path)?;
@@ -238,6 +342,19 @@ impl<B: BlockIo> Fat32Fs<B> {
         })
     }
 
+    /// Read up to `buf.len()` bytes of `path` at `offset`. Unlike a cookie,
+    /// this sees the file as it is now, writes since the open included.
+    pub fn read_at(
+        &mut self,
+        path: &str,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<usize, Fat32Error> {
+        let mut cookie = self.open_file(path)?;
+        cookie.cursor = offset;
+        self.read(&mut cookie, buf)
+    }
+
     /// Read up to `buf.len()` bytes at the cookie's cursor, advancing it.
     /// Returns bytes copied (0 at EOF).
     pub fn read(&mut self, cookie: &mut Fat32Cookie, buf: &mut [u8]) -> Result<usize, Fat32Error> {
@@ -0,0 +1,248 @@
+//! Name encoding for new entries: 8.3 aliases, NT case bits and LFN slots.
+//!
+//! A name that fits 8.3 exactly (each part all upper or all lower case) is
+//! stored as a short entry alone, lower case via the NT case bits at 0x0C.
+//! Anything else gets LFN slots plus a generated `BASIS~N.EXT` alias unique
+//! in its directory (fatgen103 §7).
+
+use crate::error::Fat32Error;
+use crate::types::*;
+use alloc::vec::Vec;
+
+/// Most UTF-16 units a long name may hold.
+const LFN_NAME_MAX: usize = 255;
+
+/// NT case bits: base / extension stored upper, displayed lower.
+pub const CASE_LOWER_BASE: u8 = 0x08;
+pub const CASE_LOWER_EXT: u8 = 0x10;
+
+/// A name ready to be written: the short entry's name and case bits, plus the
+/// UTF-16 long name when one is needed.
+pub struct Encoded {
+    pub short: [u8; 11],
+    pub case: u8,
+    pub long: Option<Vec<u16>>,
+}
+
+impl Encoded {
+    /// Directory slots this name takes, the short entry included.
+    pub fn slots(&self) -> usize {
+        1 + self.long.as_ref().map_or(0, |u| u.len().div_ceil(13))
+    }
+}
+
+/// Reject names no FAT directory may hold.
+pub fn validate(name: &str) -> Result<(), Fat32Error> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(Fat32Error::PathInvalid);
+    }
+    if name.encode_utf16().count() > LFN_NAME_MAX {
+        return Err(Fat32Error::PathTooLong);
+    }
+    let bad = |c: char| (c as u32) < 0x20 || "\"*/:<>?\\|".contains(c);
+    if name.chars().any(bad) || name.ends_with(' ') || name.ends_with('.') {
+        return Err(Fat32Error::PathInvalid);
+    }
+    Ok(())
+}
+
+fn short_char_ok(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'()-@^_`{}~".contains(&c)
+}
+
+/// Case of an 8.3 part: `Some(true)` all lower, `Some(false)` no lower case,
+/// `None` mixed.
+fn part_case(part: &str) -> Option<bool> {
+    let lower = part.bytes().any(|c| c.is_ascii_lowercase());
+    let upper = part.bytes().any(|c| c.is_ascii_uppercase());
+    match (lower, upper) {
+        (true, true) => None,
+        (lower, _) => Some(lower),
+    }
+}
+
+/// The short entry for a name that fits 8.3 as it stands, if it does.
+fn exact_short(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, ext) = match name.rsplit_once('.') {
+        Some((b, e)) => (b, e),
+        None => (name, ""),
+    };
+    if base.is_empty() || base.len() > 8 || ext.len() > 3 {
+        return None;
+    }
+    if !base.bytes().chain(ext.bytes()).all(short_char_ok) {
+        return None;
+    }
+    let mut case = 0;
+    if part_case(base)? {
+        case |= CASE_LOWER_BASE;
+    }
+    if part_case(ext)? {
+        case |= CASE_LOWER_EXT;
+    }
+    let mut short = [b' '; 11];
+    for (i, c) in base.bytes().enumerate() {
+        short[i] = c.to_ascii_uppercase();
+    }
+    for (i, c) in ext.bytes().enumerate() {
+        short[8 + i] = c.to_ascii_uppercase();
+    }
+    Some((short, case))
+}
+
+/// Uppercased short-name characters of `part`: spaces and dots dropped,
+/// anything else unrepresentable becomes `_`.
+fn basis(part: &str, max: usize) -> Vec<u8> {
+    part.chars()
+        .filter(|&c| c != ' ' && c != '.')
+        .map(|c| {
+            let b = if c.is_ascii() { c as u8 } else { b'_' };
+            if short_char_ok(b) {
+                b.to_ascii_uppercase()
+            } else {
+                b'_'
+            }
+        })
+        .take(max)
+        .collect()
+}
+
+/// Encode `name` for a directory whose short names `taken` reports. Call
+/// `validate` first.
+pub fn encode(name: &str, taken: impl Fn(&[u8; 11]) -> bool) -> Result<Encoded, Fat32Error> {
+    if let Some((short, case)) = exact_short(name) {
+        if !taken(&short) {
+            return Ok(Encoded {
+                short,
+                case,
+                long: None,
+            });
+        }
+    }
+
+    let trimmed = name.trim_start_matches('.');
+    let (base, ext) = match trimmed.rsplit_once('.') {
+        Some((b, e)) if !b.is_empty() => (b, e),
+        _ => (trimmed, ""),
+    };
+    let mut base = basis(base, 8);
+    if base.is_empty() {
+        base.push(b'_');
+    }
+    let ext = basis(ext, 3);
+
+    let mut short = [b' '; 11];
+    short[8..8 + ext.len()].copy_from_slice(&ext);
+    for n in 1..1_000_000u32 {
+        let mut tail = [0u8; 7];
+        let digits = {
+            let mut v = n;
+            let mut i = tail.len();
+            while v > 0 {
+                i -= 1;
+                tail[i] = b'0' + (v % 10) as u8;
+                v /= 10;
+            }
+            i -= 1;
+            tail[i] = b'~';
+            &tail[i..]
+        };
+        let keep = base.len().min(8 - digits.len());
+        short[..8].fill(b' ');
+        short[..keep].copy_from_slice(&base[..keep]);
+        short[keep..keep + digits.len()].copy_from_slice(digits);
+        if !taken(&short) {
+            return Ok(Encoded {
+                short,
+                case: 0,
+                long: Some(name.encode_utf16().collect()),
+            });
+        }
+    }
+    Err(Fat32Error::NoSpace)
+}
+
+/// The LFN checksum of a short name (fatgen103 §7).
+pub fn checksum(short: &[u8; 11]) -> u8 {
+    short
+        .iter()
+        .fold(0u8, |sum, &c| sum.rotate_right(1).wrapping_add(c))
+}
+
+/// A 32-byte short entry.
+pub fn short_entry(short: &[u8; 11], case: u8, attr: u8, cluster: u32, size: u32) -> [u8; 32] {
+    let mut e = [0u8; DIR_ENTRY_SIZE];
+    e[0..11].copy_from_slice(short);
+    e[11] = attr;
+    e[12] = case;
+    set_cluster(&mut e, cluster);
+    e[28..32].copy_from_slice(&size.to_le_bytes());
+    e
+}
+
+/// Point a short entry at `cluster`.
+pub fn set_cluster(e: &mut [u8], cluster: u32) {
+    e[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    e[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+}
+
+/// Stamp a short entry's write time and date with `unix_secs`, clamped to
+/// the DOS range (1980 through 2107); the time keeps two-second steps.
+pub fn set_modified(e: &mut [u8], unix_secs: u64) {
+    let (time, date) = dos_time(unix_secs);
+    e[22..24].copy_from_slice(&time.to_le_bytes());
+    e[24..26].copy_from_slice(&date.to_le_bytes());
+}
+
+/// Unix seconds as a DOS `(time, date)` pair.
+fn dos_time(unix_secs: u64) -> (u16, u16) {
+    const DOS_EPOCH: u64 = 315_532_800; // 1980-01-01T00:00:00Z
+    let secs = unix_secs.max(DOS_EPOCH);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Days since 1970 to a civil date, counted in 400-year eras from March.
+    let z = days + 719_468;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = z / 146_097 * 400 + yoe + u64::from(month <= 2);
+    if year > 2107 {
+        return (23 << 11 | 59 << 5 | 29, 127 << 9 | 12 << 5 | 31);
+    }
+    let time = (rem / 3_600) << 11 | (rem % 3_600 / 60) << 5 | (rem % 60 / 2);
+    let date = (year - 1980) << 9 | month << 5 | day;
+    (time as u16, date as u16)
+}
+
+/// All slots for `enc` in on-disk order: LFN slots last-ordinal-first, then
+/// the short entry.
+pub fn entries(enc: &Encoded, attr: u8, cluster: u32, size: u32) -> Vec<[u8; 32]> {
+    let mut out = Vec::new();
+    if let Some(units) = &enc.long {
+        let sum = checksum(&enc.short);
+        let n = units.len().div_ceil(13);
+        for slot in (0..n).rev() {
+            let mut e = [0u8; DIR_ENTRY_SIZE];
+            e[0] = (slot + 1) as u8 | if slot == n - 1 { 0x40 } else { 0 };
+            e[11] = ATTR_LONG_NAME;
+            e[13] = sum;
+            let mut unit = slot * 13;
+            for (start, end) in [(1, 11), (14, 26), (28, 32)] {
+                for i in (start..end).step_by(2) {
+                    let u = match unit.cmp(&units.len()) {
+                        core::cmp::Ordering::Less => units[unit],
+                        core::cmp::Ordering::Equal => 0x0000,
+                        core::cmp::Ordering::Greater => 0xFFFF,
+                    };
+                    e[i..i + 2].copy_from_slice(&u.to_le_bytes());
+                    unit += 1;
+                }
+            }
+            out.push(e);
+        }
+    }
+    out.push(short_entry(&enc.short, enc.case, attr, cluster, size));
+    out
+}
@@ -29,8 +29,10 @@ impl BlockIo for MemBio {
         dst.copy_from_slice(&self.data[off..off + dst.len()]);
         Ok(())
     }
-    fn write_blocks(&mut self, _start: Lba, _src: &[u8]) -> Result<(), Self::Error> {
-        Err(crate::error::Fat32Error::ReadOnly)
+    fn write_blocks(&mut self, start: Lba, src: &[u8]) -> Result<(), Self::Error> {
+        let off = start.0 as usize * SECTOR;
+        self.data[off..off + src.len()].copy_from_slice(src);
+        Ok(())
     }
     fn flush(&mut self) -> Result<(), Self::Error> {
         Ok(())
@@ -78,8 +80,14 @@ impl Builder {
         b[32..36].copy_from_slice(&(total as u32).to_le_bytes()); // total_32
         b[36..40].copy_from_slice(&(spf as u32).to_le_bytes()); // sectors/fat 32
         b[44..48].copy_from_slice(&2u32.to_le_bytes()); // root cluster
+        b[48..50].copy_from_slice(&1u16.to_le_bytes()); // FSInfo sector
         b[510] = 0x55;
         b[511] = 0xAA;
+        crate::bpb::FsInfo {
+            free_count: crate::bpb::FSI_UNKNOWN,
+            next_free: crate::bpb::FSI_UNKNOWN,
+        }
+        .write_into(&mut img[SECTOR..2 * SECTOR]);
     }
 
     fn fat_offset(spf: usize, cluster: u32) -> usize {
@@ -300,6 +308,14 @@ fn mounts_and_reads_bpb() {
     assert!(!fs.capabilities_writable());
 }
 
+#[test]
+fn counts_free_clusters() {
+    let mut fs = build_fs();
+    // Root, HELLO.TXT, BIG.BIN (3), DEEP.BIN, SUB and the LFN file.
+    assert_eq!(fs.bpb.cluster_count(), TOTAL_CLUSTERS as u32);
+    assert_eq!(fs.free_clusters().unwrap(), TOTAL_CLUSTERS as u32 - 8);
+}
+
 #[test]
 fn readdir_root() {
     let mut fs = build_fs();
@@ -577,3 +593,320 @@ fn rejects_block_size_mismatch() {
     img[11..13].copy_from_slice(&1024u16.to_le_bytes());
     assert_eq!(open_err(img), crate::error::Fat32Error::InvalidBlockSize);
 }
+
+// ── writable mounts ────────────────────────────────────────────────────────
+
+fn build_rw() -> Fat32Fs<MemBio> {
+    let img = build_fs().dev.data;
+    Fat32Fs::open_rw(MemBio { data: img }, 0).expect("mount rw")
+}
+
+fn read_all(fs: &mut Fat32Fs<MemBio>, path: &str) -> alloc::vec::Vec<u8> {
+    let mut out = alloc::vec![0u8; fs.stat(path).unwrap().size as usize];
+    let n = fs.read_at(path, 0, &mut out).unwrap();
+    assert_eq!(n, out.len());
+    out
+}
+
+fn names(fs: &mut Fat32Fs<MemBio>, path: &str) -> alloc::vec::Vec<alloc::string::String> {
+    fs.readdir(path)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name)
+        .collect()
+}
+
+fn remount(fs: Fat32Fs<MemBio>) -> Fat32Fs<MemBio> {
+    Fat32Fs::open_rw(fs.dev, 0).expect("remount")
+}
+
+#[test]
+fn read_only_mount_rejects_mutators() {
+    use crate::error::Fat32Error::ReadOnly;
+    let mut fs = build_fs();
+    assert_eq!(fs.create("/NEW.TXT"), Err(ReadOnly));
+    assert_eq!(fs.write_at("/HELLO.TXT", 0, b"x", 0), Err(ReadOnly));
+    assert_eq!(fs.truncate("/HELLO.TXT", 0, 0), Err(ReadOnly));
+    assert_eq!(fs.mkdir("/D"), Err(ReadOnly));
+    assert_eq!(fs.unlink("/HELLO.TXT"), Err(ReadOnly));
+    assert_eq!(fs.rename("/HELLO.TXT", "/B.TXT"), Err(ReadOnly));
+    assert_eq!(fs.sync(), Ok(()));
+}
+
+#[test]
+fn create_write_read_back_across_clusters() {
+    let mut fs = build_rw();
+    assert!(fs.capabilities_writable());
+    let free = fs.free_clusters().unwrap();
+    fs.create("/Boot Config.json").unwrap();
+    assert_eq!(
+        fs.create("/boot config.JSON"),
+        Err(crate::error::Fat32Error::AlreadyExists)
+    );
+
+    let data: alloc::vec::Vec<u8> = (0..1500u32).map(|i| (i * 7) as u8).collect();
+    assert_eq!(fs.write_at("/Boot Config.json", 0, &data, 0).unwrap(), 1500);
+    assert_eq!(fs.free_clusters().unwrap(), free - 3);
+
+    let mut fs = remount(fs);
+    assert!(names(&mut fs, "/").contains(&"Boot Config.json".into()));
+    assert_eq!(read_all(&mut fs, "/Boot Config.json"), data);
+    assert_eq!(fs.free_clusters().unwrap(), free - 3);
+}
+
+#[test]
+fn overwrite_in_place_and_zero_filled_gap() {
+    let mut fs = build_rw();
+    fs.write_at("/HELLO.TXT", 0, b"J", 0).unwrap();
+    assert_eq!(read_all(&mut fs, "/HELLO.TXT"), b"Jello, FAT32 world!\n");
+
+    fs.create("/sparse").unwrap();
+    fs.write_at("/sparse", 700, b"tail", 0).unwrap();
+    let got = read_all(&mut fs, "/sparse");
+    assert_eq!(got.len(), 704);
+    assert!(got[..700].iter().all(|&b| b == 0));
+    assert_eq!(&got[700..], b"tail");
+}
+
+#[test]
+fn truncate_frees_and_extends() {
+    let mut fs = build_rw();
+    let free = fs.free_clusters().unwrap();
+    fs.truncate("/BIG.BIN", 600, 0).unwrap();
+    assert_eq!(fs.free_clusters().unwrap(), free + 1);
+    let got = read_all(&mut fs, "/BIG.BIN");
+    assert_eq!(got.len(), 600);
+    assert_eq!(got[599], (599 & 0xFF) as u8);
+
+    fs.truncate("/BIG.BIN", 0, 0).unwrap();
+    assert_eq!(fs.free_clusters().unwrap(), free + 3);
+    assert_eq!(fs.stat("/BIG.BIN").unwrap().start_cluster, 0);
+
+    fs.truncate("/BIG.BIN", 10, 0).unwrap();
+    assert_eq!(read_all(&mut fs, "/BIG.BIN"), [0u8; 10]);
+    assert_eq!(
+        fs.truncate("/SUB", 0, 0),
+        Err(crate::error::Fat32Error::IsADirectory)
+    );
+}
+
+#[test]
+fn mkdir_nested_and_unlink() {
+    use crate::error::Fat32Error::*;
+    let mut fs = build_rw();
+    let free = fs.free_clusters().unwrap();
+    fs.mkdir("/EFI").unwrap();
+    fs.mkdir("/EFI/morpheus").unwrap();
+    fs.create("/EFI/morpheus/kernel.elf").unwrap();
+    fs.write_at("/EFI/morpheus/kernel.elf", 0, b"\x7fELF", 0)
+        .unwrap();
+    assert_eq!(fs.mkdir("/EFI"), Err(AlreadyExists));
+    assert_eq!(fs.mkdir("/NOPE/X"), Err(NotFound));
+    assert_eq!(fs.create("/HELLO.TXT/X"), Err(NotADirectory));
+
+    // `..` of a root child is 0, of a deeper dir its parent's cluster.
+    let efi = fs.stat("/EFI").unwrap().start_cluster;
+    let sub = fs.stat("/EFI/morpheus").unwrap().start_cluster;
+    let dots = fs.read_dir_blob(sub).unwrap();
+    assert_eq!(&dots[..11], b".          ");
+    assert_eq!(u16::from_le_bytes([dots[26], dots[27]]) as u32, sub);
+    assert_eq!(u16::from_le_bytes([dots[58], dots[59]]) as u32, efi);
+    let dots = fs.read_dir_blob(efi).unwrap();
+    assert_eq!(u16::from_le_bytes([dots[58], dots[59]]), 0);
+
+    assert_eq!(fs.unlink("/EFI/morpheus"), Err(DirectoryNotEmpty));
+    fs.unlink("/EFI/morpheus/kernel.elf").unwrap();
+    fs.unlink("/EFI/morpheus").unwrap();
+    fs.unlink("/EFI").unwrap();
+    assert_eq!(fs.stat("/EFI").map(|_| ()), Err(NotFound));
+    assert_eq!(fs.free_clusters().unwrap(), free);
+}
+
+#[test]
+fn short_aliases_are_unique() {
+    let mut fs = build_rw();
+    fs.create("/LongFileName2.txt").unwrap();
+    fs.create("/longfilename3.txt").unwrap();
+    fs.create("/readme").unwrap();
+    fs.create("/Notes.md").unwrap();
+
+    let blob = fs.read_dir_blob(2).unwrap();
+    let shorts: alloc::vec::Vec<[u8; 11]> =
+        crate::dir::scan(&blob).iter().map(|s| s.short).collect();
+    // LONGFI~1 was taken by the image already.
+    assert!(shorts.contains(&name83("LONGFI~2", "TXT")));
+    assert!(shorts.contains(&name83("LONGFI~3", "TXT")));
+    // Names that fit 8.3 keep their case through the NT bits alone.
+    assert!(shorts.contains(&name83("README", "")));
+    assert!(shorts.contains(&name83("NOTES~1", "MD")));
+    let listed = names(&mut fs, "/");
+    for n in [
+        "LongFileName2.txt",
+        "longfilename3.txt",
+        "readme",
+        "Notes.md",
+    ] {
+        assert!(listed.contains(&n.into()), "{n} missing from {listed:?}");
+    }
+}
+
+#[test]
+fn directory_grows_past_one_cluster() {
+    let mut fs = build_rw();
+    fs.mkdir("/MANY").unwrap();
+    // 16 slots per cluster; each long name takes two.
+    for i in 0..30 {
+        let path = alloc::format!("/MANY/entry-number-{i}");
+        fs.create(&path).unwrap();
+        fs.write_at(&path, 0, path.as_bytes(), 0).unwrap();
+    }
+    let start = fs.stat("/MANY").unwrap().start_cluster;
+    assert!(fs.chain(start).unwrap().len() >= 4);
+    let mut fs = remount(fs);
+    assert_eq!(fs.readdir("/MANY").unwrap().len(), 30);
+    assert_eq!(
+        read_all(&mut fs, "/MANY/entry-number-29"),
+        b"/MANY/entry-number-29"
+    );
+}
+
+#[test]
+fn rename_within_and_across_directories() {
+    use crate::error::Fat32Error::*;
+    let mut fs = build_rw();
+    fs.rename("/HELLO.TXT", "/Greeting.txt").unwrap();
+    assert_eq!(fs.stat("/HELLO.TXT").map(|_| ()), Err(NotFound));
+    assert_eq!(read_all(&mut fs, "/Greeting.txt"), b"Hello, FAT32 world!\n");
+
+    // A directory moved under another gets its `..` repointed.
+    fs.mkdir("/A").unwrap();
+    fs.rename("/SUB", "/A/moved").unwrap();
+    let a = fs.stat("/A").unwrap().start_cluster;
+    let moved = fs.stat("/A/moved").unwrap().start_cluster;
+    let dots = fs.read_dir_blob(moved).unwrap();
+    assert_eq!(u16::from_le_bytes([dots[58], dots[59]]) as u32, a);
+    assert_eq!(
+        read_all(&mut fs, "/A/moved/DEEP.BIN"),
+        b"deep file contents"
+    );
+
+    assert_eq!(fs.rename("/A", "/A/moved/inner"), Err(PathInvalid));
+    assert_eq!(fs.rename("/A", "/BIG.BIN"), Err(NotADirectory));
+    assert_eq!(fs.rename("/BIG.BIN", "/A"), Err(IsADirectory));
+    assert_eq!(fs.rename("/A/moved", "/A/gone"), Ok(()));
+}
+
+#[test]
+fn rename_replaces_existing_file() {
+    let mut fs = build_rw();
+    let free = fs.free_clusters().unwrap();
+    fs.rename("/HELLO.TXT", "/BIG.BIN").unwrap();
+    // BIG.BIN's three clusters came back.
+    assert_eq!(fs.free_clusters().unwrap(), free + 3);
+    assert_eq!(read_all(&mut fs, "/BIG.BIN"), b"Hello, FAT32 world!\n");
+    assert_eq!(
+        names(&mut fs, "/")
+            .iter()
+            .filter(|n| *n == "BIG.BIN")
+            .count(),
+        1
+    );
+
+    // Across directories the target keeps its plain 8.3 name.
+    let free = fs.free_clusters().unwrap();
+    fs.rename("/SUB/DEEP.BIN", "/BIG.BIN").unwrap();
+    assert_eq!(fs.free_clusters().unwrap(), free + 1);
+    assert_eq!(read_all(&mut fs, "/BIG.BIN"), b"deep file contents");
+    assert_eq!(fs.readdir("/SUB").unwrap().len(), 0);
+    let root = fs.read_dir_blob(fs.bpb.root_cluster).unwrap();
+    assert!(root
+        .chunks_exact(DIR_ENTRY_SIZE)
+        .any(|e| &e[0..11] == b"BIG     BIN"));
+}
+
+/// The raw short entry named `short` in the root directory.
+fn root_entry(fs: &mut Fat32Fs<MemBio>, short: &[u8; 11]) -> [u8; DIR_ENTRY_SIZE] {
+    let root = fs.read_dir_blob(fs.bpb.root_cluster).unwrap();
+    let e = root
+        .chunks_exact(DIR_ENTRY_SIZE)
+        .find(|e| &e[0..11] == short)
+        .expect("short entry");
+    e.try_into().unwrap()
+}
+
+#[test]
+fn writes_and_truncates_stamp_the_modification_time() {
+    let mut fs = build_rw();
+    let stamp = |e: [u8; DIR_ENTRY_SIZE]| {
+        (
+            u16::from_le_bytes([e[22], e[23]]),
+            u16::from_le_bytes([e[24], e[25]]),
+        )
+    };
+    // 2024-02-29 13:45:07 UTC; DOS time keeps even seconds.
+    fs.write_at("/HELLO.TXT", 0, b"J", 1_709_214_307).unwrap();
+    let want = (13 << 11 | 45 << 5 | 3, (2024 - 1980) << 9 | 2 << 5 | 29);
+    assert_eq!(stamp(root_entry(&mut fs, b"HELLO   TXT")), want);
+
+    // A truncate to the same size still counts as a change.
+    fs.truncate("/HELLO.TXT", 20, 1_735_689_599).unwrap();
+    let want = (23 << 11 | 59 << 5 | 29, (2024 - 1980) << 9 | 12 << 5 | 31);
+    assert_eq!(stamp(root_entry(&mut fs, b"HELLO   TXT")), want);
+
+    // Times outside the DOS range clamp to its ends.
+    fs.truncate("/BIG.BIN", 10, 0).unwrap();
+    assert_eq!(stamp(root_entry(&mut fs, b"BIG     BIN")), (0, 1 << 5 | 1));
+    fs.truncate("/BIG.BIN", 10, u64::MAX).unwrap();
+    let max = (23 << 11 | 59 << 5 | 29, 127 << 9 | 12 << 5 | 31);
+    assert_eq!(stamp(root_entry(&mut fs, b"BIG     BIN")), max);
+}
+
+#[test]
+fn dirty_bit_and_fsinfo_protocol() {
+    let mut fs = build_rw();
+    assert!(!fs.was_dirty());
+    let fat1 = |fs: &mut Fat32Fs<MemBio>| fs.next_cluster(1).unwrap();
+    assert_ne!(fat1(&mut fs) & FAT1_CLEAN_SHUTDOWN, 0);
+
+    fs.create("/new.txt").unwrap();
+    fs.write_at("/new.txt", 0, b"data", 0).unwrap();
+    assert_eq!(fat1(&mut fs) & FAT1_CLEAN_SHUTDOWN, 0);
+
+    // Dropped without sync: the next mount sees an unclean volume.
+    let mut crashed = remount(fs);
+    assert!(crashed.was_dirty());
+    crashed.sync().unwrap();
+    // Nothing changed since this mount, so the volume is still marked dirty.
+    assert_eq!(fat1(&mut crashed) & FAT1_CLEAN_SHUTDOWN, 0);
+    crashed.write_at("/new.txt", 4, b"!", 0).unwrap();
+    crashed
+        .write_at("/new.txt", SECTOR as u64, b"?", 0)
+        .unwrap();
+    crashed.sync().unwrap();
+    assert_ne!(fat1(&mut crashed) & FAT1_CLEAN_SHUTDOWN, 0);
+
+    let free = crashed.free_clusters().unwrap();
+    let mut fs = remount(crashed);
+    assert!(!fs.was_dirty());
+    let info = fs.fs_info().unwrap().expect("fsinfo");
+    assert_eq!(info.free_count, free);
+    assert_ne!(info.next_free, crate::bpb::FSI_UNKNOWN);
+    assert_eq!(&read_all(&mut fs, "/new.txt")[..5], b"data!");
+}
+
+#[test]
+fn out_of_space_leaves_file_intact() {
+    let mut fs = build_rw();
+    fs.create("/fill").unwrap();
+    let free = fs.free_clusters().unwrap() as usize;
+    let too_big = alloc::vec![0xAAu8; (free + 1) * SECTOR];
+    assert_eq!(
+        fs.write_at("/fill", 0, &too_big, 0),
+        Err(crate::error::Fat32Error::NoSpace)
+    );
+    assert_eq!(fs.free_clusters().unwrap() as usize, free);
+    assert_eq!(fs.stat("/fill").unwrap().size, 0);
+    fs.write_at("/fill", 0, &too_big[..free * SECTOR], 0)
+        .unwrap();
+    assert_eq!(fs.free_clusters().unwrap(), 0);
+}
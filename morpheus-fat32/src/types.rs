@@ -8,9 +8,22 @@ pub const FAT32_EOC: u32 = 0x0FFF_FFF8;
 pub const FAT32_BAD: u32 = 0x0FFF_FFF7;
 pub const FAT_ENTRY_MASK: u32 = 0x0FFF_FFFF;
 
+/// End-of-chain value the engine writes.
+pub const FAT32_EOC_MARK: u32 = 0x0FFF_FFFF;
+
 /// First two FAT slots are reserved; data clusters are numbered from 2.
 pub const FIRST_DATA_CLUSTER: u32 = 2;
 
+/// FAT[1] bit set while the volume is cleanly unmounted; a writer clears it
+/// before its first change and sets it again once everything is on disk.
+pub const FAT1_CLEAN_SHUTDOWN: u32 = 0x0800_0000;
+
+/// Largest file FAT32 can describe.
+pub const MAX_FILE_SIZE: u64 = u32::MAX as u64;
+
+/// Most entries one directory may hold (its size caps at 2 MiB).
+pub const MAX_DIR_ENTRIES: usize = 65536;
+
 pub const DIR_ENTRY_SIZE: usize = 32;
 pub const ENTRY_FREE: u8 = 0xE5;
 pub const ENTRY_END: u8 = 0x00;
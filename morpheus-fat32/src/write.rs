@@ -0,0 +1,661 @@
+//! Mutators for a writable mount (`Fat32Fs::open_rw`).
+//!
+//! Every change goes straight to the device; nothing is cached across calls
+//! but the free-cluster count and search hint. Before its first change after
+//! a mount or `sync` the engine clears FAT[1]'s clean-shutdown bit; `sync`
+//! writes the FSInfo hints and sets the bit again, so a volume left dirty
+//! tells the next mount (ours or another OS's) to check it.
+//!
+//! Ordering keeps a crash to leaked clusters at worst: clusters are linked
+//! before an entry points at them, an entry is written before the one it
+//! replaces is freed, and a chain is freed only after no entry names it.
+
+use crate::bpb::FsInfo;
+use crate::dir::{scan, Slot};
+use crate::error::Fat32Error;
+use crate::name;
+use crate::types::*;
+use crate::{Fat32Fs, FAT_SCAN_SECTORS};
+use alloc::vec;
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+
+/// A directory loaded for editing: its chain and their contents.
+struct Dir {
+    start: u32,
+    chain: Vec<u32>,
+    blob: Vec<u8>,
+}
+
+/// Split an absolute path into its parent and final component.
+fn split(path: &str) -> Result<(&str, &str), Fat32Error> {
+    let path = path.trim_end_matches('/');
+    let (parent, name) = path.rsplit_once('/').ok_or(Fat32Error::PathInvalid)?;
+    if name.is_empty() {
+        return Err(Fat32Error::PathInvalid);
+    }
+    Ok((if parent.is_empty() { "/" } else { parent }, name))
+}
+
+/// Free the slots of `slot` in `d`'s blob only, returning their span.
+fn clear(d: &mut Dir, slot: &Slot) -> core::ops::Range<usize> {
+    let span = slot.span();
+    for off in span.clone().step_by(DIR_ENTRY_SIZE) {
+        d.blob[off] = ENTRY_FREE;
+    }
+    span
+}
+
+fn find<'a>(slots: &'a [Slot], name: &str) -> Option<&'a Slot> {
+    slots
+        .iter()
+        .find(|s| s.entry.name.eq_ignore_ascii_case(name))
+}
+
+impl<B: BlockIo> Fat32Fs<B> {
+    fn write_abs(&mut self, lba: u64, src: &[u8]) -> Result<(), Fat32Error> {
+        self.dev
+            .write_blocks(Lba(lba), src)
+            .map_err(|_| Fat32Error::IoWrite)
+    }
+
+    /// Write partition-relative sectors starting at `rel_sector`.
+    fn write_sectors(&mut self, rel_sector: u32, src: &[u8]) -> Result<(), Fat32Error> {
+        let abs = self
+            .lba_start
+            .checked_add(rel_sector as u64)
+            .ok_or(Fat32Error::InvalidOffset)?;
+        self.write_abs(abs, src)
+    }
+
+    fn write_cluster(&mut self, cluster: u32, src: &[u8]) -> Result<(), Fat32Error> {
+        let rel = self.bpb.cluster_to_sector(cluster);
+        self.write_sectors(rel, src)
+    }
+
+    fn check_writable(&self) -> Result<(), Fat32Error> {
+        match self.rw {
+            Some(_) => Ok(()),
+            None => Err(Fat32Error::ReadOnly),
+        }
+    }
+
+    /// Set one FAT entry in every FAT copy, keeping its reserved top bits.
+    fn set_fat(&mut self, cluster: u32, value: u32) -> Result<(), Fat32Error> {
+        let bs = self.bpb.bytes_per_sector;
+        let fat_byte = cluster as u64 * 4;
+        let sector_in_fat = (fat_byte / bs as u64) as u32;
+        let off = (fat_byte % bs as u64) as usize;
+        for fat in 0..self.bpb.num_fats {
+            let rel = self.bpb.fat_start_sector() + fat * self.bpb.sectors_per_fat + sector_in_fat;
+            let mut sec = self.read_sectors(rel, 1)?;
+            let old = u32::from_le_bytes([sec[off], sec[off + 1], sec[off + 2], sec[off + 3]]);
+            let new = (old & !FAT_ENTRY_MASK) | (value & FAT_ENTRY_MASK);
+            sec[off..off + 4].copy_from_slice(&new.to_le_bytes());
+            self.write_sectors(rel, &sec)?;
+        }
+        Ok(())
+    }
+
+    /// Clear the clean-shutdown bit before the first change since the last
+    /// `sync`.
+    fn mark_dirty(&mut self) -> Result<(), Fat32Error> {
+        match &self.rw {
+            None => return Err(Fat32Error::ReadOnly),
+            Some(rw) if rw.dirty => return Ok(()),
+            Some(_) => {},
+        }
+        let fat1 = self.next_cluster(1)?;
+        self.set_fat(1, fat1 & !FAT1_CLEAN_SHUTDOWN)?;
+        self.dev.flush().map_err(|_| Fat32Error::IoWrite)?;
+        if let Some(rw) = &mut self.rw {
+            rw.dirty = true;
+        }
+        Ok(())
+    }
+
+    /// Put everything on disk: the FSInfo hints, then the clean-shutdown
+    /// bit. A no-op on a read-only or unchanged mount.
+    pub fn sync(&mut self) -> Result<(), Fat32Error> {
+        let (free, next_free) = match &self.rw {
+            Some(rw) if rw.dirty => (rw.free, rw.next_free),
+            _ => return Ok(()),
+        };
+        if self.bpb.fs_info_sector != 0 {
+            let rel = self.bpb.fs_info_sector;
+            let mut sec = self.read_sectors(rel, 1)?;
+            FsInfo {
+                free_count: free,
+                next_free,
+            }
+            .write_into(&mut sec);
+            self.write_sectors(rel, &sec)?;
+        }
+        self.dev.flush().map_err(|_| Fat32Error::IoWrite)?;
+        let fat1 = self.next_cluster(1)?;
+        self.set_fat(1, fat1 | FAT1_CLEAN_SHUTDOWN)?;
+        self.dev.flush().map_err(|_| Fat32Error::IoWrite)?;
+        if let Some(rw) = &mut self.rw {
+            rw.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// First free cluster in `[from, to)`, reading `FAT_SCAN_SECTORS` at a
+    /// time.
+    fn find_free(&mut self, from: u32, to: u32) -> Result<Option<u32>, Fat32Error> {
+        let per_sector = self.bpb.bytes_per_sector / 4;
+        let mut sector = from / per_sector;
+        let last = to.div_ceil(per_sector).min(self.bpb.sectors_per_fat);
+        while sector < last {
+            let n = (last - sector).min(FAT_SCAN_SECTORS);
+            let buf = self.read_sectors(self.bpb.fat_start_sector() + sector, n)?;
+            let first = sector * per_sector;
+            for (i, e) in buf.chunks_exact(4).enumerate() {
+                let cluster = first + i as u32;
+                if (from..to).contains(&cluster)
+                    && u32::from_le_bytes([e[0], e[1], e[2], e[3]]) & FAT_ENTRY_MASK == 0
+                {
+                    return Ok(Some(cluster));
+                }
+            }
+            sector += n;
+        }
+        Ok(None)
+    }
+
+    /// Take one free cluster and mark it end-of-chain.
+    fn alloc_cluster(&mut self) -> Result<u32, Fat32Error> {
+        let hint = match &self.rw {
+            Some(rw) if rw.free == 0 => return Err(Fat32Error::NoSpace),
+            Some(rw) => rw.next_free,
+            None => return Err(Fat32Error::ReadOnly),
+        };
+        let end = self.bpb.cluster_count().saturating_add(FIRST_DATA_CLUSTER);
+        let hint = if (FIRST_DATA_CLUSTER..end).contains(&hint) {
+            hint
+        } else {
+            FIRST_DATA_CLUSTER
+        };
+        let cluster = match self.find_free(hint, end)? {
+            Some(c) => c,
+            None => self
+                .find_free(FIRST_DATA_CLUSTER, hint)?
+                .ok_or(Fat32Error::NoSpace)?,
+        };
+        self.set_fat(cluster, FAT32_EOC_MARK)?;
+        if let Some(rw) = &mut self.rw {
+            rw.free -= 1;
+            rw.next_free = cluster + 1;
+        }
+        Ok(cluster)
+    }
+
+    /// Extend `chain` to `len` clusters. On failure the clusters taken so far
+    /// are returned and the chain is left as it was.
+    fn grow(&mut self, chain: &mut Vec<u32>, len: usize) -> Result<(), Fat32Error> {
+        let had = chain.len();
+        while chain.len() < len {
+            let linked = match self.alloc_cluster() {
+                Ok(c) => {
+                    let linked = match chain.last() {
+                        Some(&prev) => self.set_fat(prev, c),
+                        None => Ok(()),
+                    };
+                    chain.push(c);
+                    linked
+                },
+                Err(e) => Err(e),
+            };
+            if let Err(e) = linked {
+                let added = chain.split_off(had);
+                if let Some(&last) = chain.last() {
+                    let _ = self.set_fat(last, FAT32_EOC_MARK);
+                }
+                let _ = self.release(&added);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark `clusters` free.
+    fn release(&mut self, clusters: &[u32]) -> Result<(), Fat32Error> {
+        for &c in clusters {
+            self.set_fat(c, 0)?;
+            if let Some(rw) = &mut self.rw {
+                rw.free += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Free the chain at `start`, if there is one.
+    fn release_chain(&mut self, start: u32) -> Result<(), Fat32Error> {
+        if start < FIRST_DATA_CLUSTER {
+            return Ok(());
+        }
+        let chain = self.chain(start)?;
+        self.release(&chain)
+    }
+
+    /// Write `data` at byte `offset` of the content `chain` holds. The chain
+    /// must already cover it.
+    fn write_chain(&mut self, chain: &[u32], offset: u64, data: &[u8]) -> Result<(), Fat32Error> {
+        let bpc = self.bpb.bytes_per_cluster() as u64;
+        let mut done = 0usize;
+        while done < data.len() {
+            let at = offset + done as u64;
+            let cluster = *chain
+                .get((at / bpc) as usize)
+                .ok_or(Fat32Error::ChainCorrupt)?;
+            let within = (at % bpc) as usize;
+            let n = (bpc as usize - within).min(data.len() - done);
+            if n == bpc as usize {
+                self.write_cluster(cluster, &data[done..done + n])?;
+            } else {
+                let mut buf = self.read_cluster(cluster)?;
+                buf[within..within + n].copy_from_slice(&data[done..done + n]);
+                self.write_cluster(cluster, &buf)?;
+            }
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// Zero bytes `[from, to)` of the content `chain` holds.
+    fn zero_chain(&mut self, chain: &[u32], from: u64, to: u64) -> Result<(), Fat32Error> {
+        let bpc = self.bpb.bytes_per_cluster() as u64;
+        let zeros = vec![0u8; bpc as usize];
+        let mut at = from;
+        while at < to {
+            let n = (bpc - at % bpc).min(to - at);
+            self.write_chain(chain, at, &zeros[..n as usize])?;
+            at += n;
+        }
+        Ok(())
+    }
+
+    /// Start cluster of the directory at `path`.
+    fn dir_start(&mut self, path: &str) -> Result<u32, Fat32Error> {
+        let st = self.resolve(path)?;
+        if st.file_type != FileType::Directory {
+            return Err(Fat32Error::NotADirectory);
+        }
+        if st.start_cluster < FIRST_DATA_CLUSTER {
+            return Err(Fat32Error::ChainCorrupt);
+        }
+        Ok(st.start_cluster)
+    }
+
+    fn load_dir(&mut self, start: u32) -> Result<Dir, Fat32Error> {
+        let chain = self.chain(start)?;
+        let mut blob = Vec::new();
+        for &c in &chain {
+            blob.extend_from_slice(&self.read_cluster(c)?);
+        }
+        Ok(Dir { start, chain, blob })
+    }
+
+    /// Write back the sectors of `d` covering blob bytes `range`.
+    fn store_dir(&mut self, d: &Dir, range: core::ops::Range<usize>) -> Result<(), Fat32Error> {
+        let bs = self.bpb.bytes_per_sector as usize;
+        let bpc = self.bpb.bytes_per_cluster() as usize;
+        let mut sector = range.start / bs;
+        while sector * bs < range.end {
+            let at = sector * bs;
+            let cluster = *d.chain.get(at / bpc).ok_or(Fat32Error::ChainCorrupt)?;
+            let rel = self.bpb.cluster_to_sector(cluster) + ((at % bpc) / bs) as u32;
+            self.write_sectors(rel, &d.blob[at..at + bs])?;
+            sector += 1;
+        }
+        Ok(())
+    }
+
+    /// Blob offset of `n` consecutive free slots in `d`, growing it by a
+    /// zeroed cluster as often as needed.
+    fn free_run(&mut self, d: &mut Dir, n: usize) -> Result<usize, Fat32Error> {
+        loop {
+            let mut run = 0;
+            for (i, raw) in d.blob.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+                if raw[0] == ENTRY_FREE || raw[0] == ENTRY_END {
+                    run += 1;
+                    if run == n {
+                        return Ok((i + 1 - n) * DIR_ENTRY_SIZE);
+                    }
+                } else {
+                    run = 0;
+                }
+            }
+            let bpc = self.bpb.bytes_per_cluster() as usize;
+            if d.blob.len() + bpc > MAX_DIR_ENTRIES * DIR_ENTRY_SIZE {
+                return Err(Fat32Error::NoSpace);
+            }
+            let len = d.chain.len() + 1;
+            self.grow(&mut d.chain, len)?;
+            let fresh = *d.chain.last().ok_or(Fat32Error::ChainCorrupt)?;
+            let zeros = vec![0u8; bpc];
+            self.write_cluster(fresh, &zeros)?;
+            d.blob.extend_from_slice(&zeros);
+        }
+    }
+
+    /// Add an entry called `name` to `d` and write it out.
+    fn insert(
+        &mut self,
+        d: &mut Dir,
+        name: &str,
+        attr: u8,
+        cluster: u32,
+        size: u32,
+    ) -> Result<(), Fat32Error> {
+        let taken: Vec<[u8; 11]> = scan(&d.blob).iter().map(|s| s.short).collect();
+        let enc = name::encode(name, |short| taken.contains(short))?;
+        let at = self.free_run(d, enc.slots())?;
+        let slots = name::entries(&enc, attr, cluster, size);
+        for (i, e) in slots.iter().enumerate() {
+            let off = at + i * DIR_ENTRY_SIZE;
+            d.blob[off..off + DIR_ENTRY_SIZE].copy_from_slice(e);
+        }
+        self.store_dir(d, at..at + slots.len() * DIR_ENTRY_SIZE)
+    }
+
+    /// Free the slots of `slot` in `d` and write them out.
+    fn remove(&mut self, d: &mut Dir, slot: &Slot) -> Result<(), Fat32Error> {
+        let span = clear(d, slot);
+        self.store_dir(d, span)
+    }
+
+    /// Rewrite the start cluster, size and modification time of the short
+    /// entry at `short_off`.
+    fn set_entry(
+        &mut self,
+        d: &mut Dir,
+        short_off: usize,
+        cluster: u32,
+        size: u32,
+        now: u64,
+    ) -> Result<(), Fat32Error> {
+        let e = &mut d.blob[short_off..short_off + DIR_ENTRY_SIZE];
+        name::set_cluster(e, cluster);
+        e[28..32].copy_from_slice(&size.to_le_bytes());
+        name::set_modified(e, now);
+        self.store_dir(d, short_off..short_off + DIR_ENTRY_SIZE)
+    }
+
+    /// Load the parent of `path` and find its final component there.
+    fn locate(&mut self, path: &str) -> Result<(Dir, Slot), Fat32Error> {
+        let (parent, leaf) = split(path)?;
+        let start = self.dir_start(parent)?;
+        let d = self.load_dir(start)?;
+        let slot = scan(&d.blob)
+            .into_iter()
+            .find(|s| s.entry.name.eq_ignore_ascii_case(leaf))
+            .ok_or(Fat32Error::NotFound)?;
+        Ok((d, slot))
+    }
+
+    /// Whether the directory at `start` holds nothing but `.` and `..`.
+    fn dir_is_empty(&mut self, start: u32) -> Result<bool, Fat32Error> {
+        if start < FIRST_DATA_CLUSTER {
+            return Ok(true);
+        }
+        let d = self.load_dir(start)?;
+        Ok(scan(&d.blob).is_empty())
+    }
+
+    /// Create an empty file. Fails `AlreadyExists` if the name is taken.
+    pub fn create(&mut self, path: &str) -> Result<(), Fat32Error> {
+        self.check_writable()?;
+        let (parent, leaf) = split(path)?;
+        name::validate(leaf)?;
+        let start = self.dir_start(parent)?;
+        let mut d = self.load_dir(start)?;
+        if find(&scan(&d.blob), leaf).is_some() {
+            return Err(Fat32Error::AlreadyExists);
+        }
+        self.mark_dirty()?;
+        self.insert(&mut d, leaf, ATTR_ARCHIVE, 0, 0)
+    }
+
+    /// Create a directory holding `.` and `..`.
+    pub fn mkdir(&mut self, path: &str) -> Result<(), Fat32Error> {
+        self.check_writable()?;
+        let (parent, leaf) = split(path)?;
+        name::validate(leaf)?;
+        let start = self.dir_start(parent)?;
+        let mut d = self.load_dir(start)?;
+        if find(&scan(&d.blob), leaf).is_some() {
+            return Err(Fat32Error::AlreadyExists);
+        }
+        self.mark_dirty()?;
+        let cluster = self.alloc_cluster()?;
+        // `..` naming the root is stored as cluster 0.
+        let up = if start == self.bpb.root_cluster {
+            0
+        } else {
+            start
+        };
+        let mut blob = vec![0u8; self.bpb.bytes_per_cluster() as usize];
+        blob[..32].copy_from_slice(&name::short_entry(
+            b".          ",
+            0,
+            ATTR_DIRECTORY,
+            cluster,
+            0,
+        ));
+        blob[32..64].copy_from_slice(&name::short_entry(b"..         ", 0, ATTR_DIRECTORY, up, 0));
+        let made = self
+            .write_cluster(cluster, &blob)
+            .and_then(|()| self.insert(&mut d, leaf, ATTR_DIRECTORY, cluster, 0));
+        if made.is_err() {
+            let _ = self.release(&[cluster]);
+        }
+        made
+    }
+
+    /// Write `data` at `offset` of an existing file, growing it as needed;
+    /// a gap past the old end reads as zeros. `now` (Unix seconds) becomes
+    /// the file's modification time.
+    pub fn write_at(
+        &mut self,
+        path: &str,
+        offset: u64,
+        data: &[u8],
+        now: u64,
+    ) -> Result<usize, Fat32Error> {
+        self.check_writable()?;
+        let (mut d, slot) = self.locate(path)?;
+        if slot.entry.file_type == FileType::Directory {
+            return Err(Fat32Error::IsADirectory);
+        }
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let end = offset
+            .checked_add(data.len() as u64)
+            .ok_or(Fat32Error::FileTooLarge)?;
+        if end > MAX_FILE_SIZE {
+            return Err(Fat32Error::FileTooLarge);
+        }
+        self.mark_dirty()?;
+        let old = slot.entry.size;
+        let size = old.max(end);
+        let mut chain = self.file_chain(&slot)?;
+        self.extend_to(&mut chain, size)?;
+        if offset > old {
+            self.zero_chain(&chain, old, offset)?;
+        }
+        self.write_chain(&chain, offset, data)?;
+        self.set_entry(&mut d, slot.short_off, chain[0], size as u32, now)?;
+        Ok(data.len())
+    }
+
+    /// Set a file's size, freeing clusters past the new end or zero-filling
+    /// up to it. `now` (Unix seconds) becomes the file's modification time,
+    /// even when the size is unchanged.
+    pub fn truncate(&mut self, path: &str, size: u64, now: u64) -> Result<(), Fat32Error> {
+        self.check_writable()?;
+        let (mut d, slot) = self.locate(path)?;
+        if slot.entry.file_type == FileType::Directory {
+            return Err(Fat32Error::IsADirectory);
+        }
+        if size > MAX_FILE_SIZE {
+            return Err(Fat32Error::FileTooLarge);
+        }
+        let old = slot.entry.size;
+        self.mark_dirty()?;
+        if size == old {
+            let start = slot.entry.start_cluster;
+            return self.set_entry(&mut d, slot.short_off, start, size as u32, now);
+        }
+        let mut chain = self.file_chain(&slot)?;
+        if size > old {
+            self.extend_to(&mut chain, size)?;
+            self.zero_chain(&chain, old, size)?;
+            return self.set_entry(&mut d, slot.short_off, chain[0], size as u32, now);
+        }
+        let keep = size.div_ceil(self.bpb.bytes_per_cluster() as u64) as usize;
+        let start = if keep == 0 { 0 } else { chain[0] };
+        // The entry shrinks before the clusters past it are freed.
+        self.set_entry(&mut d, slot.short_off, start, size as u32, now)?;
+        if keep < chain.len() {
+            if keep > 0 {
+                self.set_fat(chain[keep - 1], FAT32_EOC_MARK)?;
+            }
+            self.release(&chain[keep..])?;
+        }
+        Ok(())
+    }
+
+    /// The clusters a file holds; none for an empty file.
+    fn file_chain(&mut self, slot: &Slot) -> Result<Vec<u32>, Fat32Error> {
+        if slot.entry.start_cluster < FIRST_DATA_CLUSTER {
+            return Ok(Vec::new());
+        }
+        self.chain(slot.entry.start_cluster)
+    }
+
+    /// Grow `chain` to cover `size` bytes.
+    fn extend_to(&mut self, chain: &mut Vec<u32>, size: u64) -> Result<(), Fat32Error> {
+        let need = size.div_ceil(self.bpb.bytes_per_cluster() as u64) as usize;
+        if need > chain.len() {
+            self.grow(chain, need)?;
+        }
+        Ok(())
+    }
+
+    /// Remove a file or an empty directory.
+    pub fn unlink(&mut self, path: &str) -> Result<(), Fat32Error> {
+        self.check_writable()?;
+        let (mut d, slot) = self.locate(path)?;
+        if slot.entry.file_type == FileType::Directory
+            && !self.dir_is_empty(slot.entry.start_cluster)?
+        {
+            return Err(Fat32Error::DirectoryNotEmpty);
+        }
+        self.mark_dirty()?;
+        self.remove(&mut d, &slot)?;
+        self.release_chain(slot.entry.start_cluster)
+    }
+
+    /// Move `old` to `new`, replacing a file there, or an empty directory
+    /// when `old` is one. A directory cannot move below itself.
+    pub fn rename(&mut self, old: &str, new: &str) -> Result<(), Fat32Error> {
+        self.check_writable()?;
+        let (new_parent, new_leaf) = split(new)?;
+        name::validate(new_leaf)?;
+        let (mut src, slot) = self.locate(old)?;
+        let is_dir = slot.entry.file_type == FileType::Directory;
+        if is_dir {
+            let from = old.trim_end_matches('/');
+            let below = new_parent
+                .get(..from.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(from))
+                && matches!(new_parent.as_bytes().get(from.len()), None | Some(b'/'));
+            if below {
+                return Err(Fat32Error::PathInvalid);
+            }
+        }
+        let dst_start = self.dir_start(new_parent)?;
+        let same_dir = dst_start == src.start;
+        let mut dst = if same_dir {
+            None
+        } else {
+            Some(self.load_dir(dst_start)?)
+        };
+
+        let target = {
+            let blob = dst.as_ref().map_or(&src.blob, |d| &d.blob);
+            scan(blob)
+                .into_iter()
+                .find(|s| s.entry.name.eq_ignore_ascii_case(new_leaf))
+                .filter(|s| !(same_dir && s.short_off == slot.short_off))
+        };
+        if let Some(t) = &target {
+            match (is_dir, t.entry.file_type == FileType::Directory) {
+                (false, true) => return Err(Fat32Error::IsADirectory),
+                (true, false) => return Err(Fat32Error::NotADirectory),
+                (true, true) if !self.dir_is_empty(t.entry.start_cluster)? => {
+                    return Err(Fat32Error::DirectoryNotEmpty)
+                },
+                _ => {},
+            }
+        }
+
+        self.mark_dirty()?;
+        let size = slot.entry.size as u32;
+        let cluster = slot.entry.start_cluster;
+        match &mut dst {
+            None => {
+                // One directory: free the old slots in memory so the new name
+                // may reuse them and its own alias, but write them out only
+                // once the new entry is on disk.
+                let gone = target.as_ref().map(|t| clear(&mut src, t));
+                let old = clear(&mut src, &slot);
+                self.insert(&mut src, new_leaf, slot.attr, cluster, size)?;
+                if let Some(span) = gone {
+                    self.store_dir(&src, span)?;
+                }
+                self.store_dir(&src, old)?;
+            },
+            Some(d) => {
+                // As above, the target's slots are freed in memory first so
+                // the new entry may take its name, but they reach the disk
+                // only once the new entry is there.
+                let gone = target.as_ref().map(|t| clear(d, t));
+                self.insert(d, new_leaf, slot.attr, cluster, size)?;
+                if let Some(span) = gone {
+                    self.store_dir(d, span)?;
+                }
+                self.remove(&mut src, &slot)?;
+                if is_dir && cluster >= FIRST_DATA_CLUSTER {
+                    let up = if dst_start == self.bpb.root_cluster {
+                        0
+                    } else {
+                        dst_start
+                    };
+                    self.repoint_parent(cluster, up)?;
+                }
+            },
+        }
+        match target {
+            Some(t) => self.release_chain(t.entry.start_cluster),
+            None => Ok(()),
+        }
+    }
+
+    /// Point the `..` entry of the directory at `start` at `parent`.
+    fn repoint_parent(&mut self, start: u32, parent: u32) -> Result<(), Fat32Error> {
+        let mut d = self.load_dir(start)?;
+        let at = d
+            .blob
+            .chunks_exact(DIR_ENTRY_SIZE)
+            .position(|raw| &raw[0..11] == b"..         ")
+            .ok_or(Fat32Error::ChainCorrupt)?
+            * DIR_ENTRY_SIZE;
+        name::set_cluster(&mut d.blob[at..at + DIR_ENTRY_SIZE], parent);
+        self.store_dir(&d, at..at + DIR_ENTRY_SIZE)
+    }
+}
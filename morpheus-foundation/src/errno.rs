@@ -55,6 +55,7 @@ pub const ENOSYS: u64 = e(38);
 pub const ENOTEMPTY: u64 = e(39);
 pub const ELOOP: u64 = e(40);
 pub const ENOMSG: u64 = e(42);
+pub const ENODATA: u64 = e(61);
 pub const EPROTO: u64 = e(71);
 pub const EOVERFLOW: u64 = e(75);
 pub const ENOTSOCK: u64 = e(88);
@@ -22,6 +22,8 @@ pub mod open_flags {
     /// Kernel-internal marker: this fd is a socket (dispatch by flag, not number).
     pub const O_SOCKET: u32 = 0x400;
     pub const O_NONBLOCK: u32 = 0x800;
+    /// A symbolic link in the last component fails the open with `ELOOP`.
+    pub const O_NOFOLLOW: u32 = 0x1000;
     pub const O_CLOEXEC: u32 = 0x80000;
 }
 
@@ -71,6 +73,9 @@ pub const SPAWN_FA_CLOSE: u32 = 2;
 pub const SPAWN_FA_CHDIR: u32 = 3;
 /// `SpawnArgs.flags` bit0: start from an empty fd table instead of the inherited one.
 pub const SPAWN_CLEAR_FDS: u32 = 1 << 0;
+/// `SpawnArgs.flags` bit1: start the child as `SpawnArgs.cred` instead of the
+/// caller's credential. Root only (`EPERM`).
+pub const SPAWN_SET_CRED: u32 = 1 << 1;
 
 /// `thread_create`/`thread_detach` flags.
 pub const THREAD_DETACHED: u64 = 1;
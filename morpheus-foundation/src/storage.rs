@@ -13,19 +13,98 @@ pub const DEV_AHCI: u32 = 2;
 pub const DEV_SDHCI: u32 = 3;
 pub const DEV_USBMSD: u32 = 4;
 
-/// `fs_type`. `FS_AUTO`/`FS_HELIX`/`FS_FAT32` are mount selectors (`SYS_MOUNT`);
-/// `FS_NONE`/`FS_UNKNOWN` only appear as `VolumeInfo::fs_type` detection results.
+/// `fs_type`. `FS_AUTO`/`FS_HELIX`/`FS_FAT32`/`FS_ISO9660`/`FS_EXT4` are mount
+/// selectors (`SYS_MOUNT`); `FS_NONE`/`FS_UNKNOWN` only appear as
+/// `VolumeInfo::fs_type` detection results. ISO9660 and ext2/3/4 (`FS_EXT4`)
+/// mount read-only. `FS_PROC`/`FS_SYSFS` are the synthetic `/proc` and `/sys`
+/// trees: mounted from `VOLUME_NONE`, read-only, generated on read. `FS_DEVFS`
+/// is the synthetic `/dev` tree of device nodes, also from `VOLUME_NONE`.
 pub const FS_AUTO: u32 = 0;
 pub const FS_HELIX: u32 = 1;
 pub const FS_FAT32: u32 = 2;
 pub const FS_NONE: u32 = 3;
 pub const FS_UNKNOWN: u32 = 4;
+pub const FS_ISO9660: u32 = 5;
+pub const FS_EXT4: u32 = 6;
+pub const FS_PROC: u32 = 7;
+pub const FS_SYSFS: u32 = 8;
+pub const FS_DEVFS: u32 = 9;
 
 /// `SYS_MOUNT`/`SYS_UMOUNT` flags. `MNT_STAGED` = copy source into RAM (residency
-/// axis); `MNT_FORCE` is umount-only (revoke open fds).
+/// axis); `MNT_FORCE` is umount-only (revoke open fds). `MNT_SNAPSHOT` mounts a
+/// Helix volume read-only as of a snapshot (`aux` points at a `SnapshotSpec`);
+/// the live volume may stay mounted alongside. `MNT_FSCK` checks a Helix
+/// volume before mounting it, repairing it unless the mount is read-only.
+/// `MNT_KEY` unlocks an encrypted Helix volume: `aux` points at a `MountKey`
+/// whose own `aux` carries the meaning above. `MNT_MANIFEST` mounts an ISO
+/// stored in chunks across partitions: `aux` points at a `MountManifest`
+/// holding the ISO's manifest, and the source volume is the partition holding
+/// its first chunk.
 pub const MNT_RDONLY: u32 = 1 << 0;
 pub const MNT_STAGED: u32 = 1 << 1;
 pub const MNT_FORCE: u32 = 1 << 2;
+pub const MNT_SNAPSHOT: u32 = 1 << 3;
+pub const MNT_FSCK: u32 = 1 << 4;
+pub const MNT_KEY: u32 = 1 << 5;
+pub const MNT_MANIFEST: u32 = 1 << 6;
+
+/// Longest passphrase `SYS_MOUNT` accepts under `MNT_KEY`.
+pub const MOUNT_KEY_MAX: usize = 256;
+
+/// `SYS_FS_TX` ops. Mutations by other processes on a mount with an open
+/// transaction fail `EBUSY`; the owner's exit aborts it.
+pub const TX_BEGIN: u32 = 0;
+pub const TX_COMMIT: u32 = 1;
+pub const TX_ABORT: u32 = 2;
+
+/// Helix compression policy attribute. `lz4` on a file compresses its
+/// whole-file writes; on a directory it applies to everything below that has
+/// no closer setting. `none` opts out.
+pub const XATTR_COMPRESSION: &str = "helix.compression";
+
+/// Helix version retention policy attribute, resolved like
+/// `XATTR_COMPRESSION`. `all` keeps every prior version of a file,
+/// `last:N` the newest N, `within:S` those replaced in the last S seconds,
+/// `none` none. A file under no policy keeps the old behaviour: an overwrite
+/// frees the prior version once no snapshot sees it.
+pub const XATTR_RETENTION: &str = "helix.retention";
+
+/// Helix directory quota attribute: `bytes:N`, `entries:N`, or both joined by
+/// `,`. Everything below the directory counts, nested quotas included; a
+/// write, create or rename that would take it past a limit fails `EDQUOT`.
+/// `0` or a missing limit is unlimited. Hard-linked content counts nowhere.
+pub const XATTR_QUOTA: &str = "helix.quota";
+
+/// Prefix of the Helix policy attributes above. They bind everything below the
+/// node they sit on, so only root may set or remove one.
+pub const XATTR_POLICY_PREFIX: &str = "helix.";
+
+/// Gate for `SYS_SETXATTR`/`SYS_REMOVEXATTR` on `name`: `Err(EPERM)` when it is
+/// a policy attribute and the caller is not `root`.
+pub fn check_xattr_change(name: &str, root: bool) -> Result<(), u64> {
+    if !root && name.starts_with(XATTR_POLICY_PREFIX) {
+        return Err(crate::errno::EPERM);
+    }
+    Ok(())
+}
+
+/// `FsEvent::mask` bits, and the interest mask of `SYS_FS_WATCH_ADD`. A
+/// rename is two events, `FS_EV_MOVED_FROM` on the old path then
+/// `FS_EV_MOVED_TO` on the new, sharing a `cookie`. Only volumes that keep a
+/// change feed (HelixFS) report anything.
+pub const FS_EV_CREATE: u32 = 1 << 0;
+pub const FS_EV_MODIFY: u32 = 1 << 1;
+pub const FS_EV_DELETE: u32 = 1 << 2;
+pub const FS_EV_MOVED_FROM: u32 = 1 << 3;
+pub const FS_EV_MOVED_TO: u32 = 1 << 4;
+/// Extended attributes, owner or permission bits.
+pub const FS_EV_ATTRIB: u32 = 1 << 5;
+pub const FS_EV_ALL: u32 = 0x3F;
+/// Never asked for: events were dropped before this one (`wd` 0).
+pub const FS_EV_OVERFLOW: u32 = 1 << 14;
+/// `SYS_FS_WATCH_ADD` only: report everything below a directory, not just
+/// its entries.
+pub const FS_WATCH_SUBTREE: u32 = 1 << 15;
 
 /// `VolumeInfo::flags`. `VOL_EPHEMERAL` marks a synthesized RAM volume backing a
 /// staged mount (owned by its creating process, reclaimed on reap).
@@ -55,3 +134,18 @@ pub const fn pack(index: u32, generation: u32) -> u64 {
 pub const fn unpack(handle: u64) -> (u32, u32) {
     (handle as u32, (handle >> 32) as u32)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errno::EPERM;
+
+    #[test]
+    fn policy_xattrs_need_root() {
+        for name in [XATTR_QUOTA, XATTR_RETENTION, XATTR_COMPRESSION] {
+            assert_eq!(check_xattr_change(name, false), Err(EPERM));
+            assert_eq!(check_xattr_change(name, true), Ok(()));
+        }
+        assert_eq!(check_xattr_change("user.tag", false), Ok(()));
+    }
+}
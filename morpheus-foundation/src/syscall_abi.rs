@@ -39,6 +39,7 @@ pub const SYS_UNLINK: u64 = 16;
 pub const SYS_RENAME: u64 = 17;
 pub const SYS_TRUNCATE: u64 = 18;
 pub const SYS_SYNC: u64 = 19;
+/// `snapshot(name_ptr, name_len) -> lsn | -errno`. Root only (`EPERM`).
 pub const SYS_SNAPSHOT: u64 = 20;
 pub const SYS_VERSIONS: u64 = 21;
 
@@ -235,6 +236,91 @@ pub const SYS_FCNTL: u64 = 127;
 /// `rmdir(path_ptr, path_len) -> 0 | -errno`. Dirs only (`SYS_UNLINK(16)` is files-only).
 pub const SYS_RMDIR: u64 = 128;
 pub const SYS_REPARENT: u64 = 129;
+/// `fs_tx(op, path_ptr, path_len) -> 0 | -errno`. `TX_BEGIN`/`TX_COMMIT`/`TX_ABORT`
+/// on the mount holding `path`; one open transaction per mount, owned by the caller.
+pub const SYS_FS_TX: u64 = 130;
+/// `setxattr(path_ptr, path_len, name_ptr, name_len, val_ptr, val_len) -> 0 | -errno`.
+/// Creates or replaces one extended attribute; `E2BIG` past the per-file limit.
+pub const SYS_SETXATTR: u64 = 131;
+/// `getxattr(path_ptr, path_len, name_ptr, name_len, buf_ptr, buf_len) -> value_len | -errno`.
+/// `buf_len==0` probes; a short buffer → `ERANGE`, a missing name → `ENODATA`.
+pub const SYS_GETXATTR: u64 = 132;
+/// `listxattr(path_ptr, path_len, buf_ptr, buf_len) -> total_bytes | -errno`.
+/// NUL-terminated names back to back; `buf_len==0` probes, short → `ERANGE`.
+pub const SYS_LISTXATTR: u64 = 133;
+/// `removexattr(path_ptr, path_len, name_ptr, name_len) -> 0 | -errno`.
+pub const SYS_REMOVEXATTR: u64 = 134;
+/// `snapshots(buf_ptr, max) -> count | -errno`. Fills `SnapshotInfo[min(count,max)]`
+/// for the root volume, oldest first; `max==0` probes.
+pub const SYS_SNAPSHOTS: u64 = 135;
+/// `snapshot_delete(name_ptr, name_len) -> 0 | -errno`. Drops the newest snapshot
+/// so named and frees the blocks only it kept. Root only (`EPERM`).
+pub const SYS_SNAPSHOT_DELETE: u64 = 136;
+/// `snapshot_rollback(name_ptr, name_len) -> 0 | -errno`. Makes the root namespace
+/// equal to the newest snapshot so named, atomically; the snapshot is kept.
+/// Root only (`EPERM`).
+pub const SYS_SNAPSHOT_ROLLBACK: u64 = 137;
+/// `symlink(target_ptr, target_len, path_ptr, path_len) -> 0 | -errno`. The target
+/// is stored verbatim and need not exist.
+pub const SYS_SYMLINK: u64 = 138;
+/// `readlink(path_ptr, path_len, buf_ptr, buf_len) -> target_len | -errno`.
+/// `buf_len==0` probes, short → `ERANGE`; not a link → `EINVAL`.
+pub const SYS_READLINK: u64 = 139;
+/// `link(old_ptr, old_len, new_ptr, new_len) -> 0 | -errno`. Hard link; across
+/// mounts → `EXDEV`, a directory → `EISDIR`.
+pub const SYS_LINK: u64 = 140;
+/// `lstat(path_ptr, path_len, stat_buf) -> 0 | -errno`. `stat` that does not
+/// follow a symbolic link in the last component.
+pub const SYS_LSTAT: u64 = 141;
+/// `chmod(path_ptr, path_len, mode) -> 0 | -errno`. Sets the permission bits
+/// (`mode & 0o7777`); owner or root only (`EPERM`).
+pub const SYS_CHMOD: u64 = 142;
+/// `chown(path_ptr, path_len, uid, gid) -> 0 | -errno`. Root only (`EPERM`).
+pub const SYS_CHOWN: u64 = 143;
+/// `getcred() -> uid | gid << 32`. The caller's credential; uid 0 is root.
+pub const SYS_GETCRED: u64 = 144;
+/// `setcred(uid, gid) -> 0 | -errno`. Root only (`EPERM`) and one-way: the
+/// old uid is not saved. Spawned children inherit it; to start one child
+/// unprivileged and stay root, spawn with `SPAWN_SET_CRED` instead.
+pub const SYS_SETCRED: u64 = 145;
+/// `fs_clean(path_ptr, path_len, budget, stats_buf) -> 0 | -errno`. Runs one
+/// cleaner step of about `budget` blocks of I/O on the volume holding `path`
+/// (root only, `EPERM`), then fills a `CleanStats`. `budget == 0` only reports.
+pub const SYS_FS_CLEAN: u64 = 146;
+/// `fs_resize(path_ptr, path_len, lba_count) -> lba_count | -errno`. Grows or
+/// shrinks the volume mounted at exactly `path`, GPT entry and filesystem
+/// together, to `lba_count` sectors; 0 fills the free space after it, and a
+/// count past that space is `EINVAL`. Root only (`EPERM`).
+pub const SYS_FS_RESIZE: u64 = 147;
+/// `fs_prune(path_ptr, path_len, policy_ptr, policy_len) -> dropped | -errno`.
+/// Drops the retained versions of `path` (every file below it, for a
+/// directory) that `policy` — `XATTR_RETENTION` syntax — would not keep, and
+/// returns how many. Needs write access to `path`; deleted files, root only.
+pub const SYS_FS_PRUNE: u64 = 148;
+/// `fs_restore(path_ptr, path_len, lsn) -> 0 | -errno`. Brings a file back to
+/// the version current at `lsn` (see `SYS_VERSIONS`), deleted or not; a
+/// directory has every file below it restored and what was created since
+/// removed. Atomic. A directory, or a deleted path with no versions of its
+/// own, is root only (`EPERM`). `ENOENT` if no retained version or snapshot
+/// holds it; `EBUSY` inside a transaction.
+pub const SYS_FS_RESTORE: u64 = 149;
+/// `fs_watch(flags) -> fd | -errno`. A change-notification instance: `read`
+/// on it returns whole `FsEvent` records, blocking unless `O_NONBLOCK`, and
+/// it is `EPOLLIN` under `SYS_EPOLL_*` while events are queued. `flags`:
+/// `O_NONBLOCK | O_CLOEXEC`.
+pub const SYS_FS_WATCH: u64 = 150;
+/// `fs_watch_add(fd, path_ptr, path_len, mask) -> wd | -errno`. Reports the
+/// `FS_EV_*` changes in `mask` to `path` and, for a directory, its entries
+/// (everything below with `FS_WATCH_SUBTREE`). Watching a path again
+/// replaces its mask and returns the same `wd`. Needs read access to `path`.
+pub const SYS_FS_WATCH_ADD: u64 = 151;
+/// `fs_watch_rm(fd, wd) -> 0 | -errno`.
+pub const SYS_FS_WATCH_RM: u64 = 152;
+/// `statfs(path_ptr, path_len, *mut StatFs) -> 0 | -errno`. Space and health
+/// of the volume holding `path`.
+pub const SYS_STATFS: u64 = 153;
+/// `fstatfs(fd, *mut StatFs) -> 0 | -errno`. `statfs` by open file.
+pub const SYS_FSTATFS: u64 = 154;
 
 // Seek whence constants.
 pub const SEEK_SET: u64 = 0;
@@ -246,7 +332,7 @@ pub const SEEK_END: u64 = 2;
 // insertion, gap, duplicate, or table/count mismatch a compile error.
 
 /// Number of defined syscalls. Bump by exactly one when appending.
-pub const SYSCALL_COUNT: usize = 130;
+pub const SYSCALL_COUNT: usize = 155;
 
 /// Every `SYS_*` number in ABI order. Length is pinned to `SYSCALL_COUNT`, so a
 /// missing/extra entry is itself a compile error.
@@ -381,6 +467,31 @@ pub const SYSCALL_TABLE: [u64; SYSCALL_COUNT] = [
     SYS_FCNTL,
     SYS_RMDIR,
     SYS_REPARENT,
+    SYS_FS_TX,
+    SYS_SETXATTR,
+    SYS_GETXATTR,
+    SYS_LISTXATTR,
+    SYS_REMOVEXATTR,
+    SYS_SNAPSHOTS,
+    SYS_SNAPSHOT_DELETE,
+    SYS_SNAPSHOT_ROLLBACK,
+    SYS_SYMLINK,
+    SYS_READLINK,
+    SYS_LINK,
+    SYS_LSTAT,
+    SYS_CHMOD,
+    SYS_CHOWN,
+    SYS_GETCRED,
+    SYS_SETCRED,
+    SYS_FS_CLEAN,
+    SYS_FS_RESIZE,
+    SYS_FS_PRUNE,
+    SYS_FS_RESTORE,
+    SYS_FS_WATCH,
+    SYS_FS_WATCH_ADD,
+    SYS_FS_WATCH_RM,
+    SYS_STATFS,
+    SYS_FSTATFS,
 ];
 
 const _: () = {
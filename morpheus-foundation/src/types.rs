@@ -4,7 +4,7 @@
 ///
 /// `mode` carries the `S_IFMT` type bits + low `0o7777` perm bits so std's
 /// `FileType`/`Permissions` are first-class. Readers trust `min(struct_size,
-/// size_of::<Self>())` and treat the `reserved` tail as future ino/dev/rdev/blocks.
+/// size_of::<Self>())` and treat the `reserved` tail as future ino/dev/rdev.
 #[derive(Clone, Copy, Debug, Default)]
 #[repr(C)]
 pub struct FileStat {
@@ -29,7 +29,10 @@ pub struct FileStat {
     pub gid: u32,
     pub version_count: u32,
     pub _pad0: u32,
-    pub reserved: [u64; 4],
+    /// Bytes the content occupies on the device: the compressed frame length
+    /// for a compressed Helix file, else `size`. 0 for directories.
+    pub physical_size: u64,
+    pub reserved: [u64; 3],
 }
 
 impl FileStat {
@@ -440,8 +443,8 @@ pub struct UdpRecvDesc {
 
 /// One row from `volumes(&mut buf, max)` — SYS_VOLUMES. lsblk-style projection of
 /// a `VolumeRegistry` entry. `device_kind` is `DEV_*`, `fs_type` is detected
-/// (`FS_NONE|FS_HELIX|FS_FAT32|FS_UNKNOWN`), `flags` is `VOL_*`. Both ids are
-/// generational handles (see `storage::pack`).
+/// (`FS_NONE|FS_HELIX|FS_FAT32|FS_ISO9660|FS_EXT4|FS_UNKNOWN`), `flags` is `VOL_*`.
+/// Both ids are generational handles (see `storage::pack`).
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct VolumeInfo {
@@ -554,6 +557,240 @@ impl Default for MountInfo {
     }
 }
 
+/// `SYS_MOUNT` `aux` under `MNT_SNAPSHOT`: which Helix snapshot to mount.
+/// `name_len == 0` selects the marker at `lsn`; otherwise the newest snapshot
+/// called `name[..name_len]` (and `lsn` is ignored).
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct SnapshotSpec {
+    pub version: u16,
+    pub struct_size: u16,
+    pub name_len: u16,
+    pub _pad0: [u8; 2],
+    pub lsn: u64,
+    pub name: [u8; 64],
+}
+
+impl SnapshotSpec {
+    pub const fn zeroed() -> Self {
+        Self {
+            version: 0,
+            struct_size: 0,
+            name_len: 0,
+            _pad0: [0u8; 2],
+            lsn: 0,
+            name: [0u8; 64],
+        }
+    }
+
+    /// Select the snapshot marker at `lsn`.
+    pub fn at_lsn(lsn: u64) -> Self {
+        Self {
+            struct_size: core::mem::size_of::<Self>() as u16,
+            lsn,
+            ..Self::zeroed()
+        }
+    }
+
+    /// Select the newest snapshot called `name`; `None` if it does not fit.
+    pub fn named(name: &str) -> Option<Self> {
+        let b = name.as_bytes();
+        let mut spec = Self::zeroed();
+        if b.is_empty() || b.len() > spec.name.len() {
+            return None;
+        }
+        spec.struct_size = core::mem::size_of::<Self>() as u16;
+        spec.name_len = b.len() as u16;
+        spec.name[..b.len()].copy_from_slice(b);
+        Some(spec)
+    }
+
+    /// The selected name, bounded by `name_len` (clamped to the buffer); empty
+    /// when selecting by LSN or on bad UTF-8.
+    pub fn name_str(&self) -> &str {
+        let n = (self.name_len as usize).min(self.name.len());
+        core::str::from_utf8(&self.name[..n]).unwrap_or("")
+    }
+}
+
+// `[u8; 64]` is past the array-`Default` bound, so derive can't reach it.
+impl Default for SnapshotSpec {
+    fn default() -> Self {
+        Self::zeroed()
+    }
+}
+
+/// `SYS_MOUNT` `aux` under `MNT_KEY`: the passphrase of an encrypted Helix
+/// volume, plus the `aux` the mount would otherwise have taken.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct MountKey {
+    pub passphrase_ptr: u64,
+    pub passphrase_len: u64,
+    pub aux: u64,
+}
+
+/// `SYS_MOUNT` `aux` under `MNT_MANIFEST`: the serialized manifest of a
+/// chunked ISO, as stored under `/.iso/` on the ESP.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct MountManifest {
+    pub manifest_ptr: u64,
+    pub manifest_len: u64,
+}
+
+/// One row from `snapshots(&mut buf, max)` — SYS_SNAPSHOTS. `lsn` is the handle
+/// for `O_AT_LSN` and `SnapshotSpec::at_lsn`.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct SnapshotInfo {
+    pub version: u16,
+    pub struct_size: u16,
+    pub name_len: u16,
+    pub _pad0: [u8; 2],
+    pub lsn: u64,
+    /// TSC nanoseconds since boot when the snapshot was taken.
+    pub timestamp_ns: u64,
+    pub name: [u8; 64],
+}
+
+impl SnapshotInfo {
+    pub const fn zeroed() -> Self {
+        Self {
+            version: 0,
+            struct_size: 0,
+            name_len: 0,
+            _pad0: [0u8; 2],
+            lsn: 0,
+            timestamp_ns: 0,
+            name: [0u8; 64],
+        }
+    }
+
+    /// The name, bounded by `name_len` (clamped to the buffer; lossy-empty on
+    /// bad UTF-8).
+    pub fn name_str(&self) -> &str {
+        let n = (self.name_len as usize).min(self.name.len());
+        core::str::from_utf8(&self.name[..n]).unwrap_or("")
+    }
+}
+
+/// `fs_clean(path, budget, &mut buf)` — SYS_FS_CLEAN. A volume's background
+/// cleaner progress since it was mounted.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct CleanStats {
+    pub version: u16,
+    pub struct_size: u16,
+    pub _pad0: u32,
+    /// Checkpoints taken early to recycle the log ring.
+    pub checkpoints: u64,
+    /// Entries the defragmenter has looked at.
+    pub files_scanned: u64,
+    /// Files moved into one contiguous run.
+    pub files_defragmented: u64,
+    /// Data blocks those moves copied.
+    pub blocks_moved: u64,
+    /// Complete defragmentation passes over the namespace.
+    pub passes: u64,
+    /// Blocks of I/O spent, checkpoints included.
+    pub io_blocks: u64,
+    pub reserved: [u64; 2],
+}
+
+/// `statfs(path, &mut buf)` / `fstatfs(fd, &mut buf)` — SYS_STATFS/SYS_FSTATFS.
+/// Space and health of a mounted volume, in `block_size` units. The log,
+/// index, snapshot and version figures are Helix's; 0 on other filesystems.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct StatFs {
+    pub version: u16,
+    pub struct_size: u16,
+    pub fs_type: u32,
+    /// `MNT_*` the volume was mounted with.
+    pub flags: u32,
+    pub block_size: u32,
+    pub mount_id: u64,
+    pub total_blocks: u64,
+    pub free_blocks: u64,
+    /// Allocated bytes only snapshots and retained versions hold; deleting
+    /// or pruning those is what frees them.
+    pub pinned_bytes: u64,
+    /// Live share of the log ring, percent; writes fail `ENOSPC` at 100
+    /// until a checkpoint recycles it.
+    pub log_used_pct: u32,
+    pub snapshots: u32,
+    /// Entries in the namespace.
+    pub index_live: u64,
+    /// Entries the index stores, tombstones awaiting a checkpoint included.
+    pub index_total: u64,
+    /// Versions retained under a retention policy.
+    pub versions: u64,
+    pub reserved: [u64; 4],
+}
+
+impl StatFs {
+    pub fn total_bytes(&self) -> u64 {
+        self.total_blocks.saturating_mul(self.block_size as u64)
+    }
+
+    pub fn free_bytes(&self) -> u64 {
+        self.free_blocks.saturating_mul(self.block_size as u64)
+    }
+}
+
+/// One record from `read` on a watch fd — SYS_FS_WATCH. `mask` is one
+/// `FS_EV_*` bit; `path` is absolute, the changed entry itself.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct FsEvent {
+    pub version: u16,
+    pub struct_size: u16,
+    pub path_len: u16,
+    pub _pad0: u16,
+    /// From `SYS_FS_WATCH_ADD`; 0 for `FS_EV_OVERFLOW`.
+    pub wd: u32,
+    pub mask: u32,
+    /// Pairs `FS_EV_MOVED_FROM` with its `FS_EV_MOVED_TO`; 0 otherwise.
+    pub cookie: u64,
+    pub path: [u8; 256],
+}
+
+impl FsEvent {
+    pub const fn zeroed() -> Self {
+        Self {
+            version: 0,
+            struct_size: 0,
+            path_len: 0,
+            _pad0: 0,
+            wd: 0,
+            mask: 0,
+            cookie: 0,
+            path: [0u8; 256],
+        }
+    }
+
+    /// The path, bounded by `path_len` (clamped to the buffer; lossy-empty on
+    /// bad UTF-8).
+    pub fn path_str(&self) -> &str {
+        let n = (self.path_len as usize).min(self.path.len());
+        core::str::from_utf8(&self.path[..n]).unwrap_or("")
+    }
+}
+
+impl Default for FsEvent {
+    fn default() -> Self {
+        Self::zeroed()
+    }
+}
+
+// `[u8; 64]` is past the array-`Default` bound, so derive can't reach it.
+impl Default for SnapshotInfo {
+    fn default() -> Self {
+        Self::zeroed()
+    }
+}
+
 // Fixed POSIX/option payloads carry no version head — the layout is the one
 // correct Linux x86-64 form (documented ABI exemption).
 
@@ -669,7 +906,9 @@ pub struct SpawnArgs {
     pub file_actions_count: u64,
     pub fa_stride: u32,
     pub _pad0: u32,
-    pub reserved: [u64; 4],
+    /// `uid | gid << 32` for the child; read only with `SPAWN_SET_CRED`.
+    pub cred: u64,
+    pub reserved: [u64; 3],
 }
 
 /// One `SpawnArgs.file_actions[]` record. `op` is `SPAWN_FA_*`, replayed in order.
@@ -809,6 +1048,22 @@ const _: () = {
     assert!(offset_of!(MountInfo, mount_id) == 16);
     assert!(offset_of!(MountInfo, mount_point) == 32);
 
+    assert!(size_of::<SnapshotSpec>() == 80 && align_of::<SnapshotSpec>() == 8);
+    assert!(offset_of!(SnapshotSpec, lsn) == 8);
+    assert!(offset_of!(SnapshotSpec, name) == 16);
+
+    assert!(size_of::<SnapshotInfo>() == 88 && align_of::<SnapshotInfo>() == 8);
+    assert!(offset_of!(SnapshotInfo, lsn) == 8);
+    assert!(offset_of!(SnapshotInfo, name) == 24);
+
+    assert!(size_of::<StatFs>() == 112 && align_of::<StatFs>() == 8);
+    assert!(offset_of!(StatFs, mount_id) == 16);
+    assert!(offset_of!(StatFs, log_used_pct) == 48);
+
+    assert!(size_of::<FsEvent>() == 280 && align_of::<FsEvent>() == 8);
+    assert!(offset_of!(FsEvent, wd) == 8);
+    assert!(offset_of!(FsEvent, path) == 24);
+
     assert!(size_of::<NicInfo>() == 24 && align_of::<NicInfo>() == 8);
     assert!(offset_of!(NicInfo, mac) == 8);
 
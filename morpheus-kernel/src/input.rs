@@ -128,6 +128,23 @@ pub fn poll_keyboard() -> Option<InputEvent> {
     event
 }
 
+/// Drain queued key bytes into `buf`, skipping other events. Returns the
+/// count (0 if the ring is empty); never blocks.
+pub fn read_keys(buf: &mut [u8]) -> usize {
+    let mut n = 0usize;
+    while n < buf.len() {
+        match poll_keyboard() {
+            Some(InputEvent::Key(byte, _process)) => {
+                buf[n] = byte;
+                n += 1;
+            },
+            Some(_) => continue,
+            None => break,
+        }
+    }
+    n
+}
+
 pub fn has_keyboard() -> bool {
     *KEYBOARD_REGISTERED.lock()
 }
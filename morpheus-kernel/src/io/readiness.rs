@@ -1,9 +1,9 @@
 //! Per-object readiness + a true kernel blocking primitive (no busy-poll).
 //!
-//! A pollable object (socket/pipe end, epoll instance) is named by a stable `u64`
-//! token (see [`socket_token`]/[`pipe_token`]/[`epoll_token`]) carrying a
-//! level-triggered `EPOLL*` mask. Backends [`set_ready`]/[`clear_ready`]; readers
-//! [`ready_mask`] for `epoll_wait`/`poll` or [`wait_ready`] to park.
+//! A pollable object (socket/pipe end, epoll or watch instance) is named by a stable
+//! `u64` token (see [`socket_token`]/[`pipe_token`]/[`epoll_token`]/[`watch_token`])
+//! carrying a level-triggered `EPOLL*` mask. Backends [`set_ready`]/[`clear_ready`];
+//! readers [`ready_mask`] for `epoll_wait`/`poll` or [`wait_ready`] to park.
 //!
 //! Locking discipline closing the lost-wakeup hole without a lock-order cycle: the
 //! per-token mask is a lock-free `AtomicU32`. [`set_ready`] does the atomic OR
@@ -29,6 +29,7 @@ const CLASS_SHIFT: u64 = 56;
 const CLASS_SOCKET: u64 = 1 << CLASS_SHIFT;
 const CLASS_PIPE: u64 = 2 << CLASS_SHIFT;
 const CLASS_EPOLL: u64 = 3 << CLASS_SHIFT;
+const CLASS_WATCH: u64 = 4 << CLASS_SHIFT;
 const ID_MASK: u64 = (1 << CLASS_SHIFT) - 1;
 
 #[inline]
@@ -46,6 +47,11 @@ pub fn epoll_token(epfd_cookie: u64) -> u64 {
     CLASS_EPOLL | (epfd_cookie & ID_MASK)
 }
 
+#[inline]
+pub fn watch_token(instance: u64) -> u64 {
+    CLASS_WATCH | (instance & ID_MASK)
+}
+
 struct Source {
     /// 0 = free slot. Non-zero = the owning backend's token.
     token: AtomicU64,
@@ -129,6 +135,14 @@ pub fn set_ready(token: u64, add: u32) {
     wake_token(token);
 }
 
+/// [`set_ready`] without the wake, for producers that may run under
+/// `PROCESS_TABLE_LOCK`. Their waiters must park with a deadline and re-check.
+pub fn mark_ready(token: u64, add: u32) {
+    if let Some(i) = slot_for(token) {
+        SOURCES[i].mask.fetch_or(add, Ordering::AcqRel);
+    }
+}
+
 /// Clear `sub` from `token`'s mask — a level→edge transition, e.g. after a
 /// non-blocking `recv` returns `EWOULDBLOCK` the socket layer clears `EPOLLIN`.
 pub fn clear_ready(token: u64, sub: u32) {
@@ -246,6 +246,11 @@ pub struct Process {
     pub cwd: [u8; 256],
     pub cwd_len: u16,
 
+    /// Credential the VFS checks permission bits against; 0 = root, which
+    /// bypasses them. Inherited across spawn; only root may change it.
+    pub uid: u32,
+    pub gid: u32,
+
     /// Spawn args, NUL-separated; retrieved via SYS_GETARGS.
     pub args: [u8; 256],
     pub args_len: u16,
@@ -334,6 +339,8 @@ impl Process {
             vma_table: VmaTable::new(),
             cwd,
             cwd_len: 1,
+            uid: 0,
+            gid: 0,
             args: [0u8; 256],
             args_len: 0,
             argc: 0,
@@ -380,6 +387,13 @@ impl Process {
         self.cwd_len = len as u16;
     }
 
+    pub fn cred(&self) -> crate::storage::fs_api::Cred {
+        crate::storage::fs_api::Cred {
+            uid: self.uid,
+            gid: self.gid,
+        }
+    }
+
     pub fn cwd_str(&self) -> &str {
         let len = self.cwd_len as usize;
         core::str::from_utf8(&self.cwd[..len]).unwrap_or("/")
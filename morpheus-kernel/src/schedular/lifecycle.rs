@@ -136,9 +136,11 @@ pub unsafe fn spawn_kernel_thread(
     {
         // CpuContext is opaque; HAL applies arch-side selectors (KERNEL_CS/DS).
         proc.context = CpuContext::zeroed();
+        // Enter as if called: SysV expects rsp ≡ 8 (mod 16) at the first
+        // instruction, where a return address would sit.
         hal()
             .cpu()
-            .ctx_init_kernel(&mut proc.context, entry_fn, proc.kernel_stack_top);
+            .ctx_init_kernel(&mut proc.context, entry_fn, proc.kernel_stack_top - 8);
     }
 
     let _ = (pid, entry_fn);
@@ -65,6 +65,7 @@ pub unsafe fn spawn_user_thread(
     let parent_mmap_brk = parent.mmap_brk;
     let parent_cwd = parent.cwd;
     let parent_cwd_len = parent.cwd_len;
+    let (parent_uid, parent_gid) = (parent.uid, parent.gid);
 
     let group_leader = if parent.thread_group_leader != 0 {
         parent.thread_group_leader
@@ -100,6 +101,8 @@ pub unsafe fn spawn_user_thread(
     thread.mmap_brk = parent_mmap_brk;
     thread.cwd = parent_cwd;
     thread.cwd_len = parent_cwd_len;
+    thread.uid = parent_uid;
+    thread.gid = parent_gid;
     thread.tls_base = tls_base;
     thread.ctid_ptr = ctid_ptr;
     thread.detached = flags & THREAD_DETACHED != 0;
@@ -167,7 +170,9 @@ unsafe fn inherit_fds_minus_cloexec(child: &mut Process, parent: &Process) {
 }
 
 /// Spawn an independent process from an ELF image. `clear_fds` overrides
-/// `inherit_fds`, starting the child with an empty fd table.
+/// `inherit_fds`, starting the child with an empty fd table. `cred` sets the
+/// child's `(uid, gid)`; `None` inherits the parent's. Callers check that the
+/// parent may grant it.
 #[allow(clippy::too_many_arguments)]
 pub unsafe fn spawn_user_process(
     name: &str,
@@ -178,6 +183,7 @@ pub unsafe fn spawn_user_process(
     cwd: Option<&str>,
     inherit_fds: bool,
     clear_fds: bool,
+    cred: Option<(u32, u32)>,
 ) -> Result<u32, &'static str> {
     if !SCHEDULER_READY {
         return Err("scheduler not initialized");
@@ -229,6 +235,14 @@ pub unsafe fn spawn_user_process(
         },
     }
 
+    if let Some((uid, gid)) = cred {
+        proc.uid = uid;
+        proc.gid = gid;
+    } else if let Some(Some(parent)) = PROCESS_TABLE.get(proc.parent_pid as usize) {
+        proc.uid = parent.uid;
+        proc.gid = parent.gid;
+    }
+
     if inherit_fds && !clear_fds {
         let parent_pid = proc.parent_pid as usize;
         if let Some(Some(parent)) = PROCESS_TABLE.get(parent_pid) {
@@ -67,6 +67,9 @@ pub(super) static KERNEL_LAST_WAS_IDLE: core::sync::atomic::AtomicBool =
 pub(super) static KERNEL_SKIP_STREAK: core::sync::atomic::AtomicU32 =
     core::sync::atomic::AtomicU32::new(0);
 pub(super) const MAX_KERNEL_SKIP: u32 = 1;
+/// `try_inspect_processes` attempts before giving up: long enough to outlast
+/// any holder that isn't itself waiting on us.
+const INSPECT_TRIES: u32 = 1 << 16;
 pub(super) static SCHED_SYSTEM_STATE: AtomicU8 =
     AtomicU8::new(SchedulerSystemState::Balanced as u8);
 pub(super) static PER_CORE_STATE: [AtomicU8; MAX_CPUS] =
@@ -636,6 +639,23 @@ impl Scheduler {
         n
     }
 
+    /// Run `f` over the process table under `PROCESS_TABLE_LOCK`, for callers
+    /// holding `STORAGE_LOCK`, which orders after it. The lock is only tried:
+    /// a holder stuck waiting on storage yields `None` instead of a deadlock.
+    /// `f` must not take the OFD or storage locks.
+    pub fn try_inspect_processes<R>(&self, f: impl FnOnce(&[Option<Process>]) -> R) -> Option<R> {
+        for _ in 0..INSPECT_TRIES {
+            if PROCESS_TABLE_LOCK.try_lock() {
+                // SAFETY: the table lock is held until after `f` returns.
+                let r = f(unsafe { &PROCESS_TABLE });
+                PROCESS_TABLE_LOCK.unlock();
+                return Some(r);
+            }
+            core::hint::spin_loop();
+        }
+        None
+    }
+
     pub fn live_count(&self) -> u32 {
         LIVE_COUNT.load(Ordering::Relaxed)
     }
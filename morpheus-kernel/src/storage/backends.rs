@@ -3,14 +3,21 @@
 //! the compiler flags every dispatch site that misses it. Each adapter wraps a
 //! pure engine crate and maps its private error → `VfsError`.
 
-use super::fs_api::{FdState, FsBackend, FsCapabilities, OpenFile, VfsError};
+use super::devfs::DevFs;
+use super::fs_api::{FdState, FsBackend, FsCapabilities, FsChange, OpenFile, VfsError};
+use super::procfs::ProcFs;
+use alloc::string::String;
 use alloc::vec::Vec;
 use gpt_disk_io::BlockIo;
 use gpt_disk_types::{BlockSize, Lba};
 use morpheus_block_types::{RawBlockDevice, RawIoError};
 use morpheus_foundation::flags::{dirent_type, mode, open_flags};
-use morpheus_foundation::storage::FD_COOKIE_LEN;
-use morpheus_foundation::types::{DirEntry, FileStat};
+use morpheus_foundation::storage::{
+    FD_COOKIE_LEN, FS_EV_ATTRIB, FS_EV_CREATE, FS_EV_DELETE, FS_EV_MODIFY, FS_EV_MOVED_FROM,
+};
+use morpheus_foundation::types::{CleanStats, DirEntry, FileStat, StatFs};
+use morpheus_helix::crypt::{Crypt, CryptIo};
+use morpheus_helix::log::feed::ChangeKind;
 
 /// Static-dispatch FS handle. One variant per backend; never a trait object.
 // Boxing the large variant would put a vtable-free allocation in the I/O path,
@@ -19,6 +26,12 @@ use morpheus_foundation::types::{DirEntry, FileStat};
 pub enum MountedFs {
     Helix(HelixFs),
     Fat32(Fat32Fs),
+    Iso9660(Iso9660Fs),
+    Ext4(Ext4Fs),
+    /// Synthetic `/proc` or `/sys`.
+    Proc(ProcFs),
+    /// Synthetic `/dev`.
+    Dev(DevFs),
 }
 
 impl MountedFs {
@@ -26,6 +39,10 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.capabilities(),
             MountedFs::Fat32(f) => f.capabilities(),
+            MountedFs::Iso9660(f) => f.capabilities(),
+            MountedFs::Ext4(f) => f.capabilities(),
+            MountedFs::Proc(f) => f.capabilities(),
+            MountedFs::Dev(f) => f.capabilities(),
         }
     }
     pub fn open(
@@ -38,6 +55,10 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.open(dev, path, flags, ts),
             MountedFs::Fat32(f) => f.open(dev, path, flags, ts),
+            MountedFs::Iso9660(f) => f.open(dev, path, flags, ts),
+            MountedFs::Ext4(f) => f.open(dev, path, flags, ts),
+            MountedFs::Proc(f) => f.open(dev, path, flags, ts),
+            MountedFs::Dev(f) => f.open(dev, path, flags, ts),
         }
     }
     pub fn read(
@@ -49,12 +70,20 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.read(dev, f, buf),
             MountedFs::Fat32(fs) => fs.read(dev, f, buf),
+            MountedFs::Iso9660(fs) => fs.read(dev, f, buf),
+            MountedFs::Ext4(fs) => fs.read(dev, f, buf),
+            MountedFs::Proc(fs) => fs.read(dev, f, buf),
+            MountedFs::Dev(fs) => fs.read(dev, f, buf),
         }
     }
     pub fn stat(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<FileStat, VfsError> {
         match self {
             MountedFs::Helix(h) => h.stat(dev, path),
             MountedFs::Fat32(f) => f.stat(dev, path),
+            MountedFs::Iso9660(f) => f.stat(dev, path),
+            MountedFs::Ext4(f) => f.stat(dev, path),
+            MountedFs::Proc(f) => f.stat(dev, path),
+            MountedFs::Dev(f) => f.stat(dev, path),
         }
     }
     pub fn readdir(
@@ -65,12 +94,20 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.readdir(dev, path),
             MountedFs::Fat32(f) => f.readdir(dev, path),
+            MountedFs::Iso9660(f) => f.readdir(dev, path),
+            MountedFs::Ext4(f) => f.readdir(dev, path),
+            MountedFs::Proc(f) => f.readdir(dev, path),
+            MountedFs::Dev(f) => f.readdir(dev, path),
         }
     }
     pub fn close(&mut self, dev: &mut RawBlockDevice, f: &FdState) -> Result<(), VfsError> {
         match self {
             MountedFs::Helix(h) => h.close(dev, f),
             MountedFs::Fat32(fs) => fs.close(dev, f),
+            MountedFs::Iso9660(fs) => fs.close(dev, f),
+            MountedFs::Ext4(fs) => fs.close(dev, f),
+            MountedFs::Proc(fs) => fs.close(dev, f),
+            MountedFs::Dev(fs) => fs.close(dev, f),
         }
     }
     pub fn write(
@@ -83,12 +120,20 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.write(dev, f, buf, ts),
             MountedFs::Fat32(fs) => fs.write(dev, f, buf, ts),
+            MountedFs::Iso9660(fs) => fs.write(dev, f, buf, ts),
+            MountedFs::Ext4(fs) => fs.write(dev, f, buf, ts),
+            MountedFs::Proc(fs) => fs.write(dev, f, buf, ts),
+            MountedFs::Dev(fs) => fs.write(dev, f, buf, ts),
         }
     }
     pub fn mkdir(&mut self, dev: &mut RawBlockDevice, path: &str, ts: u64) -> Result<(), VfsError> {
         match self {
             MountedFs::Helix(h) => h.mkdir(dev, path, ts),
             MountedFs::Fat32(f) => f.mkdir(dev, path, ts),
+            MountedFs::Iso9660(f) => f.mkdir(dev, path, ts),
+            MountedFs::Ext4(f) => f.mkdir(dev, path, ts),
+            MountedFs::Proc(f) => f.mkdir(dev, path, ts),
+            MountedFs::Dev(f) => f.mkdir(dev, path, ts),
         }
     }
     pub fn unlink(
@@ -100,6 +145,10 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.unlink(dev, path, ts),
             MountedFs::Fat32(f) => f.unlink(dev, path, ts),
+            MountedFs::Iso9660(f) => f.unlink(dev, path, ts),
+            MountedFs::Ext4(f) => f.unlink(dev, path, ts),
+            MountedFs::Proc(f) => f.unlink(dev, path, ts),
+            MountedFs::Dev(f) => f.unlink(dev, path, ts),
         }
     }
     pub fn rename(
@@ -112,6 +161,10 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.rename(dev, old, new, ts),
             MountedFs::Fat32(f) => f.rename(dev, old, new, ts),
+            MountedFs::Iso9660(f) => f.rename(dev, old, new, ts),
+            MountedFs::Ext4(f) => f.rename(dev, old, new, ts),
+            MountedFs::Proc(f) => f.rename(dev, old, new, ts),
+            MountedFs::Dev(f) => f.rename(dev, old, new, ts),
         }
     }
     pub fn truncate(
@@ -124,12 +177,177 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.truncate(dev, path, size, ts),
             MountedFs::Fat32(f) => f.truncate(dev, path, size, ts),
+            MountedFs::Iso9660(f) => f.truncate(dev, path, size, ts),
+            MountedFs::Ext4(f) => f.truncate(dev, path, size, ts),
+            MountedFs::Proc(f) => f.truncate(dev, path, size, ts),
+            MountedFs::Dev(f) => f.truncate(dev, path, size, ts),
+        }
+    }
+    pub fn symlink(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        target: &str,
+        path: &str,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.symlink(dev, target, path, ts),
+            MountedFs::Fat32(f) => f.symlink(dev, target, path, ts),
+            MountedFs::Iso9660(f) => f.symlink(dev, target, path, ts),
+            MountedFs::Ext4(f) => f.symlink(dev, target, path, ts),
+            MountedFs::Proc(f) => f.symlink(dev, target, path, ts),
+            MountedFs::Dev(f) => f.symlink(dev, target, path, ts),
+        }
+    }
+    pub fn readlink(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<String, VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.readlink(dev, path),
+            MountedFs::Fat32(f) => f.readlink(dev, path),
+            MountedFs::Iso9660(f) => f.readlink(dev, path),
+            MountedFs::Ext4(f) => f.readlink(dev, path),
+            MountedFs::Proc(f) => f.readlink(dev, path),
+            MountedFs::Dev(f) => f.readlink(dev, path),
+        }
+    }
+    pub fn link(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        old: &str,
+        new: &str,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.link(dev, old, new, ts),
+            MountedFs::Fat32(f) => f.link(dev, old, new, ts),
+            MountedFs::Iso9660(f) => f.link(dev, old, new, ts),
+            MountedFs::Ext4(f) => f.link(dev, old, new, ts),
+            MountedFs::Proc(f) => f.link(dev, old, new, ts),
+            MountedFs::Dev(f) => f.link(dev, old, new, ts),
+        }
+    }
+    pub fn chmod(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        mode: u32,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.chmod(dev, path, mode, ts),
+            MountedFs::Fat32(f) => f.chmod(dev, path, mode, ts),
+            MountedFs::Iso9660(f) => f.chmod(dev, path, mode, ts),
+            MountedFs::Ext4(f) => f.chmod(dev, path, mode, ts),
+            MountedFs::Proc(f) => f.chmod(dev, path, mode, ts),
+            MountedFs::Dev(f) => f.chmod(dev, path, mode, ts),
+        }
+    }
+    pub fn chown(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        uid: u32,
+        gid: u32,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.chown(dev, path, uid, gid, ts),
+            MountedFs::Fat32(f) => f.chown(dev, path, uid, gid, ts),
+            MountedFs::Iso9660(f) => f.chown(dev, path, uid, gid, ts),
+            MountedFs::Ext4(f) => f.chown(dev, path, uid, gid, ts),
+            MountedFs::Proc(f) => f.chown(dev, path, uid, gid, ts),
+            MountedFs::Dev(f) => f.chown(dev, path, uid, gid, ts),
         }
     }
     pub fn sync(&mut self, dev: &mut RawBlockDevice) -> Result<(), VfsError> {
         match self {
             MountedFs::Helix(h) => h.sync(dev),
             MountedFs::Fat32(f) => f.sync(dev),
+            MountedFs::Iso9660(f) => f.sync(dev),
+            MountedFs::Ext4(f) => f.sync(dev),
+            MountedFs::Proc(f) => f.sync(dev),
+            MountedFs::Dev(f) => f.sync(dev),
+        }
+    }
+    pub fn clean(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        budget: u64,
+        ts: u64,
+    ) -> Result<bool, VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.clean(dev, budget, ts),
+            MountedFs::Fat32(f) => f.clean(dev, budget, ts),
+            MountedFs::Iso9660(f) => f.clean(dev, budget, ts),
+            MountedFs::Ext4(f) => f.clean(dev, budget, ts),
+            MountedFs::Proc(f) => f.clean(dev, budget, ts),
+            MountedFs::Dev(f) => f.clean(dev, budget, ts),
+        }
+    }
+    pub fn clean_stats(&mut self, dev: &mut RawBlockDevice) -> Result<CleanStats, VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.clean_stats(dev),
+            MountedFs::Fat32(f) => f.clean_stats(dev),
+            MountedFs::Iso9660(f) => f.clean_stats(dev),
+            MountedFs::Ext4(f) => f.clean_stats(dev),
+            MountedFs::Proc(f) => f.clean_stats(dev),
+            MountedFs::Dev(f) => f.clean_stats(dev),
+        }
+    }
+    pub fn statfs(&mut self, dev: &mut RawBlockDevice) -> Result<StatFs, VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.statfs(dev),
+            MountedFs::Fat32(f) => f.statfs(dev),
+            MountedFs::Iso9660(f) => f.statfs(dev),
+            MountedFs::Ext4(f) => f.statfs(dev),
+            MountedFs::Proc(f) => f.statfs(dev),
+            MountedFs::Dev(f) => f.statfs(dev),
+        }
+    }
+    pub fn resize(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        lba_count: u64,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.resize(dev, lba_count, ts),
+            MountedFs::Fat32(f) => f.resize(dev, lba_count, ts),
+            MountedFs::Iso9660(f) => f.resize(dev, lba_count, ts),
+            MountedFs::Ext4(f) => f.resize(dev, lba_count, ts),
+            MountedFs::Proc(f) => f.resize(dev, lba_count, ts),
+            MountedFs::Dev(f) => f.resize(dev, lba_count, ts),
+        }
+    }
+    pub fn prune_versions(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        policy: &[u8],
+        ts: u64,
+    ) -> Result<u64, VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.prune_versions(dev, path, policy, ts),
+            MountedFs::Fat32(f) => f.prune_versions(dev, path, policy, ts),
+            MountedFs::Iso9660(f) => f.prune_versions(dev, path, policy, ts),
+            MountedFs::Ext4(f) => f.prune_versions(dev, path, policy, ts),
+            MountedFs::Proc(f) => f.prune_versions(dev, path, policy, ts),
+            MountedFs::Dev(f) => f.prune_versions(dev, path, policy, ts),
+        }
+    }
+    pub fn restore(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        lsn: u64,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.restore(dev, path, lsn, ts),
+            MountedFs::Fat32(f) => f.restore(dev, path, lsn, ts),
+            MountedFs::Iso9660(f) => f.restore(dev, path, lsn, ts),
+            MountedFs::Ext4(f) => f.restore(dev, path, lsn, ts),
+            MountedFs::Proc(f) => f.restore(dev, path, lsn, ts),
+            MountedFs::Dev(f) => f.restore(dev, path, lsn, ts),
         }
     }
     pub fn snapshot(
@@ -141,6 +359,10 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.snapshot(dev, name, ts),
             MountedFs::Fat32(f) => f.snapshot(dev, name, ts),
+            MountedFs::Iso9660(f) => f.snapshot(dev, name, ts),
+            MountedFs::Ext4(f) => f.snapshot(dev, name, ts),
+            MountedFs::Proc(f) => f.snapshot(dev, name, ts),
+            MountedFs::Dev(f) => f.snapshot(dev, name, ts),
         }
     }
     pub fn versions(
@@ -151,6 +373,164 @@ impl MountedFs {
         match self {
             MountedFs::Helix(h) => h.versions(dev, path),
             MountedFs::Fat32(f) => f.versions(dev, path),
+            MountedFs::Iso9660(f) => f.versions(dev, path),
+            MountedFs::Ext4(f) => f.versions(dev, path),
+            MountedFs::Proc(f) => f.versions(dev, path),
+            MountedFs::Dev(f) => f.versions(dev, path),
+        }
+    }
+    pub fn tx_begin(&mut self, dev: &mut RawBlockDevice, ts: u64) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.tx_begin(dev, ts),
+            MountedFs::Fat32(f) => f.tx_begin(dev, ts),
+            MountedFs::Iso9660(f) => f.tx_begin(dev, ts),
+            MountedFs::Ext4(f) => f.tx_begin(dev, ts),
+            MountedFs::Proc(f) => f.tx_begin(dev, ts),
+            MountedFs::Dev(f) => f.tx_begin(dev, ts),
+        }
+    }
+    pub fn tx_commit(&mut self, dev: &mut RawBlockDevice, ts: u64) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.tx_commit(dev, ts),
+            MountedFs::Fat32(f) => f.tx_commit(dev, ts),
+            MountedFs::Iso9660(f) => f.tx_commit(dev, ts),
+            MountedFs::Ext4(f) => f.tx_commit(dev, ts),
+            MountedFs::Proc(f) => f.tx_commit(dev, ts),
+            MountedFs::Dev(f) => f.tx_commit(dev, ts),
+        }
+    }
+    pub fn tx_abort(&mut self, dev: &mut RawBlockDevice, ts: u64) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.tx_abort(dev, ts),
+            MountedFs::Fat32(f) => f.tx_abort(dev, ts),
+            MountedFs::Iso9660(f) => f.tx_abort(dev, ts),
+            MountedFs::Ext4(f) => f.tx_abort(dev, ts),
+            MountedFs::Proc(f) => f.tx_abort(dev, ts),
+            MountedFs::Dev(f) => f.tx_abort(dev, ts),
+        }
+    }
+    pub fn watch(&mut self, on: bool) {
+        match self {
+            MountedFs::Helix(h) => h.watch(on),
+            MountedFs::Fat32(f) => f.watch(on),
+            MountedFs::Iso9660(f) => f.watch(on),
+            MountedFs::Ext4(f) => f.watch(on),
+            MountedFs::Proc(f) => f.watch(on),
+            MountedFs::Dev(f) => f.watch(on),
+        }
+    }
+    pub fn take_changes(&mut self) -> (Vec<FsChange>, bool) {
+        match self {
+            MountedFs::Helix(h) => h.take_changes(),
+            MountedFs::Fat32(f) => f.take_changes(),
+            MountedFs::Iso9660(f) => f.take_changes(),
+            MountedFs::Ext4(f) => f.take_changes(),
+            MountedFs::Proc(f) => f.take_changes(),
+            MountedFs::Dev(f) => f.take_changes(),
+        }
+    }
+    pub fn setxattr(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        name: &str,
+        value: &[u8],
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.setxattr(dev, path, name, value, ts),
+            MountedFs::Fat32(f) => f.setxattr(dev, path, name, value, ts),
+            MountedFs::Iso9660(f) => f.setxattr(dev, path, name, value, ts),
+            MountedFs::Ext4(f) => f.setxattr(dev, path, name, value, ts),
+            MountedFs::Proc(f) => f.setxattr(dev, path, name, value, ts),
+            MountedFs::Dev(f) => f.setxattr(dev, path, name, value, ts),
+        }
+    }
+    pub fn getxattr(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        name: &str,
+    ) -> Result<Vec<u8>, VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.getxattr(dev, path, name),
+            MountedFs::Fat32(f) => f.getxattr(dev, path, name),
+            MountedFs::Iso9660(f) => f.getxattr(dev, path, name),
+            MountedFs::Ext4(f) => f.getxattr(dev, path, name),
+            MountedFs::Proc(f) => f.getxattr(dev, path, name),
+            MountedFs::Dev(f) => f.getxattr(dev, path, name),
+        }
+    }
+    pub fn listxattr(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+    ) -> Result<Vec<String>, VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.listxattr(dev, path),
+            MountedFs::Fat32(f) => f.listxattr(dev, path),
+            MountedFs::Iso9660(f) => f.listxattr(dev, path),
+            MountedFs::Ext4(f) => f.listxattr(dev, path),
+            MountedFs::Proc(f) => f.listxattr(dev, path),
+            MountedFs::Dev(f) => f.listxattr(dev, path),
+        }
+    }
+    pub fn removexattr(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        name: &str,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.removexattr(dev, path, name, ts),
+            MountedFs::Fat32(f) => f.removexattr(dev, path, name, ts),
+            MountedFs::Iso9660(f) => f.removexattr(dev, path, name, ts),
+            MountedFs::Ext4(f) => f.removexattr(dev, path, name, ts),
+            MountedFs::Proc(f) => f.removexattr(dev, path, name, ts),
+            MountedFs::Dev(f) => f.removexattr(dev, path, name, ts),
+        }
+    }
+    pub fn snapshots(
+        &mut self,
+        dev: &mut RawBlockDevice,
+    ) -> Result<Vec<(u64, u64, String)>, VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.snapshots(dev),
+            MountedFs::Fat32(f) => f.snapshots(dev),
+            MountedFs::Iso9660(f) => f.snapshots(dev),
+            MountedFs::Ext4(f) => f.snapshots(dev),
+            MountedFs::Proc(f) => f.snapshots(dev),
+            MountedFs::Dev(f) => f.snapshots(dev),
+        }
+    }
+    pub fn delete_snapshot(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        name: &str,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.delete_snapshot(dev, name),
+            MountedFs::Fat32(f) => f.delete_snapshot(dev, name),
+            MountedFs::Iso9660(f) => f.delete_snapshot(dev, name),
+            MountedFs::Ext4(f) => f.delete_snapshot(dev, name),
+            MountedFs::Proc(f) => f.delete_snapshot(dev, name),
+            MountedFs::Dev(f) => f.delete_snapshot(dev, name),
+        }
+    }
+    pub fn rollback_snapshot(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        name: &str,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        match self {
+            MountedFs::Helix(h) => h.rollback_snapshot(dev, name, ts),
+            MountedFs::Fat32(f) => f.rollback_snapshot(dev, name, ts),
+            MountedFs::Iso9660(f) => f.rollback_snapshot(dev, name, ts),
+            MountedFs::Ext4(f) => f.rollback_snapshot(dev, name, ts),
+            MountedFs::Proc(f) => f.rollback_snapshot(dev, name, ts),
+            MountedFs::Dev(f) => f.rollback_snapshot(dev, name, ts),
         }
     }
 }
@@ -173,10 +553,16 @@ fn helix_err(e: morpheus_helix::HelixError) -> VfsError {
         TooManyOpenFiles => VfsError::TooManyOpen,
         ReadOnly => VfsError::ReadOnly,
         NoSpace | LogFull | FileTooLarge => VfsError::NoSpace,
-        PermissionDenied => VfsError::Perm,
+        PermissionDenied | Encrypted | BadPassphrase => VfsError::Perm,
         PathTooLong => VfsError::NameTooLong,
         InvalidOffset | PathInvalid | InvalidBlockSize | FormatTooSmall => VfsError::Inval,
         NotSupported => VfsError::Unsupported,
+        TxConflict | InUse => VfsError::Busy,
+        SnapshotTableFull => VfsError::NoSpace,
+        NoActiveTransaction | NotASymlink => VfsError::Inval,
+        NoAttribute => VfsError::NoData,
+        AttributeTooLarge => VfsError::TooBig,
+        QuotaExceeded => VfsError::Quota,
         IoReadFailed | IoWriteFailed | IoFlushFailed => VfsError::Io,
         _ => VfsError::Io,
     }
@@ -184,12 +570,18 @@ fn helix_err(e: morpheus_helix::HelixError) -> VfsError {
 
 pub struct HelixFs {
     engine: morpheus_helix::HelixFs,
+    /// Key of an encrypted volume; every engine call sees `dev` through it.
+    crypt: Option<Crypt>,
     read_only: bool,
 }
 
 impl HelixFs {
-    pub fn new(engine: morpheus_helix::HelixFs, read_only: bool) -> Self {
-        Self { engine, read_only }
+    pub fn new(engine: morpheus_helix::HelixFs, crypt: Option<Crypt>, read_only: bool) -> Self {
+        Self {
+            engine,
+            crypt,
+            read_only,
+        }
     }
 }
 
@@ -204,9 +596,11 @@ impl FsBackend for HelixFs {
     fn capabilities(&self) -> FsCapabilities {
         FsCapabilities {
             writable: !self.read_only,
-            resizable: !self.read_only,
+            // The tag region of an encrypted volume is sized at format.
+            resizable: !self.read_only && self.crypt.is_none(),
             snapshots: !self.read_only,
             versions: true,
+            ownership: true,
         }
     }
 
@@ -220,10 +614,13 @@ impl FsBackend for HelixFs {
         if self.read_only && flags & (open_flags::O_WRITE | open_flags::O_CREATE) != 0 {
             return Err(VfsError::ReadOnly);
         }
-        let key = self.engine.open(dev, path, flags, ts).map_err(helix_err)?;
+        let key = self
+            .engine
+            .open(&mut CryptIo::new(dev, self.crypt.as_mut()), path, flags, ts)
+            .map_err(helix_err)?;
         let is_dir = self
             .engine
-            .stat(path)
+            .stat(&mut CryptIo::new(dev, self.crypt.as_mut()), path)
             .map(|st| st.mode & mode::S_IFMT == mode::S_IFDIR)
             .unwrap_or(false);
         Ok(OpenFile {
@@ -238,27 +635,26 @@ impl FsBackend for HelixFs {
         f: &FdState,
         buf: &mut [u8],
     ) -> Result<usize, VfsError> {
-        // Engine reads whole files; the fd offset slices here.
-        let data = self.engine.read(dev, f.path_str()).map_err(helix_err)?;
-        let off = f.offset as usize;
-        if off >= data.len() {
-            return Ok(0);
-        }
-        let n = buf.len().min(data.len() - off);
-        buf[..n].copy_from_slice(&data[off..off + n]);
-        Ok(n)
+        self.engine
+            .read_at(
+                &mut CryptIo::new(dev, self.crypt.as_mut()),
+                f.path_str(),
+                f.offset,
+                buf,
+            )
+            .map_err(helix_err)
     }
 
-    fn stat(&mut self, _dev: &mut RawBlockDevice, path: &str) -> Result<FileStat, VfsError> {
-        self.engine.stat(path).map_err(helix_err)
+    fn stat(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<FileStat, VfsError> {
+        self.engine
+            .stat(&mut CryptIo::new(dev, self.crypt.as_mut()), path)
+            .map_err(helix_err)
     }
 
-    fn readdir(
-        &mut self,
-        _dev: &mut RawBlockDevice,
-        path: &str,
-    ) -> Result<Vec<DirEntry>, VfsError> {
-        self.engine.readdir(path).map_err(helix_err)
+    fn readdir(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<Vec<DirEntry>, VfsError> {
+        self.engine
+            .readdir(&mut CryptIo::new(dev, self.crypt.as_mut()), path)
+            .map_err(helix_err)
     }
 
     fn write(
@@ -271,25 +667,23 @@ impl FsBackend for HelixFs {
         if self.read_only {
             return Err(VfsError::ReadOnly);
         }
-        // Read-modify-write: splice `buf` in at the fd offset (the engine is
-        // whole-file). A missing file means "start empty" (fresh O_CREATE before
-        // its first flush); any other read error must propagate, or a transient
-        // I/O fault would silently clobber existing data with the spliced buffer.
-        let mut data = match self.engine.read(dev, f.path_str()) {
-            Ok(d) => d,
-            Err(morpheus_helix::HelixError::NotFound) => Vec::new(),
-            Err(e) => return Err(helix_err(e)),
-        };
-        let off = f.offset as usize;
-        let end = off.checked_add(buf.len()).ok_or(VfsError::Inval)?;
-        if end > data.len() {
-            data.resize(end, 0u8);
-        }
-        data[off..end].copy_from_slice(buf);
+        // Ranged write: only the blocks under `[offset, offset+len)` are
+        // rewritten. A missing file is created (fresh O_CREATE before its first
+        // flush).
+        let end = f
+            .offset
+            .checked_add(buf.len() as u64)
+            .ok_or(VfsError::Inval)?;
         self.engine
-            .write(dev, f.path_str(), &data, ts)
+            .write_at(
+                &mut CryptIo::new(dev, self.crypt.as_mut()),
+                f.path_str(),
+                f.offset,
+                buf,
+                ts,
+            )
             .map_err(helix_err)?;
-        f.offset = end as u64;
+        f.offset = end;
         Ok(buf.len())
     }
 
@@ -297,14 +691,18 @@ impl FsBackend for HelixFs {
         if self.read_only {
             return Err(VfsError::ReadOnly);
         }
-        self.engine.mkdir(dev, path, ts).map_err(helix_err)
+        self.engine
+            .mkdir(&mut CryptIo::new(dev, self.crypt.as_mut()), path, ts)
+            .map_err(helix_err)
     }
 
     fn unlink(&mut self, dev: &mut RawBlockDevice, path: &str, ts: u64) -> Result<(), VfsError> {
         if self.read_only {
             return Err(VfsError::ReadOnly);
         }
-        self.engine.unlink(dev, path, ts).map_err(helix_err)
+        self.engine
+            .unlink(&mut CryptIo::new(dev, self.crypt.as_mut()), path, ts)
+            .map_err(helix_err)
     }
 
     fn rename(
@@ -317,7 +715,9 @@ impl FsBackend for HelixFs {
         if self.read_only {
             return Err(VfsError::ReadOnly);
         }
-        self.engine.rename(dev, old, new, ts).map_err(helix_err)
+        self.engine
+            .rename(&mut CryptIo::new(dev, self.crypt.as_mut()), old, new, ts)
+            .map_err(helix_err)
     }
 
     fn truncate(
@@ -330,21 +730,200 @@ impl FsBackend for HelixFs {
         if self.read_only {
             return Err(VfsError::ReadOnly);
         }
-        self.engine.truncate(dev, path, size, ts).map_err(helix_err)
+        self.engine
+            .truncate(&mut CryptIo::new(dev, self.crypt.as_mut()), path, size, ts)
+            .map_err(helix_err)
+    }
+
+    fn symlink(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        target: &str,
+        path: &str,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .symlink(
+                &mut CryptIo::new(dev, self.crypt.as_mut()),
+                target,
+                path,
+                ts,
+            )
+            .map_err(helix_err)
+    }
+
+    fn readlink(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<String, VfsError> {
+        self.engine
+            .readlink(&mut CryptIo::new(dev, self.crypt.as_mut()), path)
+            .map_err(helix_err)
+    }
+
+    fn link(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        old: &str,
+        new: &str,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .link(&mut CryptIo::new(dev, self.crypt.as_mut()), old, new, ts)
+            .map_err(helix_err)
+    }
+
+    fn chmod(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        mode: u32,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .chmod(&mut CryptIo::new(dev, self.crypt.as_mut()), path, mode, ts)
+            .map_err(helix_err)
+    }
+
+    fn chown(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        uid: u32,
+        gid: u32,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .chown(
+                &mut CryptIo::new(dev, self.crypt.as_mut()),
+                path,
+                uid,
+                gid,
+                ts,
+            )
+            .map_err(helix_err)
     }
 
     fn sync(&mut self, dev: &mut RawBlockDevice) -> Result<(), VfsError> {
         if self.read_only {
             return Ok(());
         }
-        self.engine.sync(dev).map_err(helix_err)
+        self.engine
+            .sync(&mut CryptIo::new(dev, self.crypt.as_mut()))
+            .map_err(helix_err)
+    }
+
+    fn clean(&mut self, dev: &mut RawBlockDevice, budget: u64, ts: u64) -> Result<bool, VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .clean_step(&mut CryptIo::new(dev, self.crypt.as_mut()), budget, ts)
+            .map(|step| step.idle)
+            .map_err(helix_err)
+    }
+
+    fn clean_stats(&mut self, _dev: &mut RawBlockDevice) -> Result<CleanStats, VfsError> {
+        let s = self.engine.clean_stats();
+        Ok(CleanStats {
+            checkpoints: s.checkpoints,
+            files_scanned: s.files_scanned,
+            files_defragmented: s.files_defragmented,
+            blocks_moved: s.blocks_moved,
+            passes: s.passes,
+            io_blocks: s.io_blocks,
+            ..CleanStats::default()
+        })
+    }
+
+    fn statfs(&mut self, dev: &mut RawBlockDevice) -> Result<StatFs, VfsError> {
+        let u = self
+            .engine
+            .usage(&mut CryptIo::new(dev, self.crypt.as_mut()))
+            .map_err(helix_err)?;
+        Ok(StatFs {
+            block_size: morpheus_helix::types::BLOCK_SIZE,
+            total_blocks: u.total_blocks,
+            free_blocks: u.free_blocks,
+            pinned_bytes: u.pinned_blocks * morpheus_helix::types::BLOCK_SIZE as u64,
+            log_used_pct: u.log_used_pct,
+            snapshots: u.snapshots,
+            index_live: u.live_entries,
+            index_total: u.stored_entries,
+            versions: u.versions,
+            ..StatFs::default()
+        })
+    }
+
+    fn resize(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        lba_count: u64,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        let blocks =
+            lba_count / (morpheus_helix::types::BLOCK_SIZE / self.engine.device_block_size) as u64;
+        self.engine
+            .resize(&mut CryptIo::new(dev, self.crypt.as_mut()), blocks, ts)
+            .map_err(helix_err)
+    }
+
+    fn prune_versions(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        policy: &[u8],
+        ts: u64,
+    ) -> Result<u64, VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        let policy =
+            morpheus_helix::ops::retain::Retention::parse(policy).ok_or(VfsError::Inval)?;
+        self.engine
+            .prune_versions(
+                &mut CryptIo::new(dev, self.crypt.as_mut()),
+                path,
+                policy,
+                ts,
+            )
+            .map_err(helix_err)
+    }
+
+    fn restore(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        lsn: u64,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .restore(&mut CryptIo::new(dev, self.crypt.as_mut()), path, lsn, ts)
+            .map_err(helix_err)
     }
 
     fn snapshot(&mut self, dev: &mut RawBlockDevice, name: &str, ts: u64) -> Result<u64, VfsError> {
         if self.read_only {
             return Err(VfsError::ReadOnly);
         }
-        self.engine.snapshot(dev, name, ts).map_err(helix_err)
+        self.engine
+            .snapshot(&mut CryptIo::new(dev, self.crypt.as_mut()), name, ts)
+            .map_err(helix_err)
     }
 
     fn versions(
@@ -352,15 +931,153 @@ impl FsBackend for HelixFs {
         dev: &mut RawBlockDevice,
         path: &str,
     ) -> Result<Vec<(u64, u64, u32)>, VfsError> {
-        let recs = self.engine.versions(dev, path).map_err(helix_err)?;
+        let recs = self
+            .engine
+            .versions(&mut CryptIo::new(dev, self.crypt.as_mut()), path)
+            .map_err(helix_err)?;
         Ok(recs
             .into_iter()
             .map(|(lsn, ts_ns, op)| (lsn, ts_ns, op as u32))
             .collect())
     }
-}
-
-// FAT32 adapter (read-only). fat32's engine owns its `B: BlockIo` with a private
+    fn tx_begin(&mut self, dev: &mut RawBlockDevice, ts: u64) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .begin_tx(&mut CryptIo::new(dev, self.crypt.as_mut()), ts)
+            .map(|_| ())
+            .map_err(helix_err)
+    }
+
+    fn tx_commit(&mut self, dev: &mut RawBlockDevice, ts: u64) -> Result<(), VfsError> {
+        self.engine
+            .commit(&mut CryptIo::new(dev, self.crypt.as_mut()), ts)
+            .map(|_| ())
+            .map_err(helix_err)
+    }
+
+    fn tx_abort(&mut self, dev: &mut RawBlockDevice, ts: u64) -> Result<(), VfsError> {
+        self.engine
+            .abort(&mut CryptIo::new(dev, self.crypt.as_mut()), ts)
+            .map_err(helix_err)
+    }
+
+    fn watch(&mut self, on: bool) {
+        self.engine.watch(on);
+    }
+
+    fn take_changes(&mut self) -> (Vec<FsChange>, bool) {
+        let (changes, lost) = self.engine.take_changes();
+        let changes = changes
+            .into_iter()
+            .map(|c| FsChange {
+                mask: match c.kind {
+                    ChangeKind::Create => FS_EV_CREATE,
+                    ChangeKind::Modify => FS_EV_MODIFY,
+                    ChangeKind::Delete => FS_EV_DELETE,
+                    ChangeKind::Rename => FS_EV_MOVED_FROM,
+                    ChangeKind::Attrib => FS_EV_ATTRIB,
+                },
+                cookie: c.lsn,
+                path: c.path,
+                to: c.to,
+            })
+            .collect();
+        (changes, lost)
+    }
+
+    fn setxattr(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        name: &str,
+        value: &[u8],
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .setxattr(
+                &mut CryptIo::new(dev, self.crypt.as_mut()),
+                path,
+                name,
+                value,
+                ts,
+            )
+            .map_err(helix_err)
+    }
+
+    fn getxattr(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        name: &str,
+    ) -> Result<Vec<u8>, VfsError> {
+        self.engine
+            .getxattr(&mut CryptIo::new(dev, self.crypt.as_mut()), path, name)
+            .map_err(helix_err)
+    }
+
+    fn listxattr(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<Vec<String>, VfsError> {
+        self.engine
+            .listxattr(&mut CryptIo::new(dev, self.crypt.as_mut()), path)
+            .map_err(helix_err)
+    }
+
+    fn removexattr(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        name: &str,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .removexattr(&mut CryptIo::new(dev, self.crypt.as_mut()), path, name, ts)
+            .map_err(helix_err)
+    }
+
+    fn snapshots(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+    ) -> Result<Vec<(u64, u64, String)>, VfsError> {
+        Ok(self
+            .engine
+            .list_snapshots()
+            .into_iter()
+            .map(|s| (s.lsn, s.timestamp_ns, s.name))
+            .collect())
+    }
+
+    fn delete_snapshot(&mut self, dev: &mut RawBlockDevice, name: &str) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .delete_snapshot(&mut CryptIo::new(dev, self.crypt.as_mut()), name)
+            .map_err(helix_err)
+    }
+
+    fn rollback_snapshot(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        name: &str,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.engine
+            .rollback_to(&mut CryptIo::new(dev, self.crypt.as_mut()), name, ts)
+            .map_err(helix_err)
+    }
+}
+
+// FAT32 adapter. fat32's engine owns its `B: BlockIo` with a private
 // field. `DevPtr` bridges registry ownership: the adapter holds a boxed
 // `AtomicPtr` slot (stable address); before each op it stores the borrowed device
 // into the slot; the engine's I/O reads through it. Device never leaves the
@@ -421,6 +1138,10 @@ fn fat32_err(e: morpheus_fat32::error::Fat32Error) -> VfsError {
         PathTooLong => VfsError::NameTooLong,
         PathInvalid | InvalidOffset | NotFat32 | BadGeometry | InvalidBlockSize => VfsError::Inval,
         ReadOnly => VfsError::ReadOnly,
+        AlreadyExists => VfsError::Exists,
+        DirectoryNotEmpty => VfsError::NotEmpty,
+        NoSpace => VfsError::NoSpace,
+        FileTooLarge => VfsError::TooBig,
         IoRead | IoWrite | ChainCorrupt => VfsError::Io,
     }
 }
@@ -430,17 +1151,36 @@ pub struct Fat32Fs {
     /// Stable-address device slot the engine's `DevPtr` reads through. Boxed so
     /// the engine's stored pointer to it stays valid across moves of the adapter.
     slot: alloc::boxed::Box<AtomicPtr<RawBlockDevice>>,
+    read_only: bool,
 }
 
 impl Fat32Fs {
     /// Mount a FAT32 volume at `lba_start`, binding `dev` into the shared slot.
-    pub fn mount(dev: &mut RawBlockDevice, lba_start: u64) -> Result<Self, VfsError> {
+    /// A writable mount of a volume that was not cleanly unmounted is allowed,
+    /// but logged: FAT has no journal, so only an external check repairs it.
+    pub fn mount(
+        dev: &mut RawBlockDevice,
+        lba_start: u64,
+        read_only: bool,
+    ) -> Result<Self, VfsError> {
         let slot = alloc::boxed::Box::new(AtomicPtr::new(dev as *mut RawBlockDevice));
         let bridge = DevPtr {
             slot: slot.as_ref() as *const AtomicPtr<RawBlockDevice>,
         };
-        let engine = morpheus_fat32::Fat32Fs::open(bridge, lba_start).map_err(fat32_err)?;
-        Ok(Self { engine, slot })
+        let engine = if read_only {
+            morpheus_fat32::Fat32Fs::open(bridge, lba_start)
+        } else {
+            morpheus_fat32::Fat32Fs::open_rw(bridge, lba_start)
+        }
+        .map_err(fat32_err)?;
+        if engine.was_dirty() {
+            crate::serial::log_warn("FAT32", 813, "volume was not cleanly unmounted");
+        }
+        Ok(Self {
+            engine,
+            slot,
+            read_only,
+        })
     }
 
     /// Point the shared slot at the current op's device.
@@ -450,6 +1190,12 @@ impl Fat32Fs {
     }
 }
 
+/// Wall-clock Unix seconds for FAT entry times. The VFS `ts` is monotonic
+/// since boot, which would read as 1970 and clamp to the DOS epoch.
+fn fat_now() -> u64 {
+    crate::clock::realtime_ns() / 1_000_000_000
+}
+
 /// FAT32 cookie layout in the fd cookie blob: [start_cluster:u32][cursor:u64][size:u64].
 fn fat_cookie_set(c: &morpheus_fat32::Fat32Cookie) -> [u8; FD_COOKIE_LEN] {
     let mut out = [0u8; FD_COOKIE_LEN];
@@ -475,7 +1221,10 @@ fn fat_cookie_get(c: &[u8; FD_COOKIE_LEN]) -> morpheus_fat32::Fat32Cookie {
 
 impl FsBackend for Fat32Fs {
     fn capabilities(&self) -> FsCapabilities {
-        FsCapabilities::default() // read-only: everything false
+        FsCapabilities {
+            writable: !self.read_only,
+            ..FsCapabilities::default()
+        }
     }
 
     fn open(
@@ -485,19 +1234,39 @@ impl FsBackend for Fat32Fs {
         flags: u32,
         _ts: u64,
     ) -> Result<OpenFile, VfsError> {
-        if flags & (open_flags::O_WRITE | open_flags::O_CREATE | open_flags::O_TRUNC) != 0 {
+        let mutating = open_flags::O_WRITE | open_flags::O_CREATE | open_flags::O_TRUNC;
+        if self.read_only && flags & mutating != 0 {
             return Err(VfsError::ReadOnly);
         }
         self.bind(dev);
+        let st = match self.engine.stat(path) {
+            Ok(_) if flags & open_flags::O_CREATE != 0 && flags & open_flags::O_EXCL != 0 => {
+                return Err(VfsError::Exists)
+            },
+            Err(morpheus_fat32::error::Fat32Error::NotFound)
+                if flags & open_flags::O_CREATE != 0 =>
+            {
+                self.engine.create(path).map_err(fat32_err)?;
+                self.engine.stat(path).map_err(fat32_err)?
+            },
+            r => r.map_err(fat32_err)?,
+        };
         // A directory open is allowed for readdir; reads against it return IsDir.
-        let st = self.engine.stat(path).map_err(fat32_err)?;
         let is_dir = matches!(st.file_type, morpheus_fat32::types::FileType::Directory);
         if is_dir {
+            if flags & mutating != 0 {
+                return Err(VfsError::IsDir);
+            }
             return Ok(OpenFile {
                 cookie: [0u8; FD_COOKIE_LEN],
                 is_dir: true,
             });
         }
+        if flags & open_flags::O_TRUNC != 0 && st.size != 0 {
+            self.engine
+                .truncate(path, 0, fat_now())
+                .map_err(fat32_err)?;
+        }
         let cookie = self.engine.open_file(path).map_err(fat32_err)?;
         Ok(OpenFile {
             cookie: fat_cookie_set(&cookie),
@@ -512,6 +1281,14 @@ impl FsBackend for Fat32Fs {
         buf: &mut [u8],
     ) -> Result<usize, VfsError> {
         self.bind(dev);
+        if !self.read_only {
+            // Writes may have moved the file since open: go by path, as the
+            // cookie's size is the one seen then.
+            return self
+                .engine
+                .read_at(f.path_str(), f.offset, buf)
+                .map_err(fat32_err);
+        }
         let mut cookie = fat_cookie_get(&f.cookie);
         // Honor the fd's persisted offset rather than the cookie's own cursor so
         // seeks work; the engine advances `cursor` from this base.
@@ -530,6 +1307,91 @@ impl FsBackend for Fat32Fs {
         let ents = self.engine.readdir(path).map_err(fat32_err)?;
         Ok(ents.iter().map(fat_dirent_to_abi).collect())
     }
+
+    fn write(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        f: &mut FdState,
+        buf: &[u8],
+        _ts: u64,
+    ) -> Result<usize, VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.bind(dev);
+        let n = self
+            .engine
+            .write_at(f.path_str(), f.offset, buf, fat_now())
+            .map_err(fat32_err)?;
+        f.offset += n as u64;
+        Ok(n)
+    }
+
+    fn mkdir(&mut self, dev: &mut RawBlockDevice, path: &str, _ts: u64) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.bind(dev);
+        self.engine.mkdir(path).map_err(fat32_err)
+    }
+
+    fn unlink(&mut self, dev: &mut RawBlockDevice, path: &str, _ts: u64) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.bind(dev);
+        self.engine.unlink(path).map_err(fat32_err)
+    }
+
+    fn rename(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        old: &str,
+        new: &str,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.bind(dev);
+        self.engine.rename(old, new).map_err(fat32_err)
+    }
+
+    fn truncate(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        size: u64,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        self.bind(dev);
+        self.engine
+            .truncate(path, size, fat_now())
+            .map_err(fat32_err)
+    }
+
+    fn sync(&mut self, dev: &mut RawBlockDevice) -> Result<(), VfsError> {
+        if self.read_only {
+            return Ok(());
+        }
+        self.bind(dev);
+        self.engine.sync().map_err(fat32_err)
+    }
+
+    fn statfs(&mut self, dev: &mut RawBlockDevice) -> Result<StatFs, VfsError> {
+        self.bind(dev);
+        let free = self.engine.free_clusters().map_err(fat32_err)?;
+        let bpb = self.engine.bpb();
+        Ok(StatFs {
+            block_size: bpb.bytes_per_cluster(),
+            total_blocks: bpb.cluster_count() as u64,
+            free_blocks: free as u64,
+            ..StatFs::default()
+        })
+    }
 }
 
 fn fat_stat_to_abi(st: &morpheus_fat32::types::FileStat) -> FileStat {
@@ -537,6 +1399,7 @@ fn fat_stat_to_abi(st: &morpheus_fat32::types::FileStat) -> FileStat {
     FileStat {
         key: st.start_cluster as u64,
         size: st.size,
+        physical_size: if is_dir { 0 } else { st.size },
         mode: if is_dir { mode::S_IFDIR } else { mode::S_IFREG },
         version_count: 1,
         ..FileStat::default()
@@ -562,3 +1425,410 @@ fn fat_dirent_to_abi(e: &morpheus_fat32::types::DirEntry) -> DirEntry {
         ..DirEntry::zeroed()
     }
 }
+
+// ISO9660 adapter (read-only). iso9660's reader is free functions over a
+// borrowed `B: BlockIo` addressed in 2048-byte sectors, so unlike FAT32 no
+// bridge slot is needed: each op wraps the borrowed device in an `IsoIo` view
+// that translates sectors for that op only.
+
+use morpheus_storage_format::iso::{IsoBlockIoAdapter, IsoManifest, IsoReadContext};
+
+const ISO_SECTOR: u64 = 2048;
+
+/// Where an image's sectors live on the device.
+enum IsoSource {
+    /// Contiguous from `lba_start`, `scale` device blocks per ISO sector.
+    Direct { lba_start: u64, scale: u64 },
+    /// Split across chunk partitions as the manifest records them.
+    Chunked(alloc::boxed::Box<IsoReadContext>),
+}
+
+/// One op's 2048-byte-sector view of the device.
+// Lives on the stack for one op; boxing the chunked view would allocate per op.
+#[allow(clippy::large_enum_variant)]
+enum IsoIo<'a> {
+    Direct {
+        dev: &'a mut RawBlockDevice,
+        lba_start: u64,
+        scale: u64,
+    },
+    Chunked(IsoBlockIoAdapter<'a, RawBlockDevice>),
+}
+
+impl BlockIo for IsoIo<'_> {
+    type Error = RawIoError;
+    fn block_size(&self) -> BlockSize {
+        BlockSize::new(ISO_SECTOR as u32).unwrap()
+    }
+    fn num_blocks(&mut self) -> Result<u64, Self::Error> {
+        match self {
+            IsoIo::Direct {
+                dev,
+                lba_start,
+                scale,
+            } => Ok(dev.num_blocks()?.saturating_sub(*lba_start) / *scale),
+            IsoIo::Chunked(a) => a.num_blocks(),
+        }
+    }
+    fn read_blocks(&mut self, start: Lba, dst: &mut [u8]) -> Result<(), Self::Error> {
+        match self {
+            IsoIo::Direct {
+                dev,
+                lba_start,
+                scale,
+            } => dev.read_blocks(Lba(*lba_start + start.0 * *scale), dst),
+            IsoIo::Chunked(a) => a.read_blocks(start, dst),
+        }
+    }
+    fn write_blocks(&mut self, _start: Lba, _src: &[u8]) -> Result<(), Self::Error> {
+        Err(RawIoError)
+    }
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn iso_err(e: iso9660::Iso9660Error) -> VfsError {
+    use iso9660::Iso9660Error::*;
+    match e {
+        NotFound => VfsError::NotFound,
+        PathTooLong => VfsError::NameTooLong,
+        InvalidPath | InvalidSignature | UnsupportedVersion => VfsError::Inval,
+        _ => VfsError::Io,
+    }
+}
+
+pub struct Iso9660Fs {
+    source: IsoSource,
+    volume: iso9660::VolumeInfo,
+}
+
+impl Iso9660Fs {
+    /// Mount an ISO image stored contiguously from `lba_start`. The device's
+    /// blocks must tile the 2048-byte ISO sector.
+    pub fn mount(dev: &mut RawBlockDevice, lba_start: u64) -> Result<Self, VfsError> {
+        let bs = dev.block_size().to_u32() as u64;
+        if bs == 0 || ISO_SECTOR % bs != 0 {
+            return Err(VfsError::Inval);
+        }
+        Self::open(
+            dev,
+            IsoSource::Direct {
+                lba_start,
+                scale: ISO_SECTOR / bs,
+            },
+        )
+    }
+
+    /// Mount a chunked ISO from its manifest. The chunks are whole partitions
+    /// of `dev`, which addresses 512-byte blocks like every chunk writer.
+    pub fn mount_chunked(
+        dev: &mut RawBlockDevice,
+        manifest: &IsoManifest,
+    ) -> Result<Self, VfsError> {
+        if dev.block_size().to_u32() != 512 {
+            return Err(VfsError::Inval);
+        }
+        Self::open(
+            dev,
+            IsoSource::Chunked(alloc::boxed::Box::new(IsoReadContext::from_manifest(
+                manifest,
+            ))),
+        )
+    }
+
+    fn open(dev: &mut RawBlockDevice, source: IsoSource) -> Result<Self, VfsError> {
+        let volume = iso9660::mount(&mut Self::view(&source, dev), 0).map_err(iso_err)?;
+        Ok(Self { source, volume })
+    }
+
+    fn view<'a>(source: &IsoSource, dev: &'a mut RawBlockDevice) -> IsoIo<'a> {
+        match source {
+            IsoSource::Direct { lba_start, scale } => IsoIo::Direct {
+                dev,
+                lba_start: *lba_start,
+                scale: *scale,
+            },
+            IsoSource::Chunked(ctx) => {
+                IsoIo::Chunked(IsoBlockIoAdapter::new(ctx.as_ref().clone(), dev))
+            },
+        }
+    }
+
+    fn lookup(&self, dev: &mut RawBlockDevice, path: &str) -> Result<iso9660::FileEntry, VfsError> {
+        iso9660::find_file(&mut Self::view(&self.source, dev), &self.volume, path).map_err(iso_err)
+    }
+}
+
+/// ISO9660 cookie layout in the fd cookie blob: [extent_lba:u32][size:u64].
+fn iso_cookie_set(e: &iso9660::FileEntry) -> [u8; FD_COOKIE_LEN] {
+    let mut out = [0u8; FD_COOKIE_LEN];
+    out[0..4].copy_from_slice(&e.extent_lba.to_le_bytes());
+    out[4..12].copy_from_slice(&e.size.to_le_bytes());
+    out
+}
+
+fn iso_cookie_get(c: &[u8; FD_COOKIE_LEN]) -> iso9660::FileEntry {
+    let mut lba = [0u8; 4];
+    lba.copy_from_slice(&c[0..4]);
+    let mut sz = [0u8; 8];
+    sz.copy_from_slice(&c[4..12]);
+    let size = u64::from_le_bytes(sz);
+    iso9660::FileEntry {
+        name: String::new(),
+        size,
+        extent_lba: u32::from_le_bytes(lba),
+        data_length: size as u32,
+        flags: iso9660::FileFlags::default(),
+        file_unit_size: 0,
+        interleave_gap: 0,
+    }
+}
+
+impl FsBackend for Iso9660Fs {
+    fn capabilities(&self) -> FsCapabilities {
+        FsCapabilities::default() // read-only: everything false
+    }
+
+    fn open(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        flags: u32,
+        _ts: u64,
+    ) -> Result<OpenFile, VfsError> {
+        if flags & (open_flags::O_WRITE | open_flags::O_CREATE | open_flags::O_TRUNC) != 0 {
+            return Err(VfsError::ReadOnly);
+        }
+        let e = self.lookup(dev, path)?;
+        if e.flags.directory {
+            return Ok(OpenFile {
+                cookie: [0u8; FD_COOKIE_LEN],
+                is_dir: true,
+            });
+        }
+        Ok(OpenFile {
+            cookie: iso_cookie_set(&e),
+            is_dir: false,
+        })
+    }
+
+    fn read(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        f: &FdState,
+        buf: &mut [u8],
+    ) -> Result<usize, VfsError> {
+        let mut io = Self::view(&self.source, dev);
+        let mut reader = iso9660::FileReader::new(&mut io, iso_cookie_get(&f.cookie));
+        reader.seek(f.offset);
+        reader.read(buf).map_err(iso_err)
+    }
+
+    fn stat(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<FileStat, VfsError> {
+        let e = self.lookup(dev, path)?;
+        Ok(iso_stat_to_abi(&e))
+    }
+
+    fn readdir(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<Vec<DirEntry>, VfsError> {
+        let dir = self.lookup(dev, path)?;
+        if !dir.flags.directory {
+            return Err(VfsError::NotDir);
+        }
+        let mut io = Self::view(&self.source, dev);
+        let mut out = Vec::new();
+        for e in iso9660::DirectoryIterator::new(&mut io, dir.extent_lba, dir.data_length) {
+            let e = e.map_err(iso_err)?;
+            if e.name != "." && e.name != ".." {
+                out.push(iso_dirent_to_abi(&e));
+            }
+        }
+        Ok(out)
+    }
+
+    fn statfs(&mut self, _dev: &mut RawBlockDevice) -> Result<StatFs, VfsError> {
+        Ok(StatFs {
+            block_size: self.volume.logical_block_size as u32,
+            total_blocks: self.volume.volume_space_size as u64,
+            ..StatFs::default()
+        })
+    }
+}
+
+fn iso_stat_to_abi(e: &iso9660::FileEntry) -> FileStat {
+    let is_dir = e.flags.directory;
+    FileStat {
+        key: e.extent_lba as u64,
+        size: e.size,
+        physical_size: if is_dir { 0 } else { e.size },
+        mode: if is_dir { mode::S_IFDIR } else { mode::S_IFREG },
+        version_count: 1,
+        ..FileStat::default()
+    }
+}
+
+fn iso_dirent_to_abi(e: &iso9660::FileEntry) -> DirEntry {
+    let mut name = [0u8; 256];
+    let bytes = e.name.as_bytes();
+    let n = bytes.len().min(256);
+    name[..n].copy_from_slice(&bytes[..n]);
+    DirEntry {
+        name,
+        name_len: n as u16,
+        d_type: if e.flags.directory {
+            dirent_type::DT_DIR
+        } else {
+            dirent_type::DT_REG
+        },
+        size: e.size,
+        version_count: 1,
+        ..DirEntry::zeroed()
+    }
+}
+
+// ext2/3/4 adapter (read-only). The ext4 engine borrows a `B: BlockIo` per
+// call and applies the partition offset itself, so the registry's device is
+// handed straight through: no bridge slot, no sector view.
+
+fn ext4_err(e: morpheus_ext4::error::Ext4Error) -> VfsError {
+    use morpheus_ext4::error::Ext4Error::*;
+    match e {
+        NotFound => VfsError::NotFound,
+        NotADirectory => VfsError::NotDir,
+        IsADirectory => VfsError::IsDir,
+        PathTooLong => VfsError::NameTooLong,
+        Unsupported => VfsError::Unsupported,
+        NotASymlink | PathInvalid | InvalidOffset | NotExt | BadGeometry | InvalidBlockSize => {
+            VfsError::Inval
+        },
+        IoRead | Corrupt => VfsError::Io,
+    }
+}
+
+pub struct Ext4Fs {
+    engine: morpheus_ext4::Ext4Fs,
+}
+
+impl Ext4Fs {
+    /// Mount the ext2/3/4 volume at `lba_start`. A journal still flagged for
+    /// recovery is not replayed; the volume is read as of its last checkpoint,
+    /// which can miss the newest writes from an unclean Linux shutdown.
+    pub fn mount(dev: &mut RawBlockDevice, lba_start: u64) -> Result<Self, VfsError> {
+        let engine = morpheus_ext4::Ext4Fs::mount(dev, lba_start).map_err(ext4_err)?;
+        if engine.needs_recovery() {
+            crate::serial::log_warn(
+                "EXT4",
+                814,
+                "journal needs recovery; reading last checkpoint",
+            );
+        }
+        Ok(Self { engine })
+    }
+}
+
+/// ext4 cookie layout in the fd cookie blob: [ino:u32].
+fn ext4_cookie_set(ino: u32) -> [u8; FD_COOKIE_LEN] {
+    let mut out = [0u8; FD_COOKIE_LEN];
+    out[0..4].copy_from_slice(&ino.to_le_bytes());
+    out
+}
+
+fn ext4_cookie_get(c: &[u8; FD_COOKIE_LEN]) -> u32 {
+    u32::from_le_bytes([c[0], c[1], c[2], c[3]])
+}
+
+impl FsBackend for Ext4Fs {
+    fn capabilities(&self) -> FsCapabilities {
+        // Owners are Linux uids, meaningless here: mode bits are reported but
+        // not enforced.
+        FsCapabilities::default()
+    }
+
+    fn open(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        path: &str,
+        flags: u32,
+        _ts: u64,
+    ) -> Result<OpenFile, VfsError> {
+        if flags & (open_flags::O_WRITE | open_flags::O_CREATE | open_flags::O_TRUNC) != 0 {
+            return Err(VfsError::ReadOnly);
+        }
+        let st = self.engine.stat(dev, path).map_err(ext4_err)?;
+        Ok(OpenFile {
+            cookie: ext4_cookie_set(st.ino),
+            is_dir: st.file_type == morpheus_ext4::types::FileType::Directory,
+        })
+    }
+
+    fn read(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        f: &FdState,
+        buf: &mut [u8],
+    ) -> Result<usize, VfsError> {
+        self.engine
+            .read(dev, ext4_cookie_get(&f.cookie), f.offset, buf)
+            .map_err(ext4_err)
+    }
+
+    fn stat(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<FileStat, VfsError> {
+        let st = self.engine.stat(dev, path).map_err(ext4_err)?;
+        Ok(ext4_stat_to_abi(&st))
+    }
+
+    fn readdir(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<Vec<DirEntry>, VfsError> {
+        let ents = self.engine.readdir(dev, path).map_err(ext4_err)?;
+        Ok(ents.iter().map(ext4_dirent_to_abi).collect())
+    }
+
+    fn readlink(&mut self, dev: &mut RawBlockDevice, path: &str) -> Result<String, VfsError> {
+        self.engine.readlink(dev, path).map_err(ext4_err)
+    }
+
+    fn statfs(&mut self, _dev: &mut RawBlockDevice) -> Result<StatFs, VfsError> {
+        let sb = self.engine.superblock();
+        Ok(StatFs {
+            block_size: sb.block_size,
+            total_blocks: sb.blocks_count,
+            free_blocks: sb.free_blocks,
+            ..StatFs::default()
+        })
+    }
+}
+
+fn ext4_stat_to_abi(st: &morpheus_ext4::types::FileStat) -> FileStat {
+    let is_dir = st.file_type == morpheus_ext4::types::FileType::Directory;
+    FileStat {
+        key: st.ino as u64,
+        size: st.size,
+        physical_size: if is_dir { 0 } else { st.size },
+        mode: st.mode as u32,
+        nlink: st.nlink as u64,
+        uid: st.uid,
+        gid: st.gid,
+        version_count: 1,
+        ..FileStat::default()
+    }
+}
+
+fn ext4_dirent_to_abi(e: &morpheus_ext4::types::DirEntry) -> DirEntry {
+    use morpheus_ext4::types::FileType;
+    let mut name = [0u8; 256];
+    let bytes = e.name.as_bytes();
+    let n = bytes.len().min(256);
+    name[..n].copy_from_slice(&bytes[..n]);
+    DirEntry {
+        name,
+        name_len: n as u16,
+        d_type: match e.file_type {
+            FileType::Directory => dirent_type::DT_DIR,
+            FileType::Regular => dirent_type::DT_REG,
+            FileType::Symlink => dirent_type::DT_LNK,
+            FileType::Other => dirent_type::DT_UNKNOWN,
+        },
+        size: e.size,
+        version_count: 1,
+        ..DirEntry::zeroed()
+    }
+}
@@ -0,0 +1,83 @@
+//! Background cleaner thread: steps each writable mount's `FsBackend::clean`
+//! with a bounded I/O budget under `STORAGE_LOCK`, then sleeps. Short naps
+//! while there is work, long ones once every mount reports idle. Mounts held
+//! by an open transaction are skipped until it ends. A step that fails is
+//! logged and retried after the short nap.
+
+use super::fs_api::VfsError;
+use super::lock;
+use core::sync::atomic::{AtomicU32, Ordering};
+use morpheus_foundation::storage::MNT_RDONLY;
+
+/// Blocks of I/O per mount per step: 1 MiB, short enough that syscalls
+/// queued on the storage lock barely notice.
+pub const CLEAN_BUDGET_BLOCKS: u64 = 256;
+/// Nap between steps while some mount still has work.
+pub const CLEAN_BUSY_MS: u64 = 50;
+/// Nap once every mount is idle.
+pub const CLEAN_IDLE_MS: u64 = 30_000;
+
+/// PID of the cleaner thread; 0 until `spawn`.
+static CLEANER_PID: AtomicU32 = AtomicU32::new(0);
+
+/// One cleaner step on every writable mount. `true` if all of them are idle.
+pub fn clean_mounts(budget: u64) -> bool {
+    let ts = crate::global::hal().timer().now_ns();
+    // SAFETY: single critical section.
+    let guard = unsafe { lock() };
+    let g = &mut *guard.g;
+    let ids: alloc::vec::Vec<u64> = g
+        .mounts
+        .iter()
+        .filter(|(_, m)| m.flags & MNT_RDONLY == 0 && m.tx_owner.is_none())
+        .map(|(id, _)| id)
+        .collect();
+    let mut idle = true;
+    for id in ids {
+        if let Some((m, dev)) = g.mount_dev_mut(id) {
+            idle &= match m.fs.clean(dev, budget, ts) {
+                Ok(done) => done,
+                // Unsupported, read-only and busy mounts simply have nothing to do.
+                Err(VfsError::Unsupported | VfsError::ReadOnly | VfsError::Busy) => true,
+                Err(e) => {
+                    let msg = alloc::format!("mount {}: {:?}", id, e);
+                    crate::serial::log_warn("CLEAN", 815, &msg);
+                    false
+                },
+            };
+        }
+    }
+    idle
+}
+
+extern "C" fn cleaner_main() -> ! {
+    loop {
+        let nap_ms = if clean_mounts(CLEAN_BUDGET_BLOCKS) {
+            CLEAN_IDLE_MS
+        } else {
+            CLEAN_BUSY_MS
+        };
+        let deadline = crate::clock::tsc_deadline_in_ns(nap_ms * 1_000_000);
+        // SAFETY: a kernel thread with its own slot; nothing held across the nap.
+        unsafe { crate::schedular::block_sleep(deadline) };
+    }
+}
+
+/// Start the cleaner thread once root is mounted. Idempotent. Not started
+/// without a calibrated TSC: its naps would never end.
+///
+/// # Safety
+/// Scheduler initialized; not called with `STORAGE_LOCK` held.
+pub unsafe fn spawn() -> Result<u32, &'static str> {
+    let running = CLEANER_PID.load(Ordering::Acquire);
+    if running != 0 {
+        return Ok(running);
+    }
+    if crate::schedular::tsc_frequency() == 0 {
+        return Err("TSC uncalibrated");
+    }
+    let pid =
+        crate::schedular::spawn_kernel_thread("fs-cleaner", cleaner_main as usize as u64, 128)?;
+    CLEANER_PID.store(pid, Ordering::Release);
+    Ok(pid)
+}
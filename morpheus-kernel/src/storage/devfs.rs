@@ -0,0 +1,460 @@
+//! Synthetic `/dev`: device nodes dispatched to the kernel objects they name.
+//!
+//! - `volN`: seekable raw bytes of volume N (its registry slot index), through
+//!   the volume's registered `RawBlockDevice`. Writes are read-modify-write
+//!   per block, flushed before returning, and refused while the volume or one
+//!   overlapping it is mounted.
+//! - `ttyS0`: the serial console; reads drain the stdin ring.
+//! - `fb0`: the shared framebuffer back buffer, as `SYS_FB_MAP` maps it.
+//! - `random` (and `urandom`: there is only the hardware RNG), `null`, `zero`.
+//! - `input/keyboard` (raw scancodes, as `SYS_KEYBOARD_READ`) and
+//!   `input/mouse` (8-byte `SYS_MOUSE_READ` packets).
+//!
+//! Backends run under `STORAGE_LOCK`, a spinlock, so nothing here waits: an
+//! empty input ring reads as `WouldBlock`. The volume nodes come from a
+//! `VolumeView` the resolver captures before each lend of the mount.
+
+use super::fs_api::{FdState, FsBackend, FsCapabilities, OpenFile, VfsError};
+use super::procfs::path_key;
+use super::StorageGlobal;
+use crate::syscall::handler::{fb, hw, nic_fb, sync};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use gpt_disk_io::BlockIo;
+use gpt_disk_types::Lba;
+use morpheus_block_types::RawBlockDevice;
+use morpheus_foundation::errno::ENODEV;
+use morpheus_foundation::flags::{dirent_type, mode, open_flags};
+use morpheus_foundation::storage::{unpack, FD_COOKIE_LEN};
+use morpheus_foundation::types::{DirEntry, FileStat, StatFs};
+
+/// Bytes per `input/mouse` record.
+const MOUSE_PACKET_LEN: usize = 8;
+
+pub struct DevFs {
+    /// Mounted `MNT_RDONLY`: no node opens for writing.
+    read_only: bool,
+    volumes: VolumeView,
+}
+
+impl DevFs {
+    pub fn new(read_only: bool) -> Self {
+        Self {
+            read_only,
+            volumes: VolumeView::default(),
+        }
+    }
+
+    /// Replace the volume nodes. Must happen before every lend: the view's
+    /// device handles are only valid while the registry is not lent out.
+    pub fn set_volumes(&mut self, view: VolumeView) {
+        self.volumes = view;
+    }
+}
+
+/// The volume registry as `volN` nodes, taken while it is not lent out.
+#[derive(Default)]
+pub struct VolumeView {
+    nodes: Vec<VolumeNode>,
+}
+
+struct VolumeNode {
+    index: u32,
+    /// Alias of the registered device's handle.
+    dev: RawBlockDevice,
+    lba_start: u64,
+    lba_count: u64,
+    block_size: u32,
+    read_only: bool,
+    /// It, or a volume overlapping it on the same device, is mounted.
+    busy: bool,
+}
+
+impl VolumeView {
+    pub fn capture(g: &StorageGlobal) -> Self {
+        let in_use: Vec<(u64, u64, u64)> = g
+            .volumes
+            .iter()
+            .filter(|(id, v)| v.mounted || g.mounts.iter().any(|(_, m)| m.volume_id == *id))
+            .map(|(_, v)| {
+                (
+                    v.device_id,
+                    v.lba_start,
+                    v.lba_start.saturating_add(v.lba_count),
+                )
+            })
+            .collect();
+        let nodes = g
+            .volumes
+            .iter()
+            .filter(|(_, v)| v.block_size != 0)
+            .filter_map(|(id, v)| {
+                let d = g.devices.get(v.device_id)?;
+                let end = v.lba_start.saturating_add(v.lba_count);
+                Some(VolumeNode {
+                    index: unpack(id).0,
+                    // SAFETY: used only within the lend this view is captured
+                    // for; `STORAGE_LOCK` is held throughout and only the
+                    // devfs placeholder device is borrowed, so the owner's
+                    // handle is idle and its context alive.
+                    dev: unsafe { d.device.alias() },
+                    lba_start: v.lba_start,
+                    lba_count: v.lba_count,
+                    block_size: v.block_size,
+                    read_only: v.read_only,
+                    busy: in_use.iter().any(|&(dev, start, stop)| {
+                        dev == v.device_id && start < end && v.lba_start < stop
+                    }),
+                })
+            })
+            .collect();
+        Self { nodes }
+    }
+}
+
+impl VolumeNode {
+    fn size(&self) -> u64 {
+        self.lba_count.saturating_mul(self.block_size as u64)
+    }
+
+    /// Bytes from `offset` into `buf`, a block at a time; 0 past the end.
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, VfsError> {
+        let bs = self.block_size as usize;
+        let n = (buf.len() as u64).min(self.size().saturating_sub(offset)) as usize;
+        let mut block = alloc::vec![0u8; bs];
+        let mut done = 0usize;
+        while done < n {
+            let pos = offset + done as u64;
+            let within = (pos % bs as u64) as usize;
+            let take = (bs - within).min(n - done);
+            let lba = Lba(self.lba_start + pos / bs as u64);
+            self.dev
+                .read_blocks(lba, &mut block)
+                .map_err(|_| VfsError::Io)?;
+            buf[done..done + take].copy_from_slice(&block[within..within + take]);
+            done += take;
+        }
+        Ok(n)
+    }
+
+    /// `buf` at `offset`, read-modify-write for partial blocks, then a flush.
+    fn write_at(&mut self, offset: u64, buf: &[u8]) -> Result<usize, VfsError> {
+        if self.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+        if self.busy {
+            return Err(VfsError::Busy);
+        }
+        let bs = self.block_size as usize;
+        let n = (buf.len() as u64).min(self.size().saturating_sub(offset)) as usize;
+        if n == 0 && !buf.is_empty() {
+            return Err(VfsError::NoSpace);
+        }
+        let mut block = alloc::vec![0u8; bs];
+        let mut done = 0usize;
+        while done < n {
+            let pos = offset + done as u64;
+            let within = (pos % bs as u64) as usize;
+            let take = (bs - within).min(n - done);
+            let lba = Lba(self.lba_start + pos / bs as u64);
+            if take < bs {
+                self.dev
+                    .read_blocks(lba, &mut block)
+                    .map_err(|_| VfsError::Io)?;
+            }
+            block[within..within + take].copy_from_slice(&buf[done..done + take]);
+            self.dev
+                .write_blocks(lba, &block)
+                .map_err(|_| VfsError::Io)?;
+            done += take;
+        }
+        self.dev.flush().map_err(|_| VfsError::Io)?;
+        Ok(n)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Node {
+    Root,
+    Input,
+    /// Index into the `VolumeView`.
+    Volume(usize),
+    Serial,
+    Framebuffer,
+    Random,
+    Null,
+    Zero,
+    Keyboard,
+    Mouse,
+}
+
+impl DevFs {
+    fn lookup(&self, path: &str) -> Result<Node, VfsError> {
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let node = match parts.as_slice() {
+            [] => Node::Root,
+            ["ttyS0"] => Node::Serial,
+            ["fb0"] if fb_present() => Node::Framebuffer,
+            ["random"] | ["urandom"] => Node::Random,
+            ["null"] => Node::Null,
+            ["zero"] => Node::Zero,
+            ["input"] => Node::Input,
+            ["input", "keyboard"] => Node::Keyboard,
+            ["input", "mouse"] => Node::Mouse,
+            [name] => {
+                let index: u32 = name
+                    .strip_prefix("vol")
+                    .and_then(|n| n.parse().ok())
+                    .ok_or(VfsError::NotFound)?;
+                let at = self
+                    .volumes
+                    .nodes
+                    .iter()
+                    .position(|v| v.index == index && *name == format!("vol{}", index))
+                    .ok_or(VfsError::NotFound)?;
+                Node::Volume(at)
+            },
+            _ => return Err(VfsError::NotFound),
+        };
+        Ok(node)
+    }
+
+    fn list(&self, node: Node) -> Result<Vec<(String, u8)>, VfsError> {
+        let chr = |n: &str| (n.to_string(), dirent_type::DT_CHR);
+        let out = match node {
+            Node::Root => {
+                let mut out = alloc::vec![
+                    chr("null"),
+                    chr("zero"),
+                    chr("random"),
+                    chr("urandom"),
+                    chr("ttyS0"),
+                ];
+                if fb_present() {
+                    out.push(chr("fb0"));
+                }
+                out.extend(
+                    self.volumes
+                        .nodes
+                        .iter()
+                        .map(|v| (format!("vol{}", v.index), dirent_type::DT_BLK)),
+                );
+                out.push(("input".to_string(), dirent_type::DT_DIR));
+                out
+            },
+            Node::Input => alloc::vec![chr("keyboard"), chr("mouse")],
+            _ => return Err(VfsError::NotDir),
+        };
+        Ok(out)
+    }
+
+    fn stat_node(&self, path: &str, node: Node) -> FileStat {
+        let (perm, size) = match node {
+            Node::Root | Node::Input => (mode::S_IFDIR | 0o755, 0),
+            Node::Volume(at) => (mode::S_IFBLK | 0o600, self.volumes.nodes[at].size()),
+            // SAFETY: read-only snapshot of the registered framebuffer.
+            Node::Framebuffer => (
+                mode::S_IFCHR | 0o660,
+                unsafe { nic_fb::fb_registered() }.map_or(0, |i| i.size),
+            ),
+            Node::Serial => (mode::S_IFCHR | 0o660, 0),
+            Node::Random | Node::Null | Node::Zero => (mode::S_IFCHR | 0o666, 0),
+            Node::Keyboard | Node::Mouse => (mode::S_IFCHR | 0o440, 0),
+        };
+        let is_dir = matches!(node, Node::Root | Node::Input);
+        FileStat {
+            mode: perm,
+            key: path_key(path),
+            size,
+            nlink: if is_dir { 2 } else { 1 },
+            version_count: 1,
+            ..FileStat::default()
+        }
+    }
+}
+
+impl FsBackend for DevFs {
+    fn capabilities(&self) -> FsCapabilities {
+        FsCapabilities {
+            writable: !self.read_only,
+            ownership: true,
+            ..FsCapabilities::default()
+        }
+    }
+
+    /// Nodes cannot be created; `O_TRUNC` is ignored, as on any device.
+    fn open(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        path: &str,
+        flags: u32,
+        _ts: u64,
+    ) -> Result<OpenFile, VfsError> {
+        let node = match self.lookup(path) {
+            Err(VfsError::NotFound) if flags & open_flags::O_CREATE != 0 => {
+                return Err(VfsError::ReadOnly)
+            },
+            r => r?,
+        };
+        let is_dir = matches!(node, Node::Root | Node::Input);
+        if flags & open_flags::O_WRITE != 0 {
+            match node {
+                Node::Root | Node::Input => return Err(VfsError::IsDir),
+                _ if self.read_only => return Err(VfsError::ReadOnly),
+                Node::Keyboard | Node::Mouse => return Err(VfsError::ReadOnly),
+                Node::Volume(at) if self.volumes.nodes[at].read_only => {
+                    return Err(VfsError::ReadOnly)
+                },
+                _ => {},
+            }
+        }
+        Ok(OpenFile {
+            cookie: [0u8; FD_COOKIE_LEN],
+            is_dir,
+        })
+    }
+
+    fn read(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        f: &FdState,
+        buf: &mut [u8],
+    ) -> Result<usize, VfsError> {
+        match self.lookup(f.path_str())? {
+            Node::Root | Node::Input => Err(VfsError::IsDir),
+            Node::Volume(at) => self.volumes.nodes[at].read_at(f.offset, buf),
+            Node::Framebuffer => {
+                shared_fb()?;
+                // SAFETY: `buf` is the caller's validated buffer, copied
+                // outside the CR3 switch.
+                unsafe { fb::fb_back_read(f.offset, buf) }.map_err(fb_err)
+            },
+            Node::Random => hw::fill_random(buf).ok_or(VfsError::NoDev),
+            Node::Null => Ok(0),
+            Node::Zero => {
+                buf.fill(0);
+                Ok(buf.len())
+            },
+            Node::Serial => nonblocking(buf.len(), crate::stdin::read(buf)),
+            Node::Keyboard => nonblocking(buf.len(), crate::input::read_keys(buf)),
+            Node::Mouse => {
+                if buf.len() < MOUSE_PACKET_LEN {
+                    return Err(VfsError::Inval);
+                }
+                // SAFETY: drains the calling process's own motion.
+                let packet = unsafe { sync::mouse_packet() };
+                buf[..MOUSE_PACKET_LEN].copy_from_slice(&packet.to_le_bytes());
+                Ok(MOUSE_PACKET_LEN)
+            },
+        }
+    }
+
+    /// Seekable nodes (`volN`, `fb0`) advance `f.offset`; streams ignore it.
+    fn write(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        f: &mut FdState,
+        buf: &[u8],
+        _ts: u64,
+    ) -> Result<usize, VfsError> {
+        match self.lookup(f.path_str())? {
+            Node::Root | Node::Input => Err(VfsError::IsDir),
+            Node::Volume(at) => {
+                let n = self.volumes.nodes[at].write_at(f.offset, buf)?;
+                f.offset += n as u64;
+                Ok(n)
+            },
+            Node::Framebuffer => {
+                shared_fb()?;
+                // SAFETY: as for `read`.
+                let n = unsafe { fb::fb_back_write(f.offset, buf) }.map_err(fb_err)?;
+                if n == 0 && !buf.is_empty() {
+                    return Err(VfsError::NoSpace);
+                }
+                f.offset += n as u64;
+                Ok(n)
+            },
+            Node::Serial => {
+                for &b in buf {
+                    crate::serial::putc(b);
+                }
+                Ok(buf.len())
+            },
+            // Written entropy is discarded: the hardware RNG takes no seed.
+            Node::Random | Node::Null | Node::Zero => Ok(buf.len()),
+            Node::Keyboard | Node::Mouse => Err(VfsError::ReadOnly),
+        }
+    }
+
+    fn stat(&mut self, _dev: &mut RawBlockDevice, path: &str) -> Result<FileStat, VfsError> {
+        let node = self.lookup(path)?;
+        Ok(self.stat_node(path, node))
+    }
+
+    fn readdir(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        path: &str,
+    ) -> Result<Vec<DirEntry>, VfsError> {
+        let node = self.lookup(path)?;
+        Ok(self
+            .list(node)?
+            .iter()
+            .map(|(name, d_type)| {
+                let mut e = DirEntry {
+                    d_type: *d_type,
+                    version_count: 1,
+                    ..DirEntry::zeroed()
+                };
+                let n = name.len().min(e.name.len());
+                e.name[..n].copy_from_slice(&name.as_bytes()[..n]);
+                e.name_len = n as u16;
+                e
+            })
+            .collect())
+    }
+
+    /// Volume writes flush as they go.
+    fn sync(&mut self, _dev: &mut RawBlockDevice) -> Result<(), VfsError> {
+        Ok(())
+    }
+
+    fn statfs(&mut self, _dev: &mut RawBlockDevice) -> Result<StatFs, VfsError> {
+        Ok(StatFs::default())
+    }
+}
+
+fn fb_present() -> bool {
+    // SAFETY: read-only snapshot of the registered framebuffer.
+    unsafe { nic_fb::fb_registered() }.is_some()
+}
+
+/// The shared back buffer is the compositor's while one runs; its clients
+/// draw to their own surfaces.
+fn shared_fb() -> Result<(), VfsError> {
+    // SAFETY: reads the compositor pid and the caller's pid.
+    if unsafe { fb::is_composited_client() } {
+        Err(VfsError::Busy)
+    } else {
+        Ok(())
+    }
+}
+
+fn fb_err(errno: u64) -> VfsError {
+    if errno == ENODEV {
+        VfsError::NoDev
+    } else {
+        VfsError::NoSpace
+    }
+}
+
+/// `n` of `want` bytes from a stream that may be empty: `WouldBlock`
+/// instead of a 0 that would read as end of file.
+fn nonblocking(want: usize, n: usize) -> Result<usize, VfsError> {
+    if n == 0 && want != 0 {
+        Err(VfsError::WouldBlock)
+    } else {
+        Ok(n)
+    }
+}
@@ -1,9 +1,10 @@
 //! The filesystem-backend seam (spec §4): `FsBackend` trait, `VfsError`, and per-fd state.
 
+use alloc::string::String;
 use alloc::vec::Vec;
 use morpheus_block_types::RawBlockDevice;
 use morpheus_foundation::storage::FD_COOKIE_LEN;
-use morpheus_foundation::types::{DirEntry, FileStat};
+use morpheus_foundation::types::{CleanStats, DirEntry, FileStat, StatFs};
 
 /// One canonical FS error (spec §4). Each backend maps its private error
 /// (`HelixError`/`Fat32Error`) into this; the subsystem owns the single
@@ -27,6 +28,18 @@ pub enum VfsError {
     CrossDevice,
     Busy,
     NoDev,
+    /// Extended attribute not present.
+    NoData,
+    /// Extended attributes over the backend's per-entry limit.
+    TooBig,
+    /// Too many symbolic links followed resolving a path.
+    Loop,
+    /// Permission bits deny the caller (`Perm` is for owner/root-only ops).
+    Access,
+    /// A directory quota would be exceeded.
+    Quota,
+    /// Nothing to read yet from a device that cannot wait under the lock.
+    WouldBlock,
 }
 
 /// What a backend can do. `open(O_WRITE)` against `writable:false` is rejected up
@@ -38,6 +51,48 @@ pub struct FsCapabilities {
     pub resizable: bool,
     pub snapshots: bool,
     pub versions: bool,
+    /// Nodes carry an owner and permission bits (`stat` uid/gid/mode) that
+    /// the VFS enforces; without it anyone may read and only root may write.
+    pub ownership: bool,
+}
+
+/// Who a VFS call acts for: the calling process's `uid`/`gid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cred {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Cred {
+    pub const ROOT: Cred = Cred { uid: 0, gid: 0 };
+
+    pub fn is_root(self) -> bool {
+        self.uid == 0
+    }
+}
+
+/// Access wanted from a node, as one `rwx` triplet of its mode.
+pub mod access {
+    pub const READ: u32 = 4;
+    pub const WRITE: u32 = 2;
+    pub const EXEC: u32 = 1;
+}
+
+/// Whether `cred` may access a node with `st`'s owner and mode for `want`
+/// (`access` bits). The owner's triplet applies to the owner, the group's to
+/// its members, the rest to everyone else; root passes every check.
+pub fn permits(st: &FileStat, cred: Cred, want: u32) -> bool {
+    if cred.is_root() {
+        return true;
+    }
+    let shift = if cred.uid == st.uid {
+        6
+    } else if cred.gid == st.gid {
+        3
+    } else {
+        0
+    };
+    (st.mode >> shift) & want == want
 }
 
 /// What an fd points at; dispatch branches on this. Regular fds go to the VFS and
@@ -48,6 +103,8 @@ pub enum FdKind {
     Socket,
     Pipe,
     Epoll,
+    /// A change-notification instance (`storage::watch`).
+    Watch,
 }
 
 impl FdKind {
@@ -126,6 +183,16 @@ impl FdState {
         let len = (self.path_len as usize).min(self.path.len());
         core::str::from_utf8(&self.path[..len]).unwrap_or("")
     }
+
+    /// Cursor of a copied-out descriptor, through its OFD when shared. Live
+    /// fds go through `FdTable::offset`.
+    pub fn current_offset(&self) -> u64 {
+        if self.ofd != 0 {
+            ofd::offset(self.ofd)
+        } else {
+            self.offset
+        }
+    }
 }
 
 /// Shared open-file-description (OFD) slab. `dup`/`dup2`/`F_DUPFD`/`try_clone` and
@@ -513,6 +580,17 @@ impl Default for FdTable {
     }
 }
 
+/// One namespace change from a backend's change feed (`FsBackend::take_changes`).
+/// `mask` is one `FS_EV_*` bit; `FS_EV_MOVED_FROM` is the whole rename, with
+/// the destination in `to`. Paths are mount-relative.
+pub struct FsChange {
+    pub mask: u32,
+    /// Pairs the halves of a rename once split.
+    pub cookie: u64,
+    pub path: String,
+    pub to: String,
+}
+
 /// `open` result: the cookie the backend wants persisted, plus whether the
 /// resolved object is a directory (the VFS uses this to gate read/write).
 pub struct OpenFile {
@@ -585,10 +663,121 @@ pub trait FsBackend {
         Err(VfsError::Unsupported)
     }
 
+    /// Create `path` as a symbolic link to `target`, stored verbatim. Backends
+    /// never follow links; the VFS does (`StorageGlobal::follow_links`).
+    fn symlink(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _target: &str,
+        _path: &str,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Target of the symbolic link `path`; not a link → `Inval`.
+    fn readlink(&mut self, _dev: &mut RawBlockDevice, _path: &str) -> Result<String, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Give the file at `old` the further name `new`.
+    fn link(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _old: &str,
+        _new: &str,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Replace the permission bits (`0o7777`) of `path`. Gated on
+    /// `ownership`; the caller checks who may.
+    fn chmod(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _path: &str,
+        _mode: u32,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Give `path` to `uid`/`gid`. Gated on `ownership`.
+    fn chown(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _path: &str,
+        _uid: u32,
+        _gid: u32,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
     fn sync(&mut self, _dev: &mut RawBlockDevice) -> Result<(), VfsError> {
         Err(VfsError::Unsupported)
     }
 
+    /// One step of background cleaning (log recycling, defragmentation),
+    /// spending about `budget` blocks of I/O. `true` once a step finds
+    /// nothing to do, so the caller can back off.
+    fn clean(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _budget: u64,
+        _ts: u64,
+    ) -> Result<bool, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Cleaner progress since mount.
+    fn clean_stats(&mut self, _dev: &mut RawBlockDevice) -> Result<CleanStats, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Space and usage of the volume. The caller fills in the mount's own
+    /// fields (`fs_type`, `flags`, `mount_id`).
+    fn statfs(&mut self, _dev: &mut RawBlockDevice) -> Result<StatFs, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Grow or shrink the filesystem in place to `lba_count` device sectors
+    /// from its start (`capabilities().resizable`). The caller moves the
+    /// volume's bounds: first when growing, after when shrinking.
+    fn resize(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _lba_count: u64,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Drop the retained versions of `path`, or of everything under it, that
+    /// `policy` (`XATTR_RETENTION` syntax) would not keep; returns how many.
+    fn prune_versions(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _path: &str,
+        _policy: &[u8],
+        _ts: u64,
+    ) -> Result<u64, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Bring `path` — a file, or everything under a directory, deleted ones
+    /// included — back to how it was at log position `lsn`.
+    fn restore(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _path: &str,
+        _lsn: u64,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
     /// Record a point-in-time marker; returns its handle (Helix: the snapshot
     /// LSN, usable for `O_AT_LSN` reads).
     fn snapshot(
@@ -609,4 +798,93 @@ pub trait FsBackend {
     ) -> Result<Vec<(u64, u64, u32)>, VfsError> {
         Err(VfsError::Unsupported)
     }
+    /// Open a transaction: mutations until `tx_commit` land atomically across a
+    /// crash. One per volume; a second is `Busy`.
+    fn tx_begin(&mut self, _dev: &mut RawBlockDevice, _ts: u64) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    fn tx_commit(&mut self, _dev: &mut RawBlockDevice, _ts: u64) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Roll the volume back to its state at `tx_begin`.
+    fn tx_abort(&mut self, _dev: &mut RawBlockDevice, _ts: u64) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Start or stop keeping a change feed. A backend without one ignores
+    /// this and reports nothing.
+    fn watch(&mut self, _on: bool) {}
+
+    /// Changes since the last call, oldest first, and whether some were
+    /// dropped. Changes inside an open transaction wait for its commit.
+    fn take_changes(&mut self) -> (Vec<FsChange>, bool) {
+        (Vec::new(), false)
+    }
+
+    /// Create or replace extended attribute `name` on `path`.
+    fn setxattr(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _path: &str,
+        _name: &str,
+        _value: &[u8],
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Value of attribute `name`; absent → `NoData`.
+    fn getxattr(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _path: &str,
+        _name: &str,
+    ) -> Result<Vec<u8>, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    fn listxattr(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _path: &str,
+    ) -> Result<Vec<String>, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    fn removexattr(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _path: &str,
+        _name: &str,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Snapshots oldest-first as `(lsn, timestamp_ns, name)` (mirrors
+    /// `SnapshotInfo`).
+    fn snapshots(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+    ) -> Result<Vec<(u64, u64, String)>, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Drop the newest snapshot called `name`, freeing what only it kept.
+    fn delete_snapshot(&mut self, _dev: &mut RawBlockDevice, _name: &str) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    /// Atomically make the live namespace equal to the newest snapshot called
+    /// `name`.
+    fn rollback_snapshot(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        _name: &str,
+        _ts: u64,
+    ) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
 }
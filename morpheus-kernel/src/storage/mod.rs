@@ -7,24 +7,38 @@
 //! lives in later phases.
 
 pub mod backends;
+pub mod cleaner;
+pub mod devfs;
 pub mod fs_api;
+pub mod procfs;
 pub mod registry;
 pub mod slab;
 pub mod staging;
+pub mod watch;
 
 use crate::sync::RawSpinLock;
-use backends::{Fat32Fs as Fat32Adapter, HelixFs as HelixAdapter, MountedFs};
+use alloc::string::String;
+use backends::{
+    Ext4Fs as Ext4Adapter, Fat32Fs as Fat32Adapter, HelixFs as HelixAdapter,
+    Iso9660Fs as IsoAdapter, MountedFs,
+};
+use devfs::DevFs;
 use fs_api::VfsError;
 use gpt_disk_io::BlockIo;
 use gpt_disk_types::Lba;
 use morpheus_block_types::{DeviceKind, RawBlockDevice};
 use morpheus_foundation::errno::{
-    EBADF, EBUSY, EEXIST, EINVAL, EIO, EISDIR, EMFILE, ENODEV, ENOENT, ENOMEM, ENOSPC, ENOTDIR,
-    ENOTEMPTY, EPERM, EROFS, EXDEV,
+    E2BIG, EACCES, EAGAIN, EBADF, EBUSY, EDQUOT, EEXIST, EINVAL, EIO, EISDIR, ELOOP, EMFILE,
+    ENODATA, ENODEV, ENOENT, ENOMEM, ENOSPC, ENOTDIR, ENOTEMPTY, EPERM, EROFS, EXDEV,
 };
+use morpheus_foundation::flags::mode;
 use morpheus_foundation::storage::{
-    FS_AUTO, FS_FAT32, FS_HELIX, FS_NONE, FS_UNKNOWN, MNT_RDONLY, MNT_STAGED, VOLUME_NONE,
+    FS_AUTO, FS_DEVFS, FS_EXT4, FS_FAT32, FS_HELIX, FS_ISO9660, FS_NONE, FS_PROC, FS_SYSFS,
+    FS_UNKNOWN, MNT_FSCK, MNT_RDONLY, MNT_SNAPSHOT, MNT_STAGED, VOLUME_NONE,
 };
+use morpheus_foundation::types::SnapshotSpec;
+use morpheus_helix::crypt::{Crypt, CryptIo};
+use procfs::{ProcFs, SynthTree};
 use registry::{
     DeviceEntry, DeviceRegistry, MountEntry, MountTable, RamBacking, Volume, VolumeRegistry,
 };
@@ -56,6 +70,7 @@ impl StorageGlobal {
         path: &'p str,
     ) -> Option<(u64, &'s mut MountEntry, &'s mut RawBlockDevice, &'p str)> {
         let mount_id = self.mounts.resolve(path)?;
+        self.refresh_synthetic(mount_id);
         // Read mp_len, drop the shared borrow, then take the disjoint mut borrows below.
         let mp_len = self.mounts.get(mount_id)?.mount_point_len as usize;
         let rel = mount_relative(path, mp_len);
@@ -71,11 +86,108 @@ impl StorageGlobal {
         &mut self,
         mount_id: u64,
     ) -> Option<(&mut MountEntry, &mut RawBlockDevice)> {
+        self.refresh_synthetic(mount_id);
         let m = self.mounts.get_mut(mount_id)?;
         let device_id = m.device_id;
         let dev = self.devices.get_mut(device_id)?;
         Some((m, &mut dev.device))
     }
+
+    /// A synthetic mount renders the registries it is about to be borrowed
+    /// out of, so it gets a fresh copy of them before each lend; `/dev` gets
+    /// its volume nodes' device handles the same way.
+    fn refresh_synthetic(&mut self, mount_id: u64) {
+        match self.mounts.get(mount_id).map(|m| &m.fs) {
+            Some(MountedFs::Proc(_)) => {
+                let view = procfs::StorageView::capture(self);
+                if let Some(MountEntry {
+                    fs: MountedFs::Proc(p),
+                    ..
+                }) = self.mounts.get_mut(mount_id)
+                {
+                    p.set_storage(view);
+                }
+            },
+            Some(MountedFs::Dev(_)) => {
+                let view = devfs::VolumeView::capture(self);
+                if let Some(MountEntry {
+                    fs: MountedFs::Dev(d),
+                    ..
+                }) = self.mounts.get_mut(mount_id)
+                {
+                    d.set_volumes(view);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Expand the symbolic links in the canonical absolute `path`, component
+    /// by component and across mounts. A relative target resolves against the
+    /// link's directory. `follow_last` false leaves a link in the final
+    /// component alone (lstat, readlink, unlink, rename). Components that do
+    /// not exist pass through, so create paths resolve too. More than
+    /// `SYMLOOP_MAX` links → `Loop`.
+    pub fn follow_links(&mut self, path: &str, follow_last: bool) -> Result<String, VfsError> {
+        let mut pending: alloc::vec::Vec<String> = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .rev()
+            .map(String::from)
+            .collect();
+        let mut parts: alloc::vec::Vec<String> = alloc::vec::Vec::new();
+        let mut followed = 0;
+        while let Some(name) = pending.pop() {
+            match name.as_str() {
+                "." => continue,
+                ".." => {
+                    parts.pop();
+                    continue;
+                },
+                _ => parts.push(name),
+            }
+            if pending.is_empty() && !follow_last {
+                break;
+            }
+            let at = join_abs(&parts);
+            let Some((_, m, dev, rel)) = self.resolve_mut(&at) else {
+                continue;
+            };
+            if !m
+                .fs
+                .stat(dev, rel)
+                .is_ok_and(|st| st.mode & mode::S_IFMT == mode::S_IFLNK)
+            {
+                continue;
+            }
+            followed += 1;
+            if followed > SYMLOOP_MAX {
+                return Err(VfsError::Loop);
+            }
+            let target = m.fs.readlink(dev, rel)?;
+            parts.pop();
+            if target.starts_with('/') {
+                parts.clear();
+            }
+            pending.extend(
+                target
+                    .split('/')
+                    .filter(|s| !s.is_empty())
+                    .rev()
+                    .map(String::from),
+            );
+        }
+        Ok(join_abs(&parts))
+    }
+}
+
+/// Most symbolic links one path resolution follows (Linux's limit).
+pub const SYMLOOP_MAX: usize = 40;
+
+fn join_abs(parts: &[String]) -> String {
+    let mut out = String::from("/");
+    out.push_str(&parts.join("/"));
+    out
 }
 
 impl Default for StorageGlobal {
@@ -109,6 +221,7 @@ pub struct StorageGuard {
 
 impl Drop for StorageGuard {
     fn drop(&mut self) {
+        watch::pump(self.g);
         STORAGE_LOCK.unlock();
     }
 }
@@ -146,10 +259,16 @@ pub fn vfs_err_to_errno(e: VfsError) -> u64 {
         VfsError::CrossDevice => EXDEV,
         VfsError::Busy => EBUSY,
         VfsError::NoDev => ENODEV,
+        VfsError::NoData => ENODATA,
+        VfsError::TooBig => E2BIG,
+        VfsError::Loop => ELOOP,
+        VfsError::Access => EACCES,
+        VfsError::Quota => EDQUOT,
+        VfsError::WouldBlock => EAGAIN,
     }
 }
 
-/// Sniff FS at `lba_start` (spec §3 layer 2): Helix magic → `FS_HELIX`, FAT32 boot sig → `FS_FAT32`, else `FS_UNKNOWN`; I/O error → `FS_NONE`.
+/// Sniff FS at `lba_start` (spec §3 layer 2): Helix magic → `FS_HELIX`, an ISO9660 volume descriptor → `FS_ISO9660`, an ext2/3/4 superblock → `FS_EXT4`, FAT32 boot sig → `FS_FAT32`, else `FS_UNKNOWN`; I/O error → `FS_NONE`.
 pub fn detect_fs(dev: &mut RawBlockDevice, lba_start: u64) -> u32 {
     let bs = dev.block_size().to_u32() as usize;
     if bs == 0 {
@@ -163,6 +282,17 @@ pub fn detect_fs(dev: &mut RawBlockDevice, lba_start: u64) -> u32 {
     if sec.len() >= 8 && sec[..8] == morpheus_helix::types::HELIX_MAGIC {
         return FS_HELIX;
     }
+    // ISO9660: "CD001" after the type byte of the descriptor at sector 16.
+    // Checked before FAT32 since hybrid images carry an MBR boot sig too.
+    if is_iso9660(dev, lba_start, bs) {
+        return FS_ISO9660;
+    }
+    // ext2/3/4: magic in the superblock at byte 1024. Checked before FAT32
+    // because mkfs.ext* leaves the first KiB alone, so a stale FAT boot sector
+    // can survive a reformat.
+    if is_ext(dev, lba_start, bs) {
+        return FS_EXT4;
+    }
     // FAT32: 0x55AA boot sig + a plausible bytes-per-sector at offset 11.
     if sec.len() >= 512 && sec[510] == 0x55 && sec[511] == 0xAA {
         let bps = u16::from_le_bytes([sec[11], sec[12]]) as u32;
@@ -173,12 +303,47 @@ pub fn detect_fs(dev: &mut RawBlockDevice, lba_start: u64) -> u32 {
     FS_UNKNOWN
 }
 
+fn is_iso9660(dev: &mut RawBlockDevice, lba_start: u64, bs: usize) -> bool {
+    const DESCRIPTOR: usize = 16 * 2048;
+    let at = DESCRIPTOR % bs;
+    if at + 6 > bs {
+        return false;
+    }
+    let mut sec = alloc::vec![0u8; bs];
+    let lba = Lba(lba_start + (DESCRIPTOR / bs) as u64);
+    dev.read_blocks(lba, &mut sec).is_ok() && &sec[at + 1..at + 6] == b"CD001"
+}
+
+/// The 0xEF53 magic plus a sane block-size shift and revision, so a stray
+/// pair of bytes at offset 1080 does not claim the volume.
+fn is_ext(dev: &mut RawBlockDevice, lba_start: u64, bs: usize) -> bool {
+    use morpheus_ext4::types::{EXT_MAGIC, SUPERBLOCK_OFFSET, SUPERBLOCK_SIZE};
+    let sb_at = SUPERBLOCK_OFFSET as usize;
+    let at = sb_at % bs;
+    // Whole device blocks covering the superblock: two 512-byte sectors, or
+    // the one large block it sits inside.
+    let mut sb = alloc::vec![0u8; bs.max(SUPERBLOCK_SIZE)];
+    let lba = Lba(lba_start + (sb_at / bs) as u64);
+    if dev.read_blocks(lba, &mut sb).is_err() {
+        return false;
+    }
+    let sb = &sb[at..at + SUPERBLOCK_SIZE];
+    let magic = u16::from_le_bytes([sb[0x38], sb[0x39]]);
+    let log_block = u32::from_le_bytes([sb[0x18], sb[0x19], sb[0x1A], sb[0x1B]]);
+    let rev = u32::from_le_bytes([sb[0x4C], sb[0x4D], sb[0x4E], sb[0x4F]]);
+    magic == EXT_MAGIC && log_block <= 6 && rev <= 1
+}
+
+/// `fsck` checks a Helix volume first (`MNT_FSCK`), repairing unless read-only.
+/// `key` unlocks an encrypted Helix volume; no other volume takes one.
 fn build_backend(
     fs_type: u32,
     dev: &mut RawBlockDevice,
     lba_start: u64,
     block_size: u32,
     read_only: bool,
+    fsck: bool,
+    key: Option<&[u8]>,
 ) -> Result<(MountedFs, u32), VfsError> {
     let resolved = if fs_type == FS_AUTO {
         detect_fs(dev, lba_start)
@@ -187,21 +352,95 @@ fn build_backend(
     };
     match resolved {
         FS_HELIX => {
-            let engine = morpheus_helix::HelixFs::mount(dev, lba_start, block_size)
+            let mut crypt = unlock_helix(dev, lba_start, block_size, key)?;
+            let mut io = CryptIo::new(dev, crypt.as_mut());
+            if fsck {
+                check_helix(&mut io, lba_start, block_size, !read_only)?;
+            }
+            let engine = morpheus_helix::HelixFs::mount(&mut io, lba_start, block_size)
                 .map_err(backends::helix_err_pub)?;
             Ok((
-                MountedFs::Helix(HelixAdapter::new(engine, read_only)),
+                MountedFs::Helix(HelixAdapter::new(engine, crypt, read_only)),
                 FS_HELIX,
             ))
         },
+        FS_FAT32 if key.is_some() => Err(VfsError::Inval),
         FS_FAT32 => {
-            let fat = Fat32Adapter::mount(dev, lba_start)?;
+            let fat = Fat32Adapter::mount(dev, lba_start, read_only)?;
             Ok((MountedFs::Fat32(fat), FS_FAT32))
         },
+        FS_ISO9660 if key.is_some() => Err(VfsError::Inval),
+        FS_ISO9660 => {
+            let iso = IsoAdapter::mount(dev, lba_start)?;
+            Ok((MountedFs::Iso9660(iso), FS_ISO9660))
+        },
+        FS_EXT4 if key.is_some() => Err(VfsError::Inval),
+        FS_EXT4 => {
+            let ext = Ext4Adapter::mount(dev, lba_start)?;
+            Ok((MountedFs::Ext4(ext), FS_EXT4))
+        },
         _ => Err(VfsError::Inval),
     }
 }
 
+/// The key of an encrypted Helix volume; `None` without a passphrase, which
+/// leaves an encrypted volume to fail its mount with `Perm`.
+fn unlock_helix(
+    dev: &mut RawBlockDevice,
+    lba_start: u64,
+    block_size: u32,
+    key: Option<&[u8]>,
+) -> Result<Option<Crypt>, VfsError> {
+    match key {
+        Some(passphrase) => Crypt::unlock(dev, lba_start, block_size, passphrase)
+            .map(Some)
+            .map_err(backends::helix_err_pub),
+        None => Ok(None),
+    }
+}
+
+/// Mount-time fsck. Findings go to the serial log; the mount itself decides
+/// whether what is left is mountable.
+fn check_helix<B: BlockIo>(
+    dev: &mut B,
+    lba_start: u64,
+    block_size: u32,
+    repair: bool,
+) -> Result<(), VfsError> {
+    let report = morpheus_helix::fsck::check(dev, lba_start, block_size, repair)
+        .map_err(backends::helix_err_pub)?;
+    for finding in &report.findings {
+        let msg = alloc::format!("{}", finding.problem);
+        if finding.repaired {
+            crate::serial::log_info("FSCK", 810, &msg);
+        } else {
+            crate::serial::log_warn("FSCK", 811, &msg);
+        }
+    }
+    if report.is_clean() {
+        crate::serial::log_info("FSCK", 812, "volume clean");
+    }
+    Ok(())
+}
+
+/// Parse a chunked ISO's manifest and mount the image its chunks hold. The
+/// source volume must be the partition holding the first chunk, and the
+/// download must be complete.
+fn build_chunked_iso(
+    dev: &mut RawBlockDevice,
+    lba_start: u64,
+    manifest: &[u8],
+) -> Result<MountedFs, VfsError> {
+    let manifest = morpheus_storage_format::iso::IsoManifest::deserialize(manifest)
+        .map_err(|_| VfsError::Inval)?;
+    let first = manifest.chunks.get(0).ok_or(VfsError::Inval)?;
+    if !manifest.is_complete() || first.start_lba != lba_start {
+        return Err(VfsError::Inval);
+    }
+    let iso = IsoAdapter::mount_chunked(dev, &manifest)?;
+    Ok(MountedFs::Iso9660(iso))
+}
+
 fn build_fresh_helix(
     dev: &mut RawBlockDevice,
     lba_start: u64,
@@ -213,11 +452,35 @@ fn build_fresh_helix(
         dev, lba_start, lba_count, block_size, "ram", [0u8; 16],
     )
     .map_err(backends::helix_err_pub)?;
-    Ok(MountedFs::Helix(HelixAdapter::new(engine, read_only)))
+    Ok(MountedFs::Helix(HelixAdapter::new(engine, None, read_only)))
+}
+
+/// Resolve `spec` (a name wins over the LSN) and freeze a Helix engine there.
+fn build_helix_snapshot(
+    dev: &mut RawBlockDevice,
+    lba_start: u64,
+    block_size: u32,
+    spec: &SnapshotSpec,
+    key: Option<&[u8]>,
+) -> Result<MountedFs, VfsError> {
+    let mut crypt = unlock_helix(dev, lba_start, block_size, key)?;
+    let mut io = CryptIo::new(dev, crypt.as_mut());
+    let lsn = match spec.name_str() {
+        "" if spec.name_len != 0 => return Err(VfsError::Inval),
+        "" => spec.lsn,
+        name => morpheus_helix::HelixFs::lookup_snapshot(&mut io, lba_start, block_size, name)
+            .map_err(backends::helix_err_pub)?,
+    };
+    let engine = morpheus_helix::HelixFs::mount_snapshot(&mut io, lba_start, block_size, lsn)
+        .map_err(backends::helix_err_pub)?;
+    Ok(MountedFs::Helix(HelixAdapter::new(engine, crypt, true)))
 }
 
 /// Mount request (spec §5 axes): source × residency × fs_type. `aux` = required
 /// size when `source == VOLUME_NONE`; optional stage-size cap otherwise.
+/// `snapshot` is required with `MNT_SNAPSHOT` and ignored without it; `key`
+/// is the passphrase of an encrypted Helix volume (`MNT_KEY`); `manifest` the
+/// serialized manifest of a chunked ISO (`MNT_MANIFEST`).
 pub struct MountReq {
     pub source_volume_id: u64,
     pub mount_point: [u8; 256],
@@ -225,6 +488,9 @@ pub struct MountReq {
     pub fs_type: u32,
     pub flags: u32,
     pub aux: u64,
+    pub snapshot: Option<SnapshotSpec>,
+    pub key: Option<alloc::vec::Vec<u8>>,
+    pub manifest: Option<alloc::vec::Vec<u8>>,
     /// Owning pid (0 = kernel/persistent); drives reclamation and skips policy
     /// caps when `privileged`.
     pub pid: u32,
@@ -240,9 +506,32 @@ pub fn mount(req: &MountReq) -> Result<u64, u64> {
     if mp.is_empty() || !mp.starts_with('/') {
         return Err(EINVAL);
     }
+    if matches!(req.fs_type, FS_PROC | FS_SYSFS | FS_DEVFS) {
+        return mount_synthetic(req, mp);
+    }
     let staged = req.flags & MNT_STAGED != 0 || req.source_volume_id == VOLUME_NONE;
     let read_only = req.flags & MNT_RDONLY != 0;
 
+    // A fresh RAM volume has nothing to unlock.
+    if req.key.is_some() && req.source_volume_id == VOLUME_NONE {
+        return Err(EINVAL);
+    }
+    // A chunked ISO is read in place from its chunk partitions.
+    if req.manifest.is_some()
+        && (staged
+            || req.key.is_some()
+            || req.flags & MNT_SNAPSHOT != 0
+            || !matches!(req.fs_type, FS_AUTO | FS_ISO9660))
+    {
+        return Err(EINVAL);
+    }
+    if req.flags & MNT_SNAPSHOT != 0 {
+        if staged {
+            return Err(EINVAL);
+        }
+        let spec = req.snapshot.as_ref().ok_or(EINVAL)?;
+        return mount_snapshot(req, mp, spec);
+    }
     if staged {
         mount_staged(req, mp, read_only)
     } else {
@@ -269,8 +558,22 @@ fn mount_direct(req: &MountReq, mp: &str, read_only: bool) -> Result<u64, u64> {
 
     let dev = g.devices.get_mut(device_id).ok_or(ENODEV)?;
     let ro = read_only || vol_ro;
-    let (fs, fs_type) = build_backend(req.fs_type, &mut dev.device, lba_start, block_size, ro)
-        .map_err(vfs_err_to_errno)?;
+    let fsck = req.flags & MNT_FSCK != 0;
+    let (fs, fs_type) = match req.manifest.as_deref() {
+        Some(manifest) => {
+            build_chunked_iso(&mut dev.device, lba_start, manifest).map(|fs| (fs, FS_ISO9660))
+        },
+        None => build_backend(
+            req.fs_type,
+            &mut dev.device,
+            lba_start,
+            block_size,
+            ro,
+            fsck,
+            req.key.as_deref(),
+        ),
+    }
+    .map_err(vfs_err_to_errno)?;
 
     let entry = MountEntry {
         volume_id: req.source_volume_id,
@@ -283,6 +586,7 @@ fn mount_direct(req: &MountReq, mp: &str, read_only: bool) -> Result<u64, u64> {
         open_fds: 0,
         ephemeral: false,
         owner_pid: req.pid,
+        tx_owner: None,
     };
     let mount_id = g.mounts.insert(entry).ok_or(ENOMEM)?;
     if let Some(v) = g.volumes.get_mut(req.source_volume_id) {
@@ -291,6 +595,117 @@ fn mount_direct(req: &MountReq, mp: &str, read_only: bool) -> Result<u64, u64> {
     Ok(mount_id)
 }
 
+/// Synthetic mount (`FS_PROC`/`FS_SYSFS`/`FS_DEVFS`): no volume and nothing
+/// staged; `/proc` and `/sys` are always read-only. Its device is a
+/// zero-sector placeholder, since path resolution looks one up for every
+/// mount; teardown drops it.
+fn mount_synthetic(req: &MountReq, mp: &str) -> Result<u64, u64> {
+    if req.source_volume_id != VOLUME_NONE
+        || req.key.is_some()
+        || req.manifest.is_some()
+        || req.flags & (MNT_STAGED | MNT_SNAPSHOT | MNT_FSCK) != 0
+    {
+        return Err(EINVAL);
+    }
+    // SAFETY: we don't hold the lock yet; single critical section.
+    let guard = unsafe { lock() };
+    let g = &mut *guard.g;
+
+    if g.mounts.resolve_exact(mp).is_some() {
+        return Err(EEXIST);
+    }
+    let device_id = g
+        .devices
+        .insert(DeviceEntry {
+            device: procfs::null_device(),
+            kind: DeviceKind::Ram,
+            block_size: 512,
+            lba_count: 0,
+            ram: None,
+        })
+        .ok_or(ENOMEM)?;
+    let (fs, flags) = match req.fs_type {
+        FS_DEVFS => (
+            MountedFs::Dev(DevFs::new(req.flags & MNT_RDONLY != 0)),
+            req.flags,
+        ),
+        FS_PROC => (
+            MountedFs::Proc(ProcFs::new(SynthTree::Proc)),
+            req.flags | MNT_RDONLY,
+        ),
+        _ => (
+            MountedFs::Proc(ProcFs::new(SynthTree::Sys)),
+            req.flags | MNT_RDONLY,
+        ),
+    };
+    let entry = MountEntry {
+        volume_id: VOLUME_NONE,
+        device_id,
+        fs,
+        fs_type: req.fs_type,
+        flags,
+        mount_point: req.mount_point,
+        mount_point_len: req.mount_point_len,
+        open_fds: 0,
+        ephemeral: false,
+        owner_pid: req.pid,
+        tx_owner: None,
+    };
+    match g.mounts.insert(entry) {
+        Some(id) => Ok(id),
+        None => {
+            let _ = g.devices.remove(device_id);
+            Err(ENOMEM)
+        },
+    }
+}
+
+/// Snapshot mount: a read-only Helix view of the source volume frozen at a
+/// snapshot marker. It reads the same device as the live mount, which keeps
+/// the snapshot's blocks pinned, so the volume may already be mounted and its
+/// `mounted` state is left alone.
+fn mount_snapshot(req: &MountReq, mp: &str, spec: &SnapshotSpec) -> Result<u64, u64> {
+    if req.fs_type != FS_AUTO && req.fs_type != FS_HELIX {
+        return Err(EINVAL);
+    }
+    // SAFETY: we don't hold the lock yet; single critical section.
+    let guard = unsafe { lock() };
+    let g = &mut *guard.g;
+
+    if g.mounts.resolve_exact(mp).is_some() {
+        return Err(EEXIST);
+    }
+    let vol = g.volumes.get(req.source_volume_id).ok_or(ENODEV)?;
+    let (device_id, lba_start, block_size) = (vol.device_id, vol.lba_start, vol.block_size);
+    let dev = g.devices.get_mut(device_id).ok_or(ENODEV)?;
+    if detect_fs(&mut dev.device, lba_start) != FS_HELIX {
+        return Err(EINVAL);
+    }
+    let fs = build_helix_snapshot(
+        &mut dev.device,
+        lba_start,
+        block_size,
+        spec,
+        req.key.as_deref(),
+    )
+    .map_err(vfs_err_to_errno)?;
+
+    let entry = MountEntry {
+        volume_id: req.source_volume_id,
+        device_id,
+        fs,
+        fs_type: FS_HELIX,
+        flags: req.flags | MNT_RDONLY,
+        mount_point: req.mount_point,
+        mount_point_len: req.mount_point_len,
+        open_fds: 0,
+        ephemeral: false,
+        owner_pid: req.pid,
+        tx_owner: None,
+    };
+    g.mounts.insert(entry).ok_or(ENOMEM)
+}
+
 /// Staged mount (spec §7 two-phase). Phase A (locked): admission + reserve +
 /// allocate. Phase B (unlocked): copy the source LBA range into RAM. Phase C
 /// (relocked): register the `DEV_RAM` device + ephemeral volume, build the
@@ -405,7 +820,17 @@ fn mount_staged(req: &MountReq, mp: &str, read_only: bool) -> Result<u64, u64> {
     let build = if from_nothing {
         build_fresh_helix(&mut dev_ref.device, 0, ram_lba_count, block_size, ro)
     } else {
-        build_backend(req.fs_type, &mut dev_ref.device, 0, block_size, ro).map(|(fs, _)| fs)
+        let fsck = req.flags & MNT_FSCK != 0;
+        build_backend(
+            req.fs_type,
+            &mut dev_ref.device,
+            0,
+            block_size,
+            ro,
+            fsck,
+            req.key.as_deref(),
+        )
+        .map(|(fs, _)| fs)
     };
     let fs = match build {
         Ok(fs) => fs,
@@ -417,6 +842,10 @@ fn mount_staged(req: &MountReq, mp: &str, read_only: bool) -> Result<u64, u64> {
     let fs_type = match &fs {
         MountedFs::Helix(_) => FS_HELIX,
         MountedFs::Fat32(_) => FS_FAT32,
+        MountedFs::Iso9660(_) => FS_ISO9660,
+        MountedFs::Ext4(_) => FS_EXT4,
+        MountedFs::Proc(_) => FS_PROC,
+        MountedFs::Dev(_) => FS_DEVFS,
     };
 
     // Synthesize the ephemeral volume (visible in SYS_VOLUMES; owned by the pid).
@@ -458,6 +887,7 @@ fn mount_staged(req: &MountReq, mp: &str, read_only: bool) -> Result<u64, u64> {
         open_fds: 0,
         ephemeral: true,
         owner_pid: req.pid,
+        tx_owner: None,
     };
     match g.mounts.insert(entry) {
         Some(mount_id) => Ok(mount_id),
@@ -547,6 +977,82 @@ pub fn umount(mount_point: &str, flags: u32) -> Result<(), u64> {
     Ok(())
 }
 
+/// Resize the volume mounted at `mount_point` (exact) to `lba_count` sectors,
+/// its GPT entry included; 0 grows it as far as the partition table allows,
+/// and more than that is `EINVAL`.
+/// The entry moves first when growing and last when shrinking, so the
+/// partition always covers the filesystem. Returns the new sector count.
+/// Caller must NOT hold `STORAGE_LOCK`.
+pub fn resize(mount_point: &str, lba_count: u64, ts: u64) -> Result<u64, u64> {
+    use morpheus_storage_format::disk::gpt_ops;
+
+    // SAFETY: single critical section.
+    let guard = unsafe { lock() };
+    let g = &mut *guard.g;
+
+    let mount_id = g.mounts.resolve_exact(mount_point).ok_or(ENOENT)?;
+    let volume_id = {
+        let m = g.mounts.get(mount_id).ok_or(ENOENT)?;
+        if m.flags & MNT_RDONLY != 0 {
+            return Err(EROFS);
+        }
+        // A staged volume is a RAM copy and a snapshot a frozen view; neither
+        // has a partition to move.
+        if m.ephemeral || m.flags & MNT_SNAPSHOT != 0 {
+            return Err(EINVAL);
+        }
+        if m.tx_owner.is_some() {
+            return Err(EBUSY);
+        }
+        m.volume_id
+    };
+    let vol = g.volumes.get(volume_id).ok_or(ENODEV)?;
+    // The GPT code speaks 512-byte sectors only.
+    if vol.block_size != 512 {
+        return Err(EINVAL);
+    }
+    let lba_start = vol.lba_start;
+
+    let (m, dev) = g.mount_dev_mut(mount_id).ok_or(ENOENT)?;
+    // A `CryptIo` without a key is a plain `&mut` pass-through.
+    let span =
+        gpt_ops::find_partition_at(CryptIo::new(dev, None), lba_start, 512).map_err(|_| EINVAL)?;
+    // Sectors up to the next partition or the table's last usable one.
+    let room = span
+        .max_end_lba
+        .checked_sub(lba_start)
+        .and_then(|n| n.checked_add(1))
+        .ok_or(EINVAL)?;
+    let new_count = match lba_count {
+        0 => room,
+        n if n > room => return Err(EINVAL),
+        n => n,
+    };
+    let new_end = lba_start + (new_count - 1);
+    let move_entry = |dev: &mut RawBlockDevice, end: u64| {
+        gpt_ops::resize_partition(CryptIo::new(dev, None), span.index, end).map_err(|e| match e {
+            gpt_ops::GptError::IoError => EIO,
+            _ => EINVAL,
+        })
+    };
+    if new_end > span.end_lba {
+        move_entry(dev, new_end)?;
+        if let Err(e) = m.fs.resize(dev, new_count, ts) {
+            let _ = move_entry(dev, span.end_lba);
+            return Err(vfs_err_to_errno(e));
+        }
+    } else {
+        m.fs.resize(dev, new_count, ts).map_err(vfs_err_to_errno)?;
+        if new_end < span.end_lba {
+            move_entry(dev, new_end)?;
+        }
+    }
+    if let Some(v) = g.volumes.get_mut(volume_id) {
+        v.lba_count = new_count;
+    }
+    Ok(new_count)
+}
+
 /// Boot-only: tear down the current `/` mount (bypassing the `/`→EBUSY guard) so
 /// the root-selection policy can reject a candidate that lacks `/bin/init` and try
 /// the next. Frees staged RAM + restores budget for an ephemeral root. Only sound
@@ -576,9 +1082,15 @@ fn teardown_mount(g: &mut StorageGlobal, mount_id: u64) {
     let device_id = entry.device_id;
     let ephemeral = entry.ephemeral;
     let owner_pid = entry.owner_pid;
+    // A snapshot view never claimed the volume; the live mount may still hold it.
+    let claimed = entry.flags & MNT_SNAPSHOT == 0;
+    let synthetic = matches!(entry.fs, MountedFs::Proc(_) | MountedFs::Dev(_));
     drop(entry); // drops MountedFs backend
 
-    if ephemeral {
+    if synthetic {
+        // Its placeholder device served nothing else.
+        let _ = g.devices.remove(device_id);
+    } else if ephemeral {
         // Free RAM + restore budget, then drop synth volume + device.
         if let Some(dev) = g.devices.remove(device_id) {
             if let Some(ram) = dev.ram {
@@ -592,8 +1104,10 @@ fn teardown_mount(g: &mut StorageGlobal, mount_id: u64) {
             }
         }
         let _ = g.volumes.remove(volume_id);
-    } else if let Some(v) = g.volumes.get_mut(volume_id) {
-        v.mounted = false;
+    } else if claimed {
+        if let Some(v) = g.volumes.get_mut(volume_id) {
+            v.mounted = false;
+        }
     }
 }
 
@@ -699,7 +1213,23 @@ pub fn reap_process(pid: u32, fd_table: &mut fs_api::FdTable) {
         }
     }
 
-    // (2) auto-umount ephemeral mounts owned by this pid.
+    // (2) abort transactions this pid left open; replay would drop them anyway,
+    // but the live mount must not stay locked against everyone else.
+    let ts = crate::global::hal().timer().now_ns();
+    let owned: alloc::vec::Vec<u64> = g
+        .mounts
+        .iter()
+        .filter(|(_, m)| m.tx_owner == Some(pid))
+        .map(|(id, _)| id)
+        .collect();
+    for id in owned {
+        if let Some((m, dev)) = g.mount_dev_mut(id) {
+            let _ = m.fs.tx_abort(dev, ts);
+            m.tx_owner = None;
+        }
+    }
+
+    // (3) auto-umount ephemeral mounts owned by this pid.
     let mut victims: alloc::vec::Vec<u64> = alloc::vec::Vec::new();
     for (id, m) in g.mounts.iter() {
         if m.ephemeral && m.owner_pid == pid {
@@ -0,0 +1,792 @@
+//! Synthetic filesystems: `/proc` (per-process state) and `/sys` (PCI devices,
+//! volumes, mounts, network, scheduler). Nothing is stored; each read renders
+//! its node afresh from the kernel structures it names, and `stat` reports the
+//! size of a fresh render. Read-only.
+//!
+//! Backends run under `STORAGE_LOCK`, which orders after `PROCESS_TABLE_LOCK`,
+//! so process state is copied out with `try_inspect_processes` (`Busy` if the
+//! table stays locked) and the storage registries come from a `StorageView`
+//! the resolver hands over before lending the mount out.
+
+use super::fs_api::{FdKind, FdState, FsBackend, FsCapabilities, OpenFile, VfsError};
+use super::StorageGlobal;
+use crate::process::vma::Vma;
+use crate::process::{BlockReason, Process, ProcessState};
+use crate::schedular::SCHEDULER;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use morpheus_block_types::RawBlockDevice;
+use morpheus_foundation::flags::{dirent_type, mode, open_flags, PROT_EXEC, PROT_NONE, PROT_WRITE};
+use morpheus_foundation::storage::{
+    DEV_AHCI, DEV_RAM, DEV_SDHCI, DEV_USBMSD, DEV_VIRTIO, FD_COOKIE_LEN, FS_EXT4, FS_FAT32,
+    FS_HELIX, FS_ISO9660, FS_NONE, FS_PROC, FS_SYSFS, MNT_RDONLY, MNT_SNAPSHOT, MNT_STAGED,
+    VOL_EPHEMERAL, VOL_MOUNTED, VOL_RDONLY, VOL_REMOVABLE,
+};
+use morpheus_foundation::types::{DirEntry, FileStat, StatFs};
+use morpheus_hal_api::BusAddr;
+
+/// Which tree a synthetic mount serves.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SynthTree {
+    Proc,
+    Sys,
+}
+
+pub struct ProcFs {
+    tree: SynthTree,
+    storage: StorageView,
+}
+
+impl ProcFs {
+    pub fn new(tree: SynthTree) -> Self {
+        Self {
+            tree,
+            storage: StorageView::default(),
+        }
+    }
+
+    /// Replace the registry copy `/sys` and fd paths render from.
+    pub fn set_storage(&mut self, view: StorageView) {
+        self.storage = view;
+    }
+}
+
+/// The placeholder device behind a synthetic mount: no sectors, every
+/// transfer fails. Path resolution wants a device per mount.
+pub fn null_device() -> RawBlockDevice {
+    unsafe fn read(_: *mut u8, _: u64, _: *mut u8, _: usize) -> bool {
+        false
+    }
+    unsafe fn write(_: *mut u8, _: u64, _: *const u8, _: usize) -> bool {
+        false
+    }
+    unsafe fn flush(_: *mut u8) -> bool {
+        true
+    }
+    // SAFETY: the callbacks never touch the (null) context.
+    unsafe { RawBlockDevice::new(core::ptr::null_mut(), 0, 512, read, write, flush) }
+}
+
+/// Copy of the volume and mount registries, taken while they are not lent out.
+#[derive(Default)]
+pub struct StorageView {
+    volumes: Vec<VolumeRow>,
+    mounts: Vec<MountRow>,
+}
+
+struct VolumeRow {
+    id: u64,
+    device_id: u64,
+    device_kind: u32,
+    fs_type: u32,
+    lba_start: u64,
+    lba_count: u64,
+    block_size: u32,
+    label: String,
+    flags: u32,
+}
+
+struct MountRow {
+    id: u64,
+    volume_id: u64,
+    fs_type: u32,
+    flags: u32,
+    path: String,
+    open_fds: u32,
+    owner_pid: u32,
+}
+
+impl StorageView {
+    pub fn capture(g: &StorageGlobal) -> Self {
+        let volumes = g
+            .volumes
+            .iter()
+            .map(|(id, v)| {
+                let mut flags = 0;
+                for (set, bit) in [
+                    (v.read_only, VOL_RDONLY),
+                    (v.mounted, VOL_MOUNTED),
+                    (v.removable, VOL_REMOVABLE),
+                    (v.ephemeral, VOL_EPHEMERAL),
+                ] {
+                    if set {
+                        flags |= bit;
+                    }
+                }
+                VolumeRow {
+                    id,
+                    device_id: v.device_id,
+                    device_kind: g
+                        .devices
+                        .get(v.device_id)
+                        .map_or(DEV_RAM, |d| d.kind.to_dev()),
+                    fs_type: v.detected_fs,
+                    lba_start: v.lba_start,
+                    lba_count: v.lba_count,
+                    block_size: v.block_size,
+                    label: String::from_utf8_lossy(nul_trimmed(&v.label)).into_owned(),
+                    flags,
+                }
+            })
+            .collect();
+        let mounts = g
+            .mounts
+            .iter()
+            .map(|(id, m)| MountRow {
+                id,
+                volume_id: m.volume_id,
+                fs_type: m.fs_type,
+                flags: m.flags,
+                path: m.path().to_string(),
+                open_fds: m.open_fds,
+                owner_pid: m.owner_pid,
+            })
+            .collect();
+        Self { volumes, mounts }
+    }
+
+    /// Absolute path of the file a descriptor on `mount_id` names.
+    fn absolute(&self, mount_id: u64, rel: &str) -> String {
+        match self.mounts.iter().find(|m| m.id == mount_id) {
+            Some(m) if m.path != "/" => format!("{}{}", m.path, rel),
+            Some(_) => rel.to_string(),
+            None => format!("?{}", rel),
+        }
+    }
+}
+
+enum Node {
+    Dir(Dir),
+    File(File),
+    /// Symbolic link with this (relative) target.
+    Link(String),
+}
+
+#[derive(Clone, Copy)]
+enum Dir {
+    ProcRoot,
+    Pid(u32),
+    SysRoot,
+    Pci,
+    Block,
+    Net,
+}
+
+#[derive(Clone, Copy)]
+enum File {
+    Status(u32),
+    Fds(u32),
+    Vmas(u32),
+    Cmdline(u32),
+    Environ(u32),
+    Meminfo,
+    Memmap,
+    Pci(BusAddr),
+    Volume(u64),
+    Mounts,
+    Nic,
+    NetStack,
+    Sched,
+}
+
+const PID_FILES: [&str; 5] = ["status", "fds", "vmas", "cmdline", "environ"];
+
+impl ProcFs {
+    fn lookup(&self, path: &str) -> Result<Node, VfsError> {
+        let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let node = match (self.tree, parts.as_slice()) {
+            (SynthTree::Proc, []) => Node::Dir(Dir::ProcRoot),
+            (SynthTree::Proc, ["self"]) => {
+                // SAFETY: reads the calling thread's own slot.
+                Node::Link(unsafe { SCHEDULER.current_memory_leader_pid() }.to_string())
+            },
+            (SynthTree::Proc, ["meminfo"]) => Node::File(File::Meminfo),
+            (SynthTree::Proc, ["memmap"]) => Node::File(File::Memmap),
+            (SynthTree::Proc, [pid]) => Node::Dir(Dir::Pid(live_pid(pid)?)),
+            (SynthTree::Proc, [pid, name]) => {
+                let pid = live_pid(pid)?;
+                Node::File(match *name {
+                    "status" => File::Status(pid),
+                    "fds" => File::Fds(pid),
+                    "vmas" => File::Vmas(pid),
+                    "cmdline" => File::Cmdline(pid),
+                    "environ" => File::Environ(pid),
+                    _ => return Err(VfsError::NotFound),
+                })
+            },
+            (SynthTree::Sys, []) => Node::Dir(Dir::SysRoot),
+            (SynthTree::Sys, ["pci"]) => Node::Dir(Dir::Pci),
+            (SynthTree::Sys, ["pci", name]) => {
+                let addr = parse_bdf(name).ok_or(VfsError::NotFound)?;
+                if crate::hal().bus().cfg_read16(addr, 0) == 0xFFFF {
+                    return Err(VfsError::NotFound);
+                }
+                Node::File(File::Pci(addr))
+            },
+            (SynthTree::Sys, ["block"]) => Node::Dir(Dir::Block),
+            (SynthTree::Sys, ["block", id]) => {
+                let id: u64 = id.parse().map_err(|_| VfsError::NotFound)?;
+                if !self.storage.volumes.iter().any(|v| v.id == id) {
+                    return Err(VfsError::NotFound);
+                }
+                Node::File(File::Volume(id))
+            },
+            (SynthTree::Sys, ["mounts"]) => Node::File(File::Mounts),
+            (SynthTree::Sys, ["net"]) => Node::Dir(Dir::Net),
+            (SynthTree::Sys, ["net", "nic"]) => Node::File(File::Nic),
+            (SynthTree::Sys, ["net", "stack"]) => Node::File(File::NetStack),
+            (SynthTree::Sys, ["sched"]) => Node::File(File::Sched),
+            _ => return Err(VfsError::NotFound),
+        };
+        Ok(node)
+    }
+
+    fn list(&self, dir: Dir) -> Result<Vec<(String, u8)>, VfsError> {
+        let file = |n: &str| (n.to_string(), dirent_type::DT_REG);
+        let dir_ = |n: String| (n, dirent_type::DT_DIR);
+        let out = match dir {
+            Dir::ProcRoot => {
+                let mut out = alloc::vec![
+                    ("self".to_string(), dirent_type::DT_LNK),
+                    file("meminfo"),
+                    file("memmap"),
+                ];
+                let pids = SCHEDULER
+                    .try_inspect_processes(|t| {
+                        t.iter()
+                            .flatten()
+                            .filter(|p| !p.is_free())
+                            .map(|p| p.pid)
+                            .collect::<Vec<_>>()
+                    })
+                    .ok_or(VfsError::Busy)?;
+                out.extend(pids.into_iter().map(|pid| dir_(pid.to_string())));
+                out
+            },
+            Dir::Pid(_) => PID_FILES.iter().map(|n| file(n)).collect(),
+            Dir::SysRoot => alloc::vec![
+                dir_("pci".to_string()),
+                dir_("block".to_string()),
+                file("mounts"),
+                dir_("net".to_string()),
+                file("sched"),
+            ],
+            Dir::Pci => {
+                let mut out = Vec::new();
+                crate::hal().bus().for_each_device(&mut |a| {
+                    out.push(file(&format_bdf(a)));
+                });
+                out
+            },
+            Dir::Block => self
+                .storage
+                .volumes
+                .iter()
+                .map(|v| file(&v.id.to_string()))
+                .collect(),
+            Dir::Net => alloc::vec![file("nic"), file("stack")],
+        };
+        Ok(out)
+    }
+
+    fn render(&self, file: File) -> Result<Vec<u8>, VfsError> {
+        let text = match file {
+            File::Status(pid) => inspect(pid, render_status)?,
+            File::Fds(pid) => {
+                let fds = inspect(pid, |t, p| {
+                    leader(t, p)
+                        .fd_table
+                        .iter()
+                        .map(|(fd, s)| (fd, *s))
+                        .collect::<Vec<_>>()
+                })?;
+                self.render_fds(&fds)
+            },
+            File::Vmas(pid) => inspect(pid, |t, p| render_vmas(leader(t, p).vma_table.iter()))?,
+            File::Cmdline(pid) => {
+                return inspect(pid, |_, p| {
+                    p.args[..(p.args_len as usize).min(256)].to_vec()
+                })
+            },
+            File::Environ(pid) => return inspect(pid, |t, p| leader(t, p).env_block.clone()),
+            File::Meminfo => render_meminfo(),
+            File::Memmap => render_memmap(),
+            File::Pci(addr) => render_pci(addr),
+            File::Volume(id) => self.render_volume(id)?,
+            File::Mounts => self.render_mounts(),
+            File::Nic => render_nic(),
+            File::NetStack => render_net_stack(),
+            File::Sched => render_sched(),
+        };
+        Ok(text.into_bytes())
+    }
+
+    fn render_fds(&self, fds: &[(usize, FdState)]) -> String {
+        let mut s = String::new();
+        for (fd, st) in fds {
+            let _ = match st.kind {
+                FdKind::Regular => writeln!(
+                    s,
+                    "{} file {} {}",
+                    fd,
+                    st.current_offset(),
+                    self.storage.absolute(st.mount_id, st.path_str())
+                ),
+                FdKind::Socket => writeln!(s, "{} socket", fd),
+                FdKind::Pipe => writeln!(s, "{} pipe", fd),
+                FdKind::Epoll => writeln!(s, "{} epoll", fd),
+                FdKind::Watch => writeln!(s, "{} watch", fd),
+            };
+        }
+        s
+    }
+
+    fn render_volume(&self, id: u64) -> Result<String, VfsError> {
+        let v = self
+            .storage
+            .volumes
+            .iter()
+            .find(|v| v.id == id)
+            .ok_or(VfsError::NotFound)?;
+        let mut s = String::new();
+        let _ = writeln!(s, "device:\t{}", v.device_id);
+        let _ = writeln!(s, "kind:\t{}", dev_name(v.device_kind));
+        let _ = writeln!(s, "fs:\t{}", fs_name(v.fs_type));
+        let _ = writeln!(s, "label:\t{}", v.label);
+        let _ = writeln!(s, "lba_start:\t{}", v.lba_start);
+        let _ = writeln!(s, "lba_count:\t{}", v.lba_count);
+        let _ = writeln!(s, "block_size:\t{}", v.block_size);
+        let _ = writeln!(
+            s,
+            "bytes:\t{}",
+            v.lba_count.saturating_mul(v.block_size as u64)
+        );
+        let mut flags = Vec::new();
+        for (bit, name) in [
+            (VOL_RDONLY, "ro"),
+            (VOL_MOUNTED, "mounted"),
+            (VOL_REMOVABLE, "removable"),
+            (VOL_EPHEMERAL, "ephemeral"),
+        ] {
+            if v.flags & bit != 0 {
+                flags.push(name);
+            }
+        }
+        let _ = writeln!(s, "flags:\t{}", flags.join(","));
+        Ok(s)
+    }
+
+    /// One line per mount: point, fs, options, volume id, open fds, owner pid.
+    fn render_mounts(&self) -> String {
+        let mut s = String::new();
+        for m in &self.storage.mounts {
+            let mut opts = String::from(if m.flags & MNT_RDONLY != 0 {
+                "ro"
+            } else {
+                "rw"
+            });
+            if m.flags & MNT_STAGED != 0 {
+                opts.push_str(",staged");
+            }
+            if m.flags & MNT_SNAPSHOT != 0 {
+                opts.push_str(",snapshot");
+            }
+            let _ = writeln!(
+                s,
+                "{} {} {} {} {} {}",
+                m.path,
+                fs_name(m.fs_type),
+                opts,
+                m.volume_id,
+                m.open_fds,
+                m.owner_pid
+            );
+        }
+        s
+    }
+
+    fn stat_node(&self, path: &str, node: &Node) -> Result<FileStat, VfsError> {
+        let (perm, size, (uid, gid)) = match node {
+            Node::Dir(Dir::Pid(pid)) => (mode::S_IFDIR | 0o555, 0, owner(*pid)?),
+            Node::Dir(_) => (mode::S_IFDIR | 0o555, 0, (0, 0)),
+            Node::Link(target) => (mode::S_IFLNK | 0o777, target.len() as u64, (0, 0)),
+            Node::File(f) => {
+                let size = self.render(*f)?.len() as u64;
+                match *f {
+                    // Paths and secrets: the owner's business.
+                    File::Fds(pid) | File::Environ(pid) => {
+                        (mode::S_IFREG | 0o400, size, owner(pid)?)
+                    },
+                    File::Status(pid) | File::Vmas(pid) | File::Cmdline(pid) => {
+                        (mode::S_IFREG | 0o444, size, owner(pid)?)
+                    },
+                    _ => (mode::S_IFREG | 0o444, size, (0, 0)),
+                }
+            },
+        };
+        let is_dir = matches!(node, Node::Dir(_));
+        Ok(FileStat {
+            mode: perm,
+            key: path_key(path),
+            size,
+            physical_size: if is_dir { 0 } else { size },
+            nlink: if is_dir { 2 } else { 1 },
+            uid,
+            gid,
+            version_count: 1,
+            ..FileStat::default()
+        })
+    }
+}
+
+impl FsBackend for ProcFs {
+    fn capabilities(&self) -> FsCapabilities {
+        FsCapabilities {
+            ownership: true,
+            ..FsCapabilities::default()
+        }
+    }
+
+    fn open(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        path: &str,
+        flags: u32,
+        _ts: u64,
+    ) -> Result<OpenFile, VfsError> {
+        if flags & (open_flags::O_WRITE | open_flags::O_CREATE | open_flags::O_TRUNC) != 0 {
+            return Err(VfsError::ReadOnly);
+        }
+        let node = self.lookup(path)?;
+        Ok(OpenFile {
+            cookie: [0u8; FD_COOKIE_LEN],
+            is_dir: matches!(node, Node::Dir(_)),
+        })
+    }
+
+    /// Renders the whole node and returns the slice at the fd's offset, so
+    /// a reader that spans several calls can see a seam between two renders.
+    fn read(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        f: &FdState,
+        buf: &mut [u8],
+    ) -> Result<usize, VfsError> {
+        let file = match self.lookup(f.path_str())? {
+            Node::File(file) => file,
+            Node::Dir(_) => return Err(VfsError::IsDir),
+            Node::Link(_) => return Err(VfsError::Inval),
+        };
+        let data = self.render(file)?;
+        let start = (f.offset as usize).min(data.len());
+        let n = buf.len().min(data.len() - start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        Ok(n)
+    }
+
+    fn stat(&mut self, _dev: &mut RawBlockDevice, path: &str) -> Result<FileStat, VfsError> {
+        let node = self.lookup(path)?;
+        self.stat_node(path, &node)
+    }
+
+    fn readdir(
+        &mut self,
+        _dev: &mut RawBlockDevice,
+        path: &str,
+    ) -> Result<Vec<DirEntry>, VfsError> {
+        let dir = match self.lookup(path)? {
+            Node::Dir(d) => d,
+            _ => return Err(VfsError::NotDir),
+        };
+        Ok(self
+            .list(dir)?
+            .iter()
+            .map(|(name, d_type)| {
+                let mut e = DirEntry {
+                    d_type: *d_type,
+                    version_count: 1,
+                    ..DirEntry::zeroed()
+                };
+                let n = name.len().min(e.name.len());
+                e.name[..n].copy_from_slice(&name.as_bytes()[..n]);
+                e.name_len = n as u16;
+                e
+            })
+            .collect())
+    }
+
+    fn readlink(&mut self, _dev: &mut RawBlockDevice, path: &str) -> Result<String, VfsError> {
+        match self.lookup(path)? {
+            Node::Link(target) => Ok(target),
+            _ => Err(VfsError::Inval),
+        }
+    }
+
+    fn sync(&mut self, _dev: &mut RawBlockDevice) -> Result<(), VfsError> {
+        Ok(())
+    }
+
+    fn statfs(&mut self, _dev: &mut RawBlockDevice) -> Result<StatFs, VfsError> {
+        Ok(StatFs::default())
+    }
+}
+
+/// `pid` as a live process, else `NotFound`.
+fn live_pid(name: &str) -> Result<u32, VfsError> {
+    let pid: u32 = name.parse().map_err(|_| VfsError::NotFound)?;
+    inspect(pid, |_, _| pid)
+}
+
+/// Run `f` on live process `pid` under the process table lock.
+fn inspect<R>(pid: u32, f: impl FnOnce(&[Option<Process>], &Process) -> R) -> Result<R, VfsError> {
+    SCHEDULER
+        .try_inspect_processes(|t| match t.get(pid as usize) {
+            Some(Some(p)) if !p.is_free() => Ok(f(t, p)),
+            _ => Err(VfsError::NotFound),
+        })
+        .ok_or(VfsError::Busy)?
+}
+
+/// The thread-group leader holding `p`'s fd table, mappings and environment.
+fn leader<'t>(t: &'t [Option<Process>], p: &'t Process) -> &'t Process {
+    match t.get(p.thread_group_leader as usize) {
+        Some(Some(l)) if p.thread_group_leader != 0 => l,
+        _ => p,
+    }
+}
+
+fn owner(pid: u32) -> Result<(u32, u32), VfsError> {
+    inspect(pid, |_, p| (p.uid, p.gid))
+}
+
+fn render_status(t: &[Option<Process>], p: &Process) -> String {
+    let name = String::from_utf8_lossy(nul_trimmed(&p.name));
+    let state = match p.state {
+        ProcessState::Ready => "ready",
+        ProcessState::Running => "running",
+        ProcessState::Blocked(r) => match r {
+            BlockReason::Sleep(_) => "blocked (sleep)",
+            BlockReason::WaitChild(_) => "blocked (wait)",
+            BlockReason::Io | BlockReason::IoReady(_) => "blocked (io)",
+            BlockReason::StdinRead | BlockReason::InputRead => "blocked (input)",
+            BlockReason::PipeRead(_) => "blocked (pipe)",
+            BlockReason::FutexWait(_) => "blocked (futex)",
+        },
+        ProcessState::Zombie => "zombie",
+        ProcessState::Terminated => "terminated",
+    };
+    let cwd = core::str::from_utf8(&p.cwd[..(p.cwd_len as usize).min(256)]).unwrap_or("?");
+    let mut s = String::new();
+    let _ = writeln!(s, "Name:\t{}", name);
+    let _ = writeln!(s, "State:\t{}", state);
+    let _ = writeln!(s, "Pid:\t{}", p.pid);
+    let _ = writeln!(s, "Tgid:\t{}", leader(t, p).pid);
+    let _ = writeln!(s, "PPid:\t{}", p.parent_pid);
+    let _ = writeln!(s, "Uid:\t{}", p.uid);
+    let _ = writeln!(s, "Gid:\t{}", p.gid);
+    let _ = writeln!(s, "Priority:\t{}", p.priority);
+    let _ = writeln!(s, "Importance:\t{}", p.importance_16);
+    let _ = writeln!(s, "CpuTicks:\t{}", p.cpu_ticks);
+    let _ = writeln!(s, "CpuTsc:\t{}", p.cpu_tsc);
+    let _ = writeln!(s, "Pages:\t{}", p.pages_allocated);
+    let _ = writeln!(s, "Cwd:\t{}", cwd);
+    s
+}
+
+/// One line per mapping: range, protection, pages, and backing.
+fn render_vmas<'a>(vmas: impl Iterator<Item = (usize, &'a Vma)>) -> String {
+    let mut s = String::new();
+    for (_, v) in vmas {
+        let end = v.vaddr.saturating_add(v.pages.saturating_mul(4096));
+        let prot = if v.prot & PROT_NONE != 0 {
+            "---"
+        } else {
+            match (v.prot & PROT_WRITE != 0, v.prot & PROT_EXEC != 0) {
+                (false, false) => "r--",
+                (true, false) => "rw-",
+                (false, true) => "r-x",
+                (true, true) => "rwx",
+            }
+        };
+        let backing = if v.owns_phys { "anon" } else { "phys" };
+        let _ = writeln!(
+            s,
+            "{:016x}-{:016x} {} {} {} {:#x}",
+            v.vaddr, end, prot, v.pages, backing, v.phys
+        );
+    }
+    s
+}
+
+fn render_meminfo() -> String {
+    let phys = crate::hal().phys();
+    let mut s = String::new();
+    let _ = writeln!(s, "MemTotal:\t{} kB", phys.total_memory() / 1024);
+    let _ = writeln!(s, "MemFree:\t{} kB", phys.free_memory() / 1024);
+    let _ = writeln!(s, "MemUsed:\t{} kB", phys.allocated_memory() / 1024);
+    s
+}
+
+/// The firmware memory map: start, pages, type (`MemmapEntry::mem_type`).
+fn render_memmap() -> String {
+    let mut s = String::new();
+    crate::hal().phys().for_each_descriptor(&mut |d| {
+        let _ = writeln!(
+            s,
+            "{:016x} {} {}",
+            d.phys_start, d.num_pages, d.mem_type as u32
+        );
+    });
+    s
+}
+
+fn render_pci(addr: BusAddr) -> String {
+    let bus = crate::hal().bus();
+    let id = bus.cfg_read32(addr, 0x00);
+    let class = bus.cfg_read32(addr, 0x08);
+    let header = bus.cfg_read8(addr, 0x0E) & 0x7F;
+    let mut s = String::new();
+    let _ = writeln!(s, "vendor:\t{:#06x}", id & 0xFFFF);
+    let _ = writeln!(s, "device:\t{:#06x}", id >> 16);
+    let _ = writeln!(s, "class:\t{:#08x}", class >> 8);
+    let _ = writeln!(s, "revision:\t{:#04x}", class & 0xFF);
+    let _ = writeln!(s, "header:\t{}", header);
+    let _ = writeln!(s, "irq:\t{}", bus.cfg_read8(addr, 0x3C));
+    // Type 0 headers carry six BARs, bridges two.
+    let bars = if header == 0 { 6 } else { 2 };
+    for i in 0..bars {
+        let bar = bus.read_bar(addr, i);
+        if bar != 0 {
+            let _ = writeln!(s, "bar{}:\t{:#x}", i, bar);
+        }
+    }
+    s
+}
+
+fn render_nic() -> String {
+    let mut s = String::new();
+    let Some((mac, link, stats)) = crate::syscall::handler::nic_fb::nic_status() else {
+        let _ = writeln!(s, "present:\t0");
+        return s;
+    };
+    let _ = writeln!(s, "present:\t1");
+    let _ = writeln!(
+        s,
+        "mac:\t{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    );
+    let _ = writeln!(s, "link:\t{}", if link { "up" } else { "down" });
+    if let Some(st) = stats {
+        let _ = writeln!(s, "tx_packets:\t{}", st.tx_packets);
+        let _ = writeln!(s, "rx_packets:\t{}", st.rx_packets);
+        let _ = writeln!(s, "tx_bytes:\t{}", st.tx_bytes);
+        let _ = writeln!(s, "rx_bytes:\t{}", st.rx_bytes);
+        let _ = writeln!(s, "tx_errors:\t{}", st.tx_errors);
+        let _ = writeln!(s, "rx_errors:\t{}", st.rx_errors);
+        let _ = writeln!(s, "rx_dropped:\t{}", st.rx_dropped);
+        let _ = writeln!(s, "rx_crc_errors:\t{}", st.rx_crc_errors);
+        let _ = writeln!(s, "collisions:\t{}", st.collisions);
+    }
+    s
+}
+
+fn render_net_stack() -> String {
+    let mut s = String::new();
+    let Some(st) = crate::syscall::handler::net::net_stack_stats() else {
+        let _ = writeln!(s, "present:\t0");
+        return s;
+    };
+    let _ = writeln!(s, "present:\t1");
+    let _ = writeln!(s, "tx_packets:\t{}", st.tx_packets);
+    let _ = writeln!(s, "rx_packets:\t{}", st.rx_packets);
+    let _ = writeln!(s, "tx_bytes:\t{}", st.tx_bytes);
+    let _ = writeln!(s, "rx_bytes:\t{}", st.rx_bytes);
+    let _ = writeln!(s, "tx_errors:\t{}", st.tx_errors);
+    let _ = writeln!(s, "rx_errors:\t{}", st.rx_errors);
+    let _ = writeln!(s, "tcp_active:\t{}", st.tcp_active);
+    s
+}
+
+/// System-wide scheduler state, then one `cpuN` line per core: state,
+/// last transition reason, load EWMA, park candidate.
+fn render_sched() -> String {
+    let d = SCHEDULER.debug_snapshot();
+    let cpus = crate::hal().smp().cpu_count() as usize;
+    let mut s = String::new();
+    let _ = writeln!(s, "state:\t{:?}", d.system_state);
+    let _ = writeln!(s, "ticks:\t{}", SCHEDULER.tick_count());
+    let _ = writeln!(s, "live:\t{}", SCHEDULER.live_count());
+    let _ = writeln!(s, "cpus:\t{}", cpus);
+    let _ = writeln!(s, "tsc_hz:\t{}", crate::schedular::tsc_frequency());
+    let _ = writeln!(s, "idle_tsc:\t{}", crate::schedular::idle_tsc_total());
+    let _ = writeln!(s, "thermal:\t{} {}", d.thermal_level, d.thermal_confidence);
+    let hits: Vec<String> = d.tier_hits.iter().map(|h| h.to_string()).collect();
+    let _ = writeln!(s, "tier_hits:\t{}", hits.join(" "));
+    for i in 0..cpus.min(d.core_state.len()) {
+        let _ = writeln!(
+            s,
+            "cpu{}\t{} {} {} {}",
+            i,
+            d.core_state[i],
+            d.core_last_transition_reason[i],
+            d.core_load_ewma[i],
+            d.core_park_candidate[i]
+        );
+    }
+    s
+}
+
+/// `bb:dd.f`, hex, as `lspci` prints it.
+fn format_bdf(a: BusAddr) -> String {
+    format!("{:02x}:{:02x}.{:x}", a.bus, a.device, a.function)
+}
+
+fn parse_bdf(name: &str) -> Option<BusAddr> {
+    let (bus, rest) = name.split_once(':')?;
+    let (device, function) = rest.split_once('.')?;
+    let addr = BusAddr {
+        bus: u8::from_str_radix(bus, 16).ok()?,
+        device: u8::from_str_radix(device, 16).ok()?,
+        function: u8::from_str_radix(function, 16).ok()?,
+    };
+    (addr.device < 32 && addr.function < 8 && format_bdf(addr) == name).then_some(addr)
+}
+
+fn fs_name(fs_type: u32) -> &'static str {
+    match fs_type {
+        FS_HELIX => "helix",
+        FS_FAT32 => "fat32",
+        FS_ISO9660 => "iso9660",
+        FS_EXT4 => "ext4",
+        FS_PROC => "proc",
+        FS_SYSFS => "sysfs",
+        FS_NONE => "none",
+        _ => "unknown",
+    }
+}
+
+fn dev_name(kind: u32) -> &'static str {
+    match kind {
+        DEV_RAM => "ram",
+        DEV_VIRTIO => "virtio",
+        DEV_AHCI => "ahci",
+        DEV_SDHCI => "sdhci",
+        DEV_USBMSD => "usbmsd",
+        _ => "unknown",
+    }
+}
+
+fn nul_trimmed(b: &[u8]) -> &[u8] {
+    &b[..b.iter().position(|&c| c == 0).unwrap_or(b.len())]
+}
+
+/// Stable per-path `FileStat::key` (FNV-1a), as Helix keys by path hash.
+pub(super) fn path_key(path: &str) -> u64 {
+    path.trim_end_matches('/')
+        .bytes()
+        .fold(0xCBF2_9CE4_8422_2325, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0100_0000_01B3)
+        })
+}
@@ -3,6 +3,7 @@
 //! drivers alive in the same address space so Direct mounts work at runtime.
 
 use super::backends::MountedFs;
+use super::fs_api::{access, permits, Cred, VfsError};
 use super::slab::Slab;
 use morpheus_block_types::{DeviceKind, MemBlockDevice, RawBlockDevice};
 
@@ -67,7 +68,7 @@ pub struct Volume {
     pub lba_count: u64,
     pub block_size: u32,
     pub partition_guid: [u8; 16],
-    /// Detected FS (`FS_NONE|FS_HELIX|FS_FAT32|FS_UNKNOWN`).
+    /// Detected FS (`FS_NONE|FS_HELIX|FS_FAT32|FS_ISO9660|FS_EXT4|FS_UNKNOWN`).
     pub detected_fs: u32,
     pub label: [u8; 64],
     pub read_only: bool,
@@ -127,9 +128,97 @@ pub struct MountEntry {
     /// reclamation.
     pub ephemeral: bool,
     pub owner_pid: u32,
+    /// Pid holding the mount's open `SYS_FS_TX` transaction; aborted on its reap.
+    pub tx_owner: Option<u32>,
 }
 
 impl MountEntry {
+    /// True when another process's transaction owns the mount: `pid` may read
+    /// but not mutate until it commits or aborts.
+    pub fn tx_blocks(&self, pid: u32) -> bool {
+        self.tx_owner.is_some_and(|owner| owner != pid)
+    }
+
+    /// `Access` unless `cred` may `want` (`fs_api::access` bits) on the
+    /// mount-relative `rel`. A backend without `ownership` has nothing on disk
+    /// saying whose a file is, so anyone may read it and only root may write;
+    /// a failing stat (e.g. `NotFound`) is returned as is.
+    pub fn check_access(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        rel: &str,
+        cred: Cred,
+        want: u32,
+    ) -> Result<(), VfsError> {
+        if cred.is_root() {
+            return Ok(());
+        }
+        let caps = self.fs.capabilities();
+        if !caps.ownership {
+            return if want & access::WRITE != 0 && caps.writable {
+                Err(VfsError::Access)
+            } else {
+                Ok(())
+            };
+        }
+        let st = self.fs.stat(dev, rel)?;
+        if permits(&st, cred, want) {
+            Ok(())
+        } else {
+            Err(VfsError::Access)
+        }
+    }
+
+    /// `check_access` for adding or removing the name `rel`: write and search
+    /// on the directory holding it, or on the nearest existing ancestor when
+    /// creating `rel` would create that directory too. Returns the directory
+    /// checked, for `adopt`.
+    pub fn check_parent<'r>(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        rel: &'r str,
+        cred: Cred,
+    ) -> Result<&'r str, VfsError> {
+        let mut dir = rel.trim_end_matches('/');
+        loop {
+            dir = match dir.rfind('/') {
+                Some(0) | None => "/",
+                Some(i) => &dir[..i],
+            };
+            match self.check_access(dev, dir, cred, access::WRITE | access::EXEC) {
+                Err(VfsError::NotFound) if dir != "/" => continue,
+                r => return r.map(|()| dir),
+            }
+        }
+    }
+
+    /// Give what creating `rel` made to `cred`: the directories between
+    /// `ancestor` (from `check_parent`) and `rel`, and `rel` itself if
+    /// `include_leaf`. Root's creations keep the backend's default owner.
+    pub fn adopt(
+        &mut self,
+        dev: &mut RawBlockDevice,
+        rel: &str,
+        ancestor: &str,
+        cred: Cred,
+        include_leaf: bool,
+        ts: u64,
+    ) -> Result<(), VfsError> {
+        if cred.is_root() || !self.fs.capabilities().ownership {
+            return Ok(());
+        }
+        let rel = rel.trim_end_matches('/');
+        let mut end = ancestor.trim_end_matches('/').len();
+        while let Some(i) = rel.get(end + 1..).and_then(|rest| rest.find('/')) {
+            end += 1 + i;
+            self.fs.chown(dev, &rel[..end], cred.uid, cred.gid, ts)?;
+        }
+        if include_leaf {
+            self.fs.chown(dev, rel, cred.uid, cred.gid, ts)?;
+        }
+        Ok(())
+    }
+
     pub fn path(&self) -> &str {
         let len = (self.mount_point_len as usize).min(self.mount_point.len());
         core::str::from_utf8(&self.mount_point[..len]).unwrap_or("")
@@ -0,0 +1,287 @@
+//! Filesystem change notifications (`SYS_FS_WATCH*`). A watch instance is an
+//! fd (`FdKind::Watch`) whose cookie names a slot in `WATCH_TABLE`; the slot
+//! holds the instance's watches, by absolute path, and a bounded queue of
+//! `FsEvent`s. Every release of `STORAGE_LOCK` drains the mounts' change feeds
+//! (`FsBackend::take_changes`) into the queues, so an event is queued before
+//! the syscall that caused it returns. Feeds run only while a watch exists.
+//!
+//! A queue with events is `EPOLLIN` under `io::readiness`. The storage lock
+//! may be released under `PROCESS_TABLE_LOCK` (reap, spawn file actions), so
+//! delivery only marks the token; a blocked `read` re-checks on a short slice
+//! as `epoll_wait` does. Lock order: `STORAGE_LOCK`, then `WATCH_TABLE`.
+
+use super::fs_api::FsChange;
+use super::StorageGlobal;
+use crate::io::readiness::{clear_ready, mark_ready, register, unregister, watch_token};
+use crate::sync::SpinLock;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use morpheus_foundation::errno::{EINVAL, ENOSPC};
+use morpheus_foundation::flags::EPOLLIN;
+use morpheus_foundation::storage::{
+    FS_EV_ALL, FS_EV_MOVED_FROM, FS_EV_MOVED_TO, FS_EV_OVERFLOW, FS_WATCH_SUBTREE,
+};
+use morpheus_foundation::types::FsEvent;
+
+const MAX_WATCH_INSTANCES: usize = 64;
+/// Watches per instance.
+const MAX_WATCHES: usize = 256;
+/// Events an instance queues before it drops the rest for one `FS_EV_OVERFLOW`.
+pub const MAX_QUEUED: usize = 256;
+
+struct Watch {
+    wd: u32,
+    /// Absolute, links resolved, no trailing `/`.
+    path: String,
+    /// `FS_EV_*` interest, plus `FS_WATCH_SUBTREE`.
+    mask: u32,
+}
+
+impl Watch {
+    /// `path` itself, an entry of it, or with `FS_WATCH_SUBTREE` anything below.
+    fn covers(&self, path: &str) -> bool {
+        let below = if self.path == "/" {
+            path.get(1..)
+        } else {
+            path.strip_prefix(self.path.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+        };
+        match below {
+            None => path == self.path,
+            Some("") => true,
+            Some(rest) => self.mask & FS_WATCH_SUBTREE != 0 || !rest.contains('/'),
+        }
+    }
+}
+
+struct Instance {
+    watches: Vec<Watch>,
+    queue: VecDeque<FsEvent>,
+    next_wd: u32,
+    /// An `FS_EV_OVERFLOW` is queued and nothing has been read since.
+    overflowed: bool,
+}
+
+impl Instance {
+    const fn new() -> Self {
+        Self {
+            watches: Vec::new(),
+            queue: VecDeque::new(),
+            next_wd: 1,
+            overflowed: false,
+        }
+    }
+
+    fn push(&mut self, wd: u32, mask: u32, cookie: u64, path: &str) {
+        if self.overflowed {
+            return;
+        }
+        let mut ev = FsEvent {
+            version: 0,
+            struct_size: core::mem::size_of::<FsEvent>() as u16,
+            wd,
+            mask,
+            cookie,
+            ..FsEvent::zeroed()
+        };
+        if self.queue.len() + 1 >= MAX_QUEUED || mask == FS_EV_OVERFLOW {
+            self.overflowed = true;
+            ev.wd = 0;
+            ev.mask = FS_EV_OVERFLOW;
+            ev.cookie = 0;
+        } else {
+            let b = path.as_bytes();
+            let n = b.len().min(ev.path.len());
+            ev.path[..n].copy_from_slice(&b[..n]);
+            ev.path_len = n as u16;
+        }
+        self.queue.push_back(ev);
+    }
+}
+
+static WATCH_TABLE: SpinLock<[Option<Instance>; MAX_WATCH_INSTANCES]> =
+    SpinLock::new([const { None }; MAX_WATCH_INSTANCES]);
+
+/// Watches across every instance; the feeds run while it is non-zero.
+static WATCH_COUNT: AtomicUsize = AtomicUsize::new(0);
+/// Whether the feeds were last switched on.
+static FEEDING: AtomicBool = AtomicBool::new(false);
+
+/// Instance id <-> slot is 1-based so a zero cookie can never alias slot 0.
+#[inline]
+fn id_to_slot(id: u64) -> Option<usize> {
+    let i = (id as usize).checked_sub(1)?;
+    (i < MAX_WATCH_INSTANCES).then_some(i)
+}
+
+/// A new, empty instance; `None` when all are taken.
+pub fn create() -> Option<u64> {
+    let id = {
+        let mut t = WATCH_TABLE.lock();
+        let slot = t.iter().position(|i| i.is_none())?;
+        t[slot] = Some(Instance::new());
+        slot as u64 + 1
+    };
+    register(watch_token(id));
+    Some(id)
+}
+
+/// Drop an instance with its watches and queue (fd close path).
+pub fn destroy(id: u64) {
+    if let Some(slot) = id_to_slot(id) {
+        let mut t = WATCH_TABLE.lock();
+        if let Some(inst) = t[slot].take() {
+            WATCH_COUNT.fetch_sub(inst.watches.len(), Ordering::Relaxed);
+        }
+    }
+    unregister(watch_token(id));
+}
+
+/// Watch `path` (absolute, links resolved) for the `FS_EV_*` changes in
+/// `mask`; returns the watch descriptor, the existing one for a path already
+/// watched. Errno on failure.
+pub fn add(id: u64, path: &str, mask: u32) -> Result<u32, u64> {
+    if mask & FS_EV_ALL == 0 || mask & !(FS_EV_ALL | FS_WATCH_SUBTREE) != 0 {
+        return Err(EINVAL);
+    }
+    let path = if path.len() > 1 {
+        path.trim_end_matches('/')
+    } else {
+        path
+    };
+    let slot = id_to_slot(id).ok_or(EINVAL)?;
+    let mut t = WATCH_TABLE.lock();
+    let inst = t[slot].as_mut().ok_or(EINVAL)?;
+    if let Some(w) = inst.watches.iter_mut().find(|w| w.path == path) {
+        w.mask = mask;
+        return Ok(w.wd);
+    }
+    if inst.watches.len() >= MAX_WATCHES {
+        return Err(ENOSPC);
+    }
+    let wd = inst.next_wd;
+    inst.next_wd += 1;
+    inst.watches.push(Watch {
+        wd,
+        path: String::from(path),
+        mask,
+    });
+    WATCH_COUNT.fetch_add(1, Ordering::Relaxed);
+    Ok(wd)
+}
+
+/// Stop watch `wd`; events already queued for it stay.
+pub fn remove(id: u64, wd: u32) -> Result<(), u64> {
+    let slot = id_to_slot(id).ok_or(EINVAL)?;
+    let mut t = WATCH_TABLE.lock();
+    let inst = t[slot].as_mut().ok_or(EINVAL)?;
+    let i = inst.watches.iter().position(|w| w.wd == wd).ok_or(EINVAL)?;
+    inst.watches.remove(i);
+    WATCH_COUNT.fetch_sub(1, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Move up to `out.len()` queued events into `out`, oldest first; `None` for a
+/// stale id. Clears `EPOLLIN` once the queue is empty.
+pub fn take(id: u64, out: &mut [FsEvent]) -> Option<usize> {
+    let slot = id_to_slot(id)?;
+    let mut t = WATCH_TABLE.lock();
+    let inst = t[slot].as_mut()?;
+    let mut n = 0;
+    while n < out.len() {
+        match inst.queue.pop_front() {
+            Some(ev) => {
+                out[n] = ev;
+                n += 1;
+            },
+            None => break,
+        }
+    }
+    if n > 0 {
+        inst.overflowed = false;
+    }
+    if inst.queue.is_empty() {
+        clear_ready(watch_token(id), EPOLLIN);
+    }
+    Some(n)
+}
+
+/// `path` on the mount at `mount_point`, made absolute.
+fn absolute(mount_point: &str, path: &str) -> String {
+    match (mount_point, path) {
+        ("/", p) => String::from(p),
+        (mp, "/") => String::from(mp),
+        (mp, p) => {
+            let mut abs = String::from(mp.trim_end_matches('/'));
+            abs.push_str(p);
+            abs
+        },
+    }
+}
+
+/// Queue a `mask` event for every watch covering `path` that asked for it.
+fn deliver(t: &mut [Option<Instance>], mask: u32, cookie: u64, path: &str) {
+    for (slot, inst) in t.iter_mut().enumerate() {
+        let Some(inst) = inst.as_mut() else {
+            continue;
+        };
+        let before = inst.queue.len();
+        let mut hits = Vec::new();
+        for w in inst.watches.iter().filter(|w| w.mask & mask != 0) {
+            if w.covers(path) {
+                hits.push(w.wd);
+            }
+        }
+        for wd in hits {
+            inst.push(wd, mask, cookie, path);
+        }
+        if inst.queue.len() != before {
+            mark_ready(watch_token(slot as u64 + 1), EPOLLIN);
+        }
+    }
+}
+
+/// Switch the mounts' feeds to match whether anything is watched, and drain
+/// them into the queues. Called with `STORAGE_LOCK` held, just before release.
+pub(super) fn pump(g: &mut StorageGlobal) {
+    let want = WATCH_COUNT.load(Ordering::Relaxed) != 0;
+    if !FEEDING.swap(want, Ordering::Relaxed) && !want {
+        return;
+    }
+    for (_, m) in g.mounts.iter_mut() {
+        m.fs.watch(want);
+        if !want {
+            continue;
+        }
+        let (changes, lost) = m.fs.take_changes();
+        if changes.is_empty() && !lost {
+            continue;
+        }
+        let mp = m.path();
+        let mut t = WATCH_TABLE.lock();
+        if lost {
+            for (slot, inst) in t.iter_mut().enumerate() {
+                if let Some(inst) = inst.as_mut().filter(|i| !i.watches.is_empty()) {
+                    inst.push(0, FS_EV_OVERFLOW, 0, "");
+                    mark_ready(watch_token(slot as u64 + 1), EPOLLIN);
+                }
+            }
+        }
+        for FsChange {
+            mask,
+            cookie,
+            path,
+            to,
+        } in changes
+        {
+            if mask == FS_EV_MOVED_FROM {
+                deliver(&mut t[..], FS_EV_MOVED_FROM, cookie, &absolute(mp, &path));
+                deliver(&mut t[..], FS_EV_MOVED_TO, cookie, &absolute(mp, &to));
+            } else {
+                deliver(&mut t[..], mask, 0, &absolute(mp, &path));
+            }
+        }
+    }
+}
@@ -159,18 +159,7 @@ pub unsafe fn sys_keyboard_read(ptr: u64, len: u64) -> u64 {
         return EFAULT;
     }
     let buf = core::slice::from_raw_parts_mut(ptr as *mut u8, len as usize);
-    let mut n = 0usize;
-    while n < buf.len() {
-        match crate::input::poll_keyboard() {
-            Some(crate::input::InputEvent::Key(byte, _process)) => {
-                buf[n] = byte;
-                n += 1;
-            },
-            Some(_) => continue,
-            None => break,
-        }
-    }
-    n as u64
+    crate::input::read_keys(buf) as u64
 }
 
 /// Atomic-IRQ-wait on x86 (`sti; hlt; cli`) routed through HAL.
@@ -185,6 +174,28 @@ pub unsafe fn sys_getpid() -> u64 {
     SCHEDULER.current_memory_leader_pid() as u64
 }
 
+/// SYS_GETCRED: `uid | gid << 32` of the caller.
+pub unsafe fn sys_getcred() -> u64 {
+    let proc = SCHEDULER.current_process_mut();
+    proc.uid as u64 | (proc.gid as u64) << 32
+}
+
+/// SYS_SETCRED: root may become anyone; anyone else gets `EPERM` for any
+/// change, so dropping root is for good. Processes spawned afterwards inherit
+/// it; `SPAWN_SET_CRED` starts a single child as someone else instead.
+pub unsafe fn sys_setcred(uid: u64, gid: u64) -> u64 {
+    let (Ok(uid), Ok(gid)) = (u32::try_from(uid), u32::try_from(gid)) else {
+        return EINVAL;
+    };
+    let proc = SCHEDULER.current_process_mut();
+    if proc.uid != 0 && (proc.uid, proc.gid) != (uid, gid) {
+        return EPERM;
+    }
+    proc.uid = uid;
+    proc.gid = gid;
+    0
+}
+
 pub unsafe fn sys_kill(pid: u64, signum: u64) -> u64 {
     let sig = match Signal::from_u8(signum as u8) {
         Some(s) => s,
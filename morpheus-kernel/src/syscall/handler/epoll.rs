@@ -2,15 +2,17 @@
 //
 // An epoll instance is itself an fd (FdKind::Epoll); its watch set lives in
 // EPOLL_TABLE keyed by the instance id in the fd cookie. Readiness comes from
-// io::readiness as a level-triggered EPOLL* mask (sockets/pipes/nested epoll;
-// regular files are not pollable). The park primitive signals one token only, so
-// epoll_wait re-scans member masks on each wake rather than via per-source
-// callbacks; EPOLLET is layered on by diffing each watch's live vs last mask.
+// io::readiness as a level-triggered EPOLL* mask (sockets/pipes/watches/nested
+// epoll; regular files are not pollable). The park primitive signals one token
+// only, so epoll_wait re-scans member masks on each wake rather than via
+// per-source callbacks; EPOLLET is layered on by diffing each watch's live vs
+// last mask.
 
 use super::common::*;
 use crate::hal;
 use crate::io::readiness::{
     epoll_token, pipe_token, ready_mask, register, socket_token, unregister, wait_ready,
+    watch_token,
 };
 use crate::schedular::{tsc_frequency, SCHEDULER};
 use crate::storage::fs_api::{FdKind, FdState};
@@ -110,6 +112,7 @@ fn fd_token(desc: &FdState) -> Option<u64> {
         // Pipe ends stash their pipe index in `mount_id` (see ipc::sys_pipe).
         FdKind::Pipe => Some(pipe_token(desc.mount_id as u8)),
         FdKind::Epoll => Some(epoll_token(instance_id(desc))),
+        FdKind::Watch => Some(watch_token(super::watch::instance_id(desc))),
         FdKind::Regular => None,
     }
 }
@@ -267,6 +267,54 @@ unsafe fn fb_map_alloc_buffers(info: &FbInfo) -> Result<(), u64> {
     Ok(())
 }
 
+/// The shared back buffer (allocating it on first use) and the framebuffer
+/// size in bytes. ENODEV if none is registered.
+unsafe fn fb_back_buffer() -> Result<(u64, u64), u64> {
+    let info = fb_registered().ok_or(ENODEV)?;
+    if FB_BACK_PHYS.load(core::sync::atomic::Ordering::Acquire) == 0 {
+        fb_map_alloc_buffers(&info)?;
+    }
+    Ok((
+        FB_BACK_PHYS.load(core::sync::atomic::Ordering::Relaxed),
+        info.size,
+    ))
+}
+
+/// `/dev/fb0` read: back-buffer bytes from `offset` into `dst`, bounced
+/// through the kernel heap since `dst` is unreachable under kernel CR3.
+/// Returns the count, 0 at or past the end.
+pub(crate) unsafe fn fb_back_read(offset: u64, dst: &mut [u8]) -> Result<usize, u64> {
+    let (back, size) = fb_back_buffer()?;
+    let n = (dst.len() as u64).min(size.saturating_sub(offset)) as usize;
+    if n == 0 {
+        return Ok(0);
+    }
+    let mut bounce = alloc::vec![0u8; n];
+    {
+        let _guard = KernelCr3Guard::enter();
+        core::ptr::copy_nonoverlapping((back + offset) as *const u8, bounce.as_mut_ptr(), n);
+    }
+    dst[..n].copy_from_slice(&bounce);
+    Ok(n)
+}
+
+/// `/dev/fb0` write: `src` into the back buffer at `offset`, then mark it
+/// dirty for the timer present. Returns the count, 0 at or past the end.
+pub(crate) unsafe fn fb_back_write(offset: u64, src: &[u8]) -> Result<usize, u64> {
+    let (back, size) = fb_back_buffer()?;
+    let n = (src.len() as u64).min(size.saturating_sub(offset)) as usize;
+    if n == 0 {
+        return Ok(0);
+    }
+    let bounce = src[..n].to_vec();
+    {
+        let _guard = KernelCr3Guard::enter();
+        core::ptr::copy_nonoverlapping(bounce.as_ptr(), (back + offset) as *mut u8, n);
+    }
+    fb_mark_dirty();
+    Ok(n)
+}
+
 /// Timer-ISR-driven delta present: back vs shadow → VRAM.
 /// Skipped when FB_LOCK_PID != 0 (holder calls SYS_FB_PRESENT directly).
 pub unsafe fn fb_present_tick() {
@@ -65,20 +65,26 @@ pub unsafe fn sys_chdir(path_ptr: u64, path_len: u64) -> u64 {
         return 0;
     }
 
-    let is_dir = {
+    // The cwd is stored with symbolic links expanded, as `getcwd` reports it.
+    let (path, is_dir) = {
         let guard = crate::storage::lock();
         let g = &mut *guard.g;
+        let path = match g.follow_links(&path, true) {
+            Ok(p) => p,
+            Err(e) => return crate::storage::vfs_err_to_errno(e),
+        };
         let (_, m, dev, rel) = match g.resolve_mut(&path) {
             Some(t) => t,
             None => return ENOENT,
         };
-        match m.fs.stat(dev, rel) {
+        let is_dir = match m.fs.stat(dev, rel) {
             Ok(stat) => {
                 use morpheus_foundation::flags::mode;
                 stat.mode & mode::S_IFMT == mode::S_IFDIR
             },
             Err(_) => return ENOENT,
-        }
+        };
+        (path, is_dir)
     };
     if !is_dir {
         return ENOTDIR;
@@ -3,14 +3,18 @@
 
 use super::common::*;
 use crate::schedular::SCHEDULER;
-use crate::storage::fs_api::FdKind;
+use crate::storage::fs_api::{access, Cred, FdKind, VfsError};
 use crate::storage::{self, vfs_err_to_errno};
-use morpheus_foundation::errno::EXDEV;
+use morpheus_foundation::errno::{E2BIG, ELOOP, ERANGE, EXDEV};
 use morpheus_foundation::flags::mode;
 use morpheus_foundation::flags::open_flags::{
-    O_APPEND, O_CLOEXEC, O_CREATE, O_EXCL, O_PIPE_READ, O_PIPE_WRITE, O_WRITE,
+    O_APPEND, O_CLOEXEC, O_CREATE, O_EXCL, O_NOFOLLOW, O_PIPE_READ, O_PIPE_WRITE, O_READ, O_TRUNC,
+    O_WRITE,
+};
+use morpheus_foundation::storage::{
+    check_xattr_change, MNT_FSCK, MNT_KEY, MNT_MANIFEST, MNT_RDONLY, MNT_SNAPSHOT, MNT_STAGED,
+    MOUNT_KEY_MAX, TX_ABORT, TX_BEGIN, TX_COMMIT,
 };
-use morpheus_foundation::storage::{MNT_RDONLY, MNT_STAGED};
 use morpheus_foundation::syscall_abi::{SEEK_CUR, SEEK_END, SEEK_SET};
 
 pub unsafe fn sys_fs_open(path_ptr: u64, path_len: u64, flags: u64) -> u64 {
@@ -20,6 +24,7 @@ pub unsafe fn sys_fs_open(path_ptr: u64, path_len: u64, flags: u64) -> u64 {
     };
     let flags = flags as u32;
     let ts = fs_now_ns();
+    let cred = SCHEDULER.current_process_mut().cred();
     let fd_table = SCHEDULER.current_fd_table_mut();
 
     // Fail fast on full fd table before touching the FS.
@@ -30,21 +35,47 @@ pub unsafe fn sys_fs_open(path_ptr: u64, path_len: u64, flags: u64) -> u64 {
 
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let path = match g.follow_links(&path, flags & O_NOFOLLOW == 0) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
     let (mount_id, m, dev, rel) = match g.resolve_mut(&path) {
         Some(t) => t,
         None => return ENOENT,
     };
+    if flags & (O_WRITE | O_CREATE | O_TRUNC) != 0
+        && m.tx_blocks(SCHEDULER.current_process_mut().pid)
+    {
+        return EBUSY;
+    }
+
+    if flags & O_NOFOLLOW != 0
+        && m.fs
+            .stat(dev, rel)
+            .is_ok_and(|st| st.mode & mode::S_IFMT == mode::S_IFLNK)
+    {
+        return ELOOP;
+    }
 
     // O_EXCL (create_new): a real exists-check, not TOCTOU — we hold STORAGE_LOCK
     // across the probe and the create, so nothing can wedge the file in between.
     if flags & O_CREATE != 0 && flags & O_EXCL != 0 && m.fs.stat(dev, rel).is_ok() {
         return EEXIST;
     }
+    let created_under = match check_open(m, dev, rel, flags, cred) {
+        Ok(c) => c,
+        Err(e) => return vfs_err_to_errno(e),
+    };
 
     let opened = match m.fs.open(dev, rel, flags, ts) {
         Ok(o) => o,
         Err(e) => return vfs_err_to_errno(e),
     };
+    if let Some(dir) = created_under {
+        if let Err(e) = m.adopt(dev, rel, dir, cred, true, ts) {
+            return vfs_err_to_errno(e);
+        }
+    }
 
     let mut state = crate::storage::fs_api::FdState::empty();
     state.mount_id = mount_id;
@@ -74,6 +105,38 @@ pub unsafe fn sys_fs_open(path_ptr: u64, path_len: u64, flags: u64) -> u64 {
     fd as u64
 }
 
+/// Permission gate for `open`: the access `flags` ask of `rel` if it exists,
+/// else (with `O_CREATE`) the right to create it, returning the directory
+/// that was checked for `adopt`. Non-root callers only.
+fn check_open<'r>(
+    m: &mut storage::registry::MountEntry,
+    dev: &mut morpheus_block_types::RawBlockDevice,
+    rel: &'r str,
+    flags: u32,
+    cred: Cred,
+) -> Result<Option<&'r str>, VfsError> {
+    if cred.is_root() {
+        return Ok(None);
+    }
+    match m.fs.stat(dev, rel) {
+        Ok(_) => {
+            let mut want = 0;
+            if flags & O_READ != 0 || flags & O_WRITE == 0 {
+                want |= access::READ;
+            }
+            if flags & (O_WRITE | O_TRUNC) != 0 {
+                want |= access::WRITE;
+            }
+            m.check_access(dev, rel, cred, want).map(|()| None)
+        },
+        Err(VfsError::NotFound) if flags & O_CREATE != 0 => {
+            m.check_parent(dev, rel, cred).map(Some)
+        },
+        // Let the backend's open report it.
+        Err(_) => Ok(None),
+    }
+}
+
 pub unsafe fn sys_fs_close(fd: u64) -> u64 {
     let fd_table = SCHEDULER.current_fd_table_mut();
     let desc = match fd_table.get(fd as usize) {
@@ -107,7 +170,8 @@ pub unsafe fn sys_fs_close(fd: u64) -> u64 {
         return 0;
     }
 
-    // epoll fds have no mount/backend; closing one reclaims its watch set.
+    // epoll and watch fds have no mount/backend; closing one reclaims its
+    // instance.
     if desc.kind == FdKind::Epoll {
         super::epoll::destroy_for(&desc);
         return match fd_table.free(fd as usize) {
@@ -115,6 +179,13 @@ pub unsafe fn sys_fs_close(fd: u64) -> u64 {
             None => EBADF,
         };
     }
+    if desc.kind == FdKind::Watch {
+        super::watch::destroy_for(&desc);
+        return match fd_table.free(fd as usize) {
+            Some(_) => 0,
+            None => EBADF,
+        };
+    }
 
     {
         let guard = storage::lock();
@@ -145,6 +216,9 @@ pub unsafe fn sys_fs_read(fd: u64, buf_ptr: u64, len: u64) -> u64 {
     if desc.is_socket() {
         return super::socket::socket_read(fd, buf_ptr, len);
     }
+    if desc.kind == FdKind::Watch {
+        return super::watch::watch_read(fd, buf_ptr, len);
+    }
     // The authoritative cursor lives in the shared OFD for dup'd fds; seed the
     // copy the backend reads from it so aliased fds share one offset.
     desc.offset = fd_table.offset(fd as usize).unwrap_or(desc.offset);
@@ -194,6 +268,9 @@ pub unsafe fn sys_fs_write(fd: u64, buf_ptr: u64, len: u64) -> u64 {
         Some(t) => t,
         None => return EBADF,
     };
+    if m.tx_blocks(SCHEDULER.current_process_mut().pid) {
+        return EBUSY;
+    }
     // O_APPEND atomically retargets the cursor to EOF before each write (POSIX);
     // the backend is whole-file under STORAGE_LOCK, so this is race-free.
     if status & O_APPEND != 0 {
@@ -247,12 +324,25 @@ pub unsafe fn sys_fs_seek(fd: u64, offset: u64, whence: u64) -> u64 {
 }
 
 pub unsafe fn sys_fs_stat(path_ptr: u64, path_len: u64, stat_buf: u64) -> u64 {
+    stat_path(path_ptr, path_len, stat_buf, true)
+}
+
+/// SYS_LSTAT: `stat` of a symbolic link itself rather than its target.
+pub unsafe fn sys_fs_lstat(path_ptr: u64, path_len: u64, stat_buf: u64) -> u64 {
+    stat_path(path_ptr, path_len, stat_buf, false)
+}
+
+unsafe fn stat_path(path_ptr: u64, path_len: u64, stat_buf: u64, follow_last: bool) -> u64 {
     let path = match resolve_user_path(path_ptr, path_len) {
         Some(p) => p,
         None => return EINVAL,
     };
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let path = match g.follow_links(&path, follow_last) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
     let (_, m, dev, rel) = match g.resolve_mut(&path) {
         Some(t) => t,
         None => return ENOENT,
@@ -293,6 +383,10 @@ pub unsafe fn sys_fs_readdir(path_ptr: u64, path_len: u64, buf_ptr: u64, max_ent
     };
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
     let (_, m, dev, rel) = match g.resolve_mut(&path) {
         Some(t) => t,
         None => return ENOENT,
@@ -338,11 +432,28 @@ pub unsafe fn sys_fs_mkdir(path_ptr: u64, path_len: u64) -> u64 {
     let ts = fs_now_ns();
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let path = match g.follow_links(&path, false) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
     let (_, m, dev, rel) = match g.resolve_mut(&path) {
         Some(t) => t,
         None => return ENOENT,
     };
-    match m.fs.mkdir(dev, rel, ts) {
+    let proc = SCHEDULER.current_process_mut();
+    if m.tx_blocks(proc.pid) {
+        return EBUSY;
+    }
+    let cred = proc.cred();
+    let under = match m.check_parent(dev, rel, cred) {
+        Ok(d) => d,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    match m
+        .fs
+        .mkdir(dev, rel, ts)
+        .and_then(|()| m.adopt(dev, rel, under, cred, true, ts))
+    {
         Ok(()) => 0,
         Err(e) => vfs_err_to_errno(e),
     }
@@ -359,15 +470,25 @@ pub unsafe fn sys_fs_unlink(path_ptr: u64, path_len: u64) -> u64 {
     let ts = fs_now_ns();
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let path = match g.follow_links(&path, false) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
     let (_, m, dev, rel) = match g.resolve_mut(&path) {
         Some(t) => t,
         None => return ENOENT,
     };
+    if m.tx_blocks(SCHEDULER.current_process_mut().pid) {
+        return EBUSY;
+    }
     match m.fs.stat(dev, rel) {
         Ok(st) if st.mode & mode::S_IFMT == mode::S_IFDIR => return EISDIR,
         Ok(_) => {},
         Err(e) => return vfs_err_to_errno(e),
     }
+    if let Err(e) = m.check_parent(dev, rel, SCHEDULER.current_process_mut().cred()) {
+        return vfs_err_to_errno(e);
+    }
     match m.fs.unlink(dev, rel, ts) {
         Ok(()) => 0,
         Err(e) => vfs_err_to_errno(e),
@@ -386,6 +507,14 @@ pub unsafe fn sys_fs_rename(old_ptr: u64, old_len: u64, new_ptr: u64, new_len: u
     let ts = fs_now_ns();
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let old = match g.follow_links(&old, false) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let new = match g.follow_links(&new, false) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
 
     // rename across mounts is EXDEV, never an implicit copy+delete (spec §4).
     let src_mount = match g.mounts.resolve(&old) {
@@ -409,7 +538,23 @@ pub unsafe fn sys_fs_rename(old_ptr: u64, old_len: u64, new_ptr: u64, new_len: u
         Some(t) => t,
         None => return ENOENT,
     };
-    match m.fs.rename(dev, rel_old, rel_new, ts) {
+    let proc = SCHEDULER.current_process_mut();
+    if m.tx_blocks(proc.pid) {
+        return EBUSY;
+    }
+    let cred = proc.cred();
+    let under = match m
+        .check_parent(dev, rel_old, cred)
+        .and_then(|_| m.check_parent(dev, rel_new, cred))
+    {
+        Ok(d) => d,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    match m
+        .fs
+        .rename(dev, rel_old, rel_new, ts)
+        .and_then(|()| m.adopt(dev, rel_new, under, cred, false, ts))
+    {
         Ok(()) => 0,
         Err(e) => vfs_err_to_errno(e),
     }
@@ -424,10 +569,21 @@ pub unsafe fn sys_fs_truncate(path_ptr: u64, path_len: u64, new_size: u64) -> u6
     let ts = fs_now_ns();
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
     let (_, m, dev, rel) = match g.resolve_mut(&path) {
         Some(t) => t,
         None => return ENOENT,
     };
+    let proc = SCHEDULER.current_process_mut();
+    if m.tx_blocks(proc.pid) {
+        return EBUSY;
+    }
+    if let Err(e) = m.check_access(dev, rel, proc.cred(), access::WRITE) {
+        return vfs_err_to_errno(e);
+    }
     match m.fs.truncate(dev, rel, new_size, ts) {
         Ok(()) => 0,
         Err(e) => vfs_err_to_errno(e),
@@ -447,7 +603,11 @@ pub unsafe fn sys_fs_sync() -> u64 {
 }
 
 /// Named snapshot on the root mount; returns Helix LSN. Empty name = anonymous checkpoint.
+/// Root only, since a snapshot pins blocks of every file on the volume.
 pub unsafe fn sys_fs_snapshot(name_ptr: u64, name_len: u64) -> u64 {
+    if !SCHEDULER.current_process_mut().cred().is_root() {
+        return EPERM;
+    }
     let name = if name_ptr == 0 || name_len == 0 {
         ""
     } else {
@@ -463,12 +623,113 @@ pub unsafe fn sys_fs_snapshot(name_ptr: u64, name_len: u64) -> u64 {
         Some(t) => t,
         None => return ENODEV,
     };
+    if m.tx_blocks(SCHEDULER.current_process_mut().pid) {
+        return EBUSY;
+    }
     match m.fs.snapshot(dev, name, ts) {
         Ok(lsn) => lsn,
         Err(e) => vfs_err_to_errno(e),
     }
 }
 
+/// `SYS_SNAPSHOTS`: fills up to `max` `SnapshotInfo` records for the root
+/// volume (oldest first); `max == 0` probes count.
+pub unsafe fn sys_fs_snapshots(buf_ptr: u64, max: u64) -> u64 {
+    use morpheus_foundation::types::SnapshotInfo;
+
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let (_, m, dev, _rel) = match g.resolve_mut("/") {
+        Some(t) => t,
+        None => return ENODEV,
+    };
+    let snapshots = match m.fs.snapshots(dev) {
+        Ok(s) => s,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+
+    if buf_ptr == 0 || max == 0 {
+        return snapshots.len() as u64;
+    }
+    let n = snapshots.len().min(max as usize);
+    if n == 0 {
+        return 0;
+    }
+    let entry_size = core::mem::size_of::<SnapshotInfo>() as u64;
+    let total = (n as u64).saturating_mul(entry_size);
+    if !validate_user_buf(buf_ptr, total) {
+        return EFAULT;
+    }
+    let dst = buf_ptr as *mut SnapshotInfo;
+    for (i, (lsn, ts_ns, name)) in snapshots.iter().take(n).enumerate() {
+        let mut info = SnapshotInfo {
+            version: 0,
+            struct_size: entry_size as u16,
+            lsn: *lsn,
+            timestamp_ns: *ts_ns,
+            ..SnapshotInfo::zeroed()
+        };
+        let nb = name.as_bytes();
+        let len = nb.len().min(info.name.len());
+        info.name[..len].copy_from_slice(&nb[..len]);
+        info.name_len = len as u16;
+        *dst.add(i) = info;
+    }
+    n as u64
+}
+
+/// `SYS_SNAPSHOT_DELETE`: drop the newest root-volume snapshot called `name`.
+/// Root only.
+pub unsafe fn sys_fs_snapshot_delete(name_ptr: u64, name_len: u64) -> u64 {
+    if !SCHEDULER.current_process_mut().cred().is_root() {
+        return EPERM;
+    }
+    let name = match user_path(name_ptr, name_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let (_, m, dev, _rel) = match g.resolve_mut("/") {
+        Some(t) => t,
+        None => return ENODEV,
+    };
+    if m.tx_blocks(SCHEDULER.current_process_mut().pid) {
+        return EBUSY;
+    }
+    match m.fs.delete_snapshot(dev, name) {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// `SYS_SNAPSHOT_ROLLBACK`: make the root namespace equal to the newest
+/// snapshot called `name`. Open fds keep their paths and see restored content.
+/// Root only, since it rewrites files whatever their owners.
+pub unsafe fn sys_fs_snapshot_rollback(name_ptr: u64, name_len: u64) -> u64 {
+    if !SCHEDULER.current_process_mut().cred().is_root() {
+        return EPERM;
+    }
+    let name = match user_path(name_ptr, name_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let (_, m, dev, _rel) = match g.resolve_mut("/") {
+        Some(t) => t,
+        None => return ENODEV,
+    };
+    if m.tx_blocks(SCHEDULER.current_process_mut().pid) {
+        return EBUSY;
+    }
+    match m.fs.rollback_snapshot(dev, name, ts) {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
 /// Fills `buf` with up to `max` `FileVersion` records (oldest first); `max == 0` probes count.
 pub unsafe fn sys_fs_versions(path_ptr: u64, path_len: u64, buf_ptr: u64, max: u64) -> u64 {
     use morpheus_foundation::types::FileVersion;
@@ -480,6 +741,10 @@ pub unsafe fn sys_fs_versions(path_ptr: u64, path_len: u64, buf_ptr: u64, max: u
 
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
     let (_, m, dev, rel) = match g.resolve_mut(&path) {
         Some(t) => t,
         None => return ENOENT,
@@ -609,7 +874,12 @@ pub unsafe fn sys_mounts(buf_ptr: u64, max: u64) -> u64 {
 }
 
 /// `SYS_MOUNT` (spec §5). `VOLUME_NONE` → fresh RAM; `MNT_STAGED` → copy-to-RAM.
-/// `aux`: required size for RAM mounts, optional cap for staged. Returns `mount_id` or errno.
+/// `aux`: required size for RAM mounts, optional cap for staged; under
+/// `MNT_SNAPSHOT` a `*const SnapshotSpec`. Under `MNT_KEY`, `aux` is a
+/// `*const MountKey` wrapping the passphrase and one of the above. Under
+/// `MNT_MANIFEST`, `aux` is a `*const MountManifest` naming a chunked ISO's
+/// manifest. Returns `mount_id` or errno. Root only: a mount can shadow any
+/// path.
 pub unsafe fn sys_mount(
     source_volume_id: u64,
     mp_ptr: u64,
@@ -618,6 +888,9 @@ pub unsafe fn sys_mount(
     flags: u64,
     aux: u64,
 ) -> u64 {
+    if !SCHEDULER.current_process_mut().cred().is_root() {
+        return EPERM;
+    }
     let mp = match user_path(mp_ptr, mp_len) {
         Some(p) => p,
         None => return EINVAL,
@@ -627,6 +900,59 @@ pub unsafe fn sys_mount(
     let n = pb.len().min(256);
     mount_point[..n].copy_from_slice(&pb[..n]);
 
+    let flags = (flags as u32)
+        & (MNT_RDONLY | MNT_STAGED | MNT_SNAPSHOT | MNT_FSCK | MNT_KEY | MNT_MANIFEST);
+    let mut aux = aux;
+    let key = if flags & MNT_KEY != 0 {
+        use morpheus_foundation::types::MountKey;
+        if !validate_user_buf(aux, core::mem::size_of::<MountKey>() as u64) {
+            return EFAULT;
+        }
+        let mk = core::ptr::read_unaligned(aux as *const MountKey);
+        if mk.passphrase_len == 0 || mk.passphrase_len as usize > MOUNT_KEY_MAX {
+            return EINVAL;
+        }
+        if !validate_user_buf(mk.passphrase_ptr, mk.passphrase_len) {
+            return EFAULT;
+        }
+        aux = mk.aux;
+        Some(
+            core::slice::from_raw_parts(mk.passphrase_ptr as *const u8, mk.passphrase_len as usize)
+                .to_vec(),
+        )
+    } else {
+        None
+    };
+    let manifest = if flags & MNT_MANIFEST != 0 {
+        use morpheus_foundation::types::MountManifest;
+        use morpheus_storage_format::iso::MAX_MANIFEST_SIZE;
+        if !validate_user_buf(aux, core::mem::size_of::<MountManifest>() as u64) {
+            return EFAULT;
+        }
+        let mm = core::ptr::read_unaligned(aux as *const MountManifest);
+        if mm.manifest_len == 0 || mm.manifest_len as usize > MAX_MANIFEST_SIZE {
+            return EINVAL;
+        }
+        if !validate_user_buf(mm.manifest_ptr, mm.manifest_len) {
+            return EFAULT;
+        }
+        Some(
+            core::slice::from_raw_parts(mm.manifest_ptr as *const u8, mm.manifest_len as usize)
+                .to_vec(),
+        )
+    } else {
+        None
+    };
+    let snapshot = if flags & MNT_SNAPSHOT != 0 {
+        use morpheus_foundation::types::SnapshotSpec;
+        if !validate_user_buf(aux, core::mem::size_of::<SnapshotSpec>() as u64) {
+            return EFAULT;
+        }
+        Some(core::ptr::read_unaligned(aux as *const SnapshotSpec))
+    } else {
+        None
+    };
+
     // Userland mounts are unprivileged and charged to the caller's RAM budget (spec §6).
     let pid = SCHEDULER.current_process_mut().pid;
 
@@ -635,8 +961,11 @@ pub unsafe fn sys_mount(
         mount_point,
         mount_point_len: n as u16,
         fs_type: fs_type as u32,
-        flags: (flags as u32) & (MNT_RDONLY | MNT_STAGED),
+        flags,
         aux,
+        snapshot,
+        key,
+        manifest,
         pid,
         privileged: false,
     };
@@ -647,7 +976,11 @@ pub unsafe fn sys_mount(
 }
 
 /// `SYS_UMOUNT` (spec §5). Exact mountpoint match; `MNT_FORCE` revokes open fds.
+/// Root only.
 pub unsafe fn sys_umount(mp_ptr: u64, mp_len: u64, flags: u64) -> u64 {
+    if !SCHEDULER.current_process_mut().cred().is_root() {
+        return EPERM;
+    }
     let mp = match user_path(mp_ptr, mp_len) {
         Some(p) => p,
         None => return EINVAL,
@@ -682,7 +1015,7 @@ pub unsafe fn sys_fs_fstat(fd: u64, statbuf: u64) -> u64 {
         stat.mode = match desc.kind {
             FdKind::Socket => mode::S_IFSOCK,
             FdKind::Pipe => mode::S_IFIFO,
-            FdKind::Epoll => mode::S_IFCHR,
+            FdKind::Epoll | FdKind::Watch => mode::S_IFCHR,
             FdKind::Regular => mode::S_IFREG,
         };
         fill_stat_metadata(&mut stat);
@@ -758,6 +1091,9 @@ pub unsafe fn sys_fs_ftruncate(fd: u64, new_len: u64) -> u64 {
         Some(t) => t,
         None => return EBADF,
     };
+    if m.tx_blocks(SCHEDULER.current_process_mut().pid) {
+        return EBUSY;
+    }
     match m.fs.truncate(dev, desc.path_str(), new_len, ts) {
         Ok(()) => 0,
         Err(e) => vfs_err_to_errno(e),
@@ -776,17 +1112,694 @@ pub unsafe fn sys_fs_rmdir(path_ptr: u64, path_len: u64) -> u64 {
     let ts = fs_now_ns();
     let guard = storage::lock();
     let g = &mut *guard.g;
+    let path = match g.follow_links(&path, false) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
     let (_, m, dev, rel) = match g.resolve_mut(&path) {
         Some(t) => t,
         None => return ENOENT,
     };
+    if m.tx_blocks(SCHEDULER.current_process_mut().pid) {
+        return EBUSY;
+    }
     match m.fs.stat(dev, rel) {
         Ok(st) if st.mode & mode::S_IFMT == mode::S_IFDIR => {},
         Ok(_) => return ENOTDIR,
         Err(e) => return vfs_err_to_errno(e),
     }
+    if let Err(e) = m.check_parent(dev, rel, SCHEDULER.current_process_mut().cred()) {
+        return vfs_err_to_errno(e);
+    }
     match m.fs.unlink(dev, rel, ts) {
         Ok(()) => 0,
         Err(e) => vfs_err_to_errno(e),
     }
 }
+
+/// SYS_FS_TX: `op,path_ptr,path_len -> 0 | -errno`. Begin/commit/abort a
+/// transaction on the mount holding `path`. The caller owns it: other processes'
+/// mutations there get `EBUSY` until it ends, and its exit aborts it.
+pub unsafe fn sys_fs_tx(op: u64, path_ptr: u64, path_len: u64) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let pid = SCHEDULER.current_process_mut().pid;
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let (_, m, dev, _rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    let res = match op as u32 {
+        TX_BEGIN => {
+            if m.tx_owner.is_some() {
+                return EBUSY;
+            }
+            m.fs.tx_begin(dev, ts).map(|()| m.tx_owner = Some(pid))
+        },
+        TX_COMMIT | TX_ABORT => {
+            if m.tx_owner != Some(pid) {
+                return EINVAL;
+            }
+            // A failed commit leaves the transaction open for the owner to
+            // abort; abort always releases the mount.
+            let res = if op as u32 == TX_COMMIT {
+                m.fs.tx_commit(dev, ts)
+            } else {
+                m.fs.tx_abort(dev, ts)
+            };
+            if res.is_ok() || op as u32 == TX_ABORT {
+                m.tx_owner = None;
+            }
+            res
+        },
+        _ => return EINVAL,
+    };
+    match res {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// Largest value `SYS_SETXATTR` accepts; a backend may refuse less (`E2BIG`).
+const XATTR_VALUE_MAX: u64 = 4096;
+
+/// SYS_SETXATTR: `path_ptr,path_len,name_ptr,name_len,val_ptr,val_len -> 0 | -errno`.
+pub unsafe fn sys_fs_setxattr(
+    path_ptr: u64,
+    path_len: u64,
+    name_ptr: u64,
+    name_len: u64,
+    val_ptr: u64,
+    val_len: u64,
+) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let name = match user_path(name_ptr, name_len) {
+        Some(n) => n,
+        None => return EINVAL,
+    };
+    if val_len > XATTR_VALUE_MAX {
+        return E2BIG;
+    }
+    let value = if val_len == 0 {
+        &[][..]
+    } else if validate_user_buf(val_ptr, val_len) {
+        core::slice::from_raw_parts(val_ptr as *const u8, val_len as usize)
+    } else {
+        return EFAULT;
+    };
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (_, m, dev, rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    let proc = SCHEDULER.current_process_mut();
+    if m.tx_blocks(proc.pid) {
+        return EBUSY;
+    }
+    if let Err(e) = check_xattr_change(name, proc.cred().is_root()) {
+        return e;
+    }
+    if let Err(e) = m.check_access(dev, rel, proc.cred(), access::WRITE) {
+        return vfs_err_to_errno(e);
+    }
+    match m.fs.setxattr(dev, rel, name, value, ts) {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// SYS_GETXATTR: `path_ptr,path_len,name_ptr,name_len,buf_ptr,buf_len -> value_len | -errno`.
+pub unsafe fn sys_fs_getxattr(
+    path_ptr: u64,
+    path_len: u64,
+    name_ptr: u64,
+    name_len: u64,
+    buf_ptr: u64,
+    buf_len: u64,
+) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let name = match user_path(name_ptr, name_len) {
+        Some(n) => n,
+        None => return EINVAL,
+    };
+    let value = {
+        let guard = storage::lock();
+        let g = &mut *guard.g;
+        let path = match g.follow_links(&path, true) {
+            Ok(p) => p,
+            Err(e) => return vfs_err_to_errno(e),
+        };
+        let (_, m, dev, rel) = match g.resolve_mut(&path) {
+            Some(t) => t,
+            None => return ENOENT,
+        };
+        match m.fs.getxattr(dev, rel, name) {
+            Ok(v) => v,
+            Err(e) => return vfs_err_to_errno(e),
+        }
+    };
+    copy_out_probe(&value, buf_ptr, buf_len)
+}
+
+/// SYS_LISTXATTR: `path_ptr,path_len,buf_ptr,buf_len -> total_bytes | -errno`.
+pub unsafe fn sys_fs_listxattr(path_ptr: u64, path_len: u64, buf_ptr: u64, buf_len: u64) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let names = {
+        let guard = storage::lock();
+        let g = &mut *guard.g;
+        let path = match g.follow_links(&path, true) {
+            Ok(p) => p,
+            Err(e) => return vfs_err_to_errno(e),
+        };
+        let (_, m, dev, rel) = match g.resolve_mut(&path) {
+            Some(t) => t,
+            None => return ENOENT,
+        };
+        match m.fs.listxattr(dev, rel) {
+            Ok(n) => n,
+            Err(e) => return vfs_err_to_errno(e),
+        }
+    };
+    let mut packed = alloc::vec::Vec::new();
+    for n in &names {
+        packed.extend_from_slice(n.as_bytes());
+        packed.push(0);
+    }
+    copy_out_probe(&packed, buf_ptr, buf_len)
+}
+
+/// SYS_REMOVEXATTR: `path_ptr,path_len,name_ptr,name_len -> 0 | -errno`.
+pub unsafe fn sys_fs_removexattr(
+    path_ptr: u64,
+    path_len: u64,
+    name_ptr: u64,
+    name_len: u64,
+) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let name = match user_path(name_ptr, name_len) {
+        Some(n) => n,
+        None => return EINVAL,
+    };
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (_, m, dev, rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    let proc = SCHEDULER.current_process_mut();
+    if m.tx_blocks(proc.pid) {
+        return EBUSY;
+    }
+    if let Err(e) = check_xattr_change(name, proc.cred().is_root()) {
+        return e;
+    }
+    if let Err(e) = m.check_access(dev, rel, proc.cred(), access::WRITE) {
+        return vfs_err_to_errno(e);
+    }
+    match m.fs.removexattr(dev, rel, name, ts) {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// SYS_SYMLINK: `target_ptr,target_len,path_ptr,path_len -> 0 | -errno`. The
+/// target is stored as given, relative or not; it is resolved only when
+/// followed.
+pub unsafe fn sys_fs_symlink(
+    target_ptr: u64,
+    target_len: u64,
+    path_ptr: u64,
+    path_len: u64,
+) -> u64 {
+    let target = match user_path(target_ptr, target_len) {
+        Some(t) if !t.is_empty() => t,
+        _ => return EINVAL,
+    };
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, false) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (_, m, dev, rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    let proc = SCHEDULER.current_process_mut();
+    if m.tx_blocks(proc.pid) {
+        return EBUSY;
+    }
+    let cred = proc.cred();
+    let under = match m.check_parent(dev, rel, cred) {
+        Ok(d) => d,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    match m
+        .fs
+        .symlink(dev, target, rel, ts)
+        .and_then(|()| m.adopt(dev, rel, under, cred, true, ts))
+    {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// SYS_READLINK: `path_ptr,path_len,buf_ptr,buf_len -> target_len | -errno`.
+pub unsafe fn sys_fs_readlink(path_ptr: u64, path_len: u64, buf_ptr: u64, buf_len: u64) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let target = {
+        let guard = storage::lock();
+        let g = &mut *guard.g;
+        let path = match g.follow_links(&path, false) {
+            Ok(p) => p,
+            Err(e) => return vfs_err_to_errno(e),
+        };
+        let (_, m, dev, rel) = match g.resolve_mut(&path) {
+            Some(t) => t,
+            None => return ENOENT,
+        };
+        match m.fs.readlink(dev, rel) {
+            Ok(t) => t,
+            Err(e) => return vfs_err_to_errno(e),
+        }
+    };
+    copy_out_probe(target.as_bytes(), buf_ptr, buf_len)
+}
+
+/// SYS_LINK: `old_ptr,old_len,new_ptr,new_len -> 0 | -errno`. A symbolic link
+/// at `old` is linked itself, not its target (POSIX leaves this open; Linux
+/// does the same).
+pub unsafe fn sys_fs_link(old_ptr: u64, old_len: u64, new_ptr: u64, new_len: u64) -> u64 {
+    let old = match resolve_user_path(old_ptr, old_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let new = match resolve_user_path(new_ptr, new_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let old = match g.follow_links(&old, false) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let new = match g.follow_links(&new, false) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+
+    let src_mount = match g.mounts.resolve(&old) {
+        Some(id) => id,
+        None => return ENOENT,
+    };
+    let dst_mount = match g.mounts.resolve(&new) {
+        Some(id) => id,
+        None => return ENOENT,
+    };
+    if src_mount != dst_mount {
+        return EXDEV;
+    }
+    let mp_len = match g.mounts.get(src_mount) {
+        Some(m) => m.mount_point_len as usize,
+        None => return ENOENT,
+    };
+    let rel_old = storage::mount_relative(&old, mp_len);
+    let rel_new = storage::mount_relative(&new, mp_len);
+    let (m, dev) = match g.mount_dev_mut(src_mount) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    let proc = SCHEDULER.current_process_mut();
+    if m.tx_blocks(proc.pid) {
+        return EBUSY;
+    }
+    let cred = proc.cred();
+    let under = match m.check_parent(dev, rel_new, cred) {
+        Ok(d) => d,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    // The new name shares the file's owner; only directories it needed are ours.
+    match m
+        .fs
+        .link(dev, rel_old, rel_new, ts)
+        .and_then(|()| m.adopt(dev, rel_new, under, cred, false, ts))
+    {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// SYS_CHMOD: `path_ptr,path_len,mode -> 0 | -errno`. The owner or root may
+/// set the permission bits; a symbolic link is followed.
+pub unsafe fn sys_fs_chmod(path_ptr: u64, path_len: u64, new_mode: u64) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (_, m, dev, rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    let proc = SCHEDULER.current_process_mut();
+    if m.tx_blocks(proc.pid) {
+        return EBUSY;
+    }
+    let cred = proc.cred();
+    match m.fs.stat(dev, rel) {
+        Ok(st) if cred.is_root() || st.uid == cred.uid => {},
+        Ok(_) => return EPERM,
+        Err(e) => return vfs_err_to_errno(e),
+    }
+    match m.fs.chmod(dev, rel, new_mode as u32 & 0o7777, ts) {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// SYS_CHOWN: `path_ptr,path_len,uid,gid -> 0 | -errno`. Root only; a
+/// symbolic link is followed.
+pub unsafe fn sys_fs_chown(path_ptr: u64, path_len: u64, uid: u64, gid: u64) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let (Ok(uid), Ok(gid)) = (u32::try_from(uid), u32::try_from(gid)) else {
+        return EINVAL;
+    };
+    let proc = SCHEDULER.current_process_mut();
+    if !proc.cred().is_root() {
+        return EPERM;
+    }
+    let pid = proc.pid;
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (_, m, dev, rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    if m.tx_blocks(pid) {
+        return EBUSY;
+    }
+    match m.fs.chown(dev, rel, uid, gid, ts) {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// `SYS_FS_CLEAN`: one cleaner step on the volume holding `path`, then its
+/// progress. The background thread (`storage::cleaner`) does this on its
+/// own; the syscall is for reporting and for driving it by hand.
+pub unsafe fn sys_fs_clean(path_ptr: u64, path_len: u64, budget: u64, stats_buf: u64) -> u64 {
+    use morpheus_foundation::types::CleanStats;
+
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let size = core::mem::size_of::<CleanStats>() as u64;
+    if stats_buf != 0 && !validate_user_buf(stats_buf, size) {
+        return EFAULT;
+    }
+    if budget != 0 && !SCHEDULER.current_process_mut().cred().is_root() {
+        return EPERM;
+    }
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (_, m, dev, _rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    if budget != 0 {
+        // Even the owner's own transaction: a checkpoint cannot run inside one.
+        if m.tx_owner.is_some() {
+            return EBUSY;
+        }
+        if let Err(e) = m.fs.clean(dev, budget, ts) {
+            return vfs_err_to_errno(e);
+        }
+    }
+    match m.fs.clean_stats(dev) {
+        Ok(mut stats) => {
+            if stats_buf != 0 {
+                stats.struct_size = size as u16;
+                *(stats_buf as *mut CleanStats) = stats;
+            }
+            0
+        },
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// The `StatFs` of mount `mount_id`: the backend's figures plus the mount's
+/// own. `buf` must already be validated.
+unsafe fn fill_statfs(
+    mount_id: u64,
+    m: &mut storage::registry::MountEntry,
+    dev: &mut morpheus_block_types::RawBlockDevice,
+    buf: u64,
+) -> u64 {
+    use morpheus_foundation::types::StatFs;
+
+    match m.fs.statfs(dev) {
+        Ok(st) => {
+            *(buf as *mut StatFs) = StatFs {
+                struct_size: core::mem::size_of::<StatFs>() as u16,
+                fs_type: m.fs_type,
+                flags: m.flags,
+                mount_id,
+                ..st
+            };
+            0
+        },
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// `SYS_STATFS`: usage of the volume holding `path`, which must exist.
+pub unsafe fn sys_fs_statfs(path_ptr: u64, path_len: u64, buf: u64) -> u64 {
+    use morpheus_foundation::types::StatFs;
+
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    if !validate_user_buf(buf, core::mem::size_of::<StatFs>() as u64) {
+        return EFAULT;
+    }
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (mount_id, m, dev, rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    if let Err(e) = m.fs.stat(dev, rel) {
+        return vfs_err_to_errno(e);
+    }
+    fill_statfs(mount_id, m, dev, buf)
+}
+
+/// `SYS_FSTATFS`: `statfs` by open fd. Only a file fd lives on a volume.
+pub unsafe fn sys_fs_fstatfs(fd: u64, buf: u64) -> u64 {
+    use morpheus_foundation::types::StatFs;
+
+    if !validate_user_buf(buf, core::mem::size_of::<StatFs>() as u64) {
+        return EFAULT;
+    }
+    let desc = match SCHEDULER.current_fd_table_mut().get(fd as usize) {
+        Some(d) => *d,
+        None => return EBADF,
+    };
+    if desc.revoked {
+        return EBADF;
+    }
+    if desc.kind != FdKind::Regular {
+        return EINVAL;
+    }
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    match g.mount_dev_mut(desc.mount_id) {
+        Some((m, dev)) => fill_statfs(desc.mount_id, m, dev, buf),
+        None => EBADF,
+    }
+}
+
+/// `SYS_FS_RESIZE(path_ptr, path_len, lba_count)` — see `storage::resize`.
+/// Root only.
+pub unsafe fn sys_fs_resize(path_ptr: u64, path_len: u64, lba_count: u64) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    if !SCHEDULER.current_process_mut().cred().is_root() {
+        return EPERM;
+    }
+    match storage::resize(&path, lba_count, fs_now_ns()) {
+        Ok(n) => n,
+        Err(e) => e,
+    }
+}
+
+/// `SYS_FS_PRUNE(path_ptr, path_len, policy_ptr, policy_len)` — drop the
+/// retained versions the policy would not keep; returns how many.
+pub unsafe fn sys_fs_prune(path_ptr: u64, path_len: u64, policy_ptr: u64, policy_len: u64) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let policy = match user_path(policy_ptr, policy_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (_, m, dev, rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    let proc = SCHEDULER.current_process_mut();
+    // Even the caller's own transaction: the pruned history is synced at once.
+    if m.tx_owner.is_some() {
+        return EBUSY;
+    }
+    if let Err(e) = m.check_access(dev, rel, proc.cred(), access::WRITE) {
+        return vfs_err_to_errno(e);
+    }
+    match m.fs.prune_versions(dev, rel, policy.as_bytes(), ts) {
+        Ok(n) => n,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// `EPERM` unless `cred` is root.
+fn root_only(cred: Cred) -> Result<(), VfsError> {
+    if cred.is_root() {
+        Ok(())
+    } else {
+        Err(VfsError::Perm)
+    }
+}
+
+/// `SYS_FS_RESTORE(path_ptr, path_len, lsn)` — bring `path` back to how it
+/// was at `lsn`. Directories, root only.
+pub unsafe fn sys_fs_restore(path_ptr: u64, path_len: u64, lsn: u64) -> u64 {
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let ts = fs_now_ns();
+    let guard = storage::lock();
+    let g = &mut *guard.g;
+    let path = match g.follow_links(&path, true) {
+        Ok(p) => p,
+        Err(e) => return vfs_err_to_errno(e),
+    };
+    let (_, m, dev, rel) = match g.resolve_mut(&path) {
+        Some(t) => t,
+        None => return ENOENT,
+    };
+    let proc = SCHEDULER.current_process_mut();
+    // Restore runs as a transaction of its own.
+    if m.tx_owner.is_some() {
+        return EBUSY;
+    }
+    // A directory restore rewrites everything below it, whoever owns it, so
+    // it is root's alone; so is a deleted path without versions of its own,
+    // which can only come back as a directory. A deleted file comes back like
+    // a created one.
+    let cred = proc.cred();
+    let allowed = match m.fs.stat(dev, rel) {
+        Ok(st) if st.mode & mode::S_IFMT == mode::S_IFDIR => root_only(cred),
+        Ok(_) => m.check_access(dev, rel, cred, access::WRITE),
+        Err(VfsError::NotFound) => match m.fs.versions(dev, rel) {
+            Ok(v) if !v.is_empty() => m.check_parent(dev, rel, cred).map(|_| ()),
+            _ => root_only(cred),
+        },
+        Err(e) => Err(e),
+    };
+    if let Err(e) = allowed {
+        return vfs_err_to_errno(e);
+    }
+    match m.fs.restore(dev, rel, lsn, ts) {
+        Ok(()) => 0,
+        Err(e) => vfs_err_to_errno(e),
+    }
+}
+
+/// xattr get/list tail: `buf_len == 0` probes the size, a short buffer is
+/// `ERANGE`, otherwise copy and return the length.
+unsafe fn copy_out_probe(src: &[u8], buf_ptr: u64, buf_len: u64) -> u64 {
+    if buf_len == 0 || src.is_empty() {
+        return src.len() as u64;
+    }
+    if buf_len < src.len() as u64 {
+        return ERANGE;
+    }
+    if !validate_user_buf(buf_ptr, src.len() as u64) {
+        return EFAULT;
+    }
+    core::ptr::copy_nonoverlapping(src.as_ptr(), buf_ptr as *mut u8, src.len());
+    src.len() as u64
+}
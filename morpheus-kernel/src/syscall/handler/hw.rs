@@ -62,21 +62,26 @@ pub unsafe fn sys_getrandom(buf: u64, len: u64, _flags: u64) -> u64 {
     if !validate_user_buf(buf, len) {
         return EFAULT;
     }
-    let total = len as usize;
-    let dst = buf as *mut u8;
+    let dst = core::slice::from_raw_parts_mut(buf as *mut u8, len as usize);
+    match fill_random(dst) {
+        Some(n) => n as u64,
+        None => ENOSYS,
+    }
+}
+
+/// Bytes of `dst` filled from the hardware RNG; short on transient
+/// starvation, `None` if the platform has no RNG. Shared with `/dev/random`.
+pub(crate) fn fill_random(dst: &mut [u8]) -> Option<usize> {
     let mut written = 0usize;
-    while written < total {
-        let word = match hal().cpu().hw_random() {
-            Some(w) => w,
-            None if written == 0 => return ENOSYS,
-            None => break, // transient starvation: short read
-        };
-        let bytes = word.to_ne_bytes();
-        let take = core::cmp::min(8, total - written);
-        core::ptr::copy_nonoverlapping(bytes.as_ptr(), dst.add(written), take);
-        written += take;
+    for chunk in dst.chunks_mut(8) {
+        match hal().cpu().hw_random() {
+            Some(w) => chunk.copy_from_slice(&w.to_ne_bytes()[..chunk.len()]),
+            None if written == 0 => return None,
+            None => break,
+        }
+        written += chunk.len();
     }
-    written as u64
+    Some(written)
 }
 
 /// bdf = (bus << 16) | (dev << 8) | func.
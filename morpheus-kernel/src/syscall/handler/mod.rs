@@ -21,6 +21,7 @@ pub mod proc;
 pub mod socket;
 pub mod sync;
 pub mod sysinfo;
+pub mod watch;
 
 // Registration helpers + structs wired up from the boot path.
 pub use fb::shutdown_release_display_ownership;
@@ -103,6 +103,16 @@ fn net_stack_present() -> bool {
     unsafe { NET_STACK_OPS.tcp_socket.is_some() }
 }
 
+/// Stack counters (`/sys/net`); `None` until a stack with stats registers.
+pub(crate) fn net_stack_stats() -> Option<NetStats> {
+    // SAFETY: NET_STACK_OPS is replaced only by `register_net_stack`.
+    unsafe {
+        let f = NET_STACK_OPS.poll_stats?;
+        let mut st = NetStats::default();
+        (f(&mut st as *mut NetStats as *mut u8) >= 0).then_some(st)
+    }
+}
+
 pub unsafe fn sys_net(subcmd: u64, a2: u64, a3: u64, a4: u64) -> u64 {
     if !net_stack_present() {
         return ENODEV;
@@ -42,6 +42,23 @@ pub unsafe fn register_nic(ops: NicOps) {
     NIC_OPS = ops;
 }
 
+/// MAC, link state and hardware counters of the registered NIC (`/sys/net`);
+/// `None` without one. The counters are `None` if the driver keeps none.
+pub(crate) fn nic_status() -> Option<([u8; 6], bool, Option<NicHwStats>)> {
+    // SAFETY: NIC_OPS is written once at boot, before any reader runs.
+    unsafe {
+        let mac_fn = NIC_OPS.mac?;
+        let mut mac = [0u8; 6];
+        mac_fn(mac.as_mut_ptr());
+        let link = NIC_OPS.link_up.is_some_and(|f| f() > 0);
+        let stats = NIC_OPS.ctrl.and_then(|f| {
+            let mut st = NicHwStats::default();
+            (f(NIC_CTRL_STATS, &mut st as *mut NicHwStats as u64) >= 0).then_some(st)
+        });
+        Some((mac, link, stats))
+    }
+}
+
 // Write-once, read-many across cores; atomic ready-flag gates the static mut.
 use core::sync::atomic::{AtomicBool, Ordering as FbOrd};
 static mut FB_REGISTERED_STORAGE: Option<FbInfo> = None;
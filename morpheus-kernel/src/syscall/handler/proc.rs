@@ -10,7 +10,7 @@ use alloc::vec::Vec;
 use morpheus_foundation::errno::E2BIG;
 use morpheus_foundation::flags::open_flags::{O_PIPE_READ, O_PIPE_WRITE};
 use morpheus_foundation::flags::{
-    SPAWN_CLEAR_FDS, SPAWN_FA_CHDIR, SPAWN_FA_CLOSE, SPAWN_FA_DUP2, SPAWN_FA_OPEN,
+    SPAWN_CLEAR_FDS, SPAWN_FA_CHDIR, SPAWN_FA_CLOSE, SPAWN_FA_DUP2, SPAWN_FA_OPEN, SPAWN_SET_CRED,
 };
 use morpheus_foundation::types::{SpawnArgs, SpawnFileAction};
 use morpheus_foundation::PAGE_SIZE;
@@ -348,7 +348,8 @@ unsafe fn child_fd_open(
 
 /// SYS_SPAWN(*const SpawnArgs) — posix_spawn. The child inherits the parent fd
 /// table minus `O_CLOEXEC` (or empty if `SPAWN_CLEAR_FDS`), then `file_actions[]`
-/// replay in order; argv/envp/cwd come off the versioned block.
+/// replay in order; argv/envp/cwd come off the versioned block. With
+/// `SPAWN_SET_CRED` a root caller starts the child as `sa.cred`.
 pub unsafe fn sys_spawn(args_ptr: u64) -> u64 {
     if args_ptr == 0
         || args_ptr & 7 != 0
@@ -381,6 +382,14 @@ pub unsafe fn sys_spawn(args_ptr: u64) -> u64 {
         None
     };
     let clear_fds = sa.flags & SPAWN_CLEAR_FDS != 0;
+    let cred = if sa.flags & SPAWN_SET_CRED != 0 {
+        if !SCHEDULER.current_process_mut().cred().is_root() {
+            return EPERM;
+        }
+        Some((sa.cred as u32, (sa.cred >> 32) as u32))
+    } else {
+        None
+    };
 
     let ts = hal().timer().read_tsc();
 
@@ -465,6 +474,7 @@ pub unsafe fn sys_spawn(args_ptr: u64) -> u64 {
         cwd,
         true,
         clear_fds,
+        cred,
     );
 
     let _ = hal().phys().free_pages(buf_phys, pages_needed);
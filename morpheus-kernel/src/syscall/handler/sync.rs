@@ -184,6 +184,12 @@ pub unsafe fn sys_sigreturn() -> u64 {
 
 /// Packed: dx i16 [15:0], dy i16 [31:16], buttons u8 [39:32].
 pub unsafe fn sys_mouse_read() -> u64 {
+    mouse_packet()
+}
+
+/// Drain the caller's pending motion as a `SYS_MOUSE_READ` packet (also the
+/// record `/dev/input/mouse` returns). Wheel i8 [55:48] outside a compositor.
+pub(crate) unsafe fn mouse_packet() -> u64 {
     if is_composited_client() {
         let proc = SCHEDULER.current_process_mut();
         let dx = proc.mouse_dx;
@@ -0,0 +1,164 @@
+// Filesystem change notifications over unified fds. A watch instance is an fd
+// (FdKind::Watch) whose cookie carries its id in `storage::watch`, which owns
+// the watches and event queues and is fed as the storage lock is released.
+// Reads return whole FsEvent records; the fd is EPOLLIN while any are queued.
+
+use super::common::*;
+use crate::hal;
+use crate::io::readiness::{wait_ready, watch_token};
+use crate::schedular::{tsc_frequency, SCHEDULER};
+use crate::storage::fs_api::{access, FdKind, FdState};
+use crate::storage::{self, vfs_err_to_errno, watch};
+use alloc::vec;
+use morpheus_foundation::flags::open_flags::{O_CLOEXEC, O_NONBLOCK};
+use morpheus_foundation::flags::EPOLLIN;
+use morpheus_foundation::types::FsEvent;
+
+/// Read the instance id a watch fd carries in its cookie low 8 bytes.
+#[inline]
+pub fn instance_id(desc: &FdState) -> u64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&desc.cookie[..8]);
+    u64::from_ne_bytes(b)
+}
+
+/// Close-path entry: reclaim the instance a watch fd refers to.
+pub fn destroy_for(desc: &FdState) {
+    watch::destroy(instance_id(desc));
+}
+
+unsafe fn instance(fd: u64) -> Result<u64, u64> {
+    match SCHEDULER.current_fd_table_mut().get(fd as usize) {
+        Some(d) if d.kind == FdKind::Watch => Ok(instance_id(d)),
+        Some(_) => Err(EINVAL),
+        None => Err(EBADF),
+    }
+}
+
+/// SYS_FS_WATCH: `flags -> fd | -errno`. `O_NONBLOCK` makes an empty read
+/// `EAGAIN`; `O_CLOEXEC` sets FD_CLOEXEC.
+pub unsafe fn sys_fs_watch(flags: u64) -> u64 {
+    let flags = flags as u32;
+    if flags & !(O_NONBLOCK | O_CLOEXEC) != 0 {
+        return EINVAL;
+    }
+    let id = match watch::create() {
+        Some(id) => id,
+        None => return EMFILE,
+    };
+
+    let fd_table = SCHEDULER.current_fd_table_mut();
+    let fd = match fd_table.alloc() {
+        Some(fd) => fd,
+        None => {
+            watch::destroy(id);
+            return EMFILE;
+        },
+    };
+
+    let mut st = FdState::empty();
+    st.kind = FdKind::Watch;
+    st.flags = flags;
+    st.cloexec = flags & O_CLOEXEC != 0;
+    st.cookie[..8].copy_from_slice(&id.to_ne_bytes());
+    if !fd_table.set(fd, st) {
+        watch::destroy(id);
+        return EMFILE;
+    }
+    fd as u64
+}
+
+/// SYS_FS_WATCH_ADD: `fd,path_ptr,path_len,mask -> wd | -errno`.
+pub unsafe fn sys_fs_watch_add(fd: u64, path_ptr: u64, path_len: u64, mask: u64) -> u64 {
+    let id = match instance(fd) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    let path = match resolve_user_path(path_ptr, path_len) {
+        Some(p) => p,
+        None => return EINVAL,
+    };
+    let path = {
+        let guard = storage::lock();
+        let g = &mut *guard.g;
+        let path = match g.follow_links(&path, true) {
+            Ok(p) => p,
+            Err(e) => return vfs_err_to_errno(e),
+        };
+        let (_, m, dev, rel) = match g.resolve_mut(&path) {
+            Some(t) => t,
+            None => return ENOENT,
+        };
+        let cred = SCHEDULER.current_process_mut().cred();
+        let allowed =
+            m.fs.stat(dev, rel)
+                .and_then(|_| m.check_access(dev, rel, cred, access::READ));
+        if let Err(e) = allowed {
+            return vfs_err_to_errno(e);
+        }
+        path
+    };
+    let wd = match watch::add(id, &path, mask as u32) {
+        Ok(wd) => wd,
+        Err(e) => return e,
+    };
+    // Releasing the lock switches the feeds on: nothing after this returns
+    // goes unseen.
+    drop(storage::lock());
+    wd as u64
+}
+
+/// SYS_FS_WATCH_RM: `fd,wd -> 0 | -errno`.
+pub unsafe fn sys_fs_watch_rm(fd: u64, wd: u64) -> u64 {
+    let id = match instance(fd) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    match watch::remove(id, wd as u32) {
+        Ok(()) => 0,
+        Err(e) => e,
+    }
+}
+
+/// `read` on a watch fd: as many whole `FsEvent`s as fit in `len`, blocking
+/// for the first unless `O_NONBLOCK`. Returns the bytes written.
+pub unsafe fn watch_read(fd: u64, buf_ptr: u64, len: u64) -> u64 {
+    let size = core::mem::size_of::<FsEvent>() as u64;
+    if len < size {
+        return EINVAL;
+    }
+    let id = match instance(fd) {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    let nonblock = SCHEDULER
+        .current_fd_table_mut()
+        .status_flags(fd as usize)
+        .unwrap_or(0)
+        & O_NONBLOCK
+        != 0;
+
+    let mut events = vec![FsEvent::zeroed(); ((len / size) as usize).min(watch::MAX_QUEUED)];
+    // Delivery marks the token without waking (see `storage::watch`), so a
+    // parked reader re-checks on this cadence.
+    let slice = (tsc_frequency() / 500).max(1);
+    loop {
+        let n = match watch::take(id, &mut events) {
+            Some(n) => n,
+            None => return EBADF,
+        };
+        if n > 0 {
+            // The user buffer need not be aligned for `FsEvent`.
+            let dst = buf_ptr as *mut FsEvent;
+            for (i, ev) in events[..n].iter().enumerate() {
+                dst.add(i).write_unaligned(*ev);
+            }
+            return n as u64 * size;
+        }
+        if nonblock {
+            return EAGAIN;
+        }
+        let deadline = hal().timer().read_tsc().saturating_add(slice);
+        let _ = wait_ready(watch_token(id), EPOLLIN, deadline);
+    }
+}
@@ -14,8 +14,8 @@ use handler::compositor::{
     sys_win_surface_dirty_clear, sys_win_surface_list, sys_win_surface_map,
 };
 use handler::core::{
-    sys_exit, sys_getpid, sys_keyboard_read, sys_kill, sys_read, sys_sleep, sys_system_control,
-    sys_wait, sys_write, sys_yield,
+    sys_exit, sys_getcred, sys_getpid, sys_keyboard_read, sys_kill, sys_read, sys_setcred,
+    sys_sleep, sys_system_control, sys_wait, sys_write, sys_yield,
 };
 use handler::epoll::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
 use handler::fb::{
@@ -24,10 +24,13 @@ use handler::fb::{
 };
 use handler::fd::{sys_chdir, sys_dup, sys_fcntl, sys_getcwd, sys_syslog};
 use handler::fs::{
-    sys_fs_close, sys_fs_fstat, sys_fs_fsync, sys_fs_ftruncate, sys_fs_mkdir, sys_fs_open,
-    sys_fs_readdir, sys_fs_rename, sys_fs_rmdir, sys_fs_seek, sys_fs_snapshot, sys_fs_stat,
-    sys_fs_sync, sys_fs_truncate, sys_fs_unlink, sys_fs_versions, sys_mount, sys_mounts,
-    sys_umount, sys_volumes,
+    sys_fs_chmod, sys_fs_chown, sys_fs_clean, sys_fs_close, sys_fs_fstat, sys_fs_fstatfs,
+    sys_fs_fsync, sys_fs_ftruncate, sys_fs_getxattr, sys_fs_link, sys_fs_listxattr, sys_fs_lstat,
+    sys_fs_mkdir, sys_fs_open, sys_fs_prune, sys_fs_readdir, sys_fs_readlink, sys_fs_removexattr,
+    sys_fs_rename, sys_fs_resize, sys_fs_restore, sys_fs_rmdir, sys_fs_seek, sys_fs_setxattr,
+    sys_fs_snapshot, sys_fs_snapshot_delete, sys_fs_snapshot_rollback, sys_fs_snapshots,
+    sys_fs_stat, sys_fs_statfs, sys_fs_symlink, sys_fs_sync, sys_fs_truncate, sys_fs_tx,
+    sys_fs_unlink, sys_fs_versions, sys_mount, sys_mounts, sys_umount, sys_volumes,
 };
 use handler::hw::{
     sys_cache_flush, sys_dma_alloc, sys_dma_free, sys_getrandom, sys_irq_ack, sys_irq_attach,
@@ -60,6 +63,7 @@ use handler::sysinfo::{
     sys_boot_log, sys_cpuid, sys_getpriority, sys_memmap, sys_ps, sys_rdtsc, sys_setpriority,
     sys_sigaction,
 };
+use handler::watch::{sys_fs_watch, sys_fs_watch_add, sys_fs_watch_rm};
 
 pub use morpheus_foundation::syscall_abi::*;
 
@@ -218,6 +222,31 @@ pub unsafe extern "C" fn syscall_dispatch(
         SYS_FTRUNCATE => sys_fs_ftruncate(a1, a2),
         SYS_FCNTL => sys_fcntl(a1, a2, a3),
         SYS_RMDIR => sys_fs_rmdir(a1, a2),
+        SYS_FS_TX => sys_fs_tx(a1, a2, a3),
+        SYS_SETXATTR => sys_fs_setxattr(a1, a2, a3, a4, a5, a6),
+        SYS_GETXATTR => sys_fs_getxattr(a1, a2, a3, a4, a5, a6),
+        SYS_LISTXATTR => sys_fs_listxattr(a1, a2, a3, a4),
+        SYS_REMOVEXATTR => sys_fs_removexattr(a1, a2, a3, a4),
+        SYS_SNAPSHOTS => sys_fs_snapshots(a1, a2),
+        SYS_SNAPSHOT_DELETE => sys_fs_snapshot_delete(a1, a2),
+        SYS_SNAPSHOT_ROLLBACK => sys_fs_snapshot_rollback(a1, a2),
+        SYS_SYMLINK => sys_fs_symlink(a1, a2, a3, a4),
+        SYS_READLINK => sys_fs_readlink(a1, a2, a3, a4),
+        SYS_LINK => sys_fs_link(a1, a2, a3, a4),
+        SYS_LSTAT => sys_fs_lstat(a1, a2, a3),
+        SYS_CHMOD => sys_fs_chmod(a1, a2, a3),
+        SYS_CHOWN => sys_fs_chown(a1, a2, a3, a4),
+        SYS_GETCRED => sys_getcred(),
+        SYS_SETCRED => sys_setcred(a1, a2),
+        SYS_FS_CLEAN => sys_fs_clean(a1, a2, a3, a4),
+        SYS_FS_RESIZE => sys_fs_resize(a1, a2, a3),
+        SYS_FS_PRUNE => sys_fs_prune(a1, a2, a3, a4),
+        SYS_FS_RESTORE => sys_fs_restore(a1, a2, a3),
+        SYS_FS_WATCH => sys_fs_watch(a1),
+        SYS_FS_WATCH_ADD => sys_fs_watch_add(a1, a2, a3, a4),
+        SYS_FS_WATCH_RM => sys_fs_watch_rm(a1, a2),
+        SYS_STATFS => sys_fs_statfs(a1, a2, a3),
+        SYS_FSTATFS => sys_fs_fstatfs(a1, a2),
         unknown => {
             crate::serial::log_warn("SYSCALL", 801, "unknown syscall number");
             let _ = unknown;
@@ -234,3 +234,60 @@ pub fn shrink_partition<B: BlockIo>(
 
     Ok(())
 }
+
+/// Move the end of a used partition to `new_end_lba`, growing or shrinking
+/// it in place. The start never moves, so the filesystem inside stays put;
+/// resize it first when shrinking and after when growing.
+pub fn resize_partition<B: BlockIo>(
+    block_io: B,
+    partition_index: usize,
+    new_end_lba: u64,
+) -> Result<(), GptError> {
+    let mut disk = Disk::new(block_io).map_err(|_| GptError::IoError)?;
+
+    let mut header = disk
+        .read_primary_gpt_header(&mut [0u8; 512])
+        .map_err(|_| GptError::InvalidHeader)?;
+
+    let layout = header
+        .get_partition_entry_array_layout()
+        .map_err(|_| GptError::InvalidHeader)?;
+
+    let mut entry_buf = [0u8; 16384];
+    let mut entry_array = disk
+        .read_gpt_partition_entry_array(layout, &mut entry_buf)
+        .map_err(|_| GptError::IoError)?;
+
+    let start_lba = match entry_array.get_partition_entry(partition_index.try_into().unwrap()) {
+        Some(entry) if entry.is_used() => entry.starting_lba.to_u64(),
+        _ => return Err(GptError::PartitionNotFound),
+    };
+
+    if new_end_lba < start_lba || new_end_lba > header.last_usable_lba.to_u64() {
+        return Err(GptError::InvalidSize);
+    }
+
+    for i in 0..layout.num_entries as usize {
+        if i == partition_index {
+            continue;
+        }
+        if let Some(other) = entry_array.get_partition_entry(i.try_into().unwrap()) {
+            let (s, e) = (other.starting_lba.to_u64(), other.ending_lba.to_u64());
+            if other.is_used() && s <= new_end_lba && e >= start_lba {
+                return Err(GptError::OverlappingPartitions);
+            }
+        }
+    }
+
+    let entry = entry_array
+        .get_partition_entry_mut(partition_index.try_into().unwrap())
+        .ok_or(GptError::PartitionNotFound)?;
+    entry.ending_lba = LbaLe::from_u64(new_end_lba);
+
+    header.partition_entry_array_crc32 = entry_array.calculate_crc32();
+    header.update_header_crc32();
+
+    write_gpt_both(&mut disk, &mut header, &entry_array)?;
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use super::{FreeRegion, GptError};
+use super::{FreeRegion, GptError, PartitionSpan};
 use gpt_disk_io::{BlockIo, Disk};
 
 /// Returns up to 16 gaps between used partitions, sorted by start LBA.
@@ -79,3 +79,53 @@ pub fn find_free_space<B: BlockIo>(
 
     Ok(regions)
 }
+
+/// The used entry starting at `start_lba`, and the room after it.
+pub fn find_partition_at<B: BlockIo>(
+    block_io: B,
+    start_lba: u64,
+    block_size_bytes: usize,
+) -> Result<PartitionSpan, GptError> {
+    let mut disk = Disk::new(block_io).map_err(|_| GptError::IoError)?;
+
+    let header = disk
+        .read_primary_gpt_header(&mut [0u8; 512])
+        .map_err(|_| GptError::InvalidHeader)?;
+
+    let layout = header
+        .get_partition_entry_array_layout()
+        .map_err(|_| GptError::InvalidHeader)?;
+
+    let mut entry_buf = [0u8; 4096];
+    let entry_buffer = &mut entry_buf[..block_size_bytes];
+
+    let iter = disk
+        .gpt_partition_entry_array_iter(layout, entry_buffer)
+        .map_err(|_| GptError::IoError)?;
+
+    let mut found = None;
+    let mut max_end_lba = header.last_usable_lba.to_u64();
+
+    for (index, entry_result) in iter.enumerate() {
+        let entry = entry_result.map_err(|_| GptError::IoError)?;
+
+        if !entry.is_used() {
+            continue;
+        }
+
+        let start = entry.starting_lba.to_u64();
+        if start == start_lba {
+            found = Some((index, entry.ending_lba.to_u64()));
+        } else if start > start_lba {
+            max_end_lba = max_end_lba.min(start - 1);
+        }
+    }
+
+    let (index, end_lba) = found.ok_or(GptError::PartitionNotFound)?;
+    Ok(PartitionSpan {
+        index,
+        start_lba,
+        end_lba,
+        max_end_lba,
+    })
+}
@@ -4,8 +4,10 @@ mod scan;
 mod types;
 mod utils;
 
-pub use create_modify::{create_gpt, create_partition, delete_partition, shrink_partition};
-pub use find::find_free_space;
+pub use create_modify::{
+    create_gpt, create_partition, delete_partition, resize_partition, shrink_partition,
+};
+pub use find::{find_free_space, find_partition_at};
 pub use scan::scan_partitions;
-pub use types::{FreeRegion, GptError};
+pub use types::{FreeRegion, GptError, PartitionSpan};
 pub use utils::{align_lba, calculate_total_free_space, mb_to_lba};
@@ -24,3 +24,13 @@ impl FreeRegion {
         (self.size_lba() * 512) / (1024 * 1024)
     }
 }
+
+/// A used partition entry and how far its end may move: up to just before
+/// the next partition, or the last usable LBA.
+#[derive(Copy, Clone, Debug)]
+pub struct PartitionSpan {
+    pub index: usize,
+    pub start_lba: u64,
+    pub end_lba: u64,
+    pub max_end_lba: u64,
+}
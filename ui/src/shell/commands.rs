@@ -2,7 +2,8 @@ use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
-/// Paths are pre-resolved to absolute; HelixFS has no permissions.
+/// Paths are pre-resolved to absolute; the VFS checks them against the
+/// shell's credential.
 pub enum FsOp {
     Ls { path: String, long: bool },
     Cd { path: String },